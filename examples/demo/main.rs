@@ -0,0 +1,211 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! An end-to-end example of using isla as a library, driving the
+//! small architecture in `demo.ir` (see the comment at the top of
+//! that file for its instruction set). It shows the three steps
+//! every isla tool goes through:
+//!
+//! 1. Parse the IR and ISA configuration, and initialize the
+//!    architecture's registers and top-level lets.
+//! 2. Symbolically execute the `isla_footprint` function for a
+//!    concrete opcode, producing a trace of events for each path.
+//! 3. Summarise those traces as footprints, and use them to find
+//!    dependencies between instructions.
+//!
+//! Run it with `cargo run --example isla-demo`. The tests at the end
+//! of this file run the same steps, so `cargo test` checks that the
+//! library APIs used here keep working.
+
+use crossbeam::queue::SegQueue;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use isla_axiomatic::footprint_analysis::{addr_dep, data_dep, footprint_analysis, Footprint};
+use isla_lib::bitvector::{b64::B64, BV};
+use isla_lib::config::ISAConfig;
+use isla_lib::executor;
+use isla_lib::executor::{LocalFrame, TaskState};
+use isla_lib::init::{initialize_architecture, InitArchWithConfig, Initialized};
+use isla_lib::ir::*;
+use isla_lib::ir_parse::parse_ir;
+use isla_lib::simplify::write_events;
+use isla_lib::smt::Event;
+
+/// The instructions of the demo architecture, with an example
+/// encoding of each
+const PROGRAM: [(&str, u32); 6] = [
+    ("LDR R0, [R1]", 0x0200_0100),
+    ("ADD R2, R0, R3", 0x0102_0003),
+    ("LDR R3, [R2]", 0x0203_0200),
+    ("STR R3, [R1]", 0x0300_0103),
+    ("CBZ R3, #16", 0x0400_0310),
+    ("NOP", 0x0000_0000),
+];
+
+fn demo_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("examples").join("demo").join(name)
+}
+
+type Parsed = (Vec<Def<Name, B64>>, Symtab<'static>, ISAConfig<B64>);
+
+/// Parse the architecture and configuration. The parsed definitions
+/// are borrowed by the initialized architecture, so the caller owns
+/// them.
+fn load_arch() -> Result<Parsed, Box<dyn Error>> {
+    let contents: &'static str = Box::leak(fs::read_to_string(demo_file("demo.ir"))?.into_boxed_str());
+    let mut symtab = Symtab::new();
+    let defs = parse_ir(contents, &mut symtab)
+        .map_err(|errors| errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))?;
+    let type_info = IRTypeInfo::new(&defs);
+    let config = fs::read_to_string(demo_file("demo.toml"))?;
+    let isa_config = ISAConfig::parse(&config, None, &symtab, &type_info)?;
+    Ok((defs, symtab, isa_config))
+}
+
+/// Execute `isla_footprint` on an opcode, returning the events of
+/// each path in execution order
+fn trace<B: BV>(opcode: B, iarch: &Initialized<'_, B>) -> Result<Vec<Vec<Event<B>>>, Box<dyn Error>> {
+    let shared_state = &iarch.shared_state;
+    let function_id = shared_state.symtab.lookup("zisla_footprint");
+    let (args, ret_ty, instrs) = shared_state.functions.get(&function_id).unwrap();
+    let task_state = TaskState::new();
+    let task = LocalFrame::new(function_id, args, ret_ty, Some(&[Val::Bits(opcode)]), instrs)
+        .add_lets(&iarch.lets)
+        .add_regs(&iarch.regs)
+        .task(0, &task_state);
+
+    let queue = Arc::new(SegQueue::new());
+    executor::start_multi(1, None, vec![task], shared_state, queue.clone(), &executor::trace_collector);
+
+    let mut paths = Vec::new();
+    while let Some(result) = queue.pop() {
+        let (_, mut events) = result.map_err(|e| format!("{:?}", e))?;
+        paths.push(events.drain(..).rev().collect())
+    }
+    Ok(paths)
+}
+
+/// Compute the footprint of every instruction in [PROGRAM]
+fn footprints<B: BV>(arch: &InitArchWithConfig<'_, B>) -> Result<HashMap<B, Footprint>, Box<dyn Error>> {
+    let paths = PROGRAM.iter().map(|(_, opcode)| vec![Event::Instr(Val::Bits(B::from_u32(*opcode)))]).collect();
+    Ok(footprint_analysis(1, &[paths], arch, None, false, true).map_err(|e| format!("{:?}", e))?)
+}
+
+fn run(buf: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let (mut defs, symtab, isa_config) = load_arch()?;
+    let type_info = IRTypeInfo::new(&defs);
+    let iarch = initialize_architecture(&mut defs, symtab, type_info, &isa_config, AssertionMode::Optimistic, true);
+    let shared_state = &iarch.shared_state;
+
+    for (asm, opcode) in PROGRAM {
+        writeln!(buf, "{} ({:#010x})", asm, opcode)?;
+        for events in trace(B64::from_u32(opcode), &iarch)? {
+            write_events(buf, &events, shared_state);
+        }
+    }
+
+    let arch = InitArchWithConfig::from_initialized(&iarch, &isa_config);
+    let footprints = footprints(&arch)?;
+    for (asm, opcode) in PROGRAM {
+        writeln!(buf, "{}", asm)?;
+        footprints[&B64::from_u32(opcode)].pretty(buf, &shared_state.symtab)?
+    }
+
+    let instrs: Vec<B64> = PROGRAM.iter().map(|(_, opcode)| B64::from_u32(*opcode)).collect();
+    for (to, (to_asm, _)) in PROGRAM.iter().enumerate() {
+        for (from, (from_asm, _)) in PROGRAM[..to].iter().enumerate() {
+            if addr_dep(from, to, &instrs, &footprints) {
+                writeln!(buf, "addr: {} -> {}", from_asm, to_asm)?
+            }
+            if data_dep(from, to, &instrs, &footprints) {
+                writeln!(buf, "data: {} -> {}", from_asm, to_asm)?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let result = run(&mut io::stdout());
+    unsafe { isla_lib::smt::finalize_solver() };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_footprints() {
+        let (mut defs, symtab, isa_config) = load_arch().unwrap();
+        let type_info = IRTypeInfo::new(&defs);
+        let iarch = initialize_architecture(&mut defs, symtab, type_info, &isa_config, AssertionMode::Optimistic, true);
+        let arch = InitArchWithConfig::from_initialized(&iarch, &isa_config);
+        let footprints = footprints(&arch).unwrap();
+        let symtab = &iarch.shared_state.symtab;
+
+        let ldr = footprints[&B64::from_u32(PROGRAM[0].1)].summary(symtab);
+        assert_eq!(ldr.memory_reads, [8]);
+        assert!(ldr.memory_writes.is_empty());
+
+        let add = footprints[&B64::from_u32(PROGRAM[1].1)].summary(symtab);
+        assert_eq!(add.registers_read, ["PC", "R0", "R3"]);
+        assert_eq!(add.registers_written, ["PC", "R2"]);
+
+        let str = footprints[&B64::from_u32(PROGRAM[3].1)].summary(symtab);
+        assert_eq!(str.memory_writes, [8]);
+
+        let cbz = footprints[&B64::from_u32(PROGRAM[4].1)].summary(symtab);
+        assert!(cbz.is_branch);
+
+        // The loaded value flows through the ADD into the address of
+        // the second load, which in turn provides the stored data
+        let instrs: Vec<B64> = PROGRAM.iter().map(|(_, opcode)| B64::from_u32(*opcode)).collect();
+        assert!(addr_dep(0, 2, &instrs, &footprints));
+        assert!(data_dep(2, 3, &instrs, &footprints));
+        assert!(!addr_dep(0, 3, &instrs, &footprints))
+    }
+
+    #[test]
+    fn demo_undefined_opcode() {
+        let (mut defs, symtab, isa_config) = load_arch().unwrap();
+        let type_info = IRTypeInfo::new(&defs);
+        let iarch = initialize_architecture(&mut defs, symtab, type_info, &isa_config, AssertionMode::Optimistic, true);
+        assert!(trace(B64::from_u32(0xff00_0000), &iarch).is_err())
+    }
+}
@@ -897,7 +897,7 @@ impl<'ev, B: BV> ExecutionInfo<'ev, B> {
                             exec.types.insert(*v, ty);
                         }
 
-                        Event::WriteReg(reg, _, val) => {
+                        Event::WriteReg(reg, _, val, _) => {
                             // Only include read/write register events after the instruction fetch
                             if cycle_instr.is_some() {
                                 if write_event_registers.contains(reg) {
@@ -910,7 +910,7 @@ impl<'ev, B: BV> ExecutionInfo<'ev, B> {
                             exec.final_writes.insert((*reg, tid), val);
                         }
 
-                        Event::ReadReg(reg, _, _) => {
+                        Event::ReadReg(reg, _, _, _) => {
                             if cycle_instr.is_some() {
                                 if read_event_registers.contains(reg) {
                                     cycle_events.push(CycleEvent::new("Rreg", po, eid, tid, event, translate));
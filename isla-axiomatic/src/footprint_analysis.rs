@@ -39,7 +39,7 @@ use crossbeam::queue::SegQueue;
 use isla_lib::init::InitArchWithConfig;
 use serde::{Deserialize, Serialize};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 use std::io::Write;
@@ -47,16 +47,25 @@ use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 
-use isla_lib::bitvector::BV;
+use isla_lib::bitvector::{b64::B64, BV};
 use isla_lib::cache::{Cacheable, Cachekey};
+use isla_lib::config::{AccessKind, Bitfield, MemoryRegion, RegisterBitfields};
+use isla_lib::error::ExecError;
 use isla_lib::executor;
-use isla_lib::executor::{LocalFrame, TaskState, TraceError};
+use isla_lib::executor::{FunctionCache, LocalFrame, Task, TaskState, TraceError};
 use isla_lib::ir::*;
 use isla_lib::log;
-use isla_lib::simplify::{EventReferences, Taints};
-use isla_lib::smt::{smtlib, EvPath, Event, Sym};
+use isla_lib::memory::alias::{Alias, AliasAnalysis};
+use isla_lib::primop_util::{length_bits, smt_value};
+use isla_lib::register::RegisterBindings;
+use isla_lib::simplify::{EventReferences, Taints, WriteVar};
+use isla_lib::smt::smtlib::{bits64, Exp};
+use isla_lib::smt::{self, smtlib, Accessor, EvPath, Event, SmtResult, Solver, Sym};
+use isla_lib::source_loc::SourceLoc;
 use isla_lib::zencode;
 
+pub mod defs;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Footprint {
     /// Tracks which (symbolic) registers / memory reads can feed into
@@ -68,10 +77,21 @@ pub struct Footprint {
     /// Tracks which (symbolic) registers / memory reads can feed into
     /// the address of a branch
     branch_addr_taints: (Taints, bool),
+    /// Tracks which (symbolic) registers / memory reads can feed into
+    /// the address of an instruction fetch, when these are kept
+    #[serde(default)]
+    ifetch_addr_taints: (Taints, bool),
     /// The set of register reads (with subfield granularity)
     register_reads: HashSet<RegisterField>,
     /// The set of register writes (also with subfield granularity)
     register_writes: HashSet<RegisterField>,
+    /// The fields of system registers read, using the bitfield
+    /// layouts given by `registers.bitfields` in the ISA config
+    #[serde(default)]
+    register_field_reads: HashSet<(Name, String)>,
+    /// The fields of system registers written
+    #[serde(default)]
+    register_field_writes: HashSet<(Name, String)>,
     /// The set of register writes where the value was tainted by a memory read
     register_writes_tainted: HashSet<RegisterField>,
     /// All register read-write pairs to the following registers are
@@ -89,17 +109,254 @@ pub struct Footprint {
     is_load: bool,
     /// A branch is any instruction with a Branch event
     is_branch: bool,
+    /// True if the instruction performs an instruction fetch, when these are kept
+    #[serde(default)]
+    is_ifetch: bool,
     /// An exclusive is any event with an exclusive read or write kind.
     is_exclusive: bool,
+    /// The widths (in bytes) of all memory reads
+    #[serde(default)]
+    mem_read_widths: HashSet<u32>,
+    /// The widths (in bytes) of all memory writes
+    #[serde(default)]
+    mem_write_widths: HashSet<u32>,
+    /// The alignment of memory reads across all paths, or `None` if
+    /// the instruction does not read memory
+    #[serde(default)]
+    mem_read_alignment: Option<Alignment>,
+    /// The alignment of memory writes across all paths
+    #[serde(default)]
+    mem_write_alignment: Option<Alignment>,
+    /// The access kinds (as declared by `access_kinds` in the ISA
+    /// config) of all memory reads
+    #[serde(default)]
+    mem_read_kinds: HashSet<String>,
+    /// The access kinds of all memory writes
+    #[serde(default)]
+    mem_write_kinds: HashSet<String>,
+    /// The regions (as declared by `memory_regions` in the ISA config)
+    /// of all memory reads, or `unknown` for reads which are not
+    /// provably within any one region
+    #[serde(default)]
+    mem_read_regions: HashSet<String>,
+    /// The regions of all memory writes
+    #[serde(default)]
+    mem_write_regions: HashSet<String>,
+    /// The sizes (in bytes) of the blocks affected by block
+    /// operations such as DC ZVA
+    #[serde(default)]
+    block_op_sizes: HashSet<u32>,
+    /// The exclusive and atomic accesses of the instruction, if it
+    /// makes any
+    #[serde(default)]
+    exclusives: Option<Exclusives>,
+    /// True if the register writes, memory accesses, or branches
+    /// performed by the instruction differ between paths
+    #[serde(default)]
+    is_conditional: bool,
+    /// Aliased register views (as configured by `registers.aliases`)
+    /// accessed by the instruction. Register accesses are recorded
+    /// under their canonical names, so this records which views the
+    /// instruction actually used.
+    #[serde(default)]
+    register_views: HashSet<Name>,
+}
+
+/// Whether the addresses of a class of memory accesses are aligned to
+/// the size of the access. The variants are ordered from best to
+/// worst, so the alignment of several accesses is their maximum.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Alignment {
+    /// The address is aligned however it is computed from the
+    /// instruction's inputs
+    Guaranteed,
+    /// The address could be unaligned, but the path conditions rule
+    /// this out, i.e. the instruction checks the alignment
+    Required,
+    /// The access may be unaligned
+    Unaligned,
+}
+
+impl fmt::Display for Alignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Alignment::Guaranteed => write!(f, "guaranteed"),
+            Alignment::Required => write!(f, "required"),
+            Alignment::Unaligned => write!(f, "unaligned"),
+        }
+    }
+}
+
+/// The exclusive and atomic accesses made by an instruction. An
+/// atomic access is a read followed by a write of the same address on
+/// a single path, as made by atomic memory operations and
+/// compare-and-swap instructions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Exclusives {
+    /// The widths (in bytes) of exclusive reads
+    read_widths: HashSet<u32>,
+    /// The widths of exclusive writes
+    write_widths: HashSet<u32>,
+    /// The widths of the writes of atomic read-write pairs
+    atomic_widths: HashSet<u32>,
+    /// True if the exclusive or atomic write is made on some paths
+    /// but not others, i.e. the store can fail
+    conditional: bool,
+    /// The registers written on the paths where the store fails, with
+    /// the value written if it is the same concrete value on each of
+    /// them
+    failure_writes: Vec<(RegisterField, Option<String>)>,
+}
+
+/// The summary of [Exclusives], with register names as in
+/// [FootprintSummary]
+#[derive(Clone, Debug, Serialize)]
+pub struct ExclusivesSummary {
+    pub reads: Vec<u32>,
+    pub writes: Vec<u32>,
+    pub atomics: Vec<u32>,
+    pub conditional: bool,
+    pub failure_writes: BTreeMap<String, Option<String>>,
+}
+
+impl ExclusivesSummary {
+    fn union(&self, other: &ExclusivesSummary) -> ExclusivesSummary {
+        let mut failure_writes = self.failure_writes.clone();
+        for (reg, value) in &other.failure_writes {
+            let merged_value = match failure_writes.get(reg) {
+                Some(existing) if existing != value => None,
+                _ => value.clone(),
+            };
+            failure_writes.insert(reg.clone(), merged_value);
+        }
+        ExclusivesSummary {
+            reads: merged(&self.reads, &other.reads),
+            writes: merged(&self.writes, &other.writes),
+            atomics: merged(&self.atomics, &other.atomics),
+            conditional: self.conditional || other.conditional,
+            failure_writes,
+        }
+    }
+}
+
+fn merged<T: Clone + Ord>(xs: &[T], ys: &[T]) -> Vec<T> {
+    let mut merged: Vec<T> = xs.iter().chain(ys.iter()).cloned().collect();
+    merged.sort();
+    merged.dedup();
+    merged
+}
+
+/// A machine-readable summary of a footprint, for consumption by
+/// tools such as register allocators and schedulers.
+#[derive(Debug, Serialize)]
+pub struct FootprintSummary {
+    pub registers_read: Vec<String>,
+    pub registers_written: Vec<String>,
+    pub register_fields_read: Vec<String>,
+    pub register_fields_written: Vec<String>,
+    pub memory_reads: Vec<u32>,
+    pub memory_writes: Vec<u32>,
+    pub memory_read_alignment: Option<Alignment>,
+    pub memory_write_alignment: Option<Alignment>,
+    pub memory_read_kinds: Vec<String>,
+    pub memory_write_kinds: Vec<String>,
+    pub memory_read_regions: Vec<String>,
+    pub memory_write_regions: Vec<String>,
+    pub block_ops: Vec<u32>,
+    pub exclusives: Option<ExclusivesSummary>,
+    pub is_branch: bool,
+    pub is_exclusive: bool,
+    pub is_ifetch: bool,
+    pub is_conditional: bool,
+}
+
+impl FootprintSummary {
+    /// The combined footprint of two instructions, which accesses
+    /// everything that either of them does.
+    pub fn union(&self, other: &FootprintSummary) -> FootprintSummary {
+        FootprintSummary {
+            registers_read: merged(&self.registers_read, &other.registers_read),
+            registers_written: merged(&self.registers_written, &other.registers_written),
+            register_fields_read: merged(&self.register_fields_read, &other.register_fields_read),
+            register_fields_written: merged(&self.register_fields_written, &other.register_fields_written),
+            memory_reads: merged(&self.memory_reads, &other.memory_reads),
+            memory_writes: merged(&self.memory_writes, &other.memory_writes),
+            memory_read_alignment: self.memory_read_alignment.max(other.memory_read_alignment),
+            memory_write_alignment: self.memory_write_alignment.max(other.memory_write_alignment),
+            memory_read_kinds: merged(&self.memory_read_kinds, &other.memory_read_kinds),
+            memory_write_kinds: merged(&self.memory_write_kinds, &other.memory_write_kinds),
+            memory_read_regions: merged(&self.memory_read_regions, &other.memory_read_regions),
+            memory_write_regions: merged(&self.memory_write_regions, &other.memory_write_regions),
+            block_ops: merged(&self.block_ops, &other.block_ops),
+            exclusives: match (&self.exclusives, &other.exclusives) {
+                (Some(lhs), Some(rhs)) => Some(lhs.union(rhs)),
+                (lhs, rhs) => lhs.as_ref().or(rhs.as_ref()).cloned(),
+            },
+            is_branch: self.is_branch || other.is_branch,
+            is_exclusive: self.is_exclusive || other.is_exclusive,
+            is_ifetch: self.is_ifetch || other.is_ifetch,
+            is_conditional: self.is_conditional || other.is_conditional,
+        }
+    }
+}
+
+/// The condition flags an instruction defines and uses, in the order
+/// they are listed by `registers.flags` in the ISA config
+#[derive(Debug, Default, Serialize)]
+pub struct FlagLiveness {
+    pub defined: Vec<String>,
+    pub used: Vec<String>,
+}
+
+fn register_field_name(reg: Name, accessor: &[Accessor], symtab: &Symtab) -> String {
+    let mut name = zencode::decode(symtab.to_str(reg));
+    for Accessor::Field(field) in accessor {
+        name.push('.');
+        name.push_str(&zencode::decode(symtab.to_str(*field)))
+    }
+    name
 }
 
+fn register_field_names(fields: &HashSet<RegisterField>, symtab: &Symtab) -> Vec<String> {
+    let mut names: Vec<String> =
+        fields.iter().map(|(reg, accessor)| register_field_name(*reg, accessor, symtab)).collect();
+    names.sort();
+    names
+}
+
+fn bitfield_names(fields: &HashSet<(Name, String)>, symtab: &Symtab) -> Vec<String> {
+    let mut names: Vec<String> =
+        fields.iter().map(|(reg, field)| format!("{}.{}", zencode::decode(symtab.to_str(*reg)), field)).collect();
+    names.sort();
+    names
+}
+
+fn sorted_kinds(kinds: &HashSet<String>) -> Vec<String> {
+    let mut kinds: Vec<String> = kinds.iter().cloned().collect();
+    kinds.sort();
+    kinds
+}
+
+fn sorted_widths(widths: &HashSet<u32>) -> Vec<u32> {
+    let mut widths: Vec<u32> = widths.iter().copied().collect();
+    widths.sort_unstable();
+    widths
+}
+
+/// Footprints are cached with bincode, which is not self-describing,
+/// so a footprint cached with a different set of fields cannot be
+/// read back. This version is part of the cache key, and must be
+/// bumped whenever the fields of [Footprint] change.
+const FOOTPRINT_CACHE_VERSION: u32 = 2;
+
 pub struct Footprintkey {
     opcode: String,
 }
 
 impl Cachekey for Footprintkey {
     fn key(&self) -> String {
-        format!("opcode_{}", self.opcode)
+        format!("opcode_{}_v{}", self.opcode, FOOTPRINT_CACHE_VERSION)
     }
 }
 
@@ -113,16 +370,107 @@ impl Footprint {
             write_data_taints: (HashSet::new(), false),
             mem_addr_taints: (HashSet::new(), false),
             branch_addr_taints: (HashSet::new(), false),
+            ifetch_addr_taints: (HashSet::new(), false),
             register_reads: HashSet::new(),
             register_writes: HashSet::new(),
+            register_field_reads: HashSet::new(),
+            register_field_writes: HashSet::new(),
             register_writes_tainted: HashSet::new(),
             register_writes_ignored: HashSet::new(),
             register_pick_deps: HashMap::new(),
             is_store: false,
             is_load: false,
             is_branch: false,
+            is_ifetch: false,
             is_exclusive: false,
+            mem_read_widths: HashSet::new(),
+            mem_write_widths: HashSet::new(),
+            mem_read_alignment: None,
+            mem_write_alignment: None,
+            mem_read_kinds: HashSet::new(),
+            mem_write_kinds: HashSet::new(),
+            mem_read_regions: HashSet::new(),
+            mem_write_regions: HashSet::new(),
+            block_op_sizes: HashSet::new(),
+            exclusives: None,
+            is_conditional: false,
+            register_views: HashSet::new(),
+        }
+    }
+
+    /// The aliased register views accessed by the instruction
+    pub fn register_views(&self) -> &HashSet<Name> {
+        &self.register_views
+    }
+
+    pub fn summary(&self, symtab: &Symtab) -> FootprintSummary {
+        FootprintSummary {
+            registers_read: register_field_names(&self.register_reads, symtab),
+            registers_written: register_field_names(&self.register_writes, symtab),
+            register_fields_read: bitfield_names(&self.register_field_reads, symtab),
+            register_fields_written: bitfield_names(&self.register_field_writes, symtab),
+            memory_reads: sorted_widths(&self.mem_read_widths),
+            memory_writes: sorted_widths(&self.mem_write_widths),
+            memory_read_alignment: self.mem_read_alignment,
+            memory_write_alignment: self.mem_write_alignment,
+            memory_read_kinds: sorted_kinds(&self.mem_read_kinds),
+            memory_write_kinds: sorted_kinds(&self.mem_write_kinds),
+            memory_read_regions: sorted_kinds(&self.mem_read_regions),
+            memory_write_regions: sorted_kinds(&self.mem_write_regions),
+            block_ops: sorted_widths(&self.block_op_sizes),
+            exclusives: self.exclusives.as_ref().map(|exclusives| ExclusivesSummary {
+                reads: sorted_widths(&exclusives.read_widths),
+                writes: sorted_widths(&exclusives.write_widths),
+                atomics: sorted_widths(&exclusives.atomic_widths),
+                conditional: exclusives.conditional,
+                failure_writes: exclusives
+                    .failure_writes
+                    .iter()
+                    .map(|((reg, accessor), value)| (register_field_name(*reg, accessor, symtab), value.clone()))
+                    .collect(),
+            }),
+            is_branch: self.is_branch,
+            is_exclusive: self.is_exclusive,
+            is_ifetch: self.is_ifetch,
+            is_conditional: self.is_conditional,
+        }
+    }
+
+    /// Find which of `flags` the instruction reads and writes. Flags
+    /// in registers with a bitfield layout use the bit-level field
+    /// accesses, otherwise accessing a whole register accesses every
+    /// flag within it.
+    pub fn flag_liveness(
+        &self,
+        flags: &[(Name, String)],
+        bitfields: &HashMap<Name, RegisterBitfields>,
+        symtab: &Symtab,
+    ) -> FlagLiveness {
+        let accesses =
+            |fields: &HashSet<RegisterField>, bit_fields: &HashSet<(Name, String)>, reg: Name, flag: &str| {
+                if bitfields.contains_key(&reg) {
+                    bit_fields.contains(&(reg, flag.to_string()))
+                } else {
+                    fields.iter().any(|(r, accessor)| {
+                        *r == reg
+                            && match accessor.first() {
+                                None => true,
+                                Some(Accessor::Field(field)) => zencode::decode(symtab.to_str(*field)) == flag,
+                            }
+                    })
+                }
+            };
+
+        let mut liveness = FlagLiveness::default();
+        for (reg, flag) in flags {
+            if accesses(&self.register_writes, &self.register_field_writes, *reg, flag) {
+                liveness.defined.push(flag.clone())
+            }
+            if accesses(&self.register_reads, &self.register_field_reads, *reg, flag) {
+                liveness.used.push(flag.clone())
+            }
         }
+        liveness
     }
 
     /// This just prints the footprint information in a human-readable
@@ -156,6 +504,15 @@ impl Footprint {
                 component.pretty(buf, symtab)?
             }
         }
+        if self.is_ifetch {
+            write!(buf, "\n  Instruction fetch address:")?;
+            for (reg, accessor) in &self.ifetch_addr_taints.0 {
+                write!(buf, " {}", zencode::decode(symtab.to_str(*reg)))?;
+                for component in accessor {
+                    component.pretty(buf, symtab)?
+                }
+            }
+        }
         write!(buf, "\n  Register reads:")?;
         for (reg, accessor) in &self.register_reads {
             write!(buf, " {}", zencode::decode(symtab.to_str(*reg)))?;
@@ -170,6 +527,18 @@ impl Footprint {
                 component.pretty(buf, symtab)?
             }
         }
+        if !self.register_field_reads.is_empty() {
+            write!(buf, "\n  Register field reads:")?;
+            for name in bitfield_names(&self.register_field_reads, symtab) {
+                write!(buf, " {}", name)?
+            }
+        }
+        if !self.register_field_writes.is_empty() {
+            write!(buf, "\n  Register field writes:")?;
+            for name in bitfield_names(&self.register_field_writes, symtab) {
+                write!(buf, " {}", name)?
+            }
+        }
         write!(buf, "\n  Register writes (ignore):")?;
         for (from_reg, to_reg) in &self.register_writes_ignored {
             if let Some(from_reg) = from_reg {
@@ -193,10 +562,80 @@ impl Footprint {
                 write!(buf, "->{}", zencode::decode(symtab.to_str(*reg)))?
             }
         }
+        if let Some(alignment) = self.mem_read_alignment {
+            write!(buf, "\n  Memory read sizes:")?;
+            for bytes in sorted_widths(&self.mem_read_widths) {
+                write!(buf, " {}", bytes)?
+            }
+            write!(buf, " (alignment: {})", alignment)?
+        }
+        if let Some(alignment) = self.mem_write_alignment {
+            write!(buf, "\n  Memory write sizes:")?;
+            for bytes in sorted_widths(&self.mem_write_widths) {
+                write!(buf, " {}", bytes)?
+            }
+            write!(buf, " (alignment: {})", alignment)?
+        }
+        if !self.mem_read_kinds.is_empty() {
+            write!(buf, "\n  Memory read kinds:")?;
+            for kind in sorted_kinds(&self.mem_read_kinds) {
+                write!(buf, " {}", kind)?
+            }
+        }
+        if !self.mem_write_kinds.is_empty() {
+            write!(buf, "\n  Memory write kinds:")?;
+            for kind in sorted_kinds(&self.mem_write_kinds) {
+                write!(buf, " {}", kind)?
+            }
+        }
+        if !self.mem_read_regions.is_empty() {
+            write!(buf, "\n  Memory read regions:")?;
+            for region in sorted_kinds(&self.mem_read_regions) {
+                write!(buf, " {}", region)?
+            }
+        }
+        if !self.mem_write_regions.is_empty() {
+            write!(buf, "\n  Memory write regions:")?;
+            for region in sorted_kinds(&self.mem_write_regions) {
+                write!(buf, " {}", region)?
+            }
+        }
+        if !self.block_op_sizes.is_empty() {
+            write!(buf, "\n  Block operations:")?;
+            for bytes in sorted_widths(&self.block_op_sizes) {
+                write!(buf, " {}", bytes)?
+            }
+        }
+        if let Some(exclusives) = &self.exclusives {
+            write!(buf, "\n  Exclusives:")?;
+            for (name, widths) in [
+                ("Reads", &exclusives.read_widths),
+                ("Writes", &exclusives.write_widths),
+                ("Atomics", &exclusives.atomic_widths),
+            ] {
+                if !widths.is_empty() {
+                    write!(buf, "\n    {}:", name)?;
+                    for bytes in sorted_widths(widths) {
+                        write!(buf, " {}", bytes)?
+                    }
+                }
+            }
+            write!(buf, "\n    Conditional: {}", exclusives.conditional)?;
+            if exclusives.conditional {
+                write!(buf, "\n    Failure writes:")?;
+                for ((reg, accessor), value) in &exclusives.failure_writes {
+                    write!(buf, " {}", register_field_name(*reg, accessor, symtab))?;
+                    if let Some(value) = value {
+                        write!(buf, "={}", value)?
+                    }
+                }
+            }
+        }
         write!(buf, "\n  Is store: {}", self.is_store)?;
         write!(buf, "\n  Is load: {}", self.is_load)?;
         write!(buf, "\n  Is exclusive: {}", self.is_exclusive)?;
         write!(buf, "\n  Is branch: {}", self.is_branch)?;
+        write!(buf, "\n  Is conditional: {}", self.is_conditional)?;
         writeln!(buf)?;
         Ok(())
     }
@@ -420,10 +859,495 @@ pub fn pick_dep<B: BV>(from: usize, to: usize, instrs: &[B], footprints: &HashMa
     false
 }
 
+/// The dependencies from one instruction to the instruction
+/// immediately after it in program order.
+#[derive(Debug, Serialize)]
+pub struct PairDependencies {
+    pub addr: bool,
+    pub data: bool,
+    pub ctrl: bool,
+    pub rmw: bool,
+    /// Registers written by the first instruction and read by the
+    /// second
+    pub registers: Vec<String>,
+    /// True if the first instruction stores and the second loads, so
+    /// the load could read from the store if their addresses overlap
+    /// (see [store_to_load_forwarding] for when they do)
+    pub store_to_load: bool,
+    /// The accesses made by either instruction
+    pub combined: FootprintSummary,
+}
+
+/// Compute the dependencies from `first` to `second`, when `second`
+/// immediately follows `first` in program order.
+///
+/// # Panics
+///
+/// Panics if either instruction does not have a footprint.
+pub fn pair_dependencies<B: BV>(
+    first: B,
+    second: B,
+    footprints: &HashMap<B, Footprint>,
+    symtab: &Symtab,
+) -> PairDependencies {
+    let instrs = [first, second];
+    let (first, second) = (&footprints[&first], &footprints[&second]);
+
+    let mut registers: Vec<String> = first
+        .register_writes
+        .iter()
+        .filter(|(wreg, _)| second.register_reads.iter().any(|(rreg, _)| rreg == wreg))
+        .map(|(reg, _)| zencode::decode(symtab.to_str(*reg)))
+        .collect();
+    registers.sort();
+    registers.dedup();
+
+    PairDependencies {
+        addr: addr_dep(0, 1, &instrs, footprints),
+        data: data_dep(0, 1, &instrs, footprints),
+        ctrl: ctrl_dep(0, 1, &instrs, footprints),
+        rmw: rmw_dep(0, 1, &instrs, footprints),
+        registers,
+        store_to_load: first.is_store && second.is_load,
+        combined: first.summary(symtab).union(&second.summary(symtab)),
+    }
+}
+
+/// Describes when a load can read from (i.e. have a value forwarded
+/// from) an earlier store.
+#[derive(Debug)]
+pub enum Forwarding {
+    /// The load and store always overlap
+    Always,
+    /// The load and store can never overlap
+    Never,
+    /// The load and store overlap exactly when the condition holds
+    When(Exp<Sym>),
+}
+
+#[derive(Debug)]
+pub struct ForwardingPair<B> {
+    pub store_address: Val<B>,
+    pub store_bytes: u32,
+    pub load_address: Val<B>,
+    pub load_bytes: u32,
+    pub forwarding: Forwarding,
+}
+
+/// The condition under which the byte ranges `[addr1, addr1 + bytes1)`
+/// and `[addr2, addr2 + bytes2)` overlap. The addresses are extended
+/// by an extra bit to prevent wrapping.
+fn overlap_condition(addr1: Exp<Sym>, bytes1: u32, addr2: Exp<Sym>, bytes2: u32, width: u32) -> Exp<Sym> {
+    use Exp::*;
+    let end = |addr: &Exp<Sym>, bytes: u32| {
+        Box::new(Bvadd(Box::new(ZeroExtend(1, Box::new(addr.clone()))), Box::new(bits64(bytes as u64, width + 1))))
+    };
+    And(
+        Box::new(Bvult(Box::new(ZeroExtend(1, Box::new(addr1.clone()))), end(&addr2, bytes2))),
+        Box::new(Bvult(Box::new(ZeroExtend(1, Box::new(addr2.clone()))), end(&addr1, bytes1))),
+    )
+}
+
+/// Determine the conditions under which loads can read from stores
+/// for a store instruction followed by a load instruction.
+///
+/// # Arguments
+///
+/// * `events` - The events for a single path, in execution order
+/// * `split` - The index of the first event belonging to the load instruction
+/// * `alias_analysis` - Try to decide forwarding with [AliasAnalysis] before using the solver
+/// * `solver` - The solver state at the end of the path
+pub fn store_to_load_forwarding<B: BV>(
+    events: &[Event<B>],
+    split: usize,
+    alias_analysis: bool,
+    solver: &mut Solver<B>,
+) -> Result<Vec<ForwardingPair<B>>, ExecError> {
+    let (store_events, load_events) = events.split_at(split.min(events.len()));
+    let mut pairs = Vec::new();
+    let aliases = if alias_analysis { Some(AliasAnalysis::from_events(events)) } else { None };
+
+    for store in store_events.iter().filter(|ev| !ev.is_ifetch()) {
+        let Event::WriteMem { address: store_address, bytes: store_bytes, .. } = store else { continue };
+        let width = length_bits(store_address, solver, SourceLoc::unknown())?;
+
+        for load in load_events.iter().filter(|ev| !ev.is_ifetch()) {
+            let Event::ReadMem { address: load_address, bytes: load_bytes, .. } = load else { continue };
+
+            let overlap = overlap_condition(
+                smt_value(store_address, SourceLoc::unknown())?,
+                *store_bytes,
+                smt_value(load_address, SourceLoc::unknown())?,
+                *load_bytes,
+                width,
+            );
+
+            let alias = match &aliases {
+                Some(aliases) => aliases.alias(store_address, *store_bytes, load_address, *load_bytes, width),
+                None => Alias::May,
+            };
+
+            let forwarding = match alias {
+                Alias::Must => Forwarding::Always,
+                Alias::No => Forwarding::Never,
+                Alias::May => {
+                    let can_overlap = solver.check_sat_with(&overlap);
+                    let can_not_overlap = solver.check_sat_with(&Exp::Not(Box::new(overlap.clone())));
+                    match (can_overlap, can_not_overlap) {
+                        (SmtResult::Unknown, _) | (_, SmtResult::Unknown) => return Err(ExecError::Z3Unknown),
+                        (SmtResult::Sat, SmtResult::Sat) => Forwarding::When(overlap),
+                        (SmtResult::Sat, SmtResult::Unsat) => Forwarding::Always,
+                        (SmtResult::Unsat, _) => Forwarding::Never,
+                    }
+                }
+            };
+
+            pairs.push(ForwardingPair {
+                store_address: store_address.clone(),
+                store_bytes: *store_bytes,
+                load_address: load_address.clone(),
+                load_bytes: *load_bytes,
+                forwarding,
+            })
+        }
+    }
+
+    Ok(pairs)
+}
+
+/// A register or memory effect of an instruction. Registers are
+/// identified by name (including any fields), and memory accesses and
+/// block operations by their width in bytes. Exceptions are
+/// identified by the kind given in the ISA config, and `NoException`
+/// is only reported by [exception_conditions] for paths that take no
+/// exception.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Effect {
+    ReadReg(String),
+    WriteReg(String),
+    ReadMem(u32),
+    WriteMem(u32),
+    BlockOp(u32),
+    Branch,
+    Exception(String),
+    NoException,
+}
+
+/// A variable appearing in the condition for an effect. Where
+/// possible variables are replaced by the input register they were
+/// read from, so conditions from different paths can be compared.
+#[derive(Clone, Debug)]
+pub enum ConditionVar {
+    Sym(Sym),
+    Register(String),
+}
+
+impl WriteVar for ConditionVar {
+    fn write_var(&self, buf: &mut dyn Write, opts: &isla_lib::simplify::WriteOpts) -> std::io::Result<()> {
+        match self {
+            ConditionVar::Sym(v) => v.write_var(buf, opts),
+            ConditionVar::Register(name) => write!(buf, "|{}|", name),
+        }
+    }
+}
+
+/// An effect, along with the condition under which it occurs. A
+/// condition of `None` means the effect occurs on every path.
+#[derive(Debug)]
+pub struct ConditionalEffect {
+    pub effect: Effect,
+    pub condition: Option<Exp<ConditionVar>>,
+}
+
+fn register_symbols<B: BV>(name: String, val: &Val<B>, symtab: &Symtab, registers: &mut HashMap<Sym, String>) {
+    match val {
+        Val::Symbolic(v) => {
+            registers.entry(*v).or_insert(name);
+        }
+        Val::Struct(fields) => {
+            for (field, val) in fields {
+                register_symbols(format!("{}.{}", name, zencode::decode(symtab.to_str(*field))), val, symtab, registers)
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Determine the condition under which each effect in a set of paths
+/// for a single instruction occurs. The condition for each path is
+/// the conjunction of every assertion made after the first fork
+/// along that path, and the condition for an effect is the disjunction of the
+/// conditions for every path it occurs on. The events in each path
+/// should be in chronological order. Calls to any of the
+/// `exception_functions` are reported as exception effects.
+pub fn conditional_effects<B: BV>(
+    paths: &[EvPath<B>],
+    ignored_registers: &HashSet<Name>,
+    exception_functions: &HashMap<Name, String>,
+    symtab: &Symtab,
+) -> Vec<ConditionalEffect> {
+    conditional_effects_core(paths, ignored_registers, exception_functions, false, symtab)
+}
+
+/// Like [conditional_effects], but only reports the exceptions that
+/// can be taken, along with a `NoException` effect giving the
+/// condition under which the instruction takes no exception.
+pub fn exception_conditions<B: BV>(
+    paths: &[EvPath<B>],
+    ignored_registers: &HashSet<Name>,
+    exception_functions: &HashMap<Name, String>,
+    symtab: &Symtab,
+) -> Vec<ConditionalEffect> {
+    conditional_effects_core(paths, ignored_registers, exception_functions, true, symtab)
+}
+
+fn conditional_effects_core<B: BV>(
+    paths: &[EvPath<B>],
+    ignored_registers: &HashSet<Name>,
+    exception_functions: &HashMap<Name, String>,
+    only_exceptions: bool,
+    symtab: &Symtab,
+) -> Vec<ConditionalEffect> {
+    let mut effects: Vec<Effect> = Vec::new();
+    let mut path_effects: Vec<(HashSet<Effect>, Exp<ConditionVar>)> = Vec::new();
+
+    for events in paths {
+        let mut defs: HashMap<Sym, &Exp<Sym>> = HashMap::new();
+        let mut registers: HashMap<Sym, String> = HashMap::new();
+        let mut forks: Vec<&Exp<Sym>> = Vec::new();
+        let mut after_fork = false;
+        let mut occurs: HashSet<Effect> = HashSet::new();
+
+        for event in events {
+            let effect = match event {
+                Event::Fork(..) => {
+                    after_fork = true;
+                    None
+                }
+                Event::Smt(smtlib::Def::Assert(exp), _, _) if after_fork => {
+                    forks.push(exp);
+                    None
+                }
+                Event::Smt(smtlib::Def::DefineConst(v, exp), _, _) => {
+                    defs.insert(*v, exp);
+                    None
+                }
+                Event::ReadReg(reg, accessor, val, _) if !ignored_registers.contains(reg) => {
+                    let name = register_field_name(*reg, accessor, symtab);
+                    register_symbols(name.clone(), val, symtab, &mut registers);
+                    Some(Effect::ReadReg(name))
+                }
+                Event::WriteReg(reg, accessor, _, _) if !ignored_registers.contains(reg) => {
+                    Some(Effect::WriteReg(register_field_name(*reg, accessor, symtab)))
+                }
+                Event::ReadMem { bytes, opts, .. } if !opts.is_ifetch => Some(Effect::ReadMem(*bytes)),
+                Event::WriteMem { bytes, .. } => Some(Effect::WriteMem(*bytes)),
+                Event::BlockOp { bytes, .. } => Some(Effect::BlockOp(*bytes)),
+                Event::Branch { .. } => Some(Effect::Branch),
+                Event::Function { name, call: true } => exception_functions.get(name).cloned().map(Effect::Exception),
+                _ => None,
+            };
+            let effect = effect.filter(|effect| !only_exceptions || matches!(effect, Effect::Exception(_)));
+            if let Some(effect) = effect {
+                if !effects.contains(&effect) {
+                    effects.push(effect.clone())
+                }
+                occurs.insert(effect);
+            }
+        }
+
+        let condition = forks
+            .iter()
+            .map(|exp| exp.clone_expand(&defs).eval())
+            .reduce(|lhs, rhs| Exp::And(Box::new(lhs), Box::new(rhs)))
+            .unwrap_or(Exp::Bool(true));
+        let condition = condition
+            .map_var(&mut |v| {
+                Ok::<_, ()>(Exp::Var(match registers.get(v) {
+                    Some(name) => ConditionVar::Register(name.clone()),
+                    None => ConditionVar::Sym(*v),
+                }))
+            })
+            .unwrap();
+        if only_exceptions && occurs.is_empty() {
+            if !effects.contains(&Effect::NoException) {
+                effects.push(Effect::NoException)
+            }
+            occurs.insert(Effect::NoException);
+        }
+        path_effects.push((occurs, condition))
+    }
+
+    effects
+        .into_iter()
+        .map(|effect| {
+            let condition = if path_effects.iter().all(|(occurs, _)| occurs.contains(&effect)) {
+                None
+            } else {
+                path_effects
+                    .iter()
+                    .filter(|(occurs, _)| occurs.contains(&effect))
+                    .map(|(_, condition)| condition.clone())
+                    .reduce(|lhs, rhs| Exp::Or(Box::new(lhs), Box::new(rhs)))
+            };
+            ConditionalEffect { effect, condition }
+        })
+        .collect()
+}
+
+/// A named field of a partial instruction, as it appears in an
+/// encoding constraint. Fields are written as bare names, so the
+/// constraints can be given back to `--instruction-constraint`.
+#[derive(Clone, Debug)]
+pub struct FieldVar(pub String);
+
+impl WriteVar for FieldVar {
+    fn write_var(&self, buf: &mut dyn Write, _opts: &isla_lib::simplify::WriteOpts) -> std::io::Result<()> {
+        write!(buf, "{}", self.0)
+    }
+}
+
+/// How a path through a partial instruction ends. An instruction
+/// which is undefined for some field values either calls one of the
+/// ISA config's exception functions or, if the decoder has no case
+/// for those values, fails with an error.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EncodingOutcome {
+    Decoded,
+    Exception(String),
+    Error(String),
+}
+
+/// The conditions on the fields of a partial instruction under which
+/// it has an outcome. Each condition is a conjunction of branches on
+/// the fields alone, from which every branch not needed to determine
+/// the outcome has been removed, so different paths can give the same
+/// condition.
+#[derive(Debug)]
+pub struct EncodingConstraint {
+    pub outcome: EncodingOutcome,
+    pub conditions: Vec<Exp<FieldVar>>,
+}
+
+fn conjunction(exps: impl Iterator<Item = Exp<Sym>>) -> Exp<Sym> {
+    exps.reduce(|lhs, rhs| Exp::And(Box::new(lhs), Box::new(rhs))).unwrap_or(Exp::Bool(true))
+}
+
+/// Find the constraints on the fields of a partial instruction which
+/// lead to each outcome of decoding and executing it. Each path is
+/// given in chronological order, along with the error message if it
+/// ended in an error. `fields` are the names, sizes, and variables of
+/// the symbolic segments of the instruction, and `assumptions` are
+/// the constraints it was executed under, which the conditions found
+/// should be combined with. The result is sorted by outcome.
+///
+/// The branches a path takes on the fields include those which only
+/// choose between operands, such as the register a field names. Each
+/// branch is removed from a path's condition if the remaining
+/// branches still imply the instruction has the same outcome, which
+/// leaves the branches made while decoding.
+pub fn encoding_constraints<B: BV>(
+    paths: &[(EvPath<B>, Option<String>)],
+    fields: &[(String, u32, Sym)],
+    assumptions: &[Exp<Sym>],
+    exception_functions: &HashMap<Name, String>,
+) -> Result<Vec<EncodingConstraint>, ExecError> {
+    let field_names: HashMap<Sym, &String> = fields.iter().map(|(name, _, v)| (*v, name)).collect();
+    let mut outcomes: Vec<(EncodingOutcome, Vec<Vec<Exp<Sym>>>)> = Vec::new();
+
+    for (events, error) in paths {
+        let mut defs: HashMap<Sym, &Exp<Sym>> = HashMap::new();
+        let mut forks: Vec<&Exp<Sym>> = Vec::new();
+        let mut after_fork = false;
+        let mut exception = None;
+
+        for event in events {
+            match event {
+                Event::Fork(..) => after_fork = true,
+                Event::Smt(smtlib::Def::Assert(exp), _, _) if after_fork => {
+                    forks.push(exp);
+                    after_fork = false
+                }
+                Event::Smt(smtlib::Def::DefineConst(v, exp), _, _) => {
+                    defs.insert(*v, exp);
+                }
+                Event::Function { name, call: true } if exception.is_none() => {
+                    exception = exception_functions.get(name).cloned()
+                }
+                _ => (),
+            }
+        }
+
+        let outcome = match (error, exception) {
+            (Some(msg), _) => EncodingOutcome::Error(msg.clone()),
+            (None, Some(kind)) => EncodingOutcome::Exception(kind),
+            (None, None) => EncodingOutcome::Decoded,
+        };
+
+        let branches: Vec<Exp<Sym>> = forks
+            .iter()
+            .map(|exp| exp.clone_expand(&defs).eval())
+            .filter(|exp| {
+                let vars = exp.variables();
+                !vars.is_empty() && vars.iter().all(|v| field_names.contains_key(v))
+            })
+            .collect();
+
+        match outcomes.iter_mut().find(|(other, _)| *other == outcome) {
+            Some((_, conditions)) => conditions.push(branches),
+            None => outcomes.push((outcome, vec![branches])),
+        }
+    }
+    outcomes.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+    let cfg = smt::Config::new();
+    let ctx = smt::Context::new(cfg);
+    let mut solver = Solver::<B>::new(&ctx);
+    for (_, size, v) in fields {
+        solver.add(smtlib::Def::DeclareConst(*v, smtlib::Ty::BitVec(*size)))
+    }
+    for assumption in assumptions {
+        solver.add(smtlib::Def::Assert(assumption.clone()))
+    }
+
+    let mut constraints = Vec::new();
+    for (outcome, paths) in outcomes {
+        let region = paths
+            .iter()
+            .map(|branches| conjunction(branches.iter().cloned()))
+            .reduce(|lhs, rhs| Exp::Or(Box::new(lhs), Box::new(rhs)))
+            .unwrap_or(Exp::Bool(false));
+        let outside = Exp::Not(Box::new(region));
+
+        let mut conditions = Vec::new();
+        for mut branches in paths {
+            let mut i = 0;
+            while i < branches.len() {
+                let rest = branches.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, exp)| exp.clone());
+                let leaves = Exp::And(Box::new(conjunction(rest)), Box::new(outside.clone()));
+                match solver.check_sat_with(&leaves) {
+                    SmtResult::Unsat => {
+                        branches.remove(i);
+                    }
+                    SmtResult::Sat => i += 1,
+                    SmtResult::Unknown => return Err(ExecError::Z3Unknown),
+                }
+            }
+            let condition = conjunction(branches.into_iter());
+            conditions
+                .push(condition.map_var(&mut |v| Ok::<_, ()>(Exp::Var(FieldVar(field_names[v].clone())))).unwrap())
+        }
+        constraints.push(EncodingConstraint { outcome, conditions })
+    }
+
+    Ok(constraints)
+}
+
 #[derive(Debug)]
 pub enum FootprintError {
     NoIslaFootprintFn,
     SymbolicInstruction,
+    UnsatAssumptions,
     Trace(TraceError),
 }
 
@@ -439,6 +1363,7 @@ impl fmt::Display for FootprintError {
                  can be used to decode and execute an instruction"
             ),
             SymbolicInstruction => write!(f, "Instruction opcode found during footprint analysis was symbolic"),
+            UnsatAssumptions => write!(f, "The assumptions about the initial state are unsatisfiable"),
             Trace(msg) => write!(f, "{}", msg),
         }
     }
@@ -450,78 +1375,726 @@ impl Error for FootprintError {
     }
 }
 
-/// # Arguments
-///
-/// * `num_threads` - How many threads to use for analysing footprints
-/// * `thread_buckets` - A vector of paths (event vectors) for each thread in the litmus test
-/// * `arch` - The initial state and configuration of the architecture
-/// * `cache_dir` - A directory to cache footprint results
-pub fn footprint_analysis<'ir, B>(
-    num_threads: usize,
-    thread_buckets: &[Vec<EvPath<B>>],
-    arch: &InitArchWithConfig<'ir, B>,
-    cache: Option<&Path>,
-) -> Result<HashMap<B, Footprint>, FootprintError>
-where
-    B: BV,
-{
-    let mut concrete_opcodes: HashSet<B> = HashSet::new();
-    let mut footprints = HashMap::new();
+/// The bitvector holding the bitfields of a register, if a register
+/// access touches it.
+fn bitfield_value<'a, B>(bitfields: &RegisterBitfields, accessor: &[Accessor], val: &'a Val<B>) -> Option<&'a Val<B>> {
+    match (bitfields.field, accessor) {
+        (None, []) => Some(val),
+        (Some(field), [Accessor::Field(f)]) if *f == field => Some(val),
+        (Some(field), []) => match val {
+            Val::Struct(fields) => fields.get(&field),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-    for thread in thread_buckets {
-        for path in thread {
-            for event in path {
-                match event {
-                    Event::Instr(Val::Bits(bv)) => {
-                        if let Some(cache_dir) = &cache {
-                            if let Some(footprint) =
-                                Footprint::from_cache(Footprintkey { opcode: bv.to_string() }, cache_dir)
-                            {
-                                footprints.insert(*bv, footprint);
-                            } else {
-                                concrete_opcodes.insert(*bv);
-                            }
-                        } else {
-                            concrete_opcodes.insert(*bv);
-                        }
-                    }
-                    Event::Instr(_) => return Err(FootprintError::SymbolicInstruction),
-                    _ => (),
-                }
-            }
+/// Tracks which bits of each declared variable on a path are used,
+/// looking through the definitions of defined variables. This lets
+/// the footprint report the individual fields of system registers an
+/// instruction depends on, rather than the whole register.
+#[derive(Default)]
+struct BitUses<'ev> {
+    defs: HashMap<Sym, &'ev Exp<Sym>>,
+    tcx: HashMap<Sym, smtlib::Ty>,
+    visited: HashSet<(Sym, u32, u32)>,
+    uses: HashMap<Sym, Vec<(u32, u32)>>,
+}
+
+impl<'ev> BitUses<'ev> {
+    fn define(&mut self, v: Sym, exp: &'ev Exp<Sym>) {
+        if let Some(ty) = exp.infer(&self.tcx, &HashMap::new()) {
+            self.tcx.insert(v, ty);
         }
+        self.defs.insert(v, exp);
     }
 
-    log!(log::VERBOSE, &format!("Got {} uncached concrete opcodes for footprint analysis", concrete_opcodes.len()));
+    fn width(&self, exp: &Exp<Sym>) -> Option<u32> {
+        match exp.infer(&self.tcx, &HashMap::new()) {
+            Some(smtlib::Ty::BitVec(width)) => Some(width),
+            _ => None,
+        }
+    }
 
-    let function_id = match arch.shared_state.symtab.get("zisla_footprint") {
-        Some(id) => id,
-        None => return Err(FootprintError::NoIslaFootprintFn),
-    };
-    let (args, ret_ty, instrs) =
-        arch.shared_state.functions.get(&function_id).expect("isla_footprint function not in shared state!");
+    /// The bits `hi` to `lo` of an expression, if they are constant
+    fn constant(&self, exp: &Exp<Sym>, hi: u32, lo: u32) -> Option<B64> {
+        let exp = match exp {
+            Exp::Var(v) => self.defs.get(v)?,
+            _ => exp,
+        };
+        match Exp::Extract(hi, lo, Box::new(exp.clone())).eval() {
+            Exp::Bits64(bv) => Some(bv),
+            _ => None,
+        }
+    }
 
-    let task_state = TaskState::new();
-    let (task_opcodes, tasks): (Vec<B>, Vec<_>) = concrete_opcodes
-        .iter()
-        .enumerate()
-        .map(|(i, opcode)| {
-            (
-                opcode,
-                LocalFrame::new(function_id, args, ret_ty, Some(&[Val::Bits(*opcode)]), instrs)
-                    .add_lets(arch.lets)
-                    .add_regs(arch.regs)
-                    .task(i, &task_state),
-            )
+    fn shift_amount(&self, exp: &Exp<Sym>) -> Option<u32> {
+        let width = self.width(exp)?;
+        let shift = self.constant(exp, width - 1, 0)?;
+        u32::try_from(shift.lower_u64()).ok()
+    }
+
+    fn overlaps(&self, v: Sym, hi: u32, lo: u32) -> bool {
+        self.uses.get(&v).is_some_and(|uses| uses.iter().any(|(use_hi, use_lo)| *use_lo <= hi && lo <= *use_hi))
+    }
+
+    fn use_var(&mut self, v: Sym, hi: u32, lo: u32) {
+        if !self.visited.insert((v, hi, lo)) {
+            return;
+        }
+        match self.defs.get(&v).copied() {
+            Some(exp) => self.use_bits(exp, hi, lo),
+            None => self.uses.entry(v).or_default().push((hi, lo)),
+        }
+    }
+
+    fn use_var_all(&mut self, v: Sym) {
+        match self.tcx.get(&v).cloned() {
+            Some(smtlib::Ty::BitVec(width)) if width > 0 => self.use_var(v, width - 1, 0),
+            _ => {
+                if let Some(exp) = self.defs.get(&v).copied() {
+                    if self.visited.insert((v, u32::MAX, u32::MAX)) {
+                        self.use_all(exp)
+                    }
+                }
+            }
+        }
+    }
+
+    fn use_vars(&mut self, exp: &Exp<Sym>) {
+        for v in exp.variables() {
+            self.use_var_all(v)
+        }
+    }
+
+    /// Record that every bit of an expression is used
+    fn use_all(&mut self, exp: &Exp<Sym>) {
+        use Exp::*;
+        if let Some(width) = self.width(exp) {
+            if width > 0 {
+                self.use_bits(exp, width - 1, 0)
+            }
+            return;
+        }
+        match exp {
+            Var(v) => self.use_var_all(*v),
+            Not(exp) => self.use_all(exp),
+            Eq(lhs, rhs) | Neq(lhs, rhs) | And(lhs, rhs) | Or(lhs, rhs) => {
+                self.use_all(lhs);
+                self.use_all(rhs)
+            }
+            Ite(cond, then_exp, else_exp) => {
+                self.use_all(cond);
+                self.use_all(then_exp);
+                self.use_all(else_exp)
+            }
+            _ => self.use_vars(exp),
+        }
+    }
+
+    /// Record that bits `hi` to `lo` of a bitvector expression are used
+    fn use_bits(&mut self, exp: &Exp<Sym>, hi: u32, lo: u32) {
+        use Exp::*;
+        match exp {
+            Var(v) => self.use_var(*v, hi, lo),
+            Bits(_) | Bits64(_) => (),
+            Extract(_, l, exp) => self.use_bits(exp, hi + l, lo + l),
+            Concat(lhs, rhs) => match self.width(rhs) {
+                Some(w) if hi < w => self.use_bits(rhs, hi, lo),
+                Some(w) if lo >= w => self.use_bits(lhs, hi - w, lo - w),
+                Some(w) => {
+                    self.use_bits(rhs, w - 1, lo);
+                    self.use_bits(lhs, hi - w, 0)
+                }
+                None => self.use_vars(exp),
+            },
+            ZeroExtend(_, exp) => match self.width(exp) {
+                Some(w) if lo < w => self.use_bits(exp, hi.min(w - 1), lo),
+                Some(_) => (),
+                None => self.use_vars(exp),
+            },
+            SignExtend(_, exp) => match self.width(exp) {
+                Some(w) => self.use_bits(exp, hi.min(w - 1), lo.min(w - 1)),
+                None => self.use_vars(exp),
+            },
+            Bvnot(exp) => self.use_bits(exp, hi, lo),
+            // Bits masked out by a bitwise and (or or) do not depend on the other operand
+            Bvand(lhs, rhs) if self.zeros(lhs, hi, lo) => self.use_bits(lhs, hi, lo),
+            Bvand(lhs, rhs) if self.zeros(rhs, hi, lo) => self.use_bits(rhs, hi, lo),
+            Bvor(lhs, rhs) if self.ones(lhs, hi, lo) => self.use_bits(lhs, hi, lo),
+            Bvor(lhs, rhs) if self.ones(rhs, hi, lo) => self.use_bits(rhs, hi, lo),
+            Bvand(lhs, rhs) | Bvor(lhs, rhs) | Bvxor(lhs, rhs) => {
+                self.use_bits(lhs, hi, lo);
+                self.use_bits(rhs, hi, lo)
+            }
+            Bvshl(exp, shift) => match self.shift_amount(shift) {
+                Some(k) if hi < k => (),
+                Some(k) => self.use_bits(exp, hi - k, lo.saturating_sub(k)),
+                None => {
+                    self.use_all(exp);
+                    self.use_all(shift)
+                }
+            },
+            Bvlshr(exp, shift) => match (self.shift_amount(shift), self.width(exp)) {
+                (Some(k), Some(w)) if lo.saturating_add(k) < w => {
+                    self.use_bits(exp, hi.saturating_add(k).min(w - 1), lo + k)
+                }
+                (Some(_), Some(_)) => (),
+                _ => {
+                    self.use_all(exp);
+                    self.use_all(shift)
+                }
+            },
+            Ite(cond, then_exp, else_exp) => {
+                self.use_all(cond);
+                self.use_bits(then_exp, hi, lo);
+                self.use_bits(else_exp, hi, lo)
+            }
+            _ => self.use_vars(exp),
+        }
+    }
+
+    /// Are bits `hi` to `lo` of an expression all zero?
+    fn zeros(&self, exp: &Exp<Sym>, hi: u32, lo: u32) -> bool {
+        use Exp::*;
+        if let Some(bv) = self.constant(exp, hi, lo) {
+            return bv.is_zero();
+        }
+        match exp {
+            Var(v) => self.defs.get(v).is_some_and(|exp| self.zeros(exp, hi, lo)),
+            Extract(_, l, exp) => self.zeros(exp, hi + l, lo + l),
+            Concat(lhs, rhs) => match self.width(rhs) {
+                Some(w) if hi < w => self.zeros(rhs, hi, lo),
+                Some(w) if lo >= w => self.zeros(lhs, hi - w, lo - w),
+                Some(w) => self.zeros(rhs, w - 1, lo) && self.zeros(lhs, hi - w, 0),
+                None => false,
+            },
+            ZeroExtend(_, exp) => match self.width(exp) {
+                Some(w) if lo >= w => true,
+                Some(w) => self.zeros(exp, hi.min(w - 1), lo),
+                None => false,
+            },
+            Bvshl(exp, shift) => match self.shift_amount(shift) {
+                Some(k) if hi < k => true,
+                Some(k) => self.zeros(exp, hi - k, lo.saturating_sub(k)),
+                None => false,
+            },
+            Bvnot(exp) => self.ones(exp, hi, lo),
+            Bvand(lhs, rhs) => self.zeros(lhs, hi, lo) || self.zeros(rhs, hi, lo),
+            Bvor(lhs, rhs) => self.zeros(lhs, hi, lo) && self.zeros(rhs, hi, lo),
+            Ite(_, then_exp, else_exp) => self.zeros(then_exp, hi, lo) && self.zeros(else_exp, hi, lo),
+            _ => false,
+        }
+    }
+
+    /// Are bits `hi` to `lo` of an expression all ones?
+    fn ones(&self, exp: &Exp<Sym>, hi: u32, lo: u32) -> bool {
+        use Exp::*;
+        if let Some(bv) = self.constant(exp, hi, lo) {
+            return bv == B64::ones(hi - lo + 1);
+        }
+        match exp {
+            Var(v) => self.defs.get(v).is_some_and(|exp| self.ones(exp, hi, lo)),
+            Extract(_, l, exp) => self.ones(exp, hi + l, lo + l),
+            Bvnot(exp) => self.zeros(exp, hi, lo),
+            Bvand(lhs, rhs) => self.ones(lhs, hi, lo) && self.ones(rhs, hi, lo),
+            Bvor(lhs, rhs) => self.ones(lhs, hi, lo) || self.ones(rhs, hi, lo),
+            Ite(_, then_exp, else_exp) => self.ones(then_exp, hi, lo) && self.ones(else_exp, hi, lo),
+            _ => false,
+        }
+    }
+
+    /// Are bits `hi` to `lo` of an expression the same as the bits
+    /// starting at `target` of one of the `original` variables? This
+    /// is true for the fields a read-modify-write of a register
+    /// leaves unchanged.
+    fn preserves(&self, exp: &Exp<Sym>, hi: u32, lo: u32, target: u32, original: &HashSet<Sym>) -> bool {
+        use Exp::*;
+        match exp {
+            Var(v) if original.contains(v) => lo == target,
+            Var(v) => self.defs.get(v).is_some_and(|exp| self.preserves(exp, hi, lo, target, original)),
+            Extract(_, l, exp) => self.preserves(exp, hi + l, lo + l, target, original),
+            Concat(lhs, rhs) => match self.width(rhs) {
+                Some(w) if hi < w => self.preserves(rhs, hi, lo, target, original),
+                Some(w) if lo >= w => self.preserves(lhs, hi - w, lo - w, target, original),
+                Some(w) => {
+                    self.preserves(rhs, w - 1, lo, target, original)
+                        && self.preserves(lhs, hi - w, 0, target + (w - lo), original)
+                }
+                None => false,
+            },
+            ZeroExtend(_, exp) | SignExtend(_, exp) => match self.width(exp) {
+                Some(w) if hi < w => self.preserves(exp, hi, lo, target, original),
+                _ => false,
+            },
+            Bvand(lhs, rhs) => {
+                (self.ones(lhs, hi, lo) && self.preserves(rhs, hi, lo, target, original))
+                    || (self.ones(rhs, hi, lo) && self.preserves(lhs, hi, lo, target, original))
+            }
+            Bvor(lhs, rhs) => {
+                (self.zeros(lhs, hi, lo) && self.preserves(rhs, hi, lo, target, original))
+                    || (self.zeros(rhs, hi, lo) && self.preserves(lhs, hi, lo, target, original))
+            }
+            Ite(_, then_exp, else_exp) => {
+                self.preserves(then_exp, hi, lo, target, original) && self.preserves(else_exp, hi, lo, target, original)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The parts of a register value a bitfield layout applies to: the
+/// value itself, or each element of a vector register.
+fn bitfield_parts<'a, B>(
+    bitfields: &RegisterBitfields,
+    accessor: &[Accessor],
+    val: &'a Val<B>,
+) -> Vec<(Option<usize>, &'a Val<B>)> {
+    match bitfield_value(bitfields, accessor, val) {
+        Some(Val::Vector(elems)) => elems.iter().enumerate().map(|(i, elem)| (Some(i), elem)).collect(),
+        Some(val) => vec![(None, val)],
+        None => vec![],
+    }
+}
+
+/// The name of a field, prefixed by its element index within a
+/// vector register, e.g. `3.lane0`
+fn part_field_name(element: Option<usize>, bitfield: &Bitfield) -> String {
+    match element {
+        Some(i) => format!("{}.{}", i, bitfield.name),
+        None => bitfield.name.clone(),
+    }
+}
+
+/// Find the fields of the registers in `bitfields` that are read and
+/// written on a path. A field is read if any of its bits are used by
+/// the path, and written unless the value written back leaves it
+/// unchanged. When a register's value is not a symbolic bitvector we
+/// cannot tell, so all its fields are reported, unless a concrete
+/// value is written back unchanged.
+fn register_field_accesses<B: BV>(
+    events: &[Event<B>],
+    bitfields: &HashMap<Name, RegisterBitfields>,
+    ignored_registers: &HashSet<Name>,
+    footprint: &mut Footprint,
+) {
+    type Part = (Name, Option<usize>);
+
+    let mut uses = BitUses::default();
+    let mut originals: HashMap<Part, HashSet<Sym>> = HashMap::new();
+    let mut opaque_reads: HashMap<Part, Vec<&Val<B>>> = HashMap::new();
+
+    for event in events {
+        match event {
+            Event::Smt(smtlib::Def::DeclareConst(v, ty), _, _) => {
+                uses.tcx.insert(*v, ty.clone());
+            }
+            Event::Smt(smtlib::Def::DefineConst(v, exp), _, _) => uses.define(*v, exp),
+            Event::ReadReg(reg, accessor, val, _) if !ignored_registers.contains(reg) => {
+                let Some(layout) = bitfields.get(reg) else { continue };
+                for (element, part) in bitfield_parts(layout, accessor, val) {
+                    match part {
+                        Val::Symbolic(v) => {
+                            originals.entry((*reg, element)).or_default().insert(*v);
+                        }
+                        _ => opaque_reads.entry((*reg, element)).or_default().push(part),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let no_originals = HashSet::new();
+    for event in events {
+        match event {
+            Event::Smt(smtlib::Def::Assert(exp), _, _) => uses.use_all(exp),
+            Event::Fork(_, v, _, _) => uses.use_var_all(*v),
+            Event::WriteReg(reg, accessor, val, _) => {
+                let layout = bitfields.get(reg).filter(|_| !ignored_registers.contains(reg));
+                let Some(layout) = layout else {
+                    val.symbolic_variables().into_iter().for_each(|v| uses.use_var_all(v));
+                    continue;
+                };
+                let parts = bitfield_parts(layout, accessor, val);
+                if parts.is_empty() {
+                    val.symbolic_variables().into_iter().for_each(|v| uses.use_var_all(v));
+                    continue;
+                }
+                let mut written = HashSet::new();
+                for (element, part) in parts {
+                    match part {
+                        Val::Symbolic(w) => {
+                            let original = originals.get(&(*reg, element)).unwrap_or(&no_originals);
+                            let w_exp = Exp::Var(*w);
+                            for bitfield in &layout.layout {
+                                if !uses.preserves(&w_exp, bitfield.hi, bitfield.lo, bitfield.lo, original) {
+                                    footprint.register_field_writes.insert((*reg, part_field_name(element, bitfield)));
+                                    uses.use_bits(&w_exp, bitfield.hi, bitfield.lo)
+                                }
+                            }
+                            written.insert(*w);
+                        }
+                        _ => {
+                            let unchanged =
+                                opaque_reads.get(&(*reg, element)).is_some_and(|reads| reads.contains(&part));
+                            if !unchanged {
+                                for bitfield in &layout.layout {
+                                    footprint.register_field_writes.insert((*reg, part_field_name(element, bitfield)));
+                                }
+                            }
+                            part.symbolic_variables().into_iter().for_each(|v| uses.use_var_all(v))
+                        }
+                    }
+                }
+                for v in val.symbolic_variables() {
+                    if !written.contains(&v) {
+                        uses.use_var_all(v)
+                    }
+                }
+            }
+            Event::ReadMem { address, .. }
+            | Event::AddressAnnounce { address, .. }
+            | Event::Branch { address, .. }
+            | Event::BlockOp { address, .. } => {
+                address.symbolic_variables().into_iter().for_each(|v| uses.use_var_all(v))
+            }
+            Event::WriteMem { address, data, .. } => {
+                address.symbolic_variables().into_iter().for_each(|v| uses.use_var_all(v));
+                data.symbolic_variables().into_iter().for_each(|v| uses.use_var_all(v))
+            }
+            _ => (),
+        }
+    }
+
+    for ((reg, element), original) in &originals {
+        for bitfield in &bitfields[reg].layout {
+            if original.iter().any(|v| uses.overlaps(*v, bitfield.hi, bitfield.lo)) {
+                footprint.register_field_reads.insert((*reg, part_field_name(*element, bitfield)));
+            }
+        }
+    }
+    for (reg, element) in opaque_reads.keys() {
+        for bitfield in &bitfields[reg].layout {
+            footprint.register_field_reads.insert((*reg, part_field_name(*element, bitfield)));
+        }
+    }
+}
+
+/// The effects of a single path through an instruction, used to
+/// determine whether any of them are conditional.
+#[derive(Default, PartialEq, Eq)]
+struct PathEffects {
+    register_writes: HashSet<RegisterField>,
+    memory_reads: HashSet<u32>,
+    memory_writes: HashSet<u32>,
+    block_ops: HashSet<u32>,
+    is_branch: bool,
+}
+
+/// The data memory accesses on a path, as (is write, address, bytes)
+/// triples. Instruction fetches are excluded when they are kept in
+/// the trace.
+fn data_accesses<B: BV>(events: &[Event<B>], keep_ifetch: bool) -> Vec<(bool, &Val<B>, u32)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::ReadMem { opts, .. } if keep_ifetch && opts.is_ifetch => None,
+            Event::ReadMem { address, bytes, .. } => Some((false, address, *bytes)),
+            Event::WriteMem { address, bytes, .. } => Some((true, address, *bytes)),
+            _ => None,
         })
-        .unzip();
+        .collect()
+}
 
+/// Replay the definitions on a path into `solver`, skipping any which
+/// refer to variables defined before the first cycle. Returns the
+/// variables which were declared and the conjunction of the path's
+/// assertions.
+fn replay_path<B: BV>(events: &[Event<B>], solver: &mut Solver<B>) -> (HashSet<Sym>, Exp<Sym>) {
+    let mut declared: HashSet<Sym> = HashSet::new();
+    let mut assertions = Vec::new();
+    for event in events {
+        let Event::Smt(def, _, _) = event else { continue };
+        match def {
+            smtlib::Def::DeclareConst(v, _) | smtlib::Def::DeclareFun(v, _, _) => {
+                declared.insert(*v);
+                solver.add(def.clone())
+            }
+            smtlib::Def::DefineConst(v, exp) if exp.variables().iter().all(|v| declared.contains(v)) => {
+                declared.insert(*v);
+                solver.add(def.clone())
+            }
+            smtlib::Def::Assert(exp) if exp.variables().iter().all(|v| declared.contains(v)) => {
+                assertions.push(exp.clone())
+            }
+            smtlib::Def::DefineEnum(..) => solver.add(def.clone()),
+            _ => (),
+        }
+    }
+    (declared, conjunction(assertions.into_iter()))
+}
+
+/// The alignment of a memory access of `bytes` bytes at `address`,
+/// where `path_condition` holds on the path making the access and
+/// `solver` contains the definitions of the variables in `declared`.
+fn access_alignment<B: BV>(
+    address: &Val<B>,
+    bytes: u32,
+    path_condition: &Exp<Sym>,
+    declared: &HashSet<Sym>,
+    solver: &mut Solver<B>,
+) -> Alignment {
+    if bytes <= 1 {
+        return Alignment::Guaranteed;
+    }
+    let v = match address {
+        Val::Bits(bv) if bv.lower_u64() % bytes as u64 == 0 => return Alignment::Guaranteed,
+        Val::Symbolic(v) if declared.contains(v) => *v,
+        _ => return Alignment::Unaligned,
+    };
+    let Some(width) = solver.length(v) else {
+        return Alignment::Unaligned;
+    };
+    let misaligned = Exp::Neq(
+        Box::new(Exp::Bvurem(Box::new(Exp::Var(v)), Box::new(bits64(bytes as u64, width)))),
+        Box::new(bits64(0, width)),
+    );
+    match solver.check_sat_with(&Exp::And(Box::new(path_condition.clone()), Box::new(misaligned.clone()))) {
+        SmtResult::Unsat => match solver.check_sat_with(&misaligned) {
+            SmtResult::Unsat => Alignment::Guaranteed,
+            _ => Alignment::Required,
+        },
+        _ => Alignment::Unaligned,
+    }
+}
+
+/// Determine the alignment of the data memory reads and writes on a
+/// path (in chronological order). The path's definitions are replayed
+/// into a solver when any of the addresses are symbolic, skipping any
+/// which refer to variables defined before the first cycle (which are
+/// not part of footprint paths), so such addresses are treated as
+/// possibly unaligned.
+fn path_alignment<B: BV>(events: &[Event<B>], keep_ifetch: bool) -> (Option<Alignment>, Option<Alignment>) {
+    let accesses = data_accesses(events, keep_ifetch);
+    if accesses.is_empty() {
+        return (None, None);
+    }
+
+    let cfg = smt::Config::new();
+    let ctx = smt::Context::new(cfg);
+    let mut solver = Solver::<B>::new(&ctx);
+    let (declared, path_condition) = if accesses.iter().any(|(_, address, _)| matches!(address, Val::Symbolic(_))) {
+        replay_path(events, &mut solver)
+    } else {
+        (HashSet::new(), Exp::Bool(true))
+    };
+
+    let (mut reads, mut writes) = (None, None);
+    for (is_write, address, bytes) in accesses {
+        let alignment = access_alignment(address, bytes, &path_condition, &declared, &mut solver);
+        let class = if is_write { &mut writes } else { &mut reads };
+        *class = (*class).max(Some(alignment))
+    }
+    (reads, writes)
+}
+
+/// The first of `regions` which contains every byte of a memory
+/// access of `bytes` bytes at `address` whenever `path_condition`
+/// holds, if there is one.
+fn access_region<'r, B: BV>(
+    address: &Val<B>,
+    bytes: u32,
+    regions: &'r [MemoryRegion],
+    path_condition: &Exp<Sym>,
+    declared: &HashSet<Sym>,
+    solver: &mut Solver<B>,
+) -> Option<&'r MemoryRegion> {
+    let bytes = bytes.max(1) as u128;
+    match address {
+        Val::Bits(bv) => {
+            let address = bv.lower_u64() as u128;
+            regions
+                .iter()
+                .find(|region| region.range.start as u128 <= address && address + bytes <= region.range.end as u128)
+        }
+        Val::Symbolic(v) if declared.contains(v) => {
+            let width = solver.length(*v)?;
+            let max = if width >= 64 { u64::MAX as u128 } else { (1u128 << width) - 1 };
+            regions.iter().find(|region| {
+                // The highest address the access can start at while staying in the region
+                let Some(last) = (region.range.end as u128).checked_sub(bytes) else { return false };
+                let last = last.min(max);
+                if (region.range.start as u128) > last {
+                    return false;
+                }
+                let outside = Exp::Or(
+                    Box::new(Exp::Bvult(Box::new(Exp::Var(*v)), Box::new(bits64(region.range.start, width)))),
+                    Box::new(Exp::Bvugt(Box::new(Exp::Var(*v)), Box::new(bits64(last as u64, width)))),
+                );
+                matches!(
+                    solver.check_sat_with(&Exp::And(Box::new(path_condition.clone()), Box::new(outside))),
+                    SmtResult::Unsat
+                )
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Classify the data memory reads and writes on a path by the
+/// `regions` they are provably within, using `unknown` for any
+/// access which is not provably within a single region. As for
+/// [path_alignment], addresses which depend on variables defined
+/// before the first cycle are not provably within any region.
+fn path_regions<B: BV>(
+    events: &[Event<B>],
+    keep_ifetch: bool,
+    regions: &[MemoryRegion],
+) -> (HashSet<String>, HashSet<String>) {
+    let (mut reads, mut writes) = (HashSet::new(), HashSet::new());
+    let accesses = data_accesses(events, keep_ifetch);
+    if accesses.is_empty() || regions.is_empty() {
+        return (reads, writes);
+    }
+
+    let cfg = smt::Config::new();
+    let ctx = smt::Context::new(cfg);
+    let mut solver = Solver::<B>::new(&ctx);
+    let (declared, path_condition) = if accesses.iter().any(|(_, address, _)| matches!(address, Val::Symbolic(_))) {
+        replay_path(events, &mut solver)
+    } else {
+        (HashSet::new(), Exp::Bool(true))
+    };
+
+    for (is_write, address, bytes) in accesses {
+        let region = access_region(address, bytes, regions, &path_condition, &declared, &mut solver)
+            .map(|region| region.name.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+        if is_write { &mut writes } else { &mut reads }.insert(region);
+    }
+    (reads, writes)
+}
+
+/// Find the exclusive and atomic accesses made by the `paths` through
+/// an instruction, where `is_exclusive` tests whether a memory event
+/// is exclusive. Returns `None` if there are none.
+fn exclusive_accesses<B: BV>(
+    paths: &[Vec<Event<B>>],
+    is_exclusive: &dyn Fn(&Event<B>) -> bool,
+    ignored_registers: &HashSet<Name>,
+) -> Option<Exclusives> {
+    let mut exclusives = Exclusives::default();
+    let mut stores = Vec::new();
+
+    for events in paths {
+        let mut reads: Vec<&Val<B>> = Vec::new();
+        let mut stored = false;
+        for event in events {
+            match event {
+                Event::ReadMem { opts, .. } if opts.is_ifetch => (),
+                Event::ReadMem { address, bytes, .. } => {
+                    if is_exclusive(event) {
+                        exclusives.read_widths.insert(*bytes);
+                    }
+                    reads.push(address)
+                }
+                Event::WriteMem { address, bytes, .. } => {
+                    if is_exclusive(event) {
+                        exclusives.write_widths.insert(*bytes);
+                        stored = true
+                    }
+                    if reads.contains(&address) {
+                        exclusives.atomic_widths.insert(*bytes);
+                        stored = true
+                    }
+                }
+                _ => (),
+            }
+        }
+        stores.push(stored)
+    }
+
+    if exclusives.read_widths.is_empty() && exclusives.write_widths.is_empty() && exclusives.atomic_widths.is_empty() {
+        return None;
+    }
+
+    exclusives.conditional = stores.contains(&true) && stores.contains(&false);
+    if exclusives.conditional {
+        for (events, _) in paths.iter().zip(stores.iter()).filter(|(_, stored)| !**stored) {
+            for event in events {
+                let Event::WriteReg(reg, accessor, value, _) = event else { continue };
+                if ignored_registers.contains(reg) {
+                    continue;
+                }
+                let value = match value {
+                    Val::Bits(bv) => Some(bv.to_string()),
+                    _ => None,
+                };
+                match exclusives.failure_writes.iter_mut().find(|((r, a), _)| r == reg && a == accessor) {
+                    Some((_, existing)) if *existing != value => *existing = None,
+                    Some(_) => (),
+                    None => exclusives.failure_writes.push(((*reg, accessor.clone()), value)),
+                }
+            }
+        }
+    }
+
+    Some(exclusives)
+}
+
+/// Create a task running `function_id` on `opcode` from the register
+/// state `regs`, with the assumptions in the ISA config asserted
+/// about that initial state.
+fn footprint_task<'ir, 'task, B: BV>(
+    task_id: usize,
+    function_id: Name,
+    opcode: B,
+    regs: &RegisterBindings<'ir, B>,
+    arch: &InitArchWithConfig<'ir, B>,
+    task_state: &'task TaskState<B>,
+) -> Result<Task<'ir, 'task, B>, FootprintError> {
+    let (args, ret_ty, instrs) =
+        arch.shared_state.functions.get(&function_id).ok_or(FootprintError::NoIslaFootprintFn)?;
+    let mut frame = LocalFrame::new(function_id, args, ret_ty, Some(&[Val::Bits(opcode)]), instrs);
+    frame.add_lets(arch.lets).add_regs(regs);
+    if arch.isa_config.assumptions.is_empty() {
+        return Ok(frame.task(task_id, task_state));
+    }
+
+    let solver_cfg = smt::Config::new();
+    let solver_ctx = smt::Context::new(solver_cfg);
+    let mut solver = Solver::new(&solver_ctx);
+    match executor::assume_constraints(
+        &arch.isa_config.assumptions,
+        &mut frame,
+        arch.shared_state,
+        &mut solver,
+        SourceLoc::command_line(),
+    ) {
+        Ok(()) => Ok(frame.task_with_checkpoint(task_id, task_state, smt::checkpoint(&mut solver))),
+        Err(ExecError::Dead) => Err(FootprintError::UnsatAssumptions),
+        Err(err) => Err(FootprintError::Trace(TraceError::exec(err))),
+    }
+}
+
+/// Execute each task, returning the paths of each with only the
+/// events relevant to footprint analysis kept
+fn run_footprint_tasks<'ir, B: BV>(
+    num_threads: usize,
+    tasks: Vec<Task<'ir, '_, B>>,
+    shared_state: &SharedState<'ir, B>,
+) -> Result<Vec<Vec<EvPath<B>>>, FootprintError> {
     let mut footprint_buckets: Vec<Vec<EvPath<B>>> = vec![Vec::new(); tasks.len()];
     let queue = Arc::new(SegQueue::new());
 
-    let now = Instant::now();
-    executor::start_multi(num_threads, None, tasks, arch.shared_state, queue.clone(), &executor::footprint_collector);
-    log!(log::VERBOSE, &format!("Footprint analysis symbolic execution took: {}ms", now.elapsed().as_millis()));
+    if num_threads == 0 {
+        for task in tasks {
+            executor::start_single(task, shared_state, queue.as_ref(), &executor::footprint_collector)
+        }
+    } else {
+        executor::start_multi(num_threads, None, tasks, shared_state, queue.clone(), &executor::footprint_collector)
+    }
 
     loop {
         match queue.pop() {
@@ -536,6 +2109,7 @@ where
                             || ev.is_memory_read_or_write()
                             || ev.is_address_announce()
                             || ev.is_branch()
+                            || ev.is_block_op()
                             || ev.is_smt()
                             || ev.is_fork()
                     })
@@ -551,16 +2125,189 @@ where
         }
     }
 
+    Ok(footprint_buckets)
+}
+
+/// Cross-check a footprint against the model. Each register with a
+/// concrete initial value which the footprint says `opcode` does not
+/// read is made symbolic in turn, and the instruction is executed
+/// again. If that changes the number of paths, or any register
+/// write, memory access, or branch target then depends on the
+/// register, the footprint has missed a read, and a description of
+/// the discrepancy is returned.
+pub fn check_footprint<'ir, B: BV>(
+    num_threads: usize,
+    opcode: B,
+    footprint: &Footprint,
+    arch: &InitArchWithConfig<'ir, B>,
+) -> Result<Vec<String>, FootprintError> {
+    let function_id = match arch.shared_state.symtab.get("zisla_footprint") {
+        Some(id) => id,
+        None => return Err(FootprintError::NoIslaFootprintFn),
+    };
+
+    let reads: HashSet<Name> = footprint.register_reads.iter().map(|(reg, _)| *reg).collect();
+    let mut unread: Vec<(Name, &Ty<Name>)> = arch
+        .regs
+        .iter()
+        .filter(|(reg, _)| !reads.contains(reg) && !arch.isa_config.ignored_registers.contains(reg))
+        .filter(|(_, value)| matches!(value.read_last_if_initialized(), Some(v) if v.symbolic_variables().is_empty()))
+        .filter_map(|(reg, _)| Some((*reg, arch.shared_state.registers.get(reg)?)))
+        .collect();
+    unread.sort_by_key(|(reg, _)| *reg);
+
+    // The first task is the unmodified instruction, which the others
+    // are compared against
+    let havocked: Vec<RegisterBindings<'ir, B>> = unread
+        .iter()
+        .map(|(reg, ty)| {
+            let mut regs = arch.regs.clone();
+            regs.insert(*reg, arch.isa_config.relaxed_registers.contains(reg), UVal::Uninit(ty));
+            regs
+        })
+        .collect();
+    let task_state = TaskState::new();
+    let tasks = std::iter::once(arch.regs)
+        .chain(havocked.iter())
+        .enumerate()
+        .map(|(i, regs)| footprint_task(i, function_id, opcode, regs, arch, &task_state))
+        .collect::<Result<_, _>>()?;
+    let buckets = run_footprint_tasks(num_threads, tasks, arch.shared_state)?;
+
+    let mut discrepancies = Vec::new();
+    for ((reg, _), paths) in unread.iter().zip(buckets[1..].iter()) {
+        let name = zencode::decode(arch.shared_state.symtab.to_str(*reg));
+        if paths.len() != buckets[0].len() {
+            discrepancies.push(format!(
+                "{}: making {} symbolic changes the number of paths from {} to {}",
+                opcode,
+                name,
+                buckets[0].len(),
+                paths.len()
+            ));
+            continue;
+        }
+        for events in paths {
+            let evrefs = EventReferences::from_events(events);
+            let outcomes = events.iter().flat_map(|event| match event {
+                Event::WriteReg(written, _, value, _) => {
+                    let written = zencode::decode(arch.shared_state.symtab.to_str(*written));
+                    vec![(format!("the value written to {}", written), value)]
+                }
+                Event::ReadMem { address, .. } => vec![("a memory read address".to_string(), address)],
+                Event::WriteMem { address, data, .. } => vec![
+                    ("a memory write address".to_string(), address),
+                    ("the data written to memory".to_string(), data),
+                ],
+                Event::Branch { address, .. } => vec![("a branch address".to_string(), address)],
+                _ => Vec::new(),
+            });
+            for (outcome, value) in outcomes {
+                if evrefs.value_taints(value, events).0.iter().any(|(taint, _)| taint == reg) {
+                    discrepancies
+                        .push(format!("{}: {} depends on {}, which is not in the footprint", opcode, outcome, name))
+                }
+            }
+        }
+    }
+    discrepancies.dedup();
+    Ok(discrepancies)
+}
+
+/// # Arguments
+///
+/// * `num_threads` - How many threads to use for analysing footprints,
+///   where zero runs the analysis on the calling thread without
+///   spawning any others
+/// * `thread_buckets` - A vector of paths (event vectors) for each thread in the litmus test
+/// * `arch` - The initial state and configuration of the architecture
+/// * `cache_dir` - A directory to cache footprint results
+/// * `keep_ifetch` - Track instruction fetches separately from loads,
+///   rather than treating them as ordinary memory reads
+/// * `function_cache` - Reuse the results of calls to pure functions
+///   with concrete arguments between instructions (see [FunctionCache])
+pub fn footprint_analysis<'ir, B>(
+    num_threads: usize,
+    thread_buckets: &[Vec<EvPath<B>>],
+    arch: &InitArchWithConfig<'ir, B>,
+    cache: Option<&Path>,
+    keep_ifetch: bool,
+    function_cache: bool,
+) -> Result<HashMap<B, Footprint>, FootprintError>
+where
+    B: BV,
+{
+    let mut concrete_opcodes: HashSet<B> = HashSet::new();
+    let mut footprints = HashMap::new();
+
+    for thread in thread_buckets {
+        for path in thread {
+            for event in path {
+                match event {
+                    Event::Instr(Val::Bits(bv)) => {
+                        if let Some(cache_dir) = &cache {
+                            if let Some(footprint) =
+                                Footprint::from_cache(Footprintkey { opcode: bv.to_string() }, cache_dir)
+                            {
+                                footprints.insert(*bv, footprint);
+                            } else {
+                                concrete_opcodes.insert(*bv);
+                            }
+                        } else {
+                            concrete_opcodes.insert(*bv);
+                        }
+                    }
+                    Event::Instr(_) => return Err(FootprintError::SymbolicInstruction),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    log!(log::VERBOSE, &format!("Got {} uncached concrete opcodes for footprint analysis", concrete_opcodes.len()));
+
+    let function_id = match arch.shared_state.symtab.get("zisla_footprint") {
+        Some(id) => id,
+        None => return Err(FootprintError::NoIslaFootprintFn),
+    };
+
+    let function_results = Arc::new(FunctionCache::new());
+    let task_state =
+        if function_cache { TaskState::new().with_function_cache(function_results.clone()) } else { TaskState::new() };
+    let task_opcodes: Vec<B> = concrete_opcodes.iter().copied().collect();
+    let tasks = task_opcodes
+        .iter()
+        .enumerate()
+        .map(|(i, opcode)| footprint_task(i, function_id, *opcode, arch.regs, arch, &task_state))
+        .collect::<Result<_, _>>()?;
+
+    let now = Instant::now();
+    let mut footprint_buckets = run_footprint_tasks(num_threads, tasks, arch.shared_state)?;
+    log!(log::VERBOSE, &format!("Footprint analysis symbolic execution took: {}ms", now.elapsed().as_millis()));
+    if function_cache {
+        log!(log::VERBOSE, &format!("Footprint analysis function cache: {}", function_results.counts()));
+    }
+
     let num_footprints: usize = footprint_buckets.iter().map(|instr_paths| instr_paths.len()).sum();
     log!(log::VERBOSE, &format!("There are {} footprints", num_footprints));
 
-    for (i, paths) in footprint_buckets.iter().enumerate() {
+    let is_exclusive = |event: &Event<B>| {
+        event.is_exclusive() || arch.isa_config.access_kinds_of(event).contains(&AccessKind::EXCLUSIVE)
+    };
+
+    for (i, paths) in footprint_buckets.iter_mut().enumerate() {
         let opcode = task_opcodes[i];
         log!(log::VERBOSE, &format!("{:?}", opcode));
 
         let mut footprint = Footprint::new();
+        for events in paths.iter_mut() {
+            footprint.register_views.extend(arch.isa_config.canonicalize_registers(events))
+        }
+        let paths = &*paths;
+        let mut first_effects: Option<PathEffects> = None;
 
         for events in paths {
+            let mut effects = PathEffects::default();
             let evrefs = EventReferences::from_events(events);
             let mut forks: Vec<Sym> = Vec::new();
             let mut intrinsic_data: HashMap<Sym, HashSet<RegisterField>> = HashMap::new();
@@ -578,7 +2325,7 @@ where
                         forks.push(*v);
                         intrinsic_ctrl.extend(intrinsic_data.get(&v).into_iter().cloned().flatten());
                     }
-                    Event::ReadReg(reg, accessor, val) if !arch.isa_config.ignored_registers.contains(reg) => {
+                    Event::ReadReg(reg, accessor, val, _) if !arch.isa_config.ignored_registers.contains(reg) => {
                         footprint.register_reads.insert((*reg, accessor.clone()));
                         let vars = val.symbolic_variables();
                         for v in vars {
@@ -586,8 +2333,9 @@ where
                             reg_taints.insert((*reg, accessor.clone()));
                         }
                     }
-                    Event::WriteReg(reg, accessor, data) if !arch.isa_config.ignored_registers.contains(reg) => {
+                    Event::WriteReg(reg, accessor, data, _) if !arch.isa_config.ignored_registers.contains(reg) => {
                         footprint.register_writes.insert((*reg, accessor.clone()));
+                        effects.register_writes.insert((*reg, accessor.clone()));
                         // If the data written to the register is tainted by a value read
                         // from memory record this fact.
                         if evrefs.value_taints(data, events).1 {
@@ -604,11 +2352,25 @@ where
                             footprint.register_writes_ignored.insert((Some(regs[0]), regs[1]));
                         }
                     }
-                    Event::ReadMem { address, .. } => {
+                    Event::ReadMem { address, opts, .. } if keep_ifetch && opts.is_ifetch => {
+                        footprint.is_ifetch = true;
+                        evrefs.collect_value_taints(
+                            address,
+                            events,
+                            &mut footprint.ifetch_addr_taints.0,
+                            &mut footprint.ifetch_addr_taints.1,
+                        )
+                    }
+                    Event::ReadMem { address, bytes, .. } => {
                         footprint.is_load = true;
-                        if event.is_exclusive() {
+                        footprint.mem_read_widths.insert(*bytes);
+                        effects.memory_reads.insert(*bytes);
+                        if is_exclusive(event) {
                             footprint.is_exclusive = true;
                         }
+                        footprint
+                            .mem_read_kinds
+                            .extend(arch.isa_config.access_kinds_of(event).into_iter().map(str::to_string));
                         evrefs.collect_value_taints(
                             address,
                             events,
@@ -616,11 +2378,16 @@ where
                             &mut footprint.mem_addr_taints.1,
                         )
                     }
-                    Event::WriteMem { address, data, .. } => {
+                    Event::WriteMem { address, data, bytes, .. } => {
                         footprint.is_store = true;
-                        if event.is_exclusive() {
+                        footprint.mem_write_widths.insert(*bytes);
+                        effects.memory_writes.insert(*bytes);
+                        if is_exclusive(event) {
                             footprint.is_exclusive = true;
                         }
+                        footprint
+                            .mem_write_kinds
+                            .extend(arch.isa_config.access_kinds_of(event).into_iter().map(str::to_string));
                         evrefs.collect_value_taints(
                             address,
                             events,
@@ -634,7 +2401,9 @@ where
                             &mut footprint.write_data_taints.1,
                         );
                     }
-                    Event::AddressAnnounce { address } => {
+                    Event::BlockOp { address, bytes, .. } => {
+                        footprint.block_op_sizes.insert(*bytes);
+                        effects.block_ops.insert(*bytes);
                         evrefs.collect_value_taints(
                             address,
                             events,
@@ -642,8 +2411,17 @@ where
                             &mut footprint.mem_addr_taints.1,
                         );
                     }
-                    Event::Branch { address } => {
+                    Event::AddressAnnounce { address, info: _ } => {
+                        evrefs.collect_value_taints(
+                            address,
+                            events,
+                            &mut footprint.mem_addr_taints.0,
+                            &mut footprint.mem_addr_taints.1,
+                        );
+                    }
+                    Event::Branch { address, info: _ } => {
                         footprint.is_branch = true;
+                        effects.is_branch = true;
                         evrefs.collect_value_taints(
                             address,
                             events,
@@ -662,8 +2440,32 @@ where
                     _ => (),
                 }
             }
+
+            if !arch.isa_config.register_bitfields.is_empty() {
+                register_field_accesses(
+                    events,
+                    &arch.isa_config.register_bitfields,
+                    &arch.isa_config.ignored_registers,
+                    &mut footprint,
+                )
+            }
+
+            let (reads, writes) = path_alignment(events, keep_ifetch);
+            footprint.mem_read_alignment = footprint.mem_read_alignment.max(reads);
+            footprint.mem_write_alignment = footprint.mem_write_alignment.max(writes);
+
+            let (reads, writes) = path_regions(events, keep_ifetch, &arch.isa_config.memory_regions);
+            footprint.mem_read_regions.extend(reads);
+            footprint.mem_write_regions.extend(writes);
+
+            match &first_effects {
+                Some(first) => footprint.is_conditional |= *first != effects,
+                None => first_effects = Some(effects),
+            }
         }
 
+        footprint.exclusives = exclusive_accesses(paths, &is_exclusive, &arch.isa_config.ignored_registers);
+
         if let Some(cache_dir) = &cache {
             footprint.cache(Footprintkey { opcode: opcode.to_string() }, cache_dir);
         }
@@ -672,3 +2474,465 @@ where
 
     Ok(footprints)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use isla_lib::smt::{Config, Context, DefAttrs, ReadOpts, WriteOpts};
+
+    #[test]
+    fn register_field_read_modify_write() {
+        let reg = Name::from_u32(0);
+        let (r, u, w, c) = (Sym::from_u32(0), Sym::from_u32(1), Sym::from_u32(2), Sym::from_u32(3));
+        let info = SourceLoc::unknown();
+
+        // Set bit 25 of the register to u, as set_slice does, and
+        // branch on bit 0
+        let shift = || Box::new(bits64(25, 64));
+        let update = Exp::Bvor(
+            Box::new(Exp::Bvand(
+                Box::new(Exp::Var(r)),
+                Box::new(Exp::Bvnot(Box::new(Exp::Bvshl(Box::new(bits64(1, 64)), shift())))),
+            )),
+            Box::new(Exp::Bvshl(Box::new(Exp::ZeroExtend(63, Box::new(Exp::Var(u)))), shift())),
+        );
+        let events: Vec<Event<B64>> = vec![
+            Event::Smt(smtlib::Def::DeclareConst(r, smtlib::Ty::BitVec(64)), DefAttrs::default(), info),
+            Event::ReadReg(reg, vec![], Val::Symbolic(r), info),
+            Event::Smt(smtlib::Def::DeclareConst(u, smtlib::Ty::BitVec(1)), DefAttrs::default(), info),
+            Event::Smt(
+                smtlib::Def::DefineConst(
+                    c,
+                    Exp::Eq(Box::new(Exp::Extract(0, 0, Box::new(Exp::Var(r)))), Box::new(bits64(1, 1))),
+                ),
+                DefAttrs::default(),
+                info,
+            ),
+            Event::Fork(0, c, 0, info),
+            Event::Smt(smtlib::Def::DefineConst(w, update), DefAttrs::default(), info),
+            Event::WriteReg(reg, vec![], Val::Symbolic(w), info),
+        ];
+
+        let layout = ["M", "A", "EE"]
+            .iter()
+            .zip([0, 1, 25])
+            .map(|(name, bit)| Bitfield { name: name.to_string(), hi: bit, lo: bit })
+            .collect();
+        let mut bitfields = HashMap::new();
+        bitfields.insert(reg, RegisterBitfields { field: None, layout });
+
+        let mut footprint = Footprint::new();
+        register_field_accesses(&events, &bitfields, &HashSet::new(), &mut footprint);
+
+        assert_eq!(footprint.register_field_reads, HashSet::from([(reg, "M".to_string())]));
+        assert_eq!(footprint.register_field_writes, HashSet::from([(reg, "EE".to_string())]));
+    }
+
+    #[test]
+    fn vector_register_lanes() {
+        let reg = Name::from_u32(0);
+        let (v0, v1, w) = (Sym::from_u32(0), Sym::from_u32(1), Sym::from_u32(2));
+        let info = SourceLoc::unknown();
+
+        // Replace the lowest 32-bit lane of element 0 with the lowest
+        // lane of element 1, leaving element 1 unchanged
+        let lane = |v: Sym| Box::new(Exp::Extract(31, 0, Box::new(Exp::Var(v))));
+        let update = Exp::Concat(Box::new(Exp::Extract(127, 32, Box::new(Exp::Var(v0)))), lane(v1));
+        let events: Vec<Event<B64>> = vec![
+            Event::Smt(smtlib::Def::DeclareConst(v0, smtlib::Ty::BitVec(128)), DefAttrs::default(), info),
+            Event::Smt(smtlib::Def::DeclareConst(v1, smtlib::Ty::BitVec(128)), DefAttrs::default(), info),
+            Event::ReadReg(reg, vec![], Val::Vector(vec![Val::Symbolic(v0), Val::Symbolic(v1)]), info),
+            Event::Smt(smtlib::Def::DefineConst(w, update), DefAttrs::default(), info),
+            Event::WriteReg(reg, vec![], Val::Vector(vec![Val::Symbolic(w), Val::Symbolic(v1)]), info),
+        ];
+
+        let layout =
+            (0..4).map(|i| Bitfield { name: format!("lane{}", i), hi: (i + 1) * 32 - 1, lo: i * 32 }).collect();
+        let mut bitfields = HashMap::new();
+        bitfields.insert(reg, RegisterBitfields { field: None, layout });
+
+        let mut footprint = Footprint::new();
+        register_field_accesses(&events, &bitfields, &HashSet::new(), &mut footprint);
+
+        assert_eq!(footprint.register_field_reads, HashSet::from([(reg, "1.lane0".to_string())]));
+        assert_eq!(footprint.register_field_writes, HashSet::from([(reg, "0.lane0".to_string())]));
+    }
+
+    #[test]
+    fn flag_liveness_by_field() {
+        let mut symtab = Symtab::new();
+        let pstate = symtab.intern("zPSTATE");
+        let n = symtab.intern("zN");
+        let flags: Vec<(Name, String)> = ["N", "Z", "C", "V"].iter().map(|flag| (pstate, flag.to_string())).collect();
+
+        // A conditional branch reading N, and an instruction writing
+        // the whole of PSTATE
+        let mut footprint = Footprint::new();
+        footprint.register_reads.insert((pstate, vec![Accessor::Field(n)]));
+        let liveness = footprint.flag_liveness(&flags, &HashMap::new(), &symtab);
+        assert_eq!(liveness.used, vec!["N"]);
+        assert!(liveness.defined.is_empty());
+
+        footprint.register_writes.insert((pstate, vec![]));
+        let liveness = footprint.flag_liveness(&flags, &HashMap::new(), &symtab);
+        assert_eq!(liveness.defined, vec!["N", "Z", "C", "V"]);
+    }
+
+    #[test]
+    fn pair_dependencies_from_load() {
+        let mut symtab = Symtab::new();
+        let r1 = symtab.intern("zR1");
+        let r2 = symtab.intern("zR2");
+        let (first, second) = (B64::from_u32(1), B64::from_u32(2));
+
+        // A load into R1, followed by a load from the address in R1
+        let mut load = Footprint::new();
+        load.is_load = true;
+        load.mem_read_widths.insert(8);
+        load.register_reads.insert((r2, vec![]));
+        load.register_writes.insert((r1, vec![]));
+        load.register_writes_tainted.insert((r1, vec![]));
+        let mut dependent = Footprint::new();
+        dependent.is_load = true;
+        dependent.mem_read_widths.insert(4);
+        dependent.register_reads.insert((r1, vec![]));
+        dependent.mem_addr_taints.0.insert((r1, vec![]));
+        let footprints = HashMap::from([(first, load), (second, dependent)]);
+
+        let dependencies = pair_dependencies(first, second, &footprints, &symtab);
+        assert!(dependencies.addr && !dependencies.data && !dependencies.store_to_load);
+        assert_eq!(dependencies.registers, vec!["R1"]);
+        assert_eq!(dependencies.combined.registers_read, vec!["R1", "R2"]);
+        assert_eq!(dependencies.combined.memory_reads, vec![4, 8]);
+
+        let dependencies = pair_dependencies(second, first, &footprints, &symtab);
+        assert!(!dependencies.addr && dependencies.registers.is_empty())
+    }
+
+    #[test]
+    fn encoding_constraints_drop_operand_branches() {
+        let (op, r, c1, c2) = (Sym::from_u32(0), Sym::from_u32(1), Sym::from_u32(2), Sym::from_u32(3));
+        let info = SourceLoc::unknown();
+        let eq = |v: Sym, bits: u64| Exp::Eq(Box::new(Exp::Var(v)), Box::new(bits64(bits, 2)));
+        let fork = |c: Sym, taken: bool| {
+            let assertion = if taken { Exp::Var(c) } else { Exp::Not(Box::new(Exp::Var(c))) };
+            [Event::Fork(0, c, 0, info), Event::Smt(smtlib::Def::Assert(assertion), DefAttrs::default(), info)]
+        };
+        let path = |op_taken: bool, r_taken: Option<bool>| {
+            let mut events: Vec<Event<B64>> = vec![
+                Event::Smt(smtlib::Def::DefineConst(c1, eq(op, 1)), DefAttrs::default(), info),
+                Event::Smt(smtlib::Def::DefineConst(c2, eq(r, 0)), DefAttrs::default(), info),
+            ];
+            events.extend(fork(c1, op_taken));
+            if let Some(r_taken) = r_taken {
+                events.extend(fork(c2, r_taken))
+            }
+            events
+        };
+
+        // The decoder only accepts op = 1, and then branches on the
+        // register field to choose an operand
+        let paths = vec![
+            (path(true, Some(true)), None),
+            (path(false, None), Some("Pattern match failure".to_string())),
+            (path(true, Some(false)), None),
+        ];
+        let fields = vec![("op".to_string(), 2, op), ("r".to_string(), 2, r)];
+        let constraints = encoding_constraints(&paths, &fields, &[], &HashMap::new()).unwrap();
+
+        assert_eq!(constraints.len(), 2);
+        assert_eq!(constraints[0].outcome, EncodingOutcome::Decoded);
+        assert_eq!(constraints[0].conditions.len(), 2);
+        for condition in &constraints[0].conditions {
+            assert!(matches!(condition, Exp::Eq(lhs, _) if matches!(&**lhs, Exp::Var(FieldVar(name)) if name == "op")))
+        }
+        assert_eq!(constraints[1].outcome, EncodingOutcome::Error("Pattern match failure".to_string()));
+        assert!(matches!(constraints[1].conditions[..], [Exp::Not(_)]))
+    }
+
+    #[test]
+    fn effect_conditions_conjoin_asserts_after_fork() {
+        let mut symtab = Symtab::new();
+        let reg = symtab.intern("zR");
+        let (x, y, c) = (Sym::from_u32(0), Sym::from_u32(1), Sym::from_u32(2));
+        let info = SourceLoc::unknown();
+        let eq = |v: Sym, bits: u64| Exp::Eq(Box::new(Exp::Var(v)), Box::new(bits64(bits, 2)));
+        let assert = |exp: Exp<Sym>| Event::Smt(smtlib::Def::Assert(exp), DefAttrs::default(), info);
+        let prefix = || -> Vec<Event<B64>> {
+            vec![
+                Event::Smt(smtlib::Def::DeclareConst(x, smtlib::Ty::BitVec(2)), DefAttrs::default(), info),
+                Event::Smt(smtlib::Def::DeclareConst(y, smtlib::Ty::BitVec(2)), DefAttrs::default(), info),
+                Event::Smt(smtlib::Def::DefineConst(c, eq(x, 1)), DefAttrs::default(), info),
+                Event::Fork(0, c, 0, info),
+            ]
+        };
+
+        // The taken branch makes a second assertion before writing
+        // the register, which must also be part of its condition
+        let mut taken = prefix();
+        taken.extend([assert(Exp::Var(c)), assert(eq(y, 0)), Event::WriteReg(reg, vec![], Val::Symbolic(y), info)]);
+        let mut not_taken = prefix();
+        not_taken.push(assert(Exp::Not(Box::new(Exp::Var(c)))));
+
+        let effects = conditional_effects(&[taken, not_taken], &HashSet::new(), &HashMap::new(), &symtab);
+
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].effect, Effect::WriteReg("R".to_string()));
+        let is_eq = |exp: &Exp<ConditionVar>, var: Sym| match exp {
+            Exp::Eq(lhs, _) => matches!(&**lhs, Exp::Var(ConditionVar::Sym(v)) if *v == var),
+            _ => false,
+        };
+        match &effects[0].condition {
+            Some(Exp::And(lhs, rhs)) => assert!(is_eq(lhs, x) && is_eq(rhs, y)),
+            condition => panic!("unexpected condition {:?}", condition),
+        }
+    }
+
+    #[test]
+    fn overlap_conditions() {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let top = 0xffff_ffff_ffff_fffc;
+        let mut overlaps = |addr1, bytes1, addr2, bytes2| {
+            let overlap = overlap_condition(bits64(addr1, 64), bytes1, bits64(addr2, 64), bytes2, 64);
+            solver.check_sat_with(&overlap) == SmtResult::Sat
+        };
+        assert!(overlaps(0x1000, 8, 0x1004, 4));
+        assert!(overlaps(0x1004, 4, 0x1000, 8));
+        assert!(!overlaps(0x1000, 4, 0x1004, 4));
+        assert!(!overlaps(0x1004, 4, 0x1000, 4));
+
+        // An access ending at the top of the address space must not
+        // wrap around to zero, either overlapping the bottom or
+        // failing to overlap itself
+        assert!(overlaps(top, 4, top, 4));
+        assert!(overlaps(top, 4, top + 2, 1));
+        assert!(!overlaps(top, 4, 0, 4));
+        assert!(!overlaps(0, 4, top, 4));
+        assert!(!overlaps(top, 8, 0, 4))
+    }
+
+    #[test]
+    fn forwarding_pairs() {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let info = SourceLoc::unknown();
+        let x = solver.declare_const(smtlib::Ty::BitVec(64), info);
+        let top = 0xffff_ffff_ffff_fffc;
+        let write = |address: u64, bytes: u32| Event::WriteMem {
+            value: Sym::from_u32(1),
+            write_kind: Val::Bool(false),
+            address: Val::Bits(B64::from_u64(address)),
+            data: Val::Bits(B64::zeros(bytes * 8)),
+            bytes,
+            tag_value: None,
+            opts: WriteOpts::default(),
+            region: "default",
+            info,
+        };
+        let read = |address: Val<B64>, bytes: u32| Event::ReadMem {
+            value: Val::Bits(B64::zeros(bytes * 8)),
+            read_kind: Val::Bool(false),
+            address,
+            bytes,
+            tag_value: None,
+            opts: ReadOpts::default(),
+            region: "default",
+            info,
+        };
+        let events = vec![
+            write(0x1000, 8),
+            write(top, 4),
+            read(Val::Bits(B64::from_u64(0x1004)), 4),
+            read(Val::Bits(B64::from_u64(0x0)), 4),
+            read(Val::Bits(B64::from_u64(top)), 4),
+            read(Val::Symbolic(x), 4),
+        ];
+
+        for alias_analysis in [false, true] {
+            let pairs = store_to_load_forwarding(&events, 2, alias_analysis, &mut solver).unwrap();
+            let forwarding: Vec<&str> = pairs
+                .iter()
+                .map(|pair| match pair.forwarding {
+                    Forwarding::Always => "always",
+                    Forwarding::Never => "never",
+                    Forwarding::When(_) => "when",
+                })
+                .collect();
+            assert_eq!(
+                forwarding,
+                vec!["always", "never", "never", "when", "never", "never", "always", "when"],
+                "alias analysis {}",
+                alias_analysis
+            )
+        }
+    }
+
+    #[test]
+    fn memory_access_alignment() {
+        let (x, masked, aligned) = (Sym::from_u32(0), Sym::from_u32(1), Sym::from_u32(2));
+        let info = SourceLoc::unknown();
+        let smt = |def| Event::Smt(def, DefAttrs::default(), info);
+        let low_bits = |v: Sym| Exp::Extract(2, 0, Box::new(Exp::Var(v)));
+        let read = |address: Val<B64>| Event::ReadMem {
+            value: Val::Bits(B64::zeros(64)),
+            read_kind: Val::Bool(false),
+            address,
+            bytes: 8,
+            tag_value: None,
+            opts: ReadOpts::default(),
+            region: "default",
+            info,
+        };
+        let path = |address: Val<B64>, checked: bool| {
+            let mut events = vec![
+                smt(smtlib::Def::DeclareConst(x, smtlib::Ty::BitVec(64))),
+                smt(smtlib::Def::DefineConst(masked, Exp::Bvand(Box::new(Exp::Var(x)), Box::new(bits64(!7, 64))))),
+                smt(smtlib::Def::DefineConst(aligned, Exp::Eq(Box::new(low_bits(x)), Box::new(bits64(0, 3))))),
+            ];
+            if checked {
+                events.push(smt(smtlib::Def::Assert(Exp::Var(aligned))))
+            }
+            events.push(read(address));
+            events
+        };
+
+        let alignment = |events: &[Event<B64>]| path_alignment(events, false).0;
+        assert_eq!(alignment(&path(Val::Symbolic(masked), false)), Some(Alignment::Guaranteed));
+        assert_eq!(alignment(&path(Val::Symbolic(x), true)), Some(Alignment::Required));
+        assert_eq!(alignment(&path(Val::Symbolic(x), false)), Some(Alignment::Unaligned));
+        assert_eq!(alignment(&path(Val::Bits(B64::new(0x1008, 64)), false)), Some(Alignment::Guaranteed));
+        assert_eq!(alignment(&path(Val::Bits(B64::new(0x1004, 64)), false)), Some(Alignment::Unaligned));
+        assert_eq!(path_alignment(&path(Val::Symbolic(x), false), false).1, None);
+        // Variables from before the first cycle are not part of the path
+        assert_eq!(alignment(&path(Val::Symbolic(Sym::from_u32(3)), false)), Some(Alignment::Unaligned))
+    }
+
+    #[test]
+    fn memory_access_regions() {
+        let (x, in_stack) = (Sym::from_u32(0), Sym::from_u32(1));
+        let info = SourceLoc::unknown();
+        let smt = |def| Event::Smt(def, DefAttrs::default(), info);
+        let write = |address: Val<B64>| Event::WriteMem {
+            value: Sym::from_u32(2),
+            write_kind: Val::Bool(false),
+            address,
+            data: Val::Bits(B64::zeros(64)),
+            bytes: 8,
+            tag_value: None,
+            opts: WriteOpts::default(),
+            region: "default",
+            info,
+        };
+        let path = |address: Val<B64>, bounded: bool| {
+            let mut events = vec![
+                smt(smtlib::Def::DeclareConst(x, smtlib::Ty::BitVec(64))),
+                smt(smtlib::Def::DefineConst(
+                    in_stack,
+                    Exp::And(
+                        Box::new(Exp::Bvuge(Box::new(Exp::Var(x)), Box::new(bits64(0x8000, 64)))),
+                        Box::new(Exp::Bvult(Box::new(Exp::Var(x)), Box::new(bits64(0x8ff8, 64)))),
+                    ),
+                )),
+            ];
+            if bounded {
+                events.push(smt(smtlib::Def::Assert(Exp::Var(in_stack))))
+            }
+            events.push(write(address));
+            events
+        };
+        let regions = vec![
+            MemoryRegion { name: "code".to_string(), range: 0x0..0x1000 },
+            MemoryRegion { name: "stack".to_string(), range: 0x8000..0x9000 },
+        ];
+
+        let region = |events: &[Event<B64>]| {
+            let mut regions: Vec<String> = path_regions(events, false, &regions).1.into_iter().collect();
+            regions.sort();
+            regions
+        };
+        assert_eq!(region(&path(Val::Symbolic(x), true)), vec!["stack"]);
+        assert_eq!(region(&path(Val::Symbolic(x), false)), vec!["unknown"]);
+        assert_eq!(region(&path(Val::Bits(B64::new(0x10, 64)), false)), vec!["code"]);
+        // The access straddles the top of the code region
+        assert_eq!(region(&path(Val::Bits(B64::new(0xffc, 64)), false)), vec!["unknown"]);
+        assert!(path_regions(&path(Val::Symbolic(x), true), false, &[]).1.is_empty())
+    }
+
+    #[test]
+    fn exclusive_pairs() {
+        let (x, status) = (Sym::from_u32(0), Name::from_u32(0));
+        let info = SourceLoc::unknown();
+        let address = Val::Symbolic(x);
+        let read = |opts| Event::ReadMem {
+            value: Val::Bits(B64::zeros(64)),
+            read_kind: Val::Bool(false),
+            address: address.clone(),
+            bytes: 8,
+            tag_value: None,
+            opts,
+            region: "default",
+            info,
+        };
+        let write = |opts| Event::WriteMem {
+            value: Sym::from_u32(1),
+            write_kind: Val::Bool(false),
+            address: address.clone(),
+            data: Val::Bits(B64::zeros(64)),
+            bytes: 8,
+            tag_value: None,
+            opts,
+            region: "default",
+            info,
+        };
+        let set_status = |bits| Event::WriteReg(status, vec![], Val::Bits(B64::new(bits, 32)), info);
+        let ignored = HashSet::new();
+
+        // A store-exclusive which fails by writing 1 to the status
+        // register without writing memory
+        let paths = vec![vec![write(WriteOpts::exclusive()), set_status(0)], vec![set_status(1)]];
+        let exclusives = exclusive_accesses(&paths, &Event::is_exclusive, &ignored).unwrap();
+        assert_eq!(sorted_widths(&exclusives.write_widths), vec![8]);
+        assert!(exclusives.conditional);
+        assert_eq!(exclusives.failure_writes, vec![((status, vec![]), Some("#x00000001".to_string()))]);
+
+        // An atomic read-modify-write, which always writes
+        let paths = vec![vec![read(ReadOpts::default()), write(WriteOpts::default())]];
+        let exclusives = exclusive_accesses(&paths, &Event::is_exclusive, &ignored).unwrap();
+        assert_eq!(sorted_widths(&exclusives.atomic_widths), vec![8]);
+        assert!(exclusives.read_widths.is_empty() && !exclusives.conditional);
+
+        // A load-exclusive has no store to fail
+        let paths = vec![vec![read(ReadOpts::exclusive())]];
+        let exclusives = exclusive_accesses(&paths, &Event::is_exclusive, &ignored).unwrap();
+        assert_eq!(sorted_widths(&exclusives.read_widths), vec![8]);
+        assert!(!exclusives.conditional);
+
+        let paths = vec![vec![read(ReadOpts::default())], vec![write(WriteOpts::default())]];
+        assert!(exclusive_accesses(&paths, &Event::is_exclusive, &ignored).is_none())
+    }
+
+    #[test]
+    fn cache_key_versioned() {
+        let dir = std::env::temp_dir().join(format!("isla-footprint-cache-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = || Footprintkey { opcode: "#x91000000".to_string() };
+
+        // A footprint cached under the unversioned key by an older
+        // build must not be read back
+        let mut stale = Footprint::new();
+        stale.is_store = true;
+        stale.cache(key(), &dir);
+        std::fs::rename(dir.join(key().key()), dir.join("opcode_#x91000000")).unwrap();
+        assert!(Footprint::from_cache(key(), &dir).is_none());
+
+        let mut footprint = Footprint::new();
+        footprint.is_conditional = true;
+        footprint.cache(key(), &dir);
+        let cached = Footprint::from_cache(key(), &dir).unwrap();
+        assert!(cached.is_conditional && !cached.is_store);
+
+        std::fs::remove_dir_all(&dir).unwrap()
+    }
+}
@@ -0,0 +1,213 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Exports footprints in a line-based `.defs` format for herd and
+//! diy based tooling. Each instruction is summarised by the herd
+//! dependency classes it can be the target of, giving for every class
+//! the registers which are its sources, and the events of the
+//! instruction which are its sinks:
+//!
+//! ```text
+//! instr #xf8616800
+//!   events: R
+//!   addr: R0 R1 -> R
+//!   reads: R0 R1
+//!   writes: R2
+//!   loads: R2
+//! end
+//! ```
+//!
+//! Events are `R` (memory read), `W` (memory write), `B` (branch),
+//! and `X` (exclusive). Instructions making accesses of the kinds
+//! declared by `access_kinds` in the ISA config have a `kinds` line
+//! listing them after the event they apply to, as in `R:acquire`, and
+//! similarly instructions whose accesses are classified by the
+//! `memory_regions` of the ISA config have a `regions` line. The
+//! `addr` line is present for instructions
+//! which access memory, `data` for stores, and `ctrl` for branches.
+//! A source of `mem` means the value read from memory by the
+//! instruction itself feeds into the sink. The `loads` line lists the
+//! registers written with values read from memory, which are the
+//! sources of dependencies into later instructions. Registers are
+//! written with their fields separated by dots, as in
+//! [Footprint::summary].
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+
+use isla_lib::ir::{RegisterField, Symtab};
+use isla_lib::simplify::Taints;
+
+use super::{register_field_names, sorted_kinds, Footprint};
+
+fn sources(buf: &mut dyn Write, taints: &(Taints, bool), symtab: &Symtab) -> Result<(), Box<dyn Error>> {
+    for name in register_field_names(&taints.0, symtab) {
+        write!(buf, " {}", name)?
+    }
+    if taints.1 {
+        write!(buf, " mem")?
+    }
+    Ok(())
+}
+
+fn registers(buf: &mut dyn Write, fields: &HashSet<RegisterField>, symtab: &Symtab) -> Result<(), Box<dyn Error>> {
+    for name in register_field_names(fields, symtab) {
+        write!(buf, " {}", name)?
+    }
+    Ok(())
+}
+
+impl Footprint {
+    fn defs_events(&self) -> Vec<&'static str> {
+        let mut events = Vec::new();
+        if self.is_load {
+            events.push("R")
+        }
+        if self.is_store {
+            events.push("W")
+        }
+        if self.is_branch {
+            events.push("B")
+        }
+        if self.is_exclusive {
+            events.push("X")
+        }
+        events
+    }
+
+    /// Write the footprint of `opcode` in the `.defs` format
+    /// described in the module documentation.
+    pub fn defs<O: fmt::Display>(&self, buf: &mut dyn Write, opcode: O, symtab: &Symtab) -> Result<(), Box<dyn Error>> {
+        writeln!(buf, "instr {}", opcode)?;
+        write!(buf, "  events:")?;
+        for event in self.defs_events() {
+            write!(buf, " {}", event)?
+        }
+        writeln!(buf)?;
+        if !self.mem_read_kinds.is_empty() || !self.mem_write_kinds.is_empty() {
+            write!(buf, "  kinds:")?;
+            for kind in sorted_kinds(&self.mem_read_kinds) {
+                write!(buf, " R:{}", kind)?
+            }
+            for kind in sorted_kinds(&self.mem_write_kinds) {
+                write!(buf, " W:{}", kind)?
+            }
+            writeln!(buf)?
+        }
+        if !self.mem_read_regions.is_empty() || !self.mem_write_regions.is_empty() {
+            write!(buf, "  regions:")?;
+            for region in sorted_kinds(&self.mem_read_regions) {
+                write!(buf, " R:{}", region)?
+            }
+            for region in sorted_kinds(&self.mem_write_regions) {
+                write!(buf, " W:{}", region)?
+            }
+            writeln!(buf)?
+        }
+
+        let memory_events: Vec<&str> = self.defs_events().into_iter().filter(|ev| *ev == "R" || *ev == "W").collect();
+        if !memory_events.is_empty() {
+            write!(buf, "  addr:")?;
+            sources(buf, &self.mem_addr_taints, symtab)?;
+            writeln!(buf, " -> {}", memory_events.join(" "))?
+        }
+        if self.is_store {
+            write!(buf, "  data:")?;
+            sources(buf, &self.write_data_taints, symtab)?;
+            writeln!(buf, " -> W")?
+        }
+        if self.is_branch {
+            write!(buf, "  ctrl:")?;
+            sources(buf, &self.branch_addr_taints, symtab)?;
+            writeln!(buf, " -> B")?
+        }
+
+        write!(buf, "  reads:")?;
+        registers(buf, &self.register_reads, symtab)?;
+        write!(buf, "\n  writes:")?;
+        registers(buf, &self.register_writes, symtab)?;
+        write!(buf, "\n  loads:")?;
+        registers(buf, &self.register_writes_tainted, symtab)?;
+        writeln!(buf, "\nend")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use isla_lib::ir::Name;
+
+    #[test]
+    fn load_defs() {
+        let mut symtab = Symtab::new();
+        let r0 = symtab.intern("zR0");
+        let r1 = symtab.intern("zR1");
+        let r2 = symtab.intern("zR2");
+        let reg = |name: Name| (name, vec![]);
+
+        let mut footprint = Footprint::new();
+        footprint.is_load = true;
+        footprint.mem_addr_taints.0.extend([reg(r0), reg(r1)]);
+        footprint.register_reads.extend([reg(r0), reg(r1)]);
+        footprint.register_writes.insert(reg(r2));
+        footprint.register_writes_tainted.insert(reg(r2));
+
+        let mut buf = Vec::new();
+        footprint.defs(&mut buf, "#xf8616800", &symtab).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "instr #xf8616800\n  events: R\n  addr: R0 R1 -> R\n  reads: R0 R1\n  writes: R2\n  loads: R2\nend\n"
+        )
+    }
+
+    #[test]
+    fn access_kind_defs() {
+        let mut footprint = Footprint::new();
+        footprint.is_load = true;
+        footprint.is_store = true;
+        footprint.mem_read_kinds.insert("acquire".to_string());
+        footprint.mem_write_kinds.extend(["release".to_string(), "exclusive".to_string()]);
+
+        let mut buf = Vec::new();
+        footprint.defs(&mut buf, "#x00000000", &Symtab::new()).unwrap();
+        let defs = String::from_utf8(buf).unwrap();
+        assert!(defs.contains("\n  events: R W\n  kinds: R:acquire W:exclusive W:release\n"));
+
+        footprint.mem_read_regions.insert("stack".to_string());
+        footprint.mem_write_regions.insert("unknown".to_string());
+        let mut buf = Vec::new();
+        footprint.defs(&mut buf, "#x00000000", &Symtab::new()).unwrap();
+        let defs = String::from_utf8(buf).unwrap();
+        assert!(defs.contains("\n  kinds: R:acquire W:exclusive W:release\n  regions: R:stack W:unknown\n"))
+    }
+}
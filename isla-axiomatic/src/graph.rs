@@ -137,7 +137,7 @@ fn concrete_graph_from_candidate<'ir, 'm, B: BV>(
                 let graphvalue = GraphValue::from_vals("W", Some(address), *bytes, Some(data));
                 events.insert(event.name.clone(), GraphEvent::from_axiomatic(event, &litmus.objdump, Some(graphvalue)));
             }
-            Some(Event::ReadReg(_name, _, val)) => {
+            Some(Event::ReadReg(_name, _, val, _)) => {
                 let regnamestr = register_name_string(ev.unwrap(), symtab).unwrap();
                 if opts.debug && opts.show_regs.contains(&regnamestr) {
                     let fieldval = regname_val(event.base().unwrap(), symtab).unwrap();
@@ -148,7 +148,7 @@ fn concrete_graph_from_candidate<'ir, 'm, B: BV>(
                     );
                 };
             }
-            Some(Event::WriteReg(_name, _, val)) => {
+            Some(Event::WriteReg(_name, _, val, _)) => {
                 let regnamestr = register_name_string(ev.unwrap(), symtab).unwrap();
                 if opts.debug && opts.show_regs.contains(&regnamestr) {
                     let fieldval = regname_val(event.base().unwrap(), symtab).unwrap();
@@ -271,7 +271,7 @@ where
                     );
                 }
             }
-            Some(Event::ReadReg(_, _, val)) => {
+            Some(Event::ReadReg(_, _, val, _)) => {
                 if opts.debug && val.is_symbolic() {
                     if let Some(gevent) = g.events.remove(&event.name) {
                         let gval = gevent.value.unwrap();
@@ -284,7 +284,7 @@ where
                     }
                 }
             }
-            Some(Event::WriteReg(_, _, val)) => {
+            Some(Event::WriteReg(_, _, val, _)) => {
                 if opts.debug && val.is_symbolic() {
                     if let Some(gevent) = g.events.remove(&event.name) {
                         let gval = gevent.value.unwrap();
@@ -146,8 +146,8 @@ pub fn event_kind<B: BV>(_objdump: &Objdump, ev: &AxEvent<B>) -> GraphEventKind
                 GraphEventKind::ReadMem
             }
         }
-        Some(Event::ReadReg(_, _, _)) => GraphEventKind::ReadReg,
-        Some(Event::WriteReg(_, _, _)) => GraphEventKind::WriteReg,
+        Some(Event::ReadReg(_, _, _, _)) => GraphEventKind::ReadReg,
+        Some(Event::WriteReg(_, _, _, _)) => GraphEventKind::WriteReg,
         _ => GraphEventKind::Info,
     }
 }
@@ -132,6 +132,9 @@ struct UnassembledSection<'a> {
 
 static THREAD_PREFIX: &str = "litmus_";
 
+static SEQUENCE_SECTION: &str = "isla_sequence";
+static SEQUENCE_MARKER: &str = "isla_sequence_instr_";
+
 fn validate_section_name(name: &str) -> bool {
     for (i, c) in name.chars().enumerate() {
         if i == 0 && !c.is_ascii_alphabetic() {
@@ -278,6 +281,38 @@ fn requires_assembly(threads: &[(ThreadName, ThreadBody)]) -> bool {
     false
 }
 
+/// Run the assembler provided in the `ISAConfig<B>` on some input,
+/// returning the object file it created.
+fn run_assembler<B>(input: &str, isa: &ISAConfig<B>) -> Result<tmpfile::TmpFile, String> {
+    let objfile = tmpfile::TmpFile::new();
+
+    let mut assembler = SandboxedCommand::from_tool(&isa.assembler)
+        .arg("-o")
+        .arg(objfile.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| {
+            format!("Failed to spawn assembler {}. Got error: {}", &isa.assembler.executable.display(), err)
+        })?;
+
+    {
+        let stdin = assembler.stdin.as_mut().ok_or_else(|| "Failed to open stdin for assembler".to_string())?;
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|_| format!("Failed to write to assembler input file {}", objfile.path().display()))?
+    }
+
+    let output = assembler.wait_with_output().map_err(|_| "Failed to read stdout from assembler".to_string())?;
+
+    if !output.status.success() {
+        return Err(format!("Assembler failed with: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    Ok(objfile)
+}
+
 /// This function takes some assembly code for each thread, which
 /// should ideally be formatted as instructions separated by a newline
 /// and a tab (`\n\t`), and invokes the assembler provided in the
@@ -298,49 +333,26 @@ fn assemble<B>(
         return Ok((HashMap::new(), Vec::new(), Objdump::empty()));
     }
 
-    let objfile = tmpfile::TmpFile::new();
-
-    let mut assembler = SandboxedCommand::from_tool(&isa.assembler)
-        .arg("-o")
-        .arg(objfile.path())
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|err| {
-            format!("Failed to spawn assembler {}. Got error: {}", &isa.assembler.executable.display(), err)
-        })?;
-
-    // Write each thread to the assembler's standard input, in a section called `THREAD_PREFIXN` for each thread `N`
-    {
-        let stdin = assembler.stdin.as_mut().ok_or_else(|| "Failed to open stdin for assembler".to_string())?;
-        for (thread_name, body) in threads.iter() {
-            if let ThreadBody::Code(code) = body {
-                validate_code(code)?;
-                stdin
-                    .write_all(format!("\t.section {}{}, \"xa\"\n", THREAD_PREFIX, thread_name).as_bytes())
-                    .and_then(|_| stdin.write_all(code.as_bytes()))
-                    .map_err(|_| format!("Failed to write to assembler input file {}", objfile.path().display()))?
-            }
-        }
-        for section in sections {
-            validate_code(section.code)?;
-            if !validate_section_name(section.name) {
-                return Err(format!("Section name {} is invalid", section.name));
-            };
-            stdin
-                .write_all(format!("\t.section {}, \"xa\"\n", section.name).as_bytes())
-                .and_then(|_| stdin.write_all(section.code.as_bytes()))
-                .map_err(|_| format!("Failed to write to assembler input file {}", objfile.path().display()))?
+    // Give the assembler each thread in a section called `THREAD_PREFIXN` for each thread `N`
+    let mut input = String::new();
+    for (thread_name, body) in threads.iter() {
+        if let ThreadBody::Code(code) = body {
+            validate_code(code)?;
+            input.push_str(&format!("\t.section {}{}, \"xa\"\n", THREAD_PREFIX, thread_name));
+            input.push_str(code)
         }
     }
-
-    let output = assembler.wait_with_output().map_err(|_| "Failed to read stdout from assembler".to_string())?;
-
-    if !output.status.success() {
-        return Err(format!("Assembler failed with: {}", String::from_utf8_lossy(&output.stderr)));
+    for section in sections {
+        validate_code(section.code)?;
+        if !validate_section_name(section.name) {
+            return Err(format!("Section name {} is invalid", section.name));
+        };
+        input.push_str(&format!("\t.section {}, \"xa\"\n", section.name));
+        input.push_str(section.code)
     }
 
+    let objfile = run_assembler(&input, isa)?;
+
     let (mut objfile, objdump, names) = if reloc {
         let objfile_reloc = tmpfile::TmpFile::new();
         let linker_script = tmpfile::TmpFile::new();
@@ -498,6 +510,94 @@ pub fn assemble_instruction<B>(instr: &str, isa: &ISAConfig<B>) -> Result<Vec<u8
     }
 }
 
+/// A short sequence of instructions assembled together, so branches
+/// within the sequence can target its labels rather than raw offsets.
+#[derive(Clone, Debug, Default)]
+pub struct AssembledSequence {
+    /// The bytes of each instruction in the sequence, in order
+    pub opcodes: Vec<Vec<u8>>,
+    /// The offset in bytes of each label from the start of the sequence
+    pub labels: HashMap<String, u64>,
+}
+
+/// Assemble a sequence of instructions separated by newlines or
+/// semicolons, each of which may be preceded by labels such as
+/// `loop:`. Branches may only refer to labels within the sequence.
+pub fn assemble_sequence<B>(code: &str, isa: &ISAConfig<B>) -> Result<AssembledSequence, String> {
+    use goblin::Object;
+    use regex::Regex;
+
+    validate_code(code)?;
+    let label_re = Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_]*)\s*:").unwrap();
+
+    // Each instruction is preceded by a marker symbol, so the
+    // instruction boundaries can be found in the symbol table
+    let mut input = format!("\t.section {}, \"xa\"\n", SEQUENCE_SECTION);
+    let mut label_positions: Vec<(String, usize)> = Vec::new();
+    let mut instrs = 0;
+    for mut statement in code.split(['\n', ';']) {
+        while let Some(caps) = label_re.captures(statement) {
+            label_positions.push((caps[1].to_string(), instrs));
+            input.push_str(&format!("{}:\n", &caps[1]));
+            statement = &statement[caps.get(0).unwrap().end()..]
+        }
+        if !statement.trim().is_empty() {
+            input.push_str(&format!("{}{}:\n\t{}\n", SEQUENCE_MARKER, instrs, statement.trim()));
+            instrs += 1
+        }
+    }
+
+    let buffer =
+        run_assembler(&input, isa)?.read_to_end().map_err(|_| "Failed to read generated ELF file".to_string())?;
+
+    let elf = match Object::parse(&buffer) {
+        Ok(Object::Elf(elf)) => elf,
+        Ok(_) => return Err("Generated object was not an ELF file".to_string()),
+        Err(err) => return Err(format!("Failed to parse ELF file: {}", err)),
+    };
+
+    let mut code_section = None;
+    for (i, section) in elf.section_headers.iter().enumerate() {
+        match elf.shdr_strtab.get_at(section.sh_name) {
+            Some(name) if name == SEQUENCE_SECTION => code_section = Some((i, section)),
+            Some(name) if name.ends_with(SEQUENCE_SECTION) && name.starts_with(".rel") && section.sh_size > 0 => {
+                return Err(format!("Instruction sequence {} refers to symbols outside the sequence", code))
+            }
+            _ => (),
+        }
+    }
+    let Some((index, section)) = code_section else {
+        return Err(format!("Failed to assemble instruction sequence {}", code));
+    };
+    let offset = section.sh_offset as usize;
+    let bytes = &buffer[offset..(offset + section.sh_size as usize)];
+
+    // The end of each instruction is the start of the next, or the
+    // end of the section for the last one
+    let mut boundaries = vec![section.sh_size; instrs + 1];
+    for sym in elf.syms.iter() {
+        if sym.st_shndx != index {
+            continue;
+        }
+        if let Some(n) = elf.strtab.get_at(sym.st_name).and_then(|name| name.strip_prefix(SEQUENCE_MARKER)) {
+            if let Ok(n) = n.parse::<usize>() {
+                if n < instrs {
+                    boundaries[n] = sym.st_value
+                }
+            }
+        }
+    }
+
+    let opcodes = boundaries
+        .windows(2)
+        .filter(|bounds| bounds[0] < bounds[1])
+        .map(|bounds| bytes[bounds[0] as usize..bounds[1] as usize].to_vec())
+        .collect();
+    let labels = label_positions.into_iter().map(|(label, n)| (label, boundaries[n])).collect();
+
+    Ok(AssembledSequence { opcodes, labels })
+}
+
 fn parse_constrained_region<B: BV>(
     toml_region: &Value,
     symbolic_addrs: &HashMap<String, u64>,
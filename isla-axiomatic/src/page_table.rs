@@ -904,6 +904,7 @@ impl<B: BV> CustomRegion<B> for ImmutablePageTables<B> {
         bytes: u32,
         solver: &mut Solver<B>,
         _tag: bool,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         log!(log::MEMORY, &format!("Page table read: 0x{:x}", addr));
 
@@ -930,6 +931,7 @@ impl<B: BV> CustomRegion<B> for ImmutablePageTables<B> {
             tag_value: None,
             opts: ReadOpts::default(),
             region: self.region,
+            info,
         });
 
         log!(log::MEMORY, &format!("Page table descriptor: 0x{:x} -> {:?}", addr, desc));
@@ -944,11 +946,12 @@ impl<B: BV> CustomRegion<B> for ImmutablePageTables<B> {
         write_desc: Val<B>,
         solver: &mut Solver<B>,
         tag: Option<Val<B>>,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         log!(log::MEMORY, &format!("Page table write: 0x{:x} <- {:?}", addr, write_desc));
 
         let table_addr = addr & !0xFFF;
-        let write_len_bits = length_bits(&write_desc, solver, SourceLoc::unknown())?;
+        let write_len_bits = length_bits(&write_desc, solver, info)?;
 
         // Ensure page table writes are also 8 bytes and aligned
         if (addr & 0b111) != 0 || write_len_bits != 64 || table_addr < self.base_addr {
@@ -975,7 +978,7 @@ impl<B: BV> CustomRegion<B> for ImmutablePageTables<B> {
         };
 
         if skip_sat_check || solver.check_sat_with(&query) == SmtResult::Sat {
-            let value = solver.declare_const(Ty::Bool, SourceLoc::unknown());
+            let value = solver.declare_const(Ty::Bool, info);
             solver.add_event(Event::WriteMem {
                 value,
                 write_kind,
@@ -985,6 +988,7 @@ impl<B: BV> CustomRegion<B> for ImmutablePageTables<B> {
                 tag_value: tag,
                 opts: WriteOpts::default(),
                 region: self.region,
+                info,
             });
             Ok(Val::Symbolic(value))
         } else {
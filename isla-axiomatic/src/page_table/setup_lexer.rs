@@ -99,67 +99,53 @@ impl<'input> fmt::Display for Tok<'input> {
     }
 }
 
-pub struct Keyword {
-    word: &'static str,
-    token: Tok<'static>,
-    len: usize,
-    is_symbol: bool,
-}
-
-impl Keyword {
-    pub fn new(kw: &'static str, tok: Tok<'static>) -> Self {
-        let c = kw.chars().next().expect("lexer contains empty keyword");
-        Keyword { word: kw, token: tok, len: kw.len(), is_symbol: !c.is_ascii_alphabetic() }
-    }
-}
-
 lazy_static! {
-    static ref KEYWORDS: Vec<Keyword> = {
+    static ref KEYWORDS: KeywordTable<Tok<'static>> = {
         use Tok::*;
-        vec![
-            Keyword::new("|->", MapsTo),
-            Keyword::new("?->", MaybeMapsTo),
-            Keyword::new("~", Not),
-            Keyword::new("&&", BooleanAnd),
-            Keyword::new("&", BitAnd),
-            Keyword::new("||", BooleanOr),
-            Keyword::new("|", BitOr),
-            Keyword::new("(", Lparen),
-            Keyword::new(")", Rparen),
-            Keyword::new("[", Lsquare),
-            Keyword::new("]", Rsquare),
-            Keyword::new("{", Lbrace),
-            Keyword::new("}", Rbrace),
-            Keyword::new(":", Colon),
-            Keyword::new(";", Semi),
-            Keyword::new("!=", NotEq),
-            Keyword::new("==", EqEq),
-            Keyword::new("=", Eq),
-            Keyword::new("*", Star),
-            Keyword::new("^", Caret),
-            Keyword::new(",", Comma),
-            Keyword::new("..", DotDot),
-            Keyword::new(".", Dot),
-            Keyword::new("assert", Assert),
-            Keyword::new("as", As),
-            Keyword::new("at", At),
-            Keyword::new("and", And),
-            Keyword::new("aligned", Aligned),
-            Keyword::new("level", Level),
-            Keyword::new("virtual", Virtual),
-            Keyword::new("intermediate", Intermediate),
-            Keyword::new("identity", Identity),
-            Keyword::new("physical", Physical),
-            Keyword::new("with", With),
-            Keyword::new("code", Code),
-            Keyword::new("default", Default),
-            Keyword::new("true", True),
-            Keyword::new("false", False),
-            Keyword::new("let", Let),
-            Keyword::new("s1table", S1Table),
-            Keyword::new("s2table", S2Table),
-            Keyword::new("option", Option),
-        ]
+        KeywordTable::new(vec![
+            ("|->", MapsTo),
+            ("?->", MaybeMapsTo),
+            ("~", Not),
+            ("&&", BooleanAnd),
+            ("&", BitAnd),
+            ("||", BooleanOr),
+            ("|", BitOr),
+            ("(", Lparen),
+            (")", Rparen),
+            ("[", Lsquare),
+            ("]", Rsquare),
+            ("{", Lbrace),
+            ("}", Rbrace),
+            (":", Colon),
+            (";", Semi),
+            ("!=", NotEq),
+            ("==", EqEq),
+            ("=", Eq),
+            ("*", Star),
+            ("^", Caret),
+            (",", Comma),
+            ("..", DotDot),
+            (".", Dot),
+            ("assert", Assert),
+            ("as", As),
+            ("at", At),
+            ("and", And),
+            ("aligned", Aligned),
+            ("level", Level),
+            ("virtual", Virtual),
+            ("intermediate", Intermediate),
+            ("identity", Identity),
+            ("physical", Physical),
+            ("with", With),
+            ("code", Code),
+            ("default", Default),
+            ("true", True),
+            ("false", False),
+            ("let", Let),
+            ("s1table", S1Table),
+            ("s2table", S2Table),
+            ("option", Option),
+        ])
     };
 }
 
@@ -171,20 +157,9 @@ impl<'input> Iterator for SetupLexer<'input> {
     fn next(&mut self) -> Option<Self::Item> {
         use Tok::*;
         self.lexer.consume_whitespace()?;
-        let start_pos = self.lexer.pos;
-
-        for k in KEYWORDS.iter() {
-            if self.lexer.buf.starts_with(k.word) {
-                match self.lexer.buf.chars().nth(k.len) {
-                    // A keyword cannot be immediately followed by any valid identifier characters
-                    Some(c) if !k.is_symbol && (c.is_ascii_alphanumeric() || c == '_') => (),
-                    _ => {
-                        self.lexer.pos += k.len;
-                        self.lexer.buf = &self.lexer.buf[k.len..];
-                        return Some(Ok((start_pos, k.token.clone(), self.lexer.pos)));
-                    }
-                }
-            }
+
+        if let Some(keyword) = self.lexer.consume_keyword(&KEYWORDS) {
+            return Some(Ok(keyword));
         }
 
         match self.lexer.consume_regex(&ID_REGEX) {
@@ -34,6 +34,7 @@ use std::error::Error;
 use std::fmt;
 use std::fs::File;
 use std::io::Write;
+use std::ops::Range;
 use std::path::Path;
 use std::process::Command;
 use std::sync::Arc;
@@ -43,9 +44,9 @@ use std::time::Instant;
 use isla_lib::bitvector::BV;
 use isla_lib::error::{ExecError, IslaError};
 use isla_lib::executor;
-use isla_lib::executor::{LocalFrame, TaskState, TraceError};
+use isla_lib::executor::{LocalFrame, Progress, TaskState, TraceError};
 use isla_lib::ir::*;
-use isla_lib::memory::Memory;
+use isla_lib::memory::{Address, Memory};
 use isla_lib::simplify;
 use isla_lib::simplify::{write_events_with_opts, WriteOpts};
 use isla_lib::smt::smtlib;
@@ -155,6 +156,20 @@ pub struct LitmusRunOpts {
     /// sequence of events in a translation if a single read in that
     /// translation does not read from the initial state
     pub remove_uninteresting_translates: Option<bool>,
+    /// Log memory accesses which may touch these address ranges, as
+    /// for isla-footprint --watch
+    pub watchpoints: Vec<Range<Address>>,
+    /// Stop paths which jump back to a loop head more than this many
+    /// times
+    pub loop_bound: Option<usize>,
+    /// Loop bounds for specific functions, which take priority over
+    /// `loop_bound`
+    pub function_loop_bounds: HashMap<Name, usize>,
+    /// Merge branches which only compute local variables rather than
+    /// forking
+    pub merge_paths: bool,
+    /// How to report the progress of each thread's execution
+    pub progress: Option<Progress>,
 }
 
 pub struct LitmusRunInfo {
@@ -195,6 +210,7 @@ where
     let isa_config = arch.isa_config;
     let shared_state = arch.shared_state;
     let mut memory = Memory::new();
+    memory.set_endianness(isa_config.data_endianness, isa_config.instruction_endianness);
 
     for region in &litmus.self_modify_regions {
         memory.add_region(region.clone())
@@ -276,7 +292,13 @@ where
                 .iter()
                 .map(|(loc, exp)| (loc.clone(), reset_eval(exp, all_addrs, &litmus.objdump)))
                 .collect();
-            let task_state = TaskState::new().with_reset_registers(reset).with_zero_announce_exit(isa_config.zero_announce_exit);
+            let task_state = TaskState::new()
+                .with_reset_registers(reset)
+                .with_zero_announce_exit(isa_config.zero_announce_exit)
+                .with_watchpoints(opts.watchpoints.clone())
+                .with_loop_bounds(opts.loop_bound, opts.function_loop_bounds.clone())
+                .with_path_merging(opts.merge_paths)
+                .with_progress(opts.progress);
             if let Some(limit) = opts.pc_limit {
                 task_state.with_pc_limit(isa_config.pc, limit)
             } else {
@@ -404,7 +426,7 @@ where
                 || ev.is_branch()
         })?;
 
-    let footprints = footprint_analysis(opts.num_threads, &thread_buckets, farch, Some(cache.as_ref()))
+    let footprints = footprint_analysis(opts.num_threads, &thread_buckets, farch, Some(cache.as_ref()), false, true)
         .map_err(LitmusRunError::Footprint)?;
 
     let candidates = Candidates::new(&thread_buckets);
@@ -58,22 +58,10 @@ impl<'input> fmt::Display for Tok<'input> {
     }
 }
 
-pub struct Keyword {
-    word: &'static str,
-    token: Tok<'static>,
-    len: usize,
-}
-
-impl Keyword {
-    pub fn new(kw: &'static str, tok: Tok<'static>) -> Self {
-        Keyword { word: kw, token: tok, len: kw.len() }
-    }
-}
-
 lazy_static! {
-    static ref KEYWORDS: Vec<Keyword> = {
+    static ref KEYWORDS: KeywordTable<Tok<'static>> = {
         use Tok::*;
-        vec![Keyword::new("(", Lparen), Keyword::new(")", Rparen)]
+        KeywordTable::new(vec![("(", Lparen), (")", Rparen)])
     };
     pub static ref ATOM_REGEX: Regex = Regex::new(r"^[a-zA-Z_=><.!/-][0-9a-zA-Z_=><.!/-]*").unwrap();
     pub static ref BAR_ATOM_REGEX: Regex = Regex::new(r"^\|[^|]+\|").unwrap();
@@ -87,14 +75,9 @@ impl<'input> Iterator for SexpLexer<'input> {
     fn next(&mut self) -> Option<Self::Item> {
         use Tok::*;
         self.lexer.consume_whitespace()?;
-        let start_pos = self.lexer.pos;
-
-        for k in KEYWORDS.iter() {
-            if self.lexer.buf.starts_with(k.word) {
-                self.lexer.pos += k.len;
-                self.lexer.buf = &self.lexer.buf[k.len..];
-                return Some(Ok((start_pos, k.token.clone(), self.lexer.pos)));
-            }
+
+        if let Some(keyword) = self.lexer.consume_keyword(&KEYWORDS) {
+            return Some(Ok(keyword));
         }
 
         match self.lexer.consume_regex(&ATOM_REGEX) {
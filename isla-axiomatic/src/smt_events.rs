@@ -36,7 +36,7 @@ use isla_lib::bitvector::BV;
 use isla_lib::config::ISAConfig;
 use isla_lib::ir::{Name, SharedState, Val};
 use isla_lib::log;
-use isla_lib::memory::Memory;
+use isla_lib::memory::{Endianness, Memory};
 use isla_lib::smt::{Event, Sym};
 
 use isla_cat::smt::Sexp;
@@ -113,8 +113,12 @@ fn overlap_location<B: BV>(ev1: &AxEvent<B>, ev2: &AxEvent<B>) -> Sexp {
     sexp
 }
 
+/// Relate the value of a read to the value of a write it reads
+/// from. When they have different sizes, the read sees the bytes at
+/// the start of the write, which are its least significant bytes in
+/// little-endian memory, and its most significant otherwise.
 #[allow(clippy::comparison_chain)]
-fn read_write_pair<B: BV>(ev1: &AxEvent<B>, ev2: &AxEvent<B>) -> Sexp {
+fn read_write_pair<B: BV>(ev1: &AxEvent<B>, ev2: &AxEvent<B>, big_endian: bool) -> Sexp {
     use Sexp::*;
     match (ev2.read_value(), ev1.write_data()) {
         (Some((Val::Symbolic(sym1), _rbytes)), Some((Val::Symbolic(sym2), _wbytes))) => {
@@ -127,8 +131,12 @@ fn read_write_pair<B: BV>(ev1: &AxEvent<B>, ev2: &AxEvent<B>) -> Sexp {
         (Some((Val::Bits(bv), rbytes)), Some((Val::Symbolic(sym), wbytes))) => {
             if rbytes == wbytes {
                 Literal(format!("(= {} v{})", bv, sym))
+            } else if rbytes > wbytes && big_endian {
+                Literal(format!("(= {} (concat v{} {}))", bv, sym, B::zeros(rbytes * 8 - wbytes * 8)))
             } else if rbytes > wbytes {
                 Literal(format!("(= {} ((_ zero_extend {}) v{}))", bv, rbytes * 8 - wbytes * 8, sym))
+            } else if big_endian {
+                Literal(format!("(= {} ((_ extract {} {}) v{}))", bv, wbytes * 8 - 1, wbytes * 8 - rbytes * 8, sym))
             } else {
                 Literal(format!("(= {} ((_ extract {} 0) v{}))", bv, rbytes * 8 - 1, sym))
             }
@@ -136,8 +144,12 @@ fn read_write_pair<B: BV>(ev1: &AxEvent<B>, ev2: &AxEvent<B>) -> Sexp {
         (Some((Val::Symbolic(sym), rbytes)), Some((Val::Bits(bv), wbytes))) => {
             if rbytes == wbytes {
                 Literal(format!("(= v{} {})", sym, bv))
+            } else if rbytes > wbytes && big_endian {
+                Literal(format!("(= v{} {})", sym, bv.append(B::zeros(rbytes * 8 - wbytes * 8)).unwrap()))
             } else if rbytes > wbytes {
                 Literal(format!("(= v{} {})", sym, bv.zero_extend(rbytes * 8)))
+            } else if big_endian {
+                Literal(format!("(= v{} {})", sym, bv.extract(wbytes * 8 - 1, wbytes * 8 - rbytes * 8).unwrap()))
             } else {
                 Literal(format!("(= v{} {})", sym, bv.extract(rbytes * 8 - 1, 0).unwrap()))
             }
@@ -836,6 +848,12 @@ pub fn smt_of_candidate<B: BV>(
     smt_set(|ev| is_read(ev) || is_write(ev), events).write_set(output, "M")?;
     smt_set(is_ifetch, events).write_set(output, "IF")?;
 
+    // Each access kind declared in the ISA config is the set of
+    // memory events of that kind
+    for kind in &isa_config.access_kinds {
+        smt_set(|ev| ev.base.iter().any(|base| kind.matches(base)), events).write_set(output, &kind.name)?
+    }
+
     for (set, kinds) in isa_config.register_event_sets.iter() {
         smt_set(|ev| kinds.iter().any(|k| k.is_read() && ev.has_read_reg_of(k.name())), events)
             .write_set(output, &format!("read_{}", set))?;
@@ -909,7 +927,13 @@ pub fn smt_of_candidate<B: BV>(
     smt_condition_rel(disjoint, events, same_location).write_rel(output, "loc")?;
     smt_condition_rel(disjoint, events, overlap_location).write_rel(output, "overlap-loc")?;
     smt_condition_rel(po, events, same_location).write_rel(output, "po-loc")?;
-    smt_condition_rel(univ, events, read_write_pair).write_rel(output, "rw-pair")?;
+    // BE-32 accesses use physical addresses, at which words are little-endian
+    let rw_pair: fn(&AxEvent<B>, &AxEvent<B>) -> Sexp = if memory.data_endianness() == Endianness::Big {
+        |ev1, ev2| read_write_pair(ev1, ev2, true)
+    } else {
+        |ev1, ev2| read_write_pair(ev1, ev2, false)
+    };
+    smt_condition_rel(univ, events, rw_pair).write_rel(output, "rw-pair")?;
     smt_dep_rel2(addr, events, &exec.thread_opcodes, footprints).write_rel(output, "addr")?;
     smt_dep_rel2(data, events, &exec.thread_opcodes, footprints).write_rel(output, "data")?;
     smt_dep_rel(ctrl, events, &exec.thread_opcodes, footprints).write_rel(output, "ctrl")?;
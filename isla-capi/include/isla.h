@@ -0,0 +1,56 @@
+/* C interface to the isla symbolic execution engine, implemented by
+ * the isla-capi crate. All functions returning int return one of the
+ * ISLA_* status codes below, and on error a description is available
+ * from isla_last_error(). */
+
+#ifndef ISLA_H
+#define ISLA_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define ISLA_OK 0
+#define ISLA_DONE 1
+#define ISLA_ERR_NULL -1
+#define ISLA_ERR_IO -2
+#define ISLA_ERR_PARSE -3
+#define ISLA_ERR_CONFIG -4
+#define ISLA_ERR_EXECUTION -5
+#define ISLA_ERR_PANIC -6
+
+typedef struct IslaArch isla_arch;
+typedef struct IslaTraceIter isla_trace_iter;
+
+/* The last error on the calling thread, or NULL. Owned by the library. */
+const char *isla_last_error(void);
+
+/* Load a .ir or .irx architecture with an ISA configuration file. */
+int isla_arch_load(const char *ir_path, const char *config_path, size_t num_threads, isla_arch **arch);
+void isla_arch_free(isla_arch *arch);
+
+/* The footprint of an opcode as JSON, freed with isla_string_free. */
+int isla_footprint(const isla_arch *arch, uint32_t opcode, char **json);
+void isla_string_free(char *s);
+
+/* Iterate over the events of every path through an opcode. Event
+ * strings are owned by the iterator. isla_trace_next returns
+ * ISLA_DONE when there are no more events. */
+int isla_trace(const isla_arch *arch, uint32_t opcode, isla_trace_iter **iter);
+size_t isla_trace_paths(const isla_trace_iter *iter);
+bool isla_trace_path_result(const isla_trace_iter *iter, size_t path);
+int isla_trace_next(isla_trace_iter *iter, size_t *path, const char **event);
+void isla_trace_free(isla_trace_iter *iter);
+
+/* Release solver memory once no more queries will be made. */
+void isla_finalize(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
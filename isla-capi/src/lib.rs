@@ -0,0 +1,525 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A C interface to isla, for tools that want to compute
+//! instruction footprints and traces without linking against Rust
+//! code directly. All objects are passed across the interface as
+//! opaque handles, and every fallible function returns one of the
+//! `ISLA_*` status codes, with a description of the most recent
+//! error on the calling thread available from `isla_last_error`.
+//!
+//! The corresponding declarations are in `include/isla.h`. The same
+//! operations are available to Python with the `python` feature, and
+//! to Rust through [IslaArch].
+
+use crossbeam::queue::SegQueue;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::fs;
+use std::mem::ManuallyDrop;
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+use isla_axiomatic::footprint_analysis::footprint_analysis;
+use isla_lib::bitvector::{b64::B64, BV};
+use isla_lib::config::ISAConfig;
+use isla_lib::executor;
+use isla_lib::executor::{LocalFrame, TaskState};
+use isla_lib::init::{initialize_architecture, InitArchWithConfig, Initialized};
+use isla_lib::ir::serialize::{read_serialized_architecture, DeserializedArchitecture};
+use isla_lib::ir::*;
+use isla_lib::ir_lexer::expand_includes;
+use isla_lib::ir_parse::parse_ir;
+use isla_lib::simplify::{write_events_with_opts, WriteOpts};
+use isla_lib::smt::Event;
+
+pub const ISLA_OK: c_int = 0;
+/// Returned by `isla_trace_next` when there are no more events
+pub const ISLA_DONE: c_int = 1;
+pub const ISLA_ERR_NULL: c_int = -1;
+pub const ISLA_ERR_IO: c_int = -2;
+pub const ISLA_ERR_PARSE: c_int = -3;
+pub const ISLA_ERR_CONFIG: c_int = -4;
+pub const ISLA_ERR_EXECUTION: c_int = -5;
+pub const ISLA_ERR_PANIC: c_int = -6;
+
+#[cfg(feature = "python")]
+mod python;
+
+/// An error, with the `ISLA_ERR_*` code used to report it to C
+#[derive(Debug)]
+pub struct Error {
+    pub code: c_int,
+    pub message: String,
+}
+
+impl Error {
+    fn new<S: Into<String>>(code: c_int, message: S) -> Self {
+        Error { code, message: message.into() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message))
+}
+
+/// Run the body of an exported function, converting errors and
+/// panics into status codes so they never unwind into C.
+fn guard<F: FnOnce() -> Result<c_int, Error>>(f: F) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(code)) => code,
+        Ok(Err(err)) => {
+            set_last_error(&err.message);
+            err.code
+        }
+        Err(_) => {
+            set_last_error("isla panicked");
+            ISLA_ERR_PANIC
+        }
+    }
+}
+
+unsafe fn path_arg<'a>(path: *const c_char) -> Result<&'a Path, Error> {
+    if path.is_null() {
+        return Err(Error::new(ISLA_ERR_NULL, "Null path"));
+    }
+    let path = CStr::from_ptr(path).to_str().map_err(|_| Error::new(ISLA_ERR_IO, "Path is not valid UTF-8"))?;
+    Ok(Path::new(path))
+}
+
+/// The storage the symbol table of a loaded architecture refers
+/// to. It is leaked while the architecture is live, and reclaimed
+/// when the architecture is freed.
+#[derive(Clone, Copy)]
+enum Source {
+    Text(*mut str),
+    Tables(*mut [String], *mut [String]),
+}
+
+/// A path through an instruction, with the value returned by the
+/// footprint function and the events in execution order
+pub struct TracedPath {
+    pub result: bool,
+    pub events: Vec<Event<B64>>,
+}
+
+/// An initialized architecture, ready for symbolic execution
+pub struct IslaArch {
+    num_threads: usize,
+    isa_config: ISAConfig<B64>,
+    iarch: ManuallyDrop<Initialized<'static, B64>>,
+    defs: *mut [Def<Name, B64>],
+    source: Source,
+}
+
+impl IslaArch {
+    /// Load and initialize an architecture from a Sail IR file (`.ir`
+    /// or serialized `.irx`) and an ISA configuration file.
+    pub fn load(ir_path: &Path, config_path: &Path, num_threads: usize) -> Result<Self, Error> {
+        let (source, symtab, defs) = if ir_path.extension().and_then(|ext| ext.to_str()) == Some("irx") {
+            let DeserializedArchitecture { ir, strings, files } = read_serialized_architecture::<_, B64>(ir_path)
+                .map_err(|e| Error::new(ISLA_ERR_PARSE, e.to_string()))?;
+            let strings: &'static [String] = Box::leak(strings.into_boxed_slice());
+            let files: &'static [String] = Box::leak(files.into_boxed_slice());
+            let symtab = Symtab::from_raw_table(strings, files);
+            (Source::Tables(strings as *const _ as *mut _, files as *const _ as *mut _), symtab, ir)
+        } else {
+            let contents = fs::read_to_string(ir_path)
+                .map_err(|e| Error::new(ISLA_ERR_IO, format!("{}: {}", ir_path.display(), e)))?;
+            let contents = expand_includes(&contents, ir_path).map_err(|e| Error::new(ISLA_ERR_IO, e.to_string()))?;
+            let contents: &'static str = Box::leak(contents.into_boxed_str());
+            let source = Source::Text(contents as *const str as *mut str);
+            let mut symtab = Symtab::new();
+            match parse_ir(contents, &mut symtab) {
+                Ok(defs) => (source, symtab, defs),
+                Err(errors) => {
+                    let message = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n");
+                    drop(symtab);
+                    unsafe { free_source(source) };
+                    return Err(Error::new(ISLA_ERR_PARSE, message));
+                }
+            }
+        };
+
+        let type_info = IRTypeInfo::new(&defs);
+        let config = fs::read_to_string(config_path)
+            .map_err(|e| Error::new(ISLA_ERR_IO, format!("{}: {}", config_path.display(), e)));
+        let isa_config = config.and_then(|config| {
+            ISAConfig::parse(&config, None, &symtab, &type_info).map_err(|e| Error::new(ISLA_ERR_CONFIG, e))
+        });
+        let isa_config = match isa_config {
+            Ok(isa_config) => isa_config,
+            Err(err) => {
+                drop(symtab);
+                unsafe { free_source(source) };
+                return Err(err);
+            }
+        };
+
+        let defs: &'static mut [Def<Name, B64>] = Box::leak(defs.into_boxed_slice());
+        let defs_ptr = defs as *mut _;
+        let iarch = initialize_architecture(defs, symtab, type_info, &isa_config, AssertionMode::Optimistic, true);
+
+        Ok(IslaArch {
+            num_threads: num_threads.max(1),
+            isa_config,
+            iarch: ManuallyDrop::new(iarch),
+            defs: defs_ptr,
+            source,
+        })
+    }
+
+    fn arch_with_config(&self) -> InitArchWithConfig<'_, B64> {
+        InitArchWithConfig::from_initialized(&self.iarch, &self.isa_config)
+    }
+
+    pub fn shared_state(&self) -> &SharedState<'static, B64> {
+        &self.iarch.shared_state
+    }
+
+    /// The footprint of an opcode, as a JSON object in the same
+    /// format as `isla-footprint --footprint-format=json`
+    pub fn footprint(&self, opcode: u32) -> Result<serde_json::Value, Error> {
+        let opcode = B64::from_u32(opcode);
+        let paths = vec![vec![Event::Instr(Val::Bits(opcode))]];
+        let mut footprints =
+            footprint_analysis(self.num_threads, &[paths], &self.arch_with_config(), None, false, true)
+                .map_err(|e| Error::new(ISLA_ERR_EXECUTION, format!("{:?}", e)))?;
+        let footprint = footprints
+            .remove(&opcode)
+            .ok_or_else(|| Error::new(ISLA_ERR_EXECUTION, "No footprint computed for opcode"))?;
+        Ok(serde_json::json!({
+            "opcode": opcode.to_string(),
+            "footprint": footprint.summary(&self.iarch.shared_state.symtab),
+        }))
+    }
+
+    /// Symbolically execute an opcode using the architecture's
+    /// `isla_footprint` function, returning every path through it.
+    pub fn trace(&self, opcode: u32) -> Result<Vec<TracedPath>, Error> {
+        let shared_state = &self.iarch.shared_state;
+        let function_id = shared_state
+            .symtab
+            .get("zisla_footprint")
+            .ok_or_else(|| Error::new(ISLA_ERR_CONFIG, "No isla_footprint function in architecture"))?;
+        let (args, ret_ty, instrs) = shared_state.functions.get(&function_id).unwrap();
+        let task_state = TaskState::new();
+        let task = LocalFrame::new(function_id, args, ret_ty, Some(&[Val::Bits(B64::from_u32(opcode))]), instrs)
+            .add_lets(&self.iarch.lets)
+            .add_regs(&self.iarch.regs)
+            .task(0, &task_state);
+
+        let queue = Arc::new(SegQueue::new());
+        executor::start_multi(
+            self.num_threads,
+            None,
+            vec![task],
+            shared_state,
+            queue.clone(),
+            &executor::trace_result_collector,
+        );
+
+        let mut paths = Vec::new();
+        loop {
+            match queue.pop() {
+                Some(Ok((_, result, mut events))) => {
+                    paths.push(TracedPath { result, events: events.drain(..).rev().collect() })
+                }
+                Some(Err(err)) => return Err(Error::new(ISLA_ERR_EXECUTION, err.to_string())),
+                None => break,
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// Print the events of a path as a `(trace ...)` S-expression
+pub fn write_path(buf: &mut dyn std::io::Write, events: &[Event<B64>], shared_state: &SharedState<B64>) {
+    write_events_with_opts(buf, events, shared_state, &WriteOpts::default()).unwrap()
+}
+
+unsafe fn free_source(source: Source) {
+    match source {
+        Source::Text(contents) => drop(Box::from_raw(contents)),
+        Source::Tables(strings, files) => {
+            drop(Box::from_raw(strings));
+            drop(Box::from_raw(files))
+        }
+    }
+}
+
+impl Drop for IslaArch {
+    fn drop(&mut self) {
+        // Everything borrowing from the IR and symbol table must be
+        // dropped before the storage it refers to.
+        unsafe {
+            ManuallyDrop::drop(&mut self.iarch);
+            drop(Box::from_raw(self.defs));
+            free_source(self.source)
+        }
+    }
+}
+
+/// The events of every path through an instruction, rendered as
+/// S-expressions in execution order
+pub struct IslaTraceIter {
+    paths: Vec<(bool, Vec<CString>)>,
+    path: usize,
+    event: usize,
+}
+
+fn render_path(events: &[Event<B64>], shared_state: &SharedState<B64>) -> Vec<CString> {
+    // With prefix set the trace is printed as `(trace` followed by
+    // one event per line, without the closing parenthesis.
+    let opts = WriteOpts { prefix: true, ..WriteOpts::default() };
+    let mut buf = Vec::new();
+    write_events_with_opts(&mut buf, events, shared_state, &opts).unwrap();
+    String::from_utf8_lossy(&buf)
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| CString::new(line).unwrap())
+        .collect()
+}
+
+/// Return a description of the last error reported on this thread,
+/// or null if there has been none. The string is owned by the
+/// library and is valid until the next error on this thread.
+#[no_mangle]
+pub extern "C" fn isla_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map(|msg| msg.as_ptr()).unwrap_or(ptr::null()))
+}
+
+/// Load and initialize an architecture from a Sail IR file (`.ir` or
+/// serialized `.irx`) and an ISA configuration file, using up to
+/// `num_threads` threads for each query.
+///
+/// # Safety
+///
+/// `ir_path` and `config_path` must be null-terminated strings, and
+/// `arch` must be a valid pointer to store the result in.
+#[no_mangle]
+pub unsafe extern "C" fn isla_arch_load(
+    ir_path: *const c_char,
+    config_path: *const c_char,
+    num_threads: usize,
+    arch: *mut *mut IslaArch,
+) -> c_int {
+    guard(|| {
+        if arch.is_null() {
+            return Err(Error::new(ISLA_ERR_NULL, "Null architecture pointer"));
+        }
+        let loaded = IslaArch::load(path_arg(ir_path)?, path_arg(config_path)?, num_threads)?;
+        *arch = Box::into_raw(Box::new(loaded));
+        Ok(ISLA_OK)
+    })
+}
+
+/// # Safety
+///
+/// `arch` must be null or have been returned by `isla_arch_load`, and
+/// must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn isla_arch_free(arch: *mut IslaArch) {
+    if !arch.is_null() {
+        drop(Box::from_raw(arch))
+    }
+}
+
+/// Compute the footprint of a 32-bit opcode, storing it as a JSON
+/// object (in the same format as `isla-footprint --json`) in
+/// `json`. The string must be freed with `isla_string_free`.
+///
+/// # Safety
+///
+/// `arch` must have been returned by `isla_arch_load`, and `json`
+/// must be a valid pointer to store the result in.
+#[no_mangle]
+pub unsafe extern "C" fn isla_footprint(arch: *const IslaArch, opcode: u32, json: *mut *mut c_char) -> c_int {
+    guard(|| {
+        if arch.is_null() || json.is_null() {
+            return Err(Error::new(ISLA_ERR_NULL, "Null argument to isla_footprint"));
+        }
+        let summary = (*arch).footprint(opcode)?;
+        *json = CString::new(summary.to_string()).unwrap().into_raw();
+        Ok(ISLA_OK)
+    })
+}
+
+/// # Safety
+///
+/// `s` must be null or a string returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn isla_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s))
+    }
+}
+
+/// Symbolically execute a 32-bit opcode, storing an iterator over
+/// the events of every path in `iter`. The iterator must be freed
+/// with `isla_trace_free`.
+///
+/// # Safety
+///
+/// `arch` must have been returned by `isla_arch_load`, and `iter`
+/// must be a valid pointer to store the result in.
+#[no_mangle]
+pub unsafe extern "C" fn isla_trace(arch: *const IslaArch, opcode: u32, iter: *mut *mut IslaTraceIter) -> c_int {
+    guard(|| {
+        if arch.is_null() || iter.is_null() {
+            return Err(Error::new(ISLA_ERR_NULL, "Null argument to isla_trace"));
+        }
+        let arch = &*arch;
+        let paths = arch
+            .trace(opcode)?
+            .iter()
+            .map(|path| (path.result, render_path(&path.events, arch.shared_state())))
+            .collect();
+        *iter = Box::into_raw(Box::new(IslaTraceIter { paths, path: 0, event: 0 }));
+        Ok(ISLA_OK)
+    })
+}
+
+/// The number of paths found by `isla_trace`
+///
+/// # Safety
+///
+/// `iter` must have been returned by `isla_trace`.
+#[no_mangle]
+pub unsafe extern "C" fn isla_trace_paths(iter: *const IslaTraceIter) -> usize {
+    iter.as_ref().map(|iter| iter.paths.len()).unwrap_or(0)
+}
+
+/// Whether the footprint function returned true on a path
+///
+/// # Safety
+///
+/// `iter` must have been returned by `isla_trace`.
+#[no_mangle]
+pub unsafe extern "C" fn isla_trace_path_result(iter: *const IslaTraceIter, path: usize) -> bool {
+    iter.as_ref().and_then(|iter| iter.paths.get(path)).map(|(result, _)| *result).unwrap_or(false)
+}
+
+/// Get the next event, setting `path` to the index of the path it
+/// belongs to and `event` to its S-expression. The event string is
+/// owned by the iterator. Returns `ISLA_DONE` once every event of
+/// every path has been returned.
+///
+/// # Safety
+///
+/// `iter` must have been returned by `isla_trace`, and `path` and
+/// `event` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn isla_trace_next(
+    iter: *mut IslaTraceIter,
+    path: *mut usize,
+    event: *mut *const c_char,
+) -> c_int {
+    guard(|| {
+        if iter.is_null() || path.is_null() || event.is_null() {
+            return Err(Error::new(ISLA_ERR_NULL, "Null argument to isla_trace_next"));
+        }
+        let iter = &mut *iter;
+        while let Some((_, events)) = iter.paths.get(iter.path) {
+            if let Some(ev) = events.get(iter.event) {
+                *path = iter.path;
+                *event = ev.as_ptr();
+                iter.event += 1;
+                return Ok(ISLA_OK);
+            }
+            iter.path += 1;
+            iter.event = 0
+        }
+        Ok(ISLA_DONE)
+    })
+}
+
+/// # Safety
+///
+/// `iter` must be null or have been returned by `isla_trace`, and
+/// must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn isla_trace_free(iter: *mut IslaTraceIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter))
+    }
+}
+
+/// Release memory held by the SMT solver.
+///
+/// # Safety
+///
+/// Should only be called once no more queries will be made.
+#[no_mangle]
+pub unsafe extern "C" fn isla_finalize() {
+    isla_lib::smt::finalize_solver()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_missing_file() {
+        let ir = CString::new("/nonexistent/arch.ir").unwrap();
+        let config = CString::new("/nonexistent/arch.toml").unwrap();
+        let mut arch = ptr::null_mut();
+        let code = unsafe { isla_arch_load(ir.as_ptr(), config.as_ptr(), 1, &mut arch) };
+        assert_eq!(code, ISLA_ERR_IO);
+        assert!(arch.is_null());
+        let message = unsafe { CStr::from_ptr(isla_last_error()) };
+        assert!(message.to_str().unwrap().contains("/nonexistent/arch.ir"))
+    }
+
+    #[test]
+    fn null_arguments() {
+        let mut json = ptr::null_mut();
+        assert_eq!(unsafe { isla_footprint(ptr::null(), 0, &mut json) }, ISLA_ERR_NULL);
+        assert_eq!(unsafe { isla_trace_paths(ptr::null()) }, 0)
+    }
+}
@@ -0,0 +1,185 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A Python module, `isla`, exposing architecture loading, symbolic
+//! execution of opcodes, and the events of each path with names
+//! resolved through the symbol table. Built with the `python`
+//! feature, for example using maturin.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+use std::path::Path;
+
+use isla_lib::bitvector::b64::B64;
+use isla_lib::ir::*;
+use isla_lib::smt::{Accessor, Event};
+use isla_lib::zencode;
+
+use crate::{write_path, Error, IslaArch};
+
+create_exception!(isla, IslaError, PyException);
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> PyErr {
+        IslaError::new_err(err.message)
+    }
+}
+
+/// An initialized architecture
+#[pyclass(module = "isla", name = "Architecture", unsendable)]
+struct PyArchitecture {
+    arch: IslaArch,
+}
+
+/// A path through an instruction
+#[pyclass(module = "isla", name = "Path", get_all)]
+struct PyPath {
+    /// The value returned by the footprint function
+    result: bool,
+    events: Vec<PyEvent>,
+    /// The path as a `(trace ...)` S-expression
+    text: String,
+}
+
+/// A trace event. `kind` is named as in the S-expression output, and
+/// the other fields are set when they apply to that kind of event.
+#[pyclass(module = "isla", name = "Event", get_all)]
+#[derive(Clone)]
+struct PyEvent {
+    kind: &'static str,
+    /// The register or function name
+    name: Option<String>,
+    /// The value read, written, or returned
+    value: Option<String>,
+    address: Option<String>,
+    bytes: Option<u32>,
+}
+
+impl PyEvent {
+    fn new(kind: &'static str) -> Self {
+        PyEvent { kind, name: None, value: None, address: None, bytes: None }
+    }
+
+    fn from_event(event: &Event<B64>, shared_state: &SharedState<B64>) -> Self {
+        let symtab = &shared_state.symtab;
+        let name = |name: Name| zencode::decode(symtab.to_str(name));
+        let register = |reg: Name, accessor: &[Accessor]| {
+            let mut register = name(reg);
+            for Accessor::Field(field) in accessor {
+                register.push('.');
+                register.push_str(&name(*field))
+            }
+            register
+        };
+        let value = |val: &Val<B64>| Some(val.to_string(shared_state));
+
+        match event {
+            Event::ReadReg(reg, accessor, val, _) => {
+                PyEvent { name: Some(register(*reg, accessor)), value: value(val), ..PyEvent::new("read-reg") }
+            }
+            Event::WriteReg(reg, accessor, val, _) => {
+                PyEvent { name: Some(register(*reg, accessor)), value: value(val), ..PyEvent::new("write-reg") }
+            }
+            Event::ReadMem { value: val, address, bytes, .. } => {
+                PyEvent { value: value(val), address: value(address), bytes: Some(*bytes), ..PyEvent::new("read-mem") }
+            }
+            Event::WriteMem { data, address, bytes, .. } => PyEvent {
+                value: value(data),
+                address: value(address),
+                bytes: Some(*bytes),
+                ..PyEvent::new("write-mem")
+            },
+            Event::Branch { address, .. } => PyEvent { address: value(address), ..PyEvent::new("branch-address") },
+            Event::Abstract { name: f, return_value, .. } => {
+                PyEvent { name: Some(name(*f)), value: value(return_value), ..PyEvent::new("abstract") }
+            }
+            Event::Instr(opcode) => PyEvent { value: value(opcode), ..PyEvent::new("instr") },
+            Event::Smt(..) => PyEvent::new("smt"),
+            Event::Fork(..) => PyEvent::new("branch"),
+            Event::Cycle => PyEvent::new("cycle"),
+            _ => PyEvent::new("other"),
+        }
+    }
+}
+
+#[pymethods]
+impl PyEvent {
+    fn __repr__(&self) -> String {
+        let fields: Vec<String> = [("name", &self.name), ("value", &self.value), ("address", &self.address)]
+            .iter()
+            .filter_map(|(field, value)| value.as_ref().map(|value| format!("{}={}", field, value)))
+            .chain(self.bytes.map(|bytes| format!("bytes={}", bytes)))
+            .collect();
+        format!("Event({}{}{})", self.kind, if fields.is_empty() { "" } else { " " }, fields.join(" "))
+    }
+}
+
+#[pymethods]
+impl PyArchitecture {
+    #[new]
+    #[pyo3(signature = (ir, config, threads = 1))]
+    fn new(ir: &str, config: &str, threads: usize) -> PyResult<Self> {
+        Ok(PyArchitecture { arch: IslaArch::load(Path::new(ir), Path::new(config), threads)? })
+    }
+
+    /// The footprint of an opcode, as a JSON string
+    fn footprint(&self, opcode: u32) -> PyResult<String> {
+        Ok(self.arch.footprint(opcode)?.to_string())
+    }
+
+    /// Every path through an opcode
+    fn trace(&self, opcode: u32) -> PyResult<Vec<PyPath>> {
+        let shared_state = self.arch.shared_state();
+        Ok(self
+            .arch
+            .trace(opcode)?
+            .into_iter()
+            .map(|path| {
+                let mut text = Vec::new();
+                write_path(&mut text, &path.events, shared_state);
+                PyPath {
+                    result: path.result,
+                    events: path.events.iter().map(|event| PyEvent::from_event(event, shared_state)).collect(),
+                    text: String::from_utf8_lossy(&text).to_string(),
+                }
+            })
+            .collect())
+    }
+}
+
+#[pymodule]
+fn isla(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyArchitecture>()?;
+    m.add_class::<PyPath>()?;
+    m.add_class::<PyEvent>()?;
+    m.add("IslaError", m.py().get_type::<IslaError>())?;
+    Ok(())
+}
@@ -0,0 +1,112 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Compares deciding whether stores and loads relative to a common
+//! base address overlap, as done for store-to-load forwarding by
+//! isla-footprint, using the SMT solver for every pair and using
+//! `isla_lib::memory::alias` first.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use isla_lib::bitvector::b64::B64;
+use isla_lib::ir::Val;
+use isla_lib::memory::alias::{Alias, AliasAnalysis};
+use isla_lib::smt::smtlib::Exp::*;
+use isla_lib::smt::smtlib::*;
+use isla_lib::smt::*;
+use isla_lib::source_loc::SourceLoc;
+
+const ACCESSES: u32 = 8;
+
+/// A base address, followed by the addresses of stores and loads at
+/// increasing offsets from it
+fn trace() -> Vec<Event<B64>> {
+    let mut events = vec![Event::Smt(
+        Def::DeclareConst(Sym::from_u32(0), Ty::BitVec(64)),
+        DefAttrs::default(),
+        SourceLoc::unknown(),
+    )];
+    for i in 1..=2 * ACCESSES {
+        let address = Bvadd(Box::new(Var(Sym::from_u32(0))), Box::new(bits64(4 * (i % ACCESSES) as u64, 64)));
+        events.push(Event::Smt(Def::DefineConst(Sym::from_u32(i), address), DefAttrs::default(), SourceLoc::unknown()))
+    }
+    events
+}
+
+fn overlap(a1: Sym, a2: Sym, bytes: u64) -> Exp<Sym> {
+    let end = |a: Sym| Box::new(Bvadd(Box::new(ZeroExtend(1, Box::new(Var(a)))), Box::new(bits64(bytes, 65))));
+    And(
+        Box::new(Bvult(Box::new(ZeroExtend(1, Box::new(Var(a1)))), end(a2))),
+        Box::new(Bvult(Box::new(ZeroExtend(1, Box::new(Var(a2)))), end(a1))),
+    )
+}
+
+fn forwarding(events: &[Event<B64>], alias_analysis: bool) -> usize {
+    let ctx = Context::new(Config::new());
+    let mut solver = Solver::<B64>::new(&ctx);
+    for event in events {
+        if let Event::Smt(def, _, _) = event {
+            solver.add(def.clone())
+        }
+    }
+    let aliases = AliasAnalysis::from_events(events);
+
+    let mut decided = 0;
+    for store in 1..=ACCESSES {
+        for load in ACCESSES + 1..=2 * ACCESSES {
+            let (store, load) = (Sym::from_u32(store), Sym::from_u32(load));
+            let alias = if alias_analysis {
+                aliases.alias(&Val::<B64>::Symbolic(store), 8, &Val::Symbolic(load), 4, 64)
+            } else {
+                Alias::May
+            };
+            if alias == Alias::May {
+                let overlap = overlap(store, load, 4);
+                solver.check_sat_with(&overlap);
+                solver.check_sat_with(&Not(Box::new(overlap)));
+            } else {
+                decided += 1
+            }
+        }
+    }
+    decided
+}
+
+fn bench_alias(c: &mut Criterion) {
+    let events = trace();
+
+    let mut group = c.benchmark_group("forwarding");
+    group.sample_size(10);
+    group.bench_function("solver", |b| b.iter(|| forwarding(&events, false)));
+    group.bench_function("alias analysis", |b| b.iter(|| forwarding(&events, true)));
+    group.finish()
+}
+
+criterion_group!(benches, bench_alias);
+criterion_main!(benches);
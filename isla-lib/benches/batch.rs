@@ -0,0 +1,85 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Compares declaring and asserting the constants of a long trace one
+//! at a time and in batches, with `Solver::declare_consts` and
+//! `Solver::assert_all`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use isla_lib::bitvector::b64::B64;
+use isla_lib::bitvector::BV;
+use isla_lib::smt::smtlib::Exp::*;
+use isla_lib::smt::smtlib::*;
+use isla_lib::smt::*;
+use isla_lib::source_loc::SourceLoc;
+
+const VARS: usize = 4096;
+
+/// Each constant is one more than the last, as a chain of register
+/// writes might produce
+fn chain(vs: &[Sym]) -> Vec<Exp<Sym>> {
+    let mut exps = vec![Eq(Box::new(Var(vs[0])), Box::new(Bits64(B64::new(0, 64))))];
+    for pair in vs.windows(2) {
+        exps.push(Eq(
+            Box::new(Var(pair[1])),
+            Box::new(Bvadd(Box::new(Var(pair[0])), Box::new(Bits64(B64::new(1, 64))))),
+        ))
+    }
+    exps
+}
+
+fn unbatched(ctx: &Context) -> usize {
+    let mut solver = Solver::<B64>::new(ctx);
+    let vs: Vec<Sym> = (0..VARS).map(|_| solver.declare_const(Ty::BitVec(64), SourceLoc::unknown())).collect();
+    for exp in chain(&vs) {
+        solver.assert(exp)
+    }
+    solver.trace().head().len()
+}
+
+fn batched(ctx: &Context) -> usize {
+    let mut solver = Solver::<B64>::new(ctx);
+    let vs = solver.declare_consts(vec![Ty::BitVec(64); VARS], SourceLoc::unknown());
+    solver.assert_all(chain(&vs));
+    solver.trace().head().len()
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let ctx = Context::new(Config::new());
+
+    let mut group = c.benchmark_group("batch");
+    group.sample_size(10);
+    group.bench_function("unbatched", |b| b.iter(|| unbatched(&ctx)));
+    group.bench_function("batched", |b| b.iter(|| batched(&ctx)));
+    group.finish()
+}
+
+criterion_group!(benches, bench_batch);
+criterion_main!(benches);
@@ -0,0 +1,118 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Compares creating the solver for a task forked after a long
+//! decode-like prefix by replaying its whole checkpoint, and by
+//! restoring it with a `CheckpointCache` which holds either the
+//! prefix or the checkpoint itself, along with the cost of copying a
+//! solver into the cache.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use isla_lib::bitvector::b64::B64;
+use isla_lib::smt::smtlib::Exp::*;
+use isla_lib::smt::smtlib::*;
+use isla_lib::smt::*;
+use isla_lib::source_loc::SourceLoc;
+
+const FIELDS: u32 = 500;
+
+/// Checkpoints after decoding a symbolic opcode by testing many
+/// fields of it, and after a few more events on one path.
+fn checkpoints() -> (Checkpoint<B64>, Checkpoint<B64>) {
+    let ctx = Context::new(Config::new());
+    let mut solver = Solver::<B64>::new(&ctx);
+    let info = SourceLoc::unknown();
+    let opcode = solver.declare_const(Ty::BitVec(32), info);
+    let mut decoded = solver.define_const(Bool(false), info);
+    for i in 0..FIELDS {
+        let lo = i % 24;
+        let field = solver.define_const(Extract(lo + 7, lo, Box::new(Var(opcode))), info);
+        let matches = solver.define_const(Eq(Box::new(Var(field)), Box::new(bits64(i as u64 % 256, 8))), info);
+        decoded = solver.define_const(Or(Box::new(Var(decoded)), Box::new(Var(matches))), info);
+        if i % 50 == 0 {
+            solver.add(Def::Assert(Not(Box::new(Var(matches)))))
+        }
+    }
+    solver.add(Def::Assert(Var(decoded)));
+    let prefix = checkpoint(&mut solver);
+    for i in 0..10 {
+        let v = solver.define_const(Bvadd(Box::new(Var(opcode)), Box::new(bits64(i, 32))), info);
+        solver.add(Def::Assert(Bvugt(Box::new(Var(v)), Box::new(bits64(i, 32)))))
+    }
+    (prefix, checkpoint(&mut solver))
+}
+
+fn warm_cache(checkpoint: &Checkpoint<B64>) -> CheckpointCache<B64> {
+    let cache = CheckpointCache::new(8);
+    let ctx = Context::new(Config::new());
+    cache.restore(&ctx, checkpoint.clone());
+    cache
+}
+
+fn bench_checkpoint(c: &mut Criterion) {
+    let (prefix, point) = checkpoints();
+
+    let mut group = c.benchmark_group("restore");
+    group.sample_size(20);
+    group.bench_function("replay", |b| {
+        b.iter(|| {
+            let ctx = Context::new(Config::new());
+            let solver = Solver::from_checkpoint(&ctx, point.clone());
+            solver.trace().len()
+        })
+    });
+    group.bench_function("cached prefix", |b| {
+        b.iter_batched(
+            || warm_cache(&prefix),
+            |cache| {
+                let ctx = Context::new(Config::new());
+                let solver = cache.restore(&ctx, point.clone());
+                solver.trace().len()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    let cache = warm_cache(&point);
+    group.bench_function("cached checkpoint", |b| {
+        b.iter(|| {
+            let ctx = Context::new(Config::new());
+            let solver = cache.restore(&ctx, point.clone());
+            solver.trace().len()
+        })
+    });
+    group.finish();
+
+    let ctx = Context::new(Config::new());
+    let solver = Solver::from_checkpoint(&ctx, point);
+    c.bench_function("snapshot", |b| b.iter(|| solver.snapshot()));
+}
+
+criterion_group!(benches, bench_checkpoint);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Compares solver time for expressions shaped like those the models
+//! generate, with and without the rewrite rules in
+//! `isla_lib::smt::rewrite` applied first.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use isla_lib::bitvector::b64::B64;
+use isla_lib::bitvector::BV;
+use isla_lib::smt::rewrite;
+use isla_lib::smt::smtlib::Exp::*;
+use isla_lib::smt::smtlib::*;
+use isla_lib::smt::*;
+
+const VARS: u32 = 16;
+
+fn bv(bits: u64, len: u32) -> Box<Exp<Sym>> {
+    Box::new(Bits64(B64::new(bits, len)))
+}
+
+/// A 64-bit value reassembled from its two halves, masked twice, and
+/// moved through a 128-bit zero extension
+fn noisy(v: Sym) -> Exp<Sym> {
+    let x = Box::new(Var(v));
+    let reassembled = Concat(Box::new(Extract(63, 32, x.clone())), Box::new(Extract(31, 0, x)));
+    let masked =
+        Bvand(Box::new(Bvand(Box::new(reassembled), bv(0xffff_ffff_ffff_fff0, 64))), bv(0x0fff_ffff_ffff_ffff, 64));
+    let widened = Concat(bv(0, 64), Box::new(masked));
+    Extract(63, 0, Box::new(Bvand(Box::new(Extract(127, 0, Box::new(widened))), Box::new(Bits(vec![true; 128])))))
+}
+
+/// Require the masked values to multiply out to a given product, so
+/// the solver has some real work to do
+fn problem() -> Exp<Sym> {
+    let product =
+        (1..VARS).fold(noisy(Sym::from_u32(0)), |acc, v| Bvmul(Box::new(acc), Box::new(noisy(Sym::from_u32(v)))));
+    let distinct = (1..VARS).fold(Bool(true), |acc, v| {
+        And(Box::new(acc), Box::new(Bvult(Box::new(noisy(Sym::from_u32(v - 1))), Box::new(noisy(Sym::from_u32(v))))))
+    });
+    And(Box::new(Eq(Box::new(product), bv(0x1234_5678_9abc_def0, 64))), Box::new(distinct))
+}
+
+fn check(exp: &Exp<Sym>) -> SmtResult {
+    let ctx = Context::new(Config::new());
+    let mut solver = Solver::<B64>::new(&ctx);
+    for v in 0..VARS {
+        solver.add(Def::DeclareConst(Sym::from_u32(v), Ty::BitVec(64)))
+    }
+    solver.check_sat_with(exp)
+}
+
+fn bench_rewrite(c: &mut Criterion) {
+    let original = problem();
+    let mut rewritten = original.clone();
+    rewrite::rewrite(&mut rewritten);
+
+    let mut group = c.benchmark_group("rewrite");
+    group.sample_size(10);
+    group.bench_function("original", |b| b.iter(|| check(&original)));
+    group.bench_function("rewritten", |b| b.iter(|| check(&rewritten)));
+    group.finish()
+}
+
+criterion_group!(benches, bench_rewrite);
+criterion_main!(benches);
@@ -0,0 +1,1625 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! This module implements an abstract interpreter for the IR, which
+//! runs an instruction once over a domain of partially-known values
+//! rather than forking on every symbolic branch. Bitvectors are
+//! tracked bit-by-bit as either known or unknown, and other values
+//! are either fully known or unknown. Where both sides of a branch
+//! are possible the interpreter takes both and joins the resulting
+//! states at the point where control flow merges.
+//!
+//! The result is an [AbstractFootprint], which over-approximates
+//! the registers and memory an instruction can access, together with
+//! whatever is known about the values involved. This is much cheaper
+//! than symbolic execution for instructions with many paths, at the
+//! cost of losing any relationships between values.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::mem;
+
+use crate::bitvector::BV;
+use crate::executor::{eval_op, LocalFrame};
+use crate::ir::static_footprint::static_footprint;
+use crate::ir::*;
+use crate::log;
+use crate::primop::{Binary, Primops, Unary, Variadic};
+use crate::smt::{Config, Context, EnumMember, Solver};
+use crate::source_loc::SourceLoc;
+use crate::zencode;
+
+/// Calls nested deeper than this are summarized rather than inlined
+const MAX_CALL_DEPTH: usize = 64;
+
+/// The maximum number of instructions the interpreter will step
+/// through before giving up
+const MAX_STEPS: usize = 10_000_000;
+
+/// A bitvector where each bit is either known or unknown. Bits set
+/// in `known` are known to have the corresponding value in `bits`,
+/// and `bits` is always zero where the value is unknown.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KnownBits<B> {
+    known: B,
+    bits: B,
+}
+
+impl<B: BV> KnownBits<B> {
+    pub fn exact(bv: B) -> Self {
+        KnownBits { known: B::ones(bv.len()), bits: bv }
+    }
+
+    pub fn unknown(len: u32) -> Self {
+        KnownBits { known: B::zeros(len), bits: B::zeros(len) }
+    }
+
+    pub fn len(self) -> u32 {
+        self.known.len()
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value of the bitvector, if every bit is known
+    pub fn value(self) -> Option<B> {
+        if self.known == B::ones(self.len()) {
+            Some(self.bits)
+        } else {
+            None
+        }
+    }
+
+    /// The value of the bit at index `n`, if it is known
+    pub fn bit(self, n: u32) -> Option<bool> {
+        if n < self.len() && !self.known.slice(n, 1)?.is_zero() {
+            Some(!self.bits.slice(n, 1)?.is_zero())
+        } else {
+            None
+        }
+    }
+
+    pub fn join(self, other: Self) -> Self {
+        let known = self.known & other.known & !(self.bits ^ other.bits);
+        KnownBits { known, bits: self.bits & known }
+    }
+
+    pub fn bvnot(self) -> Self {
+        KnownBits { known: self.known, bits: !self.bits & self.known }
+    }
+
+    pub fn bvand(self, other: Self) -> Self {
+        let known = (self.known & other.known) | (self.known & !self.bits) | (other.known & !other.bits);
+        KnownBits { known, bits: self.bits & other.bits & known }
+    }
+
+    pub fn bvor(self, other: Self) -> Self {
+        let known = (self.known & other.known) | self.bits | other.bits;
+        KnownBits { known, bits: (self.bits | other.bits) & known }
+    }
+
+    pub fn bvxor(self, other: Self) -> Self {
+        let known = self.known & other.known;
+        KnownBits { known, bits: (self.bits ^ other.bits) & known }
+    }
+
+    /// The bits known in both operands up to and including the
+    /// lowest unknown bit in either, which are exactly the bits of a
+    /// sum or difference that do not depend on an unknown carry.
+    fn known_low_bits(self, other: Self) -> B {
+        let both = self.known & other.known;
+        both & !(both + B::new(1, self.len()))
+    }
+
+    pub fn bvadd(self, other: Self) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        let known = self.known_low_bits(other);
+        KnownBits { known, bits: (self.bits + other.bits) & known }
+    }
+
+    pub fn bvsub(self, other: Self) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        let known = self.known_low_bits(other);
+        KnownBits { known, bits: (self.bits - other.bits) & known }
+    }
+
+    /// Returns `Some(false)` if the two bitvectors differ in a bit
+    /// known in both, and `Some(true)` only if both are fully known
+    /// and equal.
+    pub fn equal(self, other: Self) -> Option<bool> {
+        if !(self.known & other.known & (self.bits ^ other.bits)).is_zero() {
+            Some(false)
+        } else if self.value().is_some() && other.value().is_some() {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    pub fn append(self, suffix: Self) -> Option<Self> {
+        Some(KnownBits { known: self.known.append(suffix.known)?, bits: self.bits.append(suffix.bits)? })
+    }
+
+    pub fn slice(self, from: u32, len: u32) -> Option<Self> {
+        Some(KnownBits { known: self.known.slice(from, len)?, bits: self.bits.slice(from, len)? })
+    }
+
+    pub fn set_slice(self, n: u32, update: Self) -> Option<Self> {
+        if n.checked_add(update.len())? > self.len() {
+            return None;
+        }
+        Some(KnownBits { known: self.known.set_slice(n, update.known), bits: self.bits.set_slice(n, update.bits) })
+    }
+
+    pub fn zero_extend(self, new_len: u32) -> Option<Self> {
+        if new_len < self.len() || new_len > B::MAX_WIDTH {
+            return None;
+        }
+        Some(KnownBits { known: !(!self.known).zero_extend(new_len), bits: self.bits.zero_extend(new_len) })
+    }
+
+    /// Sign extend, where the new bits are known only if the sign bit
+    /// is. Because unknown bits are zero in both `known` and `bits`,
+    /// sign extending each of them separately does exactly this.
+    pub fn sign_extend(self, new_len: u32) -> Option<Self> {
+        if new_len < self.len() || new_len > B::MAX_WIDTH {
+            return None;
+        }
+        if self.is_empty() {
+            return self.zero_extend(new_len);
+        }
+        Some(KnownBits { known: self.known.sign_extend(new_len), bits: self.bits.sign_extend(new_len) })
+    }
+
+    pub fn shiftl(self, shift: i128) -> Self {
+        let ones = B::ones(self.len());
+        KnownBits { known: self.known.shiftl(shift) | !ones.shiftl(shift), bits: self.bits.shiftl(shift) }
+    }
+
+    pub fn shiftr(self, shift: i128) -> Self {
+        let ones = B::ones(self.len());
+        KnownBits { known: self.known.shiftr(shift) | !ones.shiftr(shift), bits: self.bits.shiftr(shift) }
+    }
+
+    pub fn replicate(self, times: i128) -> Option<Self> {
+        Some(KnownBits { known: self.known.replicate(times)?, bits: self.bits.replicate(times)? })
+    }
+}
+
+/// Known bitvectors are printed in hexadecimal where every nibble is
+/// either fully known or fully unknown, and in binary otherwise, with
+/// `?` standing for an unknown digit.
+impl<B: BV> fmt::Display for KnownBits<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.len();
+        let nibble = |n: u32| -> (B, B) { (self.known.slice(n * 4, 4).unwrap(), self.bits.slice(n * 4, 4).unwrap()) };
+        if len > 0 && len & 3 == 0 && (0..len / 4).all(|n| nibble(n).0.is_zero() || nibble(n).0 == B::ones(4)) {
+            write!(f, "#x")?;
+            for n in (0..len / 4).rev() {
+                let (known, bits) = nibble(n);
+                if known.is_zero() {
+                    write!(f, "?")?
+                } else {
+                    write!(f, "{:x}", bits.lower_u64())?
+                }
+            }
+        } else {
+            write!(f, "#b")?;
+            for n in (0..len).rev() {
+                match self.bit(n) {
+                    Some(true) => write!(f, "1")?,
+                    Some(false) => write!(f, "0")?,
+                    None => write!(f, "?")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An abstract value. `Top` is a value about which nothing is known,
+/// and the `Option` variants are flat domains where `None` means any
+/// value of that type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbsVal<B> {
+    Top,
+    Unit,
+    Bool(Option<bool>),
+    I64(Option<i64>),
+    I128(Option<i128>),
+    Bits(KnownBits<B>),
+    String(Option<String>),
+    Enum(Option<EnumMember>),
+    Ref(Name),
+    Struct(HashMap<Name, AbsVal<B>>),
+    /// A union value, which could have been built by any of the
+    /// constructors in the map
+    Ctor(HashMap<Name, AbsVal<B>>),
+    Vector(Vec<AbsVal<B>>),
+    List(Vec<AbsVal<B>>),
+}
+
+impl<B: BV> AbsVal<B> {
+    pub fn unknown_bits(len: u32) -> Self {
+        if len <= B::MAX_WIDTH {
+            AbsVal::Bits(KnownBits::unknown(len))
+        } else {
+            AbsVal::Top
+        }
+    }
+
+    /// The most general abstract value of a type
+    pub fn top(ty: &Ty<Name>, shared_state: &SharedState<B>) -> Self {
+        use AbsVal::*;
+        match ty {
+            Ty::I64 => I64(None),
+            Ty::I128 => I128(None),
+            Ty::Bits(len) => AbsVal::unknown_bits(*len),
+            Ty::Bit => AbsVal::unknown_bits(1),
+            Ty::Unit => Unit,
+            Ty::Bool => Bool(None),
+            Ty::String => String(None),
+            Ty::Enum(_) => Enum(None),
+            Ty::Struct(s) => match shared_state.type_info.structs.get(s) {
+                Some(fields) => Struct(fields.iter().map(|(f, ty)| (*f, Self::top(ty, shared_state))).collect()),
+                None => Top,
+            },
+            Ty::FixedVector(len, ty) => Vector(vec![Self::top(ty, shared_state); *len as usize]),
+            _ => Top,
+        }
+    }
+
+    pub fn from_val(val: &Val<B>) -> Self {
+        use AbsVal::*;
+        match val {
+            Val::I64(n) => I64(Some(*n)),
+            Val::I128(n) => I128(Some(*n)),
+            Val::Bool(b) => Bool(Some(*b)),
+            Val::Bits(bv) => Bits(KnownBits::exact(*bv)),
+            Val::String(s) => String(Some(s.clone())),
+            Val::Unit => Unit,
+            Val::Vector(vals) => Vector(vals.iter().map(Self::from_val).collect()),
+            Val::List(vals) => List(vals.iter().map(Self::from_val).collect()),
+            Val::Enum(member) => Enum(Some(*member)),
+            Val::Struct(fields) => Struct(fields.iter().map(|(f, v)| (*f, Self::from_val(v))).collect()),
+            Val::Ctor(ctor, v) => Ctor(HashMap::from([(*ctor, Self::from_val(v))])),
+            Val::Ref(reg) => Ref(*reg),
+            Val::Symbolic(_) | Val::MixedBits(_) | Val::SymbolicCtor(_, _) | Val::Poison | Val::BigInt(_) => Top,
+        }
+    }
+
+    /// Convert back to a concrete value, if this abstract value
+    /// stands for exactly one value
+    pub fn to_val(&self) -> Option<Val<B>> {
+        use AbsVal::*;
+        Some(match self {
+            Top => return None,
+            Unit => Val::Unit,
+            Bool(b) => Val::Bool((*b)?),
+            I64(n) => Val::I64((*n)?),
+            I128(n) => Val::I128((*n)?),
+            Bits(bits) => Val::Bits(bits.value()?),
+            String(s) => Val::String(s.clone()?),
+            Enum(member) => Val::Enum((*member)?),
+            Ref(reg) => Val::Ref(*reg),
+            Struct(fields) => Val::Struct(fields.iter().map(|(f, v)| Some((*f, v.to_val()?))).collect::<Option<_>>()?),
+            Ctor(ctors) if ctors.len() == 1 => {
+                let (ctor, v) = ctors.iter().next().unwrap();
+                Val::Ctor(*ctor, Box::new(v.to_val()?))
+            }
+            Ctor(_) => return None,
+            Vector(vals) => Val::Vector(vals.iter().map(Self::to_val).collect::<Option<_>>()?),
+            List(vals) => Val::List(vals.iter().map(Self::to_val).collect::<Option<_>>()?),
+        })
+    }
+
+    /// The least upper bound of two abstract values
+    pub fn join(&self, other: &Self) -> Self {
+        use AbsVal::*;
+        fn flat<T: Clone + PartialEq>(x: &Option<T>, y: &Option<T>) -> Option<T> {
+            if x == y {
+                x.clone()
+            } else {
+                None
+            }
+        }
+        match (self, other) {
+            (Unit, Unit) => Unit,
+            (Bool(x), Bool(y)) => Bool(flat(x, y)),
+            (I64(x), I64(y)) => I64(flat(x, y)),
+            (I128(x), I128(y)) => I128(flat(x, y)),
+            (String(x), String(y)) => String(flat(x, y)),
+            (Enum(x), Enum(y)) => Enum(flat(x, y)),
+            (Bits(x), Bits(y)) if x.len() == y.len() => Bits(x.join(*y)),
+            (Ref(x), Ref(y)) if x == y => Ref(*x),
+            (Struct(xs), Struct(ys)) => Struct(xs.iter().filter_map(|(f, x)| Some((*f, x.join(ys.get(f)?)))).collect()),
+            (Ctor(xs), Ctor(ys)) => {
+                let mut ctors = xs.clone();
+                for (ctor, y) in ys {
+                    let joined = match ctors.get(ctor) {
+                        Some(x) => x.join(y),
+                        None => y.clone(),
+                    };
+                    ctors.insert(*ctor, joined);
+                }
+                Ctor(ctors)
+            }
+            (Vector(xs), Vector(ys)) if xs.len() == ys.len() => {
+                Vector(xs.iter().zip(ys).map(|(x, y)| x.join(y)).collect())
+            }
+            (List(xs), List(ys)) if xs.len() == ys.len() => List(xs.iter().zip(ys).map(|(x, y)| x.join(y)).collect()),
+            _ => Top,
+        }
+    }
+
+    /// Whether two abstract values are equal, if that can be decided
+    pub fn equal(&self, other: &Self) -> Option<bool> {
+        use AbsVal::*;
+        match (self, other) {
+            (Bits(x), Bits(y)) if x.len() == y.len() => x.equal(*y),
+            (Bool(Some(x)), Bool(Some(y))) => Some(x == y),
+            (Enum(Some(x)), Enum(Some(y))) => Some(x == y),
+            (I64(Some(x)), I64(Some(y))) => Some(x == y),
+            (I128(Some(x)), I128(Some(y))) => Some(x == y),
+            _ => match (self.to_val(), other.to_val()) {
+                (Some(x), Some(y)) => Some(x == y),
+                _ => None,
+            },
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            AbsVal::Bool(b) => *b,
+            _ => None,
+        }
+    }
+
+    fn as_int(&self) -> Option<i128> {
+        match self {
+            AbsVal::I64(n) => n.map(i128::from),
+            AbsVal::I128(n) => *n,
+            _ => None,
+        }
+    }
+
+    fn field(self, field: Name) -> Self {
+        match self {
+            AbsVal::Struct(mut fields) => fields.remove(&field).unwrap_or(AbsVal::Top),
+            _ => AbsVal::Top,
+        }
+    }
+
+    pub fn to_string(&self, shared_state: &SharedState<B>) -> String {
+        use AbsVal::*;
+        let name = |n: Name| zencode::decode(shared_state.symtab.to_str(n));
+        let unknown = "?".to_string();
+        match self {
+            Top => unknown,
+            Unit => "()".to_string(),
+            Bool(b) => b.map(|b| b.to_string()).unwrap_or(unknown),
+            I64(n) => n.map(|n| n.to_string()).unwrap_or(unknown),
+            I128(n) => n.map(|n| n.to_string()).unwrap_or(unknown),
+            Bits(bits) => bits.to_string(),
+            String(s) => s.as_ref().map(|s| format!("{:?}", s)).unwrap_or(unknown),
+            Enum(member) => member
+                .and_then(|member| {
+                    let members = shared_state.type_info.enums.get(&member.enum_id.to_name())?;
+                    Some(format!("|{}|", name(*members.get(member.member)?)))
+                })
+                .unwrap_or(unknown),
+            Ref(reg) => format!("(_ reg |{}|)", name(*reg)),
+            Struct(fields) => {
+                let mut fields: Vec<_> =
+                    fields.iter().map(|(f, v)| format!("(|{}| {})", name(*f), v.to_string(shared_state))).collect();
+                fields.sort();
+                format!("(struct {})", fields.join(" "))
+            }
+            Ctor(ctors) => {
+                let mut ctors: Vec<_> =
+                    ctors.iter().map(|(c, v)| format!("(|{}| {})", name(*c), v.to_string(shared_state))).collect();
+                ctors.sort();
+                if ctors.len() == 1 {
+                    ctors.pop().unwrap()
+                } else {
+                    format!("(or {})", ctors.join(" "))
+                }
+            }
+            Vector(vals) => {
+                format!("(vec {})", vals.iter().map(|v| v.to_string(shared_state)).collect::<Vec<_>>().join(" "))
+            }
+            List(vals) => {
+                format!("(list {})", vals.iter().map(|v| v.to_string(shared_state)).collect::<Vec<_>>().join(" "))
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AccessKind {
+    Read,
+    Write,
+    ReadTag,
+    WriteTag,
+}
+
+impl fmt::Display for AccessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessKind::Read => write!(f, "read-mem"),
+            AccessKind::Write => write!(f, "write-mem"),
+            AccessKind::ReadTag => write!(f, "read-tag"),
+            AccessKind::WriteTag => write!(f, "write-tag"),
+        }
+    }
+}
+
+/// A memory access made by one primitive call site in the IR, joined
+/// over every time it is reached.
+#[derive(Clone, Debug)]
+pub struct MemoryAccess<B> {
+    pub kind: AccessKind,
+    pub address: AbsVal<B>,
+    pub bytes: AbsVal<B>,
+    /// The value written, for writes
+    pub data: Option<AbsVal<B>>,
+    pub info: SourceLoc,
+}
+
+#[derive(Clone, Debug)]
+pub struct AbstractFootprint<B> {
+    /// Every register read, with the join of the values read
+    pub reads: HashMap<Name, AbsVal<B>>,
+    /// Every register written, with the join of the values written
+    pub writes: HashMap<Name, AbsVal<B>>,
+    /// Memory accesses, in the order their call sites were first reached
+    pub memory: Vec<MemoryAccess<B>>,
+    /// Functions which were not interpreted, because they were
+    /// recursive or nested too deeply, and were instead summarized
+    /// using [static_footprint]
+    pub summarized: HashSet<Name>,
+}
+
+/// Returns the kind of access made by a memory primitive, with the
+/// argument positions of its address and byte count.
+fn memory_primop(name: &str) -> Option<(AccessKind, usize, Option<usize>)> {
+    match name {
+        "read_mem"
+        | "read_mem_ifetch"
+        | "read_mem_exclusive"
+        | "platform_read_mem"
+        | "platform_read_mem_ifetch"
+        | "platform_read_mem_exclusive" => Some((AccessKind::Read, 2, Some(3))),
+        "platform_read_memt" => Some((AccessKind::Read, 1, Some(2))),
+        "write_mem" | "write_mem_exclusive" | "platform_write_mem" | "platform_write_mem_exclusive" => {
+            Some((AccessKind::Write, 2, Some(3)))
+        }
+        "platform_write_memt" => Some((AccessKind::Write, 1, Some(2))),
+        "read_tag_bool" => Some((AccessKind::ReadTag, 0, None)),
+        "write_tag_bool" => Some((AccessKind::WriteTag, 0, None)),
+        "platform_write_tag" => Some((AccessKind::WriteTag, 1, None)),
+        _ => None,
+    }
+}
+
+/// Primitives with effects outside the solver, which must never be
+/// run by the interpreter.
+fn output_primop(name: &str) -> bool {
+    name.starts_with("print") || name.starts_with("prerr") || name == "sail_putchar"
+}
+
+#[derive(Clone, Debug)]
+struct State<B> {
+    vars: HashMap<Name, AbsVal<B>>,
+    lets: HashMap<Name, AbsVal<B>>,
+    regs: HashMap<Name, AbsVal<B>>,
+}
+
+fn join_into<B: BV>(into: &mut HashMap<Name, AbsVal<B>>, from: &HashMap<Name, AbsVal<B>>) -> bool {
+    let mut changed = false;
+    for (id, v) in from {
+        match into.get_mut(id) {
+            Some(old) => {
+                let new = old.join(v);
+                if new != *old {
+                    *old = new;
+                    changed = true
+                }
+            }
+            None => {
+                into.insert(*id, v.clone());
+                changed = true
+            }
+        }
+    }
+    // A variable missing on one side is only declared on some paths,
+    // and will be (re)initialized before it is used.
+    changed
+}
+
+impl<B: BV> State<B> {
+    fn join(&mut self, other: &Self) -> bool {
+        let vars = join_into(&mut self.vars, &other.vars);
+        let lets = join_into(&mut self.lets, &other.lets);
+        let regs = join_into(&mut self.regs, &other.regs);
+        vars || lets || regs
+    }
+}
+
+/// How control leaves an instruction
+enum Flow {
+    Next,
+    Goto(usize),
+    Branch(usize),
+    Return,
+    Stop,
+}
+
+struct Interp<'a, 'ir, 'ctx, B> {
+    shared_state: &'a SharedState<'ir, B>,
+    solver: Solver<'ctx, B>,
+    frame: LocalFrame<'ir, B>,
+    primops: HashMap<usize, String>,
+    reset_registers: &'a HashMap<Loc<Name>, Reset<B>>,
+    references: HashSet<Name>,
+    stack: Vec<Name>,
+    steps: usize,
+    footprint: AbstractFootprint<B>,
+    memory_index: HashMap<(Name, usize), usize>,
+}
+
+impl<'a, 'ir, 'ctx, B: BV> Interp<'a, 'ir, 'ctx, B> {
+    fn name(&self, n: Name) -> String {
+        zencode::decode(self.shared_state.symtab.to_str(n))
+    }
+
+    fn primop_name(&self, f: usize) -> &str {
+        self.primops.get(&f).map(String::as_str).unwrap_or("")
+    }
+
+    fn read_reg(&mut self, reg: Name, state: &State<B>) -> AbsVal<B> {
+        let value = match state.regs.get(&reg) {
+            Some(value) => value.clone(),
+            None => AbsVal::top(&self.shared_state.registers[&reg], self.shared_state),
+        };
+        let joined = match self.footprint.reads.get(&reg) {
+            Some(old) => old.join(&value),
+            None => value.clone(),
+        };
+        self.footprint.reads.insert(reg, joined);
+        value
+    }
+
+    fn write_reg(&mut self, reg: Name, value: AbsVal<B>, state: &mut State<B>) {
+        let joined = match self.footprint.writes.get(&reg) {
+            Some(old) => old.join(&value),
+            None => value.clone(),
+        };
+        self.footprint.writes.insert(reg, joined);
+        state.regs.insert(reg, value);
+    }
+
+    fn lookup(&mut self, id: Name, state: &State<B>) -> AbsVal<B> {
+        if let Some(value) = state.vars.get(&id) {
+            value.clone()
+        } else if self.shared_state.registers.contains_key(&id) {
+            self.read_reg(id, state)
+        } else if let Some(value) = state.lets.get(&id) {
+            value.clone()
+        } else if let Some((member, size, enum_id)) = self.shared_state.type_info.enum_members.get(&id) {
+            let enum_id = self.solver.get_enum(*enum_id, *size);
+            AbsVal::Enum(Some(EnumMember { enum_id, member: *member }))
+        } else {
+            AbsVal::Top
+        }
+    }
+
+    /// The current value at a location, without counting it as a
+    /// register read
+    fn loc_value(&self, loc: &Loc<Name>, state: &State<B>) -> AbsVal<B> {
+        match loc {
+            Loc::Id(id) => {
+                state.vars.get(id).or_else(|| state.regs.get(id)).or_else(|| state.lets.get(id)).cloned().unwrap_or(
+                    match self.shared_state.registers.get(id) {
+                        Some(ty) => AbsVal::top(ty, self.shared_state),
+                        None => AbsVal::Top,
+                    },
+                )
+            }
+            Loc::Field(loc, field) => self.loc_value(loc, state).field(*field),
+            Loc::Addr(loc) => match self.loc_value(loc, state) {
+                AbsVal::Ref(reg) => self.loc_value(&Loc::Id(reg), state),
+                _ => AbsVal::Top,
+            },
+        }
+    }
+
+    fn assign(&mut self, loc: &Loc<Name>, value: AbsVal<B>, state: &mut State<B>) {
+        match loc {
+            Loc::Id(id) => {
+                if state.vars.contains_key(id) {
+                    state.vars.insert(*id, value);
+                } else if self.shared_state.registers.contains_key(id) {
+                    self.write_reg(*id, value, state)
+                } else if state.lets.contains_key(id) {
+                    state.lets.insert(*id, value);
+                } else {
+                    state.vars.insert(*id, value);
+                }
+            }
+            Loc::Field(inner, field) => {
+                let updated = match self.loc_value(inner, state) {
+                    AbsVal::Struct(mut fields) => {
+                        fields.insert(*field, value);
+                        AbsVal::Struct(fields)
+                    }
+                    _ => AbsVal::Top,
+                };
+                self.assign(inner, updated, state)
+            }
+            Loc::Addr(inner) => match self.loc_value(inner, state) {
+                AbsVal::Ref(reg) => self.assign(&Loc::Id(reg), value, state),
+                _ => {
+                    let references: Vec<Name> = self.references.iter().copied().collect();
+                    for reg in references {
+                        self.write_reg(reg, AbsVal::Top, state)
+                    }
+                }
+            },
+        }
+    }
+
+    fn eval(&mut self, exp: &Exp<Name>, state: &State<B>) -> AbsVal<B> {
+        use AbsVal::*;
+        match exp {
+            Exp::Id(id) => self.lookup(*id, state),
+            Exp::Ref(reg) => Ref(*reg),
+            Exp::Bool(b) => Bool(Some(*b)),
+            Exp::Bits(bv) => match B::new(bv.lower_u64(), bv.len()) {
+                bv if bv.len() <= B::MAX_WIDTH => Bits(KnownBits::exact(bv)),
+                _ => Top,
+            },
+            Exp::String(s) => String(Some(s.clone())),
+            Exp::Unit => Unit,
+            Exp::I64(n) => I64(Some(*n)),
+            Exp::I128(n) => I128(Some(*n)),
+            Exp::BigInt(_) => I128(None),
+            Exp::Undefined(ty) => AbsVal::top(ty, self.shared_state),
+            Exp::Struct(_, fields) => Struct(fields.iter().map(|(f, exp)| (*f, self.eval(exp, state))).collect()),
+            Exp::Kind(ctor, exp) => match self.eval(exp, state) {
+                Ctor(ctors) if !ctors.contains_key(ctor) => Bool(Some(true)),
+                Ctor(ctors) if ctors.len() == 1 => Bool(Some(false)),
+                _ => Bool(None),
+            },
+            Exp::Unwrap(ctor, exp) => match self.eval(exp, state) {
+                Ctor(mut ctors) => ctors.remove(ctor).unwrap_or(Top),
+                _ => Top,
+            },
+            Exp::Field(exp, field) => self.eval(exp, state).field(*field),
+            Exp::Call(op, args) => {
+                let args: Vec<AbsVal<B>> = args.iter().map(|arg| self.eval(arg, state)).collect();
+                self.op(op, args)
+            }
+        }
+    }
+
+    fn op(&mut self, op: &Op, args: Vec<AbsVal<B>>) -> AbsVal<B> {
+        use AbsVal::*;
+        if let Some(vals) = args.iter().map(AbsVal::to_val).collect::<Option<Vec<_>>>() {
+            return match eval_op(op, vals, &mut self.solver, SourceLoc::unknown()) {
+                Ok(v) => AbsVal::from_val(&v),
+                Err(_) => Top,
+            };
+        }
+        match (op, args.as_slice()) {
+            (Op::Not, [x]) => Bool(x.as_bool().map(|b| !b)),
+            (Op::And, [x, y]) => match (x.as_bool(), y.as_bool()) {
+                (Some(false), _) | (_, Some(false)) => Bool(Some(false)),
+                _ => Bool(None),
+            },
+            (Op::Or, [x, y]) => match (x.as_bool(), y.as_bool()) {
+                (Some(true), _) | (_, Some(true)) => Bool(Some(true)),
+                _ => Bool(None),
+            },
+            (Op::Eq, [x, y]) => Bool(x.equal(y)),
+            (Op::Neq, [x, y]) => Bool(x.equal(y).map(|b| !b)),
+            (Op::Lt | Op::Gt | Op::Lteq | Op::Gteq | Op::IsEmpty, _) => Bool(None),
+            (Op::Bvnot, [Bits(x)]) => Bits(x.bvnot()),
+            (Op::Bvand, [Bits(x), Bits(y)]) if x.len() == y.len() => Bits(x.bvand(*y)),
+            (Op::Bvor, [Bits(x), Bits(y)]) if x.len() == y.len() => Bits(x.bvor(*y)),
+            (Op::Bvxor, [Bits(x), Bits(y)]) if x.len() == y.len() => Bits(x.bvxor(*y)),
+            (Op::Bvadd, [Bits(x), Bits(y)]) if x.len() == y.len() => Bits(x.bvadd(*y)),
+            (Op::Bvsub, [Bits(x), Bits(y)]) if x.len() == y.len() => Bits(x.bvsub(*y)),
+            (Op::Concat, [Bits(x), Bits(y)]) => x.append(*y).map(Bits).unwrap_or(Top),
+            (Op::Slice(len), [Bits(x), n]) => n
+                .as_int()
+                .and_then(|n| x.slice(n.try_into().ok()?, *len))
+                .map(Bits)
+                .unwrap_or(AbsVal::unknown_bits(*len)),
+            (Op::SetSlice, [Bits(x), n, Bits(update)]) => n
+                .as_int()
+                .and_then(|n| x.set_slice(n.try_into().ok()?, *update))
+                .map(Bits)
+                .unwrap_or(AbsVal::unknown_bits(x.len())),
+            (Op::ZeroExtend(len), [Bits(x)]) => x.zero_extend(*len).map(Bits).unwrap_or(Top),
+            (Op::Bvaccess, [Bits(x), n]) => {
+                n.as_int().and_then(|n| x.slice(n.try_into().ok()?, 1)).map(Bits).unwrap_or(AbsVal::unknown_bits(1))
+            }
+            (Op::Add | Op::Sub, [I64(_), I64(_)]) => I64(None),
+            (Op::Add | Op::Sub, [I128(_), I128(_)]) => I128(None),
+            _ => Top,
+        }
+    }
+
+    fn unary(&mut self, f: Unary<B>, x: AbsVal<B>, info: SourceLoc) -> AbsVal<B> {
+        use AbsVal::*;
+        let name = self.primop_name(f as usize).to_string();
+        if output_primop(&name) {
+            return Unit;
+        }
+        if let Some(x) = x.to_val() {
+            return f(x, &mut self.solver, info).map(|v| AbsVal::from_val(&v)).unwrap_or(Top);
+        }
+        match (name.as_str(), x) {
+            ("not", x) => Bool(x.as_bool().map(|b| !b)),
+            ("not_bits" | "not_vec", Bits(x)) => Bits(x.bvnot()),
+            ("length", Bits(x)) => I128(Some(x.len() as i128)),
+            ("sail_unsigned" | "sail_signed", Bits(_)) => I128(None),
+            ("bit_to_bool", Bits(x)) => Bool(x.bit(0)),
+            _ => Top,
+        }
+    }
+
+    fn binary(&mut self, f: Binary<B>, x: AbsVal<B>, y: AbsVal<B>, info: SourceLoc) -> AbsVal<B> {
+        use AbsVal::*;
+        let name = self.primop_name(f as usize).to_string();
+        if output_primop(&name) {
+            return Unit;
+        }
+        if let (Some(x), Some(y)) = (x.to_val(), y.to_val()) {
+            return f(x, y, &mut self.solver, info).map(|v| AbsVal::from_val(&v)).unwrap_or(Top);
+        }
+        let as_u32 = |n: &AbsVal<B>| n.as_int().and_then(|n| u32::try_from(n).ok());
+        match (name.as_str(), &x, &y) {
+            ("and_bool" | "strict_and_bool", _, _) => match (x.as_bool(), y.as_bool()) {
+                (Some(false), _) | (_, Some(false)) => Bool(Some(false)),
+                _ => Bool(None),
+            },
+            ("or_bool" | "strict_or_bool", _, _) => match (x.as_bool(), y.as_bool()) {
+                (Some(true), _) | (_, Some(true)) => Bool(Some(true)),
+                _ => Bool(None),
+            },
+            ("eq_bit" | "eq_bits" | "eq_anything" | "eq_bool" | "eq_int" | "eq_string", _, _) => Bool(x.equal(&y)),
+            ("neq_bits" | "neq_anything" | "neq_bool" | "neq_int", _, _) => Bool(x.equal(&y).map(|b| !b)),
+            ("and_bits" | "and_vec", Bits(x), Bits(y)) if x.len() == y.len() => Bits(x.bvand(*y)),
+            ("or_bits" | "or_vec", Bits(x), Bits(y)) if x.len() == y.len() => Bits(x.bvor(*y)),
+            ("xor_bits" | "xor_vec", Bits(x), Bits(y)) if x.len() == y.len() => Bits(x.bvxor(*y)),
+            ("add_bits" | "add_vec", Bits(x), Bits(y)) if x.len() == y.len() => Bits(x.bvadd(*y)),
+            ("sub_bits" | "sub_vec", Bits(x), Bits(y)) if x.len() == y.len() => Bits(x.bvsub(*y)),
+            ("add_bits_int" | "add_vec_int", Bits(x), n) => match n.as_int() {
+                Some(n) => Bits(x.bvadd(KnownBits::exact(B::zeros(x.len()).add_i128(n)))),
+                None => Bits(KnownBits::unknown(x.len())),
+            },
+            ("sub_bits_int" | "sub_vec_int", Bits(x), n) => match n.as_int() {
+                Some(n) => Bits(x.bvsub(KnownBits::exact(B::zeros(x.len()).add_i128(n)))),
+                None => Bits(KnownBits::unknown(x.len())),
+            },
+            ("append" | "append_64", Bits(x), Bits(y)) => x.append(*y).map(Bits).unwrap_or(Top),
+            ("vector_access" | "bitvector_access", Bits(x), n) => {
+                as_u32(n).and_then(|n| x.slice(n, 1)).map(Bits).unwrap_or(AbsVal::unknown_bits(1))
+            }
+            ("vector_access", Vector(xs), n) => match n.as_int() {
+                Some(n) => usize::try_from(n).ok().and_then(|n| xs.get(n)).cloned().unwrap_or(Top),
+                None => xs.iter().skip(1).fold(xs.first().cloned().unwrap_or(Top), |acc, x| acc.join(x)),
+            },
+            ("zero_extend" | "zeros_extend", Bits(x), n) => {
+                as_u32(n).and_then(|n| x.zero_extend(n)).map(Bits).unwrap_or(Top)
+            }
+            ("sign_extend", Bits(x), n) => as_u32(n).and_then(|n| x.sign_extend(n)).map(Bits).unwrap_or(Top),
+            ("sail_truncate", Bits(x), n) => match as_u32(n) {
+                Some(n) => x.slice(0, n).map(Bits).unwrap_or(Top),
+                None => Top,
+            },
+            ("sail_truncateLSB", Bits(x), n) => match as_u32(n) {
+                Some(n) if n <= x.len() => x.slice(x.len() - n, n).map(Bits).unwrap_or(Top),
+                _ => Top,
+            },
+            ("shiftl" | "sail_shiftleft", Bits(x), n) => match n.as_int() {
+                Some(n) => Bits(x.shiftl(n)),
+                None => Bits(KnownBits::unknown(x.len())),
+            },
+            ("shiftr" | "sail_shiftright", Bits(x), n) => match n.as_int() {
+                Some(n) => Bits(x.shiftr(n)),
+                None => Bits(KnownBits::unknown(x.len())),
+            },
+            ("replicate_bits", Bits(x), n) => n.as_int().and_then(|n| x.replicate(n)).map(Bits).unwrap_or(Top),
+            ("cons", _, List(xs)) => {
+                let mut list = vec![x.clone()];
+                list.extend(xs.iter().cloned());
+                List(list)
+            }
+            (
+                "lt" | "gt" | "lteq" | "gteq" | "lt_int" | "gt_int" | "lteq_int" | "gteq_int" | "eq_real" | "lt_real"
+                | "gt_real" | "lteq_real" | "gteq_real",
+                _,
+                _,
+            ) => Bool(None),
+            _ => Top,
+        }
+    }
+
+    fn record_access(&mut self, site: (Name, usize), access: MemoryAccess<B>) {
+        match self.memory_index.get(&site) {
+            Some(i) => {
+                let old = &mut self.footprint.memory[*i];
+                old.address = old.address.join(&access.address);
+                old.bytes = old.bytes.join(&access.bytes);
+                old.data = match (&old.data, &access.data) {
+                    (Some(x), Some(y)) => Some(x.join(y)),
+                    _ => None,
+                }
+            }
+            None => {
+                self.memory_index.insert(site, self.footprint.memory.len());
+                self.footprint.memory.push(access)
+            }
+        }
+    }
+
+    fn variadic(&mut self, site: (Name, usize), f: Variadic<B>, args: Vec<AbsVal<B>>, info: SourceLoc) -> AbsVal<B> {
+        use AbsVal::*;
+        let name = self.primop_name(f as usize).to_string();
+        if let Some((kind, address, bytes)) = memory_primop(&name) {
+            let arg = |n: usize| args.get(n).cloned().unwrap_or(Top);
+            let data = match kind {
+                AccessKind::Write => Some(arg(bytes.unwrap() + 1)),
+                AccessKind::WriteTag => Some(arg(address + 1)),
+                _ => None,
+            };
+            let bytes_val = bytes.map(arg).unwrap_or(Unit);
+            self.record_access(
+                site,
+                MemoryAccess { kind, address: arg(address), bytes: bytes_val.clone(), data, info },
+            );
+            return match kind {
+                AccessKind::Read => match bytes_val.as_int() {
+                    Some(bytes) => u32::try_from(bytes * 8).map(AbsVal::unknown_bits).unwrap_or(Top),
+                    None => Top,
+                },
+                AccessKind::ReadTag => Bool(None),
+                AccessKind::Write => Bool(None),
+                AccessKind::WriteTag => Unit,
+            };
+        }
+        if output_primop(&name) || name.is_empty() {
+            return if name.is_empty() { Top } else { Unit };
+        }
+        if let Some(vals) = args.iter().map(AbsVal::to_val).collect::<Option<Vec<_>>>() {
+            return f(vals, &mut self.solver, &mut self.frame, info).map(|v| AbsVal::from_val(&v)).unwrap_or(Top);
+        }
+        let as_u32 = |n: &AbsVal<B>| n.as_int().and_then(|n| u32::try_from(n).ok());
+        match (name.as_str(), args.as_slice()) {
+            ("slice", [Bits(x), from, len]) => match (as_u32(from), as_u32(len)) {
+                (Some(from), Some(len)) => x.slice(from, len).map(Bits).unwrap_or(Top),
+                (None, Some(len)) => AbsVal::unknown_bits(len),
+                _ => Top,
+            },
+            ("vector_subrange" | "subrange_bits", [Bits(x), high, low]) => match (as_u32(high), as_u32(low)) {
+                (Some(high), Some(low)) if low <= high => x.slice(low, high - low + 1).map(Bits).unwrap_or(Top),
+                _ => Top,
+            },
+            ("vector_update_subrange" | "update_subrange_bits", [Bits(x), _, low, Bits(update)]) => as_u32(low)
+                .and_then(|low| x.set_slice(low, *update))
+                .map(Bits)
+                .unwrap_or(Bits(KnownBits::unknown(x.len()))),
+            ("bitvector_update", [Bits(x), n, Bits(bit)]) => {
+                as_u32(n).and_then(|n| x.set_slice(n, *bit)).map(Bits).unwrap_or(Bits(KnownBits::unknown(x.len())))
+            }
+            ("vector_update", [Vector(xs), n, v]) => match as_u32(n) {
+                Some(n) if (n as usize) < xs.len() => {
+                    let mut xs = xs.clone();
+                    xs[n as usize] = v.clone();
+                    Vector(xs)
+                }
+                _ => Vector(xs.iter().map(|x| x.join(v)).collect()),
+            },
+            ("set_slice", [_, _, Bits(x), n, Bits(update)]) => {
+                as_u32(n).and_then(|n| x.set_slice(n, *update)).map(Bits).unwrap_or(Bits(KnownBits::unknown(x.len())))
+            }
+            ("get_slice_int", [len, _, _]) => as_u32(len).map(AbsVal::unknown_bits).unwrap_or(Top),
+            _ => Top,
+        }
+    }
+
+    fn special(&mut self, f: Name, mut args: Vec<AbsVal<B>>, state: &mut State<B>) -> AbsVal<B> {
+        use AbsVal::*;
+        if f == INTERNAL_VECTOR_INIT && args.len() == 1 {
+            match args[0].as_int() {
+                Some(len) if (0..=1 << 16).contains(&len) => Vector(vec![Top; len as usize]),
+                _ => Top,
+            }
+        } else if f == INTERNAL_VECTOR_UPDATE && args.len() == 3 {
+            let v = args.pop().unwrap();
+            let n = args.pop().unwrap();
+            match (args.pop().unwrap(), n.as_int()) {
+                (Vector(mut xs), Some(n)) if 0 <= n && (n as usize) < xs.len() => {
+                    xs[n as usize] = v;
+                    Vector(xs)
+                }
+                (Vector(xs), _) => Vector(xs.iter().map(|x| x.join(&v)).collect()),
+                _ => Top,
+            }
+        } else if f == RESET_REGISTERS {
+            let shared_state = self.shared_state;
+            let resets = shared_state
+                .reset_registers
+                .iter()
+                .filter(|(loc, _)| !self.reset_registers.contains_key(loc))
+                .map(|(loc, reset)| (loc, reset))
+                .chain(self.reset_registers.iter());
+            for (loc, reset) in resets {
+                let value = match reset(self.frame.memory(), shared_state.typedefs(), &mut self.solver) {
+                    Ok(value) => AbsVal::from_val(&value),
+                    Err(_) => Top,
+                };
+                self.assign(loc, value, state)
+            }
+            Unit
+        } else if f == ITE_PHI {
+            let mut value: Option<AbsVal<B>> = None;
+            for pair in args.chunks_exact(2) {
+                if pair[0].as_bool() != Some(false) {
+                    value = Some(match value {
+                        Some(value) => value.join(&pair[1]),
+                        None => pair[1].clone(),
+                    })
+                }
+            }
+            value.unwrap_or(Top)
+        } else if f == REG_DEREF && args.len() == 1 {
+            match &args[0] {
+                Ref(reg) if self.shared_state.registers.contains_key(reg) => self.read_reg(*reg, state),
+                _ => {
+                    let references: Vec<Name> = self.references.iter().copied().collect();
+                    for reg in references {
+                        self.read_reg(reg, state);
+                    }
+                    Top
+                }
+            }
+        } else if f == READ_REGISTER_FROM_VECTOR && args.len() == 2 {
+            match (&args[0], &args[1]) {
+                (n, Vector(regs)) => {
+                    let n = match n {
+                        Bits(bits) => bits.value().and_then(|bv| usize::try_from(bv.lower_u64()).ok()),
+                        n => n.as_int().and_then(|n| usize::try_from(n).ok()),
+                    };
+                    let regs: Vec<Name> = match n {
+                        Some(n) => regs.get(n).into_iter().cloned().collect(),
+                        None => regs.to_vec(),
+                    }
+                    .into_iter()
+                    .filter_map(|reg| if let Ref(reg) = reg { Some(reg) } else { None })
+                    .collect();
+                    let mut value: Option<AbsVal<B>> = None;
+                    for reg in regs {
+                        let v = self.read_reg(reg, state);
+                        value = Some(value.map(|value| value.join(&v)).unwrap_or(v))
+                    }
+                    value.unwrap_or(Top)
+                }
+                _ => Top,
+            }
+        } else if f == WRITE_REGISTER_FROM_VECTOR && args.len() == 3 {
+            if let Vector(regs) = &args[2] {
+                let n = match &args[0] {
+                    Bits(bits) => bits.value().and_then(|bv| usize::try_from(bv.lower_u64()).ok()),
+                    n => n.as_int().and_then(|n| usize::try_from(n).ok()),
+                };
+                match n.and_then(|n| regs.get(n)) {
+                    Some(Ref(reg)) => self.write_reg(*reg, args[1].clone(), state),
+                    _ => {
+                        for reg in regs {
+                            if let Ref(reg) = reg {
+                                let old = state.regs.get(reg).cloned().unwrap_or(Top);
+                                self.write_reg(*reg, old.join(&args[1]), state)
+                            }
+                        }
+                    }
+                }
+            }
+            Unit
+        } else if f == ABSTRACT_CALL || f == ABSTRACT_PRIMOP {
+            if let Some(Ref(abstracted_fn)) = args.last() {
+                if self.shared_state.block_functions.contains_key(abstracted_fn) {
+                    let site = (*abstracted_fn, usize::MAX);
+                    let access = MemoryAccess {
+                        kind: AccessKind::Write,
+                        address: Top,
+                        bytes: Top,
+                        data: None,
+                        info: SourceLoc::unknown(),
+                    };
+                    self.record_access(site, access)
+                }
+            }
+            Top
+        } else if f == INSTR_ANNOUNCE {
+            Unit
+        } else if self.shared_state.type_info.union_ctors.contains(&f) && args.len() == 1 {
+            Ctor(HashMap::from([(f, args.pop().unwrap())]))
+        } else {
+            Top
+        }
+    }
+
+    /// Account for a function we are not going to interpret, using
+    /// everything its static footprint could read or write.
+    fn summarize(&mut self, f: Name, ret_ty: &Ty<Name>, state: &mut State<B>) -> AbsVal<B> {
+        let shared_state = self.shared_state;
+        let footprint = static_footprint(f, shared_state);
+        self.footprint.summarized.insert(f);
+        for reg in footprint.reads.iter().chain(footprint.references.iter()) {
+            self.read_reg(*reg, state);
+        }
+        for reg in footprint.writes.iter().chain(footprint.references.iter()) {
+            self.write_reg(*reg, AbsVal::Top, state)
+        }
+        for g in &footprint.functions {
+            let (_, _, instrs) = &shared_state.functions[g];
+            for (pc, instr) in instrs.iter().enumerate() {
+                let loc = match instr {
+                    Instr::PrimopVariadic(loc, p, _, info) => {
+                        if let Some((kind, _, _)) = memory_primop(self.primop_name(*p as usize)) {
+                            let data = if kind == AccessKind::Write || kind == AccessKind::WriteTag {
+                                Some(AbsVal::Top)
+                            } else {
+                                None
+                            };
+                            let bytes = AbsVal::Top;
+                            let access = MemoryAccess { kind, address: AbsVal::Top, bytes, data, info: *info };
+                            self.record_access((*g, pc), access)
+                        }
+                        loc
+                    }
+                    Instr::Copy(loc, _, _)
+                    | Instr::Call(loc, _, _, _, _)
+                    | Instr::PrimopUnary(loc, _, _, _)
+                    | Instr::PrimopBinary(loc, _, _, _, _)
+                    | Instr::PrimopReset(loc, _, _) => loc,
+                    _ => continue,
+                };
+                let id = loc.id();
+                if let Some(value) = state.lets.get_mut(&id) {
+                    *value = AbsVal::Top
+                }
+            }
+        }
+        AbsVal::top(ret_ty, shared_state)
+    }
+
+    fn call(&mut self, f: Name, args: Vec<AbsVal<B>>, state: &mut State<B>) -> Result<Option<AbsVal<B>>, String> {
+        let shared_state = self.shared_state;
+        let Some((params, ret_ty, instrs)) = shared_state.functions.get(&f) else {
+            return Ok(Some(self.special(f, args, state)));
+        };
+        if shared_state.function_stubs.contains_key(&f) {
+            return Ok(Some(AbsVal::top(ret_ty, shared_state)));
+        }
+        if self.stack.contains(&f) || self.stack.len() >= MAX_CALL_DEPTH {
+            return Ok(Some(self.summarize(f, ret_ty, state)));
+        }
+        let mut vars: HashMap<Name, AbsVal<B>> = params.iter().map(|(p, _)| *p).zip(args).collect();
+        vars.insert(RETURN, AbsVal::top(ret_ty, shared_state));
+        let entry = State { vars, lets: mem::take(&mut state.lets), regs: mem::take(&mut state.regs) };
+        self.stack.push(f);
+        let exit = self.function(f, instrs, entry)?;
+        self.stack.pop();
+        Ok(exit.map(|mut exit| {
+            state.lets = exit.lets;
+            state.regs = exit.regs;
+            exit.vars.remove(&RETURN).unwrap_or(AbsVal::Top)
+        }))
+    }
+
+    fn step(&mut self, f: Name, pc: usize, instr: &Instr<Name, B>, state: &mut State<B>) -> Result<Flow, String> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(format!("Abstract interpretation exceeded {} steps", MAX_STEPS));
+        }
+        match instr {
+            Instr::Decl(id, ty, _) => {
+                let value = AbsVal::top(ty, self.shared_state);
+                state.vars.insert(*id, value);
+            }
+            Instr::Init(id, _, exp, _) => {
+                let value = self.eval(exp, state);
+                state.vars.insert(*id, value);
+            }
+            Instr::Jump(exp, target, _) => {
+                return Ok(match self.eval(exp, state).as_bool() {
+                    Some(true) => Flow::Goto(*target),
+                    Some(false) => Flow::Next,
+                    None => Flow::Branch(*target),
+                })
+            }
+            Instr::Goto(target) => return Ok(Flow::Goto(*target)),
+            Instr::Copy(loc, exp, _) => {
+                let value = self.eval(exp, state);
+                self.assign(loc, value, state)
+            }
+            Instr::Monomorphize(..) => (),
+            Instr::Call(loc, _, g, args, _) => {
+                let args = args.iter().map(|arg| self.eval(arg, state)).collect();
+                match self.call(*g, args, state)? {
+                    Some(value) => self.assign(loc, value, state),
+                    None => return Ok(Flow::Stop),
+                }
+            }
+            Instr::PrimopUnary(loc, p, arg, info) => {
+                let arg = self.eval(arg, state);
+                let value = self.unary(*p, arg, *info);
+                self.assign(loc, value, state)
+            }
+            Instr::PrimopBinary(loc, p, arg1, arg2, info) => {
+                let arg1 = self.eval(arg1, state);
+                let arg2 = self.eval(arg2, state);
+                let value = self.binary(*p, arg1, arg2, *info);
+                self.assign(loc, value, state)
+            }
+            Instr::PrimopVariadic(loc, p, args, info) => {
+                let args = args.iter().map(|arg| self.eval(arg, state)).collect();
+                let value = self.variadic((f, pc), *p, args, *info);
+                self.assign(loc, value, state)
+            }
+            Instr::PrimopReset(loc, reset, _) => {
+                let value = match reset(self.frame.memory(), self.shared_state.typedefs(), &mut self.solver) {
+                    Ok(value) => AbsVal::from_val(&value),
+                    Err(_) => AbsVal::Top,
+                };
+                self.assign(loc, value, state)
+            }
+            Instr::Exit(_, _) => return Ok(Flow::Stop),
+            Instr::Arbitrary => {
+                state.vars.insert(RETURN, AbsVal::Top);
+                return Ok(Flow::Return);
+            }
+            Instr::End => return Ok(Flow::Return),
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Interpret a function body from an entry state, returning the
+    /// join of the states at every `End`, or `None` if the function
+    /// cannot return.
+    fn function(&mut self, f: Name, instrs: &[Instr<Name, B>], entry: State<B>) -> Result<Option<State<B>>, String> {
+        let mut leaders = HashSet::new();
+        for (pc, instr) in instrs.iter().enumerate() {
+            match instr {
+                Instr::Jump(_, target, _) => {
+                    leaders.insert(*target);
+                    leaders.insert(pc + 1);
+                }
+                Instr::Goto(target) => {
+                    leaders.insert(*target);
+                }
+                _ => (),
+            }
+        }
+
+        let mut states: HashMap<usize, State<B>> = HashMap::from([(0, entry)]);
+        let mut worklist = BTreeSet::from([0]);
+        let mut exit: Option<State<B>> = None;
+
+        let propagate = |states: &mut HashMap<usize, State<B>>, worklist: &mut BTreeSet<usize>, pc, state| match states
+            .get_mut(&pc)
+        {
+            Some(old) => {
+                if old.join(&state) {
+                    worklist.insert(pc);
+                }
+            }
+            None => {
+                states.insert(pc, state);
+                worklist.insert(pc);
+            }
+        };
+
+        while let Some(start) = worklist.iter().next().copied() {
+            worklist.remove(&start);
+            let mut state = states[&start].clone();
+            let mut pc = start;
+            while pc < instrs.len() {
+                match self.step(f, pc, &instrs[pc], &mut state)? {
+                    Flow::Next => {
+                        pc += 1;
+                        if leaders.contains(&pc) {
+                            propagate(&mut states, &mut worklist, pc, state);
+                            break;
+                        }
+                    }
+                    Flow::Goto(target) => {
+                        propagate(&mut states, &mut worklist, target, state);
+                        break;
+                    }
+                    Flow::Branch(target) => {
+                        propagate(&mut states, &mut worklist, target, state.clone());
+                        propagate(&mut states, &mut worklist, pc + 1, state);
+                        break;
+                    }
+                    Flow::Return => {
+                        match &mut exit {
+                            Some(exit) => {
+                                exit.join(&state);
+                            }
+                            None => exit = Some(state),
+                        }
+                        break;
+                    }
+                    Flow::Stop => break,
+                }
+            }
+        }
+
+        Ok(exit)
+    }
+}
+
+/// Abstractly interpret `function` applied to `args`, starting from
+/// the registers and lets in `frame`. The `reset_registers` are any
+/// additional register resets to apply on top of those in the
+/// architecture when the model resets its registers.
+pub fn abstract_footprint<'ir, B: BV>(
+    function: Name,
+    args: &[Val<B>],
+    frame: &LocalFrame<'ir, B>,
+    reset_registers: &HashMap<Loc<Name>, Reset<B>>,
+    shared_state: &SharedState<'ir, B>,
+) -> Result<AbstractFootprint<B>, String> {
+    let Some((params, ret_ty, instrs)) = shared_state.functions.get(&function) else {
+        return Err(format!("Function {} has no body", zencode::decode(shared_state.symtab.to_str(function))));
+    };
+
+    // Primitives are identified by their function pointers. Identical
+    // functions can be merged by the compiler, in which case we make
+    // sure any memory primitive keeps its name so it is never run.
+    let mut primops: HashMap<usize, String> = HashMap::new();
+    let Primops { unary, binary, variadic, .. } = Primops::<B>::default();
+    let pointers = unary
+        .into_iter()
+        .map(|(name, f)| (f as usize, name))
+        .chain(binary.into_iter().map(|(name, f)| (f as usize, name)))
+        .chain(variadic.into_iter().map(|(name, f)| (f as usize, name)));
+    for (f, name) in pointers {
+        if !matches!(primops.get(&f), Some(name) if memory_primop(name).is_some() || output_primop(name)) {
+            primops.insert(f, name);
+        }
+    }
+
+    let mut entry = State { vars: HashMap::new(), lets: HashMap::new(), regs: HashMap::new() };
+    for (name, reg) in frame.regs().iter() {
+        let value = match reg.read_last_if_initialized() {
+            Some(value) => AbsVal::from_val(value),
+            None => AbsVal::top(&shared_state.registers[name], shared_state),
+        };
+        entry.regs.insert(*name, value);
+    }
+    for (id, value) in frame.lets().iter() {
+        let value = match value {
+            UVal::Init(value) => AbsVal::from_val(value),
+            UVal::Uninit(ty) => AbsVal::top(ty, shared_state),
+        };
+        entry.lets.insert(*id, value);
+    }
+    entry.vars.insert(RETURN, AbsVal::top(ret_ty, shared_state));
+    for ((param, ty), arg) in params.iter().zip(args.iter().map(Some).chain(std::iter::repeat(None))) {
+        let value = match arg {
+            Some(arg) => AbsVal::from_val(arg),
+            None => AbsVal::top(ty, shared_state),
+        };
+        entry.vars.insert(*param, value);
+    }
+
+    let cfg = Config::new();
+    let ctx = Context::new(cfg);
+    let mut interp = Interp {
+        shared_state,
+        solver: Solver::new(&ctx),
+        frame: LocalFrame::new(function, &[], &Ty::Unit, None, &[]),
+        primops,
+        reset_registers,
+        references: static_footprint(function, shared_state).references,
+        stack: vec![function],
+        steps: 0,
+        footprint: AbstractFootprint {
+            reads: HashMap::new(),
+            writes: HashMap::new(),
+            memory: Vec::new(),
+            summarized: HashSet::new(),
+        },
+        memory_index: HashMap::new(),
+    };
+    interp.frame.set_memory(frame.memory().clone());
+    interp.function(function, instrs, entry)?;
+    log!(log::VERBOSE, format!("Abstract interpretation of {} took {} steps", interp.name(function), interp.steps));
+    Ok(interp.footprint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::executor::start_single;
+    use crate::ir_parse::parse_ir;
+    use crate::register::RegisterBindings;
+    use crate::smt::Event;
+    use std::sync::Mutex;
+
+    fn bits(s: &str) -> KnownBits<B64> {
+        let len = s.len() as u32;
+        let mut known = 0;
+        let mut value = 0;
+        for (i, c) in s.chars().rev().enumerate() {
+            match c {
+                '0' => known |= 1 << i,
+                '1' => {
+                    known |= 1 << i;
+                    value |= 1 << i
+                }
+                _ => (),
+            }
+        }
+        KnownBits { known: B64::new(known, len), bits: B64::new(value, len) }
+    }
+
+    #[test]
+    fn known_bits_logic() {
+        assert_eq!(bits("1?0?").bvand(bits("110?")), bits("1?0?"));
+        assert_eq!(bits("1?0?").bvand(bits("0000")), bits("0000"));
+        assert_eq!(bits("1?0?").bvor(bits("?1?0")), bits("11??"));
+        assert_eq!(bits("1?0?").bvxor(bits("1100")), bits("0?0?"));
+        assert_eq!(bits("1?0?").bvnot(), bits("0?1?"));
+        assert_eq!(bits("1?01").join(bits("1?11")), bits("1??1"));
+    }
+
+    #[test]
+    fn known_bits_arith() {
+        assert_eq!(bits("??01").bvadd(bits("0001")), bits("??10"));
+        assert_eq!(bits("?1?1").bvadd(bits("0001")), bits("???0"));
+        assert_eq!(bits("1111").bvadd(bits("0001")), bits("0000"));
+        assert_eq!(bits("??00").bvsub(bits("0001")), bits("??11"));
+    }
+
+    #[test]
+    fn known_bits_equal() {
+        assert_eq!(bits("1?").equal(bits("0?")), Some(false));
+        assert_eq!(bits("1?").equal(bits("1?")), None);
+        assert_eq!(bits("10").equal(bits("10")), Some(true));
+    }
+
+    #[test]
+    fn known_bits_resize() {
+        assert_eq!(bits("?1").zero_extend(4), Some(bits("00?1")));
+        assert_eq!(bits("?1").sign_extend(4), Some(bits("???1")));
+        assert_eq!(bits("1?").sign_extend(4), Some(bits("111?")));
+        assert_eq!(bits("1?").append(bits("0")), Some(bits("1?0")));
+        assert_eq!(bits("1?01").slice(1, 2), Some(bits("?0")));
+        assert_eq!(bits("????").set_slice(1, bits("10")), Some(bits("?10?")));
+        assert_eq!(bits("1?01").shiftl(1), bits("?010"));
+        assert_eq!(bits("1?01").shiftr(2), bits("001?"));
+        assert_eq!(bits("").to_string(), "#b");
+        assert_eq!(bits("0000????").to_string(), "#x0?");
+        assert_eq!(bits("1?01").to_string(), "#b1?01");
+    }
+
+    /// `zf` takes a branch on its argument. One side increments `R1`
+    /// in a loop, and the other reads memory at the address in `R0`,
+    /// stores the result at a fixed address, and calls the recursive
+    /// `zcount`, which copies `R0` to `R2` and reads memory.
+    const FIXTURE: &str = r#"
+enum zread_kind { zRead_plain }
+enum zwrite_kind { zWrite_plain }
+register zR0 : %bv64
+register zR1 : %bv64
+register zR2 : %bv64
+val zplatform_read_mem = "platform_read_mem" : (%enum zread_kind, %i, %bv, %i) -> %bv
+val zplatform_write_mem = "platform_write_mem" : (%enum zwrite_kind, %i, %bv, %i, %bv) -> %bool
+val zcount : (%bv8) -> %unit
+fn zcount(zn) {
+  zu : %unit;
+  zv : %bv32;
+  jump @eq(zn, 0x00) goto 6;
+  zR2 = zR0;
+  zv = zplatform_read_mem(zRead_plain, 64, zR0, 4);
+  zu = zcount(@bvsub(zn, 0x01));
+  return = ();
+  end
+}
+val zf : (%bv8) -> %bv8
+fn zf(zx) {
+  zi : %bv8 = 0x00;
+  zb : %bv64;
+  zw : %bool;
+  zu : %unit;
+  jump @eq(zx, 0x01) goto 9;
+  jump @eq(zi, 0x03) goto 12;
+  zR1 = @bvadd(zR1, 0x0000000000000001);
+  zi = @bvadd(zi, 0x01);
+  goto 5;
+  zb = zplatform_read_mem(zRead_plain, 64, zR0, 8);
+  zw = zplatform_write_mem(zWrite_plain, 64, 0x0000000000001000, 8, zb);
+  zu = zcount(0x02);
+  return = zi;
+  end
+}
+"#;
+
+    struct FixtureRun {
+        footprint: AbstractFootprint<B64>,
+        /// The events of each symbolic path
+        traces: Vec<Vec<Event<B64>>>,
+        /// The names of the registers read and written, and the
+        /// functions summarized, by the abstract interpreter
+        reads: Vec<String>,
+        writes: Vec<String>,
+        summarized: Vec<String>,
+    }
+
+    /// Run `zf` in [FIXTURE] on `args` both abstractly and symbolically
+    fn run_fixture(args: &[Val<B64>]) -> FixtureRun {
+        let mut symtab = Symtab::new();
+        let mut defs = parse_ir::<B64>(FIXTURE, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let mut externs = HashMap::new();
+        for def in &defs {
+            if let Def::Extern(f, is_abstract, ext, _, _) = def {
+                externs.insert(*f, (ext.to_string(), *is_abstract));
+            }
+        }
+        let primops = Primops::default();
+        for def in defs.iter_mut() {
+            if let Def::Fn(_, _, instrs) = def {
+                *instrs = instrs.drain(..).map(|instr| insert_instr_primops(instr, &externs, &primops)).collect()
+            }
+        }
+
+        let type_info = IRTypeInfo::new(&defs);
+        let f = symtab.lookup("zf");
+        let shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let mut regs = RegisterBindings::new();
+        for (reg, ty) in &shared_state.registers {
+            regs.insert(*reg, false, UVal::Uninit(ty))
+        }
+        let (params, ret_ty, instrs) = shared_state.functions.get(&f).unwrap();
+        let args = if args.is_empty() { None } else { Some(args) };
+        let mut frame = LocalFrame::new(f, params, ret_ty, args, instrs);
+        frame.add_regs(&regs);
+
+        let footprint = abstract_footprint(f, args.unwrap_or(&[]), &frame, &HashMap::new(), &shared_state).unwrap();
+
+        let task_state = crate::executor::TaskState::new();
+        let traces = Mutex::new(Vec::new());
+        start_single(frame.task(0, &task_state), &shared_state, &traces, &|_, _, result, _, solver, traces| {
+            assert!(result.is_ok());
+            let mut events: Vec<Event<B64>> = solver.trace().to_vec().into_iter().cloned().collect();
+            events.reverse();
+            traces.lock().unwrap().push(events)
+        });
+        let names = |names: Vec<&Name>| {
+            let mut names: Vec<String> =
+                names.into_iter().map(|n| shared_state.symtab.to_str(*n).to_string()).collect();
+            names.sort();
+            names
+        };
+        FixtureRun {
+            reads: names(footprint.reads.keys().collect()),
+            writes: names(footprint.writes.keys().collect()),
+            summarized: names(footprint.summarized.iter().collect()),
+            footprint,
+            traces: traces.into_inner().unwrap(),
+        }
+    }
+
+    /// Whether an abstract value includes a value from a trace. Values
+    /// which are not concrete are always included.
+    fn covers(abs: &AbsVal<B64>, val: &Val<B64>) -> bool {
+        let val = AbsVal::from_val(val);
+        val == AbsVal::Top || abs.join(&val) == *abs
+    }
+
+    /// Check every event of the symbolic traces is included in the
+    /// abstract footprint
+    fn assert_over_approximates(footprint: &AbstractFootprint<B64>, traces: &[Vec<Event<B64>>]) {
+        let accesses = |kind| footprint.memory.iter().filter(move |access| access.kind == kind);
+        for event in traces.iter().flatten() {
+            match event {
+                Event::ReadReg(reg, _, value, _) => assert!(covers(&footprint.reads[reg], value)),
+                Event::WriteReg(reg, _, value, _) => assert!(covers(&footprint.writes[reg], value)),
+                Event::ReadMem { address, bytes, .. } => assert!(accesses(AccessKind::Read).any(|access| {
+                    covers(&access.address, address) && covers(&access.bytes, &Val::I64(*bytes as i64))
+                })),
+                Event::WriteMem { address, data, bytes, .. } => assert!(accesses(AccessKind::Write).any(|access| {
+                    covers(&access.address, address)
+                        && covers(&access.bytes, &Val::I64(*bytes as i64))
+                        && matches!(&access.data, Some(abs) if covers(abs, data))
+                })),
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn abstract_fixture_symbolic() {
+        let FixtureRun { footprint, traces, reads, writes, summarized } = run_fixture(&[]);
+        assert_eq!(traces.len(), 2);
+        // The reads made by the recursive calls are only covered by
+        // the summary of zcount
+        assert!(traces.iter().flatten().any(|event| matches!(event, Event::ReadMem { bytes: 4, .. })));
+        assert_over_approximates(&footprint, &traces);
+        assert_eq!(reads, ["zR0", "zR1"]);
+        assert_eq!(writes, ["zR1", "zR2"]);
+        assert_eq!(summarized, ["zcount"]);
+        assert!(footprint.memory.iter().any(|access| access.kind == AccessKind::Write
+            && access.address == AbsVal::Bits(KnownBits::exact(B64::new(0x1000, 64)))
+            && access.bytes == AbsVal::I64(Some(8))))
+    }
+
+    #[test]
+    fn abstract_fixture_concrete() {
+        // Only the memory side of the branch is taken, so R1 is
+        // neither read nor written
+        let FixtureRun { footprint, traces, reads, writes, .. } = run_fixture(&[Val::Bits(B64::new(1, 8))]);
+        assert_eq!(traces.len(), 1);
+        assert_over_approximates(&footprint, &traces);
+        assert_eq!(reads, ["zR0"]);
+        assert_eq!(writes, ["zR2"]);
+
+        // On the other side the loop is unrolled abstractly until it
+        // reaches a fixed point, and no memory is accessed
+        let FixtureRun { footprint, traces, reads, writes, summarized } = run_fixture(&[Val::Bits(B64::new(0, 8))]);
+        assert_eq!(traces.len(), 1);
+        assert_over_approximates(&footprint, &traces);
+        assert_eq!(reads, ["zR1"]);
+        assert_eq!(writes, ["zR1"]);
+        assert!(footprint.memory.is_empty() && summarized.is_empty())
+    }
+}
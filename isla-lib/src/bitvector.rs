@@ -74,6 +74,7 @@ macro_rules! write_bits {
 
 pub mod b129;
 pub mod b64;
+pub mod length_check;
 
 /// If we are indexing a vector of length `n`, we require this many
 /// bits to represent all possible indices.
@@ -297,9 +298,15 @@ where
     fn set_slice(self, n: u32, update: Self) -> Self;
 
     fn extract(self, high: u32, low: u32) -> Option<Self> {
-        let len = (high - low) + 1;
+        #[cfg(feature = "length-checks")]
+        length_check::check(
+            low <= high && high < self.len(),
+            "extract",
+            &[self.len(), high, low],
+            "low <= high < length",
+        );
         if low <= high && high <= self.len() {
-            self.slice(low, len)
+            self.slice(low, (high - low) + 1)
         } else {
             None
         }
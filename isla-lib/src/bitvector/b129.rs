@@ -37,6 +37,8 @@ use std::fmt;
 use std::ops::{Add, BitAnd, BitOr, BitXor, Neg, Not, Shl, Shr, Sub};
 use std::u128;
 
+#[cfg(feature = "length-checks")]
+use super::length_check::check;
 use super::{bzhi_u128, bzhi_u64, BV};
 use crate::error::ExecError;
 
@@ -119,6 +121,8 @@ impl BitXor for B129 {
     type Output = Self;
 
     fn bitxor(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvxor", &[self.len, rhs.len], "operands of equal length");
         B129 { len: self.len, tag: self.tag ^ rhs.tag, bits: self.bits ^ rhs.bits }
     }
 }
@@ -127,6 +131,8 @@ impl BitOr for B129 {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvor", &[self.len, rhs.len], "operands of equal length");
         B129 { len: self.len, tag: self.tag | rhs.tag, bits: self.bits | rhs.bits }
     }
 }
@@ -135,6 +141,8 @@ impl BitAnd for B129 {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvand", &[self.len, rhs.len], "operands of equal length");
         B129 { len: self.len, tag: self.tag & rhs.tag, bits: self.bits & rhs.bits }
     }
 }
@@ -143,6 +151,8 @@ impl Add<B129> for B129 {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvadd", &[self.len, rhs.len], "operands of equal length");
         let (sum, carry) = self.bits.overflowing_add(rhs.bits);
         bzhi(B129 { len: self.len, tag: self.tag ^ rhs.tag ^ carry, bits: sum }, self.len)
     }
@@ -160,6 +170,8 @@ impl Sub<B129> for B129 {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvsub", &[self.len, rhs.len], "operands of equal length");
         self + (-rhs)
     }
 }
@@ -282,11 +294,19 @@ impl BV for B129 {
     }
 
     fn zero_extend(self, new_len: u32) -> Self {
+        #[cfg(feature = "length-checks")]
+        if check(self.len <= new_len && new_len <= 129, "zero_extend", &[self.len, new_len], "a longer length") {
+            return self;
+        }
         assert!(self.len <= new_len && new_len <= 129);
         B129 { len: new_len, tag: self.tag, bits: self.bits }
     }
 
     fn sign_extend(self, new_len: u32) -> Self {
+        #[cfg(feature = "length-checks")]
+        if check(self.len <= new_len && new_len <= 129, "sign_extend", &[self.len, new_len], "a longer length") {
+            return self;
+        }
         assert!(self.len <= new_len && new_len <= 129);
         if self.len == 0 {
             B129 { len: new_len, tag: false, bits: 0 }
@@ -328,6 +348,8 @@ impl BV for B129 {
     }
 
     fn slice(self, from: u32, len: u32) -> Option<Self> {
+        #[cfg(feature = "length-checks")]
+        check(from + len <= self.len, "slice", &[self.len, from, len], "a slice within the bitvector");
         if from + len <= self.len {
             Some(bzhi(B129 { len, ..self >> shift_u32(from, self.len) }, len))
         } else {
@@ -385,6 +407,11 @@ impl BV for B129 {
     }
 
     fn set_slice(self, n: u32, update: Self) -> Self {
+        #[cfg(feature = "length-checks")]
+        if check(n + update.len <= self.len, "set_slice", &[self.len, n, update.len], "an update within the bitvector")
+        {
+            return self;
+        }
         let mask = (ALL_ONES_129 << shift_u32(n, 129)).slice(0, n + update.len).unwrap().zero_extend(self.len);
         let update = update.zero_extend(self.len) << shift_u32(n, self.len);
         (self & !mask) | update
@@ -617,4 +644,25 @@ mod tests {
         assert_eq!(B129::from_str(all_ones_str).map(B129::to_vec), bit_vector_from_str(all_ones_str));
         assert_eq!(B129::from_str(cap_zero_str).map(B129::to_vec), bit_vector_from_str(cap_zero_str))
     }
+
+    #[cfg(feature = "length-checks")]
+    #[test]
+    fn test_length_checks() {
+        use crate::bitvector::length_check::*;
+        set_length_checks(true).unwrap();
+        let _ = take_length_error();
+        assert_eq!(B129::new(1, 8) + B129::new(1, 8), B129::new(2, 8));
+        assert_eq!(take_length_error(), None);
+        let _ = B129::new(1, 8) + B129::new(1, 16);
+        let _ = B129::new(1, 8).zero_extend(4);
+        assert_eq!(
+            take_length_error(),
+            Some(LengthError { op: "bvadd", lengths: vec![8, 16], expected: "operands of equal length" })
+        );
+        assert_eq!(B129::new(1, 8).zero_extend(4), B129::new(1, 8));
+        assert_eq!(take_length_error().map(|err| err.op), Some("zero_extend"));
+        assert_eq!(B129::new(1, 8).extract(8, 0), None);
+        assert_eq!(take_length_error().map(|err| err.op), Some("extract"));
+        set_length_checks(false).unwrap()
+    }
 }
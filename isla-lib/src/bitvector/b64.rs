@@ -36,6 +36,8 @@ use std::fmt;
 use std::ops::{Add, BitAnd, BitOr, BitXor, Neg, Not, Shl, Shr, Sub};
 use std::u128;
 
+#[cfg(feature = "length-checks")]
+use super::length_check::check;
 use super::{bzhi_u128, bzhi_u64, BV};
 use crate::error::ExecError;
 
@@ -88,6 +90,8 @@ impl BitXor for B64 {
     type Output = Self;
 
     fn bitxor(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvxor", &[self.len, rhs.len], "operands of equal length");
         B64 { len: self.len, bits: self.bits ^ rhs.bits }
     }
 }
@@ -96,6 +100,8 @@ impl BitOr for B64 {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvor", &[self.len, rhs.len], "operands of equal length");
         B64 { len: self.len, bits: self.bits | rhs.bits }
     }
 }
@@ -104,6 +110,8 @@ impl BitAnd for B64 {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvand", &[self.len, rhs.len], "operands of equal length");
         B64 { len: self.len, bits: self.bits & rhs.bits }
     }
 }
@@ -120,6 +128,8 @@ impl Add<B64> for B64 {
     type Output = B64;
 
     fn add(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvadd", &[self.len, rhs.len], "operands of equal length");
         B64 { len: self.len, bits: bzhi_u64(self.bits.wrapping_add(rhs.bits), self.len) }
     }
 }
@@ -128,6 +138,8 @@ impl Sub<B64> for B64 {
     type Output = B64;
 
     fn sub(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "length-checks")]
+        check(self.len == rhs.len, "bvsub", &[self.len, rhs.len], "operands of equal length");
         B64 { len: self.len, bits: bzhi_u64(self.bits.wrapping_sub(rhs.bits), self.len) }
     }
 }
@@ -260,11 +272,19 @@ impl BV for B64 {
     }
 
     fn zero_extend(self, new_len: u32) -> Self {
+        #[cfg(feature = "length-checks")]
+        if check(self.len <= new_len && new_len <= 64, "zero_extend", &[self.len, new_len], "a longer length") {
+            return self;
+        }
         assert!(self.len <= new_len && new_len <= 64);
         B64 { len: new_len, bits: self.bits }
     }
 
     fn sign_extend(self, new_len: u32) -> Self {
+        #[cfg(feature = "length-checks")]
+        if check(self.len <= new_len && new_len <= 64, "sign_extend", &[self.len, new_len], "a longer length") {
+            return self;
+        }
         assert!(self.len <= new_len && new_len <= 64);
         if self.len > 0 {
             if (self.bits >> (self.len - 1)) & 0b1 == 0b1 {
@@ -287,6 +307,8 @@ impl BV for B64 {
     }
 
     fn slice(self, from: u32, len: u32) -> Option<Self> {
+        #[cfg(feature = "length-checks")]
+        check(from + len <= self.len, "slice", &[self.len, from, len], "a slice within the bitvector");
         if from + len <= self.len {
             Some(B64 { len, bits: bzhi_u64(self.bits >> from, len) })
         } else {
@@ -295,6 +317,11 @@ impl BV for B64 {
     }
 
     fn set_slice(self, n: u32, update: Self) -> Self {
+        #[cfg(feature = "length-checks")]
+        if check(n + update.len <= self.len, "set_slice", &[self.len, n, update.len], "an update within the bitvector")
+        {
+            return self;
+        }
         let mask = !bzhi_u64(0xFFFF_FFFF_FFFF_FFFF << n, n + update.len);
         let update = update.bits << n;
         B64 { len: self.len, bits: (self.bits & mask) | update }
@@ -0,0 +1,134 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+//! Optional checks that the operands of each [super::b64::B64] and
+//! [super::b129::B129] operation have the lengths the operation expects, for debugging
+//! models which misuse bitvector lengths. The checks are only
+//! compiled in with the `length-checks` feature, and must then be
+//! enabled with [set_length_checks]. A failed check does not panic,
+//! as the operation cannot return an error. Instead it is recorded
+//! for the current thread and the executor turns it into an
+//! [crate::error::ExecError::BitvectorLength] error, at the
+//! instruction which performed the operation.
+
+use std::fmt;
+
+/// A bitvector operation applied to operands of the wrong lengths
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LengthError {
+    /// The name of the operation
+    pub op: &'static str,
+    /// The lengths of the operands, along with any lengths or
+    /// indices given as arguments to the operation
+    pub lengths: Vec<u32>,
+    /// What the operation expected of the lengths
+    pub expected: &'static str,
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lengths: Vec<String> = self.lengths.iter().map(|len| len.to_string()).collect();
+        write!(f, "{} applied to lengths ({}), expected {}", self.op, lengths.join(", "), self.expected)
+    }
+}
+
+#[cfg(feature = "length-checks")]
+mod enabled {
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::LengthError;
+
+    static LENGTH_CHECKS: AtomicBool = AtomicBool::new(false);
+
+    thread_local! {
+        static PENDING: RefCell<Option<LengthError>> = RefCell::new(None);
+    }
+
+    /// Enable or disable the length checks
+    pub fn set_length_checks(enabled: bool) -> Result<(), String> {
+        LENGTH_CHECKS.store(enabled, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// True if the length checks are enabled
+    pub fn length_checks() -> bool {
+        LENGTH_CHECKS.load(Ordering::Relaxed)
+    }
+
+    /// Take the first failed check on this thread since the last
+    /// call, if there is one
+    pub fn take_length_error() -> Option<LengthError> {
+        PENDING.with(|pending| pending.borrow_mut().take())
+    }
+
+    /// Record a failed check unless one is already pending, returning
+    /// true if the check failed
+    pub(crate) fn check(ok: bool, op: &'static str, lengths: &[u32], expected: &'static str) -> bool {
+        if ok || !length_checks() {
+            return false;
+        }
+        PENDING.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            if pending.is_none() {
+                *pending = Some(LengthError { op, lengths: lengths.to_vec(), expected })
+            }
+        });
+        true
+    }
+}
+
+#[cfg(feature = "length-checks")]
+pub use enabled::{length_checks, set_length_checks, take_length_error};
+
+#[cfg(feature = "length-checks")]
+pub(crate) use enabled::check;
+
+/// Enable or disable the length checks, which fails if isla-lib was
+/// built without the `length-checks` feature.
+#[cfg(not(feature = "length-checks"))]
+pub fn set_length_checks(enabled: bool) -> Result<(), String> {
+    if enabled {
+        Err("Bitvector length checks require isla to be built with the length-checks feature".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// True if the length checks are enabled
+#[cfg(not(feature = "length-checks"))]
+pub fn length_checks() -> bool {
+    false
+}
+
+/// Take the first failed check on this thread since the last call,
+/// if there is one
+#[cfg(not(feature = "length-checks"))]
+pub fn take_length_error() -> Option<LengthError> {
+    None
+}
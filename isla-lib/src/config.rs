@@ -30,11 +30,14 @@
 //! This module loads a TOML file containing configuration for a specific instruction set
 //! architecture.
 
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
@@ -43,13 +46,40 @@ use toml::Value;
 use crate::bitvector::BV;
 use crate::ir::{IRTypeInfo, Loc, Name, Reset, Symtab, URVal, Val};
 use crate::ir_lexer::new_ir_lexer;
+use crate::memory::Endianness;
 use crate::primop_util::symbolic_from_typedefs;
 use crate::smt::smtlib::Exp;
+use crate::smt::{EnumId, EnumMember, Event};
 use crate::smt_parser;
 use crate::source_loc::SourceLoc;
 use crate::value_parser::{LocParser, URValParser, ValParser};
 use crate::zencode;
 
+mod schema;
+
+pub use schema::ConfigError;
+use schema::ConfigErrors;
+
+/// Merge an overlay configuration into `config`. Tables are merged
+/// key by key, so an overlay only needs to contain the keys it
+/// changes, and any other value in the overlay (including arrays)
+/// replaces the value in `config`.
+pub fn merge_overlay(config: &mut Value, overlay: Value) {
+    match (config, overlay) {
+        (Value::Table(config), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match config.get_mut(&key) {
+                    Some(existing) => merge_overlay(existing, value),
+                    None => {
+                        config.insert(key, value);
+                    }
+                }
+            }
+        }
+        (config, overlay) => *config = overlay,
+    }
+}
+
 fn allowed_keys(config: &Value, root: &str, allowed_keys: &[&str]) -> Result<(), String> {
     let Value::Table(tbl) = config else { return Err(format!("{} should be a toml key-value table", root)) };
 
@@ -89,7 +119,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Tool {
     pub executable: PathBuf,
     pub options: Vec<String>,
@@ -114,6 +144,7 @@ fn get_tool_path(config: &Value, tool: &str) -> Result<Tool, String> {
     }
 }
 
+#[derive(Default)]
 struct Toolchain {
     assembler: Tool,
     objdump: Tool,
@@ -195,6 +226,22 @@ fn get_program_counter(config: &Value, symtab: &Symtab) -> Result<Name, String>
     }
 }
 
+fn get_optimize_ir(config: &Value) -> Result<bool, String> {
+    match config.get("optimize_ir") {
+        Some(Value::Boolean(b)) => Ok(*b),
+        Some(_) => Err("optimize_ir must have a boolean value if it exists in configuration".to_string()),
+        None => Ok(false),
+    }
+}
+
+fn get_alias_analysis(config: &Value) -> Result<bool, String> {
+    match config.get("alias_analysis") {
+        Some(Value::Boolean(b)) => Ok(*b),
+        Some(_) => Err("alias_analysis must have a boolean value if it exists in configuration".to_string()),
+        None => Ok(true),
+    }
+}
+
 fn get_zero_announce_exit(config: &Value) -> Result<bool, String> {
     match config.get("zero_announce_exit") {
         Some(Value::Boolean(b)) => Ok(*b),
@@ -298,6 +345,97 @@ fn get_table_value(config: &Value, table: &str, key: &str) -> Result<u64, String
         })
 }
 
+/// Read the address range treated as zero by `--zero-memory` from
+/// `memory.zero_base` and `memory.zero_top`, which default to the
+/// whole 64-bit address space.
+fn get_zero_region(config: &Value) -> Result<Range<u64>, String> {
+    let memory = config.get("memory");
+    let base = match memory.and_then(|memory| memory.get("zero_base")) {
+        Some(_) => get_table_value(config, "memory", "zero_base")?,
+        None => 0,
+    };
+    let top = match memory.and_then(|memory| memory.get("zero_top")) {
+        Some(_) => get_table_value(config, "memory", "zero_top")?,
+        None => 0xffff_ffff_ffff_ffff,
+    };
+    if base < top {
+        Ok(base..top)
+    } else {
+        Err(format!("memory.zero_base (0x{:x}) must be below memory.zero_top (0x{:x})", base, top))
+    }
+}
+
+/// Read the random seed for the SMT solver from `solver.seed`.
+fn get_solver_seed(config: &Value) -> Result<Option<u32>, String> {
+    match config.get("solver").and_then(|solver| solver.get("seed")) {
+        Some(Value::Integer(seed)) => {
+            u32::try_from(*seed).map(Some).map_err(|_| format!("solver.seed {} is not a valid seed", seed))
+        }
+        Some(_) => Err("solver.seed should be an integer".to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Bounds for `--randomize-addresses`, from the
+/// `address_randomization` table. Each run offsets the page tables,
+/// thread memory, and symbolic addresses by the same multiple of
+/// `align`, which is at most `max_offset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AddressRandomization {
+    pub max_offset: u64,
+    pub align: u64,
+}
+
+impl AddressRandomization {
+    pub fn random_offset<R: Rng>(&self, rng: &mut R) -> u64 {
+        rng.gen_range(0, (self.max_offset / self.align).saturating_add(1)) * self.align
+    }
+}
+
+/// Read `address_randomization.max_offset` and
+/// `address_randomization.align`, where the alignment defaults to
+/// `mmu.page_size` so page table entries remain aligned.
+fn get_address_randomization(config: &Value) -> Result<Option<AddressRandomization>, String> {
+    if config.get("address_randomization").is_none() {
+        return Ok(None);
+    }
+    let max_offset = get_table_value(config, "address_randomization", "max_offset")?;
+    let align = match config.get("address_randomization").and_then(|table| table.get("align")) {
+        Some(_) => get_table_value(config, "address_randomization", "align")?,
+        None => get_table_value(config, "mmu", "page_size").unwrap_or(1),
+    };
+    if !align.is_power_of_two() {
+        return Err(format!("address_randomization.align (0x{:x}) must be a power of two", align));
+    }
+    Ok(Some(AddressRandomization { max_offset, align }))
+}
+
+/// Read the Z3 parameters in `solver.params`, e.g. `smt.relevancy = 0`,
+/// where nested tables give dotted parameter names.
+fn get_solver_params(config: &Value) -> Result<Vec<(String, String)>, String> {
+    fn flatten(prefix: &str, value: &Value, params: &mut Vec<(String, String)>) -> Result<(), String> {
+        match value {
+            Value::Table(table) => {
+                for (key, value) in table {
+                    let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                    flatten(&name, value, params)?
+                }
+            }
+            Value::String(s) => params.push((prefix.to_string(), s.clone())),
+            Value::Integer(i) => params.push((prefix.to_string(), i.to_string())),
+            Value::Boolean(b) => params.push((prefix.to_string(), b.to_string())),
+            _ => return Err(format!("Solver parameter {} should be a string, integer, or boolean", prefix)),
+        }
+        Ok(())
+    }
+
+    let mut params = Vec::new();
+    if let Some(table) = config.get("solver").and_then(|solver| solver.get("params")) {
+        flatten("", table, &mut params)?
+    }
+    Ok(params)
+}
+
 fn get_table_string(config: &Value, table: &str, key: &str) -> Result<String, String> {
     config
         .get(table)
@@ -371,6 +509,21 @@ fn get_default_registers<B: BV>(
     }
 }
 
+fn get_let_values<B: BV>(
+    config: &Value,
+    symtab: &Symtab,
+    type_info: &IRTypeInfo,
+) -> Result<HashMap<Name, Val<B>>, String> {
+    let Some(lets) = config.get("lets") else { return Ok(HashMap::new()) };
+    let Some(lets) = lets.as_table() else { return Err("lets should be a table of <let> = <value> pairs".to_string()) };
+    lets.into_iter()
+        .map(|(id, value)| match symtab.get(&zencode::encode(id)) {
+            Some(name) => Ok((name, from_toml_value(value, symtab, type_info)?)),
+            None => Err(format!("Let binding {} does not exist in supplied architecture", id)),
+        })
+        .collect()
+}
+
 fn get_const_primops<B: BV>(
     config: &Value,
     symtab: &Symtab,
@@ -444,14 +597,14 @@ fn get_reset_registers<B: BV>(config: &Value, symtab: &Symtab, type_info: &IRTyp
     }
 }
 
-fn get_reset_constraints(config: &Value) -> Result<Vec<Exp<Loc<String>>>, String> {
-    let reset_toml =
-        config.get("constraints").and_then(|section| section.as_table()).and_then(|section| section.get("reset"));
-    if let Some(toml) = reset_toml {
+/// Read the constraints in `constraints.<key>`
+fn get_constraints(config: &Value, key: &str) -> Result<Vec<Exp<Loc<String>>>, String> {
+    let toml = config.get("constraints").and_then(|section| section.as_table()).and_then(|section| section.get(key));
+    if let Some(toml) = toml {
         let constraints = toml
             .as_array()
             .and_then(|vec| vec.iter().map(|item| item.as_str()).collect::<Option<Vec<_>>>())
-            .ok_or_else(|| "constraints.reset should be an array of constraint strings".to_string())?;
+            .ok_or_else(|| format!("constraints.{} should be an array of constraint strings", key))?;
         constraints
             .iter()
             .map(|constraint| smt_parser::ExpParser::new().parse(constraint).map_err(|err| err.to_string()))
@@ -506,6 +659,15 @@ fn get_translation_function(config: &Value, symtab: &Symtab) -> Result<Option<Na
     }
 }
 
+fn get_decode_function(config: &Value, symtab: &Symtab) -> Result<Option<Name>, String> {
+    let Some(value) = config.get("decode_function") else { return Ok(None) };
+    let Some(string) = value.as_str() else { return Err("decode_function must be a string".to_string()) };
+    match symtab.get(&zencode::encode(string)) {
+        Some(name) => Ok(Some(name)),
+        None => Err(format!("function {} does not exist in supplied architecture", string)),
+    }
+}
+
 fn get_trace_functions(config: &Value, symtab: &Symtab) -> Result<HashSet<Name>, String> {
     let trace = config.get("trace");
 
@@ -529,6 +691,128 @@ fn get_trace_functions(config: &Value, symtab: &Symtab) -> Result<HashSet<Name>,
     }
 }
 
+/// Read the `pure` list of functions whose results only depend on
+/// their arguments, so calls to them can be cached. As with register
+/// defaults, functions which are not in the architecture are skipped
+/// with a warning, so one list can serve several versions of it.
+fn get_pure_functions(config: &Value, symtab: &Symtab) -> Result<HashSet<Name>, String> {
+    let Some(pure) = config.get("pure") else { return Ok(HashSet::new()) };
+
+    let Some(pure) = pure.as_array() else { return Err("pure should be a list of function names".to_string()) };
+
+    pure.iter()
+        .filter_map(|function| {
+            let Some(name) = function.as_str() else {
+                return Some(Err(format!("pure should be a list of function names, found {}", function)));
+            };
+            if let Some(function) = symtab.get(&zencode::encode(name)) {
+                Some(Ok(function))
+            } else {
+                eprintln!("Warning: Could not find function {} when parsing pure in configuration", name);
+                None
+            }
+        })
+        .collect()
+}
+
+/// A summary that replaces every call to a function. The result is
+/// taken from the first case whose arguments match, and otherwise
+/// from `default`, which is a fresh symbolic value of the function's
+/// return type if not given. Each register in `havoc` is also given a
+/// fresh symbolic value by the call.
+#[derive(Clone, Debug)]
+pub struct FunctionStub<B> {
+    pub cases: Vec<StubCase<B>>,
+    pub default: Option<Val<B>>,
+    pub havoc: Vec<Name>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StubCase<B> {
+    /// The arguments to match, where `None` matches anything
+    pub args: Vec<Option<Val<B>>>,
+    pub result: Val<B>,
+}
+
+/// Read the `[stubs]` table, where each entry is either `"havoc"`, or
+/// a table with optional `havoc`, `cases`, and `default` fields, e.g.
+///
+/// ```toml
+/// [stubs]
+/// AArch64_TranslationTableWalk = "havoc"
+/// HaveEL = { cases = [{ args = ["0b11"], result = false }], default = true }
+/// ```
+///
+/// An argument of `"_"` in a case matches anything.
+fn get_function_stubs<B: BV>(
+    config: &Value,
+    symtab: &Symtab,
+    type_info: &IRTypeInfo,
+) -> Result<HashMap<Name, FunctionStub<B>>, String> {
+    let Some(stubs) = config.get("stubs") else { return Ok(HashMap::new()) };
+    let Some(stubs) = stubs.as_table() else { return Err("stubs should be a table".to_string()) };
+
+    let mut functions = HashMap::new();
+
+    for (function, stub) in stubs {
+        let Some(name) = symtab.get(&zencode::encode(function)) else {
+            return Err(format!("Function {} in stubs does not exist in supplied architecture", function));
+        };
+        let fields = match stub {
+            Value::String(s) if s == "havoc" => {
+                functions.insert(name, FunctionStub { cases: Vec::new(), default: None, havoc: Vec::new() });
+                continue;
+            }
+            Value::Table(fields) => fields,
+            _ => return Err(format!("stubs.{} should be \"havoc\" or a table", function)),
+        };
+        allowed_keys(stub, &format!("stubs.{}", function), &["havoc", "cases", "default"])?;
+
+        let havoc = match fields.get("havoc") {
+            Some(Value::Array(registers)) => registers
+                .iter()
+                .map(|register| {
+                    register.as_str().and_then(|r| symtab.get(&zencode::encode(r))).ok_or_else(|| {
+                        format!("Could not find register {} when parsing stubs.{}.havoc", register, function)
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(format!("stubs.{}.havoc should be a list of register names", function)),
+            None => Vec::new(),
+        };
+
+        let mut cases = Vec::new();
+        if let Some(table) = fields.get("cases") {
+            let Some(table) = table.as_array() else {
+                return Err(format!("stubs.{}.cases should be a list of tables", function));
+            };
+            for case in table {
+                let (Some(args), Some(result)) = (case.get("args").and_then(Value::as_array), case.get("result"))
+                else {
+                    return Err(format!("Each case in stubs.{}.cases should have args and result fields", function));
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Value::String(s) if s == "_" => Ok(None),
+                        _ => from_toml_value(arg, symtab, type_info).map(Some),
+                    })
+                    .collect::<Result<_, _>>()?;
+                cases.push(StubCase { args, result: from_toml_value(result, symtab, type_info)? })
+            }
+        }
+
+        let default = match fields.get("default") {
+            Some(value) => Some(from_toml_value(value, symtab, type_info)?),
+            None => None,
+        };
+
+        functions.insert(name, FunctionStub { cases, default, havoc });
+    }
+
+    Ok(functions)
+}
+
 fn get_registers_set<C>(config: &Value, set_name: &str, symtab: &Symtab) -> Result<C, String>
 where
     C: FromIterator<Name> + Default,
@@ -585,6 +869,38 @@ fn get_in_program_order(config: &Value, symtab: &Symtab) -> Result<HashSet<Name>
     Ok(events)
 }
 
+/// Read the `[exceptions]` table, which maps each kind of exception
+/// to the function (or list of functions) in the model that takes it,
+/// returning a map from function to exception kind.
+fn get_exception_functions(config: &Value, symtab: &Symtab) -> Result<HashMap<Name, String>, String> {
+    let mut functions = HashMap::new();
+
+    let Some(exceptions) = config.get("exceptions") else { return Ok(functions) };
+
+    let Some(exceptions) = exceptions.as_table() else {
+        return Err("exceptions should be a table in configuration".to_string());
+    };
+
+    for (kind, value) in exceptions {
+        let names: Vec<&Value> = match value {
+            Value::String(_) => vec![value],
+            Value::Array(names) => names.iter().collect(),
+            _ => return Err(format!("exception {} should be a function name or list of names", kind)),
+        };
+        for name in names {
+            let Some(s) = name.as_str() else {
+                return Err(format!("exception {} should contain function names in configuration", kind));
+            };
+            let Some(function) = symtab.get(&zencode::encode(s)) else {
+                return Err(format!("Could not find function {} for exception {} in configuration", s, kind));
+            };
+            functions.insert(function, kind.clone());
+        }
+    }
+
+    Ok(functions)
+}
+
 fn get_default_sizeof(config: &Value) -> Result<u32, String> {
     let Some(v) = config.get("default_sizeof") else { return Ok(4) };
     let Some(i) = v.as_integer() else { return Err("default_sizeof should be an integer".to_string()) };
@@ -594,6 +910,486 @@ fn get_default_sizeof(config: &Value) -> Result<u32, String> {
     }
 }
 
+/// The control register bit which enables alignment checking for
+/// memory accesses, e.g. SCTLR_EL1.A on Arm. If the register is a
+/// struct then `field` names the bitvector field containing the bit.
+#[derive(Clone, Copy, Debug)]
+pub struct AlignmentCheck {
+    pub register: Name,
+    pub field: Option<Name>,
+    pub bit: u32,
+}
+
+fn get_alignment_check(config: &Value, symtab: &Symtab) -> Result<Option<AlignmentCheck>, String> {
+    let Some(table) = config.get("alignment_check") else { return Ok(None) };
+    let lookup = |name: &str| {
+        symtab
+            .get(&zencode::encode(name))
+            .ok_or_else(|| format!("{} in alignment_check does not exist in supplied architecture", name))
+    };
+    let register = table
+        .get("register")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "alignment_check.register should be a string".to_string())?;
+    let field = match table.get("field") {
+        Some(field) => {
+            Some(lookup(field.as_str().ok_or_else(|| "alignment_check.field should be a string".to_string())?)?)
+        }
+        None => None,
+    };
+    let bit = table
+        .get("bit")
+        .and_then(Value::as_integer)
+        .and_then(|i| u32::try_from(i).ok())
+        .ok_or_else(|| "alignment_check.bit should be a non-negative integer".to_string())?;
+    Ok(Some(AlignmentCheck { register: lookup(register)?, field, bit }))
+}
+
+/// A named range of bits `hi` down to `lo` within a register
+#[derive(Clone, Debug)]
+pub struct Bitfield {
+    pub name: String,
+    pub hi: u32,
+    pub lo: u32,
+}
+
+/// The bitfield layout of a bitvector system register. If the
+/// register is a struct then `field` names the bitvector field
+/// containing the bits, as for [AlignmentCheck]. If the register is
+/// a vector of bitvectors then the layout applies to each element.
+#[derive(Clone, Debug)]
+pub struct RegisterBitfields {
+    pub field: Option<Name>,
+    pub layout: Vec<Bitfield>,
+}
+
+/// Read the `[registers.bitfields]` table, which gives the layout of
+/// system registers so footprints can report which of their fields
+/// are read and written.
+fn get_register_bitfields(config: &Value, symtab: &Symtab) -> Result<HashMap<Name, RegisterBitfields>, String> {
+    let mut registers = HashMap::new();
+
+    let Some(bitfields) = config.get("registers").and_then(|registers| registers.get("bitfields")) else {
+        return Ok(registers);
+    };
+
+    let Some(bitfields) = bitfields.as_table() else {
+        return Err("registers.bitfields should be a table in configuration".to_string());
+    };
+
+    let lookup = |name: &str| {
+        symtab
+            .get(&zencode::encode(name))
+            .ok_or_else(|| format!("{} in registers.bitfields does not exist in supplied architecture", name))
+    };
+
+    for (register, table) in bitfields {
+        let field = match table.get("field") {
+            Some(field) => Some(lookup(
+                field.as_str().ok_or_else(|| format!("registers.bitfields.{}.field should be a string", register))?,
+            )?),
+            None => None,
+        };
+
+        let Some(fields) = table.get("layout").and_then(Value::as_table) else {
+            return Err(format!("registers.bitfields.{}.layout should be a table of fields", register));
+        };
+
+        let bit = |value: &Value| value.as_integer().and_then(|i| u32::try_from(i).ok());
+        let mut layout = Vec::new();
+        for (name, range) in fields {
+            let (hi, lo) = match range {
+                Value::Integer(_) => (bit(range), bit(range)),
+                Value::Array(range) if range.len() == 2 => (bit(&range[0]), bit(&range[1])),
+                _ => (None, None),
+            };
+            match (hi, lo) {
+                (Some(hi), Some(lo)) if hi >= lo => layout.push(Bitfield { name: name.clone(), hi, lo }),
+                _ => {
+                    return Err(format!(
+                        "registers.bitfields.{}.layout.{} should be a bit or a [high, low] range",
+                        register, name
+                    ))
+                }
+            }
+        }
+        layout.sort_by_key(|bitfield| bitfield.lo);
+
+        registers.insert(lookup(register)?, RegisterBitfields { field, layout });
+    }
+
+    Ok(registers)
+}
+
+/// A register in the model which is another name for (part of) a
+/// canonical register, as given by `registers.aliases`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterAlias {
+    pub register: Name,
+    /// The bits of the canonical register the alias refers to, as
+    /// `(high, low)`, or `None` if it refers to the whole register.
+    pub slice: Option<(u32, u32)>,
+}
+
+/// Read the `[registers.aliases]` table, where each entry is either
+/// `view = "register"` or `view = { register = "register", slice =
+/// [high, low] }`. Both must be registers in the model, and the
+/// canonical register cannot itself be an alias.
+fn get_register_aliases(config: &Value, symtab: &Symtab) -> Result<HashMap<Name, RegisterAlias>, String> {
+    let mut aliases = HashMap::new();
+
+    let Some(table) = config.get("registers").and_then(|registers| registers.get("aliases")) else {
+        return Ok(aliases);
+    };
+
+    let Some(table) = table.as_table() else {
+        return Err("registers.aliases should be a table in configuration".to_string());
+    };
+
+    let lookup = |name: &str| {
+        symtab
+            .get(&zencode::encode(name))
+            .ok_or_else(|| format!("{} in registers.aliases does not exist in supplied architecture", name))
+    };
+
+    for (view, alias) in table {
+        let (register, slice) = match alias {
+            Value::String(register) => (register.as_str(), None),
+            Value::Table(alias) => {
+                let Some(register) = alias.get("register").and_then(Value::as_str) else {
+                    return Err(format!("registers.aliases.{}.register should be a string", view));
+                };
+                let bit = |value: &Value| value.as_integer().and_then(|i| u32::try_from(i).ok());
+                let slice = match alias.get("slice") {
+                    None => None,
+                    Some(Value::Array(range)) if range.len() == 2 => match (bit(&range[0]), bit(&range[1])) {
+                        (Some(hi), Some(lo)) if hi >= lo => Some((hi, lo)),
+                        _ => return Err(format!("registers.aliases.{}.slice should be a [high, low] range", view)),
+                    },
+                    Some(_) => return Err(format!("registers.aliases.{}.slice should be a [high, low] range", view)),
+                };
+                (register, slice)
+            }
+            _ => return Err(format!("registers.aliases.{} should be a register name or table", view)),
+        };
+        aliases.insert(lookup(view)?, RegisterAlias { register: lookup(register)?, slice });
+    }
+
+    for (view, alias) in &aliases {
+        if aliases.contains_key(&alias.register) {
+            return Err(format!(
+                "registers.aliases.{} refers to {}, which is itself an alias",
+                zencode::decode(symtab.to_str(*view)),
+                zencode::decode(symtab.to_str(alias.register))
+            ));
+        }
+    }
+
+    Ok(aliases)
+}
+
+/// Read the `[registers.lanes]` table, which splits vector registers
+/// into equal width lanes, e.g. `_V = { width = 128, lane = 32 }`.
+/// The lanes are named `lane0`, `lane1`, and so on from the least
+/// significant bits, and are tracked in footprints like any other
+/// bitfield.
+fn add_register_lanes(
+    config: &Value,
+    symtab: &Symtab,
+    registers: &mut HashMap<Name, RegisterBitfields>,
+) -> Result<(), String> {
+    let Some(lanes) = config.get("registers").and_then(|registers| registers.get("lanes")) else {
+        return Ok(());
+    };
+
+    let Some(lanes) = lanes.as_table() else {
+        return Err("registers.lanes should be a table in configuration".to_string());
+    };
+
+    for (register, table) in lanes {
+        let Some(name) = symtab.get(&zencode::encode(register)) else {
+            return Err(format!("{} in registers.lanes does not exist in supplied architecture", register));
+        };
+
+        let size = |key: &str| {
+            table
+                .get(key)
+                .and_then(Value::as_integer)
+                .and_then(|i| u32::try_from(i).ok())
+                .filter(|i| *i > 0)
+                .ok_or_else(|| format!("registers.lanes.{}.{} should be a positive integer", register, key))
+        };
+        let width = size("width")?;
+        let lane = size("lane")?;
+        if width % lane != 0 {
+            return Err(format!("registers.lanes.{}: lane width {} does not divide {}", register, lane, width));
+        }
+
+        if registers.contains_key(&name) {
+            return Err(format!("{} has both registers.bitfields and registers.lanes in configuration", register));
+        }
+
+        let layout = (0..width / lane)
+            .map(|i| Bitfield { name: format!("lane{}", i), hi: (i + 1) * lane - 1, lo: i * lane })
+            .collect();
+        registers.insert(name, RegisterBitfields { field: None, layout });
+    }
+
+    Ok(())
+}
+
+/// Read `endianness`, returning the byte order for data and for
+/// instructions.
+fn get_endianness(config: &Value) -> Result<(Endianness, Endianness), String> {
+    use Endianness::*;
+    match config.get("endianness").map(|endianness| endianness.as_str()) {
+        None | Some(Some("little")) => Ok((Little, Little)),
+        Some(Some("big")) => Ok((Big, Big)),
+        Some(Some("be8")) => Ok((Big, Little)),
+        Some(Some("be32")) => Ok((WordInvariantBig, WordInvariantBig)),
+        Some(_) => Err("endianness should be one of little, big, be8, or be32".to_string()),
+    }
+}
+
+/// The instruction sets which can be named by the `disassembler`
+/// key, for annotating opcodes with their disassembly.
+pub const DISASSEMBLERS: &[&str] = &["aarch64", "arm", "thumb", "x86", "x86_64", "mips32", "mips64", "ppc32", "ppc64"];
+
+fn get_disassembler(config: &Value) -> Result<Option<String>, String> {
+    match config.get("disassembler").map(Value::as_str) {
+        None => Ok(None),
+        Some(Some(disassembler)) if DISASSEMBLERS.contains(&disassembler) => Ok(Some(disassembler.to_string())),
+        Some(_) => Err(format!("disassembler should be one of {}", DISASSEMBLERS.join(", "))),
+    }
+}
+
+/// Read `registers.flags`, a list of condition flags written as
+/// `register.field`.
+fn get_flags(config: &Value, symtab: &Symtab) -> Result<Vec<(Name, String)>, String> {
+    let Some(flags) = config.get("registers").and_then(|registers| registers.get("flags")) else {
+        return Ok(Vec::new());
+    };
+
+    let Some(flags) = flags.as_array() else {
+        return Err("registers.flags should be a list of register.field names".to_string());
+    };
+
+    flags
+        .iter()
+        .map(|flag| match flag.as_str().and_then(|flag| flag.split_once('.')) {
+            Some((register, field)) => match symtab.get(&zencode::encode(register)) {
+                Some(register) => Ok((register, field.to_string())),
+                None => {
+                    Err(format!("Register {} in registers.flags does not exist in supplied architecture", register))
+                }
+            },
+            None => Err(format!("registers.flags entry {} should have the form register.field", flag)),
+        })
+        .collect()
+}
+
+/// A named kind of memory access, such as acquire or release, given
+/// by the members of the model's read and write kind enumerations
+/// which have that kind. Declared by the `access_kinds` table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessKind {
+    pub name: String,
+    members: Vec<EnumMember>,
+}
+
+impl AccessKind {
+    /// Accesses of the kind with this name are treated as exclusive
+    /// by footprint analysis, like those made by the exclusive memory
+    /// primitives
+    pub const EXCLUSIVE: &'static str = "exclusive";
+
+    pub fn new(name: String, members: Vec<EnumMember>) -> Self {
+        AccessKind { name, members }
+    }
+
+    /// Test if a `read_kind` or `write_kind` value is of this kind
+    pub fn contains<B>(&self, kind: &Val<B>) -> bool {
+        matches!(kind, Val::Enum(member) if self.members.contains(member))
+    }
+
+    /// Test if a memory read or write event is of this kind
+    pub fn matches<B>(&self, event: &Event<B>) -> bool {
+        match event {
+            Event::ReadMem { read_kind, .. } => self.contains(read_kind),
+            Event::WriteMem { write_kind, .. } => self.contains(write_kind),
+            _ => false,
+        }
+    }
+}
+
+/// Read the `access_kinds` table, which maps each kind to an enum
+/// member or a list of them.
+fn get_access_kinds(config: &Value, symtab: &Symtab, type_info: &IRTypeInfo) -> Result<Vec<AccessKind>, String> {
+    let Some(kinds) = config.get("access_kinds") else { return Ok(Vec::new()) };
+    let Some(kinds) = kinds.as_table() else {
+        return Err("access_kinds should be a table of <kind> = <enum members> pairs".to_string());
+    };
+
+    kinds
+        .iter()
+        .map(|(kind, members)| {
+            let members = match members {
+                Value::String(member) => vec![member.as_str()],
+                Value::Array(members) => members.iter().filter_map(Value::as_str).collect(),
+                _ => return Err(format!("access_kinds.{} should be an enum member or a list of them", kind)),
+            };
+            let members = members
+                .iter()
+                .map(|member| {
+                    match symtab.get(&zencode::encode(member)).and_then(|name| type_info.enum_members.get(&name)) {
+                        Some((member, _, enum_id)) => {
+                            Ok(EnumMember { enum_id: EnumId::from_name(*enum_id), member: *member })
+                        }
+                        None => Err(format!(
+                            "{} in access_kinds.{} is not an enum member in supplied architecture",
+                            member, kind
+                        )),
+                    }
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(AccessKind { name: kind.clone(), members })
+        })
+        .collect()
+}
+
+/// A named privilege level, such as an exception level, selected by
+/// constraints on the initial register state.
+#[derive(Clone, Debug)]
+pub struct PrivilegeLevel {
+    pub name: String,
+    pub constraints: Vec<Exp<Loc<String>>>,
+}
+
+/// Read the `privilege_levels` table, which maps the name of each
+/// level to the constraints on the initial register state that put
+/// the processor in it, written as in `constraints.assume`.
+fn get_privilege_levels(config: &Value) -> Result<Vec<PrivilegeLevel>, String> {
+    let Some(levels) = config.get("privilege_levels") else { return Ok(Vec::new()) };
+    let Some(levels) = levels.as_table() else {
+        return Err("privilege_levels should be a table of <level> = <constraints> pairs".to_string());
+    };
+
+    levels
+        .iter()
+        .map(|(level, constraints)| {
+            let constraints = constraints
+                .as_array()
+                .and_then(|vec| vec.iter().map(|item| item.as_str()).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| format!("privilege_levels.{} should be an array of constraint strings", level))?;
+            let constraints = constraints
+                .iter()
+                .map(|constraint| {
+                    smt_parser::ExpParser::new()
+                        .parse(constraint)
+                        .map_err(|err| format!("privilege_levels.{}: {}", level, err))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(PrivilegeLevel { name: level.clone(), constraints })
+        })
+        .collect()
+}
+
+/// A named region of memory, such as the stack, covering the
+/// addresses in `range`.
+#[derive(Clone, Debug)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub range: Range<u64>,
+}
+
+/// Read the `memory_regions` table, which maps the name of each
+/// region to its `base` and (exclusive) `top` address.
+fn get_memory_regions(config: &Value) -> Result<Vec<MemoryRegion>, String> {
+    let Some(regions) = config.get("memory_regions") else { return Ok(Vec::new()) };
+    let Some(table) = regions.as_table() else {
+        return Err("memory_regions should be a table of <region> = { base, top } pairs".to_string());
+    };
+
+    table
+        .keys()
+        .map(|region| {
+            let base = get_table_value(regions, region, "base").map_err(|err| format!("memory_regions: {}", err))?;
+            let top = get_table_value(regions, region, "top").map_err(|err| format!("memory_regions: {}", err))?;
+            if base < top {
+                Ok(MemoryRegion { name: region.clone(), range: base..top })
+            } else {
+                Err(format!("memory_regions.{}: base (0x{:x}) must be below top (0x{:x})", region, base, top))
+            }
+        })
+        .collect()
+}
+
+/// An abstract function that operates on a whole block of memory,
+/// such as DC ZVA or a cache maintenance operation. The address is
+/// the first argument, or a field of it if `address_field` is set.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockFunction {
+    /// The size of the block in bytes, always a power of two
+    pub bytes: u32,
+    pub address_field: Option<Name>,
+}
+
+/// Read the `[blocks]` table, returning whether block operations
+/// should be modelled precisely and the functions that perform them.
+fn get_blocks(config: &Value, symtab: &Symtab) -> Result<(bool, HashMap<Name, BlockFunction>), String> {
+    let mut functions = HashMap::new();
+
+    let Some(blocks) = config.get("blocks") else { return Ok((false, functions)) };
+
+    let precise = match blocks.get("precise") {
+        Some(precise) => precise.as_bool().ok_or_else(|| "blocks.precise should be a boolean".to_string())?,
+        None => false,
+    };
+
+    let mut sizes: HashMap<&str, u32> = HashMap::new();
+    if let Some(table) = blocks.get("sizes") {
+        let Some(table) = table.as_table() else { return Err("blocks.sizes should be a table".to_string()) };
+        for (block, size) in table {
+            let Some(size) = size.as_integer().and_then(|i| u32::try_from(i).ok()).filter(|i| i.is_power_of_two())
+            else {
+                return Err(format!("blocks.sizes.{} should be a power of two", block));
+            };
+            sizes.insert(block, size);
+        }
+    }
+
+    if let Some(table) = blocks.get("functions") {
+        let Some(table) = table.as_table() else { return Err("blocks.functions should be a table".to_string()) };
+        for (function, block) in table {
+            let (block, address_field) = match block {
+                Value::String(block) => (block.as_str(), None),
+                Value::Table(fields) => {
+                    let Some(block) = fields.get("block").and_then(Value::as_str) else {
+                        return Err(format!("blocks.functions.{} should have a block field", function));
+                    };
+                    (block, fields.get("address").and_then(Value::as_str))
+                }
+                _ => return Err(format!("blocks.functions.{} should be a block name or table", function)),
+            };
+            let Some(bytes) = sizes.get(block) else {
+                return Err(format!("No size given for block {} in blocks.sizes", block));
+            };
+            let lookup = |name: &str| {
+                symtab
+                    .get(&zencode::encode(name))
+                    .ok_or_else(|| format!("{} in blocks does not exist in supplied architecture", name))
+            };
+            let address_field = match address_field {
+                Some(field) => Some(lookup(field)?),
+                None => None,
+            };
+            functions.insert(lookup(function)?, BlockFunction { bytes: *bytes, address_field });
+        }
+    }
+
+    Ok((precise, functions))
+}
+
 pub struct ISAConfig<B> {
     /// The identifier for the program counter register
     pub pc: Name,
@@ -607,6 +1403,9 @@ pub struct ISAConfig<B> {
     pub nm: Tool,
     /// A path to a linker for the architecture
     pub linker: Tool,
+    /// The instruction set used to disassemble opcodes, one of
+    /// [DISASSEMBLERS]
+    pub disassembler: Option<String>,
     /// The base address for the page tables
     pub page_table_base: u64,
     /// The number of bytes in each page
@@ -630,34 +1429,95 @@ pub struct ISAConfig<B> {
     pub symbolic_addr_top: u64,
     /// The number of bytes between each symbolic address
     pub symbolic_addr_stride: u64,
+    /// The byte order of data in concrete memory
+    pub data_endianness: Endianness,
+    /// The byte order of instructions in concrete memory
+    pub instruction_endianness: Endianness,
+    /// The address range treated as zero by `--zero-memory`
+    pub zero_region: Range<u64>,
+    /// The random seed for the SMT solver, if not Z3's default
+    pub solver_seed: Option<u32>,
+    /// Parameters for the SMT solver, as Z3 parameter names and values
+    pub solver_params: Vec<(String, String)>,
+    /// Bounds for randomizing the placement of memory regions
+    pub address_randomization: Option<AddressRandomization>,
     /// Default values for specified registers
     pub default_registers: HashMap<Name, Val<B>>,
     /// Reset values for specified registers
     pub reset_registers: Vec<(Loc<Name>, Reset<B>)>,
     /// Constraints that should hold at reset_registers
     pub reset_constraints: Vec<Exp<Loc<String>>>,
+    /// Constraints on the initial register state assumed before
+    /// executing each instruction in footprint analysis
+    pub assumptions: Vec<Exp<Loc<String>>>,
     /// Constant primops
     pub const_primops: HashMap<String, Reset<B>>,
+    /// Values for top-level let bindings, used instead of evaluating them
+    pub let_values: HashMap<Name, Val<B>>,
     /// Assumptions to use about function behaviour
     pub function_assumptions: Vec<(String, Vec<Option<Exp<Loc<String>>>>, Exp<Loc<String>>)>,
     /// Register synonyms to rename
     pub register_renames: HashMap<String, Name>,
+    /// Registers which are other names for (part of) a canonical register
+    pub register_aliases: HashMap<Name, RegisterAlias>,
     /// Registers to ignore during footprint analysis
     pub ignored_registers: HashSet<Name>,
     /// Relaxed registers
     pub relaxed_registers: HashSet<Name>,
+    /// The bitfield layouts of system registers, for reporting field
+    /// accesses in footprints
+    pub register_bitfields: HashMap<Name, RegisterBitfields>,
+    /// Condition flags, as a register and the name of a field within it
+    pub flags: Vec<(Name, String)>,
+    /// Registers that start as fresh symbolic variables
+    pub symbolic_registers: HashSet<Name>,
+    /// Registers that always start with a concrete value, from either
+    /// the defaults or the model's register initializer
+    pub concrete_registers: HashSet<Name>,
     /// Print debug information for any function calls in this set during symbolic execution
     pub probes: HashSet<Name>,
+    /// Print the old and new values of every write to these registers
+    pub register_probes: HashSet<Name>,
     /// Trace calls to functions in this set
     pub trace_functions: HashSet<Name>,
+    /// Functions whose results depend only on their arguments, which
+    /// are cached within each path
+    pub pure_functions: HashSet<Name>,
+    /// Summaries that replace calls to functions
+    pub function_stubs: HashMap<Name, FunctionStub<B>>,
     /// Address translation function
     pub translation_function: Option<Name>,
+    /// The function that decodes an instruction
+    pub decode_function: Option<Name>,
     /// The abstract events that should be included in program order
     pub in_program_order: HashSet<Name>,
     /// The default size (in bytes) for memory accesses in litmus tests
     pub default_sizeof: u32,
     /// Exit if sail_instr_announce is called with a zero bitvector
-    pub zero_announce_exit: bool
+    pub zero_announce_exit: bool,
+    /// The register bit controlling alignment checks, if any
+    pub alignment_check: Option<AlignmentCheck>,
+    /// Functions that take an exception, and the kind of exception
+    /// each one takes
+    pub exception_functions: HashMap<Name, String>,
+    /// Record the full block affected by calls to `block_functions`
+    pub precise_blocks: bool,
+    /// Abstract functions that operate on a block of memory
+    pub block_functions: HashMap<Name, BlockFunction>,
+    /// Decide whether memory accesses overlap using
+    /// `memory::alias` where possible, rather than the SMT solver
+    pub alias_analysis: bool,
+    /// Apply the optimizations in [crate::ir::optimize] to the IR
+    /// when the architecture is initialized
+    pub optimize_ir: bool,
+    /// Named kinds of memory access, in the order they are declared
+    pub access_kinds: Vec<AccessKind>,
+    /// Named privilege levels, each with the constraints on the
+    /// initial register state that select it, sorted by name
+    pub privilege_levels: Vec<PrivilegeLevel>,
+    /// Named regions of memory used to classify the accesses in
+    /// footprints, sorted by name
+    pub memory_regions: Vec<MemoryRegion>,
 }
 
 impl<B: BV> ISAConfig<B> {
@@ -667,55 +1527,239 @@ impl<B: BV> ISAConfig<B> {
         symtab: &Symtab,
         type_info: &IRTypeInfo,
     ) -> Result<Self, String> {
-        let config = match contents.parse::<Value>() {
-            Ok(config) => config,
-            Err(e) => return Err(format!("Error when parsing configuration: {}", e)),
-        };
+        Self::parse_with_errors(contents, &[], toolchain_name, symtab, type_info)
+            .map_err(|errors| errors.iter().map(ConfigError::to_string).collect::<Vec<_>>().join("\n"))
+    }
+
+    /// Parse the configuration, with each of `overlays` merged over
+    /// it in order (see [merge_overlay]), reporting every problem
+    /// found rather than stopping at the first.
+    pub fn parse_with_errors(
+        contents: &str,
+        overlays: &[&str],
+        toolchain_name: Option<&str>,
+        symtab: &Symtab,
+        type_info: &IRTypeInfo,
+    ) -> Result<Self, Vec<ConfigError>> {
+        let mut sources = vec![contents];
+        sources.extend_from_slice(overlays);
+
+        let mut config = Value::Table(toml::value::Table::new());
+        for (source, contents) in sources.iter().enumerate() {
+            match contents.parse::<Value>() {
+                Ok(overlay) => merge_overlay(&mut config, overlay),
+                Err(e) => {
+                    return Err(vec![ConfigError {
+                        key: String::new(),
+                        source,
+                        line: e.line_col().map(|(line, _)| line + 1),
+                        message: format!("Error when parsing configuration: {}", e),
+                    }])
+                }
+            }
+        }
+
+        let mut errors = ConfigErrors::new(&sources);
+        errors.check_schema(&config);
 
         // Insert the translation_function into the set of functions
         // to trace, if it is provided by the config
-        let translation_function = get_translation_function(&config, symtab)?;
-        let mut trace_functions = get_trace_functions(&config, symtab)?;
+        let translation_function = errors.check("translation_function", get_translation_function(&config, symtab));
+        let mut trace_functions = errors.check("trace", get_trace_functions(&config, symtab));
         if let Some(f) = translation_function {
             trace_functions.insert(f);
         }
 
-        let toolchain = get_toolchain(&config, toolchain_name)?;
-
-        Ok(ISAConfig {
-            pc: get_program_counter(&config, symtab)?,
-            register_event_sets: get_register_event_sets(&config, symtab)?,
+        // Calls to exception functions must be traced so the
+        // exceptions taken on each path can be found
+        let exception_functions = errors.check("exceptions", get_exception_functions(&config, symtab));
+        trace_functions.extend(exception_functions.keys().copied());
+
+        let pc = errors.check("pc", get_program_counter(&config, symtab).map(Some));
+        let toolchain = errors.check("toolchain", get_toolchain(&config, toolchain_name));
+        let (data_endianness, instruction_endianness) = errors.check("endianness", get_endianness(&config));
+        let (precise_blocks, block_functions) = errors.check("blocks", get_blocks(&config, symtab));
+        let mut register_bitfields = errors.check("registers.bitfields", get_register_bitfields(&config, symtab));
+        errors.check("registers.lanes", add_register_lanes(&config, symtab, &mut register_bitfields));
+
+        let mut isa_config = ISAConfig {
+            // The placeholder is never used, as a missing pc is an error
+            pc: pc.unwrap_or_else(|| Name::from_u32(0)),
+            register_event_sets: errors.check("registers", get_register_event_sets(&config, symtab)),
             assembler: toolchain.assembler,
             objdump: toolchain.objdump,
             nm: toolchain.nm,
             linker: toolchain.linker,
-            page_table_base: get_table_value(&config, "mmu", "page_table_base")?,
-            page_size: get_table_value(&config, "mmu", "page_size")?,
-            s2_page_table_base: get_table_value(&config, "mmu", "s2_page_table_base")?,
-            s2_page_size: get_table_value(&config, "mmu", "s2_page_size")?,
+            disassembler: errors.check("disassembler", get_disassembler(&config)),
+            page_table_base: errors.check("mmu.page_table_base", get_table_value(&config, "mmu", "page_table_base")),
+            page_size: errors.check("mmu.page_size", get_table_value(&config, "mmu", "page_size")),
+            s2_page_table_base: errors
+                .check("mmu.s2_page_table_base", get_table_value(&config, "mmu", "s2_page_table_base")),
+            s2_page_size: errors.check("mmu.s2_page_size", get_table_value(&config, "mmu", "s2_page_size")),
             default_page_table_setup: get_table_string(&config, "mmu", "default_setup")
                 .unwrap_or_else(|_| String::new()),
-            thread_base: get_table_value(&config, "threads", "base")?,
-            thread_top: get_table_value(&config, "threads", "top")?,
-            thread_stride: get_table_value(&config, "threads", "stride")?,
-            symbolic_addr_base: get_table_value(&config, "symbolic_addrs", "base")?,
-            symbolic_addr_top: get_table_value(&config, "symbolic_addrs", "top")?,
-            symbolic_addr_stride: get_table_value(&config, "symbolic_addrs", "stride")?,
-            default_registers: get_default_registers(&config, symtab, type_info)?,
-            reset_registers: get_reset_registers(&config, symtab, type_info)?,
-            reset_constraints: get_reset_constraints(&config)?,
-            const_primops: get_const_primops(&config, symtab, type_info)?,
+            thread_base: errors.check("threads.base", get_table_value(&config, "threads", "base")),
+            thread_top: errors.check("threads.top", get_table_value(&config, "threads", "top")),
+            thread_stride: errors.check("threads.stride", get_table_value(&config, "threads", "stride")),
+            symbolic_addr_base: errors.check("symbolic_addrs.base", get_table_value(&config, "symbolic_addrs", "base")),
+            symbolic_addr_top: errors.check("symbolic_addrs.top", get_table_value(&config, "symbolic_addrs", "top")),
+            symbolic_addr_stride: errors
+                .check("symbolic_addrs.stride", get_table_value(&config, "symbolic_addrs", "stride")),
+            data_endianness,
+            instruction_endianness,
+            zero_region: errors.check("memory", get_zero_region(&config)),
+            solver_seed: errors.check("solver.seed", get_solver_seed(&config)),
+            solver_params: errors.check("solver.params", get_solver_params(&config)),
+            address_randomization: errors.check("address_randomization", get_address_randomization(&config)),
+            default_registers: errors.check("registers.defaults", get_default_registers(&config, symtab, type_info)),
+            reset_registers: errors.check("registers.reset", get_reset_registers(&config, symtab, type_info)),
+            reset_constraints: errors.check("constraints.reset", get_constraints(&config, "reset")),
+            assumptions: errors.check("constraints.assume", get_constraints(&config, "assume")),
+            const_primops: errors.check("const_primops", get_const_primops(&config, symtab, type_info)),
+            let_values: errors.check("lets", get_let_values(&config, symtab, type_info)),
             function_assumptions: Vec::new(),
-            register_renames: get_register_renames(&config, symtab)?,
-            ignored_registers: get_registers_set(&config, "ignore", symtab)?,
-            relaxed_registers: get_registers_set(&config, "relaxed", symtab)?,
+            register_renames: errors.check("registers.renames", get_register_renames(&config, symtab)),
+            register_aliases: errors.check("registers.aliases", get_register_aliases(&config, symtab)),
+            ignored_registers: errors.check("registers.ignore", get_registers_set(&config, "ignore", symtab)),
+            relaxed_registers: errors.check("registers.relaxed", get_registers_set(&config, "relaxed", symtab)),
+            register_bitfields,
+            flags: errors.check("registers.flags", get_flags(&config, symtab)),
+            symbolic_registers: HashSet::new(),
+            concrete_registers: errors.check("registers.concrete", get_registers_set(&config, "concrete", symtab)),
             probes: HashSet::new(),
+            register_probes: HashSet::new(),
             trace_functions,
+            pure_functions: errors.check("pure", get_pure_functions(&config, symtab)),
+            function_stubs: errors.check("stubs", get_function_stubs(&config, symtab, type_info)),
             translation_function,
-            in_program_order: get_in_program_order(&config, symtab)?,
-            default_sizeof: get_default_sizeof(&config)?,
-            zero_announce_exit: get_zero_announce_exit(&config)?,
-        })
+            decode_function: errors.check("decode_function", get_decode_function(&config, symtab)),
+            in_program_order: errors.check("in_program_order", get_in_program_order(&config, symtab)),
+            default_sizeof: errors.check("default_sizeof", get_default_sizeof(&config)),
+            zero_announce_exit: errors.check("zero_announce_exit", get_zero_announce_exit(&config)),
+            alignment_check: errors.check("alignment_check", get_alignment_check(&config, symtab)),
+            exception_functions,
+            precise_blocks,
+            block_functions,
+            alias_analysis: errors.check("alias_analysis", get_alias_analysis(&config)),
+            optimize_ir: errors.check("optimize_ir", get_optimize_ir(&config)),
+            access_kinds: errors.check("access_kinds", get_access_kinds(&config, symtab, type_info)),
+            privilege_levels: errors.check("privilege_levels", get_privilege_levels(&config)),
+            memory_regions: errors.check("memory_regions", get_memory_regions(&config)),
+        };
+
+        let symbolic_registers: Vec<Name> =
+            errors.check("registers.symbolic", get_registers_set(&config, "symbolic", symtab));
+        errors.finish()?;
+
+        for register in symbolic_registers {
+            isa_config.set_symbolic_register(register)
+        }
+
+        Ok(isa_config)
+    }
+
+    /// Make a register start as a fresh symbolic variable, dropping
+    /// any default or reset value the configuration gives it.
+    /// Move the page tables, thread memory, and symbolic addresses by
+    /// a random offset within the bounds of the
+    /// `address_randomization` table, returning the offset.
+    pub fn randomize_addresses<R: Rng>(&mut self, rng: &mut R) -> Result<u64, String> {
+        let bounds = self
+            .address_randomization
+            .ok_or_else(|| "The configuration has no address_randomization table".to_string())?;
+        let offset = bounds.random_offset(rng);
+        let overflow = |name: &str| format!("Offsetting {} by 0x{:x} overflows", name, offset);
+        for (name, addr) in [
+            ("mmu.page_table_base", &mut self.page_table_base),
+            ("mmu.s2_page_table_base", &mut self.s2_page_table_base),
+            ("threads.base", &mut self.thread_base),
+            ("threads.top", &mut self.thread_top),
+            ("symbolic_addrs.base", &mut self.symbolic_addr_base),
+            ("symbolic_addrs.top", &mut self.symbolic_addr_top),
+        ] {
+            *addr = addr.checked_add(offset).ok_or_else(|| overflow(name))?
+        }
+        Ok(offset)
+    }
+
+    pub fn set_symbolic_register(&mut self, register: Name) {
+        self.concrete_registers.remove(&register);
+        self.default_registers.remove(&register);
+        self.reset_registers.retain(|(loc, _)| loc.id() != register);
+        self.symbolic_registers.insert(register);
+    }
+
+    /// Make a register start with a concrete value. If the
+    /// configuration has no default for it, the model's register
+    /// initializer is used even when model initializers are
+    /// otherwise disabled.
+    pub fn set_concrete_register(&mut self, register: Name) {
+        self.symbolic_registers.remove(&register);
+        self.concrete_registers.insert(register);
+    }
+
+    /// The canonical register for a register which is an alias given
+    /// by `registers.aliases`, or the register itself otherwise.
+    pub fn canonical_register(&self, register: Name) -> Name {
+        self.register_aliases.get(&register).map_or(register, |alias| alias.register)
+    }
+
+    /// The aliases of a canonical register.
+    pub fn register_views(&self, register: Name) -> impl Iterator<Item = Name> + '_ {
+        self.register_aliases.iter().filter(move |(_, alias)| alias.register == register).map(|(view, _)| *view)
+    }
+
+    /// Describe an alias as accessed, e.g. `W0=R0[31:0]`, or just give
+    /// the register's name if it is not an alias.
+    pub fn register_view_string(&self, register: Name, symtab: &Symtab) -> String {
+        let name = zencode::decode(symtab.to_str(register));
+        match self.register_aliases.get(&register) {
+            Some(RegisterAlias { register: canonical, slice }) => {
+                let canonical = zencode::decode(symtab.to_str(*canonical));
+                match slice {
+                    Some((hi, lo)) => format!("{}={}[{}:{}]", name, canonical, hi, lo),
+                    None => format!("{}={}", name, canonical),
+                }
+            }
+            None => name,
+        }
+    }
+
+    /// Find an access kind by name
+    pub fn access_kind(&self, name: &str) -> Option<&AccessKind> {
+        self.access_kinds.iter().find(|kind| kind.name == name)
+    }
+
+    /// The names of the access kinds of a memory read or write event
+    pub fn access_kinds_of(&self, event: &Event<B>) -> Vec<&str> {
+        self.access_kinds.iter().filter(|kind| kind.matches(event)).map(|kind| kind.name.as_str()).collect()
+    }
+
+    /// Rename every access to an aliased register in `events` to its
+    /// canonical register, returning the aliases which were accessed.
+    /// The values accessed are left unchanged, so for an alias of
+    /// part of a register they are only the accessed slice.
+    pub fn canonicalize_registers(&self, events: &mut [Event<B>]) -> HashSet<Name> {
+        let mut views = HashSet::new();
+        if self.register_aliases.is_empty() {
+            return views;
+        }
+        for event in events.iter_mut() {
+            let registers = match event {
+                Event::ReadReg(register, _, _, _)
+                | Event::WriteReg(register, _, _, _)
+                | Event::AssumeReg(register, _, _) => std::slice::from_mut(register),
+                Event::MarkReg { regs, .. } => regs.as_mut_slice(),
+                _ => continue,
+            };
+            for register in registers {
+                if let Some(alias) = self.register_aliases.get(register) {
+                    views.insert(*register);
+                    *register = alias.register
+                }
+            }
+        }
+        views
     }
 
     pub fn read_event_registers(&self) -> HashSet<Name> {
@@ -753,20 +1797,227 @@ impl<B: BV> ISAConfig<B> {
     where
         P: AsRef<Path>,
     {
-        let mut contents = String::new();
-        match File::open(&path) {
-            Ok(mut handle) => match handle.read_to_string(&mut contents) {
-                Ok(_) => (),
-                Err(e) => return Err(format!("Unexpected failure while reading config: {}", e)),
-            },
-            Err(e) => return Err(format!("Error when loading config '{}': {}", path.as_ref().display(), e)),
-        };
-        hasher.input(&contents);
+        Self::from_file_with_overlays(hasher, path, &[], toolchain_name, symtab, type_info)
+    }
+
+    /// Load the configuration from a TOML file, and merge each of the
+    /// `overlays` files over it in order.
+    pub fn from_file_with_overlays<P>(
+        hasher: &mut Sha256,
+        path: P,
+        overlays: &[P],
+        toolchain_name: Option<&str>,
+        symtab: &Symtab,
+        type_info: &IRTypeInfo,
+    ) -> Result<Self, String>
+    where
+        P: AsRef<Path>,
+    {
+        let paths: Vec<&Path> = std::iter::once(&path).chain(overlays).map(AsRef::as_ref).collect();
+        let mut sources = Vec::new();
+        for path in &paths {
+            let mut contents = String::new();
+            match File::open(path) {
+                Ok(mut handle) => match handle.read_to_string(&mut contents) {
+                    Ok(_) => (),
+                    Err(e) => return Err(format!("Unexpected failure while reading config: {}", e)),
+                },
+                Err(e) => return Err(format!("Error when loading config '{}': {}", path.display(), e)),
+            };
+            hasher.input(&contents);
+            sources.push(contents)
+        }
         hasher.input(toolchain_name.unwrap_or("default"));
 
-        match Self::parse(&contents, toolchain_name, symtab, type_info) {
-            Ok(config) => Ok(config),
-            Err(msg) => Err(format!("{}: {}", path.as_ref().display(), msg)),
+        let overlays: Vec<&str> = sources[1..].iter().map(String::as_str).collect();
+        Self::parse_with_errors(&sources[0], &overlays, toolchain_name, symtab, type_info).map_err(|errors| {
+            errors
+                .iter()
+                .map(|error| {
+                    let path = paths[error.source].display();
+                    match error.line {
+                        Some(line) => format!("{}:{}: {}", path, line, error.message),
+                        None => format!("{}: {}", path, error.message),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlay_precedence() {
+        let mut config: Value =
+            "pc = \"_PC\"\n[registers]\nignore = [\"A\", \"B\"]\n[registers.defaults]\nX = \"0x0\"\n".parse().unwrap();
+        let overlays = ["[registers]\nignore = [\"C\"]\n", "[registers.defaults]\nY = \"0x1\"\n", "pc = \"_PC2\"\n"];
+        for overlay in overlays {
+            merge_overlay(&mut config, overlay.parse().unwrap())
+        }
+        let expected: Value =
+            "pc = \"_PC2\"\n[registers]\nignore = [\"C\"]\n[registers.defaults]\nX = \"0x0\"\nY = \"0x1\"\n"
+                .parse()
+                .unwrap();
+        assert_eq!(config, expected)
+    }
+
+    #[test]
+    fn register_aliases() {
+        let mut symtab = Symtab::new();
+        let r0 = symtab.intern("zR0");
+        let w0 = symtab.intern("zW0");
+        let x0 = symtab.intern("zX0");
+        let config: Value =
+            "[registers.aliases]\nW0 = { register = \"R0\", slice = [31, 0] }\nX0 = \"R0\"\n".parse().unwrap();
+        let aliases = get_register_aliases(&config, &symtab).unwrap();
+        assert_eq!(aliases[&w0], RegisterAlias { register: r0, slice: Some((31, 0)) });
+        assert_eq!(aliases[&x0], RegisterAlias { register: r0, slice: None });
+
+        let chained: Value = "[registers.aliases]\nW0 = \"X0\"\nX0 = \"R0\"\n".parse().unwrap();
+        assert!(get_register_aliases(&chained, &symtab).is_err());
+        let reversed: Value = "[registers.aliases]\nW0 = { register = \"R0\", slice = [0, 31] }\n".parse().unwrap();
+        assert!(get_register_aliases(&reversed, &symtab).is_err())
+    }
+
+    #[test]
+    fn address_randomization() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let config: Value = "[mmu]\npage_size = \"0x1000\"\n".parse().unwrap();
+        assert_eq!(get_address_randomization(&config).unwrap(), None);
+        let config: Value =
+            "[mmu]\npage_size = \"0x1000\"\n[address_randomization]\nmax_offset = \"0x10000\"\n".parse().unwrap();
+        let bounds = get_address_randomization(&config).unwrap().unwrap();
+        assert_eq!(bounds, AddressRandomization { max_offset: 0x10000, align: 0x1000 });
+        let config: Value = "[address_randomization]\nmax_offset = \"0x10000\"\nalign = \"0x30\"\n".parse().unwrap();
+        assert!(get_address_randomization(&config).is_err());
+
+        for seed in 0..20 {
+            let offset = bounds.random_offset(&mut StdRng::seed_from_u64(seed));
+            assert!(offset <= 0x10000 && offset & 0xfff == 0);
+            assert_eq!(bounds.random_offset(&mut StdRng::seed_from_u64(seed)), offset)
+        }
+    }
+
+    #[test]
+    fn zero_region() {
+        let config: Value = "pc = \"_PC\"\n".parse().unwrap();
+        assert_eq!(get_zero_region(&config).unwrap(), 0..0xffff_ffff_ffff_ffff);
+        let config: Value = "[memory]\nzero_top = \"0x100000000\"\n".parse().unwrap();
+        assert_eq!(get_zero_region(&config).unwrap(), 0..0x1_0000_0000);
+        let config: Value = "[memory]\nzero_base = \"0x2000\"\nzero_top = \"0x1000\"\n".parse().unwrap();
+        assert!(get_zero_region(&config).is_err())
+    }
+
+    #[test]
+    fn solver_params() {
+        let config: Value =
+            "[solver]\nseed = 7\n[solver.params]\nsmt.relevancy = 0\n\"timeout\" = \"1000\"\n".parse().unwrap();
+        assert_eq!(get_solver_seed(&config).unwrap(), Some(7));
+        assert_eq!(
+            get_solver_params(&config).unwrap(),
+            vec![("smt.relevancy".to_string(), "0".to_string()), ("timeout".to_string(), "1000".to_string())]
+        );
+        let config: Value = "[solver]\nseed = -1\n".parse().unwrap();
+        assert!(get_solver_seed(&config).is_err())
+    }
+
+    #[test]
+    fn privilege_levels() {
+        let config: Value =
+            "[privilege_levels]\nEL1 = [\"(= EL #b01)\"]\nEL0 = [\"(= EL #b00)\", \"(= SP #b0)\"]\n".parse().unwrap();
+        let levels = get_privilege_levels(&config).unwrap();
+        let names: Vec<&str> = levels.iter().map(|level| level.name.as_str()).collect();
+        assert_eq!(names, vec!["EL0", "EL1"]);
+        assert_eq!(levels[0].constraints.len(), 2);
+        let config: Value = "[privilege_levels]\nEL0 = \"(= EL #b00)\"\n".parse().unwrap();
+        assert!(get_privilege_levels(&config).is_err());
+        let config: Value = "[privilege_levels]\nEL0 = [\"(= EL\"]\n".parse().unwrap();
+        assert!(get_privilege_levels(&config).is_err())
+    }
+
+    #[test]
+    fn function_stubs() {
+        use crate::bitvector::b64::B64;
+
+        let mut symtab = Symtab::new();
+        let (f, g, r) = (symtab.intern("zf"), symtab.intern("zg"), symtab.intern("zR"));
+        let type_info = IRTypeInfo::new::<B64>(&[]);
+        let config: Value = "[stubs]\nf = \"havoc\"\ng = { havoc = [\"R\"], cases = [{ args = [\"0x01\", \"_\"], result = \"0x10\" }], default = \"0x20\" }\n"
+            .parse()
+            .unwrap();
+        let stubs = get_function_stubs::<B64>(&config, &symtab, &type_info).unwrap();
+        assert!(stubs[&f].cases.is_empty() && stubs[&f].default.is_none() && stubs[&f].havoc.is_empty());
+        assert_eq!(stubs[&g].havoc, vec![r]);
+        assert_eq!(stubs[&g].cases.len(), 1);
+        assert_eq!(stubs[&g].cases[0].args, vec![Some(Val::Bits(B64::new(1, 8))), None]);
+        assert_eq!(stubs[&g].cases[0].result, Val::Bits(B64::new(0x10, 8)));
+        assert_eq!(stubs[&g].default, Some(Val::Bits(B64::new(0x20, 8))));
+
+        let config: Value = "[stubs]\ng = { default = \"0x20\" }\n".parse().unwrap();
+        let stubs = get_function_stubs::<B64>(&config, &symtab, &type_info).unwrap();
+        assert!(stubs[&g].cases.is_empty() && stubs[&g].havoc.is_empty());
+        assert_eq!(stubs[&g].default, Some(Val::Bits(B64::new(0x20, 8))));
+
+        for stubs in [
+            "[stubs]\nh = \"havoc\"\n",
+            "[stubs]\ng = { havoc = [\"S\"] }\n",
+            "[stubs]\ng = { havoc = \"R\" }\n",
+            "[stubs]\ng = { cases = [{ args = [\"0x01\"] }] }\n",
+            "[stubs]\ng = { result = \"0x10\" }\n",
+        ] {
+            let config: Value = stubs.parse().unwrap();
+            assert!(get_function_stubs::<B64>(&config, &symtab, &type_info).is_err(), "{}", stubs)
         }
     }
+
+    #[test]
+    fn blocks() {
+        let (zero, clean) = (zencode::encode("dc_zva"), zencode::encode("dc_cvac"));
+        let mut symtab = Symtab::new();
+        let zero = symtab.intern(&zero);
+        let clean = symtab.intern(&clean);
+        let address = symtab.intern("zaddress");
+        let config: Value = "[blocks]\nprecise = true\nsizes = { zva = 64, line = 16 }\n[blocks.functions]\ndc_zva = \"zva\"\ndc_cvac = { block = \"line\", address = \"address\" }\n"
+            .parse()
+            .unwrap();
+        let (precise, functions) = get_blocks(&config, &symtab).unwrap();
+        assert!(precise);
+        assert_eq!(functions.len(), 2);
+        assert!(matches!(functions[&zero], BlockFunction { bytes: 64, address_field: None }));
+        assert!(
+            matches!(functions[&clean], BlockFunction { bytes: 16, address_field: Some(field) } if field == address)
+        );
+
+        let config: Value = "pc = \"_PC\"\n".parse().unwrap();
+        let (precise, functions) = get_blocks(&config, &symtab).unwrap();
+        assert!(!precise && functions.is_empty());
+        let config: Value = "[blocks]\nsizes = { zva = 48 }\n".parse().unwrap();
+        assert!(get_blocks(&config, &symtab).is_err());
+        let config: Value = "[blocks.functions]\ndc_zva = \"zva\"\n".parse().unwrap();
+        assert!(get_blocks(&config, &symtab).is_err());
+        let config: Value =
+            "[blocks]\nsizes = { zva = 64 }\n[blocks.functions]\ndc_missing = \"zva\"\n".parse().unwrap();
+        assert!(get_blocks(&config, &symtab).is_err())
+    }
+
+    #[test]
+    fn memory_regions() {
+        let config: Value = "[memory_regions]\nstack = { base = \"0x8000\", top = \"0x9000\" }\ncode = { base = \"0\", top = \"0x1000\" }\n"
+            .parse()
+            .unwrap();
+        let regions = get_memory_regions(&config).unwrap();
+        let names: Vec<&str> = regions.iter().map(|region| region.name.as_str()).collect();
+        assert_eq!(names, vec!["code", "stack"]);
+        assert_eq!(regions[1].range, 0x8000..0x9000);
+        let config: Value = "[memory_regions]\nstack = { base = \"0x9000\", top = \"0x8000\" }\n".parse().unwrap();
+        assert!(get_memory_regions(&config).is_err());
+        let config: Value = "[memory_regions]\nstack = { base = \"0x8000\" }\n".parse().unwrap();
+        assert!(get_memory_regions(&config).is_err())
+    }
 }
@@ -0,0 +1,359 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The layout of the ISA configuration file. Each key the
+//! configuration reads is given a type here, so a configuration can
+//! be checked against it before its values are interpreted, and every
+//! problem with it can be reported along with the line of the file it
+//! occurs on.
+
+use std::fmt;
+use toml::Value;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum KeyType {
+    Boolean,
+    Integer,
+    String,
+    Array,
+    Table,
+}
+
+impl KeyType {
+    fn matches(self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (KeyType::Boolean, Value::Boolean(_))
+                | (KeyType::Integer, Value::Integer(_))
+                | (KeyType::String, Value::String(_))
+                | (KeyType::Array, Value::Array(_))
+                | (KeyType::Table, Value::Table(_))
+        )
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            KeyType::Boolean => "a boolean",
+            KeyType::Integer => "an integer",
+            KeyType::String => "a string",
+            KeyType::Array => "a list",
+            KeyType::Table => "a table",
+        }
+    }
+}
+
+/// Every key in the configuration and its type. Tables listed here
+/// may only contain the keys listed within them, except for those in
+/// `OPEN_TABLES` whose keys are names from the architecture.
+const SCHEMA: &[(&str, KeyType)] = &[
+    ("pc", KeyType::String),
+    ("endianness", KeyType::String),
+    ("toolchain", KeyType::Array),
+    ("assembler", KeyType::String),
+    ("objdump", KeyType::String),
+    ("nm", KeyType::String),
+    ("linker", KeyType::String),
+    ("disassembler", KeyType::String),
+    ("mmu", KeyType::Table),
+    ("mmu.page_table_base", KeyType::String),
+    ("mmu.page_size", KeyType::String),
+    ("mmu.s2_page_table_base", KeyType::String),
+    ("mmu.s2_page_size", KeyType::String),
+    ("mmu.default_setup", KeyType::String),
+    ("threads", KeyType::Table),
+    ("threads.base", KeyType::String),
+    ("threads.top", KeyType::String),
+    ("threads.stride", KeyType::String),
+    ("symbolic_addrs", KeyType::Table),
+    ("symbolic_addrs.base", KeyType::String),
+    ("symbolic_addrs.top", KeyType::String),
+    ("symbolic_addrs.stride", KeyType::String),
+    ("memory", KeyType::Table),
+    ("memory.zero_base", KeyType::String),
+    ("memory.zero_top", KeyType::String),
+    ("solver", KeyType::Table),
+    ("solver.seed", KeyType::Integer),
+    ("solver.params", KeyType::Table),
+    ("address_randomization", KeyType::Table),
+    ("address_randomization.max_offset", KeyType::String),
+    ("address_randomization.align", KeyType::String),
+    ("registers", KeyType::Table),
+    ("registers.ignore", KeyType::Array),
+    ("registers.relaxed", KeyType::Array),
+    ("registers.concrete", KeyType::Array),
+    ("registers.symbolic", KeyType::Array),
+    ("registers.flags", KeyType::Array),
+    ("registers.defaults", KeyType::Table),
+    ("registers.reset", KeyType::Table),
+    ("registers.renames", KeyType::Table),
+    ("registers.aliases", KeyType::Table),
+    ("registers.read_events", KeyType::Table),
+    ("registers.write_events", KeyType::Table),
+    ("registers.bitfields", KeyType::Table),
+    ("registers.lanes", KeyType::Table),
+    ("constraints", KeyType::Table),
+    ("constraints.reset", KeyType::Array),
+    ("constraints.assume", KeyType::Array),
+    ("const_primops", KeyType::Table),
+    ("lets", KeyType::Table),
+    ("translation_function", KeyType::String),
+    ("decode_function", KeyType::String),
+    ("trace", KeyType::Array),
+    ("pure", KeyType::Array),
+    ("stubs", KeyType::Table),
+    ("in_program_order", KeyType::Array),
+    ("exceptions", KeyType::Table),
+    ("default_sizeof", KeyType::Integer),
+    ("zero_announce_exit", KeyType::Boolean),
+    ("alias_analysis", KeyType::Boolean),
+    ("optimize_ir", KeyType::Boolean),
+    ("access_kinds", KeyType::Table),
+    ("privilege_levels", KeyType::Table),
+    ("memory_regions", KeyType::Table),
+    ("alignment_check", KeyType::Table),
+    ("alignment_check.register", KeyType::String),
+    ("alignment_check.field", KeyType::String),
+    ("alignment_check.bit", KeyType::Integer),
+    ("blocks", KeyType::Table),
+    ("blocks.precise", KeyType::Boolean),
+    ("blocks.sizes", KeyType::Table),
+    ("blocks.functions", KeyType::Table),
+];
+
+/// Tables whose keys are registers, functions, or other names, and
+/// so are checked when they are read rather than against the schema.
+const OPEN_TABLES: &[&str] = &[
+    "registers.defaults",
+    "registers.reset",
+    "registers.renames",
+    "registers.aliases",
+    "registers.read_events",
+    "registers.write_events",
+    "registers.bitfields",
+    "registers.lanes",
+    "solver.params",
+    "const_primops",
+    "lets",
+    "stubs",
+    "exceptions",
+    "blocks.sizes",
+    "blocks.functions",
+    "access_kinds",
+    "privilege_levels",
+    "memory_regions",
+];
+
+/// Keys used by older configuration files which are no longer read.
+const OBSOLETE_KEYS: &[&str] =
+    &["ifetch", "read_exclusives", "write_exclusives", "reads", "writes", "cache_ops", "barriers"];
+
+/// A problem with a configuration file, at the key it was found in.
+#[derive(Clone, Debug)]
+pub struct ConfigError {
+    pub key: String,
+    /// The file containing the key, where 0 is the base configuration
+    /// and `n` is its `n`th overlay
+    pub source: usize,
+    /// The line of the file containing the key (or its table), if
+    /// it is present in the file
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Find the line on which `key`, a dot-separated path such as
+/// `mmu.page_size`, is defined in `contents`.
+fn key_line(contents: &str, key: &str) -> Option<usize> {
+    let (table, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut current = "";
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            current = line.trim_start_matches('[').trim_end_matches(']').trim();
+            if current == key {
+                return Some(i + 1);
+            }
+        } else if current == table {
+            if let Some((k, _)) = line.split_once('=') {
+                if k.trim().trim_matches('"') == name {
+                    return Some(i + 1);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Find the file and line which define `key` in the base
+/// configuration `sources[0]` or its overlays, preferring later
+/// overlays as they take precedence. If the key is not present in any
+/// of them, the nearest enclosing table is used instead.
+fn locate(sources: &[&str], key: &str) -> (usize, Option<usize>) {
+    let mut key = key;
+    loop {
+        for (source, contents) in sources.iter().enumerate().rev() {
+            if let Some(line) = key_line(contents, key) {
+                return (source, Some(line));
+            }
+        }
+        match key.rsplit_once('.') {
+            Some((table, _)) => key = table,
+            None => return (0, None),
+        }
+    }
+}
+
+/// Collects the problems found in a configuration file, so they can
+/// all be reported at once.
+pub(super) struct ConfigErrors<'a> {
+    sources: &'a [&'a str],
+    errors: Vec<ConfigError>,
+}
+
+impl<'a> ConfigErrors<'a> {
+    pub(super) fn new(sources: &'a [&'a str]) -> Self {
+        ConfigErrors { sources, errors: Vec::new() }
+    }
+
+    fn push(&mut self, key: &str, message: String) {
+        let (source, line) = locate(self.sources, key);
+        self.errors.push(ConfigError { key: key.to_string(), source, line, message })
+    }
+
+    /// Check the types of the keys in `config` against the schema,
+    /// and warn about keys which are not part of it.
+    pub(super) fn check_schema(&mut self, config: &Value) {
+        self.check_table(config, "")
+    }
+
+    fn check_table(&mut self, table: &Value, prefix: &str) {
+        let Some(table) = table.as_table() else { return };
+        for (k, value) in table {
+            let key = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+            match SCHEMA.iter().find(|(schema_key, _)| *schema_key == key) {
+                Some((_, ty)) if !ty.matches(value) => self.push(&key, format!("{} should be {}", key, ty.name())),
+                Some((_, KeyType::Table)) if !OPEN_TABLES.contains(&key.as_str()) => self.check_table(value, &key),
+                Some(_) => (),
+                None if prefix.is_empty() && OBSOLETE_KEYS.contains(&k.as_str()) => (),
+                None => match locate(self.sources, &key) {
+                    (0, Some(line)) => {
+                        eprintln!("Warning: Unknown key {} on line {} of configuration is ignored", key, line)
+                    }
+                    (source, Some(line)) => eprintln!(
+                        "Warning: Unknown key {} on line {} of configuration overlay {} is ignored",
+                        key, line, source
+                    ),
+                    (_, None) => eprintln!("Warning: Unknown key {} in configuration is ignored", key),
+                },
+            }
+        }
+    }
+
+    /// Record the error from reading `key`, if any, returning a
+    /// default value in its place. Errors for keys which have already
+    /// failed the schema check are not repeated.
+    pub(super) fn check<T: Default>(&mut self, key: &str, result: Result<T, String>) -> T {
+        result.unwrap_or_else(|message| {
+            let reported =
+                self.errors.iter().any(|error| error.key == key || key.starts_with(&format!("{}.", error.key)));
+            if !reported {
+                self.push(key, message)
+            }
+            T::default()
+        })
+    }
+
+    /// Return the errors in the order they occur in the files, with
+    /// those for keys that are missing from them last.
+    pub(super) fn finish(mut self) -> Result<(), Vec<ConfigError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            self.errors.sort_by_key(|error| (error.line.is_none(), error.source, error.line));
+            Err(self.errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"pc = "_PC"
+zero_announce_exit = "yes"
+
+[mmu]
+page_table_base = "0x300000"
+page_size = 4096
+
+[registers.defaults]
+"_PC" = "0x0"
+"#;
+
+    #[test]
+    fn schema_errors() {
+        let config = CONFIG.parse::<Value>().unwrap();
+        let mut errors = ConfigErrors::new(&[CONFIG]);
+        errors.check_schema(&config);
+        assert_eq!(errors.check("mmu.page_size", Err::<u64, _>("repeated".to_string())), 0);
+        assert_eq!(errors.check("threads.base", Err::<u64, _>("No threads.base found in config".to_string())), 0);
+
+        let errors: Vec<String> = errors.finish().unwrap_err().iter().map(ConfigError::to_string).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 2: zero_announce_exit should be a boolean",
+                "line 6: mmu.page_size should be a string",
+                "No threads.base found in config",
+            ]
+        )
+    }
+
+    #[test]
+    fn key_lines() {
+        const OVERLAY: &str = "[mmu]\npage_size = \"4096\"\n";
+        let sources = [CONFIG, OVERLAY];
+        assert_eq!(locate(&sources, "pc"), (0, Some(1)));
+        assert_eq!(locate(&sources, "mmu.page_table_base"), (0, Some(5)));
+        assert_eq!(locate(&sources, "mmu.page_size"), (1, Some(2)));
+        assert_eq!(locate(&sources, "mmu.s2_page_size"), (1, Some(1)));
+        assert_eq!(locate(&sources, "registers.defaults"), (0, Some(8)));
+        assert_eq!(locate(&sources, "registers.defaults._PC"), (0, Some(9)));
+        assert_eq!(locate(&sources, "registers.ignore"), (0, None));
+        assert_eq!(locate(&sources, "lets"), (0, None))
+    }
+}
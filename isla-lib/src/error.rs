@@ -30,6 +30,7 @@
 use std::error::Error;
 use std::fmt;
 
+use crate::bitvector::length_check::LengthError;
 use crate::source_loc::SourceLoc;
 
 pub trait IslaError {
@@ -80,12 +81,22 @@ pub enum ExecError {
     Timeout,
     Dead,
     Exit,
+    /// The task ran out of its time slice, and the remainder of its
+    /// path was queued as a new task (see [crate::executor::TaskState::with_time_slice])
+    Yield,
     NoModel,
     Z3Error(String),
     Z3Unknown,
     /// Execution stopped because this function is in the stop_functions set
     Stopped(String),
     PCLimitReached(u64),
+    /// A user-provided access assertion (named by the first field)
+    /// can be violated, with the second field containing a witness
+    /// address.
+    AccessAssertion(String, String),
+    /// A bitvector operation was applied to operands with the wrong
+    /// lengths, found when bitvector length checks are enabled.
+    BitvectorLength(LengthError, SourceLoc),
 }
 
 impl IslaError for ExecError {
@@ -96,7 +107,8 @@ impl IslaError for ExecError {
             | AssertionFailure(_, info)
             | NoFunction(_, info)
             | SymbolicLength(_, info)
-            | MatchFailure(info) => *info,
+            | MatchFailure(info)
+            | BitvectorLength(_, info) => *info,
             _ => SourceLoc::unknown(),
         }
     }
@@ -125,11 +137,16 @@ impl fmt::Display for ExecError {
             Timeout => write!(f, "Timeout"),
             Dead => write!(f, "Dead code found"),
             Exit => write!(f, "Exit called"),
+            Yield => write!(f, "Time slice expired"),
             NoModel => write!(f, "No SMT model found"),
             Z3Error(msg) => write!(f, "SMT solver error: {}", msg),
             Z3Unknown => write!(f, "SMT solver returned unknown"),
             Stopped(func) => write!(f, "Execution stopped at {}", func),
             PCLimitReached(pc_value) => write!(f, "Executed instruction at {} more than specified limit", pc_value),
+            AccessAssertion(name, witness) => {
+                write!(f, "Memory access assertion {} violated, witness address: {}", name, witness)
+            }
+            BitvectorLength(err, _) => write!(f, "Bitvector length error: {}", err),
         }
     }
 }
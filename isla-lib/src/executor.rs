@@ -34,28 +34,32 @@
 use crossbeam::deque::{Injector, Steal, Stealer, Worker};
 use crossbeam::queue::SegQueue;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fmt;
 use std::mem;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::bitvector::{b64::B64, required_index_bits, BV};
+use crate::bitvector::{self, b64::B64, required_index_bits, BV};
+use crate::config::{BlockFunction, FunctionStub};
 use crate::error::{ExecError, IslaError};
 use crate::ir::*;
 use crate::log;
-use crate::memory::Memory;
+use crate::memory::{Address, Memory};
 use crate::primop;
 use crate::primop_util::{build_ite, ite_phi, smt_value, symbolic};
 use crate::probe;
 use crate::register::*;
-use crate::smt::smtlib::Def;
+use crate::simplify;
 use crate::smt::*;
 use crate::source_loc::SourceLoc;
+use crate::trace;
 use crate::zencode;
 
 #[derive(Clone)]
@@ -119,7 +123,7 @@ fn get_id_and_initialize<'state, 'ir, B: BV>(
                 let symbol = zencode::decode(shared_state.symtab.to_str(id));
                 // HACK: Don't store the entire TLB in the trace
                 if !for_write && symbol != "_TLB" {
-                    solver.add_event(Event::ReadReg(id, accessor.to_vec(), value.clone()));
+                    solver.add_event(Event::ReadReg(id, accessor.to_vec(), value.clone(), info));
                 }
                 Borrowed(value)
             }
@@ -183,6 +187,41 @@ fn fix_index_length<B: BV>(i: Sym, from: u32, to: u32, solver: &mut Solver<B>, i
     }
 }
 
+/// The block-aligned address affected by a call to a block function
+fn block_address<B: BV>(
+    args: &[Val<B>],
+    block: &BlockFunction,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Val<B>, ExecError> {
+    use smtlib::Exp::*;
+    let address = match (args.first(), block.address_field) {
+        (Some(address), None) => address,
+        (Some(Val::Struct(fields)), Some(field)) => match fields.get(&field) {
+            Some(address) => address,
+            None => return Err(ExecError::Type("Block operation address field not found".to_string(), info)),
+        },
+        _ => return Err(ExecError::Type("Block operation has no address".to_string(), info)),
+    };
+    let mask = |len: u32| {
+        if len == 0 {
+            0
+        } else {
+            !(block.bytes as u64 - 1) & (u64::MAX >> (64 - len))
+        }
+    };
+    match address {
+        Val::Bits(bv) if bv.len() <= 64 => Ok(Val::Bits(*bv & B::new(mask(bv.len()), bv.len()))),
+        Val::Symbolic(v) => match solver.length(*v) {
+            Some(len) if len <= 64 => Ok(Val::Symbolic(
+                solver.define_const(Bvand(Box::new(Var(*v)), Box::new(smtlib::bits64(mask(len), len))), info),
+            )),
+            _ => Err(ExecError::Type("Block operation address is not a bitvector".to_string(), info)),
+        },
+        _ => Err(ExecError::Type(format!("Block operation address {:?} is not a bitvector", address), info)),
+    }
+}
+
 fn read_register_from_vector<'state, 'ir, B: BV>(
     n: Val<B>,
     regs_vector: Val<B>,
@@ -238,7 +277,7 @@ fn read_register_from_vector<'state, 'ir, B: BV>(
         ConcreteIndex(i) => {
             // This unwrap should be same as all register references must point to value registers
             let value = local_state.regs.get(regs[i], shared_state, solver, info)?.unwrap();
-            solver.add_event(Event::ReadReg(regs[i], Vec::new(), value.clone()));
+            solver.add_event(Event::ReadReg(regs[i], Vec::new(), value.clone(), info));
             Ok(value.clone())
         }
         SymbolicIndex(i) => {
@@ -260,13 +299,16 @@ fn read_register_from_vector<'state, 'ir, B: BV>(
                 primitive: true,
                 args: vec![n, regs_vector, Val::Vector(reg_values)],
                 return_value: chain.clone(),
+                info,
             });
             Ok(chain)
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn write_register_from_vector<'state, 'ir, B: BV>(
+    tid: usize,
     n: Val<B>,
     value: Val<B>,
     regs_vector: Val<B>,
@@ -322,9 +364,13 @@ fn write_register_from_vector<'state, 'ir, B: BV>(
 
     match index {
         ConcreteIndex(i) => {
+            if shared_state.register_probes.contains(&regs[i]) {
+                let old_value = local_state.regs.get_last_if_initialized(regs[i]);
+                probe::register_write(tid, regs[i], old_value, &value, shared_state, solver, info)
+            }
             // This unwrap should be same as all register references must point to value registers
             local_state.regs.assign(regs[i], value.clone(), shared_state);
-            solver.add_event(Event::WriteReg(regs[i], Vec::new(), value))
+            solver.add_event(Event::WriteReg(regs[i], Vec::new(), value, info))
         }
         SymbolicIndex(i) => {
             let mut reg_values = Vec::new();
@@ -334,13 +380,13 @@ fn write_register_from_vector<'state, 'ir, B: BV>(
                     solver.define_const(Eq(Box::new(Var(i)), Box::new(Bits64(B64::new(j as u64, rib)))), info)
                 });
                 let current_value = local_state.regs.get(*reg, shared_state, solver, info)?.unwrap().clone();
-                local_state.regs.assign(
-                    *reg,
-                    solver.with_def_attrs(DefAttrs::uninteresting(), |solver| {
-                        build_ite(choice, &value, &current_value, solver, info)
-                    })?,
-                    shared_state,
-                );
+                let new_value = solver.with_def_attrs(DefAttrs::uninteresting(), |solver| {
+                    build_ite(choice, &value, &current_value, solver, info)
+                })?;
+                if shared_state.register_probes.contains(reg) {
+                    probe::register_write(tid, *reg, Some(&current_value), &new_value, shared_state, solver, info)
+                }
+                local_state.regs.assign(*reg, new_value, shared_state);
                 reg_values.push(current_value);
             }
             solver.add_event(Event::Abstract {
@@ -348,6 +394,7 @@ fn write_register_from_vector<'state, 'ir, B: BV>(
                 primitive: true,
                 args: vec![n, value, regs_vector, Val::Vector(reg_values)],
                 return_value: Val::Unit,
+                info,
             })
         }
     }
@@ -355,6 +402,45 @@ fn write_register_from_vector<'state, 'ir, B: BV>(
     Ok(())
 }
 
+/// Apply one of the built-in operators which can appear within IR
+/// expressions to its evaluated arguments.
+pub(crate) fn eval_op<B: BV>(
+    op: &Op,
+    args: Vec<Val<B>>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Val<B>, ExecError> {
+    match op {
+        Op::Lt => primop::op_lt(args[0].clone(), args[1].clone(), solver, info),
+        Op::Gt => primop::op_gt(args[0].clone(), args[1].clone(), solver, info),
+        Op::Lteq => primop::op_lteq(args[0].clone(), args[1].clone(), solver, info),
+        Op::Gteq => primop::op_gteq(args[0].clone(), args[1].clone(), solver, info),
+        Op::Eq => primop::op_eq(args[0].clone(), args[1].clone(), solver, info),
+        Op::Neq => primop::op_neq(args[0].clone(), args[1].clone(), solver, info),
+        Op::Add => primop::op_add(args[0].clone(), args[1].clone(), solver, info),
+        Op::Sub => primop::op_sub(args[0].clone(), args[1].clone(), solver, info),
+        Op::Bvnot => primop::not_bits(args[0].clone(), solver, info),
+        Op::Bvor => primop::or_bits(args[0].clone(), args[1].clone(), solver, info),
+        Op::Bvxor => primop::xor_bits(args[0].clone(), args[1].clone(), solver, info),
+        Op::Bvand => primop::and_bits(args[0].clone(), args[1].clone(), solver, info),
+        Op::Bvadd => primop::add_bits(args[0].clone(), args[1].clone(), solver, info),
+        Op::Bvsub => primop::sub_bits(args[0].clone(), args[1].clone(), solver, info),
+        Op::Bvaccess => primop::vector_access(args[0].clone(), args[1].clone(), solver, info),
+        Op::Concat => primop::append(args[0].clone(), args[1].clone(), solver, info),
+        Op::Not => primop::not_bool(args[0].clone(), solver, info),
+        Op::And => primop::and_bool(args[0].clone(), args[1].clone(), solver, info),
+        Op::Or => primop::or_bool(args[0].clone(), args[1].clone(), solver, info),
+        Op::Slice(len) => primop::op_slice(args[0].clone(), args[1].clone(), *len, solver, info),
+        Op::SetSlice => primop::op_set_slice(args[0].clone(), args[1].clone(), args[2].clone(), solver, info),
+        Op::Unsigned(_) => primop::op_unsigned(args[0].clone(), solver, info),
+        Op::Signed(_) => primop::op_signed(args[0].clone(), solver, info),
+        Op::Head => primop::op_head(args[0].clone(), solver, info),
+        Op::Tail => primop::op_tail(args[0].clone(), solver, info),
+        Op::IsEmpty => primop::op_is_empty(args[0].clone(), solver, info),
+        Op::ZeroExtend(len) => primop::op_zero_extend(args[0].clone(), *len, solver, info),
+    }
+}
+
 fn eval_exp_with_accessor<'state, 'ir, B: BV>(
     exp: &Exp<Name>,
     local_state: &'state mut LocalState<'ir, B>,
@@ -371,6 +457,7 @@ fn eval_exp_with_accessor<'state, 'ir, B: BV>(
 
         I64(n) => Owned(Val::I64(*n)),
         I128(n) => Owned(Val::I128(*n)),
+        BigInt(n) => Owned(Val::BigInt(n.clone())),
         Unit => Owned(Val::Unit),
         Bool(b) => Owned(Val::Bool(*b)),
         // The parser only returns 64-bit or less bitvectors
@@ -384,35 +471,7 @@ fn eval_exp_with_accessor<'state, 'ir, B: BV>(
             for arg in unevaluated_args {
                 args.push(eval_exp(arg, local_state, shared_state, solver, info)?.into_owned())
             }
-            Owned(match op {
-                Op::Lt => primop::op_lt(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Gt => primop::op_gt(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Lteq => primop::op_lteq(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Gteq => primop::op_gteq(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Eq => primop::op_eq(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Neq => primop::op_neq(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Add => primop::op_add(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Sub => primop::op_sub(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Bvnot => primop::not_bits(args[0].clone(), solver, info)?,
-                Op::Bvor => primop::or_bits(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Bvxor => primop::xor_bits(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Bvand => primop::and_bits(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Bvadd => primop::add_bits(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Bvsub => primop::sub_bits(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Bvaccess => primop::vector_access(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Concat => primop::append(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Not => primop::not_bool(args[0].clone(), solver, info)?,
-                Op::And => primop::and_bool(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Or => primop::or_bool(args[0].clone(), args[1].clone(), solver, info)?,
-                Op::Slice(len) => primop::op_slice(args[0].clone(), args[1].clone(), *len, solver, info)?,
-                Op::SetSlice => primop::op_set_slice(args[0].clone(), args[1].clone(), args[2].clone(), solver, info)?,
-                Op::Unsigned(_) => primop::op_unsigned(args[0].clone(), solver, info)?,
-                Op::Signed(_) => primop::op_signed(args[0].clone(), solver, info)?,
-                Op::Head => primop::op_head(args[0].clone(), solver, info)?,
-                Op::Tail => primop::op_tail(args[0].clone(), solver, info)?,
-                Op::IsEmpty => primop::op_is_empty(args[0].clone(), solver, info)?,
-                Op::ZeroExtend(len) => primop::op_zero_extend(args[0].clone(), *len, solver, info)?,
-            })
+            Owned(eval_op(op, args, solver, info)?)
         }
 
         Kind(ctor_a, exp) => {
@@ -504,7 +563,9 @@ fn eval_exp<'state, 'ir, B: BV>(
     eval_exp_with_accessor(exp, local_state, shared_state, solver, &mut Vec::new(), info)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn assign_with_accessor<'ir, B: BV>(
+    tid: usize,
     loc: &Loc<Name>,
     v: Val<B>,
     local_state: &mut LocalState<'ir, B>,
@@ -523,7 +584,11 @@ fn assign_with_accessor<'ir, B: BV>(
                 let symbol = zencode::decode(shared_state.symtab.to_str(*id));
                 // HACK: Don't store the entire TLB in the trace
                 if symbol != "_TLB" {
-                    solver.add_event(Event::WriteReg(*id, accessor.to_vec(), v.clone()))
+                    solver.add_event(Event::WriteReg(*id, accessor.to_vec(), v.clone(), info))
+                }
+                if shared_state.register_probes.contains(id) {
+                    let old_value = local_state.regs.get_last_if_initialized(*id);
+                    probe::register_write(tid, *id, old_value, &v, shared_state, solver, info)
                 }
                 local_state.regs.assign(*id, v, shared_state);
             }
@@ -540,6 +605,7 @@ fn assign_with_accessor<'ir, B: BV>(
                         let mut field_values = field_values.clone();
                         field_values.insert(*field, v);
                         assign_with_accessor(
+                            tid,
                             loc,
                             Val::Struct(field_values),
                             local_state,
@@ -564,7 +630,7 @@ fn assign_with_accessor<'ir, B: BV>(
         Loc::Addr(loc) => {
             if let Val::Ref(reg) = get_loc_and_initialize(loc, local_state, shared_state, solver, accessor, info, true)?
             {
-                assign_with_accessor(&Loc::Id(reg), v, local_state, shared_state, solver, accessor, info)?
+                assign_with_accessor(tid, &Loc::Id(reg), v, local_state, shared_state, solver, accessor, info)?
             } else {
                 panic!("Cannot get address of non-reference {:?}", loc)
             }
@@ -591,7 +657,7 @@ fn assign<'ir, B: BV>(
         log_from!(tid, log::PROBE, &format!("Assigning {}[{:?}] <- {:?}", symbol, id, v))
     }
 
-    assign_with_accessor(loc, v, local_state, shared_state, solver, &mut Vec::new(), info)
+    assign_with_accessor(tid, loc, v, local_state, shared_state, solver, &mut Vec::new(), info)
 }
 
 /// The callstack is implemented as a closure that restores the
@@ -623,7 +689,9 @@ pub struct Frame<'ir, B> {
     stack_call: Stack<'ir, B>,
     backtrace: Arc<Backtrace>,
     function_assumptions: Arc<HashMap<Name, Vec<(Vec<Val<B>>, Val<B>)>>>,
+    pure_results: Arc<FunctionResults<B>>,
     pc_counts: Arc<HashMap<B, usize>>,
+    loop_counts: Arc<HashMap<usize, usize>>,
 }
 
 /// A `LocalFrame` is a mutable frame which is used by a currently
@@ -641,7 +709,12 @@ pub struct LocalFrame<'ir, B> {
     stack_call: Stack<'ir, B>,
     backtrace: Backtrace,
     function_assumptions: HashMap<Name, Vec<(Vec<Val<B>>, Val<B>)>>,
+    /// Results of calls to the pure functions in `SharedState`
+    pure_results: FunctionResults<B>,
     pc_counts: HashMap<B, usize>,
+    /// The number of jumps back to each loop head in the current
+    /// function call
+    loop_counts: HashMap<usize, usize>,
 }
 
 pub fn unfreeze_frame<'ir, B: BV>(frame: &Frame<'ir, B>) -> LocalFrame<'ir, B> {
@@ -657,7 +730,9 @@ pub fn unfreeze_frame<'ir, B: BV>(frame: &Frame<'ir, B>) -> LocalFrame<'ir, B> {
         stack_call: frame.stack_call.clone(),
         backtrace: (*frame.backtrace).clone(),
         function_assumptions: (*frame.function_assumptions).clone(),
+        pure_results: (*frame.pure_results).clone(),
         pc_counts: (*frame.pc_counts).clone(),
+        loop_counts: (*frame.loop_counts).clone(),
     }
 }
 
@@ -674,7 +749,9 @@ pub fn freeze_frame<'ir, B: BV>(frame: &LocalFrame<'ir, B>) -> Frame<'ir, B> {
         stack_call: frame.stack_call.clone(),
         backtrace: Arc::new(frame.backtrace.clone()),
         function_assumptions: Arc::new(frame.function_assumptions.clone()),
+        pure_results: Arc::new(frame.pure_results.clone()),
         pc_counts: Arc::new(frame.pc_counts.clone()),
+        loop_counts: Arc::new(frame.loop_counts.clone()),
     }
 }
 
@@ -789,7 +866,9 @@ impl<'ir, B: BV> LocalFrame<'ir, B> {
             stack_call: None,
             backtrace: Vec::new(),
             function_assumptions: HashMap::new(),
+            pure_results: HashMap::new(),
             pc_counts: HashMap::new(),
+            loop_counts: HashMap::new(),
         }
     }
 
@@ -835,6 +914,188 @@ fn pop_call_stack<B: BV>(frame: &mut LocalFrame<'_, B>) {
     }
 }
 
+/// Counts a jump to `target` as another iteration of a loop if it
+/// jumps backwards, and records a [Event::LoopBound] if this exceeds
+/// the loop bound for the current function. Any loop with a head
+/// between `target` and the jump has been left (or is nested in the
+/// loop being repeated), so its count starts again when it is next
+/// entered.
+fn loop_bound_exceeded<B: BV>(
+    target: usize,
+    frame: &mut LocalFrame<'_, B>,
+    task_state: &TaskState<B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> bool {
+    if target > frame.pc {
+        return false;
+    }
+    let Some(bound) = task_state.function_loop_bounds.get(&frame.function_name).or(task_state.loop_bound.as_ref())
+    else {
+        return false;
+    };
+    let pc = frame.pc;
+    frame.loop_counts.retain(|head, _| *head <= target || *head > pc);
+    let count = frame.loop_counts.entry(target).or_insert(0);
+    *count += 1;
+    if *count > *bound {
+        solver.add_event(Event::LoopBound { function: frame.function_name, bound: *bound, info });
+        true
+    } else {
+        false
+    }
+}
+
+/// The most instructions on either side of a branch that path merging
+/// will consider before the branch is forked instead.
+const MERGE_LIMIT: usize = 64;
+
+/// If both sides of the branch at `frame.pc` are straight-line code
+/// which only computes local variables, returns the instruction at
+/// which they join, so the branch can be merged rather than forked.
+/// Primops are only allowed if they have no side effects (see
+/// [primop::is_mergeable_unary]), as both sides are executed on the
+/// same path.
+fn merge_point<B: BV>(target: usize, frame: &LocalFrame<'_, B>, shared_state: &SharedState<'_, B>) -> Option<usize> {
+    let local_exp = |exp: &Exp<Name>| {
+        let mut ids = HashSet::new();
+        exp.collect_ids(&mut ids);
+        !ids.iter().any(|id| shared_state.registers.contains_key(id))
+    };
+    let local_loc = |loc: &Loc<Name>| {
+        !matches!(loc, Loc::Addr(_))
+            && !shared_state.registers.contains_key(&loc.id())
+            && !frame.local_state.lets.contains_key(&loc.id())
+    };
+
+    // The instructions executed from `pc`, up to and including the
+    // first which cannot be merged
+    let side = |mut pc: usize| {
+        let mut pcs = Vec::new();
+        while pcs.len() <= MERGE_LIMIT && pc < frame.instrs.len() {
+            pcs.push(pc);
+            pc = match &frame.instrs[pc] {
+                Instr::Decl(..) => pc + 1,
+                Instr::Init(_, _, exp, _) if local_exp(exp) => pc + 1,
+                Instr::Copy(loc, exp, _) if local_loc(loc) && local_exp(exp) => pc + 1,
+                Instr::PrimopUnary(loc, f, arg, _)
+                    if primop::is_mergeable_unary(*f) && local_loc(loc) && local_exp(arg) =>
+                {
+                    pc + 1
+                }
+                Instr::PrimopBinary(loc, f, arg1, arg2, _)
+                    if primop::is_mergeable_binary(*f) && local_loc(loc) && local_exp(arg1) && local_exp(arg2) =>
+                {
+                    pc + 1
+                }
+                Instr::Goto(target) if *target > pc => *target,
+                _ => break,
+            }
+        }
+        pcs
+    };
+
+    let taken = side(target);
+    let not_taken = side(frame.pc + 1);
+    taken.into_iter().find(|pc| not_taken.contains(pc))
+}
+
+/// Execute one side of a branch found by [merge_point], from `pc`
+/// until it reaches `join`.
+fn run_merge_side<'ir, B: BV>(
+    tid: usize,
+    pc: usize,
+    join: usize,
+    frame: &mut LocalFrame<'ir, B>,
+    shared_state: &SharedState<'ir, B>,
+    solver: &mut Solver<B>,
+) -> Result<(), ExecError> {
+    frame.pc = pc;
+    while frame.pc != join {
+        match &frame.instrs[frame.pc] {
+            Instr::Decl(v, ty, _) => {
+                frame.vars_mut().insert(*v, UVal::Uninit(ty));
+                frame.pc += 1
+            }
+            Instr::Init(var, _, exp, info) => {
+                let value = eval_exp(exp, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                frame.vars_mut().insert(*var, UVal::Init(value));
+                frame.pc += 1
+            }
+            Instr::Copy(loc, exp, info) => {
+                let value = eval_exp(exp, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                assign(tid, loc, value, &mut frame.local_state, shared_state, solver, *info)?;
+                frame.pc += 1
+            }
+            Instr::PrimopUnary(loc, f, arg, info) => {
+                let arg = eval_exp(arg, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                let value = f(arg, solver, *info)?;
+                assign(tid, loc, value, &mut frame.local_state, shared_state, solver, *info)?;
+                frame.pc += 1
+            }
+            Instr::PrimopBinary(loc, f, arg1, arg2, info) => {
+                let arg1 = eval_exp(arg1, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                let arg2 = eval_exp(arg2, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                let value = f(arg1, arg2, solver, *info)?;
+                assign(tid, loc, value, &mut frame.local_state, shared_state, solver, *info)?;
+                frame.pc += 1
+            }
+            Instr::Goto(target) => frame.pc = *target,
+            _ => return Err(ExecError::Unreachable("Unmergeable instruction when merging paths".to_string())),
+        }
+    }
+    Ok(())
+}
+
+/// Rather than forking at a branch on `cond`, execute both sides up
+/// to their `join` point, and combine the local variables they
+/// compute using if-then-else terms.
+#[allow(clippy::too_many_arguments)]
+fn merge_branch<'ir, B: BV>(
+    tid: usize,
+    cond: Sym,
+    target: usize,
+    join: usize,
+    frame: &mut LocalFrame<'ir, B>,
+    shared_state: &SharedState<'ir, B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<(), ExecError> {
+    let events_before = solver.trace().head().len();
+    let vars = frame.local_state.vars.clone();
+    let not_taken = frame.pc + 1;
+
+    run_merge_side(tid, target, join, frame, shared_state, solver)?;
+    let taken_vars = mem::replace(&mut frame.local_state.vars, vars);
+    run_merge_side(tid, not_taken, join, frame, shared_state, solver)?;
+
+    // merge_point only allows primops which declare and define
+    // variables, as anything else would apply to both paths
+    if !solver.trace().head()[events_before..]
+        .iter()
+        .all(|event| matches!(event, Event::Smt(smtlib::Def::DeclareConst(..) | smtlib::Def::DefineConst(..), _, _)))
+    {
+        return Err(ExecError::Unreachable("Side effect when merging paths".to_string()));
+    }
+
+    for (id, taken_value) in taken_vars {
+        match (taken_value, frame.local_state.vars.get(&id)) {
+            (UVal::Init(taken_value), Some(UVal::Init(value))) => {
+                if taken_value != *value {
+                    let merged = build_ite(cond, &taken_value, value, solver, info)?;
+                    frame.local_state.vars.insert(id, UVal::Init(merged));
+                }
+            }
+            (UVal::Uninit(_), Some(_)) => (),
+            (taken_value, _) => {
+                frame.local_state.vars.insert(id, taken_value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Copy, Clone, Debug)]
 struct Timeout {
     start_time: Instant,
@@ -867,8 +1128,65 @@ fn smt_exp_to_value<B: BV>(exp: smtlib::Exp<Sym>, solver: &mut Solver<B>) -> Res
     Ok(v)
 }
 
+/// Assert that each of `constraints`, which refer to register
+/// locations by name, holds for the register values in `frame`.
+/// Registers without a value are initialized first, so they can be
+/// constrained. Returns [ExecError::Dead] if the constraints are
+/// unsatisfiable.
+pub fn assume_constraints<'ir, B: BV>(
+    constraints: &[smtlib::Exp<Loc<String>>],
+    frame: &mut LocalFrame<'ir, B>,
+    shared_state: &SharedState<'ir, B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<(), ExecError> {
+    if constraints.is_empty() {
+        return Ok(());
+    }
+    let mut assertions = Vec::with_capacity(constraints.len());
+    for constraint in constraints {
+        let assertion_exp = constraint_exp(constraint, frame, shared_state, solver, info)?;
+        solver.add_event(Event::Assume(constraint.clone()));
+        assertions.push(assertion_exp);
+    }
+    solver.assert_all(assertions);
+    if solver.check_sat().is_unsat()? {
+        return Err(ExecError::Dead);
+    }
+    Ok(())
+}
+
+/// Translate `constraint`, which refers to register locations by
+/// name, into an SMT expression over the register values in `frame`.
+/// Registers without a value are initialized first.
+pub fn constraint_exp<'ir, B: BV>(
+    constraint: &smtlib::Exp<Loc<String>>,
+    frame: &mut LocalFrame<'ir, B>,
+    shared_state: &SharedState<'ir, B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<smtlib::Exp<Sym>, ExecError> {
+    let mut lookup = |s| match shared_state.symtab.get_loc(s) {
+        Some(loc) => {
+            let value = get_loc_and_initialize(
+                &loc,
+                &mut frame.local_state,
+                shared_state,
+                solver,
+                &mut Vec::new(),
+                info,
+                false,
+            )
+            .map_err(|e| e.to_string())?;
+            smt_value(&value, info).map_err(|e| e.to_string())
+        }
+        None => Err(format!("Location {} not found", s)),
+    };
+    constraint.map_var(&mut lookup).map_err(ExecError::Unreachable)
+}
+
 pub fn reset_registers<'ir, 'task, B: BV>(
-    _tid: usize,
+    tid: usize,
     frame: &mut LocalFrame<'ir, B>,
     task_state: &'task TaskState<B>,
     shared_state: &SharedState<'ir, B>,
@@ -880,6 +1198,7 @@ pub fn reset_registers<'ir, 'task, B: BV>(
             let value = reset(&frame.memory, shared_state.typedefs(), solver)?;
             let mut accessor = Vec::new();
             assign_with_accessor(
+                tid,
                 loc,
                 value.clone(),
                 &mut frame.local_state,
@@ -897,37 +1216,27 @@ pub fn reset_registers<'ir, 'task, B: BV>(
         }
     }
     for (loc, reset) in &task_state.reset_registers {
+        if shared_state.constant_registers.contains(&loc.id()) {
+            return Err(ExecError::Unreachable(format!(
+                "register {} is reset by a task, but was replaced by its default value when the IR was optimized",
+                zencode::decode(shared_state.symtab.to_str(loc.id()))
+            )));
+        }
         let value = reset(&frame.memory, shared_state.typedefs(), solver)?;
         let mut accessor = Vec::new();
-        assign_with_accessor(loc, value.clone(), &mut frame.local_state, shared_state, solver, &mut accessor, info)?;
+        assign_with_accessor(
+            tid,
+            loc,
+            value.clone(),
+            &mut frame.local_state,
+            shared_state,
+            solver,
+            &mut accessor,
+            info,
+        )?;
         solver.add_event(Event::AssumeReg(loc.id(), accessor, value));
     }
-    if !shared_state.reset_constraints.is_empty() {
-        for constraint in &shared_state.reset_constraints {
-            let mut lookup = |s| match shared_state.symtab.get_loc(s) {
-                Some(loc) => {
-                    let value = get_loc_and_initialize(
-                        &loc,
-                        &mut frame.local_state,
-                        shared_state,
-                        solver,
-                        &mut Vec::new(),
-                        info,
-                        false,
-                    )
-                    .map_err(|e| e.to_string())?;
-                    smt_value(&value, info).map_err(|e| e.to_string())
-                }
-                None => Err(format!("Location {} not found", s)),
-            };
-            let assertion_exp = constraint.map_var(&mut lookup).map_err(ExecError::Unreachable)?;
-            solver.add_event(Event::Assume(constraint.clone()));
-            solver.add(Def::Assert(assertion_exp));
-        }
-        if solver.check_sat().is_unsat()? {
-            return Err(ExecError::Dead);
-        }
-    }
+    assume_constraints(&shared_state.reset_constraints, frame, shared_state, solver, info)?;
     // The arguments and result of any function assumptions are
     // evaluated now so that they can refer to register values in the
     // prestate of an instruction.
@@ -1071,13 +1380,27 @@ fn run<'ir, 'task, B: BV>(
     timeout: Timeout,
     stop_conditions: Option<&'task StopConditions>,
     queue: &Worker<Task<'ir, 'task, B>>,
+    yield_queue: Option<&Injector<Task<'ir, 'task, B>>>,
     frame: &Frame<'ir, B>,
     task_state: &'task TaskState<B>,
     shared_state: &SharedState<'ir, B>,
     solver: &mut Solver<B>,
 ) -> Result<(Val<B>, LocalFrame<'ir, B>), (ExecError, Backtrace)> {
     let mut frame = unfreeze_frame(frame);
-    match run_loop(tid, task_id, timeout, stop_conditions, queue, &mut frame, task_state, shared_state, solver) {
+    // Discard any failed length check left over from outside the loop
+    let _ = bitvector::length_check::take_length_error();
+    match run_loop(
+        tid,
+        task_id,
+        timeout,
+        stop_conditions,
+        queue,
+        yield_queue,
+        &mut frame,
+        task_state,
+        shared_state,
+        solver,
+    ) {
         Ok(v) => Ok((v, frame)),
         Err(err) => {
             frame.backtrace.push((frame.function_name, frame.pc));
@@ -1086,6 +1409,74 @@ fn run<'ir, 'task, B: BV>(
     }
 }
 
+/// Computes the result of a call to a function which has been
+/// replaced by a [FunctionStub], and havocs the registers it declares.
+#[allow(clippy::too_many_arguments)]
+fn call_stub<'ir, B: BV>(
+    tid: usize,
+    stub: &FunctionStub<B>,
+    ret_ty: &Ty<Name>,
+    args: &[Val<B>],
+    local_state: &mut LocalState<'ir, B>,
+    shared_state: &SharedState<'ir, B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Val<B>, ExecError> {
+    use smtlib::Exp::*;
+
+    for reg in &stub.havoc {
+        let Some(ty) = shared_state.registers.get(reg) else {
+            let symbol = zencode::decode(shared_state.symtab.to_str(*reg));
+            return Err(ExecError::Type(format!("Stub havocs {}, which is not a register", symbol), info));
+        };
+        let value = symbolic(ty, shared_state, solver, info)?;
+        assign_with_accessor(tid, &Loc::Id(*reg), value, local_state, shared_state, solver, &mut Vec::new(), info)?
+    }
+
+    // The cases which may apply, up to the first which always does
+    let mut choices = Vec::new();
+    let mut otherwise = None;
+    'cases: for case in &stub.cases {
+        if case.args.len() != args.len() {
+            return Err(ExecError::Type(
+                format!("Stub case has {} arguments, but was called with {}", case.args.len(), args.len()),
+                info,
+            ));
+        }
+        let mut conds = Vec::new();
+        for (case_arg, arg) in case.args.iter().zip(args.iter()) {
+            let Some(case_arg) = case_arg else { continue };
+            match primop::eq_anything(case_arg.clone(), arg.clone(), solver, info)? {
+                Val::Bool(true) => (),
+                Val::Bool(false) => continue 'cases,
+                Val::Symbolic(v) => conds.push(v),
+                _ => return Err(ExecError::Type("Stub argument comparison".to_string(), info)),
+            }
+        }
+        match conds.pop() {
+            None => {
+                otherwise = Some(&case.result);
+                break;
+            }
+            Some(cond) if conds.is_empty() => choices.push((cond, &case.result)),
+            Some(cond) => {
+                let cond = conds.into_iter().fold(Var(cond), |acc, c| And(Box::new(Var(c)), Box::new(acc)));
+                choices.push((solver.define_const(cond, info), &case.result))
+            }
+        }
+    }
+
+    let mut result = match (otherwise, &stub.default) {
+        (Some(value), _) | (None, Some(value)) => value.clone(),
+        (None, None) => symbolic(ret_ty, shared_state, solver, info)?,
+    };
+    for (choice, case_result) in choices.into_iter().rev() {
+        result = build_ite(choice, case_result, &result, solver, info)?
+    }
+
+    Ok(result)
+}
+
 #[allow(clippy::too_many_arguments)]
 fn run_special_primop<'ir, 'task, B: BV>(
     loc: &Loc<Name>,
@@ -1167,7 +1558,7 @@ fn run_special_primop<'ir, 'task, B: BV>(
         if let Val::Ref(reg) = eval_exp(&args[0], &mut frame.local_state, shared_state, solver, info)?.into_owned() {
             match frame.regs_mut().get(reg, shared_state, solver, info)? {
                 Some(value) => {
-                    solver.add_event(Event::ReadReg(reg, Vec::new(), value.clone()));
+                    solver.add_event(Event::ReadReg(reg, Vec::new(), value.clone(), info));
                     assign(tid, loc, value.clone(), &mut frame.local_state, shared_state, solver, info)?
                 }
                 None => return Err(ExecError::Type(format!("reg_deref {:?}", &reg), info)),
@@ -1191,12 +1582,25 @@ fn run_special_primop<'ir, 'task, B: BV>(
             &shared_state.externs[&abstracted_fn].1
         };
         let return_value = symbolic(return_ty, shared_state, solver, info)?;
+        let block_op = match shared_state.block_functions.get(&abstracted_fn) {
+            Some(block) => Some(Event::BlockOp {
+                name: abstracted_fn,
+                address: block_address(&args, block, solver, info)?,
+                bytes: block.bytes,
+                info,
+            }),
+            None => None,
+        };
         solver.add_event(Event::Abstract {
             name: abstracted_fn,
             primitive: f == ABSTRACT_PRIMOP,
             args,
             return_value: return_value.clone(),
+            info,
         });
+        if let Some(block_op) = block_op {
+            solver.add_event(block_op)
+        }
         assign(tid, loc, return_value, &mut frame.local_state, shared_state, solver, info)?;
         frame.pc += 1
     } else if f == READ_REGISTER_FROM_VECTOR {
@@ -1211,7 +1615,7 @@ fn run_special_primop<'ir, 'task, B: BV>(
         let n = eval_exp(&args[0], &mut frame.local_state, shared_state, solver, info)?.into_owned();
         let value = eval_exp(&args[1], &mut frame.local_state, shared_state, solver, info)?.into_owned();
         let regs = eval_exp(&args[2], &mut frame.local_state, shared_state, solver, info)?.into_owned();
-        write_register_from_vector(n, value, regs, &mut frame.local_state, shared_state, solver, info)?;
+        write_register_from_vector(tid, n, value, regs, &mut frame.local_state, shared_state, solver, info)?;
         assign(tid, loc, Val::Unit, &mut frame.local_state, shared_state, solver, info)?;
         frame.pc += 1
     } else if f == INSTR_ANNOUNCE {
@@ -1256,6 +1660,50 @@ fn run_special_primop<'ir, 'task, B: BV>(
     Ok(())
 }
 
+/// The operands of an expression in a decode function, looking
+/// through a negation, which are recorded with the value of the
+/// expression by `TaskState::with_decode_trace`
+fn decode_operands<'ir, B: BV>(
+    exp: &Exp<Name>,
+    local_state: &mut LocalState<'ir, B>,
+    shared_state: &SharedState<'ir, B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Vec<Val<B>>, ExecError> {
+    match exp {
+        Exp::Call(Op::Not, args) if args.len() == 1 => {
+            decode_operands(&args[0], local_state, shared_state, solver, info)
+        }
+        Exp::Call(_, args) => {
+            args.iter().map(|arg| eval_exp(arg, local_state, shared_state, solver, info).map(Cow::into_owned)).collect()
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// The local variable assigned by an instruction, if it is in a
+/// decode function and so the assigned value is recorded
+fn decode_target<B>(loc: &Loc<Name>, frame: &LocalFrame<'_, B>, task_state: &TaskState<B>) -> Option<Name> {
+    match loc {
+        Loc::Id(name) if task_state.is_decoding(frame.function_name) => Some(*name),
+        _ => None,
+    }
+}
+
+/// Record the result of a primop assigned by a decode function, see
+/// [decode_target]. Primops called for their effects return unit,
+/// which is not recorded.
+fn record_decode_value<B: BV>(
+    decoded: Option<(Name, Vec<Val<B>>)>,
+    value: &Val<B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) {
+    if let (Some((name, args)), false) = (decoded, matches!(value, Val::Unit)) {
+        solver.add_event(Event::DecodeValue { name, args, value: value.clone(), info })
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn run_loop<'ir, 'task, B: BV>(
     tid: usize,
@@ -1263,12 +1711,27 @@ fn run_loop<'ir, 'task, B: BV>(
     timeout: Timeout,
     stop_conditions: Option<&'task StopConditions>,
     queue: &Worker<Task<'ir, 'task, B>>,
+    yield_queue: Option<&Injector<Task<'ir, 'task, B>>>,
     frame: &mut LocalFrame<'ir, B>,
     task_state: &'task TaskState<B>,
     shared_state: &SharedState<'ir, B>,
     solver: &mut Solver<B>,
 ) -> Result<Val<B>, ExecError> {
+    let length_checks = bitvector::length_check::length_checks();
+    let mut last_info = SourceLoc::unknown();
+    let slice_start = Instant::now();
+    let mut resumed = true;
+
     'main_loop: loop {
+        if length_checks {
+            if let Some(err) = bitvector::length_check::take_length_error() {
+                return Err(ExecError::BitvectorLength(err, last_info));
+            }
+            if frame.pc < frame.instrs.len() {
+                last_info = frame.instrs[frame.pc].source_loc().unwrap_or_else(SourceLoc::unknown)
+            }
+        }
+
         if frame.pc >= frame.instrs.len() {
             // Currently this happens when evaluating letbindings.
             return Ok(Val::Unit);
@@ -1278,6 +1741,46 @@ fn run_loop<'ir, 'task, B: BV>(
             return Err(ExecError::Timeout);
         }
 
+        // Once a path has used up its time slice, put the rest of it
+        // on the global queue behind any other waiting work. Every
+        // resumed task executes at least one instruction, so a path
+        // always makes progress.
+        if let (Some(slice), Some(yield_queue)) = (task_state.time_slice, yield_queue) {
+            if !resumed && slice_start.elapsed() > slice {
+                log_from!(tid, log::VERBOSE, "Yielding at the end of time slice");
+                yield_queue.push(Task {
+                    id: task_id,
+                    frame: freeze_frame(frame),
+                    checkpoint: checkpoint(solver),
+                    fork_cond: None,
+                    state: task_state,
+                    stop_conditions,
+                });
+                return Err(ExecError::Yield);
+            }
+        }
+        resumed = false;
+
+        if let Some(budget) = task_state.trace_budget {
+            let before = solver.trace().len();
+            if solver.simplify_trace_over(budget, simplify::simplify_partial_trace) {
+                log_from!(
+                    tid,
+                    log::VERBOSE,
+                    &format!("Simplified trace from {} to {} events", before, solver.trace().len())
+                )
+            }
+        }
+
+        if task_state.undefined_audit {
+            if let Some(kind) =
+                shared_state.undefined_sites.get(&frame.function_name).and_then(|sites| sites.get(&frame.pc))
+            {
+                let info = frame.instrs[frame.pc].source_loc().unwrap_or_else(SourceLoc::unknown);
+                solver.add_event(Event::Undefined { function: frame.function_name, kind: *kind, info })
+            }
+        }
+
         match &frame.instrs[frame.pc] {
             Instr::Decl(v, ty, _) => {
                 frame.vars_mut().insert(*v, UVal::Uninit(ty));
@@ -1286,12 +1789,24 @@ fn run_loop<'ir, 'task, B: BV>(
 
             Instr::Init(var, _, exp, info) => {
                 let value = eval_exp(exp, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                if task_state.is_decoding(frame.function_name) && matches!(exp, Exp::Call(Op::Slice(_), _)) {
+                    let args = decode_operands(exp, &mut frame.local_state, shared_state, solver, *info)?;
+                    solver.add_event(Event::DecodeValue { name: *var, args, value: value.clone(), info: *info })
+                }
                 frame.vars_mut().insert(*var, UVal::Init(value));
                 frame.pc += 1;
             }
 
             Instr::Jump(exp, target, info) => {
+                let tested = if task_state.is_decoding(frame.function_name) {
+                    Some(decode_operands(exp, &mut frame.local_state, shared_state, solver, *info)?)
+                } else {
+                    None
+                };
                 let value = eval_exp(exp, &mut frame.local_state, shared_state, solver, *info)?;
+                if let Some(args) = tested {
+                    solver.add_event(Event::DecodeTest { args, value: value.as_ref().clone(), info: *info })
+                }
                 match *value.as_ref() {
                     Val::Symbolic(v) => {
                         use smtlib::Def::*;
@@ -1299,10 +1814,34 @@ fn run_loop<'ir, 'task, B: BV>(
 
                         let test_true = Var(v);
                         let test_false = Not(Box::new(Var(v)));
-                        let can_be_true = solver.check_sat_with(&test_true).is_sat()?;
-                        let can_be_false = solver.check_sat_with(&test_false).is_sat()?;
-
-                        if can_be_true && can_be_false {
+                        let fork_check = Instant::now();
+                        let (true_result, false_result) =
+                            solver.check_fork(&test_true, &test_false, task_state.concurrent_fork_checks);
+                        if let Some(counters) = task_state.progress_counters() {
+                            counters
+                                .solver_micros
+                                .fetch_add(fork_check.elapsed().as_micros() as u64, Ordering::Relaxed);
+                        }
+                        let can_be_true = true_result.is_sat()?;
+                        let can_be_false = false_result.is_sat()?;
+
+                        // Merged sides are not audited, so the audit
+                        // forks at every branch
+                        let join =
+                            if can_be_true && can_be_false && task_state.merge_paths && !task_state.undefined_audit {
+                                merge_point(*target, frame, shared_state)
+                            } else {
+                                None
+                            };
+
+                        if let Some(join) = join {
+                            log_from!(
+                                tid,
+                                log::FORK,
+                                &format!("Merging paths at {}", info.location_string(shared_state.symtab.files()))
+                            );
+                            merge_branch(tid, v, *target, join, frame, shared_state, solver, *info)?
+                        } else if can_be_true && can_be_false {
                             if_logging!(log::FORK, {
                                 log_from!(tid, log::FORK, info.location_string(shared_state.symtab.files()));
                                 probe::taint_info(log::FORK, v, Some(shared_state), solver)
@@ -1311,6 +1850,7 @@ fn run_loop<'ir, 'task, B: BV>(
                             let point = checkpoint(solver);
                             let frozen = Frame { pc: frame.pc + 1, ..freeze_frame(frame) };
                             frame.forks += 1;
+                            task_state.count_queued();
                             queue.push(Task {
                                 id: task_id,
                                 frame: frozen,
@@ -1325,9 +1865,15 @@ fn run_loop<'ir, 'task, B: BV>(
                             solver.add_event(Event::Fork(frame.forks - 1, v, 0, *info));
 
                             solver.add(Assert(test_true));
+                            if loop_bound_exceeded(*target, frame, task_state, solver, *info) {
+                                return Ok(Val::Poison);
+                            }
                             frame.pc = *target
                         } else if can_be_true {
                             solver.add(Assert(test_true));
+                            if loop_bound_exceeded(*target, frame, task_state, solver, *info) {
+                                return Ok(Val::Poison);
+                            }
                             frame.pc = *target
                         } else if can_be_false {
                             solver.add(Assert(test_false));
@@ -1337,7 +1883,13 @@ fn run_loop<'ir, 'task, B: BV>(
                         }
                     }
                     Val::Bool(jump) => {
+                        if task_state.guard_functions.contains(&frame.function_name) {
+                            solver.add_event(Event::Guard { function: frame.function_name, taken: jump, info: *info })
+                        }
                         if jump {
+                            if loop_bound_exceeded(*target, frame, task_state, solver, *info) {
+                                return Ok(Val::Poison);
+                            }
                             frame.pc = *target
                         } else {
                             frame.pc += 1
@@ -1349,17 +1901,28 @@ fn run_loop<'ir, 'task, B: BV>(
                 }
             }
 
-            Instr::Goto(target) => frame.pc = *target,
+            Instr::Goto(target) => {
+                if loop_bound_exceeded(*target, frame, task_state, solver, SourceLoc::unknown()) {
+                    return Ok(Val::Poison);
+                }
+                frame.pc = *target
+            }
 
             Instr::Copy(loc, exp, info) => {
                 let value = eval_exp(exp, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                if let (Some(name), Exp::Call(Op::Slice(_), _)) = (decode_target(loc, frame, task_state), exp) {
+                    let args = decode_operands(exp, &mut frame.local_state, shared_state, solver, *info)?;
+                    solver.add_event(Event::DecodeValue { name, args, value: value.clone(), info: *info })
+                }
                 assign(tid, loc, value, &mut frame.local_state, shared_state, solver, *info)?;
                 frame.pc += 1;
             }
 
             Instr::PrimopUnary(loc, f, arg, info) => {
                 let arg = eval_exp(arg, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                let decoded = decode_target(loc, frame, task_state).map(|name| (name, vec![arg.clone()]));
                 let value = f(arg, solver, *info)?;
+                record_decode_value(decoded, &value, solver, *info);
                 assign(tid, loc, value, &mut frame.local_state, shared_state, solver, *info)?;
                 frame.pc += 1;
             }
@@ -1367,17 +1930,27 @@ fn run_loop<'ir, 'task, B: BV>(
             Instr::PrimopBinary(loc, f, arg1, arg2, info) => {
                 let arg1 = eval_exp(arg1, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
                 let arg2 = eval_exp(arg2, &mut frame.local_state, shared_state, solver, *info)?.into_owned();
+                let decoded =
+                    decode_target(loc, frame, task_state).map(|name| (name, vec![arg1.clone(), arg2.clone()]));
                 let value = f(arg1, arg2, solver, *info)?;
+                record_decode_value(decoded, &value, solver, *info);
                 assign(tid, loc, value, &mut frame.local_state, shared_state, solver, *info)?;
                 frame.pc += 1;
             }
 
             Instr::PrimopVariadic(loc, f, args, info) => {
-                let args = args
+                let args: Vec<Val<B>> = args
                     .iter()
                     .map(|arg| eval_exp(arg, &mut frame.local_state, shared_state, solver, *info).map(Cow::into_owned))
                     .collect::<Result<_, _>>()?;
+                let decoded = decode_target(loc, frame, task_state).map(|name| (name, args.clone()));
+                let events_before = solver.trace().head().len();
                 let value = f(args, solver, frame, *info)?;
+                record_decode_value(decoded, &value, solver, *info);
+                if !task_state.watchpoints.is_empty() {
+                    let events = solver.trace().head()[events_before..].to_vec();
+                    probe::watchpoints(tid, &task_state.watchpoints, &events, shared_state, solver)?
+                }
                 assign(tid, loc, value, &mut frame.local_state, shared_state, solver, *info)?;
                 frame.pc += 1;
             }
@@ -1421,6 +1994,7 @@ fn run_loop<'ir, 'task, B: BV>(
                                         args,
                                         primitive: false,
                                         return_value: Val::Poison,
+                                        info: *info,
                                     });
                                     return Ok(Val::Poison);
                                 }
@@ -1466,9 +2040,72 @@ fn run_loop<'ir, 'task, B: BV>(
                             }
                         }
 
+                        if let Some(stub) = shared_state.function_stubs.get(f) {
+                            let result = call_stub(
+                                tid,
+                                stub,
+                                ret_ty,
+                                &args,
+                                &mut frame.local_state,
+                                shared_state,
+                                solver,
+                                *info,
+                            )?;
+                            solver.add_event(Event::UseFunAssumption { name: *f, args, return_value: result.clone() });
+                            if shared_state.trace_functions.contains(f) {
+                                solver.trace_return(*f)
+                            }
+                            assign(tid, loc, result, &mut frame.local_state, shared_state, solver, *info)?;
+                            frame.pc += 1;
+                            continue 'main_loop;
+                        }
+
+                        let decode_call = task_state.decode_functions.contains(f)
+                            || task_state.decode_functions.contains(&frame.function_name);
+                        if decode_call {
+                            solver.add_event(Event::DecodeCall { name: *f, args: args.clone(), info: *info })
+                        }
+
+                        // Pure functions with the same arguments as an
+                        // earlier call on this path, or with the same
+                        // concrete arguments as a call cached by any
+                        // task, return the same result
+                        let pure_call = if shared_state.pure_functions.contains(f) {
+                            let cached = frame
+                                .pure_results
+                                .get(f)
+                                .and_then(|results| {
+                                    results
+                                        .iter()
+                                        .find(|(cached_args, _)| *cached_args == args)
+                                        .map(|(_, result)| result.clone())
+                                })
+                                .or_else(|| task_state.function_cache.as_ref().and_then(|cache| cache.get(*f, &args)));
+                            if let Some(result) = cached {
+                                if shared_state.trace_functions.contains(f) {
+                                    solver.trace_return(*f)
+                                }
+                                if decode_call {
+                                    solver.add_event(Event::DecodeReturn { name: *f, return_value: result.clone() })
+                                }
+                                assign(tid, loc, result, &mut frame.local_state, shared_state, solver, *info)?;
+                                frame.pc += 1;
+                                continue 'main_loop;
+                            }
+                            Some((*f, args.clone(), solver.trace().len()))
+                        } else {
+                            None
+                        };
+
                         let caller_pc = frame.pc;
                         let caller_instrs = frame.instrs;
                         let caller_stack_call = frame.stack_call.clone();
+                        let caller_loop_counts = mem::take(&mut frame.loop_counts);
+                        // Results can only be shared with other tasks
+                        // if simplifying the trace can't hide the
+                        // events added by the call
+                        let function_cache =
+                            task_state.function_cache.clone().filter(|_| task_state.trace_budget.is_none());
                         push_call_stack(frame);
                         frame.backtrace.push((frame.function_name, caller_pc));
                         frame.function_name = *f;
@@ -1485,6 +2122,17 @@ fn run_loop<'ir, 'task, B: BV>(
                             frame.pc = caller_pc + 1;
                             frame.instrs = caller_instrs;
                             frame.stack_call = caller_stack_call.clone();
+                            frame.loop_counts = caller_loop_counts.clone();
+                            if let Some((f, args, trace_len)) = &pure_call {
+                                if frame.get_exception().is_none() {
+                                    if let Some(cache) = &function_cache {
+                                        if solver.trace().len() == *trace_len {
+                                            cache.insert(*f, args, &ret)
+                                        }
+                                    }
+                                    frame.pure_results.entry(*f).or_default().push((args.clone(), ret.clone()))
+                                }
+                            }
                             assign(tid, &loc.clone(), ret, &mut frame.local_state, shared_state, solver, *info)
                         }));
 
@@ -1519,6 +2167,12 @@ fn run_loop<'ir, 'task, B: BV>(
                         solver.trace_return(frame.function_name)
                     }
 
+                    if task_state.decode_functions.contains(&frame.function_name)
+                        || matches!(frame.backtrace.last(), Some((caller, _)) if task_state.decode_functions.contains(caller))
+                    {
+                        solver.add_event(Event::DecodeReturn { name: frame.function_name, return_value: value.clone() })
+                    }
+
                     let caller = match &frame.stack_call {
                         None => return Ok(value),
                         Some(caller) => Arc::clone(caller),
@@ -1580,6 +2234,7 @@ fn run_loop<'ir, 'task, B: BV>(
                     log_from!(tid, log::FORK, format!("Fork @ monomorphizing v{}", v));
 
                     frame.forks += 1;
+                    task_state.count_queued();
 
                     queue.push(Task {
                         id: task_id,
@@ -1656,21 +2311,145 @@ pub type Collector<'ir, B, R> = dyn 'ir
     + Sync
     + Fn(usize, usize, Result<(Val<B>, LocalFrame<'ir, B>), (ExecError, Backtrace)>, &SharedState<'ir, B>, Solver<B>, &R);
 
-pub struct TaskState<B> {
-    reset_registers: HashMap<Loc<Name>, Reset<B>>,
-    // We might want to avoid loops in the assembly by requiring that
-    // any unique program counter (pc) is only visited a fixed number
-    // of times. Note that this is the architectural PC, not the isla
-    // IR program counter in the frame.
-    pc_limit: Option<(Name, usize)>,
-    // Exit if we ever announce an instruction with all bits set to zero
-    zero_announce_exit: bool
+type FunctionResults<B> = HashMap<Name, Vec<(Vec<Val<B>>, Val<B>)>>;
+
+/// Results of calls to the `pure_functions` in [SharedState] which
+/// can be reused by other tasks, e.g. when computing the footprints
+/// of many instructions which call the same helper functions. A call
+/// is only cached when its arguments and result are concrete and it
+/// added no events to the trace, so the result cannot depend on the
+/// path it was computed on.
+pub struct FunctionCache<B> {
+    results: RwLock<FunctionResults<B>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
-impl<B> TaskState<B> {
-    pub fn new() -> Self {
-        TaskState { reset_registers: HashMap::new(), pc_limit: None, zero_announce_exit: true }
-    }
+/// How often a [FunctionCache] was used.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionCacheCounts {
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl fmt::Display for FunctionCacheCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} cached function results, {} hits and {} misses", self.entries, self.hits, self.misses)
+    }
+}
+
+impl<B: BV> FunctionCache<B> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        FunctionCache { results: RwLock::new(HashMap::new()), hits: AtomicU64::new(0), misses: AtomicU64::new(0) }
+    }
+
+    fn get(&self, f: Name, args: &[Val<B>]) -> Option<Val<B>> {
+        if args.iter().any(Val::is_symbolic) {
+            return None;
+        }
+        let results = self.results.read().unwrap();
+        let result = results
+            .get(&f)
+            .and_then(|results| results.iter().find(|(cached_args, _)| cached_args == args))
+            .map(|(_, result)| result.clone());
+        if result.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn insert(&self, f: Name, args: &[Val<B>], result: &Val<B>) {
+        if result.is_symbolic() || args.iter().any(Val::is_symbolic) {
+            return;
+        }
+        let mut results = self.results.write().unwrap();
+        let results = results.entry(f).or_default();
+        if !results.iter().any(|(cached_args, _)| cached_args == args) {
+            results.push((args.to_vec(), result.clone()))
+        }
+    }
+
+    pub fn counts(&self) -> FunctionCacheCounts {
+        FunctionCacheCounts {
+            entries: self.results.read().unwrap().values().map(|results| results.len() as u64).sum(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct TaskState<B> {
+    reset_registers: HashMap<Loc<Name>, Reset<B>>,
+    // We might want to avoid loops in the assembly by requiring that
+    // any unique program counter (pc) is only visited a fixed number
+    // of times. Note that this is the architectural PC, not the isla
+    // IR program counter in the frame.
+    pc_limit: Option<(Name, usize)>,
+    // Functions for which concrete branch outcomes are recorded as
+    // guard events in the trace
+    guard_functions: HashSet<Name>,
+    // Calls to and from these functions are recorded as decode
+    // events in the trace
+    decode_functions: HashSet<Name>,
+    // Memory accesses which may touch these address ranges are
+    // logged as probes
+    watchpoints: Vec<Range<Address>>,
+    // The maximum number of jumps back to the head of any one loop
+    // within a function call, either for specific functions or for
+    // all functions
+    loop_bound: Option<usize>,
+    function_loop_bounds: HashMap<Name, usize>,
+    // Record an event whenever an under-specified construct in the
+    // IR is evaluated
+    undefined_audit: bool,
+    // Merge the sides of branches which only compute local variables,
+    // rather than forking
+    merge_paths: bool,
+    // Simplify the trace of a path while it is executing once its
+    // estimated size exceeds this many bytes
+    trace_budget: Option<usize>,
+    // Check the feasibility of both sides of a symbolic branch at the
+    // same time, rather than one after the other
+    concurrent_fork_checks: bool,
+    // Results of pure function calls shared between tasks
+    function_cache: Option<Arc<FunctionCache<B>>>,
+    // Solver states for checkpoints shared between tasks
+    checkpoint_cache: Option<Arc<CheckpointCache<B>>>,
+    // How long a path may run in start_multi before it is suspended
+    // and re-queued
+    time_slice: Option<Duration>,
+    // How start_multi reports its progress, and the counts of tasks
+    // it reports
+    progress: Option<(Progress, Arc<ProgressCounters>)>,
+    // Exit if we ever announce an instruction with all bits set to zero
+    zero_announce_exit: bool
+}
+
+impl<B> TaskState<B> {
+    pub fn new() -> Self {
+        TaskState {
+            reset_registers: HashMap::new(),
+            pc_limit: None,
+            zero_announce_exit: true,
+            guard_functions: HashSet::new(),
+            decode_functions: HashSet::new(),
+            watchpoints: Vec::new(),
+            loop_bound: None,
+            function_loop_bounds: HashMap::new(),
+            undefined_audit: false,
+            merge_paths: false,
+            trace_budget: None,
+            concurrent_fork_checks: false,
+            function_cache: None,
+            checkpoint_cache: None,
+            time_slice: None,
+            progress: None,
+        }
+    }
 
     pub fn with_reset_registers(self, reset_registers: HashMap<Loc<Name>, Reset<B>>) -> Self {
         TaskState { reset_registers, ..self }
@@ -1683,6 +2462,110 @@ impl<B> TaskState<B> {
     pub fn with_zero_announce_exit(self, b: bool) -> Self {
         TaskState { zero_announce_exit: b, ..self }
     }
+
+    pub fn with_guard_functions(self, guard_functions: HashSet<Name>) -> Self {
+        TaskState { guard_functions, ..self }
+    }
+
+    /// Record calls to the given functions, and any calls they make,
+    /// with their arguments and return values. The slices, primop
+    /// results, and branch conditions computed by the functions
+    /// themselves are also recorded.
+    pub fn with_decode_trace(self, decode_functions: HashSet<Name>) -> Self {
+        TaskState { decode_functions, ..self }
+    }
+
+    fn is_decoding(&self, function: Name) -> bool {
+        !self.decode_functions.is_empty() && self.decode_functions.contains(&function)
+    }
+
+    /// Log any memory access which may touch one of the given address
+    /// ranges, along with the registers its address depends on
+    pub fn with_watchpoints(self, watchpoints: Vec<Range<Address>>) -> Self {
+        TaskState { watchpoints, ..self }
+    }
+
+    /// Stop any path which jumps back to the head of a loop more than
+    /// `loop_bound` times in a single function call, recording a
+    /// [Event::LoopBound] in its trace. Bounds for specific functions
+    /// in `function_loop_bounds` override `loop_bound`.
+    pub fn with_loop_bounds(self, loop_bound: Option<usize>, function_loop_bounds: HashMap<Name, usize>) -> Self {
+        TaskState { loop_bound, function_loop_bounds, ..self }
+    }
+
+    /// Record an [Event::Undefined] whenever a path evaluates an
+    /// `undefined` expression, calls an `undefined_*` builtin, or
+    /// returns via `arbitrary` (see [SharedState::undefined_sites]).
+    /// Branches are not merged while auditing.
+    pub fn with_undefined_audit(self, undefined_audit: bool) -> Self {
+        TaskState { undefined_audit, ..self }
+    }
+
+    pub fn with_path_merging(self, merge_paths: bool) -> Self {
+        TaskState { merge_paths, ..self }
+    }
+
+    /// Whenever the trace of a path grows beyond `trace_budget` bytes
+    /// (as estimated by [Trace::size_estimate]), simplify it with
+    /// [simplify::simplify_partial_trace] rather than waiting until
+    /// the path is complete.
+    pub fn with_trace_budget(self, trace_budget: Option<usize>) -> Self {
+        TaskState { trace_budget, ..self }
+    }
+
+    /// Check whether each side of a symbolic branch is feasible on
+    /// two threads at once (see [Solver::check_sat_pair]). This
+    /// reduces the time taken by each fork when the solver calls are
+    /// expensive, at the cost of copying the solver's assertions.
+    pub fn with_concurrent_fork_checks(self, concurrent_fork_checks: bool) -> Self {
+        TaskState { concurrent_fork_checks, ..self }
+    }
+
+    /// Share the results of calls to pure functions between all the
+    /// tasks using this state (see [FunctionCache]).
+    pub fn with_function_cache(self, function_cache: Arc<FunctionCache<B>>) -> Self {
+        TaskState { function_cache: Some(function_cache), ..self }
+    }
+
+    /// Create the solver for each task using this state from the
+    /// solver states of any earlier checkpoints in its trace, rather
+    /// than replaying the whole trace (see [CheckpointCache]).
+    pub fn with_checkpoint_cache(self, checkpoint_cache: Arc<CheckpointCache<B>>) -> Self {
+        TaskState { checkpoint_cache: Some(checkpoint_cache), ..self }
+    }
+
+    /// When run by [start_multi], suspend any path which has been
+    /// executing for longer than `time_slice` and put it back on the
+    /// shared queue, so a single slow path cannot keep a thread busy
+    /// while other work is waiting. The path is resumed later, possibly
+    /// on another thread, from the instruction where it stopped.
+    pub fn with_time_slice(self, time_slice: Option<Duration>) -> Self {
+        TaskState { time_slice, ..self }
+    }
+
+    /// Make [start_multi] print the progress of runs using this task
+    /// state to stderr every `interval`, and once more when each run
+    /// finishes. The counts are kept with the task state, so runs
+    /// using separate task states are reported separately.
+    pub fn with_progress(self, progress: Option<Progress>) -> Self {
+        TaskState { progress: progress.map(|progress| (progress, Arc::new(ProgressCounters::default()))), ..self }
+    }
+
+    /// The counts of the tasks run with this task state, if it has
+    /// been given [TaskState::with_progress].
+    pub fn progress_counts(&self) -> Option<ProgressCounts> {
+        self.progress_counters().map(ProgressCounters::counts)
+    }
+
+    fn progress_counters(&self) -> Option<&ProgressCounters> {
+        self.progress.as_ref().map(|(_, counters)| counters.as_ref())
+    }
+
+    fn count_queued(&self) {
+        if let Some(counters) = self.progress_counters() {
+            counters.queued.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 }
 
 impl<B> Default for TaskState<B> {
@@ -1711,6 +2594,104 @@ impl<'ir, 'task, B> Task<'ir, 'task, B> {
     }
 }
 
+/// The order in which the paths found by symbolic execution are
+/// explored. Whenever execution forks it continues down one side of
+/// the branch, and the strategy decides which of the remaining paths
+/// is explored next once the current one is finished.
+#[derive(Copy, Clone)]
+pub enum Strategy<'a, B> {
+    /// Explore the most recent fork first
+    DepthFirst,
+    /// Explore the oldest fork first
+    BreadthFirst,
+    /// Explore the path with the highest score first, using the
+    /// checkpoint of the solver state at the fork. Paths with equal
+    /// scores are explored depth-first.
+    Priority(&'a (dyn Fn(&Checkpoint<B>) -> i64 + Sync)),
+}
+
+impl<'a, B> Strategy<'a, B> {
+    fn worker<T>(&self) -> Worker<T> {
+        match self {
+            Strategy::BreadthFirst => Worker::new_fifo(),
+            Strategy::DepthFirst | Strategy::Priority(_) => Worker::new_lifo(),
+        }
+    }
+
+    /// Move the tasks forked while exploring a path from a worker's
+    /// queue into the shared priority queue, if the strategy uses
+    /// one, scoring each task once as it is moved.
+    fn schedule<'ir, 'task>(&self, queue: &Worker<Task<'ir, 'task, B>>, prioritized: &PriorityQueue<'ir, 'task, B>) {
+        if let Strategy::Priority(score) = self {
+            let mut forked = Vec::new();
+            while let Some(task) = queue.pop() {
+                forked.push(task)
+            }
+            // The worker pops the most recent fork first, so reverse
+            // to queue them in the order they were forked
+            for task in forked.into_iter().rev() {
+                prioritized.push(score(&task.checkpoint), task)
+            }
+        }
+    }
+}
+
+struct Scored<'ir, 'task, B> {
+    score: i64,
+    seq: u64,
+    task: Task<'ir, 'task, B>,
+}
+
+impl<'ir, 'task, B> PartialEq for Scored<'ir, 'task, B> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.score, self.seq) == (other.score, other.seq)
+    }
+}
+
+impl<'ir, 'task, B> Eq for Scored<'ir, 'task, B> {}
+
+impl<'ir, 'task, B> PartialOrd for Scored<'ir, 'task, B> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'ir, 'task, B> Ord for Scored<'ir, 'task, B> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (self.score, self.seq).cmp(&(other.score, other.seq))
+    }
+}
+
+/// The tasks waiting to be explored under [Strategy::Priority]. The
+/// queue is shared by every thread, so the highest scoring task
+/// overall is explored next, and tasks with equal scores are
+/// explored most recently queued first.
+struct PriorityQueue<'ir, 'task, B> {
+    tasks: Mutex<BinaryHeap<Scored<'ir, 'task, B>>>,
+    next_seq: AtomicU64,
+}
+
+impl<'ir, 'task, B> PriorityQueue<'ir, 'task, B> {
+    fn new() -> Self {
+        PriorityQueue { tasks: Mutex::new(BinaryHeap::new()), next_seq: AtomicU64::new(0) }
+    }
+
+    fn push(&self, score: i64, task: Task<'ir, 'task, B>) {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        self.tasks.lock().unwrap().push(Scored { score, seq, task })
+    }
+
+    fn pop(&self) -> Option<Task<'ir, 'task, B>> {
+        self.tasks.lock().unwrap().pop().map(|scored| scored.task)
+    }
+}
+
+/// A [Strategy::Priority] score which explores the paths that have
+/// performed the most memory accesses first.
+pub fn memory_first<B: BV>(checkpoint: &Checkpoint<B>) -> i64 {
+    checkpoint.memory_accesses() as i64
+}
+
 /// Start symbolically executing a Task using just the current thread, collecting the results using
 /// the given collector.
 pub fn start_single<'ir, 'task, B: BV, R>(
@@ -1719,13 +2700,27 @@ pub fn start_single<'ir, 'task, B: BV, R>(
     collected: &R,
     collector: &Collector<'ir, B, R>,
 ) {
-    let queue = Worker::new_lifo();
+    start_single_with_strategy(Strategy::DepthFirst, task, shared_state, collected, collector)
+}
+
+/// As [start_single], but exploring paths in the order given by `strategy`.
+pub fn start_single_with_strategy<'ir, 'task, B: BV, R>(
+    strategy: Strategy<B>,
+    task: Task<'ir, 'task, B>,
+    shared_state: &SharedState<'ir, B>,
+    collected: &R,
+    collector: &Collector<'ir, B, R>,
+) {
+    let queue = strategy.worker();
+    let prioritized = PriorityQueue::new();
     queue.push(task);
-    while let Some(task) = queue.pop() {
+    loop {
+        strategy.schedule(&queue, &prioritized);
+        let Some(task) = prioritized.pop().or_else(|| queue.pop()) else { break };
         let mut cfg = Config::new();
         cfg.set_param_value("model", "true");
         let ctx = Context::new(cfg);
-        let mut solver = Solver::from_checkpoint(&ctx, task.checkpoint);
+        let mut solver = restore_solver(&ctx, task.state, task.checkpoint);
         if let Some((def, event)) = task.fork_cond {
             solver.add_event(event);
 
@@ -1737,6 +2732,7 @@ pub fn start_single<'ir, 'task, B: BV, R>(
             Timeout::unlimited(),
             task.stop_conditions,
             &queue,
+            None,
             &task.frame,
             task.state,
             shared_state,
@@ -1746,6 +2742,17 @@ pub fn start_single<'ir, 'task, B: BV, R>(
     }
 }
 
+fn restore_solver<'ctx, B: BV>(
+    ctx: &'ctx Context,
+    task_state: &TaskState<B>,
+    checkpoint: Checkpoint<B>,
+) -> Solver<'ctx, B> {
+    match &task_state.checkpoint_cache {
+        Some(cache) => cache.restore(ctx, checkpoint),
+        None => Solver::from_checkpoint(ctx, checkpoint),
+    }
+}
+
 fn find_task<T>(local: &Worker<T>, global: &Injector<T>, stealers: &RwLock<Vec<Stealer<T>>>) -> Option<T> {
     let stealers = stealers.read().unwrap();
     local.pop().or_else(|| {
@@ -1758,10 +2765,130 @@ fn find_task<T>(local: &Worker<T>, global: &Injector<T>, stealers: &RwLock<Vec<S
     })
 }
 
+/// Counts of the tasks run by [start_multi] for a [TaskState], which
+/// are used to report its progress (see [TaskState::with_progress]).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProgressCounts {
+    /// Tasks queued, either at the start or when a path forks
+    pub queued: u64,
+    pub finished: u64,
+    /// Finished tasks whose path became unsatisfiable
+    pub dead: u64,
+    /// Finished tasks whose path ended in any other error
+    pub failed: u64,
+    /// Time spent in the solver checks made at symbolic branches
+    pub solver_micros: u64,
+}
+
+impl ProgressCounts {
+    /// Tasks which are queued or running
+    pub fn pending(&self) -> u64 {
+        self.queued.saturating_sub(self.finished)
+    }
+
+    /// Finished tasks which produced a trace, including those that
+    /// ended by exiting
+    pub fn traces(&self) -> u64 {
+        self.finished.saturating_sub(self.dead + self.failed)
+    }
+
+    fn since(self, start: Self) -> Self {
+        ProgressCounts {
+            queued: self.queued - start.queued,
+            finished: self.finished - start.finished,
+            dead: self.dead - start.dead,
+            failed: self.failed - start.failed,
+            solver_micros: self.solver_micros - start.solver_micros,
+        }
+    }
+}
+
+/// The counters behind [ProgressCounts], which are shared by the
+/// tasks of a [TaskState] as they are queued and finish.
+#[derive(Debug, Default)]
+pub struct ProgressCounters {
+    queued: AtomicU64,
+    finished: AtomicU64,
+    dead: AtomicU64,
+    failed: AtomicU64,
+    solver_micros: AtomicU64,
+}
+
+impl ProgressCounters {
+    pub fn counts(&self) -> ProgressCounts {
+        ProgressCounts {
+            queued: self.queued.load(Ordering::Relaxed),
+            finished: self.finished.load(Ordering::Relaxed),
+            dead: self.dead.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            solver_micros: self.solver_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// A human-readable line
+    Text,
+    /// One JSON object per line, for tools which wrap isla
+    Json,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub interval: Duration,
+    pub format: ProgressFormat,
+}
+
+fn report_progress(format: ProgressFormat, elapsed: Duration, counts: ProgressCounts, done: bool) {
+    // New tasks are forked as paths run, so this estimate assumes
+    // the remaining work is like that which has finished already
+    let eta = if done {
+        Some(Duration::ZERO)
+    } else if counts.finished > 0 {
+        Some(elapsed.mul_f64(counts.pending() as f64 / counts.finished as f64))
+    } else {
+        None
+    };
+    match format {
+        ProgressFormat::Text => eprintln!(
+            "[{:.1}s] {} tasks ({} finished, {} pending), {} traces, {} dead, {} failed, {:.1}s in solver{}",
+            elapsed.as_secs_f64(),
+            counts.queued,
+            counts.finished,
+            counts.pending(),
+            counts.traces(),
+            counts.dead,
+            counts.failed,
+            counts.solver_micros as f64 / 1_000_000.0,
+            match eta {
+                _ if done => ", done".to_string(),
+                Some(eta) => format!(", about {:.1}s left", eta.as_secs_f64()),
+                None => String::new(),
+            }
+        ),
+        ProgressFormat::Json => eprintln!(
+            "{{\"elapsed_ms\":{},\"queued\":{},\"finished\":{},\"pending\":{},\"traces\":{},\"dead\":{},\"failed\":{},\"solver_ms\":{},\"eta_ms\":{},\"done\":{}}}",
+            elapsed.as_millis(),
+            counts.queued,
+            counts.finished,
+            counts.pending(),
+            counts.traces(),
+            counts.dead,
+            counts.failed,
+            counts.solver_micros / 1000,
+            eta.map(|eta| eta.as_millis().to_string()).unwrap_or_else(|| "null".to_string()),
+            done
+        ),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn do_work<'ir, 'task, B: BV, R>(
     tid: usize,
     timeout: Timeout,
     queue: &Worker<Task<'ir, 'task, B>>,
+    global: &Injector<Task<'ir, 'task, B>>,
     task: Task<'ir, 'task, B>,
     shared_state: &SharedState<'ir, B>,
     collected: &R,
@@ -1769,14 +2896,39 @@ fn do_work<'ir, 'task, B: BV, R>(
 ) {
     let cfg = Config::new();
     let ctx = Context::new(cfg);
-    let mut solver = Solver::from_checkpoint(&ctx, task.checkpoint);
+    let mut solver = restore_solver(&ctx, task.state, task.checkpoint);
     if let Some((def, event)) = task.fork_cond {
         solver.add_event(event);
         solver.add(def)
     };
-    let result =
-        run(tid, task.id, timeout, task.stop_conditions, queue, &task.frame, task.state, shared_state, &mut solver);
-    collector(tid, task.id, result, shared_state, solver, collected)
+    let result = run(
+        tid,
+        task.id,
+        timeout,
+        task.stop_conditions,
+        queue,
+        Some(global),
+        &task.frame,
+        task.state,
+        shared_state,
+        &mut solver,
+    );
+    let counters = task.state.progress_counters();
+    match (&result, counters) {
+        // The rest of the path has been queued as a new task
+        (Err((ExecError::Yield, _)), _) => return,
+        (Ok(_) | Err((ExecError::Exit, _)), _) | (_, None) => (),
+        (Err((ExecError::Dead, _)), Some(counters)) => {
+            counters.dead.fetch_add(1, Ordering::Relaxed);
+        }
+        (Err(_), Some(counters)) => {
+            counters.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    collector(tid, task.id, result, shared_state, solver, collected);
+    if let Some(counters) = counters {
+        counters.finished.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 enum Response {
@@ -1801,17 +2953,46 @@ pub fn start_multi<'ir, 'task, B: BV, R>(
     collector: &Collector<'ir, B, R>,
 ) where
     R: Send + Sync,
+{
+    start_multi_with_strategy(num_threads, timeout, Strategy::DepthFirst, tasks, shared_state, collected, collector)
+}
+
+/// As [start_multi], but with each thread exploring its paths in the
+/// order given by `strategy`. For [Strategy::DepthFirst] and
+/// [Strategy::BreadthFirst] idle threads still steal work from busy
+/// ones, so the order is only followed within each thread. The tasks
+/// waiting under [Strategy::Priority] are shared by all the threads,
+/// so its order is followed across them, except for tasks stolen
+/// while the path that forked them is still running.
+pub fn start_multi_with_strategy<'ir, 'task, B: BV, R>(
+    num_threads: usize,
+    timeout: Option<u64>,
+    strategy: Strategy<B>,
+    tasks: Vec<Task<'ir, 'task, B>>,
+    shared_state: &SharedState<'ir, B>,
+    collected: Arc<R>,
+    collector: &Collector<'ir, B, R>,
+) where
+    R: Send + Sync,
 {
     let timeout = Timeout { start_time: Instant::now(), duration: timeout.map(Duration::from_secs) };
+    // The tasks of a run share a TaskState, which may have been used
+    // by earlier runs, so only the counts since the start are reported
+    let progress = tasks.first().and_then(|task| task.state.progress.clone());
+    let start_counts = progress.as_ref().map(|(_, counters)| counters.counts()).unwrap_or_default();
+    let mut last_report = Instant::now();
 
     let (tx, rx): (Sender<Activity>, Receiver<Activity>) = mpsc::channel();
     let global: Arc<Injector<Task<B>>> = Arc::new(Injector::<Task<B>>::new());
     let stealers: Arc<RwLock<Vec<Stealer<Task<B>>>>> = Arc::new(RwLock::new(Vec::new()));
 
     for task in tasks {
+        task.state.count_queued();
         global.push(task);
     }
 
+    let prioritized = &PriorityQueue::new();
+
     thread::scope(|scope| {
         for tid in 0..num_threads {
             // When a worker is idle, it reports that to the main orchestrating thread, which can
@@ -1824,17 +3005,20 @@ pub fn start_multi<'ir, 'task, B: BV, R>(
             let collected = collected.clone();
 
             scope.spawn(move || {
-                let q = Worker::new_lifo();
+                let q = strategy.worker();
                 {
                     let mut stealers = stealers.write().unwrap();
                     stealers.push(q.stealer());
                 }
+                let next_task = || prioritized.pop().or_else(|| find_task(&q, &global, &stealers));
                 loop {
-                    if let Some(task) = find_task(&q, &global, &stealers) {
+                    if let Some(task) = next_task() {
                         thread_tx.send(Activity::Busy(tid)).unwrap();
-                        do_work(tid, timeout, &q, task, shared_state, collected.as_ref(), collector);
-                        while let Some(task) = find_task(&q, &global, &stealers) {
-                            do_work(tid, timeout, &q, task, shared_state, collected.as_ref(), collector)
+                        do_work(tid, timeout, &q, &global, task, shared_state, collected.as_ref(), collector);
+                        strategy.schedule(&q, prioritized);
+                        while let Some(task) = next_task() {
+                            do_work(tid, timeout, &q, &global, task, shared_state, collected.as_ref(), collector);
+                            strategy.schedule(&q, prioritized)
                         }
                     };
                     thread_tx.send(Activity::Idle(tid, poke_tx.clone())).unwrap();
@@ -1881,8 +3065,19 @@ pub fn start_multi<'ir, 'task, B: BV, R>(
                         Activity::Busy(tid) => panic!("Found busy thread {} when quiescent", tid),
                     }
                 }
+                if let Some((progress, counters)) = &progress {
+                    let counts = counters.counts().since(start_counts);
+                    report_progress(progress.format, timeout.start_time.elapsed(), counts, true)
+                }
                 break;
             }
+            if let Some((progress, counters)) = &progress {
+                if last_report.elapsed() >= progress.interval {
+                    let counts = counters.counts().since(start_counts);
+                    report_progress(progress.format, timeout.start_time.elapsed(), counts, false);
+                    last_report = Instant::now()
+                }
+            }
             for message in &last_messages {
                 match message {
                     Activity::Idle(tid, poke) => {
@@ -1952,12 +3147,14 @@ pub enum TraceError {
     UnexpectedValue(String),
     /// An execution error occured when generating the trace
     Exec { err: ExecError, model: Option<String> },
+    /// A post-processor (see [crate::postprocess]) failed on the trace
+    PostProcess { name: String, msg: String },
 }
 
 impl IslaError for TraceError {
     fn source_loc(&self) -> SourceLoc {
         match self {
-            TraceError::UnexpectedValue(_) => SourceLoc::unknown(),
+            TraceError::UnexpectedValue(_) | TraceError::PostProcess { .. } => SourceLoc::unknown(),
             TraceError::Exec { err, .. } => err.source_loc(),
         }
     }
@@ -1969,6 +3166,7 @@ impl fmt::Display for TraceError {
             TraceError::UnexpectedValue(s) => write!(f, "Unexpected value {}", s),
             TraceError::Exec { err, model: Some(s) } => write!(f, "{}\nModel: {}", err, s),
             TraceError::Exec { err, model: None } => write!(f, "{}", err),
+            TraceError::PostProcess { name, msg } => write!(f, "Post-processor {} failed: {}", name, msg),
         }
     }
 }
@@ -1987,12 +3185,29 @@ impl TraceError {
     }
 }
 
+/// The order of the events placed in the queues by the trace
+/// collectors below.
+pub const COLLECTOR_ORDER: EventOrder = EventOrder::Reverse;
+
 pub type TraceQueue<B> = SegQueue<Result<(usize, Vec<Event<B>>), TraceError>>;
 
 pub type TraceResultQueue<B> = SegQueue<Result<(usize, bool, Vec<Event<B>>), TraceError>>;
 
 pub type TraceValueQueue<B> = SegQueue<Result<(usize, Val<B>, Vec<Event<B>>), TraceError>>;
 
+pub type TraceRegistersQueue<B> = SegQueue<Result<(usize, Vec<Event<B>>, HashMap<Name, Val<B>>), TraceError>>;
+
+/// A path collected by [frame_collector].
+pub enum FramePath<'ir, B> {
+    /// The path returned, with its final frame and a checkpoint it
+    /// can be continued from
+    Returned(Frame<'ir, B>, Checkpoint<B>),
+    /// The path exited, so there is no frame to continue from
+    Exited,
+}
+
+pub type FrameQueue<'ir, B> = SegQueue<Result<(usize, FramePath<'ir, B>), TraceError>>;
+
 pub fn trace_collector<'ir, B: BV>(
     tid: usize,
     task_id: usize,
@@ -2022,6 +3237,51 @@ pub fn trace_collector<'ir, B: BV>(
     }
 }
 
+/// A collector like [trace_collector] which also captures the value
+/// of each initialized register in the final frame of every path. A
+/// path which ends by exiting has no final frame, so for those the
+/// values are taken from the trace by
+/// [crate::trace::final_register_values] instead.
+pub fn trace_registers_collector<'ir, B: BV>(
+    tid: usize,
+    task_id: usize,
+    result: Result<(Val<B>, LocalFrame<'ir, B>), (ExecError, Backtrace)>,
+    shared_state: &SharedState<'ir, B>,
+    mut solver: Solver<B>,
+    collected: &TraceRegistersQueue<B>,
+) {
+    match result {
+        Ok((_, frame)) => {
+            let registers = frame
+                .regs()
+                .iter()
+                .filter_map(|(name, reg)| reg.read_last_if_initialized().map(|value| (*name, value.clone())))
+                .collect();
+            let mut events = solver.trace().to_vec();
+            collected.push(Ok((task_id, events.drain(..).cloned().collect(), registers)))
+        }
+        Err((ExecError::Exit, _)) => {
+            let events: Vec<Event<B>> = solver.trace().to_vec().into_iter().cloned().collect();
+            let mut execution_order = events.clone();
+            COLLECTOR_ORDER.convert(EventOrder::Execution, &mut execution_order);
+            collected.push(Ok((task_id, events, trace::final_register_values(&execution_order))))
+        }
+        Err((ExecError::Dead, _)) => (),
+        Err((err, backtrace)) => {
+            log_from!(tid, log::VERBOSE, format!("Error {:?}", err));
+            for (f, pc) in backtrace.iter().rev() {
+                log_from!(tid, log::VERBOSE, format!("  {} @ {}", shared_state.symtab.to_str(*f), pc));
+            }
+            if solver.check_sat() == SmtResult::Sat {
+                let model = Model::new(&solver);
+                collected.push(Err(TraceError::exec_model(err, model)))
+            } else {
+                collected.push(Err(TraceError::exec(err)))
+            }
+        }
+    }
+}
+
 pub fn trace_value_collector<'ir, B: BV>(
     _: usize,
     task_id: usize,
@@ -2066,6 +3326,29 @@ pub fn trace_result_collector<'ir, B: BV>(
     }
 }
 
+/// Collects the final frame and a solver checkpoint for each path, so
+/// that execution can be continued afterwards, e.g. to run another
+/// instruction in sequence using `LocalFrame::new_call`. Paths which
+/// exit are collected as [FramePath::Exited], so callers can report
+/// them.
+pub fn frame_collector<'ir, B: BV>(
+    _: usize,
+    task_id: usize,
+    result: Result<(Val<B>, LocalFrame<'ir, B>), (ExecError, Backtrace)>,
+    _: &SharedState<'ir, B>,
+    mut solver: Solver<B>,
+    collected: &FrameQueue<'ir, B>,
+) {
+    match result {
+        Ok((_, frame)) => {
+            collected.push(Ok((task_id, FramePath::Returned(freeze_frame(&frame), checkpoint(&mut solver)))))
+        }
+        Err((ExecError::Exit, _)) => collected.push(Ok((task_id, FramePath::Exited))),
+        Err((ExecError::Dead, _)) => (),
+        Err((err, _)) => collected.push(Err(TraceError::exec(err))),
+    }
+}
+
 pub fn footprint_collector<'ir, B: BV>(
     _: usize,
     task_id: usize,
@@ -2089,3 +3372,412 @@ pub fn footprint_collector<'ir, B: BV>(
         Err((err, _)) => collected.push(Err(TraceError::exec(err))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir_parse::parse_ir;
+    use crate::primop::Primops;
+
+    /// The result and events of a path, in execution order
+    type Traced = (Result<Val<B64>, String>, Vec<Event<B64>>);
+
+    /// Run the function `zf` in the IR, which takes a symbolic `%bv8`
+    /// argument, returning the result and trace of each path. The
+    /// `pure` functions are added to the shared state, and the task
+    /// state is built once the names in the IR are known.
+    fn run_traced(
+        ir: &str,
+        pure: &[&str],
+        task_state: impl FnOnce(&Symtab) -> TaskState<B64>,
+    ) -> Vec<Traced> {
+        run_traced_with_strategy(ir, pure, Strategy::DepthFirst, task_state)
+    }
+
+    /// Parse the IR, replacing calls to externs with primops as
+    /// architectures are when they are loaded.
+    fn parse_test_ir(ir: &str) -> (Symtab<'_>, Vec<Def<Name, B64>>) {
+        let mut symtab = Symtab::new();
+        let mut defs = parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let mut externs = HashMap::new();
+        for def in &defs {
+            if let Def::Extern(f, is_abstract, ext, _, _) = def {
+                externs.insert(*f, (ext.to_string(), *is_abstract));
+            }
+        }
+        externs.insert(SAIL_ASSERT, ("optimistic_assert".to_string(), false));
+        let primops = Primops::default();
+        for def in defs.iter_mut() {
+            if let Def::Fn(_, _, instrs) = def {
+                *instrs = instrs.drain(..).map(|instr| insert_instr_primops(instr, &externs, &primops)).collect()
+            }
+        }
+        (symtab, defs)
+    }
+
+    fn test_shared_state<'ir>(symtab: Symtab<'ir>, defs: &'ir [Def<Name, B64>]) -> SharedState<'ir, B64> {
+        let type_info = IRTypeInfo::new(defs);
+        SharedState::new(symtab, defs, type_info, HashSet::new(), HashSet::new(), Vec::new(), Vec::new(), Vec::new())
+    }
+
+    /// The task for a call to `zf` in the IR, with a symbolic argument
+    fn test_task<'ir, 'task>(
+        shared_state: &SharedState<'ir, B64>,
+        task_state: &'task TaskState<B64>,
+    ) -> Task<'ir, 'task, B64> {
+        let x = shared_state.symtab.lookup("zx");
+        let f = shared_state.symtab.lookup("zf");
+        let (args, ret_ty, instrs) = shared_state.functions.get(&f).expect("no function zf in IR");
+        assert_eq!(args.iter().map(|(arg, _)| *arg).collect::<Vec<_>>(), vec![x]);
+        LocalFrame::new(f, args, ret_ty, None, instrs).task(0, task_state)
+    }
+
+    fn collect_traced<'ir>(
+        _: usize,
+        _: usize,
+        result: Result<(Val<B64>, LocalFrame<'ir, B64>), (ExecError, Backtrace)>,
+        _: &SharedState<'ir, B64>,
+        solver: Solver<B64>,
+        results: &Mutex<Vec<Traced>>,
+    ) {
+        let mut events: Vec<Event<B64>> = solver.trace().to_vec().into_iter().cloned().collect();
+        events.reverse();
+        let result = result.map(|(value, _)| value).map_err(|(err, _)| err.to_string());
+        results.lock().unwrap().push((result, events))
+    }
+
+    /// As [run_traced], exploring the paths in the order given by
+    /// `strategy`. The results are in the order the paths finished.
+    fn run_traced_with_strategy(
+        ir: &str,
+        pure: &[&str],
+        strategy: Strategy<B64>,
+        task_state: impl FnOnce(&Symtab) -> TaskState<B64>,
+    ) -> Vec<Traced> {
+        let (symtab, defs) = parse_test_ir(ir);
+        let task_state = task_state(&symtab);
+        let pure_functions = pure.iter().map(|name| symtab.lookup(name)).collect();
+        let mut shared_state = test_shared_state(symtab, &defs);
+        shared_state.pure_functions = pure_functions;
+        let task = test_task(&shared_state, &task_state);
+        let results = Mutex::new(Vec::new());
+        start_single_with_strategy(strategy, task, &shared_state, &results, &collect_traced);
+        results.into_inner().unwrap()
+    }
+
+    /// As [run_traced], but running the paths across `num_threads`
+    /// threads with [start_multi], so they finish in no particular
+    /// order.
+    fn run_traced_multi(ir: &str, num_threads: usize, task_state: &TaskState<B64>) -> Vec<Traced> {
+        let (symtab, defs) = parse_test_ir(ir);
+        let shared_state = test_shared_state(symtab, &defs);
+        let task = test_task(&shared_state, task_state);
+        let results = Arc::new(Mutex::new(Vec::new()));
+        start_multi(num_threads, None, vec![task], &shared_state, results.clone(), &collect_traced);
+        let results = results.lock().unwrap().drain(..).collect();
+        results
+    }
+
+    fn run_symbolic(ir: &str, task_state: TaskState<B64>) -> Vec<Result<Val<B64>, String>> {
+        run_traced(ir, &[], |_| task_state).into_iter().map(|(result, _)| result).collect()
+    }
+
+    const MERGE_PURE: &str = "val zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zy : %bv8 = 0x00;\n  jump @eq(zx, 0x01) goto 4;\n  zy = @bvadd(zx, 0x02);\n  goto 5;\n  zy = 0x01;\n  return = zy;\n  end\n}\n";
+
+    const MERGE_ASSUME: &str = "val zassume = \"assume\" : (%bool) -> %unit\nval zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zy : %bv8 = 0x00;\n  zu : %unit;\n  jump @eq(zx, 0x01) goto 6;\n  zu = zassume(@eq(zx, 0x02));\n  zy = 0x02;\n  goto 7;\n  zy = 0x01;\n  return = zy;\n  end\n}\n";
+
+    const MERGE_ASSERT: &str = "val zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zy : %bv8 = 0x00;\n  zu : %unit;\n  jump @eq(zx, 0x01) goto 5;\n  zu = zsail_assert(@eq(zx, 0x02), \"not two\");\n  goto 6;\n  zy = 0x01;\n  return = zy;\n  end\n}\n";
+
+    const STRATEGY: &str = "val zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zy : %bv8 = 0x00;\n  jump @eq(@bvand(zx, 0x01), 0x00) goto 3;\n  zy = @bvadd(zy, 0x01);\n  jump @eq(@bvand(zx, 0x02), 0x00) goto 5;\n  zy = @bvadd(zy, 0x02);\n  return = zy;\n  end\n}\n";
+
+    #[test]
+    fn strategy_order() {
+        let order = |strategy| -> Vec<u64> {
+            run_traced_with_strategy(STRATEGY, &[], strategy, |_| TaskState::new())
+                .into_iter()
+                .map(|(result, _)| match result {
+                    Ok(Val::Bits(bv)) => bv.lower_u64(),
+                    result => panic!("unexpected result {:?}", result),
+                })
+                .collect()
+        };
+        assert_eq!(order(Strategy::DepthFirst), vec![0, 2, 1, 3]);
+        assert_eq!(order(Strategy::BreadthFirst), vec![0, 1, 2, 3]);
+
+        // Equal scores are explored depth-first. Preferring shorter
+        // traces explores the path forked at the first branch next,
+        // then the paths forked at the second branch tie
+        assert_eq!(order(Strategy::Priority(&|_| 0)), vec![0, 2, 1, 3]);
+        let shortest = |checkpoint: &Checkpoint<B64>| -(checkpoint.trace().as_ref().map_or(0, Trace::len) as i64);
+        assert_eq!(order(Strategy::Priority(&shortest)), vec![0, 1, 3, 2])
+    }
+
+    #[test]
+    fn progress_counts_per_run() {
+        // Two runs at once must not count each other's tasks
+        let progress = Progress { interval: Duration::from_secs(3600), format: ProgressFormat::Text };
+        let task_states: Vec<TaskState<B64>> = (0..2).map(|_| TaskState::new().with_progress(Some(progress))).collect();
+        std::thread::scope(|scope| {
+            for task_state in &task_states {
+                scope.spawn(move || assert_eq!(run_traced_multi(STRATEGY, 2, task_state).len(), 4));
+            }
+        });
+        for task_state in &task_states {
+            let counts = task_state.progress_counts().unwrap();
+            assert_eq!((counts.queued, counts.finished, counts.dead, counts.failed), (4, 4, 0, 0))
+        }
+        assert!(TaskState::<B64>::new().progress_counts().is_none())
+    }
+
+    #[test]
+    fn time_slice_yield() {
+        // Suspending every path at each instruction and resuming it
+        // from the queue must not change what it does
+        let traces = |task_state: &TaskState<B64>| {
+            let mut traces: Vec<String> =
+                run_traced_multi(STRATEGY, 2, task_state).iter().map(|traced| format!("{:?}", traced)).collect();
+            traces.sort();
+            traces
+        };
+        let unsliced = traces(&TaskState::new());
+        assert_eq!(unsliced.len(), 4);
+        assert_eq!(traces(&TaskState::new().with_time_slice(Some(Duration::ZERO))), unsliced)
+    }
+
+    #[test]
+    fn merge_pure_branch() {
+        let forked = run_symbolic(MERGE_PURE, TaskState::new());
+        assert_eq!(forked.len(), 2);
+        let merged = run_symbolic(MERGE_PURE, TaskState::new().with_path_merging(true));
+        assert_eq!(merged.len(), 1);
+        assert!(matches!(merged[0], Ok(Val::Symbolic(_))))
+    }
+
+    #[test]
+    fn merge_assume_forks() {
+        // The assumption only holds on one side of the branch, so the
+        // branch must be forked rather than merged
+        let results = run_symbolic(MERGE_ASSUME, TaskState::new().with_path_merging(true));
+        assert_eq!(results.len(), 2);
+        let mut values: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        values.sort_by_key(|value| format!("{:?}", value));
+        assert_eq!(values, vec![Val::Bits(B64::new(1, 8)), Val::Bits(B64::new(2, 8))])
+    }
+
+    #[test]
+    fn merge_assert_forks() {
+        let results = run_symbolic(MERGE_ASSERT, TaskState::new().with_path_merging(true));
+        assert_eq!(results.len(), 2, "{:?}", results);
+        assert!(results.iter().all(Result::is_ok), "{:?}", results)
+    }
+
+    const NESTED_LOOP: &str = "val zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zi : %bv8 = 0x00;\n  zs : %bv8 = 0x00;\n  jump @eq(zi, 0x04) goto 10;\n  zj : %bv8 = 0x00;\n  jump @eq(zj, 0x04) goto 8;\n  zs = @bvadd(zs, 0x01);\n  zj = @bvadd(zj, 0x01);\n  goto 4;\n  zi = @bvadd(zi, 0x01);\n  goto 2;\n  return = zs;\n  end\n}\n";
+
+    #[test]
+    fn nested_loop_bound() {
+        // Each loop runs four times, so the inner loop's count must
+        // start again on each iteration of the outer loop
+        let results = run_symbolic(NESTED_LOOP, TaskState::new().with_loop_bounds(Some(8), HashMap::new()));
+        assert_eq!(results, vec![Ok(Val::Bits(B64::new(16, 8)))]);
+        let results = run_symbolic(NESTED_LOOP, TaskState::new().with_loop_bounds(Some(3), HashMap::new()));
+        assert_ne!(results, vec![Ok(Val::Bits(B64::new(16, 8)))])
+    }
+
+    #[test]
+    fn reset_constant_register() {
+        let mut symtab = Symtab::new();
+        let defs = parse_ir::<B64>("register zR : %bv8\n", &mut symtab).map_err(|_| "parse failed").unwrap();
+        let r = symtab.lookup("zR");
+        let type_info = IRTypeInfo::new(&defs);
+        let mut shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let ty = Ty::Bits(8);
+        let mut frame = LocalFrame::<B64>::new(r, &[], &ty, None, &[]);
+        let reset: Reset<B64> = Arc::new(|_, _, _| Ok(Val::Bits(B64::new(1, 8))));
+        let task_state = TaskState::new().with_reset_registers(vec![(Loc::Id(r), reset)].into_iter().collect());
+
+        // A register whose reads were replaced by its default value
+        // when the IR was optimized cannot be reset by a task
+        shared_state.constant_registers.insert(r);
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let result = reset_registers(0, &mut frame, &task_state, &shared_state, &mut solver, SourceLoc::unknown());
+        assert!(matches!(result, Err(ExecError::Unreachable(_))), "{:?}", result.err())
+    }
+
+    const PURE_CALLS: &str = "val zundefined_bool = \"undefined_bool\" : (%unit) -> %bool\nval zg : (%bv8) -> %bool\nfn zg(zy) {\n  return = zundefined_bool(());\n  end\n}\nval zf : (%bv8) -> %bv8\nfn zf(zx) {\n  za : %bool;\n  zb : %bool;\n  za = zg(zx);\n  zb = zg(zx);\n  jump @eq(za, zb) goto 7;\n  return = 0x01;\n  goto 8;\n  return = 0x00;\n  end\n}\n";
+
+    #[test]
+    fn pure_call_results() {
+        // Each call to zg returns a fresh value, so the results only
+        // differ when the second call is not replaced by the first
+        let results = run_symbolic(PURE_CALLS, TaskState::new());
+        assert_eq!(results.len(), 2, "{:?}", results);
+
+        let mut g = None;
+        let results = run_traced(PURE_CALLS, &["zg"], |symtab| {
+            g = Some(symtab.lookup("zg"));
+            TaskState::new().with_decode_trace([symtab.lookup("zf")].into_iter().collect())
+        });
+        assert_eq!(results.len(), 1);
+        let (result, events) = &results[0];
+        assert_eq!(result, &Ok(Val::Bits(B64::new(0, 8))));
+
+        // The cached call is still part of the decode trace
+        let calls = events.iter().filter(|ev| matches!(ev, Event::DecodeCall { name, .. } if Some(*name) == g)).count();
+        let returns =
+            events.iter().filter(|ev| matches!(ev, Event::DecodeReturn { name, .. } if Some(*name) == g)).count();
+        assert_eq!((calls, returns), (2, 2))
+    }
+
+    const DECODE: &str = "val zeq_bits = \"eq_bits\" : (%bv, %bv) -> %bool\nval zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zop : %bv4 = @slice::<4>(zx, 4);\n  zlow : %bv4;\n  zlow = @slice::<4>(zx, 0);\n  zb : %bool;\n  zb = zeq_bits(zlow, 0x3);\n  jump @eq(zop, 0x1) goto 8;\n  return = 0x00;\n  goto 9;\n  return = 0x01;\n  end\n}\n";
+
+    #[test]
+    fn decode_values() {
+        let mut names = HashMap::new();
+        let results = run_traced(DECODE, &[], |symtab| {
+            for name in ["zop", "zlow", "zb"] {
+                names.insert(symtab.lookup(name), name);
+            }
+            TaskState::new().with_decode_trace([symtab.lookup("zf")].into_iter().collect())
+        });
+        assert_eq!(results.len(), 2);
+
+        for (_, events) in &results {
+            let values: Vec<(&str, &Val<B64>, usize)> = events
+                .iter()
+                .filter_map(|ev| match ev {
+                    Event::DecodeValue { name, args, value, .. } => Some((names[name], value, args.len())),
+                    _ => None,
+                })
+                .collect();
+            let tests: Vec<&[Val<B64>]> = events
+                .iter()
+                .filter_map(|ev| match ev {
+                    Event::DecodeTest { args, .. } => Some(args.as_slice()),
+                    _ => None,
+                })
+                .collect();
+
+            // Both slices and the primop comparing one of them are
+            // recorded, then the branch on the other slice
+            match (values.as_slice(), tests.as_slice()) {
+                ([("zop", op, 2), ("zlow", _, 2), ("zb", _, 2)], [[tested, Val::Bits(bv)]]) => {
+                    assert_eq!(*op, tested);
+                    assert_eq!(*bv, B64::new(1, 4))
+                }
+                _ => panic!("Unexpected decode events {:?} {:?}", values, tests),
+            }
+        }
+
+        let results = run_traced(DECODE, &[], |_| TaskState::new());
+        assert!(results.iter().all(|(_, events)| !events.iter().any(Event::is_decode)))
+    }
+
+    const STUB: &str = "register zR : %bv8\nval zg : (%bv8) -> %bv8\nfn zg(zy) {\n  return = zy;\n  end\n}\nval zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zy : %bv8;\n  zy = zg(zx);\n  return = zy;\n  end\n}\n";
+
+    #[test]
+    fn stubbed_call() {
+        use crate::config::StubCase;
+
+        let (symtab, defs) = parse_test_ir(STUB);
+        let (g, r) = (symtab.lookup("zg"), symtab.lookup("zR"));
+        let mut shared_state = test_shared_state(symtab, &defs);
+        let bits = |bits| Val::Bits(B64::new(bits, 8));
+        let stub = FunctionStub {
+            cases: vec![StubCase { args: vec![Some(bits(0x01))], result: bits(0x10) }],
+            default: Some(bits(0x20)),
+            havoc: vec![r],
+        };
+        shared_state.function_stubs.insert(g, stub);
+        let mut regs = RegisterBindings::new();
+        regs.insert(r, false, UVal::Init(bits(0x00)));
+        let task_state = TaskState::new();
+        let f = shared_state.symtab.lookup("zf");
+        let (args, ret_ty, instrs) = &shared_state.functions[&f];
+        let task = LocalFrame::new(f, args, ret_ty, None, instrs).add_regs(&regs).task(0, &task_state);
+        let results = Mutex::new(Vec::new());
+        start_single(task, &shared_state, &results, &collect_traced);
+        let results = results.into_inner().unwrap();
+        assert_eq!(results.len(), 1);
+
+        // The call is replaced by an ite over the case and default,
+        // rather than running zg, which would return its argument
+        let (result, events) = &results[0];
+        let Ok(Val::Symbolic(v)) = result else { panic!("unexpected result {:?}", result) };
+        let ite = events.iter().find_map(|ev| match ev {
+            Event::Smt(smtlib::Def::DefineConst(w, smtlib::Exp::Ite(_, lhs, rhs)), _, _) if w == v => Some((lhs, rhs)),
+            _ => None,
+        });
+        match ite {
+            Some((lhs, rhs)) => {
+                assert_eq!(**lhs, smtlib::Exp::Bits64(B64::new(0x10, 8)));
+                assert_eq!(**rhs, smtlib::Exp::Bits64(B64::new(0x20, 8)))
+            }
+            None => panic!("no ite defining the result in {:?}", events),
+        }
+
+        // The havocked register is written with a fresh value
+        assert!(events.iter().any(|ev| matches!(ev, Event::WriteReg(reg, _, Val::Symbolic(_), _) if *reg == r)))
+    }
+
+    #[test]
+    fn block_address_masking() {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let info = SourceLoc::unknown();
+        let block = BlockFunction { bytes: 64, address_field: None };
+
+        let address = |bits, len| [Val::Bits(B64::new(bits, len))];
+        let masked =
+            |solver: &mut Solver<B64>, args: &[Val<B64>], block| block_address(args, block, solver, info).unwrap();
+        assert_eq!(masked(&mut solver, &address(0x1234_5677, 64), &block), Val::Bits(B64::new(0x1234_5640, 64)));
+        assert_eq!(masked(&mut solver, &address(0x7f, 7), &block), Val::Bits(B64::new(0x40, 7)));
+        assert_eq!(masked(&mut solver, &address(0, 0), &block), Val::Bits(B64::new(0, 0)));
+
+        let field = Name::from_u32(0);
+        let mut fields = HashMap::default();
+        fields.insert(field, Val::Bits(B64::new(0xffff, 16)));
+        let with_field = BlockFunction { bytes: 16, address_field: Some(field) };
+        assert_eq!(masked(&mut solver, &[Val::Struct(fields)], &with_field), Val::Bits(B64::new(0xfff0, 16)));
+
+        let x = solver.declare_const(smtlib::Ty::BitVec(64), info);
+        solver.assert_eq(smtlib::Exp::Var(x), smtlib::bits64(0x1234_5677, 64));
+        let Val::Symbolic(v) = masked(&mut solver, &[Val::Symbolic(x)], &block) else {
+            panic!("expected a symbolic address")
+        };
+        let other = smtlib::Exp::Neq(Box::new(smtlib::Exp::Var(v)), Box::new(smtlib::bits64(0x1234_5640, 64)));
+        assert_eq!(solver.check_sat_with(&other), SmtResult::Unsat)
+    }
+
+    #[test]
+    fn function_cache() {
+        let cache: FunctionCache<B64> = FunctionCache::new();
+        let f = Name::from_u32(0);
+        let args = vec![Val::Bits(B64::new(3, 8)), Val::I128(1)];
+        assert_eq!(cache.get(f, &args), None);
+
+        cache.insert(f, &args, &Val::Bits(B64::new(4, 8)));
+        assert_eq!(cache.get(f, &args), Some(Val::Bits(B64::new(4, 8))));
+        assert_eq!(cache.get(Name::from_u32(1), &args), None);
+        assert_eq!(cache.get(f, &[Val::Bits(B64::new(3, 8)), Val::I128(2)]), None);
+
+        // Results involving symbolic values are specific to the path
+        // which computed them
+        let symbolic_args = vec![Val::Symbolic(Sym::from_u32(0)), Val::I128(1)];
+        cache.insert(f, &symbolic_args, &Val::Bits(B64::new(4, 8)));
+        assert_eq!(cache.get(f, &symbolic_args), None);
+        cache.insert(f, &[Val::I128(2)], &Val::Symbolic(Sym::from_u32(0)));
+        assert_eq!(cache.get(f, &[Val::I128(2)]), None);
+
+        assert_eq!(cache.counts(), FunctionCacheCounts { entries: 1, hits: 1, misses: 4 })
+    }
+}
@@ -0,0 +1,159 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019-2023 Alasdair Armstrong
+// Copyright (c) 2020 Brian Campbell
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A minimal API for exploring traces outside of the symbolic
+//! executor, for example in a browser-based trace viewer built for
+//! `wasm32-unknown-unknown` with the `z3` feature disabled. An
+//! [Explorer] is created from a serialized architecture (as produced
+//! by `isla-preprocess`), and can then parse, simplify, and print
+//! traces saved by [crate::trace::save_traces], resolving the names
+//! in them with the architecture's symbol table.
+
+use std::collections::HashSet;
+
+use crate::bitvector::BV;
+use crate::ir::serialize::DeserializedArchitecture;
+use crate::ir::{IRTypeInfo, Name, SharedState, Symtab};
+use crate::simplify::{self, WriteOpts};
+use crate::smt::{Event, EventOrder};
+use crate::trace;
+use crate::zencode;
+
+pub struct Explorer<'ir, B> {
+    shared_state: SharedState<'ir, B>,
+}
+
+impl<'ir, B: BV> Explorer<'ir, B> {
+    pub fn new(arch: &'ir DeserializedArchitecture<B>) -> Self {
+        let symtab = Symtab::from_raw_table(&arch.strings, &arch.files);
+        let type_info = IRTypeInfo::new(&arch.ir);
+        let shared_state = SharedState::new(
+            symtab,
+            &arch.ir,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+        Explorer { shared_state }
+    }
+
+    pub fn symtab(&self) -> &Symtab<'ir> {
+        &self.shared_state.symtab
+    }
+
+    /// The original Sail name of an identifier in a trace, such as a
+    /// register or function name.
+    pub fn name(&self, name: Name) -> String {
+        zencode::decode(self.shared_state.symtab.to_str_demangled(name))
+    }
+
+    /// Find the identifier for a Sail name, the inverse of
+    /// [Explorer::name] for names which were not mangled.
+    pub fn lookup(&self, name: &str) -> Option<Name> {
+        self.shared_state.symtab.get(&zencode::encode(name))
+    }
+
+    /// Parse the contents of a file written by
+    /// [crate::trace::save_traces].
+    pub fn parse_traces(&self, bytes: &[u8]) -> Result<Vec<Vec<Event<B>>>, String> {
+        trace::parse_traces(bytes)
+    }
+
+    /// Apply the default simplifications used when printing
+    /// footprints. The events should be in execution order, as
+    /// written by [crate::trace::save_traces].
+    pub fn simplify(&self, events: &mut Vec<Event<B>>) {
+        EventOrder::Execution.convert(simplify::SIMPLIFY_ORDER, events);
+        simplify::hide_initialization(events);
+        simplify::remove_extra_register_fields(events);
+        simplify::remove_repeated_register_reads(events);
+        simplify::remove_unused_register_assumptions(events);
+        simplify::remove_unused(events);
+        simplify::propagate_forwards_used_once(events);
+        simplify::commute_extract(events);
+        simplify::eval(events);
+        simplify::SIMPLIFY_ORDER.convert(EventOrder::Execution, events)
+    }
+
+    /// Print a trace in execution order.
+    pub fn write_trace(&self, events: &[Event<B>]) -> String {
+        let mut buf = Vec::new();
+        simplify::write_events_with_opts(&mut buf, events, &self.shared_state, &WriteOpts::default()).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::ir::serialize::{read_serialized_architecture_from, write_serialized_architecture};
+    use crate::ir::Val;
+    use crate::ir_parse::parse_ir;
+    use crate::source_loc::SourceLoc;
+
+    #[test]
+    fn explore_saved_traces() {
+        let mut symtab = Symtab::new();
+        let defs = parse_ir::<B64>("register zR0 : %bv64\n", &mut symtab).map_err(|_| "parse failed").unwrap();
+        let r0 = symtab.lookup("zR0");
+        let path = std::env::temp_dir().join(format!("isla-explorer-test-{}.irx", std::process::id()));
+        write_serialized_architecture(path.to_str().unwrap(), defs, &symtab).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let arch = read_serialized_architecture_from::<_, B64>(bytes.as_slice()).unwrap();
+        let explorer = Explorer::new(&arch);
+        assert_eq!(explorer.lookup("R0"), Some(r0));
+        assert_eq!(explorer.name(r0), "R0");
+        assert_eq!(explorer.lookup("R1"), None);
+
+        let info = SourceLoc::unknown();
+        // Register accesses before the first cycle are hidden as
+        // initialization when simplifying
+        let traces: Vec<Vec<Event<B64>>> = vec![vec![
+            Event::Cycle,
+            Event::ReadReg(r0, Vec::new(), Val::Bits(B64::new(1, 64)), info),
+            Event::WriteReg(r0, Vec::new(), Val::Bits(B64::new(2, 64)), info),
+        ]];
+        let saved = bincode::serialize(&traces).unwrap();
+        let mut traces = explorer.parse_traces(&saved).unwrap();
+        assert_eq!(traces.len(), 1);
+        explorer.simplify(&mut traces[0]);
+        let trace = explorer.write_trace(&traces[0]);
+        assert!(trace.contains("(read-reg |R0| nil #x0000000000000001)"), "{}", trace);
+        assert!(trace.contains("(write-reg |R0| nil #x0000000000000002)"), "{}", trace);
+
+        assert!(explorer.parse_traces(b"not a trace").is_err())
+    }
+}
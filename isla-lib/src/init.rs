@@ -52,23 +52,66 @@
 //! module to set up everything ready for symbolic execution.
 
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::Mutex;
 
 use crate::bitvector::BV;
 use crate::config::ISAConfig;
 use crate::executor::{start_single, LocalFrame, TaskState};
+use crate::ir::optimize::{optimize, written_names};
 use crate::ir::*;
 use crate::log;
 use crate::register::RegisterBindings;
 use crate::zencode;
 
+/// The result of evaluating a top-level let binding at
+/// initialization time.
+#[derive(Clone, Debug)]
+pub enum LetStatus {
+    Concrete,
+    /// The value was given by the `lets` table in the ISA config
+    Forced,
+    /// The value depends on symbolic variables
+    Symbolic,
+    /// Evaluation finished without initializing the binding
+    Uninitialized,
+    /// Evaluation failed with the given error
+    Failed(String),
+}
+
+impl fmt::Display for LetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LetStatus::Concrete => write!(f, "concrete"),
+            LetStatus::Forced => write!(f, "forced by configuration"),
+            LetStatus::Symbolic => write!(f, "symbolic"),
+            LetStatus::Uninitialized => write!(f, "uninitialized"),
+            LetStatus::Failed(msg) => write!(f, "evaluation failed: {}", msg),
+        }
+    }
+}
+
 fn initialize_letbinding<'ir, B: BV>(
     bindings: &'ir [(Name, Ty<Name>)],
     setup: &'ir [Instr<Name, B>],
     shared_state: &SharedState<'ir, B>,
+    let_values: &HashMap<Name, Val<B>>,
     registers: &Mutex<RegisterBindings<'ir, B>>,
     letbindings: &Mutex<Bindings<'ir, B>>,
+    let_status: &Mutex<Vec<(Name, LetStatus)>>,
 ) {
+    // If every binding is given by the configuration there is no need
+    // to evaluate the setup code
+    if bindings.iter().all(|(id, _)| let_values.contains_key(id)) {
+        let mut state = letbindings.lock().unwrap();
+        let mut status = let_status.lock().unwrap();
+        for (id, _) in bindings.iter() {
+            state.insert(*id, UVal::Init(let_values[id].clone()));
+            status.push((*id, LetStatus::Forced))
+        }
+        return;
+    }
+
     let vars: Vec<_> = bindings.iter().map(|(id, ty)| (*id, ty)).collect();
     let task_state = TaskState::new();
     let task = {
@@ -83,25 +126,51 @@ fn initialize_letbinding<'ir, B: BV>(
     start_single(
         task,
         shared_state,
-        &letbindings,
-        &move |_tid, _task_id, result, shared_state, _solver, letbindings| match result {
+        &(letbindings, let_status),
+        &move |_tid, _task_id, result, shared_state, _solver, (letbindings, let_status)| match result {
             Ok((_, frame)) => {
+                let mut status = let_status.lock().unwrap();
                 for (id, _) in bindings.iter() {
                     match frame.vars().get(id) {
                         Some(value) => {
+                            status.push((
+                                *id,
+                                match value {
+                                    UVal::Init(v) if v.is_symbolic() => LetStatus::Symbolic,
+                                    UVal::Init(_) => LetStatus::Concrete,
+                                    UVal::Uninit(_) => LetStatus::Uninitialized,
+                                },
+                            ));
                             let mut state = letbindings.lock().unwrap();
                             state.insert(*id, value.clone());
                         }
                         None => {
                             let symbol = zencode::decode(shared_state.symtab.to_str(*id));
-                            log!(log::VERBOSE, &format!("No value for symbol {}", symbol))
+                            log!(log::VERBOSE, &format!("No value for symbol {}", symbol));
+                            status.push((*id, LetStatus::Uninitialized))
                         }
                     }
                 }
             }
-            Err(err) => log!(log::VERBOSE, &format!("Failed to evaluate letbinding: {:?}", err)),
+            Err(err) => {
+                log!(log::VERBOSE, &format!("Failed to evaluate letbinding: {:?}", err));
+                let mut status = let_status.lock().unwrap();
+                for (id, _) in bindings.iter() {
+                    status.push((*id, LetStatus::Failed(format!("{}", err.0))))
+                }
+            }
         },
     );
+
+    // Values from the configuration override whatever was evaluated
+    for (id, _) in bindings.iter() {
+        if let Some(value) = let_values.get(id) {
+            letbindings.lock().unwrap().insert(*id, UVal::Init(value.clone()));
+            let mut status = let_status.lock().unwrap();
+            status.retain(|(other, _)| other != id);
+            status.push((*id, LetStatus::Forced))
+        }
+    }
 }
 
 fn initialize_register<'ir, B: BV>(
@@ -156,10 +225,41 @@ fn initialize_register<'ir, B: BV>(
     }
 }
 
+/// The registers which keep their value from `registers.defaults`
+/// for the whole of execution, so reads of them can be replaced by
+/// that value (see [crate::ir::optimize]). These are the registers
+/// which are never written in the architecture, and which are not
+/// reset, havocked by a stub, relaxed, symbolic, or aliased. Resets
+/// given to a task by [crate::executor::TaskState::with_reset_registers]
+/// are not known here, so the executor rejects them for these
+/// registers instead (see [crate::ir::SharedState::constant_registers]).
+pub fn constant_registers<B: BV>(arch: &[Def<Name, B>], isa_config: &ISAConfig<B>) -> HashMap<Name, Val<B>> {
+    let mut excluded = written_names(arch);
+    excluded.extend(&isa_config.relaxed_registers);
+    excluded.extend(&isa_config.symbolic_registers);
+    excluded.extend(isa_config.reset_registers.iter().map(|(loc, _)| loc.id()));
+    excluded.extend(isa_config.function_stubs.values().flat_map(|stub| stub.havoc.iter().copied()));
+    for (alias, canonical) in &isa_config.register_aliases {
+        excluded.insert(*alias);
+        excluded.insert(canonical.register);
+    }
+    if let Some(check) = &isa_config.alignment_check {
+        excluded.insert(check.register);
+    }
+    isa_config
+        .default_registers
+        .iter()
+        .filter(|(reg, _)| !excluded.contains(reg))
+        .map(|(reg, value)| (*reg, value.clone()))
+        .collect()
+}
+
 pub struct Initialized<'ir, B> {
     pub regs: RegisterBindings<'ir, B>,
     pub lets: Bindings<'ir, B>,
     pub shared_state: SharedState<'ir, B>,
+    /// How each top-level let binding was initialized, in definition order
+    pub let_status: Vec<(Name, LetStatus)>,
 }
 
 /// Initialize an architecture for symbolic execution, producing an
@@ -192,9 +292,18 @@ pub fn initialize_architecture<'ir, B: BV>(
     use_model_register_init: bool,
 ) -> Initialized<'ir, B> {
     insert_monomorphize(arch);
+    let undefined = undefined_sites(arch);
     insert_primops(arch, mode, isa_config);
+    let constants = if isa_config.optimize_ir {
+        let constants = constant_registers(arch, isa_config);
+        let stats = optimize(arch, &constants);
+        log!(log::VERBOSE, &format!("Optimized IR: {}", stats));
+        constants
+    } else {
+        HashMap::default()
+    };
 
-    let shared_state = SharedState::new(
+    let mut shared_state = SharedState::new(
         symtab,
         arch,
         type_info,
@@ -204,13 +313,24 @@ pub fn initialize_architecture<'ir, B: BV>(
         isa_config.reset_constraints.clone(),
         isa_config.function_assumptions.clone(),
     );
+    if isa_config.precise_blocks {
+        shared_state.block_functions = isa_config.block_functions.clone()
+    }
+    shared_state.pure_functions = isa_config.pure_functions.clone();
+    shared_state.function_stubs = isa_config.function_stubs.clone();
+    shared_state.register_probes = isa_config.register_probes.clone();
+    shared_state.undefined_sites = undefined;
+    shared_state.constant_registers = constants.keys().copied().collect();
 
     let lets = Mutex::new(HashMap::default());
     let regs = Mutex::new(RegisterBindings::new());
+    let let_status = Mutex::new(Vec::new());
 
     for def in arch.iter() {
         match def {
-            Def::Let(bindings, setup) => initialize_letbinding(bindings, setup, &shared_state, &regs, &lets),
+            Def::Let(bindings, setup) => {
+                initialize_letbinding(bindings, setup, &shared_state, &isa_config.let_values, &regs, &lets, &let_status)
+            }
             Def::Register(id, ty, setup) => initialize_register(
                 id,
                 ty,
@@ -218,7 +338,8 @@ pub fn initialize_architecture<'ir, B: BV>(
                 &shared_state,
                 &isa_config.default_registers,
                 &isa_config.relaxed_registers,
-                use_model_register_init,
+                (use_model_register_init && !isa_config.symbolic_registers.contains(id))
+                    || isa_config.concrete_registers.contains(id),
                 &regs,
                 &lets,
             ),
@@ -226,7 +347,17 @@ pub fn initialize_architecture<'ir, B: BV>(
         }
     }
 
-    Initialized { regs: regs.into_inner().unwrap(), lets: lets.into_inner().unwrap(), shared_state }
+    let regs = regs.into_inner().unwrap();
+    for id in &isa_config.concrete_registers {
+        if regs.get_last_if_initialized(*id).is_none() {
+            eprintln!(
+                "Warning: register {} should be concrete, but it has no default value or initializer",
+                zencode::decode(shared_state.symtab.to_str(*id))
+            )
+        }
+    }
+
+    Initialized { regs, lets: lets.into_inner().unwrap(), shared_state, let_status: let_status.into_inner().unwrap() }
 }
 
 #[derive(Clone)]
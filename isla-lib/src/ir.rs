@@ -42,6 +42,7 @@
 //! specification see the [crate::init] module.
 
 use ahash;
+use num_bigint::BigInt;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
@@ -50,7 +51,7 @@ use std::io::Write;
 use std::sync::Arc;
 
 use crate::bitvector::{b64::B64, BV};
-use crate::config::ISAConfig;
+use crate::config::{BlockFunction, FunctionStub, ISAConfig};
 use crate::error::ExecError;
 use crate::memory::Memory;
 use crate::primop::{self, Binary, Primops, Unary, Variadic};
@@ -58,10 +59,13 @@ use crate::smt::{smtlib, Accessor, EnumMember, Solver, Sym};
 use crate::source_loc::SourceLoc;
 use crate::zencode;
 
+pub mod dead_code;
 pub mod linearize;
+pub mod optimize;
 pub mod partial_linearize;
 pub mod serialize;
 pub mod ssa;
+pub mod static_footprint;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
 pub struct Name {
@@ -91,6 +95,10 @@ pub struct FPTy {
 }
 
 impl FPTy {
+    pub fn new(ebits: u32, sbits: u32) -> Self {
+        FPTy { ebits, sbits }
+    }
+
     pub fn exponent_width(self) -> u32 {
         self.ebits
     }
@@ -142,6 +150,62 @@ pub enum Ty<A> {
     RoundingMode,
 }
 
+impl Ty<Name> {
+    /// Write a type in the syntax of the IR, with the names of any
+    /// enumerations, structs, or unions decoded.
+    pub fn write(&self, buf: &mut dyn Write, symtab: &Symtab) -> std::io::Result<()> {
+        use Ty::*;
+        match self {
+            I64 => write!(buf, "%i64"),
+            I128 => write!(buf, "%i"),
+            AnyBits => write!(buf, "%bv"),
+            Bits(sz) => write!(buf, "%bv{}", sz),
+            Unit => write!(buf, "%unit"),
+            Bool => write!(buf, "%bool"),
+            Bit => write!(buf, "%bit"),
+            String => write!(buf, "%string"),
+            Real => write!(buf, "%real"),
+            Enum(id) => write!(buf, "%enum {}", zencode::decode(symtab.to_str(*id))),
+            Struct(id) => write!(buf, "%struct {}", zencode::decode(symtab.to_str(*id))),
+            Union(id) => write!(buf, "%union {}", zencode::decode(symtab.to_str(*id))),
+            Vector(ty) => {
+                write!(buf, "%vec(")?;
+                ty.write(buf, symtab)?;
+                write!(buf, ")")
+            }
+            FixedVector(len, ty) => {
+                write!(buf, "%fvec({}, ", len)?;
+                ty.write(buf, symtab)?;
+                write!(buf, ")")
+            }
+            List(ty) => {
+                write!(buf, "%list(")?;
+                ty.write(buf, symtab)?;
+                write!(buf, ")")
+            }
+            Ref(ty) => {
+                write!(buf, "&(")?;
+                ty.write(buf, symtab)?;
+                write!(buf, ")")
+            }
+            Float(fpty) => match (fpty.ebits, fpty.sbits) {
+                (5, 11) => write!(buf, "%f16"),
+                (8, 24) => write!(buf, "%f32"),
+                (11, 53) => write!(buf, "%f64"),
+                (15, 113) => write!(buf, "%f128"),
+                (ebits, sbits) => write!(buf, "%float({}, {})", ebits, sbits),
+            },
+            RoundingMode => write!(buf, "%rounding_mode"),
+        }
+    }
+
+    pub fn to_string(&self, symtab: &Symtab) -> std::string::String {
+        let mut buf = Vec::new();
+        self.write(&mut buf, symtab).unwrap();
+        std::string::String::from_utf8(buf).unwrap()
+    }
+}
+
 /// A [Loc] is a location that can be assigned to.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Loc<A> {
@@ -207,7 +271,7 @@ pub enum Op {
     IsEmpty,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BitsSegment<B> {
     Symbolic(Sym),
     Concrete(B),
@@ -224,7 +288,7 @@ pub enum BitsSegment<B> {
 ///
 /// Note that the equality trait implements a literal equality, see
 /// [crate::primop] for a semantic comparison.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Val<B> {
     Symbolic(Sym),
     I64(i64),
@@ -242,6 +306,10 @@ pub enum Val<B> {
     SymbolicCtor(Sym, HashMap<Name, Val<B>>),
     Ref(Name),
     Poison,
+    /// An integer which does not fit in an `I128`. Integer primops
+    /// only produce these when their result overflows, so any value
+    /// which would fit is always an `I128`.
+    BigInt(BigInt),
 }
 
 impl<B: BV> From<&BitsSegment<B>> for Val<B> {
@@ -253,6 +321,17 @@ impl<B: BV> From<&BitsSegment<B>> for Val<B> {
     }
 }
 
+fn write_pretty_bits<B: BV>(buf: &mut dyn Write, bv: &B) -> std::io::Result<()> {
+    let len = bv.len() as usize;
+    let (digits, partial) = (len / 4, len % 4);
+    let hex = format!("{:x}", bv);
+    if partial == 0 {
+        write!(buf, "0x{}{}", "0".repeat(digits.saturating_sub(hex.len())), hex)
+    } else {
+        write!(buf, "0x{}{}:{}", "0".repeat((digits + 1).saturating_sub(hex.len())), hex, len)
+    }
+}
+
 impl<B: BV> Val<B> {
     pub fn collect_symbolic_variables(&self, vars: &mut HashSet<Sym>) {
         use Val::*;
@@ -270,7 +349,7 @@ impl<B: BV> Val<B> {
                     }
                 }
             }
-            I64(_) | I128(_) | Bool(_) | Bits(_) | Enum(_) | String(_) | Unit | Ref(_) | Poison => (),
+            I64(_) | I128(_) | BigInt(_) | Bool(_) | Bits(_) | Enum(_) | String(_) | Unit | Ref(_) | Poison => (),
             Vector(vals) | List(vals) => vals.iter().for_each(|val| val.collect_symbolic_variables(vars)),
             Struct(vals) => vals.iter().for_each(|(_, val)| val.collect_symbolic_variables(vars)),
             Ctor(_, val) => val.collect_symbolic_variables(vars),
@@ -278,6 +357,14 @@ impl<B: BV> Val<B> {
         }
     }
 
+    /// An integer value, which is an `I128` if it fits
+    pub fn int(n: BigInt) -> Self {
+        match i128::try_from(&n) {
+            Ok(n) => Val::I128(n),
+            Err(_) => Val::BigInt(n),
+        }
+    }
+
     pub fn widen_int(self) -> Self {
         match self {
             Val::I64(i) => Val::I128(i as i128),
@@ -309,6 +396,7 @@ impl<B: BV> Val<B> {
             Symbolic(v) => write!(buf, "v{}", v),
             I64(n) => write!(buf, "(_ bv{} 64)", n),
             I128(n) => write!(buf, "(_ bv{} 128)", n),
+            BigInt(n) => write!(buf, "(_ int {})", n),
             Bool(b) => write!(buf, "{}", b),
             Bits(bv) => write!(buf, "{}", bv),
             MixedBits(bs) => {
@@ -405,6 +493,101 @@ impl<B: BV> Val<B> {
         String::from_utf8(buf).unwrap()
     }
 
+    /// Write a value for people to read rather than in SMT-like
+    /// syntax. Enumeration members and struct fields are printed by
+    /// name, with the fields of a struct always in the same order
+    /// rather than in hash order, integers are printed in
+    /// decimal, and bitvectors are printed in hexadecimal padded to
+    /// their length, followed by the length if it is not a multiple of
+    /// four.
+    pub fn write_pretty(&self, buf: &mut dyn Write, shared_state: &SharedState<B>) -> std::io::Result<()> {
+        use Val::*;
+        let symtab = &shared_state.symtab;
+        let write_seq = |buf: &mut dyn Write, vals: &[Val<B>]| -> std::io::Result<()> {
+            write!(buf, "[")?;
+            for (i, val) in vals.iter().enumerate() {
+                if i > 0 {
+                    write!(buf, ", ")?
+                }
+                val.write_pretty(buf, shared_state)?
+            }
+            write!(buf, "]")
+        };
+        match self {
+            Symbolic(v) => write!(buf, "v{}", v),
+            I64(n) => write!(buf, "{}", n),
+            I128(n) => write!(buf, "{}", n),
+            BigInt(n) => write!(buf, "{}", n),
+            Bool(b) => write!(buf, "{}", b),
+            Bits(bv) => write_pretty_bits(buf, bv),
+            MixedBits(bs) => {
+                write!(buf, "(")?;
+                for (i, segment) in bs.iter().enumerate() {
+                    if i > 0 {
+                        write!(buf, " @ ")?
+                    }
+                    match segment {
+                        BitsSegment::Symbolic(v) => write!(buf, "v{}", v)?,
+                        BitsSegment::Concrete(bv) => write_pretty_bits(buf, bv)?,
+                    }
+                }
+                write!(buf, ")")
+            }
+            String(s) => write!(buf, "{:?}", s),
+            Enum(EnumMember { enum_id, member }) => {
+                let members = shared_state.type_info.enums.get(&enum_id.to_name()).expect("Failed to get enumeration");
+                write!(buf, "{}", zencode::decode(symtab.to_str(members[*member])))
+            }
+            Unit => write!(buf, "()"),
+            List(vals) | Vector(vals) => write_seq(buf, vals),
+            Struct(fields) => {
+                // Find the struct with exactly these fields to print
+                // its name
+                let declared = shared_state.type_info.structs.iter().find(|(_, declared_fields)| {
+                    declared_fields.len() == fields.len() && declared_fields.keys().all(|f| fields.contains_key(f))
+                });
+                if let Some((name, _)) = declared {
+                    write!(buf, "{} ", zencode::decode(symtab.to_str_demangled(*name)))?
+                }
+                let mut names: Vec<Name> = fields.keys().copied().collect();
+                names.sort();
+                write!(buf, "{{")?;
+                for (i, name) in names.iter().enumerate() {
+                    write!(buf, "{}{} = ", if i > 0 { ", " } else { " " }, zencode::decode(symtab.to_str(*name)))?;
+                    fields[name].write_pretty(buf, shared_state)?
+                }
+                write!(buf, " }}")
+            }
+            Ctor(ctor, v) => {
+                write!(buf, "{}(", zencode::decode(symtab.to_str_demangled(*ctor)))?;
+                v.write_pretty(buf, shared_state)?;
+                write!(buf, ")")
+            }
+            SymbolicCtor(v, possibilities) => {
+                write!(buf, "v{} in {{", v)?;
+                for (i, (ctor, v)) in possibilities.iter().enumerate() {
+                    write!(
+                        buf,
+                        "{}{}(",
+                        if i > 0 { ", " } else { " " },
+                        zencode::decode(symtab.to_str_demangled(*ctor))
+                    )?;
+                    v.write_pretty(buf, shared_state)?;
+                    write!(buf, ")")?
+                }
+                write!(buf, " }}")
+            }
+            Ref(reg) => write!(buf, "ref {}", zencode::decode(symtab.to_str(*reg))),
+            Poison => write!(buf, "poison"),
+        }
+    }
+
+    pub fn to_pretty_string(&self, shared_state: &SharedState<B>) -> String {
+        let mut buf = Vec::new();
+        self.write_pretty(&mut buf, shared_state).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
     /// Just enough of a type check to pick up bad default registers
     pub fn plausible<N: std::fmt::Debug>(&self, ty: &Ty<N>, shared_state: &SharedState<B>) -> Result<(), String> {
         match (self, ty) {
@@ -494,27 +677,55 @@ pub enum Exp<A> {
     Unwrap(A, Box<Exp<A>>),
     Field(Box<Exp<A>>, A),
     Call(Op, Vec<Exp<A>>),
+    /// An integer literal which does not fit in an `I128`
+    BigInt(BigInt),
+}
+
+impl<A> Exp<A> {
+    /// An integer literal of type `%i<size>` (with `%i` being
+    /// `%i128`), or `None` if the size is unsupported or the literal
+    /// does not fit in it. As `%i` is unbounded, literals of that
+    /// type which do not fit in 128 bits are `BigInt`s.
+    pub fn int_literal(n: &str, size: &str) -> Option<Self> {
+        match size {
+            "64" => n.parse().ok().map(Exp::I64),
+            "128" => n.parse().ok().map(Exp::I128).or_else(|| n.parse().ok().map(Exp::BigInt)),
+            _ => None,
+        }
+    }
 }
 
 impl<A: Hash + Eq + Clone> Exp<A> {
-    fn collect_ids(&self, ids: &mut HashSet<A>) {
+    pub(crate) fn collect_ids(&self, ids: &mut HashSet<A>) {
         use Exp::*;
         match self {
             Id(id) => {
                 ids.insert(id.clone());
             }
-            Ref(_) | Bool(_) | Bits(_) | String(_) | Unit | I64(_) | I128(_) | Undefined(_) => (),
+            Ref(_) | Bool(_) | Bits(_) | String(_) | Unit | I64(_) | I128(_) | BigInt(_) | Undefined(_) => (),
             Kind(_, exp) | Unwrap(_, exp) | Field(exp, _) => exp.collect_ids(ids),
             Call(_, exps) => exps.iter().for_each(|exp| exp.collect_ids(ids)),
             Struct(_, fields) => fields.iter().for_each(|(_, exp)| exp.collect_ids(ids)),
         }
     }
 
+    /// True if the expression contains an `undefined` value
+    pub fn contains_undefined(&self) -> bool {
+        use Exp::*;
+        match self {
+            Undefined(_) => true,
+            Id(_) | Ref(_) | Bool(_) | Bits(_) | String(_) | Unit | I64(_) | I128(_) | BigInt(_) => false,
+            Kind(_, exp) | Unwrap(_, exp) | Field(exp, _) => exp.contains_undefined(),
+            Call(_, exps) => exps.iter().any(Exp::contains_undefined),
+            Struct(_, fields) => fields.iter().any(|(_, exp)| exp.contains_undefined()),
+        }
+    }
+
     pub(crate) fn collect_variables<'a, 'b>(&'a mut self, vars: &'b mut Vec<Variable<'a, A>>) {
         use Exp::*;
         match self {
             Id(id) => vars.push(Variable::Usage(id)),
-            Ref(_) | Bool(_) | Bits(_) | String(_) | Unit | I64(_) | I128(_) | Undefined(_) => (),
+            Ref(_) | Bool(_) | Bits(_) | String(_) | Unit | I64(_) | I128(_) | BigInt(_) | Undefined(_) => (),
             Kind(_, exp) | Unwrap(_, exp) | Field(exp, _) => exp.collect_variables(vars),
             Call(_, exps) => exps.iter_mut().for_each(|exp| exp.collect_variables(vars)),
             Struct(_, fields) => fields.iter_mut().for_each(|(_, exp)| exp.collect_variables(vars)),
@@ -595,6 +806,27 @@ pub enum Instr<A, B> {
     End,
 }
 
+impl<A, B> Instr<A, B> {
+    /// The source location of an instruction, if it has one
+    pub fn source_loc(&self) -> Option<SourceLoc> {
+        use Instr::*;
+        match self {
+            Decl(_, _, info)
+            | Init(_, _, _, info)
+            | Jump(_, _, info)
+            | Copy(_, _, info)
+            | Monomorphize(_, info)
+            | Call(_, _, _, _, info)
+            | PrimopUnary(_, _, _, info)
+            | PrimopBinary(_, _, _, _, info)
+            | PrimopVariadic(_, _, _, info)
+            | PrimopReset(_, _, info)
+            | Exit(_, info) => Some(*info).filter(|info| !info.is_unknown()),
+            Goto(_) | Arbitrary | End => None,
+        }
+    }
+}
+
 impl<A: fmt::Debug, B: fmt::Debug> fmt::Debug for Instr<A, B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Instr::*;
@@ -970,6 +1202,9 @@ pub struct SharedState<'ir, B> {
     pub registers: HashMap<Name, Ty<Name>>,
     /// `probes` is a set of function/location identifers to print debug information for when called
     pub probes: HashSet<Name>,
+    /// `register_probes` is a set of registers for which every write
+    /// is printed as debug information, with its old and new values
+    pub register_probes: HashSet<Name>,
     /// `trace_functions` defines a set of functions which we include
     /// in the traces as function call and return events
     pub trace_functions: HashSet<Name>,
@@ -983,6 +1218,23 @@ pub struct SharedState<'ir, B> {
     /// given arguments has the given result, skipping execution
     /// derived from the ISA config
     pub function_assumptions: Vec<(String, Vec<Option<smtlib::Exp<Loc<String>>>>, smtlib::Exp<Loc<String>>)>,
+    /// Abstract functions that operate on a block of memory, which
+    /// are recorded as block operations. Empty unless the ISA config
+    /// asks for block operations to be modelled precisely.
+    pub block_functions: HashMap<Name, BlockFunction>,
+    /// `pure_functions` have results which depend only on their
+    /// arguments, so the executor caches them
+    pub pure_functions: HashSet<Name>,
+    /// `function_stubs` replace calls to functions with a summary,
+    /// derived from the ISA config
+    pub function_stubs: HashMap<Name, FunctionStub<B>>,
+    /// `undefined_sites` are the instructions in each function which
+    /// evaluate an under-specified construct (see [undefined_sites])
+    pub undefined_sites: HashMap<Name, HashMap<usize, UndefinedKind>>,
+    /// `constant_registers` have had their reads replaced by their
+    /// default value when the IR was optimized (see
+    /// [crate::init::constant_registers]), so they cannot be reset
+    pub constant_registers: HashSet<Name>,
 }
 
 #[derive(Copy, Clone)]
@@ -1085,10 +1337,16 @@ impl<'ir, B: BV> SharedState<'ir, B> {
             type_info,
             registers,
             probes,
+            register_probes: HashSet::new(),
             trace_functions,
             reset_registers,
             reset_constraints,
             function_assumptions,
+            block_functions: HashMap::new(),
+            pure_functions: HashSet::new(),
+            function_stubs: HashMap::new(),
+            undefined_sites: HashMap::new(),
+            constant_registers: HashSet::new(),
         }
     }
 
@@ -1111,7 +1369,7 @@ impl<'ir, B: BV> SharedState<'ir, B> {
     }
 }
 
-fn insert_instr_primops<B: BV>(
+pub(crate) fn insert_instr_primops<B: BV>(
     instr: Instr<Name, B>,
     externs: &HashMap<Name, (String, bool)>,
     primops: &Primops<B>,
@@ -1173,6 +1431,69 @@ pub enum AssertionMode {
 }
 
 /// Change Calls without implementations into Primops
+/// The ways in which the IR leaves a value under-specified
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum UndefinedKind {
+    /// An `undefined` expression
+    Undefined,
+    /// A call to one of the `undefined_*` builtins, such as `undefined_bitvector`
+    Builtin,
+    /// A return via `arbitrary`
+    Arbitrary,
+}
+
+impl fmt::Display for UndefinedKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UndefinedKind::Undefined => write!(f, "undefined"),
+            UndefinedKind::Builtin => write!(f, "builtin"),
+            UndefinedKind::Arbitrary => write!(f, "arbitrary"),
+        }
+    }
+}
+
+/// Find the instructions in each function which evaluate an
+/// `undefined` expression, call an `undefined_*` builtin, or return
+/// via `arbitrary`. This must be done before [insert_primops], which
+/// replaces the calls to builtins but preserves instruction indices.
+pub(crate) fn undefined_sites<B: BV>(defs: &[Def<Name, B>]) -> HashMap<Name, HashMap<usize, UndefinedKind>> {
+    let mut builtins: HashSet<Name> = HashSet::new();
+    for def in defs.iter() {
+        if let Def::Extern(f, _, ext, _, _) = def {
+            if ext.starts_with("undefined_") {
+                builtins.insert(*f);
+            }
+        }
+    }
+
+    let mut sites = HashMap::new();
+    for def in defs.iter() {
+        if let Def::Fn(f, _, body) = def {
+            let mut function_sites = HashMap::new();
+            for (i, instr) in body.iter().enumerate() {
+                let kind = match instr {
+                    Instr::Arbitrary => Some(UndefinedKind::Arbitrary),
+                    Instr::Call(_, _, g, _, _) if builtins.contains(g) => Some(UndefinedKind::Builtin),
+                    Instr::Init(_, _, exp, _) | Instr::Jump(exp, _, _) | Instr::Copy(_, exp, _) => {
+                        Some(UndefinedKind::Undefined).filter(|_| exp.contains_undefined())
+                    }
+                    Instr::Call(_, _, _, args, _) => {
+                        Some(UndefinedKind::Undefined).filter(|_| args.iter().any(Exp::contains_undefined))
+                    }
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    function_sites.insert(i, kind);
+                }
+            }
+            if !function_sites.is_empty() {
+                sites.insert(*f, function_sites);
+            }
+        }
+    }
+    sites
+}
+
 pub(crate) fn insert_primops<B: BV>(defs: &mut [Def<Name, B>], mode: AssertionMode, isa_config: &ISAConfig<B>) {
     let mut externs: HashMap<Name, (String, bool)> = HashMap::new();
     for def in defs.iter() {
@@ -1471,6 +1792,30 @@ fn has_call<B: BV>(instrs: &[Instr<Name, B>], target_function: Name) -> bool {
     false
 }
 
+/// The name, arguments with their types, and return type of a function.
+pub type FunctionSignature<'a> = (Name, Vec<(Name, &'a Ty<Name>)>, &'a Ty<Name>);
+
+/// The signature of each function with a body in the IR, in the order
+/// they are defined.
+pub fn function_signatures<B: BV>(defs: &[Def<Name, B>]) -> Vec<FunctionSignature<'_>> {
+    let vals: HashMap<Name, (&Vec<Ty<Name>>, &Ty<Name>)> = defs
+        .iter()
+        .filter_map(|def| match def {
+            Def::Val(f, arg_tys, ret_ty) => Some((*f, (arg_tys, ret_ty))),
+            _ => None,
+        })
+        .collect();
+    defs.iter()
+        .filter_map(|def| match def {
+            Def::Fn(f, args, _) => {
+                let (arg_tys, ret_ty) = vals.get(f)?;
+                Some((*f, args.iter().copied().zip(arg_tys.iter()).collect(), *ret_ty))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn function_return_type<B: BV>(defs: &[Def<Name, B>], target_function: Name) -> Option<&Ty<Name>> {
     for def in defs {
         match def {
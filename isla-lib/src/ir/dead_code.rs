@@ -0,0 +1,407 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! This module implements a static dead code analysis over the IR of
+//! a function. It finds instructions that cannot be reached from the
+//! start of the function, and assignments to local variables whose
+//! values are never read, using a reaching definitions analysis.
+//!
+//! Jumps whose conditions only depend on top-level let bindings with
+//! concrete boolean values are resolved, so branches which are
+//! impossible given the let bindings in the configuration are
+//! reported as unreachable.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::*;
+
+/// The dead code found in a single function.
+#[derive(Clone, Debug, Default)]
+pub struct DeadCode {
+    /// Maximal runs of unreachable instructions, as half-open ranges
+    /// of instruction indices. Runs made up only of `goto`, `end`,
+    /// and `arbitrary` instructions are left out, as the Sail
+    /// compiler routinely generates these after exits.
+    pub unreachable: Vec<Range<usize>>,
+    /// Reachable `Init` and `Copy` instructions which assign a value to
+    /// a local variable that is never read afterwards, along with the
+    /// variable
+    pub dead_assignments: Vec<(usize, Name)>,
+}
+
+impl DeadCode {
+    pub fn is_empty(&self) -> bool {
+        self.unreachable.is_empty() && self.dead_assignments.is_empty()
+    }
+}
+
+/// The top-level let bindings with a concrete boolean value, which
+/// can be used to resolve jumps in [dead_code].
+pub fn boolean_lets<B: BV>(lets: &Bindings<B>) -> HashMap<Name, bool> {
+    lets.iter()
+        .filter_map(|(id, value)| match value {
+            UVal::Init(Val::Bool(b)) => Some((*id, *b)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The value of a jump condition, if it is determined by `constants`.
+fn eval_condition(exp: &Exp<Name>, constants: &HashMap<Name, bool>, locals: &HashSet<Name>) -> Option<bool> {
+    match exp {
+        Exp::Bool(b) => Some(*b),
+        Exp::Id(id) if !locals.contains(id) => constants.get(id).copied(),
+        Exp::Call(Op::Not, args) if args.len() == 1 => eval_condition(&args[0], constants, locals).map(|b| !b),
+        Exp::Call(Op::And, args) if args.len() == 2 => {
+            match (eval_condition(&args[0], constants, locals), eval_condition(&args[1], constants, locals)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            }
+        }
+        Exp::Call(Op::Or, args) if args.len() == 2 => {
+            match (eval_condition(&args[0], constants, locals), eval_condition(&args[1], constants, locals)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn successors<B>(
+    i: usize,
+    instrs: &[Instr<Name, B>],
+    constants: &HashMap<Name, bool>,
+    locals: &HashSet<Name>,
+) -> Vec<usize> {
+    let succs = match &instrs[i] {
+        Instr::Goto(target) => vec![*target],
+        Instr::Jump(exp, target, _) => match eval_condition(exp, constants, locals) {
+            Some(true) => vec![*target],
+            Some(false) => vec![i + 1],
+            None => vec![*target, i + 1],
+        },
+        Instr::Exit(..) | Instr::Arbitrary | Instr::End => vec![],
+        _ => vec![i + 1],
+    };
+    succs.into_iter().filter(|succ| *succ < instrs.len()).collect()
+}
+
+/// The local variable completely overwritten by an instruction, if any.
+fn defined<B>(instr: &Instr<Name, B>) -> Option<Name> {
+    match instr {
+        Instr::Decl(id, _, _) | Instr::Init(id, _, _, _) => Some(*id),
+        Instr::Copy(Loc::Id(id), _, _)
+        | Instr::Call(Loc::Id(id), _, _, _, _)
+        | Instr::PrimopUnary(Loc::Id(id), _, _, _)
+        | Instr::PrimopBinary(Loc::Id(id), _, _, _, _)
+        | Instr::PrimopVariadic(Loc::Id(id), _, _, _)
+        | Instr::PrimopReset(Loc::Id(id), _, _) => Some(*id),
+        _ => None,
+    }
+}
+
+/// Whether the assignment made by an instruction should be reported
+/// when it is dead. Declarations have no value to be dead, and calls
+/// and primitives (which include external functions such as memory
+/// accesses) may be needed for their side effects.
+fn reportable<B>(instr: &Instr<Name, B>) -> bool {
+    matches!(instr, Instr::Init(..) | Instr::Copy(..))
+}
+
+/// Variables read by a location being assigned to. Updating a field
+/// of a variable reads the rest of it.
+fn loc_uses(loc: &Loc<Name>, uses: &mut HashSet<Name>) {
+    match loc {
+        Loc::Id(_) => (),
+        Loc::Field(loc, _) | Loc::Addr(loc) => {
+            uses.insert(loc.id());
+        }
+    }
+}
+
+fn used<B>(instr: &Instr<Name, B>) -> HashSet<Name> {
+    let mut uses = HashSet::new();
+    match instr {
+        Instr::Init(_, _, exp, _) | Instr::Jump(exp, _, _) => exp.collect_ids(&mut uses),
+        Instr::Copy(loc, exp, _) | Instr::PrimopUnary(loc, _, exp, _) => {
+            loc_uses(loc, &mut uses);
+            exp.collect_ids(&mut uses)
+        }
+        Instr::PrimopBinary(loc, _, exp1, exp2, _) => {
+            loc_uses(loc, &mut uses);
+            exp1.collect_ids(&mut uses);
+            exp2.collect_ids(&mut uses)
+        }
+        Instr::PrimopVariadic(loc, _, args, _) | Instr::Call(loc, _, _, args, _) => {
+            loc_uses(loc, &mut uses);
+            args.iter().for_each(|exp| exp.collect_ids(&mut uses))
+        }
+        Instr::PrimopReset(loc, _, _) => loc_uses(loc, &mut uses),
+        Instr::Monomorphize(id, _) => {
+            uses.insert(*id);
+        }
+        Instr::End => {
+            uses.insert(RETURN);
+        }
+        Instr::Decl(..) | Instr::Goto(_) | Instr::Exit(..) | Instr::Arbitrary => (),
+    }
+    uses
+}
+
+/// Variables which have a reference taken, and so could be read
+/// through it without appearing in [used].
+fn referenced<B>(instr: &Instr<Name, B>, refs: &mut HashSet<Name>) {
+    fn exp_refs(exp: &Exp<Name>, refs: &mut HashSet<Name>) {
+        match exp {
+            Exp::Ref(id) => {
+                refs.insert(*id);
+            }
+            Exp::Kind(_, exp) | Exp::Unwrap(_, exp) | Exp::Field(exp, _) => exp_refs(exp, refs),
+            Exp::Call(_, exps) => exps.iter().for_each(|exp| exp_refs(exp, refs)),
+            Exp::Struct(_, fields) => fields.iter().for_each(|(_, exp)| exp_refs(exp, refs)),
+            _ => (),
+        }
+    }
+
+    match instr {
+        Instr::Init(_, _, exp, _)
+        | Instr::Jump(exp, _, _)
+        | Instr::Copy(_, exp, _)
+        | Instr::PrimopUnary(_, _, exp, _) => exp_refs(exp, refs),
+        Instr::PrimopBinary(_, _, exp1, exp2, _) => {
+            exp_refs(exp1, refs);
+            exp_refs(exp2, refs)
+        }
+        Instr::PrimopVariadic(_, _, args, _) | Instr::Call(_, _, _, args, _) => {
+            args.iter().for_each(|exp| exp_refs(exp, refs))
+        }
+        _ => (),
+    }
+}
+
+/// A set of definitions, indexed by their position in the list of
+/// definitions in a function.
+#[derive(Clone, PartialEq, Eq)]
+struct DefSet {
+    words: Vec<u64>,
+}
+
+impl DefSet {
+    fn new(size: usize) -> Self {
+        DefSet { words: vec![0; size / 64 + 1] }
+    }
+
+    fn insert(&mut self, def: usize) {
+        self.words[def / 64] |= 1 << (def % 64)
+    }
+
+    fn remove(&mut self, def: usize) {
+        self.words[def / 64] &= !(1 << (def % 64))
+    }
+
+    fn contains(&self, def: usize) -> bool {
+        self.words[def / 64] & (1 << (def % 64)) != 0
+    }
+
+    fn union_with(&mut self, other: &DefSet) {
+        self.words.iter_mut().zip(other.words.iter()).for_each(|(w, o)| *w |= o)
+    }
+}
+
+/// Find the dead code in a function. Jumps with conditions decided
+/// by `constants` (see [boolean_lets]) only have one successor.
+pub fn dead_code<B: BV>(instrs: &[Instr<Name, B>], constants: &HashMap<Name, bool>) -> DeadCode {
+    let mut result = DeadCode::default();
+    if instrs.is_empty() {
+        return result;
+    }
+
+    let mut locals = HashSet::new();
+    let mut refs = HashSet::new();
+    locals.insert(RETURN);
+    for instr in instrs {
+        if let Instr::Decl(id, _, _) | Instr::Init(id, _, _, _) = instr {
+            locals.insert(*id);
+        }
+        referenced(instr, &mut refs)
+    }
+
+    let succs: Vec<Vec<usize>> = (0..instrs.len()).map(|i| successors(i, instrs, constants, &locals)).collect();
+
+    let mut reachable = vec![false; instrs.len()];
+    let mut stack = vec![0];
+    reachable[0] = true;
+    while let Some(i) = stack.pop() {
+        for succ in &succs[i] {
+            if !reachable[*succ] {
+                reachable[*succ] = true;
+                stack.push(*succ)
+            }
+        }
+    }
+
+    let mut i = 0;
+    while i < instrs.len() {
+        if reachable[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < instrs.len() && !reachable[i] {
+            i += 1
+        }
+        if instrs[start..i].iter().any(|instr| !matches!(instr, Instr::Goto(_) | Instr::End | Instr::Arbitrary)) {
+            result.unreachable.push(start..i)
+        }
+    }
+
+    // Number the definitions of local variables in reachable code
+    let mut defs: Vec<(usize, Name)> = Vec::new();
+    let mut def_index: HashMap<usize, usize> = HashMap::new();
+    let mut defs_of: HashMap<Name, Vec<usize>> = HashMap::new();
+    for (i, instr) in instrs.iter().enumerate() {
+        if let Some(id) = defined(instr).filter(|id| reachable[i] && locals.contains(id)) {
+            def_index.insert(i, defs.len());
+            defs_of.entry(id).or_default().push(defs.len());
+            defs.push((i, id))
+        }
+    }
+
+    // Split the reachable instructions into basic blocks
+    let mut leaders: Vec<usize> = vec![0];
+    for (i, instr) in instrs.iter().enumerate() {
+        if !reachable[i] {
+            continue;
+        }
+        if let Instr::Goto(_) | Instr::Jump(..) | Instr::Exit(..) | Instr::Arbitrary | Instr::End = instr {
+            leaders.extend(succs[i].iter().copied());
+            leaders.push(i + 1)
+        }
+    }
+    leaders.retain(|i| *i < instrs.len() && reachable[*i]);
+    leaders.sort_unstable();
+    leaders.dedup();
+
+    let block_of: HashMap<usize, usize> = leaders.iter().enumerate().map(|(b, i)| (*i, b)).collect();
+    let blocks: Vec<Range<usize>> = leaders
+        .iter()
+        .enumerate()
+        .map(|(b, start)| {
+            let end = leaders.get(b + 1).copied().unwrap_or(instrs.len());
+            let end = (*start..end).find(|i| !reachable[*i]).unwrap_or(end);
+            *start..end
+        })
+        .collect();
+    let block_succs: Vec<Vec<usize>> =
+        blocks.iter().map(|block| succs[block.end - 1].iter().map(|succ| block_of[succ]).collect()).collect();
+
+    // Apply the effect of an instruction on the definitions reaching it
+    let transfer = |i: usize, set: &mut DefSet| {
+        if let Some(def) = def_index.get(&i) {
+            defs_of[&defs[*def].1].iter().for_each(|other| set.remove(*other));
+            set.insert(*def)
+        }
+    };
+
+    let mut block_in: Vec<DefSet> = vec![DefSet::new(defs.len()); blocks.len()];
+    let mut worklist: Vec<usize> = (0..blocks.len()).rev().collect();
+    let mut queued = vec![true; blocks.len()];
+    while let Some(b) = worklist.pop() {
+        queued[b] = false;
+        let mut set = block_in[b].clone();
+        blocks[b].clone().for_each(|i| transfer(i, &mut set));
+        for succ in &block_succs[b] {
+            let mut updated = block_in[*succ].clone();
+            updated.union_with(&set);
+            if updated != block_in[*succ] {
+                block_in[*succ] = updated;
+                if !queued[*succ] {
+                    queued[*succ] = true;
+                    worklist.push(*succ)
+                }
+            }
+        }
+    }
+
+    // A definition is live if it reaches a use of its variable
+    let mut live = DefSet::new(defs.len());
+    for (b, block) in blocks.iter().enumerate() {
+        let mut set = block_in[b].clone();
+        for i in block.clone() {
+            for id in used(&instrs[i]) {
+                defs_of
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .filter(|def| set.contains(**def))
+                    .for_each(|def| live.insert(*def))
+            }
+            transfer(i, &mut set)
+        }
+    }
+
+    for (def, (i, id)) in defs.iter().enumerate() {
+        if !live.contains(def) && !refs.contains(id) && reportable(&instrs[*i]) {
+            result.dead_assignments.push((*i, *id))
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::ir_parse::parse_ir;
+
+    #[test]
+    fn unreachable_and_dead() {
+        let ir = "val zf : (%bool) -> %bool\nfn zf(zx) {\n  zy : %bool = zx;\n  zz : %bool = false;\n  zy = true;\n  jump zflag goto 6;\n  zz = zy;\n  goto 6;\n  return = zz;\n  end\n}\n";
+        let mut symtab = Symtab::new();
+        let defs = parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let Some(Def::Fn(_, _, instrs)) = defs.iter().find(|def| matches!(def, Def::Fn(..))) else {
+            panic!("no function in IR")
+        };
+        let y = symtab.lookup("zy");
+
+        let dead = dead_code(instrs, &HashMap::new());
+        assert!(dead.unreachable.is_empty());
+        assert_eq!(dead.dead_assignments, [(0, y)]);
+
+        let constants = HashMap::from([(symtab.lookup("zflag"), true)]);
+        let dead = dead_code(instrs, &constants);
+        assert_eq!(dead.unreachable, vec![Range { start: 4, end: 6 }]);
+        assert_eq!(dead.dead_assignments, [(0, y), (2, y)])
+    }
+}
@@ -195,6 +195,7 @@ pub(crate) fn unssa_exp(exp: &Exp<SSAName>, symtab: &mut Symtab, names: &mut Has
         Unit => Unit,
         I64(n) => I64(*n),
         I128(n) => I128(*n),
+        BigInt(n) => BigInt(n.clone()),
         Undefined(ty) => Undefined(unssa_ty(ty)),
         Struct(s, fields) => Struct(
             s.unssa_orig(symtab),
@@ -470,7 +471,7 @@ pub fn self_test<'ir, B: BV>(
         ]
     }));
 
-    let Initialized { regs, lets, shared_state } =
+    let Initialized { regs, lets, shared_state, .. } =
         initialize_architecture(&mut arch, symtab, type_info, isa_config, AssertionMode::Optimistic, false);
 
     let (args, _, instrs) = shared_state.functions.get(&comparison).unwrap();
@@ -0,0 +1,627 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! This module implements optimizations over the IR of each function,
+//! which are applied when an architecture is loaded (see
+//! [crate::init::initialize_architecture]). Operators applied to
+//! literals are folded, reads of local variables known to hold a
+//! literal are replaced by it (copy propagation), and reads of
+//! registers which keep a constant value from the configuration are
+//! replaced by that value.
+//!
+//! Instructions are only ever rewritten in place, so instruction
+//! indices are unchanged. In particular a jump whose condition
+//! becomes a literal is kept rather than being turned into a `goto`,
+//! so guard events and loop bounds behave as before, but the branch
+//! which cannot be taken is ignored when propagating variables.
+//!
+//! [specialize] goes further for an architecture which will only be
+//! run with particular register values, replacing code made
+//! unreachable by those values with `arbitrary`.
+
+use std::collections::{HashMap, HashSet};
+
+use super::dead_code::dead_code;
+use super::*;
+
+/// Counts of the changes made by [optimize].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OptimizeStats {
+    /// Operators applied to literals which were replaced by their result
+    pub folded: usize,
+    /// Reads of local variables replaced by a literal
+    pub propagated: usize,
+    /// Reads of constant registers replaced by their value
+    pub registers: usize,
+    /// Jumps whose condition became a literal
+    pub branches: usize,
+    /// Unreachable instructions removed by [specialize]
+    pub pruned: usize,
+}
+
+impl fmt::Display for OptimizeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "folded {} operations, propagated {} variables and {} registers, decided {} branches",
+            self.folded, self.propagated, self.registers, self.branches
+        )?;
+        if self.pruned > 0 {
+            write!(f, ", pruned {} instructions", self.pruned)?
+        }
+        Ok(())
+    }
+}
+
+/// The literal expression for a concrete value, if it has one.
+/// Bitvectors longer than 64 bits have no literal form.
+pub fn literal<B: BV>(value: &Val<B>) -> Option<Exp<Name>> {
+    match value {
+        Val::Bool(b) => Some(Exp::Bool(*b)),
+        Val::I64(n) => Some(Exp::I64(*n)),
+        Val::I128(n) => Some(Exp::I128(*n)),
+        Val::Unit => Some(Exp::Unit),
+        Val::Bits(bv) if bv.len() <= 64 => Some(Exp::Bits(B64::new(bv.lower_u64(), bv.len()))),
+        _ => None,
+    }
+}
+
+fn is_literal(exp: &Exp<Name>) -> bool {
+    matches!(exp, Exp::Bool(_) | Exp::Bits(_) | Exp::I64(_) | Exp::I128(_) | Exp::Unit)
+}
+
+fn same_literal(x: &Exp<Name>, y: &Exp<Name>) -> bool {
+    match (x, y) {
+        (Exp::Bool(x), Exp::Bool(y)) => x == y,
+        (Exp::Bits(x), Exp::Bits(y)) => x == y,
+        (Exp::I64(x), Exp::I64(y)) => x == y,
+        (Exp::I128(x), Exp::I128(y)) => x == y,
+        (Exp::Unit, Exp::Unit) => true,
+        _ => false,
+    }
+}
+
+fn same_values(x: &HashMap<Name, Exp<Name>>, y: &HashMap<Name, Exp<Name>>) -> bool {
+    x.len() == y.len() && x.iter().all(|(id, exp)| matches!(y.get(id), Some(other) if same_literal(exp, other)))
+}
+
+/// Apply an operator to literal arguments, giving the same result as
+/// evaluating it during execution. Cases which would fail, or which
+/// would involve the SMT solver even for concrete arguments, are left
+/// alone.
+fn fold_op(op: &Op, args: &[Exp<Name>]) -> Option<Exp<Name>> {
+    use Exp::*;
+    Some(match (op, args) {
+        (Op::Not, [Bool(x)]) => Bool(!x),
+        (Op::And, [Bool(x), Bool(y)]) => Bool(*x && *y),
+        (Op::Or, [Bool(x), Bool(y)]) => Bool(*x || *y),
+        (Op::Eq | Op::Neq, [x, y]) => {
+            let eq = match (x, y) {
+                (Bool(x), Bool(y)) => x == y,
+                (Bits(x), Bits(y)) if x.len() == y.len() => x == y,
+                (I64(x), I64(y)) => x == y,
+                (I128(x), I128(y)) => x == y,
+                _ => return None,
+            };
+            Bool(if matches!(op, Op::Eq) { eq } else { !eq })
+        }
+        (Op::Lt, [I64(x), I64(y)]) => Bool(x < y),
+        (Op::Lteq, [I64(x), I64(y)]) => Bool(x <= y),
+        (Op::Gt, [I64(x), I64(y)]) => Bool(x > y),
+        (Op::Gteq, [I64(x), I64(y)]) => Bool(x >= y),
+        (Op::Add, [I64(x), I64(y)]) => I64(x.wrapping_add(*y)),
+        (Op::Sub, [I64(x), I64(y)]) => I64(x.wrapping_sub(*y)),
+        (Op::Bvnot, [Bits(x)]) => Bits(!*x),
+        (Op::Bvand | Op::Bvor | Op::Bvxor | Op::Bvadd | Op::Bvsub, [Bits(x), Bits(y)]) if x.len() == y.len() => {
+            Bits(match op {
+                Op::Bvand => *x & *y,
+                Op::Bvor => *x | *y,
+                Op::Bvxor => *x ^ *y,
+                Op::Bvadd => *x + *y,
+                _ => *x - *y,
+            })
+        }
+        (Op::Concat, [Bits(x), Bits(y)]) => Bits(x.append(*y)?),
+        (Op::Slice(len), [Bits(x), I64(from)]) if *from >= 0 => Bits(x.slice(*from as u32, *len)?),
+        (Op::ZeroExtend(len), [Bits(x)]) if *len <= 64 && *len >= x.len() => Bits(x.zero_extend(*len)),
+        _ => return None,
+    })
+}
+
+/// What is known about the names in a function while folding its
+/// expressions.
+struct Env<'a, B> {
+    /// The arguments and local variables of the function, which
+    /// shadow any registers with the same name
+    locals: &'a HashSet<Name>,
+    registers: &'a HashMap<Name, Val<B>>,
+}
+
+fn fold_exp<B: BV>(exp: &mut Exp<Name>, env: &Env<B>, known: &HashMap<Name, Exp<Name>>, stats: &mut OptimizeStats) {
+    match exp {
+        Exp::Id(id) => {
+            if let Some(value) = known.get(id) {
+                *exp = value.clone();
+                stats.propagated += 1
+            } else if let Some(value) = env.registers.get(id).filter(|_| !env.locals.contains(id)).and_then(literal) {
+                *exp = value;
+                stats.registers += 1
+            }
+        }
+        Exp::Field(inner, field) => {
+            if let Exp::Id(id) = **inner {
+                if let Some(Val::Struct(members)) = env.registers.get(&id).filter(|_| !env.locals.contains(&id)) {
+                    if let Some(value) = members.get(field).and_then(literal) {
+                        *exp = value;
+                        stats.registers += 1;
+                        return;
+                    }
+                }
+            }
+            fold_exp(inner, env, known, stats)
+        }
+        Exp::Call(op, args) => {
+            args.iter_mut().for_each(|arg| fold_exp(arg, env, known, stats));
+            if let Some(value) = fold_op(op, args) {
+                *exp = value;
+                stats.folded += 1
+            }
+        }
+        Exp::Kind(_, inner) | Exp::Unwrap(_, inner) => fold_exp(inner, env, known, stats),
+        Exp::Struct(_, fields) => fields.iter_mut().for_each(|(_, exp)| fold_exp(exp, env, known, stats)),
+        Exp::Ref(_)
+        | Exp::Bool(_)
+        | Exp::Bits(_)
+        | Exp::String(_)
+        | Exp::Unit
+        | Exp::I64(_)
+        | Exp::I128(_)
+        | Exp::BigInt(_)
+        | Exp::Undefined(_) => (),
+    }
+}
+
+/// The expressions an instruction evaluates, not including the
+/// locations it assigns to.
+fn read_exps<B>(instr: &Instr<Name, B>) -> Vec<&Exp<Name>> {
+    match instr {
+        Instr::Init(_, _, exp, _)
+        | Instr::Jump(exp, _, _)
+        | Instr::Copy(_, exp, _)
+        | Instr::PrimopUnary(_, _, exp, _) => {
+            vec![exp]
+        }
+        Instr::PrimopBinary(_, _, exp1, exp2, _) => vec![exp1, exp2],
+        Instr::PrimopVariadic(_, _, args, _) | Instr::Call(_, _, _, args, _) => args.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn read_exps_mut<B>(instr: &mut Instr<Name, B>) -> Vec<&mut Exp<Name>> {
+    match instr {
+        Instr::Init(_, _, exp, _)
+        | Instr::Jump(exp, _, _)
+        | Instr::Copy(_, exp, _)
+        | Instr::PrimopUnary(_, _, exp, _) => {
+            vec![exp]
+        }
+        Instr::PrimopBinary(_, _, exp1, exp2, _) => vec![exp1, exp2],
+        Instr::PrimopVariadic(_, _, args, _) | Instr::Call(_, _, _, args, _) => args.iter_mut().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The location an instruction assigns to, if any.
+fn assigned<B>(instr: &Instr<Name, B>) -> Option<&Loc<Name>> {
+    match instr {
+        Instr::Copy(loc, _, _)
+        | Instr::Call(loc, _, _, _, _)
+        | Instr::PrimopUnary(loc, _, _, _)
+        | Instr::PrimopBinary(loc, _, _, _, _)
+        | Instr::PrimopVariadic(loc, _, _, _)
+        | Instr::PrimopReset(loc, _, _) => Some(loc),
+        _ => None,
+    }
+}
+
+fn collect_refs(exp: &Exp<Name>, refs: &mut HashSet<Name>) {
+    match exp {
+        Exp::Ref(id) => {
+            refs.insert(*id);
+        }
+        Exp::Kind(_, exp) | Exp::Unwrap(_, exp) | Exp::Field(exp, _) => collect_refs(exp, refs),
+        Exp::Call(_, exps) => exps.iter().for_each(|exp| collect_refs(exp, refs)),
+        Exp::Struct(_, fields) => fields.iter().for_each(|(_, exp)| collect_refs(exp, refs)),
+        _ => (),
+    }
+}
+
+/// The names which are assigned to, or have a reference taken,
+/// anywhere in the architecture. A register which is not in this set
+/// can only change through the executor itself, e.g. via the
+/// `reset_registers` builtin.
+pub fn written_names<B: BV>(arch: &[Def<Name, B>]) -> HashSet<Name> {
+    let mut written = HashSet::new();
+    for def in arch {
+        let instrs = match def {
+            Def::Fn(_, _, instrs) | Def::Let(_, instrs) | Def::Register(_, _, instrs) => instrs,
+            _ => continue,
+        };
+        for instr in instrs {
+            if let Some(loc) = assigned(instr) {
+                written.insert(loc.id());
+            }
+            read_exps(instr).into_iter().for_each(|exp| collect_refs(exp, &mut written))
+        }
+    }
+    written
+}
+
+fn successors<B>(i: usize, instrs: &[Instr<Name, B>]) -> Vec<usize> {
+    let succs = match &instrs[i] {
+        Instr::Goto(target) | Instr::Jump(Exp::Bool(true), target, _) => vec![*target],
+        Instr::Jump(Exp::Bool(false), _, _) => vec![i + 1],
+        Instr::Jump(_, target, _) => vec![*target, i + 1],
+        Instr::Exit(..) | Instr::Arbitrary | Instr::End => vec![],
+        _ => vec![i + 1],
+    };
+    succs.into_iter().filter(|succ| *succ < instrs.len()).collect()
+}
+
+/// Update the literal values of the `tracked` local variables for the
+/// effect of an instruction.
+fn transfer<B: BV>(
+    instr: &Instr<Name, B>,
+    env: &Env<B>,
+    tracked: &HashSet<Name>,
+    known: &mut HashMap<Name, Exp<Name>>,
+) {
+    let (id, exp) = match instr {
+        Instr::Decl(id, _, _) => {
+            known.remove(id);
+            return;
+        }
+        Instr::Init(id, _, exp, _) | Instr::Copy(Loc::Id(id), exp, _) => (*id, exp),
+        _ => {
+            if let Some(loc) = assigned(instr) {
+                known.remove(&loc.id());
+            }
+            return;
+        }
+    };
+    if !tracked.contains(&id) {
+        return;
+    }
+    let mut exp = exp.clone();
+    fold_exp(&mut exp, env, known, &mut OptimizeStats::default());
+    if is_literal(&exp) {
+        known.insert(id, exp);
+    } else {
+        known.remove(&id);
+    }
+}
+
+/// The literal values of local variables before each instruction, or
+/// `None` for instructions which cannot be reached.
+fn known_values<B: BV>(
+    instrs: &[Instr<Name, B>],
+    env: &Env<B>,
+    tracked: &HashSet<Name>,
+) -> Vec<Option<HashMap<Name, Exp<Name>>>> {
+    let mut known_in: Vec<Option<HashMap<Name, Exp<Name>>>> = vec![None; instrs.len()];
+    known_in[0] = Some(HashMap::new());
+    let mut worklist = vec![0];
+    while let Some(i) = worklist.pop() {
+        let mut known = known_in[i].clone().unwrap();
+        transfer(&instrs[i], env, tracked, &mut known);
+        for succ in successors(i, instrs) {
+            let updated = match &known_in[succ] {
+                None => known.clone(),
+                Some(old) => old
+                    .iter()
+                    .filter(|(id, exp)| matches!(known.get(id), Some(other) if same_literal(exp, other)))
+                    .map(|(id, exp)| (*id, exp.clone()))
+                    .collect(),
+            };
+            if !matches!(&known_in[succ], Some(old) if same_values(old, &updated)) {
+                known_in[succ] = Some(updated);
+                worklist.push(succ)
+            }
+        }
+    }
+    known_in
+}
+
+/// Optimize the body of a single function with the given arguments.
+/// Reads of the registers in `registers` which are not shadowed by a
+/// local variable are replaced by their values.
+pub fn optimize_function<B: BV>(
+    args: &[Name],
+    instrs: &mut [Instr<Name, B>],
+    registers: &HashMap<Name, Val<B>>,
+) -> OptimizeStats {
+    let mut stats = OptimizeStats::default();
+    if instrs.is_empty() {
+        return stats;
+    }
+
+    let mut locals: HashSet<Name> = args.iter().copied().collect();
+    let mut refs = HashSet::new();
+    locals.insert(RETURN);
+    for instr in instrs.iter() {
+        if let Instr::Decl(id, _, _) | Instr::Init(id, _, _, _) = instr {
+            locals.insert(*id);
+        }
+        read_exps(instr).into_iter().for_each(|exp| collect_refs(exp, &mut refs))
+    }
+    let env = Env { locals: &locals, registers };
+
+    let undecided = |instrs: &[Instr<Name, B>]| {
+        instrs.iter().filter(|instr| matches!(instr, Instr::Jump(exp, _, _) if !is_literal(exp))).count()
+    };
+    let jumps = undecided(instrs);
+
+    let no_values = HashMap::new();
+    for instr in instrs.iter_mut() {
+        read_exps_mut(instr).into_iter().for_each(|exp| fold_exp(exp, &env, &no_values, &mut stats))
+    }
+
+    // A variable can only be known to hold a literal if some variable
+    // is assigned one directly
+    let assigns_literal = instrs
+        .iter()
+        .any(|instr| matches!(instr, Instr::Init(_, _, exp, _) | Instr::Copy(Loc::Id(_), exp, _) if is_literal(exp)));
+    // Variables which cannot be changed through a reference
+    let tracked: HashSet<Name> = locals.difference(&refs).copied().collect();
+
+    // Propagating values can decide more jumps, which in turn can
+    // give more variables a single value
+    if assigns_literal {
+        loop {
+            let before = stats;
+            let known_in = known_values(instrs, &env, &tracked);
+            for (instr, known) in instrs.iter_mut().zip(known_in.iter()) {
+                if let Some(known) = known {
+                    read_exps_mut(instr).into_iter().for_each(|exp| fold_exp(exp, &env, known, &mut stats))
+                }
+            }
+            if stats == before {
+                break;
+            }
+        }
+    }
+
+    stats.branches = jumps - undecided(instrs);
+    stats
+}
+
+/// Optimize every function in the architecture (see [optimize_function]).
+pub fn optimize<B: BV>(arch: &mut [Def<Name, B>], registers: &HashMap<Name, Val<B>>) -> OptimizeStats {
+    let mut stats = OptimizeStats::default();
+    for def in arch.iter_mut() {
+        if let Def::Fn(_, args, instrs) = def {
+            let fn_stats = optimize_function(args, instrs, registers);
+            stats.folded += fn_stats.folded;
+            stats.propagated += fn_stats.propagated;
+            stats.registers += fn_stats.registers;
+            stats.branches += fn_stats.branches
+        }
+    }
+    stats
+}
+
+/// Replace the instructions of a function which cannot be reached
+/// from its start, given the jumps whose conditions are literals,
+/// with `arbitrary` (as the Sail compiler does for the code following
+/// an exit). Returns the number of instructions replaced.
+pub fn prune_unreachable<B: BV>(instrs: &mut [Instr<Name, B>]) -> usize {
+    let mut pruned = 0;
+    for range in dead_code(instrs, &HashMap::new()).unreachable {
+        for instr in &mut instrs[range] {
+            if !matches!(instr, Instr::Arbitrary) {
+                *instr = Instr::Arbitrary;
+                pruned += 1
+            }
+        }
+    }
+    pruned
+}
+
+/// Add the value a register, or a field of a struct register, is
+/// fixed to into the `registers` passed to [specialize].
+pub fn fix_register<B: BV>(
+    arch: &[Def<Name, B>],
+    registers: &mut HashMap<Name, Val<B>>,
+    loc: &Loc<Name>,
+    value: Val<B>,
+) -> Result<(), String> {
+    let (reg, field) = match loc {
+        Loc::Id(reg) => (*reg, None),
+        Loc::Field(inner, field) => match **inner {
+            Loc::Id(reg) => (reg, Some(*field)),
+            _ => return Err("only a register or a field of a register can be fixed".to_string()),
+        },
+        Loc::Addr(_) => return Err("only a register or a field of a register can be fixed".to_string()),
+    };
+    if !arch.iter().any(|def| matches!(def, Def::Register(id, _, _) if *id == reg)) {
+        return Err("not a register in the architecture".to_string());
+    }
+    match field {
+        None => {
+            registers.insert(reg, value);
+        }
+        Some(field) => match registers.entry(reg).or_insert_with(|| Val::Struct(HashMap::default())) {
+            Val::Struct(fields) => {
+                fields.insert(field, value);
+            }
+            _ => return Err("a field was given for a register which is not a struct".to_string()),
+        },
+    }
+    Ok(())
+}
+
+/// Specialize an architecture to fixed values of the registers in
+/// `registers`, by optimizing it as [optimize] does and then pruning
+/// the branches which become unreachable. The registers need not be
+/// those found by [crate::init::constant_registers], as a register
+/// which the architecture can write may still be known to keep its
+/// value in a particular test environment.
+pub fn specialize<B: BV>(arch: &mut [Def<Name, B>], registers: &HashMap<Name, Val<B>>) -> OptimizeStats {
+    let mut stats = optimize(arch, registers);
+    for def in arch.iter_mut() {
+        if let Def::Fn(_, _, instrs) = def {
+            stats.pruned += prune_unreachable(instrs)
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::executor::{start_single, LocalFrame, TaskState};
+    use crate::ir_parse::parse_ir;
+
+    const BRANCHES: &str = "val zf : (%i64) -> %i64\nfn zf(zx) {\n  zy : %i64 = 2;\n  zz : %i64 = @iadd(zy, 3);\n  jump @eq(zz, 5) goto 5;\n  return = zx;\n  goto 9;\n  jump @lt(zx, zz) goto 8;\n  return = @isub(zx, zz);\n  goto 9;\n  return = @iadd(zx, zz);\n  end\n}\n";
+
+    fn function(defs: &[Def<Name, B64>]) -> (Name, &[Instr<Name, B64>]) {
+        let Some(Def::Fn(f, _, instrs)) = defs.iter().find(|def| matches!(def, Def::Fn(..))) else {
+            panic!("no function in IR")
+        };
+        (*f, instrs)
+    }
+
+    #[test]
+    fn fold_and_propagate() {
+        let mut symtab = Symtab::new();
+        let mut defs = parse_ir::<B64>(BRANCHES, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let x = symtab.lookup("zx");
+
+        let stats = optimize(&mut defs, &HashMap::new());
+        assert_eq!(stats.branches, 1);
+        assert!(stats.folded >= 2 && stats.propagated >= 3);
+
+        let (_, instrs) = function(&defs);
+        assert!(matches!(&instrs[1], Instr::Init(_, _, Exp::I64(5), _)));
+        assert!(matches!(&instrs[2], Instr::Jump(Exp::Bool(true), 5, _)));
+        // The untaken branch is kept, as instruction indices are unchanged
+        assert!(matches!(&instrs[3], Instr::Copy(_, Exp::Id(id), _) if *id == x));
+        assert!(matches!(&instrs[8], Instr::Copy(_, Exp::Call(Op::Add, args), _) if matches!(args[1], Exp::I64(5))))
+    }
+
+    #[test]
+    fn constant_registers() {
+        let ir = "register zR : %bv8\nregister zS : %bv8\nval zf : (%bv8) -> %bv8\nfn zf(zx) {\n  zR = zx;\n  return = @bvadd(zS, zR);\n  end\n}\n";
+        let mut symtab = Symtab::new();
+        let mut defs = parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let (r, s) = (symtab.lookup("zR"), symtab.lookup("zS"));
+
+        let written = written_names(&defs);
+        assert!(written.contains(&r) && !written.contains(&s));
+
+        let registers = HashMap::from([(s, Val::Bits(B64::new(1, 8)))]);
+        let stats = optimize(&mut defs, &registers);
+        assert_eq!(stats.registers, 1);
+
+        let (_, instrs) = function(&defs);
+        let Instr::Copy(_, Exp::Call(Op::Bvadd, args), _) = &instrs[1] else { panic!("unexpected instruction") };
+        assert!(matches!(&args[0], Exp::Bits(bv) if *bv == B64::new(1, 8)));
+        assert!(matches!(&args[1], Exp::Id(id) if *id == r))
+    }
+
+    #[test]
+    fn specialize_field() {
+        let ir = "struct zctl { zE2H: %bool, zTGE: %bool }\nregister zHCR : %struct zctl\nval zf : (%i64) -> %i64\nfn zf(zx) {\n  jump zHCR.zE2H goto 3;\n  return = zx;\n  goto 4;\n  return = @iadd(zx, 1);\n  end\n}\n";
+        let mut symtab = Symtab::new();
+        let mut defs = parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let (hcr, e2h) = (symtab.lookup("zHCR"), symtab.lookup("zE2H"));
+
+        let mut registers = HashMap::new();
+        let field = Loc::Field(Box::new(Loc::Id(hcr)), e2h);
+        fix_register(&defs, &mut registers, &field, Val::Bool(true)).unwrap();
+        assert!(fix_register(&defs, &mut registers, &Loc::Id(e2h), Val::Bool(true)).is_err());
+
+        let stats = specialize(&mut defs, &registers);
+        assert_eq!((stats.registers, stats.branches, stats.pruned), (1, 1, 2));
+        let (_, instrs) = function(&defs);
+        assert!(matches!(&instrs[0], Instr::Jump(Exp::Bool(true), 3, _)));
+        assert!(matches!(&instrs[1..3], [Instr::Arbitrary, Instr::Arbitrary]));
+        assert!(matches!(&instrs[3], Instr::Copy(_, Exp::Call(Op::Add, _), _)))
+    }
+
+    /// Run the single function in the IR with each argument, returning
+    /// the results.
+    fn run(ir: &str, optimized: bool, inputs: &[i64]) -> Vec<Val<B64>> {
+        let mut symtab = Symtab::new();
+        let mut defs = parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        if optimized {
+            optimize(&mut defs, &HashMap::new());
+        }
+        let type_info = IRTypeInfo::new(&defs);
+        let x = symtab.lookup("zx");
+        let shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let (f, instrs) = function(&defs);
+
+        inputs
+            .iter()
+            .map(|input| {
+                let task_state = TaskState::new();
+                let task = LocalFrame::new(f, &[(x, &Ty::I64)], &Ty::I64, Some(&[Val::I64(*input)]), instrs)
+                    .task(0, &task_state);
+                let results = Mutex::new(Vec::new());
+                start_single(task, &shared_state, &results, &|_, _, result, _, _, results| {
+                    results.lock().unwrap().push(result.map(|(value, _)| value).map_err(|(err, _)| err.to_string()))
+                });
+                let mut results = results.into_inner().unwrap();
+                assert_eq!(results.len(), 1);
+                results.pop().unwrap().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn semantics_preserved() {
+        let inputs = [-3, 0, 4, 5, 6, 100];
+        let expected: Vec<Val<B64>> = inputs.iter().map(|x| Val::I64(if *x < 5 { x + 5 } else { x - 5 })).collect();
+        assert_eq!(run(BRANCHES, false, &inputs), expected);
+        assert_eq!(run(BRANCHES, true, &inputs), expected)
+    }
+}
@@ -203,11 +203,11 @@ pub fn write_serialized_architecture<B: BV>(
     let raw_symtab = bincode::serialize(&symtab.to_raw_table()).map_err(|_| SerializationError::ArchitectureError)?;
 
     file.write_all(b"ISLAARCH").map_err(IOError)?;
-    file.write_all(&version.len().to_le_bytes()).map_err(IOError)?;
+    file.write_all(&(version.len() as u64).to_le_bytes()).map_err(IOError)?;
     file.write_all(version).map_err(IOError)?;
-    file.write_all(&raw_ir.len().to_le_bytes()).map_err(IOError)?;
+    file.write_all(&(raw_ir.len() as u64).to_le_bytes()).map_err(IOError)?;
     file.write_all(&raw_ir).map_err(IOError)?;
-    file.write_all(&raw_symtab.len().to_le_bytes()).map_err(IOError)?;
+    file.write_all(&(raw_symtab.len() as u64).to_le_bytes()).map_err(IOError)?;
     file.write_all(&raw_symtab).map_err(IOError)?;
 
     Ok(())
@@ -232,9 +232,20 @@ where
     P: AsRef<Path>,
     B: BV,
 {
-    use SerializationError::*;
+    let buf = File::open(input).map_err(SerializationError::IOError)?;
+    read_serialized_architecture_from(buf)
+}
 
-    let mut buf = File::open(input).map_err(IOError)?;
+/// Read a serialized architecture from any reader, e.g. the bytes of
+/// an `.irx` file fetched by a browser. Lengths are stored as 64-bit
+/// integers, so files can be read on 32-bit targets such as
+/// `wasm32-unknown-unknown`.
+pub fn read_serialized_architecture_from<R, B>(mut buf: R) -> Result<DeserializedArchitecture<B>, SerializationError>
+where
+    R: Read,
+    B: BV,
+{
+    use SerializationError::*;
 
     let mut isla_magic = [0u8; 8];
     buf.read_exact(&mut isla_magic).map_err(IOError)?;
@@ -245,7 +256,7 @@ where
     let mut len = [0u8; 8];
 
     buf.read_exact(&mut len).map_err(IOError)?;
-    let mut version = vec![0; usize::from_le_bytes(len)];
+    let mut version = vec![0; u64::from_le_bytes(len) as usize];
     buf.read_exact(&mut version).map_err(IOError)?;
 
     if version != env!("ISLA_VERSION").as_bytes() {
@@ -256,11 +267,11 @@ where
     }
 
     buf.read_exact(&mut len).map_err(IOError)?;
-    let mut raw_ir = vec![0; usize::from_le_bytes(len)];
+    let mut raw_ir = vec![0; u64::from_le_bytes(len) as usize];
     buf.read_exact(&mut raw_ir).map_err(IOError)?;
 
     buf.read_exact(&mut len).map_err(IOError)?;
-    let mut raw_symtab = vec![0; usize::from_le_bytes(len)];
+    let mut raw_symtab = vec![0; u64::from_le_bytes(len) as usize];
     buf.read_exact(&mut raw_symtab).map_err(IOError)?;
 
     let ir: Vec<Def<Name, B>> = deserialize(&raw_ir).ok_or(SerializationError::ArchitectureError)?;
@@ -717,6 +717,7 @@ fn block_exp(exp: &Exp<Name>) -> Exp<SSAName> {
         Unit => Unit,
         I64(n) => I64(*n),
         I128(n) => I128(*n),
+        BigInt(n) => BigInt(n.clone()),
         Undefined(ty) => Undefined(block_ty(ty)),
         Struct(s, fields) => {
             Struct(SSAName::new(*s), fields.iter().map(|(field, exp)| (SSAName::new(*field), block_exp(exp))).collect())
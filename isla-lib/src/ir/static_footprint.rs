@@ -0,0 +1,190 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! This module implements a static over-approximation of the
+//! registers a function can read and write, found by syntactically
+//! scanning the IR of the function and everything it can call. This
+//! is much faster than symbolic execution, so is useful for coarse
+//! screening, but it will include registers on paths that can never
+//! be taken.
+
+use std::collections::{HashSet, VecDeque};
+
+use super::*;
+
+/// The registers that a function and its callees could access.
+#[derive(Clone, Debug, Default)]
+pub struct StaticFootprint {
+    pub reads: HashSet<Name>,
+    pub writes: HashSet<Name>,
+    /// Every function reachable from the analysed function
+    /// (including itself) that has a body
+    pub functions: HashSet<Name>,
+    /// Registers that have a reference taken, and so could be read or
+    /// written indirectly
+    pub references: HashSet<Name>,
+}
+
+fn exp_registers<B>(exp: &Exp<Name>, shared_state: &SharedState<B>, footprint: &mut StaticFootprint) {
+    match exp {
+        Exp::Id(id) if shared_state.registers.contains_key(id) => {
+            footprint.reads.insert(*id);
+        }
+        Exp::Ref(id) if shared_state.registers.contains_key(id) => {
+            footprint.references.insert(*id);
+        }
+        Exp::Kind(_, exp) | Exp::Unwrap(_, exp) | Exp::Field(exp, _) => exp_registers(exp, shared_state, footprint),
+        Exp::Call(_, exps) => exps.iter().for_each(|exp| exp_registers(exp, shared_state, footprint)),
+        Exp::Struct(_, fields) => fields.iter().for_each(|(_, exp)| exp_registers(exp, shared_state, footprint)),
+        _ => (),
+    }
+}
+
+fn loc_registers<B>(loc: &Loc<Name>, shared_state: &SharedState<B>, footprint: &mut StaticFootprint) {
+    if let Loc::Id(_) | Loc::Field(_, _) = loc {
+        let id = loc.id();
+        if shared_state.registers.contains_key(&id) {
+            footprint.writes.insert(id);
+        }
+    }
+}
+
+/// Compute the static footprint of `function`, following calls to
+/// any other functions defined in the IR. Calls to primitives are
+/// assumed not to access registers.
+pub fn static_footprint<B: BV>(function: Name, shared_state: &SharedState<B>) -> StaticFootprint {
+    let mut footprint = StaticFootprint::default();
+    let mut queue = VecDeque::from([function]);
+
+    while let Some(f) = queue.pop_front() {
+        let Some((_, _, instrs)) = shared_state.functions.get(&f) else { continue };
+        if !footprint.functions.insert(f) {
+            continue;
+        }
+
+        for instr in instrs.iter() {
+            match instr {
+                Instr::Init(_, _, exp, _) | Instr::Jump(exp, _, _) => exp_registers(exp, shared_state, &mut footprint),
+                Instr::Copy(loc, exp, _) | Instr::PrimopUnary(loc, _, exp, _) => {
+                    loc_registers(loc, shared_state, &mut footprint);
+                    exp_registers(exp, shared_state, &mut footprint)
+                }
+                Instr::PrimopBinary(loc, _, exp1, exp2, _) => {
+                    loc_registers(loc, shared_state, &mut footprint);
+                    exp_registers(exp1, shared_state, &mut footprint);
+                    exp_registers(exp2, shared_state, &mut footprint)
+                }
+                Instr::PrimopVariadic(loc, _, args, _) => {
+                    loc_registers(loc, shared_state, &mut footprint);
+                    args.iter().for_each(|exp| exp_registers(exp, shared_state, &mut footprint))
+                }
+                Instr::Call(loc, _, callee, args, _) => {
+                    loc_registers(loc, shared_state, &mut footprint);
+                    args.iter().for_each(|exp| exp_registers(exp, shared_state, &mut footprint));
+                    if !footprint.functions.contains(callee) {
+                        queue.push_back(*callee)
+                    }
+                }
+                Instr::PrimopReset(loc, _, _) => loc_registers(loc, shared_state, &mut footprint),
+                Instr::Decl(..)
+                | Instr::Goto(_)
+                | Instr::Monomorphize(..)
+                | Instr::Exit(..)
+                | Instr::Arbitrary
+                | Instr::End => (),
+            }
+        }
+    }
+
+    footprint
+}
+
+/// The functions with bodies that `function` can reach in at most
+/// `depth` calls, including `function` itself.
+pub fn call_tree<B: BV>(function: Name, depth: usize, shared_state: &SharedState<B>) -> HashSet<Name> {
+    let mut functions = HashSet::new();
+    let mut queue = VecDeque::from([(function, 0)]);
+
+    while let Some((f, d)) = queue.pop_front() {
+        let Some((_, _, instrs)) = shared_state.functions.get(&f) else { continue };
+        if !functions.insert(f) || d == depth {
+            continue;
+        }
+
+        for instr in instrs.iter() {
+            if let Instr::Call(_, _, callee, _, _) = instr {
+                if !functions.contains(callee) {
+                    queue.push_back((*callee, d + 1))
+                }
+            }
+        }
+    }
+
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::ir_parse::parse_ir;
+
+    const IR: &str = "register zA : %bv8\n\nregister zB : %bv8\n\nregister zC : %bv8\n\nregister zD : %bv8\n\nval zg : (%bv8) -> %unit\nfn zg(zx) {\n  zB = zx;\n  return = ();\n  end\n}\n\nval zh : (%unit) -> %unit\nfn zh(zu) {\n  zD = 0x00;\n  return = ();\n  end\n}\n\nval zf : (%bv8) -> %unit\nfn zf(zx) {\n  zy : %bv8 = zA;\n  zr : &(%bv8) = &zC;\n  zu : %unit;\n  zu = zg(zy);\n  zu = zg(zx);\n  return = ();\n  end\n}\n";
+
+    #[test]
+    fn reads_writes_and_references() {
+        let mut symtab = Symtab::new();
+        let defs = parse_ir::<B64>(IR, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let (a, b, c) = (symtab.lookup("zA"), symtab.lookup("zB"), symtab.lookup("zC"));
+        let (f, g, h) = (symtab.lookup("zf"), symtab.lookup("zg"), symtab.lookup("zh"));
+        let type_info = IRTypeInfo::new(&defs);
+        let shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+
+        // Registers accessed by callees are included, but not those of
+        // functions which are never called
+        let footprint = static_footprint(f, &shared_state);
+        assert_eq!(footprint.reads, HashSet::from([a]));
+        assert_eq!(footprint.writes, HashSet::from([b]));
+        assert_eq!(footprint.references, HashSet::from([c]));
+        assert_eq!(footprint.functions, HashSet::from([f, g]));
+
+        assert_eq!(call_tree(f, 0, &shared_state), HashSet::from([f]));
+        assert_eq!(call_tree(f, 1, &shared_state), HashSet::from([f, g]));
+        assert!(static_footprint(h, &shared_state).reads.is_empty())
+    }
+}
@@ -29,7 +29,10 @@
 
 use lexgen::lexer;
 use lexgen_util::LexerError;
+use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use crate::lexer::*;
 
@@ -42,6 +45,7 @@ pub enum Tok<'input> {
     Hex(&'input str),
     Bin(&'input str),
     Pragma(&'input str, &'input str),
+    Include(&'input str),
     OpNot,
     OpOr,
     OpAnd,
@@ -71,6 +75,7 @@ pub enum Tok<'input> {
     OpZeroExtend,
     TyI,
     TyF,
+    TyFloat,
     TyBv,
     TyUnit,
     TyBool,
@@ -150,6 +155,8 @@ lexer! {
     rule Init {
         $$whitespace,
 
+        "//" (_ # '\n')*,
+
         "::<" = Tok::TurboFish,
         "`" = Tok::Backtick,
         ">" = Tok::Gt,
@@ -203,6 +210,7 @@ lexer! {
         "%bv" = Tok::TyBv,
         "%i" = Tok::TyI,
         "%f" = Tok::TyF,
+        "%float" = Tok::TyFloat,
         "%rounding_mode" = Tok::TyRoundingMode,
         "@slice" = Tok::OpSlice,
         "@set_slice" = Tok::OpSetSlice,
@@ -269,6 +277,12 @@ lexer! {
             lexer.return_(Tok::Pragma(pragma, args.trim()))
         },
 
+        "include" $$whitespace+ '"' (_ # '"')* '"' => |lexer| {
+            let file = lexer.match_()["include".len()..].trim_start();
+            let file = file.strip_prefix('"').unwrap().strip_suffix('"').unwrap();
+            lexer.return_(Tok::Include(file))
+        },
+
         '"' => |lexer| lexer.switch(LexerRule::String),
     }
 
@@ -294,3 +308,135 @@ pub fn new_ir_lexer<'a>(input: &'a str) -> impl Iterator<Item = Span<'a>> {
         Err(LexerError { location, .. }) => Err(LexError { pos: location.byte_idx }),
     })
 }
+
+#[derive(Debug)]
+pub enum IncludeError {
+    IOError(PathBuf, std::io::Error),
+    Cycle(PathBuf),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use IncludeError::*;
+        match self {
+            IOError(file, err) => write!(f, "Could not read included file {}: {}", file.display(), err),
+            Cycle(file) => write!(f, "File {} includes itself", file.display()),
+        }
+    }
+}
+
+impl Error for IncludeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Replace every `include "file"` directive in the IR with the
+/// contents of that file, recursively. Relative paths are resolved
+/// against the directory containing the including file.
+pub fn expand_includes<P: AsRef<Path>>(contents: &str, file: P) -> Result<String, IncludeError> {
+    let file = file.as_ref();
+    let canonical = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    let mut stack = vec![(file.to_path_buf(), canonical)];
+    expand_includes_rec(contents, &mut stack)
+}
+
+/// The stack contains each file being expanded, both as it was named
+/// (which relative includes are resolved against) and canonicalized
+/// (so that different paths to the same file are detected as a
+/// cycle).
+fn expand_includes_rec(contents: &str, stack: &mut Vec<(PathBuf, PathBuf)>) -> Result<String, IncludeError> {
+    let dir = stack.last().and_then(|(file, _)| file.parent()).map(Path::to_path_buf).unwrap_or_default();
+
+    let mut expanded = String::new();
+    let mut last = 0;
+
+    for span in new_ir_lexer(contents) {
+        match span {
+            Ok((start, Tok::Include(file), end)) => {
+                let file = dir.join(file);
+                let canonical = fs::canonicalize(&file).map_err(|err| IncludeError::IOError(file.clone(), err))?;
+                if stack.iter().any(|(_, included)| *included == canonical) {
+                    return Err(IncludeError::Cycle(file));
+                }
+                let included = fs::read_to_string(&file).map_err(|err| IncludeError::IOError(file.clone(), err))?;
+                stack.push((file, canonical));
+                expanded.push_str(&contents[last..start]);
+                expanded.push_str(&expand_includes_rec(&included, stack)?);
+                stack.pop();
+                last = end
+            }
+            Ok(_) => (),
+            // Lexical errors are reported when the expanded file is parsed
+            Err(_) => break,
+        }
+    }
+
+    expanded.push_str(&contents[last..]);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_comments() {
+        let toks: Vec<_> =
+            new_ir_lexer("val // a comment with \"include\" in it\n zfoo").map(|span| span.unwrap().1).collect();
+        assert!(matches!(toks[..], [Tok::Val, Tok::Id("zfoo")]))
+    }
+
+    #[test]
+    fn longest_match() {
+        let toks: Vec<_> =
+            new_ir_lexer("-> > %float %f @lteq @lt ::< : letx let").map(|span| span.unwrap().1).collect();
+        assert!(matches!(
+            toks[..],
+            [
+                Tok::Arrow,
+                Tok::Gt,
+                Tok::TyFloat,
+                Tok::TyF,
+                Tok::OpLteq,
+                Tok::OpLt,
+                Tok::TurboFish,
+                Tok::Colon,
+                Tok::Id("letx"),
+                Tok::Let
+            ]
+        ))
+    }
+
+    #[test]
+    fn include_directive() {
+        let toks: Vec<_> = new_ir_lexer("include \"a/b.ir\" register").map(|span| span.unwrap().1).collect();
+        assert!(matches!(toks[..], [Tok::Include("a/b.ir"), Tok::Register]))
+    }
+
+    #[test]
+    fn expand_nested_includes() {
+        let dir = std::env::temp_dir().join(format!("isla_include_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("inner.ir"), "register zR : %bool").unwrap();
+        fs::write(dir.join("outer.ir"), "include \"sub/inner.ir\"\nregister zS : %bool").unwrap();
+        fs::write(dir.join("cycle.ir"), "include \"cycle.ir\"").unwrap();
+
+        let expanded = expand_includes("include \"outer.ir\"\n", dir.join("main.ir")).unwrap();
+        assert_eq!(expanded, "register zR : %bool\nregister zS : %bool\n");
+
+        assert!(matches!(expand_includes("include \"cycle.ir\"", dir.join("main.ir")), Err(IncludeError::Cycle(_))));
+
+        // A file which includes itself through a different path
+        fs::write(dir.join("sub").join("self.ir"), "include \"../sub/self.ir\"").unwrap();
+        let self_include = expand_includes("include \"sub/self.ir\"", dir.join("main.ir"));
+        assert!(matches!(self_include, Err(IncludeError::Cycle(_))), "{:?}", self_include);
+
+        // The top-level file is also part of any cycle
+        fs::write(dir.join("top.ir"), "include \"sub/../top.ir\"").unwrap();
+        let top_include = expand_includes("include \"sub/../top.ir\"", dir.join("top.ir"));
+        assert!(matches!(top_include, Err(IncludeError::Cycle(_))), "{:?}", top_include);
+
+        fs::remove_dir_all(dir).unwrap()
+    }
+}
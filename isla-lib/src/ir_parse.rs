@@ -0,0 +1,187 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Parsing of Sail IR files, with errors reported by line and column
+//! alongside the offending source line. The parser recovers from
+//! syntax errors at the start of the next definition, so a single pass
+//! reports every definition that fails to parse.
+
+use lalrpop_util::{ErrorRecovery, ParseError};
+use std::error::Error;
+use std::fmt;
+
+use crate::bitvector::BV;
+use crate::ir::{Def, Name, Symtab};
+use crate::ir_lexer::{new_ir_lexer, Tok};
+use crate::ir_parser::IrParser;
+use crate::lexer::LexError;
+
+/// The maximum number of expected tokens listed in an error message
+const MAX_EXPECTED: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IrParseError {
+    /// The line (counting from 1) where the error occurred
+    pub line: usize,
+    /// The column (counting from 1, in characters) where the error occurred
+    pub column: usize,
+    pub message: String,
+    /// The source line containing the error
+    pub snippet: String,
+}
+
+impl fmt::Display for IrParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}:{}: {}", self.line, self.column, self.message)?;
+        writeln!(f, "  | {}", self.snippet)?;
+        write!(f, "  | {:>width$}", "^", width = self.column)
+    }
+}
+
+impl Error for IrParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl IrParseError {
+    fn new(contents: &str, pos: usize, message: String) -> Self {
+        let pos = pos.min(contents.len());
+        let line_start = contents[..pos].rfind('\n').map_or(0, |n| n + 1);
+        let line_end = contents[pos..].find('\n').map_or(contents.len(), |n| pos + n);
+        IrParseError {
+            line: contents[..line_start].matches('\n').count() + 1,
+            column: contents[line_start..pos].chars().count() + 1,
+            message,
+            snippet: contents[line_start..line_end].trim_end().to_string(),
+        }
+    }
+
+    fn from_parse_error(contents: &str, error: ParseError<usize, Tok<'_>, LexError>) -> Self {
+        use ParseError::*;
+        match error {
+            InvalidToken { location } => IrParseError::new(contents, location, "invalid token".to_string()),
+            UnrecognizedEOF { location, expected } => {
+                IrParseError::new(contents, location, format!("unexpected end of file{}", expected_message(&expected)))
+            }
+            UnrecognizedToken { token: (start, _, end), expected } => IrParseError::new(
+                contents,
+                start,
+                format!("unexpected `{}`{}", &contents[start..end], expected_message(&expected)),
+            ),
+            ExtraToken { token: (start, _, end) } => {
+                IrParseError::new(contents, start, format!("extra token `{}`", &contents[start..end]))
+            }
+            User { error } => IrParseError::new(contents, error.pos, "lexical error".to_string()),
+        }
+    }
+}
+
+fn expected_message(expected: &[String]) -> String {
+    if expected.is_empty() {
+        String::new()
+    } else if expected.len() > MAX_EXPECTED {
+        format!(", expected one of {}, ...", expected[..MAX_EXPECTED].join(", "))
+    } else {
+        format!(", expected one of {}", expected.join(", "))
+    }
+}
+
+/// Parse the contents of an IR file, returning every syntax error
+/// found if it does not parse.
+pub fn parse_ir<'input, B: BV>(
+    contents: &'input str,
+    symtab: &mut Symtab<'input>,
+) -> Result<Vec<Def<Name, B>>, Vec<IrParseError>> {
+    let mut recovered: Vec<ErrorRecovery<usize, Tok<'input>, LexError>> = Vec::new();
+    let result = IrParser::new().parse(symtab, &mut recovered, new_ir_lexer(contents));
+    let mut errors: Vec<IrParseError> =
+        recovered.into_iter().map(|recovery| IrParseError::from_parse_error(contents, recovery.error)).collect();
+    match result {
+        Ok(defs) if errors.is_empty() => Ok(defs),
+        Ok(_) => Err(errors),
+        Err(error) => {
+            errors.push(IrParseError::from_parse_error(contents, error));
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+
+    #[test]
+    fn reports_every_syntax_error() {
+        let ir = "register zR : %bool\nval zf : (%bool) -> \nregister zS : %bool\nfn zg(zx) {\n  jump zx goto;\n}\nregister zT : %bv64\n";
+        let mut symtab = Symtab::new();
+        let Err(errors) = parse_ir::<B64>(ir, &mut symtab) else { panic!("IR with syntax errors parsed") };
+        assert_eq!(errors.len(), 2);
+        assert_eq!((errors[0].line, errors[0].column), (3, 1));
+        assert!(errors[0].message.starts_with("unexpected `register`"));
+        assert_eq!(errors[0].snippet, "register zS : %bool");
+        assert_eq!((errors[1].line, errors[1].column), (5, 15));
+        assert!(errors[1].to_string().ends_with("  |   jump zx goto;\n  |               ^"))
+    }
+
+    #[test]
+    fn parses_valid_ir() {
+        let mut symtab = Symtab::new();
+        let defs = parse_ir::<B64>("register zR : %bool\nregister zS : %bv64", &mut symtab)
+            .map_err(|_| "parse failed")
+            .unwrap();
+        assert_eq!(defs.len(), 2)
+    }
+
+    #[test]
+    fn reports_lexical_errors() {
+        let mut symtab = Symtab::new();
+        let Err(errors) = parse_ir::<B64>("register zR : %bool\n  register ^ zS", &mut symtab) else {
+            panic!("IR with lexical error parsed")
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (2, 12));
+        assert_eq!(errors[0].message, "lexical error")
+    }
+
+    #[test]
+    fn function_signatures() {
+        let ir = "enum zE { zA, zB }\nval zf : (%bv32, %enum zE, %vec(%i64)) -> %f32\nfn zf(zx, zy, zz) {\n  end\n}\nval zg = \"g\" : (%unit) -> %unit\n";
+        let mut symtab = Symtab::new();
+        let defs = parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let signatures = crate::ir::function_signatures(&defs);
+        assert_eq!(signatures.len(), 1);
+        let (f, args, ret_ty) = &signatures[0];
+        assert_eq!(symtab.to_str(*f), "zf");
+        let args: Vec<String> = args.iter().map(|(_, ty)| ty.to_string(&symtab)).collect();
+        assert_eq!(args, ["%bv32", "%enum E", "%vec(%i64)"]);
+        assert_eq!(ret_ty.to_string(&symtab), "%f32")
+    }
+}
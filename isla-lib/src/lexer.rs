@@ -41,6 +41,77 @@ impl fmt::Display for LexError {
     }
 }
 
+/// A set of keywords stored as a trie, so the longest keyword at the
+/// start of some input is found in a single pass over it, whatever
+/// order the keywords are listed in. A keyword starting with a letter
+/// only matches when it is not immediately followed by an identifier
+/// character, so `as` does not match the start of `ask`.
+pub struct KeywordTable<T> {
+    nodes: Vec<KeywordNode<T>>,
+}
+
+struct KeywordNode<T> {
+    children: Vec<(u8, usize)>,
+    token: Option<T>,
+}
+
+impl<T> KeywordNode<T> {
+    fn new() -> Self {
+        KeywordNode { children: Vec::new(), token: None }
+    }
+
+    fn child(&self, byte: u8) -> Option<usize> {
+        self.children.iter().find(|(b, _)| *b == byte).map(|(_, child)| *child)
+    }
+}
+
+impl<T> KeywordTable<T> {
+    pub fn new(keywords: Vec<(&str, T)>) -> Self {
+        let mut nodes = vec![KeywordNode::new()];
+        for (word, token) in keywords {
+            assert!(!word.is_empty(), "lexer contains empty keyword");
+            let mut node = 0;
+            for byte in word.bytes() {
+                node = match nodes[node].child(byte) {
+                    Some(child) => child,
+                    None => {
+                        nodes.push(KeywordNode::new());
+                        let child = nodes.len() - 1;
+                        nodes[node].children.push((byte, child));
+                        child
+                    }
+                }
+            }
+            assert!(nodes[node].token.is_none(), "lexer contains duplicate keyword {}", word);
+            nodes[node].token = Some(token)
+        }
+        KeywordTable { nodes }
+    }
+
+    /// Returns the length and token of the longest keyword at the
+    /// start of `input`, if any.
+    pub fn longest_match(&self, input: &str) -> Option<(usize, &T)> {
+        let bytes = input.as_bytes();
+        let is_word = bytes.first()?.is_ascii_alphabetic();
+        let mut node = 0;
+        let mut longest = None;
+        for (i, byte) in bytes.iter().enumerate() {
+            match self.nodes[node].child(*byte) {
+                Some(child) => node = child,
+                None => break,
+            }
+            if let Some(token) = &self.nodes[node].token {
+                let at_boundary =
+                    !is_word || !matches!(bytes.get(i + 1), Some(c) if c.is_ascii_alphanumeric() || *c == b'_');
+                if at_boundary {
+                    longest = Some((i + 1, token))
+                }
+            }
+        }
+        longest
+    }
+}
+
 pub struct Lexer<'input> {
     pub buf: &'input str,
     pub pos: usize,
@@ -64,6 +135,14 @@ impl<'input> Lexer<'input> {
         }
     }
 
+    pub fn consume_keyword<T: Clone>(&mut self, keywords: &KeywordTable<T>) -> Option<(usize, T, usize)> {
+        let (len, token) = keywords.longest_match(self.buf)?;
+        let start_pos = self.pos;
+        self.pos += len;
+        self.buf = &self.buf[len..];
+        Some((start_pos, token.clone(), self.pos))
+    }
+
     pub fn consume_regex(&mut self, r: &Regex) -> Option<(usize, &'input str, usize)> {
         match r.find(self.buf) {
             None => None,
@@ -119,3 +198,35 @@ lazy_static! {
     pub static ref CAP_REGEX: Regex = Regex::new(r"^[#0]c[0-1][0-9a-fA-F]*").unwrap();
     pub static ref NAT_REGEX: Regex = Regex::new(r"^[0-9]+").unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyword_longest_match() {
+        // Shorter keywords listed first must not shadow longer ones
+        let keywords = KeywordTable::new(vec![("&", 0), ("&&", 1), (">", 2), ("->", 3), ("as", 4), ("assert", 5)]);
+        assert_eq!(keywords.longest_match("&&x"), Some((2, &1)));
+        assert_eq!(keywords.longest_match("& &"), Some((1, &0)));
+        assert_eq!(keywords.longest_match("->"), Some((2, &3)));
+        assert_eq!(keywords.longest_match(">-"), Some((1, &2)));
+        assert_eq!(keywords.longest_match("assert("), Some((6, &5)));
+        assert_eq!(keywords.longest_match("as x"), Some((2, &4)));
+        // Word keywords must end at an identifier boundary
+        assert_eq!(keywords.longest_match("asserted"), None);
+        assert_eq!(keywords.longest_match("ass"), None);
+        assert_eq!(keywords.longest_match("-"), None);
+        assert_eq!(keywords.longest_match(""), None)
+    }
+
+    #[test]
+    fn consume_keyword() {
+        let keywords = KeywordTable::new(vec![("||", "or"), ("|", "bar")]);
+        let mut lexer = Lexer::new("|| |");
+        assert_eq!(lexer.consume_keyword(&keywords), Some((0, "or", 2)));
+        lexer.consume_whitespace();
+        assert_eq!(lexer.consume_keyword(&keywords), Some((3, "bar", 4)));
+        assert_eq!(lexer.buf, "")
+    }
+}
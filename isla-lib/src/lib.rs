@@ -41,19 +41,23 @@ lalrpop_mod!(#[allow(clippy::all)] pub ir_parser);
 lalrpop_mod!(#[allow(clippy::all)] pub value_parser);
 lalrpop_mod!(#[allow(clippy::all)] pub smt_parser);
 
+pub mod abstract_interp;
 pub mod bitvector;
 pub mod cache;
 pub mod config;
 pub mod error;
 pub mod executor;
+pub mod explorer;
 pub mod init;
 pub mod ir;
 pub mod ir_lexer;
+pub mod ir_parse;
 pub mod lexer;
 pub mod memory;
+pub mod postprocess;
 pub mod primop;
 pub mod primop_util;
-mod probe;
+pub mod probe;
 pub mod register;
 pub mod simplify;
 pub mod smt;
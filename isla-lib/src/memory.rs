@@ -45,16 +45,68 @@ use std::sync::Arc;
 use crate::bitvector::BV;
 use crate::error::ExecError;
 use crate::ir;
-use crate::ir::Val;
+use crate::ir::{Loc, Val};
 use crate::log;
+use crate::primop_util::smt_value;
 use crate::probe;
 use crate::smt::smtlib::{bits64, Def, Exp};
 use crate::smt::{Event, Model, ReadOpts, SmtResult, Solver, Sym, WriteOpts};
 use crate::source_loc::SourceLoc;
+use crate::zencode;
+
+pub mod alias;
 
 /// For now, we assume that we only deal with 64-bit architectures.
 pub type Address = u64;
 
+/// The byte order of multi-byte values in memory
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+    /// The word-invariant big-endian mode of older Arm cores (BE-32),
+    /// where a word reads the same as in little-endian mode, but byte
+    /// and halfword accesses are to the opposite end of their word
+    WordInvariantBig,
+}
+
+/// The address actually accessed by a `bytes` byte access to
+/// `address`. In BE-32 mode byte and halfword accesses come from the
+/// opposite end of their word, which is only meaningful for naturally
+/// aligned accesses, so any unaligned BE-32 access returns `None`.
+pub fn physical_address(endianness: Endianness, address: Address, bytes: u32) -> Option<Address> {
+    let bytes = u64::from(bytes);
+    match endianness {
+        Endianness::WordInvariantBig if bytes == 1 || bytes == 2 => {
+            if address & (bytes - 1) == 0 {
+                Some(address ^ (4 - bytes))
+            } else {
+                None
+            }
+        }
+        Endianness::WordInvariantBig if (address | bytes) & 3 != 0 => None,
+        _ => Some(address),
+    }
+}
+
+/// The addresses making up a `bytes` byte access to the physical
+/// address `address` (see [physical_address]), most significant byte
+/// first.
+pub fn byte_order(endianness: Endianness, address: Address, bytes: u32) -> Vec<Address> {
+    let bytes = u64::from(bytes);
+    match endianness {
+        Endianness::Little => (address..address + bytes).rev().collect(),
+        Endianness::Big => (address..address + bytes).collect(),
+        // Byte and halfword accesses have already been moved to the
+        // correct end of their word, within which they are little-endian
+        Endianness::WordInvariantBig if bytes < 4 => (address..address + bytes).rev().collect(),
+        // Larger accesses are a sequence of words, the first being
+        // the most significant
+        Endianness::WordInvariantBig => (0..bytes).map(|i| address + (i & !3) + (3 - (i & 3))).collect(),
+    }
+}
+
 pub trait CustomRegion<B> {
     fn read(
         &self,
@@ -63,6 +115,7 @@ pub trait CustomRegion<B> {
         bytes: u32,
         solver: &mut Solver<B>,
         tag: bool,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError>;
 
     fn write(
@@ -72,6 +125,7 @@ pub trait CustomRegion<B> {
         data: Val<B>,
         solver: &mut Solver<B>,
         tag: Option<Val<B>>,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError>;
 
     fn initial_value(&self, address: Address, bytes: u32) -> Option<B>;
@@ -159,6 +213,126 @@ impl<B> Region<B> {
     }
 }
 
+/// Callbacks which handle the reads and writes to a region of
+/// memory, so embedders can model memory-mapped devices such as a
+/// UART or a timer. They are registered for a range of addresses
+/// with [Memory::add_callback_region], and the memory events for
+/// accesses they handle are marked as produced by a callback (see
+/// [Event::is_callback]).
+///
+/// Memory is cloned when execution forks, so any state kept by the
+/// callbacks is per-path. Reads only have shared access to the
+/// callbacks, so devices where reads have side effects need to use
+/// interior mutability.
+pub trait RegionCallbacks<B>: Send + Sync {
+    /// Supply the value for a read of `bytes` bytes at `address`, or
+    /// `None` to leave the value read symbolic.
+    fn read(&self, address: Address, bytes: u32) -> Option<B>;
+
+    /// Observe a write of `bytes` bytes at `address`. The data may be
+    /// symbolic.
+    fn write(&mut self, address: Address, bytes: u32, data: &Val<B>);
+
+    /// The name of the device, which is recorded as the region of
+    /// each memory event.
+    fn name(&self) -> &'static str;
+
+    /// As for [CustomRegion::clone_dyn].
+    fn clone_dyn(&self) -> Box<dyn RegionCallbacks<B>>;
+}
+
+/// A custom region which passes each access to a set of
+/// [RegionCallbacks].
+struct CallbackRegion<B> {
+    callbacks: Box<dyn RegionCallbacks<B>>,
+}
+
+impl<B: BV> CustomRegion<B> for CallbackRegion<B> {
+    fn read(
+        &self,
+        read_kind: Val<B>,
+        address: Address,
+        bytes: u32,
+        solver: &mut Solver<B>,
+        tag: bool,
+        info: SourceLoc,
+    ) -> Result<Val<B>, ExecError> {
+        use crate::smt::smtlib::Ty;
+
+        if tag {
+            return Err(ExecError::BadRead("Tagged read from callback region"));
+        }
+
+        let value = match self.callbacks.read(address, bytes) {
+            Some(bv) if bv.len() == bytes * 8 => Val::Bits(bv),
+            Some(_) => return Err(ExecError::BadRead("Callback returned a value of the wrong length")),
+            None => Val::Symbolic(solver.declare_const(Ty::BitVec(bytes * 8), info)),
+        };
+        log!(log::MEMORY, &format!("Callback read {}: 0x{:x} -> {:?}", self.callbacks.name(), address, value));
+
+        solver.add_event(Event::ReadMem {
+            value: value.clone(),
+            read_kind,
+            address: Val::Bits(B::from_u64(address)),
+            bytes,
+            tag_value: None,
+            opts: ReadOpts { is_callback: true, ..ReadOpts::default() },
+            region: self.callbacks.name(),
+            info,
+        });
+
+        Ok(value)
+    }
+
+    fn write(
+        &mut self,
+        write_kind: Val<B>,
+        address: Address,
+        data: Val<B>,
+        solver: &mut Solver<B>,
+        tag: Option<Val<B>>,
+        info: SourceLoc,
+    ) -> Result<Val<B>, ExecError> {
+        use crate::smt::smtlib::Ty;
+
+        let data_length = crate::primop_util::length_bits(&data, solver, info)?;
+        if data_length % 8 != 0 {
+            return Err(ExecError::Type(format!("callback write {:?}", &data_length), info));
+        }
+        let bytes = data_length / 8;
+
+        log!(log::MEMORY, &format!("Callback write {}: 0x{:x} <- {:?}", self.callbacks.name(), address, data));
+        self.callbacks.write(address, bytes, &data);
+
+        let value = solver.declare_const(Ty::Bool, info);
+        solver.add_event(Event::WriteMem {
+            value,
+            write_kind,
+            address: Val::Bits(B::from_u64(address)),
+            data,
+            bytes,
+            tag_value: tag,
+            opts: WriteOpts::callback(),
+            region: self.callbacks.name(),
+            info,
+        });
+
+        Ok(Val::Symbolic(value))
+    }
+
+    fn initial_value(&self, _address: Address, _bytes: u32) -> Option<B> {
+        None
+    }
+
+    fn region_name(&self) -> &'static str {
+        self.callbacks.name()
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Send + Sync + CustomRegion<B>> {
+        Box::new(CallbackRegion { callbacks: self.callbacks.clone_dyn() })
+    }
+}
+
 // Optional client interface.  At the time of writing this is only
 // used by the test generation to enforce sequential memory, so we
 // jump through a few hoops to avoid other clients seeing it.  If it
@@ -221,6 +395,29 @@ impl<B> Clone for Box<dyn MemoryCallbacks<B>> {
     }
 }
 
+/// Supplies values for reads from concrete addresses that are not in
+/// any region, given the address and number of bytes. Reads it
+/// returns `None` for are treated as they would be without a
+/// resolver, and a value too wide for the number of bytes read is an
+/// error.
+#[derive(Clone)]
+pub struct ReadResolver(Arc<dyn Send + Sync + Fn(Address, u32) -> Option<u64>>);
+
+impl ReadResolver {
+    pub fn new<F>(resolve: F) -> Self
+    where
+        F: 'static + Send + Sync + Fn(Address, u32) -> Option<u64>,
+    {
+        ReadResolver(Arc::new(resolve))
+    }
+}
+
+impl fmt::Debug for ReadResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<read resolver>")
+    }
+}
+
 fn make_bv_bit_pair<B>(left: Val<B>, right: Val<B>) -> Val<B> {
     let mut fields = HashMap::default();
     fields.insert(ir::BV_BIT_LEFT, left);
@@ -228,17 +425,87 @@ fn make_bv_bit_pair<B>(left: Val<B>, right: Val<B>) -> Val<B> {
     Val::Struct(fields)
 }
 
+/// An access assertion is a property that must hold for every memory
+/// access. It is given the address (as an SMT expression) and the
+/// number of bytes being accessed, and returns a boolean SMT
+/// expression. If the negation of this expression is satisfiable at
+/// any access then the path is reported as an error, along with a
+/// witness address that violates the assertion.
+pub struct AccessAssertion<B> {
+    name: String,
+    assertion: Arc<AccessAssertionFn<B>>,
+}
+
+type AccessAssertionFn<B> = dyn Send + Sync + Fn(&Exp<Sym>, u32, &mut Solver<B>) -> Exp<Sym>;
+
+impl<B> Clone for AccessAssertion<B> {
+    fn clone(&self) -> Self {
+        AccessAssertion { name: self.name.clone(), assertion: self.assertion.clone() }
+    }
+}
+
+impl<B> fmt::Debug for AccessAssertion<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AccessAssertion({:?}, <closure>)", self.name)
+    }
+}
+
+impl<B: BV> AccessAssertion<B> {
+    pub fn new<F>(name: &str, assertion: F) -> Self
+    where
+        F: 'static + Send + Sync + Fn(&Exp<Sym>, u32, &mut Solver<B>) -> Exp<Sym>,
+    {
+        AccessAssertion { name: name.to_string(), assertion: Arc::new(assertion) }
+    }
+
+    /// Create an access assertion from an SMT expression template,
+    /// where the variable `addr` refers to the 64-bit address being
+    /// accessed, and `size` is the number of bytes being accessed
+    /// (also as a 64-bit bitvector).
+    pub fn from_template(name: &str, template: Exp<Loc<String>>) -> Result<Self, String> {
+        // Check the template only contains the allowed variables up-front
+        template.map_var(&mut |loc| template_var(loc, &Exp::Bool(false), 0))?;
+        Ok(Self::new(name, move |addr, bytes, _| template.map_var(&mut |loc| template_var(loc, addr, bytes)).unwrap()))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+fn template_var(loc: &Loc<String>, addr: &Exp<Sym>, bytes: u32) -> Result<Exp<Sym>, String> {
+    // The SMT parser z-encodes identifiers
+    match loc {
+        Loc::Id(name) if zencode::decode(name) == "addr" => Ok(addr.clone()),
+        Loc::Id(name) if zencode::decode(name) == "size" => Ok(bits64(bytes as u64, 64)),
+        _ => Err(format!("Only addr and size can appear in an access assertion, not {}", loc)),
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Memory<B> {
     regions: Vec<Region<B>>,
     client_info: Option<Box<dyn MemoryCallbacks<B>>>,
+    access_assertions: Vec<AccessAssertion<B>>,
+    read_resolver: Option<ReadResolver>,
+    data_endianness: Endianness,
+    instruction_endianness: Endianness,
 }
 
-static DEFAULT_REGION_NAME: &str = "default";
+pub(crate) static DEFAULT_REGION_NAME: &str = "default";
+
+static RESOLVED_REGION_NAME: &str = "resolved";
 
 impl<B: BV> Memory<B> {
     pub fn new() -> Self {
-        Memory { regions: Vec::new(), client_info: None }
+        Memory {
+            regions: Vec::new(),
+            client_info: None,
+            access_assertions: Vec::new(),
+            read_resolver: None,
+            data_endianness: Endianness::Little,
+            instruction_endianness: Endianness::Little,
+        }
     }
 
     pub fn region_name_at(&self, addr: Address) -> &'static str {
@@ -308,10 +575,121 @@ impl<B: BV> Memory<B> {
         self.regions.push(Region::Concrete(range, HashMap::new()))
     }
 
+    /// Add a region where reads and writes are handled by the given
+    /// callbacks.
+    pub fn add_callback_region(&mut self, range: Range<Address>, callbacks: Box<dyn RegionCallbacks<B>>) {
+        self.regions.push(Region::Custom(range, Box::new(CallbackRegion { callbacks })))
+    }
+
     pub fn set_client_info(&mut self, info: Box<dyn MemoryCallbacks<B>>) {
         self.client_info = Some(info);
     }
 
+    /// Set the byte order used when accessing data and fetching
+    /// instructions
+    pub fn set_endianness(&mut self, data: Endianness, instruction: Endianness) {
+        self.data_endianness = data;
+        self.instruction_endianness = instruction
+    }
+
+    pub fn data_endianness(&self) -> Endianness {
+        self.data_endianness
+    }
+
+    /// Translate the address of a `bytes` byte access into the address
+    /// which is actually accessed under the given byte order (see
+    /// [physical_address]). Returns `error` if the access is, or could
+    /// be, unaligned in a way the byte order does not support.
+    fn access_address(
+        &self,
+        address: Val<B>,
+        bytes: u32,
+        endianness: Endianness,
+        error: ExecError,
+        solver: &mut Solver<B>,
+        info: SourceLoc,
+    ) -> Result<Val<B>, ExecError> {
+        use Exp::*;
+        use SmtResult::*;
+
+        if endianness != Endianness::WordInvariantBig {
+            return Ok(address);
+        }
+
+        match address {
+            Val::Bits(bv) => match physical_address(endianness, bv.lower_u64(), bytes) {
+                Some(physical) => Ok(Val::Bits(B::new(physical, bv.len()))),
+                None => Err(error),
+            },
+
+            Val::Symbolic(v) => {
+                let mask = match bytes {
+                    1 => return Ok(flip_address(v, 3, solver, info)),
+                    2 => 1,
+                    _ if bytes & 3 == 0 => 3,
+                    _ => return Err(error),
+                };
+                let unaligned =
+                    Neq(Box::new(Bvand(Box::new(Var(v)), Box::new(bits64(mask, 64)))), Box::new(bits64(0, 64)));
+                match solver.check_sat_with(&unaligned) {
+                    Sat => {
+                        probe::taint_info(log::MEMORY, v, None, solver);
+                        Err(error)
+                    }
+                    Unknown => Err(ExecError::Z3Unknown),
+                    Unsat if bytes == 2 => Ok(flip_address(v, 2, solver, info)),
+                    Unsat => Ok(Val::Symbolic(v)),
+                }
+            }
+
+            address => Ok(address),
+        }
+    }
+
+    pub fn set_read_resolver(&mut self, resolver: ReadResolver) {
+        self.read_resolver = Some(resolver)
+    }
+
+    pub fn add_access_assertion(&mut self, assertion: AccessAssertion<B>) {
+        self.access_assertions.push(assertion)
+    }
+
+    /// Check that every access assertion holds for an access of
+    /// `bytes` bytes at `address`. If any assertion can be violated,
+    /// an error is returned containing a witness address.
+    fn check_access_assertions(&self, address: &Val<B>, bytes: u32, solver: &mut Solver<B>) -> Result<(), ExecError> {
+        use SmtResult::*;
+
+        if self.access_assertions.is_empty() {
+            return Ok(());
+        }
+
+        let address_exp = smt_value(address, SourceLoc::unknown())?;
+
+        for assertion in &self.access_assertions {
+            let exp = (assertion.assertion)(&address_exp, bytes, solver);
+            match solver.check_sat_with(&Exp::Not(Box::new(exp))) {
+                Sat => {
+                    let mut model = Model::new(solver);
+                    let witness = match model.get_exp(&address_exp)? {
+                        Some(Exp::Bits64(bv)) => bv.to_string(),
+                        Some(exp) => format!("{:?}", exp),
+                        None => "unknown".to_string(),
+                    };
+                    log!(log::MEMORY, &format!("Access assertion {} violated at address: {}", assertion.name, witness));
+                    if let Val::Symbolic(v) = address {
+                        probe::taint_info(log::MEMORY, *v, None, solver)
+                    }
+                    return Err(ExecError::AccessAssertion(assertion.name.clone(), witness));
+                }
+                Unknown => return Err(ExecError::Z3Unknown),
+                Unsat => (),
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn write_byte(&mut self, address: Address, byte: u8) {
         for region in &mut self.regions {
             match region {
@@ -348,13 +726,13 @@ impl<B: BV> Memory<B> {
     }
 
     pub fn read_initial(&self, address: Address, bytes: u32) -> Result<Val<B>, ExecError> {
+        let address = physical_address(self.data_endianness, address, bytes)
+            .ok_or(ExecError::BadRead("Unaligned initial read"))?;
         let mut byte_vec: Vec<u8> = Vec::with_capacity(bytes as usize);
-        for i in address..(address + u64::from(bytes)) {
+        for i in byte_order(self.data_endianness, address, bytes) {
             byte_vec.push(self.read_initial_byte(i)?)
         }
 
-        reverse_endianness(&mut byte_vec);
-
         if byte_vec.len() <= 8 {
             Ok(Val::Bits(B::from_bytes(&byte_vec)))
         } else {
@@ -412,12 +790,19 @@ impl<B: BV> Memory<B> {
         solver: &mut Solver<B>,
         tag: bool,
         opts: ReadOpts,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         log!(log::MEMORY, &format!("Read: {:?} {:?} {:?} {:?}", read_kind, address, bytes, tag));
 
         if let Val::I128(bytes) = bytes.widen_int() {
             let bytes = u32::try_from(bytes).expect("Bytes did not fit in u32 in memory read");
 
+            self.check_access_assertions(&address, bytes, solver)?;
+
+            let endianness = if opts.is_ifetch { self.instruction_endianness } else { self.data_endianness };
+            let address =
+                self.access_address(address, bytes, endianness, ExecError::BadRead("Unaligned access"), solver, info)?;
+
             match address {
                 Val::Bits(concrete_addr) => {
                     for region in &self.regions {
@@ -433,6 +818,7 @@ impl<B: BV> Memory<B> {
                                     tag,
                                     opts,
                                     region.region_name(),
+                                    info,
                                 )
                             }
 
@@ -445,6 +831,7 @@ impl<B: BV> Memory<B> {
                                     tag,
                                     opts,
                                     region.region_name(),
+                                    info,
                                 )
                             }
 
@@ -457,12 +844,14 @@ impl<B: BV> Memory<B> {
                                     tag,
                                     opts,
                                     region.region_name(),
+                                    info,
                                 )
                             }
 
                             Region::Concrete(range, contents) if range.contains(&concrete_addr.lower_u64()) => {
                                 return read_concrete(
                                     contents,
+                                    endianness,
                                     read_kind,
                                     concrete_addr.lower_u64(),
                                     bytes,
@@ -470,68 +859,110 @@ impl<B: BV> Memory<B> {
                                     tag,
                                     opts,
                                     region.region_name(),
-                                )
+                                    info,
+                                );
                             }
 
                             Region::Custom(range, contents) if range.contains(&concrete_addr.lower_u64()) => {
-                                return contents.read(read_kind, concrete_addr.lower_u64(), bytes, solver, tag)
+                                return contents.read(read_kind, concrete_addr.lower_u64(), bytes, solver, tag, info)
                             }
 
                             _ => continue,
                         }
                     }
 
+                    if let (Some(resolver), false, true) = (&self.read_resolver, tag, bytes <= 8) {
+                        if let Some(resolved) = (resolver.0)(concrete_addr.lower_u64(), bytes) {
+                            if bytes < 8 && resolved >> (bytes * 8) != 0 {
+                                return Err(ExecError::BadRead("Resolved value is wider than the read"));
+                            }
+                            // The resolved value is an assumption about the
+                            // unmapped location, so it stays visible in the trace
+                            let value = self.read_symbolic(
+                                read_kind,
+                                address,
+                                bytes,
+                                solver,
+                                tag,
+                                opts,
+                                RESOLVED_REGION_NAME,
+                                info,
+                            )?;
+                            if let Val::Symbolic(v) = value {
+                                solver.add(Def::Assert(Exp::Eq(
+                                    Box::new(Exp::Var(v)),
+                                    Box::new(bits64(resolved, bytes * 8)),
+                                )))
+                            }
+                            return Ok(value);
+                        }
+                    }
+
                     if opts.is_ifetch {
                         Err(ExecError::BadRead("Attempted to fetch instruction from default memory"))
                     } else {
-                        self.read_symbolic(read_kind, address, bytes, solver, tag, opts, DEFAULT_REGION_NAME)
+                        self.read_symbolic(read_kind, address, bytes, solver, tag, opts, DEFAULT_REGION_NAME, info)
                     }
                 }
 
                 Val::Symbolic(symbolic_addr) => {
                     self.check_overlap(symbolic_addr, ExecError::BadRead("Possible symbolic address overlap"), solver)?;
-                    self.read_symbolic(read_kind, address, bytes, solver, tag, opts, DEFAULT_REGION_NAME)
+                    self.read_symbolic(read_kind, address, bytes, solver, tag, opts, DEFAULT_REGION_NAME, info)
                 }
 
-                _ => Err(ExecError::Type("Non bitvector address in read".to_string(), SourceLoc::unknown())),
+                _ => Err(ExecError::Type("Non bitvector address in read".to_string(), info)),
             }
         } else {
-            Err(ExecError::SymbolicLength("read_symbolic", SourceLoc::unknown()))
+            Err(ExecError::SymbolicLength("read_symbolic", info))
         }
     }
 
     pub fn write(
         &mut self,
         write_kind: Val<B>,
-        address: Val<B>,
+        mut address: Val<B>,
         data: Val<B>,
         solver: &mut Solver<B>,
         tag: Option<Val<B>>,
         opts: WriteOpts,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         log!(log::MEMORY, &format!("Write: {:?} {:?} {:?} {:?}", write_kind, address, data, tag));
 
+        if !self.access_assertions.is_empty() || self.data_endianness != Endianness::Little {
+            let bytes = crate::primop_util::length_bits(&data, solver, info)? / 8;
+            self.check_access_assertions(&address, bytes, solver)?;
+            address = self.access_address(
+                address,
+                bytes,
+                self.data_endianness,
+                ExecError::BadWrite("Unaligned access"),
+                solver,
+                info,
+            )?
+        }
+
         match address {
             Val::Bits(concrete_addr) => {
                 for region in self.regions.iter_mut() {
                     match region {
                         Region::Custom(range, contents) if range.contains(&concrete_addr.lower_u64()) => {
-                            return contents.write(write_kind, concrete_addr.lower_u64(), data, solver, tag)
+                            return contents.write(write_kind, concrete_addr.lower_u64(), data, solver, tag, info)
                         }
 
                         _ => continue,
                     }
                 }
 
-                self.write_symbolic(write_kind, address, data, solver, tag, opts, DEFAULT_REGION_NAME)
+                self.write_symbolic(write_kind, address, data, solver, tag, opts, DEFAULT_REGION_NAME, info)
             }
 
             Val::Symbolic(symbolic_addr) => {
                 self.check_overlap(symbolic_addr, ExecError::BadWrite("possible symbolic address overlap"), solver)?;
-                self.write_symbolic(write_kind, address, data, solver, tag, opts, DEFAULT_REGION_NAME)
+                self.write_symbolic(write_kind, address, data, solver, tag, opts, DEFAULT_REGION_NAME, info)
             }
 
-            _ => Err(ExecError::Type("Non bitvector address in write".to_string(), SourceLoc::unknown())),
+            _ => Err(ExecError::Type("Non bitvector address in write".to_string(), info)),
         }
     }
 
@@ -541,16 +972,18 @@ impl<B: BV> Memory<B> {
         address: Val<B>,
         tag: Val<B>,
         solver: &mut Solver<B>,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         log!(log::MEMORY, &format!("Write tag: {:?} {:?} {:?}", write_kind, address, tag));
 
-        self.write_symbolic_tag(write_kind, address, tag, solver)
+        self.write_symbolic_tag(write_kind, address, tag, solver, info)
     }
 
     /// The simplest read is to symbolically read a memory location. In
     /// that case we just return a fresh SMT bitvector of the appropriate
     /// size, and add a ReadMem event to the trace. For this we need the
     /// number of bytes to be non-symbolic.
+    #[allow(clippy::too_many_arguments)]
     fn read_symbolic(
         &self,
         read_kind: Val<B>,
@@ -560,6 +993,7 @@ impl<B: BV> Memory<B> {
         tag: bool,
         opts: ReadOpts,
         region: &'static str,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         use crate::smt::smtlib::*;
 
@@ -595,6 +1029,7 @@ impl<B: BV> Memory<B> {
             tag_value: tag_ir_value.clone(),
             opts,
             region,
+            info,
         });
 
         log!(log::MEMORY, &format!("Read symbolic: {} {:?}", value, tag_value));
@@ -612,6 +1047,7 @@ impl<B: BV> Memory<B> {
     /// others). Raises a type error if the data argument is not a
     /// bitvector with a length that is a multiple of 8. This should be
     /// guaranteed by the Sail type system.
+    #[allow(clippy::too_many_arguments)]
     fn write_symbolic(
         &mut self,
         write_kind: Val<B>,
@@ -621,12 +1057,13 @@ impl<B: BV> Memory<B> {
         tag: Option<Val<B>>,
         opts: WriteOpts,
         region: &'static str,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         use crate::smt::smtlib::*;
 
-        let data_length = crate::primop_util::length_bits(&data, solver, SourceLoc::unknown())?;
+        let data_length = crate::primop_util::length_bits(&data, solver, info)?;
         if data_length % 8 != 0 {
-            return Err(ExecError::Type(format!("write_symbolic {:?}", &data_length), SourceLoc::unknown()));
+            return Err(ExecError::Type(format!("write_symbolic {:?}", &data_length), info));
         };
         let bytes = data_length / 8;
 
@@ -636,7 +1073,17 @@ impl<B: BV> Memory<B> {
             Some(c) => c.symbolic_write(&self.regions, solver, value, &write_kind, &address, &data, bytes, &tag, &opts),
             None => (),
         };
-        solver.add_event(Event::WriteMem { value, write_kind, address, data, bytes, tag_value: tag, opts, region });
+        solver.add_event(Event::WriteMem {
+            value,
+            write_kind,
+            address,
+            data,
+            bytes,
+            tag_value: tag,
+            opts,
+            region,
+            info,
+        });
 
         Ok(Val::Symbolic(value))
     }
@@ -647,6 +1094,7 @@ impl<B: BV> Memory<B> {
         address: Val<B>,
         tag: Val<B>,
         solver: &mut Solver<B>,
+        info: SourceLoc,
     ) -> Result<Val<B>, ExecError> {
         use crate::smt::smtlib::*;
 
@@ -665,6 +1113,7 @@ impl<B: BV> Memory<B> {
             tag_value: Some(tag),
             opts: WriteOpts::default(),
             region: DEFAULT_REGION_NAME,
+            info,
         });
 
         Ok(Val::Symbolic(value))
@@ -750,17 +1199,6 @@ pub fn smt_address_constraint<B: BV>(
         })
 }
 
-fn reverse_endianness(bytes: &mut [u8]) {
-    if bytes.len() <= 2 {
-        bytes.reverse()
-    } else {
-        let (bytes_upper, bytes_lower) = bytes.split_at_mut(bytes.len() / 2);
-        reverse_endianness(bytes_upper);
-        reverse_endianness(bytes_lower);
-        bytes.rotate_left(bytes.len() / 2)
-    }
-}
-
 #[allow(clippy::too_many_arguments)]
 fn read_constrained<B: BV>(
     range: &Range<Address>,
@@ -772,6 +1210,7 @@ fn read_constrained<B: BV>(
     tag: bool,
     opts: ReadOpts,
     region: &'static str,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
     let constrained = generator(solver);
     if address == range.start && address + bytes as u64 == range.end {
@@ -783,6 +1222,7 @@ fn read_constrained<B: BV>(
             tag_value: None,
             opts,
             region,
+            info,
         });
         if tag {
             Ok(make_bv_bit_pair(Val::Symbolic(constrained), Val::Bits(B::zeros(1))))
@@ -794,8 +1234,16 @@ fn read_constrained<B: BV>(
     }
 }
 
+/// The physical address of a BE-32 byte or halfword access to a
+/// symbolic address
+fn flip_address<B: BV>(address: Sym, flip: u64, solver: &mut Solver<B>, info: SourceLoc) -> Val<B> {
+    Val::Symbolic(solver.define_const(Exp::Bvxor(Box::new(Exp::Var(address)), Box::new(bits64(flip, 64))), info))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn read_concrete<B: BV>(
     memory: &HashMap<Address, u8>,
+    endianness: Endianness,
     read_kind: Val<B>,
     address: Address,
     bytes: u32,
@@ -803,14 +1251,13 @@ fn read_concrete<B: BV>(
     tag: bool,
     opts: ReadOpts,
     region: &'static str,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
     let mut byte_vec: Vec<u8> = Vec::with_capacity(bytes as usize);
-    for i in address..(address + u64::from(bytes)) {
+    for i in byte_order(endianness, address, bytes) {
         byte_vec.push(*memory.get(&i).unwrap_or(&0))
     }
 
-    reverse_endianness(&mut byte_vec);
-
     if byte_vec.len() <= 8 {
         log!(log::MEMORY, &format!("Read concrete: {:?}", byte_vec));
 
@@ -823,6 +1270,7 @@ fn read_concrete<B: BV>(
             tag_value: None,
             opts,
             region,
+            info,
         });
         if tag {
             Ok(make_bv_bit_pair(Val::Bits(B::from_bytes(&byte_vec)), Val::Bits(B::zeros(1))))
@@ -834,3 +1282,254 @@ fn read_concrete<B: BV>(
         Err(ExecError::BadRead("Concrete read more than 8 bytes"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::smt::smtlib::Ty;
+    use crate::smt::{Config, Context};
+
+    #[derive(Clone)]
+    struct Latch {
+        value: u64,
+    }
+
+    impl RegionCallbacks<B64> for Latch {
+        fn read(&self, _address: Address, bytes: u32) -> Option<B64> {
+            Some(B64::new(self.value, bytes * 8))
+        }
+
+        fn write(&mut self, _address: Address, _bytes: u32, data: &Val<B64>) {
+            if let Val::Bits(bv) = data {
+                self.value = bv.lower_u64()
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "latch"
+        }
+
+        fn clone_dyn(&self) -> Box<dyn RegionCallbacks<B64>> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn byte_orders() {
+        assert_eq!(byte_order(Endianness::Little, 0x10, 4), vec![0x13, 0x12, 0x11, 0x10]);
+        assert_eq!(byte_order(Endianness::Big, 0x10, 4), vec![0x10, 0x11, 0x12, 0x13]);
+        // BE-32 words are little-endian, but bytes and halfwords come
+        // from the other end of the word
+        assert_eq!(byte_order(Endianness::WordInvariantBig, 0x10, 4), vec![0x13, 0x12, 0x11, 0x10]);
+        assert_eq!(byte_order(Endianness::WordInvariantBig, 0x13, 1), vec![0x13]);
+        assert_eq!(byte_order(Endianness::WordInvariantBig, 0x12, 2), vec![0x13, 0x12]);
+        assert_eq!(
+            byte_order(Endianness::WordInvariantBig, 0x10, 8),
+            vec![0x13, 0x12, 0x11, 0x10, 0x17, 0x16, 0x15, 0x14]
+        );
+    }
+
+    #[test]
+    fn access_assertion_witness() -> Result<(), ExecError> {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let mut memory = Memory::new();
+        let template = crate::smt_parser::ExpParser::new().parse("(bvult addr #x0000000000001000)").unwrap();
+        memory.add_access_assertion(AccessAssertion::from_template("below", template).unwrap());
+
+        assert!(read_bytes(&memory, 0x800, 4, &mut solver).is_ok());
+
+        // Only one of the two possible addresses violates the
+        // assertion, so it must be the witness
+        let v = solver.declare_const(Ty::BitVec(64), SourceLoc::unknown());
+        let is = |bits| Box::new(Exp::Eq(Box::new(Exp::Var(v)), Box::new(bits64(bits, 64))));
+        solver.add(Def::Assert(Exp::Or(is(0x800), is(0x2000))));
+        let result = memory.read(
+            Val::Unit,
+            Val::Symbolic(v),
+            Val::I128(4),
+            &mut solver,
+            false,
+            ReadOpts::default(),
+            SourceLoc::unknown(),
+        );
+        match result {
+            Err(ExecError::AccessAssertion(name, witness)) => {
+                assert_eq!(name, "below");
+                assert_eq!(witness, "#x0000000000002000")
+            }
+            result => panic!("expected an access assertion violation, got {:?}", result),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn physical_addresses() {
+        assert_eq!(physical_address(Endianness::Little, 0x11, 2), Some(0x11));
+        assert_eq!(physical_address(Endianness::Big, 0x11, 4), Some(0x11));
+        // BE-32 bytes and halfwords come from the other end of the
+        // word, so they must be naturally aligned
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x10, 1), Some(0x13));
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x13, 1), Some(0x10));
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x10, 2), Some(0x12));
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x12, 2), Some(0x10));
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x11, 2), None);
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x10, 8), Some(0x10));
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x12, 4), None);
+        assert_eq!(physical_address(Endianness::WordInvariantBig, 0x10, 3), None);
+    }
+
+    fn read_bytes(
+        memory: &Memory<B64>,
+        address: u64,
+        bytes: i128,
+        solver: &mut Solver<B64>,
+    ) -> Result<Val<B64>, ExecError> {
+        let address = Val::Bits(B64::from_u64(address));
+        memory.read(Val::Unit, address, Val::I128(bytes), solver, false, ReadOpts::default(), SourceLoc::unknown())
+    }
+
+    fn last_access_address(solver: &Solver<B64>) -> Option<Val<B64>> {
+        solver.trace().to_vec().into_iter().find_map(|ev| match ev {
+            Event::ReadMem { address, .. } | Event::WriteMem { address, .. } => Some(address.clone()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn resolved_reads() -> Result<(), ExecError> {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let mut memory = Memory::new();
+        memory.set_read_resolver(ReadResolver::new(|address, _| match address {
+            0x2000 => Some(0xab),
+            0x3000 => Some(0x1234),
+            _ => None,
+        }));
+
+        let is_only = |solver: &mut Solver<B64>, value: &Val<B64>, bits: u64, len: u32| {
+            let Val::Symbolic(v) = value else { panic!("expected a symbolic read") };
+            solver.check_sat_with(&Exp::Neq(Box::new(Exp::Var(*v)), Box::new(bits64(bits, len)))) == SmtResult::Unsat
+        };
+
+        let last_region = |solver: &Solver<B64>| {
+            solver.trace().to_vec().into_iter().find_map(|ev| match ev {
+                Event::ReadMem { region, .. } => Some(*region),
+                _ => None,
+            })
+        };
+
+        let value = read_bytes(&memory, 0x2000, 1, &mut solver)?;
+        assert!(is_only(&mut solver, &value, 0xab, 8));
+        assert_eq!(last_region(&solver), Some(RESOLVED_REGION_NAME));
+        let value = read_bytes(&memory, 0x3000, 2, &mut solver)?;
+        assert!(is_only(&mut solver, &value, 0x1234, 16));
+
+        // The value must fit in the bytes read, rather than being
+        // truncated
+        assert!(matches!(read_bytes(&memory, 0x3000, 1, &mut solver), Err(ExecError::BadRead(_))));
+
+        let value = read_bytes(&memory, 0x4000, 1, &mut solver)?;
+        assert!(!is_only(&mut solver, &value, 0xab, 8));
+        assert_eq!(last_region(&solver), Some(DEFAULT_REGION_NAME));
+        Ok(())
+    }
+
+    #[test]
+    fn endian_accesses() -> Result<(), ExecError> {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let mut memory = Memory::new();
+        memory.add_concrete_region(
+            0x1000..0x1004,
+            [(0x1000, 0x11), (0x1001, 0x22), (0x1002, 0x33), (0x1003, 0x44)].into(),
+        );
+
+        assert_eq!(read_bytes(&memory, 0x1000, 4, &mut solver)?, Val::Bits(B64::new(0x44332211, 32)));
+
+        memory.set_endianness(Endianness::Big, Endianness::Big);
+        assert_eq!(read_bytes(&memory, 0x1000, 4, &mut solver)?, Val::Bits(B64::new(0x11223344, 32)));
+        assert_eq!(read_bytes(&memory, 0x1001, 2, &mut solver)?, Val::Bits(B64::new(0x2233, 16)));
+        assert_eq!(memory.read_initial(0x1000, 2)?, Val::Bits(B64::new(0x1122, 16)));
+
+        memory.set_endianness(Endianness::WordInvariantBig, Endianness::WordInvariantBig);
+        assert_eq!(read_bytes(&memory, 0x1000, 4, &mut solver)?, Val::Bits(B64::new(0x44332211, 32)));
+        assert_eq!(read_bytes(&memory, 0x1000, 1, &mut solver)?, Val::Bits(B64::new(0x44, 8)));
+        assert_eq!(last_access_address(&solver), Some(Val::Bits(B64::from_u64(0x1003))));
+        assert_eq!(read_bytes(&memory, 0x1000, 2, &mut solver)?, Val::Bits(B64::new(0x4433, 16)));
+        assert_eq!(memory.read_initial(0x1002, 2)?, Val::Bits(B64::new(0x2211, 16)));
+        assert!(matches!(read_bytes(&memory, 0x1001, 2, &mut solver), Err(ExecError::BadRead(_))));
+        assert!(matches!(read_bytes(&memory, 0x1002, 4, &mut solver), Err(ExecError::BadRead(_))));
+
+        // Writes are moved to the same physical address as reads
+        let data = Val::Bits(B64::new(0x55, 8));
+        let address = Val::Bits(B64::from_u64(0x1000));
+        memory.write(
+            Val::Unit,
+            address,
+            data.clone(),
+            &mut solver,
+            None,
+            WriteOpts::default(),
+            SourceLoc::unknown(),
+        )?;
+        assert_eq!(last_access_address(&solver), Some(Val::Bits(B64::from_u64(0x1003))));
+
+        // A symbolic halfword address could be unaligned
+        let v = solver.declare_const(Ty::BitVec(64), SourceLoc::unknown());
+        let data = Val::Bits(B64::new(0x55, 16));
+        let result = memory.write(
+            Val::Unit,
+            Val::Symbolic(v),
+            data,
+            &mut solver,
+            None,
+            WriteOpts::default(),
+            SourceLoc::unknown(),
+        );
+        assert!(matches!(result, Err(ExecError::BadWrite(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn callback_region() -> Result<(), ExecError> {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let mut memory = Memory::new();
+        memory.add_callback_region(0x1000..0x1008, Box::new(Latch { value: 0x2a }));
+
+        let kind = Val::Unit;
+        let address = Val::Bits(B64::from_u64(0x1000));
+        let bytes = Val::I128(4);
+        let read = memory.read(
+            kind.clone(),
+            address.clone(),
+            bytes.clone(),
+            &mut solver,
+            false,
+            ReadOpts::default(),
+            SourceLoc::unknown(),
+        )?;
+        assert_eq!(read, Val::Bits(B64::new(0x2a, 32)));
+
+        let data = Val::Bits(B64::new(0x7, 32));
+        memory.write(
+            kind.clone(),
+            address.clone(),
+            data,
+            &mut solver,
+            None,
+            WriteOpts::default(),
+            SourceLoc::unknown(),
+        )?;
+        let read = memory.read(kind, address, bytes, &mut solver, false, ReadOpts::default(), SourceLoc::unknown())?;
+        assert_eq!(read, Val::Bits(B64::new(0x7, 32)));
+
+        let events = solver.trace().to_vec();
+        let accesses: Vec<_> = events.iter().filter(|ev| ev.is_memory_read_or_write()).collect();
+        assert_eq!(accesses.len(), 3);
+        assert!(accesses.iter().all(|ev| ev.is_callback() && ev.in_region("latch")));
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A cheap alias analysis for the byte ranges touched by memory
+//! accesses. Addresses are split into a symbolic base and a concrete
+//! offset by following the definitions in the trace, so accesses
+//! relative to the same base (e.g. a store to `[x0, #8]` followed by
+//! a load from `[x0, #12]`) can often be shown to overlap or not
+//! without asking the SMT solver. Anything the analysis cannot decide
+//! is reported as [Alias::May], and should be left to the solver.
+
+use std::collections::HashMap;
+
+use crate::bitvector::{bzhi_u64, BV};
+use crate::ir::Val;
+use crate::smt::smtlib::{Def, Exp};
+use crate::smt::{Event, Sym};
+
+/// How many definitions are followed when decomposing an address
+const DEPTH_LIMIT: usize = 32;
+
+/// Whether two memory accesses touch any of the same bytes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Alias {
+    /// The accesses overlap for every value of their addresses
+    Must,
+    /// The accesses never overlap
+    No,
+    /// The analysis could not decide
+    May,
+}
+
+pub struct AliasAnalysis<'ev> {
+    definitions: HashMap<Sym, &'ev Exp<Sym>>,
+}
+
+impl<'ev> AliasAnalysis<'ev> {
+    pub fn from_events<B: BV>(events: &'ev [Event<B>]) -> Self {
+        let mut definitions = HashMap::new();
+        for event in events {
+            if let Event::Smt(Def::DefineConst(v, exp), _, _) = event {
+                definitions.insert(*v, exp);
+            }
+        }
+        AliasAnalysis { definitions }
+    }
+
+    /// Split an address into an optional symbolic base and an offset
+    /// modulo 2^`width`, or `None` if it has some other form.
+    fn decompose(&self, exp: &Exp<Sym>, width: u32, depth: usize) -> Option<(Option<Sym>, u64)> {
+        use Exp::*;
+        if depth > DEPTH_LIMIT {
+            return None;
+        }
+        match exp {
+            Var(v) => match self.definitions.get(v) {
+                Some(def) => self.decompose(def, width, depth + 1).or(Some((Some(*v), 0))),
+                None => Some((Some(*v), 0)),
+            },
+            Bits64(bv) => Some((None, bv.lower_u64())),
+            Bits(bv) if bv.len() <= 64 => {
+                Some((None, bv.iter().enumerate().fold(0, |acc, (i, bit)| acc | ((*bit as u64) << i))))
+            }
+            Bvadd(lhs, rhs) => match (self.decompose(lhs, width, depth + 1)?, self.decompose(rhs, width, depth + 1)?) {
+                ((base, o1), (None, o2)) | ((None, o1), (base, o2)) => {
+                    Some((base, bzhi_u64(o1.wrapping_add(o2), width)))
+                }
+                _ => None,
+            },
+            Bvsub(lhs, rhs) => match (self.decompose(lhs, width, depth + 1)?, self.decompose(rhs, width, depth + 1)?) {
+                ((base, o1), (None, o2)) => Some((base, bzhi_u64(o1.wrapping_sub(o2), width))),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn decompose_val<B: BV>(&self, address: &Val<B>, width: u32) -> Option<(Option<Sym>, u64)> {
+        match address {
+            Val::Bits(bv) => Some((None, bzhi_u64(bv.lower_u64(), width))),
+            Val::Symbolic(v) => self.decompose(&Exp::Var(*v), width, 0),
+            _ => None,
+        }
+    }
+
+    /// Decide whether the byte ranges `[addr1, addr1 + bytes1)` and
+    /// `[addr2, addr2 + bytes2)` overlap, where the addresses are
+    /// `width` bits wide and the ranges do not wrap around the top of
+    /// the address space.
+    pub fn alias<B: BV>(&self, addr1: &Val<B>, bytes1: u32, addr2: &Val<B>, bytes2: u32, width: u32) -> Alias {
+        if width > 64 || bytes1 == 0 || bytes2 == 0 {
+            return Alias::May;
+        }
+        let (Some((base1, o1)), Some((base2, o2))) =
+            (self.decompose_val(addr1, width), self.decompose_val(addr2, width))
+        else {
+            return Alias::May;
+        };
+
+        if base1.is_none() && base2.is_none() {
+            let (o1, o2, bytes1, bytes2) = (o1 as u128, o2 as u128, bytes1 as u128, bytes2 as u128);
+            return if o1 < o2 + bytes2 && o2 < o1 + bytes1 { Alias::Must } else { Alias::No };
+        }
+
+        if base1 != base2 {
+            return Alias::May;
+        }
+
+        // With a common base the addresses differ by a constant
+        // modulo 2^width. Ranges which are disjoint modulo 2^width are
+        // disjoint whatever the base, but ones which overlap might not
+        // when only one of them wraps, unless they start at the same
+        // address.
+        let modulus = 1u128 << width;
+        let distance = (o2 as u128 + modulus - o1 as u128) % modulus;
+        if distance == 0 {
+            Alias::Must
+        } else if distance >= bytes1 as u128 && modulus - distance >= bytes2 as u128 {
+            Alias::No
+        } else {
+            Alias::May
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::smt::smtlib::bits64;
+    use crate::smt::DefAttrs;
+    use crate::source_loc::SourceLoc;
+
+    fn define(v: u32, exp: Exp<Sym>) -> Event<B64> {
+        Event::Smt(Def::DefineConst(Sym::from_u32(v), exp), DefAttrs::default(), SourceLoc::unknown())
+    }
+
+    fn offset(base: u32, offset: u64) -> Exp<Sym> {
+        Exp::Bvadd(Box::new(Exp::Var(Sym::from_u32(base))), Box::new(bits64(offset, 64)))
+    }
+
+    #[test]
+    fn aliasing() {
+        let events = vec![
+            define(1, offset(0, 8)),
+            define(2, offset(0, 12)),
+            define(3, offset(1, 4)),
+            define(4, Exp::Bvsub(Box::new(Exp::Var(Sym::from_u32(0))), Box::new(bits64(4, 64)))),
+        ];
+        let analysis = AliasAnalysis::from_events(&events);
+        let sym = |v| Val::<B64>::Symbolic(Sym::from_u32(v));
+
+        assert_eq!(analysis.alias(&sym(1), 4, &sym(2), 4, 64), Alias::No);
+        assert_eq!(analysis.alias(&sym(1), 8, &sym(2), 4, 64), Alias::May);
+        assert_eq!(analysis.alias(&sym(2), 4, &sym(3), 4, 64), Alias::Must);
+        assert_eq!(analysis.alias(&sym(4), 4, &sym(0), 4, 64), Alias::No);
+        assert_eq!(analysis.alias(&sym(4), 8, &sym(0), 4, 64), Alias::May);
+        assert_eq!(analysis.alias(&sym(0), 8, &sym(5), 8, 64), Alias::May);
+
+        let bits = |a| Val::Bits(B64::from_u64(a));
+        assert_eq!(analysis.alias(&bits(0x1000), 8, &bits(0x1004), 4, 64), Alias::Must);
+        assert_eq!(analysis.alias(&bits(0x1000), 4, &bits(0x1004), 4, 64), Alias::No);
+        assert_eq!(analysis.alias(&bits(0xffff_ffff_ffff_fffc), 8, &bits(0), 4, 64), Alias::No);
+        assert_eq!(analysis.alias(&bits(0x1000), 4, &sym(0), 4, 64), Alias::May)
+    }
+}
@@ -0,0 +1,263 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Hooks for running downstream analyses over each trace found by
+//! symbolic execution, without modifying the tools that produce
+//! them. A [TracePostProcessor] is registered with a set of
+//! [PostProcessors], which [collector] calls for every trace as it is
+//! collected, and which are finished once execution is over.
+//!
+//! With the `plugins` feature, post-processors can also be loaded
+//! from a dynamic library by [PostProcessors::load_plugin]. The
+//! library must export a function named by [PLUGIN_REGISTER_SYMBOL]
+//! of type [PluginRegister], declared as
+//!
+//! ```text
+//! #[no_mangle]
+//! pub fn isla_register_post_processors(processors: &mut PostProcessors<B129>) { ... }
+//! ```
+//!
+//! As Rust has no stable ABI, the library must be built with the same
+//! compiler and version of isla-lib as the tool loading it.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::bitvector::BV;
+use crate::error::ExecError;
+use crate::executor::{Collector, TraceError, TraceQueue};
+use crate::ir::SharedState;
+use crate::smt::{Event, EventOrder};
+
+/// An analysis run over each trace.
+pub trait TracePostProcessor<B: BV>: Send + Sync {
+    /// The name of the post-processor, used in error messages
+    fn name(&self) -> &str;
+
+    /// Process the events of a trace, in execution order. Traces are
+    /// processed by the worker threads as they are found, so this can
+    /// be called concurrently, and traces arrive in no particular
+    /// order.
+    fn process(&self, task_id: usize, events: &[Event<B>], shared_state: &SharedState<B>) -> Result<(), String>;
+
+    /// Called once every trace has been processed, e.g. to report
+    /// results accumulated over all of them.
+    fn finish(&self, _shared_state: &SharedState<B>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The name of the function a plugin library exports to register its
+/// post-processors.
+pub const PLUGIN_REGISTER_SYMBOL: &str = "isla_register_post_processors";
+
+/// The type of a plugin's registration function.
+pub type PluginRegister<B> = fn(&mut PostProcessors<B>);
+
+/// A set of registered post-processors, which are run in the order
+/// they were registered.
+pub struct PostProcessors<B> {
+    processors: Vec<Box<dyn TracePostProcessor<B>>>,
+    // Declared after the processors, so any code they contain is
+    // only unloaded once they have been dropped
+    #[cfg(feature = "plugins")]
+    libraries: Vec<libloading::Library>,
+}
+
+impl<B: BV> PostProcessors<B> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        PostProcessors {
+            processors: Vec::new(),
+            #[cfg(feature = "plugins")]
+            libraries: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, processor: Box<dyn TracePostProcessor<B>>) {
+        self.processors.push(processor)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.processors.iter().map(|processor| processor.name()).collect()
+    }
+
+    /// Run each post-processor over a trace. Every post-processor is
+    /// run even if an earlier one fails, and the first error is
+    /// returned along with the name of the post-processor.
+    pub fn process(
+        &self,
+        task_id: usize,
+        events: &[Event<B>],
+        shared_state: &SharedState<B>,
+    ) -> Result<(), (String, String)> {
+        let mut result = Ok(());
+        for processor in &self.processors {
+            if let Err(msg) = processor.process(task_id, events, shared_state) {
+                result = result.and(Err((processor.name().to_string(), msg)))
+            }
+        }
+        result
+    }
+
+    /// Finish each post-processor, returning the first error as for
+    /// [PostProcessors::process].
+    pub fn finish(&self, shared_state: &SharedState<B>) -> Result<(), (String, String)> {
+        let mut result = Ok(());
+        for processor in &self.processors {
+            if let Err(msg) = processor.finish(shared_state) {
+                result = result.and(Err((processor.name().to_string(), msg)))
+            }
+        }
+        result
+    }
+
+    /// Load a plugin library, and call its registration function to
+    /// register its post-processors.
+    #[cfg(feature = "plugins")]
+    pub fn load_plugin(&mut self, path: &Path) -> Result<(), String> {
+        // Safety: loading a library runs its initializers, and the
+        // registration function is trusted to have the type given by
+        // PluginRegister, as documented above
+        unsafe {
+            let library = libloading::Library::new(path)
+                .map_err(|e| format!("Could not load plugin {}: {}", path.display(), e))?;
+            let register: PluginRegister<B> = *library
+                .get::<PluginRegister<B>>(PLUGIN_REGISTER_SYMBOL.as_bytes())
+                .map_err(|e| format!("Plugin {} has no {} function: {}", path.display(), PLUGIN_REGISTER_SYMBOL, e))?;
+            register(self);
+            self.libraries.push(library)
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn load_plugin(&mut self, path: &Path) -> Result<(), String> {
+        Err(format!("Cannot load plugin {}, isla was built without the plugins feature", path.display()))
+    }
+}
+
+/// Wrap a collector for traces, so each trace it collects is also
+/// passed to `processors`. If a post-processor fails, the error is
+/// collected in addition to the trace.
+pub fn collector<'ir, B: BV>(
+    processors: Arc<PostProcessors<B>>,
+    inner: Box<Collector<'ir, B, TraceQueue<B>>>,
+) -> Box<Collector<'ir, B, TraceQueue<B>>> {
+    Box::new(move |tid, task_id, result, shared_state, solver, collected| {
+        if matches!(result, Ok(_) | Err((ExecError::Exit, _))) && !processors.is_empty() {
+            let events: Vec<Event<B>> = solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect();
+            if let Err((name, msg)) = processors.process(task_id, &events, shared_state) {
+                collected.push(Err(TraceError::PostProcess { name, msg }))
+            }
+        }
+        inner(tid, task_id, result, shared_state, solver, collected)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::bitvector::b64::B64;
+    use crate::ir::{IRTypeInfo, Symtab, Val};
+    use crate::source_loc::SourceLoc;
+
+    struct CountWrites(AtomicUsize);
+
+    impl<B: BV> TracePostProcessor<B> for CountWrites {
+        fn name(&self) -> &str {
+            "count-writes"
+        }
+
+        fn process(&self, _: usize, events: &[Event<B>], _: &SharedState<B>) -> Result<(), String> {
+            self.0.fetch_add(events.iter().filter(|ev| matches!(ev, Event::WriteReg(..))).count(), Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn finish(&self, _: &SharedState<B>) -> Result<(), String> {
+            Err(format!("{} writes", self.0.load(Ordering::Relaxed)))
+        }
+    }
+
+    struct RejectReads;
+
+    impl<B: BV> TracePostProcessor<B> for RejectReads {
+        fn name(&self) -> &str {
+            "reject-reads"
+        }
+
+        fn process(&self, _: usize, events: &[Event<B>], shared_state: &SharedState<B>) -> Result<(), String> {
+            match events.iter().find_map(|ev| if let Event::ReadReg(r, _, _, _) = ev { Some(*r) } else { None }) {
+                Some(r) => Err(format!("read of {}", shared_state.symtab.to_str(r))),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn post_processors() {
+        let ir = "register zR : %bv8\n";
+        let mut symtab = Symtab::new();
+        let defs = crate::ir_parse::parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let type_info = IRTypeInfo::new(&defs);
+        let r = symtab.lookup("zR");
+        let shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+
+        let mut processors = PostProcessors::new();
+        processors.register(Box::new(CountWrites(AtomicUsize::new(0))));
+        processors.register(Box::new(RejectReads));
+        assert_eq!(processors.names(), vec!["count-writes", "reject-reads"]);
+
+        let write = Event::WriteReg(r, Vec::new(), Val::Bits(B64::new(1, 8)), SourceLoc::unknown());
+        let read = Event::ReadReg(r, Vec::new(), Val::Bits(B64::new(1, 8)), SourceLoc::unknown());
+        assert!(processors.process(0, std::slice::from_ref(&write), &shared_state).is_ok());
+        assert_eq!(
+            processors.process(1, &[write, read], &shared_state),
+            Err(("reject-reads".to_string(), "read of zR".to_string()))
+        );
+        assert_eq!(processors.finish(&shared_state), Err(("count-writes".to_string(), "2 writes".to_string())))
+    }
+}
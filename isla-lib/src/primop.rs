@@ -48,6 +48,8 @@ use std::convert::{TryFrom, TryInto};
 use std::ops::{Not, Shl, Shr};
 use std::str::FromStr;
 
+use num_bigint::{BigInt, Sign};
+
 use crate::bitvector::b64::B64;
 use crate::bitvector::BV;
 use crate::error::ExecError;
@@ -127,6 +129,62 @@ macro_rules! binary_primop_copy {
     };
 }
 
+/// As `binary_primop_copy`, but for integer operations which can
+/// overflow. Sail integers are unbounded, so rather than wrapping at
+/// 128 bits an overflowing result, or any operation on an integer
+/// which is already outside that range, is computed using arbitrary
+/// precision and the SMT Int theory.
+macro_rules! checked_binary_primop {
+    ($f:ident, $name:expr, $concrete_op:path, $smt_op:path, $int_op:expr) => {
+        pub fn $f<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
+            match (x, y) {
+                (Val::I128(x), Val::I128(y)) => match $concrete_op(x, y) {
+                    Some(z) => Ok(Val::I128(z)),
+                    None => int_theory_binary($int_op, Val::I128(x), Val::I128(y), solver, info),
+                },
+                (x, y) if is_int_theory(&x, solver) || is_int_theory(&y, solver) => {
+                    int_theory_binary($int_op, x, y, solver, info)
+                }
+                (Val::Symbolic(x), Val::Symbolic(y)) => {
+                    solver.define_const($smt_op(Box::new(Exp::Var(x)), Box::new(Exp::Var(y))), info).into()
+                }
+                (Val::Symbolic(x), Val::I128(y)) => {
+                    solver.define_const($smt_op(Box::new(Exp::Var(x)), Box::new(smt_i128(y))), info).into()
+                }
+                (Val::I128(x), Val::Symbolic(y)) => {
+                    solver.define_const($smt_op(Box::new(smt_i128(x)), Box::new(Exp::Var(y))), info).into()
+                }
+                (x, y) => Err(ExecError::Type(format!("{} {:?} {:?}", $name, &x, &y), info)),
+            }
+        }
+    };
+}
+
+/// Integer comparisons, which use the SMT Int theory for integers
+/// outside the 128-bit range like `checked_binary_primop`.
+macro_rules! int_compare_primop {
+    ($f:ident, $name:expr, $concrete_op:path, $smt_op:path, $int_op:expr) => {
+        pub fn $f<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
+            match (x, y) {
+                (Val::I128(x), Val::I128(y)) => Ok(Val::Bool($concrete_op(&x, &y))),
+                (x, y) if is_int_theory(&x, solver) || is_int_theory(&y, solver) => {
+                    int_theory_binary($int_op, x, y, solver, info)
+                }
+                (Val::Symbolic(x), Val::Symbolic(y)) => {
+                    solver.define_const($smt_op(Box::new(Exp::Var(x)), Box::new(Exp::Var(y))), info).into()
+                }
+                (Val::Symbolic(x), Val::I128(y)) => {
+                    solver.define_const($smt_op(Box::new(Exp::Var(x)), Box::new(smt_i128(y))), info).into()
+                }
+                (Val::I128(x), Val::Symbolic(y)) => {
+                    solver.define_const($smt_op(Box::new(smt_i128(x)), Box::new(Exp::Var(y))), info).into()
+                }
+                (x, y) => Err(ExecError::Type(format!("{} {:?} {:?}", $name, &x, &y), info)),
+            }
+        }
+    };
+}
+
 macro_rules! binary_primop {
     ($f:ident, $name:expr, $unwrap:path, $wrap:path, $concrete_op:path, $smt_op:path, $to_symbolic:path) => {
         pub fn $f<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
@@ -246,7 +304,10 @@ fn i128_to_i64<B: BV>(x: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Res
             Ok(y) => Ok(Val::I64(y)),
             Err(_) => Err(ExecError::Overflow),
         },
-        Val::Symbolic(x) => solver.define_const(Exp::Extract(63, 0, Box::new(Exp::Var(x))), info).into(),
+        Val::BigInt(_) => Err(ExecError::Overflow),
+        Val::Symbolic(x) if !solver.is_int(x) => {
+            solver.define_const(Exp::Extract(63, 0, Box::new(Exp::Var(x))), info).into()
+        }
         _ => Err(ExecError::Type(format!("%i->%i64 {:?}", &x), info)),
     }
 }
@@ -360,12 +421,39 @@ pub(crate) fn op_signed<B: BV>(bits: Val<B>, solver: &mut Solver<B>, info: Sourc
 // Basic comparisons
 
 unary_primop_copy!(not_bool, "not".to_string(), Val::Bool, Val::Bool, bool::not, Exp::Not);
-binary_primop!(eq_int, "eq_int".to_string(), Val::I128, Val::Bool, i128::eq, Exp::Eq, smt_i128);
 binary_primop!(eq_bool, "eq_bool".to_string(), Val::Bool, Val::Bool, bool::eq, Exp::Eq, Exp::Bool);
-binary_primop!(lteq_int, "lteq".to_string(), Val::I128, Val::Bool, i128::le, Exp::Bvsle, smt_i128);
-binary_primop!(gteq_int, "gteq".to_string(), Val::I128, Val::Bool, i128::ge, Exp::Bvsge, smt_i128);
-binary_primop!(lt_int, "lt".to_string(), Val::I128, Val::Bool, i128::lt, Exp::Bvslt, smt_i128);
-binary_primop!(gt_int, "gt".to_string(), Val::I128, Val::Bool, i128::gt, Exp::Bvsgt, smt_i128);
+int_compare_primop!(lteq_int, "lteq", i128::le, Exp::Bvsle, IntBinary::Le);
+int_compare_primop!(gteq_int, "gteq", i128::ge, Exp::Bvsge, IntBinary::Ge);
+int_compare_primop!(lt_int, "lt", i128::lt, Exp::Bvslt, IntBinary::Lt);
+int_compare_primop!(gt_int, "gt", i128::gt, Exp::Bvsgt, IntBinary::Gt);
+
+pub(crate) fn eq_int<B: BV>(
+    x: Val<B>,
+    y: Val<B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Val<B>, ExecError> {
+    match (x, y) {
+        (Val::I128(x), Val::I128(y)) => Ok(Val::Bool(x == y)),
+        // Integers are only BigInts when they do not fit in an I128
+        (Val::I128(_), Val::BigInt(_)) | (Val::BigInt(_), Val::I128(_)) => Ok(Val::Bool(false)),
+        (Val::BigInt(x), Val::BigInt(y)) => Ok(Val::Bool(x == y)),
+        (x, y) if is_int_theory(&x, solver) || is_int_theory(&y, solver) => {
+            let (x, y) = (smt_int(&x, solver, info)?, smt_int(&y, solver, info)?);
+            solver.define_const(Exp::Eq(Box::new(x), Box::new(y)), info).into()
+        }
+        (Val::Symbolic(x), Val::Symbolic(y)) => {
+            solver.define_const(Exp::Eq(Box::new(Exp::Var(x)), Box::new(Exp::Var(y))), info).into()
+        }
+        (Val::Symbolic(x), Val::I128(y)) => {
+            solver.define_const(Exp::Eq(Box::new(Exp::Var(x)), Box::new(smt_i128(y))), info).into()
+        }
+        (Val::I128(x), Val::Symbolic(y)) => {
+            solver.define_const(Exp::Eq(Box::new(smt_i128(x)), Box::new(Exp::Var(y))), info).into()
+        }
+        (x, y) => Err(ExecError::Type(format!("eq_int {:?} {:?}", &x, &y), info)),
+    }
+}
 
 pub fn and_bool<B: BV>(lhs: Val<B>, rhs: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match (lhs, rhs) {
@@ -395,7 +483,15 @@ pub fn or_bool<B: BV>(lhs: Val<B>, rhs: Val<B>, solver: &mut Solver<B>, info: So
 
 fn abs_int<B: BV>(x: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match x {
-        Val::I128(x) => Ok(Val::I128(x.abs())),
+        Val::I128(x) => Ok(Val::int(BigInt::from(x.unsigned_abs()))),
+        Val::BigInt(x) if x.sign() == Sign::Minus => Ok(Val::BigInt(-x)),
+        Val::BigInt(x) => Ok(Val::BigInt(x)),
+        Val::Symbolic(x) if solver.is_int(x) => {
+            let x = Box::new(Exp::Var(x));
+            let negative = Exp::IntBinary(IntBinary::Lt, x.clone(), Box::new(Exp::Int(BigInt::from(0))));
+            let neg_x = Box::new(Exp::IntUnary(IntUnary::Neg, x.clone()));
+            solver.define_const(Exp::Ite(Box::new(negative), neg_x, x), info).into()
+        }
         Val::Symbolic(x) => {
             let y = solver.fresh();
             solver.add(Def::DefineConst(
@@ -412,6 +508,93 @@ fn abs_int<B: BV>(x: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<
     }
 }
 
+// Arbitrary-precision integers
+
+/// Apply an integer operation where at least one operand is outside
+/// the 128-bit range, either because an `I128` operation overflowed
+/// or because it is a `BigInt` or Int-sorted symbolic value.
+fn int_theory_binary<B: BV>(
+    op: IntBinary,
+    x: Val<B>,
+    y: Val<B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Val<B>, ExecError> {
+    use IntBinary::*;
+    if let (Some(x), Some(y)) = (concrete_int(&x), concrete_int(&y)) {
+        return Ok(match op {
+            Add => Val::int(x + y),
+            Sub => Val::int(x - y),
+            Mul => Val::int(x * y),
+            Le => Val::Bool(x <= y),
+            Lt => Val::Bool(x < y),
+            Ge => Val::Bool(x >= y),
+            Gt => Val::Bool(x > y),
+        });
+    }
+    let (x, y) = (smt_int(&x, solver, info)?, smt_int(&y, solver, info)?);
+    solver.define_const(Exp::IntBinary(op, Box::new(x), Box::new(y)), info).into()
+}
+
+/// Pick `x` if `op` holds between `x` and `y`, otherwise `y`, for
+/// integers using the Int theory.
+fn int_theory_select<B: BV>(
+    op: IntBinary,
+    x: Val<B>,
+    y: Val<B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Val<B>, ExecError> {
+    match int_theory_binary(op, x.clone(), y.clone(), solver, info)? {
+        Val::Bool(true) => Ok(x),
+        Val::Bool(false) => Ok(y),
+        Val::Symbolic(b) => {
+            let (x, y) = (smt_int(&x, solver, info)?, smt_int(&y, solver, info)?);
+            solver.define_const(Exp::Ite(Box::new(Exp::Var(b)), Box::new(x), Box::new(y)), info).into()
+        }
+        result => Err(ExecError::Type(format!("int_theory_select {:?}", &result), info)),
+    }
+}
+
+/// The unsigned value of a concrete bitvector, which unlike
+/// [BV::unsigned] works for any width.
+fn big_unsigned<B: BV>(bits: B) -> BigInt {
+    bits.to_vec().iter().rev().fold(BigInt::from(0), |n, bit| (n << 1) + u8::from(*bit))
+}
+
+/// Divide with rounding towards zero, or Euclidean division if
+/// `euclid` is true, returning the quotient and remainder.
+fn big_div_rem(x: BigInt, y: BigInt, euclid: bool) -> (BigInt, BigInt) {
+    let (q, r) = (&x / &y, &x % &y);
+    if euclid && r.sign() == Sign::Minus {
+        if y.sign() == Sign::Plus {
+            (q - 1, r + y)
+        } else {
+            (q + 1, r - y)
+        }
+    } else {
+        (q, r)
+    }
+}
+
+/// As `binary_primop_copy` for the integer division primops, but
+/// computing the result with arbitrary precision when either operand
+/// is a concrete `BigInt`.
+macro_rules! int_div_primop {
+    ($f:ident, $i128_f:ident, $euclid:expr, $remainder:expr) => {
+        fn $f<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
+            match (&x, &y) {
+                (Val::BigInt(_), Val::I128(_) | Val::BigInt(_)) | (Val::I128(_), Val::BigInt(_)) => {
+                    let (x, y) = (concrete_int(&x).unwrap(), concrete_int(&y).unwrap());
+                    let (q, r) = big_div_rem(x, y, $euclid);
+                    Ok(Val::int(if $remainder { r } else { q }))
+                }
+                _ => $i128_f(x, y, solver, info),
+            }
+        }
+    };
+}
+
 // Arithmetic operations
 
 fn ediv_i128<V: Clone>(x: Box<Exp<V>>, y: Box<Exp<V>>) -> Exp<V> {
@@ -439,12 +622,24 @@ fn emod_i128<V: Clone>(x: Box<Exp<V>>, y: Box<Exp<V>>) -> Exp<V> {
     )
 }
 
-binary_primop_copy!(sub_int, "sub_int".to_string(), Val::I128, Val::I128, i128::wrapping_sub, Exp::Bvsub, smt_i128);
-binary_primop_copy!(mult_int, "mult_int".to_string(), Val::I128, Val::I128, i128::wrapping_mul, Exp::Bvmul, smt_i128);
-unary_primop_copy!(neg_int, "neg_int".to_string(), Val::I128, Val::I128, i128::wrapping_neg, Exp::Bvneg);
-binary_primop_copy!(tdiv_int, "tdiv_int".to_string(), Val::I128, Val::I128, i128::wrapping_div, Exp::Bvsdiv, smt_i128);
+checked_binary_primop!(sub_int, "sub_int", i128::checked_sub, Exp::Bvsub, IntBinary::Sub);
+checked_binary_primop!(mult_int, "mult_int", i128::checked_mul, Exp::Bvmul, IntBinary::Mul);
+
+pub fn neg_int<B: BV>(x: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
+    match x {
+        Val::I128(x) => Ok(Val::int(-BigInt::from(x))),
+        Val::BigInt(x) => Ok(Val::int(-x)),
+        Val::Symbolic(x) if solver.is_int(x) => {
+            solver.define_const(Exp::IntUnary(IntUnary::Neg, Box::new(Exp::Var(x))), info).into()
+        }
+        Val::Symbolic(x) => solver.define_const(Exp::Bvneg(Box::new(Exp::Var(x))), info).into(),
+        _ => Err(ExecError::Type(format!("neg_int {:?}", &x), info)),
+    }
+}
+
+binary_primop_copy!(tdiv_i128, "tdiv_int".to_string(), Val::I128, Val::I128, i128::wrapping_div, Exp::Bvsdiv, smt_i128);
 binary_primop_copy!(
-    ediv_int,
+    ediv_i128_primop,
     "ediv_int".to_string(),
     Val::I128,
     Val::I128,
@@ -452,9 +647,9 @@ binary_primop_copy!(
     ediv_i128,
     smt_i128
 );
-binary_primop_copy!(tmod_int, "tmod_int".to_string(), Val::I128, Val::I128, i128::wrapping_rem, Exp::Bvsrem, smt_i128);
+binary_primop_copy!(tmod_i128, "tmod_int".to_string(), Val::I128, Val::I128, i128::wrapping_rem, Exp::Bvsrem, smt_i128);
 binary_primop_copy!(
-    emod_int,
+    emod_i128_primop,
     "emod_int".to_string(),
     Val::I128,
     Val::I128,
@@ -462,7 +657,43 @@ binary_primop_copy!(
     emod_i128,
     smt_i128
 );
-binary_primop_copy!(shl_int, "shl_int".to_string(), Val::I128, Val::I128, i128::shl, Exp::Bvshl, smt_i128);
+int_div_primop!(tdiv_int, tdiv_i128, false, false);
+int_div_primop!(ediv_int, ediv_i128_primop, true, false);
+int_div_primop!(tmod_int, tmod_i128, false, true);
+int_div_primop!(emod_int, emod_i128_primop, true, true);
+
+/// Shift left, as multiplication by a power of two, so shifting any
+/// set bits (or the sign) out is an overflow.
+fn checked_shl_i128(x: i128, y: i128) -> Option<i128> {
+    let y = u32::try_from(y).ok().filter(|y| *y < 128)?;
+    let shifted = x << y;
+    if shifted >> y == x {
+        Some(shifted)
+    } else {
+        None
+    }
+}
+
+fn shl_int<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
+    match (x, y) {
+        (Val::I128(x), Val::I128(y)) if checked_shl_i128(x, y).is_some() => {
+            Ok(Val::I128(checked_shl_i128(x, y).unwrap()))
+        }
+        // Shifting an Int-theory value by a concrete amount is
+        // multiplication by a power of two
+        (x, Val::I128(y)) if y >= 0 && (matches!(x, Val::I128(_)) || is_int_theory(&x, solver)) => {
+            let shift = usize::try_from(y).map_err(|_| ExecError::Overflow)?;
+            int_theory_binary(IntBinary::Mul, x, Val::int(BigInt::from(1) << shift), solver, info)
+        }
+        (Val::Symbolic(x), Val::Symbolic(y)) if !solver.is_int(x) && !solver.is_int(y) => {
+            solver.define_const(Exp::Bvshl(Box::new(Exp::Var(x)), Box::new(Exp::Var(y))), info).into()
+        }
+        (Val::I128(x), Val::Symbolic(y)) if !solver.is_int(y) => {
+            solver.define_const(Exp::Bvshl(Box::new(smt_i128(x)), Box::new(Exp::Var(y))), info).into()
+        }
+        (x, y) => Err(ExecError::Type(format!("shl_int {:?} {:?}", &x, &y), info)),
+    }
+}
 binary_primop_copy!(shr_int, "shr_int".to_string(), Val::I128, Val::I128, i128::shr, Exp::Bvashr, smt_i128);
 binary_primop_copy!(shl_mach_int, "shl_mach_int".to_string(), Val::I64, Val::I64, i64::shl, Exp::Bvshl, smt_i64);
 binary_primop_copy!(shr_mach_int, "shr_mach_int".to_string(), Val::I64, Val::I64, i64::shr, Exp::Bvashr, smt_i64);
@@ -475,6 +706,9 @@ pub(crate) fn add_int<B: BV>(
     info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
     match (x, y) {
+        (x, y) if is_int_theory(&x, solver) || is_int_theory(&y, solver) => {
+            int_theory_binary(IntBinary::Add, x, y, solver, info)
+        }
         (Val::Symbolic(x), Val::Symbolic(y)) => {
             solver.define_const(Exp::Bvadd(Box::new(Exp::Var(x)), Box::new(Exp::Var(y))), info).into()
         }
@@ -492,7 +726,10 @@ pub(crate) fn add_int<B: BV>(
                 Ok(Val::Symbolic(y))
             }
         }
-        (Val::I128(x), Val::I128(y)) => Ok(Val::I128(i128::wrapping_add(x, y))),
+        (Val::I128(x), Val::I128(y)) => match x.checked_add(y) {
+            Some(z) => Ok(Val::I128(z)),
+            None => int_theory_binary(IntBinary::Add, Val::I128(x), Val::I128(y), solver, info),
+        },
         (x, y) => Err(ExecError::Type(format!("add_int {:?} {:?}", &x, &y), info)),
     }
 }
@@ -509,6 +746,9 @@ macro_rules! symbolic_compare {
 fn max_int<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match (x, y) {
         (Val::I128(x), Val::I128(y)) => Ok(Val::I128(i128::max(x, y))),
+        (x, y) if is_int_theory(&x, solver) || is_int_theory(&y, solver) => {
+            int_theory_select(IntBinary::Gt, x, y, solver, info)
+        }
         (Val::I128(x), Val::Symbolic(y)) => symbolic_compare!(Exp::Bvsgt, smt_i128(x), Exp::Var(y), solver),
         (Val::Symbolic(x), Val::I128(y)) => symbolic_compare!(Exp::Bvsgt, Exp::Var(x), smt_i128(y), solver),
         (Val::Symbolic(x), Val::Symbolic(y)) => symbolic_compare!(Exp::Bvsgt, Exp::Var(x), Exp::Var(y), solver),
@@ -519,6 +759,9 @@ fn max_int<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc)
 fn min_int<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match (x, y) {
         (Val::I128(x), Val::I128(y)) => Ok(Val::I128(i128::min(x, y))),
+        (x, y) if is_int_theory(&x, solver) || is_int_theory(&y, solver) => {
+            int_theory_select(IntBinary::Lt, x, y, solver, info)
+        }
         (Val::I128(x), Val::Symbolic(y)) => symbolic_compare!(Exp::Bvslt, smt_i128(x), Exp::Var(y), solver),
         (Val::Symbolic(x), Val::I128(y)) => symbolic_compare!(Exp::Bvslt, Exp::Var(x), smt_i128(y), solver),
         (Val::Symbolic(x), Val::Symbolic(y)) => symbolic_compare!(Exp::Bvslt, Exp::Var(x), Exp::Var(y), solver),
@@ -528,22 +771,38 @@ fn min_int<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc)
 
 fn pow2<B: BV>(x: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match x {
-        Val::I128(x) => Ok(Val::I128(1 << x)),
-        Val::Symbolic(x) => solver.define_const(Exp::Bvshl(Box::new(smt_i128(1)), Box::new(Exp::Var(x))), info).into(),
+        Val::I128(x) => match checked_shl_i128(1, x) {
+            Some(y) => Ok(Val::I128(y)),
+            None => Ok(Val::BigInt(BigInt::from(1) << usize::try_from(x).map_err(|_| ExecError::Overflow)?)),
+        },
+        Val::Symbolic(x) if !solver.is_int(x) => {
+            solver.define_const(Exp::Bvshl(Box::new(smt_i128(1)), Box::new(Exp::Var(x))), info).into()
+        }
         _ => Err(ExecError::Type(format!("pow2 {:?}", &x), info)),
     }
 }
 
 fn pow_int<B: BV>(x: Val<B>, y: Val<B>, _solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match (x, y) {
-        (Val::I128(x), Val::I128(y)) => Ok(Val::I128(x.pow(y.try_into().map_err(|_| ExecError::Overflow)?))),
+        (Val::I128(x), Val::I128(y)) => {
+            let y = y.try_into().map_err(|_| ExecError::Overflow)?;
+            Ok(x.checked_pow(y).map(Val::I128).unwrap_or_else(|| Val::BigInt(BigInt::from(x).pow(y))))
+        }
+        (Val::BigInt(x), Val::I128(y)) => Ok(Val::BigInt(x.pow(y.try_into().map_err(|_| ExecError::Overflow)?))),
         (x, y) => Err(ExecError::Type(format!("pow_int {:?} {:?}", &x, &y), info)),
     }
 }
 
 fn sub_nat<B: BV>(x: Val<B>, y: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match (x, y) {
-        (Val::I128(x), Val::I128(y)) => Ok(Val::I128(i128::max(x - y, 0))),
+        (Val::I128(x), Val::I128(y)) => match x.checked_sub(y) {
+            Some(z) => Ok(Val::I128(i128::max(z, 0))),
+            None => int_theory_select(IntBinary::Gt, Val::int(BigInt::from(x) - y), Val::I128(0), solver, info),
+        },
+        (x, y) if is_int_theory(&x, solver) || is_int_theory(&y, solver) => {
+            let z = int_theory_binary(IntBinary::Sub, x, y, solver, info)?;
+            int_theory_select(IntBinary::Gt, z, Val::I128(0), solver, info)
+        }
         (Val::I128(x), Val::Symbolic(y)) => {
             symbolic_compare!(Exp::Bvsgt, Exp::Bvsub(Box::new(smt_i128(x)), Box::new(Exp::Var(y))), smt_i128(0), solver)
         }
@@ -1080,12 +1339,16 @@ fn sail_truncate_lsb<B: BV>(
 fn sail_unsigned<B: BV>(bits: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     let bits = replace_mixed_bits(bits, solver, info)?;
     match bits {
-        Val::Bits(bits) => Ok(Val::I128(bits.unsigned())),
+        Val::Bits(bits) if bits.len() < 128 => Ok(Val::I128(bits.unsigned())),
+        Val::Bits(bits) => Ok(Val::int(big_unsigned(bits))),
         Val::Symbolic(bits) => match solver.length(bits) {
-            Some(length) => {
-                assert!(length < 128);
+            Some(length) if length < 128 => {
                 solver.define_const(Exp::ZeroExtend(128 - length, Box::new(Exp::Var(bits))), info).into()
             }
+            Some(length) => {
+                let unsigned = Exp::ZeroExtend(1, Box::new(Exp::Var(bits)));
+                solver.define_const(Exp::IntUnary(IntUnary::FromSigned(length + 1), Box::new(unsigned)), info).into()
+            }
             None => Err(ExecError::Type(format!("sail_unsigned (solver cannot determine length) {:?}", &bits), info)),
         },
         _ => Err(ExecError::Type(format!("sail_unsigned {:?}", &bits), info)),
@@ -1095,12 +1358,19 @@ fn sail_unsigned<B: BV>(bits: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -
 fn sail_signed<B: BV>(bits: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     let bits = replace_mixed_bits(bits, solver, info)?;
     match bits {
-        Val::Bits(bits) => Ok(Val::I128(bits.signed())),
+        Val::Bits(bits) if bits.len() <= 128 => Ok(Val::I128(bits.signed())),
+        Val::Bits(bits) if bits.slice(bits.len() - 1, 1) == Some(B::BIT_ONE) => {
+            Ok(Val::int(big_unsigned(bits) - (BigInt::from(1) << bits.len())))
+        }
+        Val::Bits(bits) => Ok(Val::int(big_unsigned(bits))),
         Val::Symbolic(bits) => match solver.length(bits) {
-            Some(length) => {
-                assert!(length < 128);
+            Some(length) if length < 128 => {
                 solver.define_const(Exp::SignExtend(128 - length, Box::new(Exp::Var(bits))), info).into()
             }
+            Some(128) => Ok(Val::Symbolic(bits)),
+            Some(length) => {
+                solver.define_const(Exp::IntUnary(IntUnary::FromSigned(length), Box::new(Exp::Var(bits))), info).into()
+            }
             None => Err(ExecError::Type(format!("sail_signed (solver cannot determine length) {:?}", &bits), info)),
         },
         _ => Err(ExecError::Type(format!("sail_signed {:?}", &bits), info)),
@@ -1790,6 +2060,21 @@ fn get_slice_int_internal<B: BV>(
     info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
     match length {
+        Val::I128(length) if is_int_theory(&n, solver) => match (from, concrete_int(&n)) {
+            (Val::I128(from), Some(n)) if length < 128 && length <= B::MAX_WIDTH as i128 && from >= 0 => {
+                let mask = (BigInt::from(1) << length as usize) - 1;
+                let bits = i128::try_from((n >> from as usize) & mask).unwrap();
+                Ok(Val::Bits(B::get_slice_int(length as u32, bits, 0)))
+            }
+            (Val::I128(from), _) => {
+                // Take the integer modulo 2^(from + length) as a
+                // bitvector, which contains the slice
+                let width = u32::try_from(from + length).map_err(|_| ExecError::Overflow)?;
+                let bits = Exp::IntUnary(IntUnary::ToBits(width), Box::new(smt_int(&n, solver, info)?));
+                slice!(width, bits, Val::<B>::I128(from), length, solver, info)
+            }
+            (from, _) => Err(ExecError::Type(format!("get_slice_int {:?} {:?}", &n, &from), info)),
+        },
         Val::I128(length) => match n {
             Val::Symbolic(n) => slice!(128, Exp::Var(n), from, length, solver, info),
             Val::I128(n) => match from {
@@ -1842,21 +2127,57 @@ fn eq_string<B: BV>(lhs: Val<B>, rhs: Val<B>, _: &mut Solver<B>, info: SourceLoc
 fn concat_str<B: BV>(lhs: Val<B>, rhs: Val<B>, _: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match (lhs, rhs) {
         (Val::String(lhs), Val::String(rhs)) => Ok(Val::String(format!("{}{}", lhs, rhs))),
+        // An undefined string poisons any message it is part of
+        (Val::Poison, Val::String(_)) | (Val::String(_), Val::Poison) | (Val::Poison, Val::Poison) => Ok(Val::Poison),
         (lhs, rhs) => Err(ExecError::Type(format!("concat_str {:?} {:?}", &lhs, &rhs), info)),
     }
 }
 
+// Integers which fit in 128 bits are printed in two's complement, as
+// hex_str always has. A BigInt has no fixed width to take the
+// complement in, so a negative one is printed with a sign instead.
+fn hex_string(n: i128, upper: bool) -> String {
+    if upper {
+        format!("0x{:X}", n)
+    } else {
+        format!("0x{:x}", n)
+    }
+}
+
+fn big_hex_string(n: &BigInt, upper: bool) -> String {
+    let sign = if n.sign() == Sign::Minus { "-" } else { "" };
+    if upper {
+        format!("{}0x{:X}", sign, n.magnitude())
+    } else {
+        format!("{}0x{:x}", sign, n.magnitude())
+    }
+}
+
 fn hex_str<B: BV>(n: Val<B>, _: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match n {
-        Val::I128(n) => Ok(Val::String(format!("0x{:x}", n))),
+        Val::I128(n) => Ok(Val::String(hex_string(n, false))),
+        Val::I64(n) => Ok(Val::String(hex_string(n as i128, false))),
+        Val::BigInt(n) => Ok(Val::String(big_hex_string(&n, false))),
         Val::Symbolic(v) => Ok(Val::String(format!("0x[{}]", v))),
         _ => Err(ExecError::Type(format!("hex_str {:?}", &n), info)),
     }
 }
 
+fn hex_str_upper<B: BV>(n: Val<B>, _: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
+    match n {
+        Val::I128(n) => Ok(Val::String(hex_string(n, true))),
+        Val::I64(n) => Ok(Val::String(hex_string(n as i128, true))),
+        Val::BigInt(n) => Ok(Val::String(big_hex_string(&n, true))),
+        Val::Symbolic(v) => Ok(Val::String(format!("0x[{}]", v))),
+        _ => Err(ExecError::Type(format!("hex_str_upper {:?}", &n), info)),
+    }
+}
+
 fn dec_str<B: BV>(n: Val<B>, _: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match n {
         Val::I128(n) => Ok(Val::String(format!("{}", n))),
+        Val::I64(n) => Ok(Val::String(format!("{}", n))),
+        Val::BigInt(n) => Ok(Val::String(format!("{}", n))),
         Val::Symbolic(v) => Ok(Val::String(format!("[{}]", v))),
         _ => Err(ExecError::Type(format!("dec_str {:?}", &n), info)),
     }
@@ -1886,6 +2207,10 @@ pub fn eq_anything<B: BV>(
     info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
     match (replace_mixed_bits(lhs, solver, info)?, replace_mixed_bits(rhs, solver, info)?) {
+        (
+            lhs @ (Val::I128(_) | Val::BigInt(_) | Val::Symbolic(_)),
+            rhs @ (Val::I128(_) | Val::BigInt(_) | Val::Symbolic(_)),
+        ) if is_int_theory(&lhs, solver) || is_int_theory(&rhs, solver) => eq_int(lhs, rhs, solver, info),
         (Val::Symbolic(lhs), Val::Symbolic(rhs)) => {
             solver.define_const(Exp::Eq(Box::new(Exp::Var(lhs)), Box::new(Exp::Var(rhs))), info).into()
         }
@@ -2010,7 +2335,7 @@ fn string_of_bits<B: BV>(bv: Val<B>, _: &mut Solver<B>, info: SourceLoc) -> Resu
 
 fn decimal_string_of_segment<B: BV>(segment: &BitsSegment<B>) -> String {
     match segment {
-        BitsSegment::Concrete(bv) => format!("{}", bv),
+        BitsSegment::Concrete(bv) => format!("{}", bv.unsigned()),
         BitsSegment::Symbolic(v) => format!("v{}", v),
     }
 }
@@ -2029,6 +2354,8 @@ fn decimal_string_of_bits<B: BV>(bv: Val<B>, _: &mut Solver<B>, info: SourceLoc)
 fn string_of_int<B: BV>(n: Val<B>, _: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
     match n {
         Val::I128(n) => Ok(Val::String(format!("{}", n))),
+        Val::I64(n) => Ok(Val::String(format!("{}", n))),
+        Val::BigInt(n) => Ok(Val::String(format!("{}", n))),
         Val::Symbolic(v) => Ok(Val::String(format!("v{}", v))),
         other => Err(ExecError::Type(format!("string_of_int {:?}", &other), info)),
     }
@@ -2199,36 +2526,36 @@ fn read_mem<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::default())
+    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::default(), info)
 }
 
 fn read_mem_ifetch<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::ifetch())
+    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::ifetch(), info)
 }
 
 fn read_mem_exclusive<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::exclusive())
+    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::exclusive(), info)
 }
 
 fn read_memt<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory().read(args[0].clone(), args[1].clone(), args[2].clone(), solver, true, ReadOpts::default())
+    frame.memory().read(args[0].clone(), args[1].clone(), args[2].clone(), solver, true, ReadOpts::default(), info)
 }
 
 fn bad_read<B: BV>(_: Val<B>, _: &mut Solver<B>, _: SourceLoc) -> Result<Val<B>, ExecError> {
@@ -2239,25 +2566,41 @@ fn write_mem<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory_mut().write(args[0].clone(), args[2].clone(), args[4].clone(), solver, None, WriteOpts::default())
+    frame.memory_mut().write(
+        args[0].clone(),
+        args[2].clone(),
+        args[4].clone(),
+        solver,
+        None,
+        WriteOpts::default(),
+        info,
+    )
 }
 
 fn write_mem_exclusive<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory_mut().write(args[0].clone(), args[2].clone(), args[4].clone(), solver, None, WriteOpts::exclusive())
+    frame.memory_mut().write(
+        args[0].clone(),
+        args[2].clone(),
+        args[4].clone(),
+        solver,
+        None,
+        WriteOpts::exclusive(),
+        info,
+    )
 }
 
 fn write_memt<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
     frame.memory_mut().write(
         args[0].clone(),
@@ -2266,6 +2609,7 @@ fn write_memt<B: BV>(
         solver,
         Some(args[4].clone()),
         WriteOpts::default(),
+        info,
     )
 }
 
@@ -2273,9 +2617,9 @@ fn write_tag<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory_mut().write_tag(args[0].clone(), args[1].clone(), args[2].clone(), solver)
+    frame.memory_mut().write_tag(args[0].clone(), args[1].clone(), args[2].clone(), solver, info)
 }
 
 fn bad_write<B: BV>(_: Val<B>, _: &mut Solver<B>, _: SourceLoc) -> Result<Val<B>, ExecError> {
@@ -2315,9 +2659,9 @@ fn branch_announce<B: BV>(
     _: Val<B>,
     target: Val<B>,
     solver: &mut Solver<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    solver.add_event(Event::Branch { address: target });
+    solver.add_event(Event::Branch { address: target, info });
     Ok(Val::Unit)
 }
 
@@ -2325,9 +2669,9 @@ fn address_announce<B: BV>(
     _: Val<B>,
     address: Val<B>,
     solver: &mut Solver<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    solver.add_event(Event::AddressAnnounce { address });
+    solver.add_event(Event::AddressAnnounce { address, info });
     Ok(Val::Unit)
 }
 
@@ -2367,6 +2711,23 @@ fn mark_register<B: BV>(r: Val<B>, mark: Val<B>, solver: &mut Solver<B>, info: S
     }
 }
 
+/// Records a user-defined [Event::Log] in the trace, so models can
+/// annotate traces with a label and a value.
+fn log_event<B: BV>(
+    label: Val<B>,
+    value: Val<B>,
+    solver: &mut Solver<B>,
+    info: SourceLoc,
+) -> Result<Val<B>, ExecError> {
+    match label {
+        Val::String(label) => {
+            solver.add_event(Event::Log { label, value, info });
+            Ok(Val::Unit)
+        }
+        label => Err(ExecError::Type(format!("isla_log_event {:?}", &label), info)),
+    }
+}
+
 fn mark_register_pair_internal<B: BV>(
     r1: Val<B>,
     r2: Val<B>,
@@ -2515,6 +2876,7 @@ pub fn unary_primops<B: BV>() -> HashMap<String, Unary<B>> {
     primops.insert("bad_read".to_string(), bad_read as Unary<B>);
     primops.insert("bad_write".to_string(), bad_write as Unary<B>);
     primops.insert("hex_str".to_string(), hex_str as Unary<B>);
+    primops.insert("hex_str_upper".to_string(), hex_str_upper as Unary<B>);
     primops.insert("dec_str".to_string(), dec_str as Unary<B>);
     primops.insert("string_length".to_string(), string_length as Unary<B>);
     primops.insert("string_of_bits".to_string(), string_of_bits as Unary<B>);
@@ -2603,6 +2965,7 @@ pub fn binary_primops<B: BV>() -> HashMap<String, Binary<B>> {
     primops.insert("branch_announce".to_string(), branch_announce as Binary<B>);
     primops.insert("address_announce".to_string(), address_announce as Binary<B>);
     primops.insert("mark_register".to_string(), mark_register as Binary<B>);
+    primops.insert("isla_log_event".to_string(), log_event as Binary<B>);
     primops.extend(float::binary_primops());
     primops
 }
@@ -2656,6 +3019,69 @@ pub fn variadic_primops<B: BV>() -> HashMap<String, Variadic<B>> {
     primops
 }
 
+/// Returns true if the unary primop `f` only computes its result, so
+/// path merging can run it on both sides of a branch. Primops which
+/// assert or assume anything, or add events to the trace, must never
+/// be listed here, as their effects would apply to both paths. A
+/// primop which is not recognised just causes the branch to be
+/// forked.
+pub(crate) fn is_mergeable_unary<B: BV>(f: Unary<B>) -> bool {
+    let mergeable: &[Unary<B>] = &[
+        i64_to_i128,
+        bit_to_bool,
+        not_bool,
+        not_bits,
+        length,
+        zeros,
+        ones,
+        sail_unsigned,
+        sail_signed,
+        count_leading_zeros,
+        one_if,
+        zero_if,
+    ];
+    mergeable.iter().any(|g| *g as usize == f as usize)
+}
+
+/// As [is_mergeable_unary], for binary primops.
+pub(crate) fn is_mergeable_binary<B: BV>(f: Binary<B>) -> bool {
+    let mergeable: &[Binary<B>] = &[
+        and_bool,
+        or_bool,
+        eq_int,
+        eq_bool,
+        lteq_int,
+        gteq_int,
+        lt_int,
+        gt_int,
+        add_int,
+        sub_int,
+        max_int,
+        min_int,
+        eq_bits,
+        neq_bits,
+        xor_bits,
+        or_bits,
+        and_bits,
+        add_bits,
+        sub_bits,
+        add_bits_int,
+        sub_bits_int,
+        zero_extend,
+        sign_extend,
+        sail_truncate,
+        sail_truncate_lsb,
+        append,
+        shiftr,
+        shiftl,
+        arith_shiftr,
+        shift_bits_right,
+        shift_bits_left,
+        eq_anything,
+    ];
+    mergeable.iter().any(|g| *g as usize == f as usize)
+}
+
 pub struct Primops<B> {
     pub unary: HashMap<String, Unary<B>>,
     pub binary: HashMap<String, Binary<B>>,
@@ -2679,6 +3105,85 @@ mod tests {
     use crate::smt::{Config, Context, SmtResult, Solver};
     use crate::source_loc::SourceLoc;
 
+    #[test]
+    fn integer_overflow() -> Result<(), ExecError> {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let info = SourceLoc::unknown();
+        let int = |i: i128| Val::<B64>::I128(i);
+        let big = |i: i128| BigInt::from(i);
+
+        assert_eq!(add_int(int(i128::MAX), int(1), &mut solver, info)?, Val::BigInt(big(i128::MAX) + 1));
+        assert_eq!(sub_int(int(i128::MIN), int(1), &mut solver, info)?, Val::BigInt(big(i128::MIN) - 1));
+        assert_eq!(mult_int(int(1 << 100), int(1 << 30), &mut solver, info)?, Val::BigInt(big(1) << 130));
+        assert_eq!(neg_int(int(i128::MIN), &mut solver, info)?, Val::BigInt(-big(i128::MIN)));
+        assert_eq!(abs_int(int(i128::MIN), &mut solver, info)?, Val::BigInt(-big(i128::MIN)));
+        assert_eq!(shl_int(int(3), int(126), &mut solver, info)?, Val::BigInt(big(3) << 126));
+        assert_eq!(pow2(int(127), &mut solver, info)?, Val::BigInt(big(1) << 127));
+        assert_eq!(pow_int(int(10), int(40), &mut solver, info)?, Val::BigInt(big(10).pow(40)));
+        assert_eq!(sub_nat(int(i128::MIN), int(1), &mut solver, info)?, int(0));
+
+        // Results which fit are always I128s
+        let x = add_int(int(i128::MAX), int(1), &mut solver, info)?;
+        assert_eq!(sub_int(x.clone(), int(1), &mut solver, info)?, int(i128::MAX));
+        assert_eq!(eq_int(x.clone(), int(0), &mut solver, info)?, Val::Bool(false));
+        assert_eq!(gt_int(x.clone(), int(i128::MAX), &mut solver, info)?, Val::Bool(true));
+        assert_eq!(max_int(x.clone(), int(3), &mut solver, info)?, x);
+        assert_eq!(emod_int(x.clone(), int(-7), &mut solver, info)?, int(2));
+        assert_eq!(
+            ediv_int(neg_int(x.clone(), &mut solver, info)?, int(-7), &mut solver, info)?,
+            int(i128::MAX / 7 + 1)
+        );
+        assert_eq!(tmod_int(neg_int(x.clone(), &mut solver, info)?, int(7), &mut solver, info)?, int(-2));
+        assert_eq!(
+            dec_str(x.clone(), &mut solver, info)?,
+            Val::String("170141183460469231731687303715884105728".to_string())
+        );
+        let slice = get_slice_int_internal(int(8), x, int(120), &mut solver, info)?;
+        assert_eq!(slice, Val::Bits(B64::new(0x80, 8)));
+
+        assert!(matches!(shl_int(int(-1), int(127), &mut solver, info)?, Val::I128(i128::MIN)));
+        assert!(matches!(pow2(int(126), &mut solver, info)?, Val::I128(n) if n == 1 << 126));
+        assert!(matches!(sub_nat(int(3), int(5), &mut solver, info)?, Val::I128(0)));
+        Ok(())
+    }
+
+    #[test]
+    fn int_theory() -> Result<(), ExecError> {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let info = SourceLoc::unknown();
+        let big = |i: i128| BigInt::from(i);
+
+        // A symbolic integer plus a BigInt is Int-sorted, and can
+        // exceed the 128-bit range without wrapping
+        let x = Val::Symbolic(solver.declare_const(Ty::BitVec(128), info));
+        let y = add_int(x.clone(), Val::BigInt(big(1) << 127), &mut solver, info)?;
+        let Val::Symbolic(v) = y else { panic!("expected symbolic result") };
+        assert!(solver.is_int(v));
+        let top = Val::BigInt((big(1) << 128) - 1);
+        let Val::Symbolic(eq) = eq_int(y.clone(), top.clone(), &mut solver, info)? else { panic!() };
+        assert_eq!(solver.check_sat_with(&Exp::Var(eq)), SmtResult::Sat);
+        let Val::Symbolic(gt) = gt_int(y.clone(), top, &mut solver, info)? else { panic!() };
+        assert_eq!(solver.check_sat_with(&Exp::Var(gt)), SmtResult::Unsat);
+        let Val::Symbolic(neg) = lt_int(y.clone(), Val::I128(0), &mut solver, info)? else { panic!() };
+        assert_eq!(solver.check_sat_with(&Exp::Var(neg)), SmtResult::Unsat);
+
+        // Slicing the Int-sorted value gives back bits of x
+        let low = get_slice_int_internal(Val::I128(128), y, Val::I128(0), &mut solver, info)?;
+        let Val::Symbolic(low) = low else { panic!() };
+        assert_eq!(solver.length(low), Some(128));
+
+        // The unsigned value of a symbolic 128-bit vector is never negative
+        let bits = Val::Symbolic(solver.declare_const(Ty::BitVec(128), info));
+        let unsigned = sail_unsigned(bits, &mut solver, info)?;
+        let Val::Symbolic(neg) = lt_int(unsigned, Val::I128(0), &mut solver, info)? else { panic!() };
+        assert_eq!(solver.check_sat_with(&Exp::Var(neg)), SmtResult::Unsat);
+        Ok(())
+    }
+
     #[test]
     fn mixed_bits() -> Result<(), ExecError> {
         let cfg = Config::new();
@@ -2748,4 +3253,55 @@ mod tests {
         assert!(solver.check_sat() == SmtResult::Sat);
         Ok(())
     }
+
+    #[test]
+    fn fp_ieee_round_trip() -> Result<(), ExecError> {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        // 1.5 as a single precision float
+        let bits = solver.define_const(smt_sbits(B64::new(0x3fc0_0000, 32)), SourceLoc::unknown());
+        let fp = float::fp32_from_ieee(Val::Symbolic(bits), &mut solver, SourceLoc::unknown())?;
+        assert!(!matches!(float::fp_is_nan(fp.clone(), &mut solver, SourceLoc::unknown())?, Val::Bool(true)));
+        let back = float::fp32_to_ieee(fp, &mut solver, SourceLoc::unknown())?;
+        let _ = pessimistic_assert(
+            op_eq(back, Val::Bits(B64::new(0x3fc0_0000, 32)), &mut solver, SourceLoc::unknown())?,
+            Val::String("fp round trip".to_string()),
+            &mut solver,
+            SourceLoc::unknown(),
+        )?;
+        assert!(solver.check_sat() == SmtResult::Sat);
+        Ok(())
+    }
+
+    #[test]
+    fn string_formatting() -> Result<(), ExecError> {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let info = SourceLoc::unknown();
+        let string = |v: Val<B64>| match v {
+            Val::String(s) => s,
+            other => panic!("expected string, got {:?}", other),
+        };
+        assert_eq!(string(hex_str(Val::I128(255), &mut solver, info)?), "0xff");
+        assert_eq!(string(hex_str(Val::I128(-16), &mut solver, info)?), "0xfffffffffffffffffffffffffffffff0");
+        assert_eq!(string(hex_str(Val::I64(-1), &mut solver, info)?), format!("0x{}", "f".repeat(32)));
+        assert_eq!(
+            string(hex_str(Val::BigInt(BigInt::from(1_i32) << 128_usize), &mut solver, info)?),
+            format!("0x1{}", "0".repeat(32))
+        );
+        assert_eq!(
+            string(hex_str_upper(Val::BigInt(-(BigInt::from(0xab_i32) << 128_usize)), &mut solver, info)?),
+            format!("-0xAB{}", "0".repeat(32))
+        );
+        assert_eq!(string(hex_str_upper(Val::I64(255), &mut solver, info)?), "0xFF");
+        assert_eq!(string(dec_str(Val::I64(-3), &mut solver, info)?), "-3");
+        let bits = Val::MixedBits(vec![BitsSegment::Concrete(B64::new(0xff, 8))]);
+        assert_eq!(string(decimal_string_of_bits(bits, &mut solver, info)?), "255");
+        let msg = concat_str(Val::String("x = ".to_string()), Val::String("0xff".to_string()), &mut solver, info)?;
+        assert_eq!(string(msg), "x = 0xff");
+        assert!(matches!(concat_str(Val::Poison, Val::String("".to_string()), &mut solver, info)?, Val::Poison));
+        Ok(())
+    }
 }
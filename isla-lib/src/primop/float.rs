@@ -161,6 +161,32 @@ fp_unary_primop!(fp128_from_ieee, {
     FPUnary::FromIEEE(ty.exponent_width(), ty.significand_width())
 });
 
+/// SMTLIB has no operation for converting a floating point number
+/// into its IEEE 754-2008 interchange format, as NaNs have multiple
+/// bit representations. We therefore create a fresh bitvector which
+/// is constrained to be a representation of the floating point input.
+macro_rules! fp_to_ieee_primop {
+    ($f:ident, $ty:expr) => {
+        pub fn $f<B: BV>(v: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
+            let ty = $ty;
+            match v {
+                Val::Symbolic(v) => {
+                    let bits = solver.declare_const(Ty::BitVec(ty.exponent_width() + ty.significand_width()), info);
+                    let from_ieee = FPUnary::FromIEEE(ty.exponent_width(), ty.significand_width());
+                    solver.assert_eq(Exp::FPUnary(from_ieee, Box::new(Exp::Var(bits))), Exp::Var(v));
+                    Ok(Val::Symbolic(bits))
+                }
+                _ => Err(ExecError::Type(stringify!($f).to_string(), info)),
+            }
+        }
+    };
+}
+
+fp_to_ieee_primop!(fp16_to_ieee, FPTy::fp16());
+fp_to_ieee_primop!(fp32_to_ieee, FPTy::fp32());
+fp_to_ieee_primop!(fp64_to_ieee, FPTy::fp64());
+fp_to_ieee_primop!(fp128_to_ieee, FPTy::fp128());
+
 macro_rules! fp_rounding_unary_primop {
     ($f:ident, $op:expr) => {
         pub fn $f<B: BV>(rm: Val<B>, v: Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Val<B>, ExecError> {
@@ -199,15 +225,15 @@ fp_rounding_unary_primop!(fp16_from_signed, {
     FPRoundingUnary::FromSigned(ty.exponent_width(), ty.significand_width())
 });
 fp_rounding_unary_primop!(fp32_from_signed, {
-    let ty = FPTy::fp16();
+    let ty = FPTy::fp32();
     FPRoundingUnary::FromSigned(ty.exponent_width(), ty.significand_width())
 });
 fp_rounding_unary_primop!(fp64_from_signed, {
-    let ty = FPTy::fp16();
+    let ty = FPTy::fp64();
     FPRoundingUnary::FromSigned(ty.exponent_width(), ty.significand_width())
 });
 fp_rounding_unary_primop!(fp128_from_signed, {
-    let ty = FPTy::fp16();
+    let ty = FPTy::fp128();
     FPRoundingUnary::FromSigned(ty.exponent_width(), ty.significand_width())
 });
 
@@ -216,15 +242,15 @@ fp_rounding_unary_primop!(fp16_from_unsigned, {
     FPRoundingUnary::FromUnsigned(ty.exponent_width(), ty.significand_width())
 });
 fp_rounding_unary_primop!(fp32_from_unsigned, {
-    let ty = FPTy::fp16();
+    let ty = FPTy::fp32();
     FPRoundingUnary::FromUnsigned(ty.exponent_width(), ty.significand_width())
 });
 fp_rounding_unary_primop!(fp64_from_unsigned, {
-    let ty = FPTy::fp16();
+    let ty = FPTy::fp64();
     FPRoundingUnary::FromUnsigned(ty.exponent_width(), ty.significand_width())
 });
 fp_rounding_unary_primop!(fp128_from_unsigned, {
-    let ty = FPTy::fp16();
+    let ty = FPTy::fp128();
     FPRoundingUnary::FromUnsigned(ty.exponent_width(), ty.significand_width())
 });
 
@@ -233,6 +259,11 @@ fp_rounding_unary_primop!(fp_to_signed32, FPRoundingUnary::ToSigned(32));
 fp_rounding_unary_primop!(fp_to_signed64, FPRoundingUnary::ToSigned(64));
 fp_rounding_unary_primop!(fp_to_signed128, FPRoundingUnary::ToSigned(128));
 
+fp_rounding_unary_primop!(fp_to_unsigned16, FPRoundingUnary::ToUnsigned(16));
+fp_rounding_unary_primop!(fp_to_unsigned32, FPRoundingUnary::ToUnsigned(32));
+fp_rounding_unary_primop!(fp_to_unsigned64, FPRoundingUnary::ToUnsigned(64));
+fp_rounding_unary_primop!(fp_to_unsigned128, FPRoundingUnary::ToUnsigned(128));
+
 macro_rules! fp_binary_primop {
     ($f:ident, $op:expr) => {
         pub fn $f<B: BV>(
@@ -374,6 +405,11 @@ pub fn unary_primops<B: BV>() -> HashMap<String, Unary<B>> {
     primops.insert("fp64_from_ieee".to_string(), fp64_from_ieee as Unary<B>);
     primops.insert("fp128_from_ieee".to_string(), fp128_from_ieee as Unary<B>);
 
+    primops.insert("fp16_to_ieee".to_string(), fp16_to_ieee as Unary<B>);
+    primops.insert("fp32_to_ieee".to_string(), fp32_to_ieee as Unary<B>);
+    primops.insert("fp64_to_ieee".to_string(), fp64_to_ieee as Unary<B>);
+    primops.insert("fp128_to_ieee".to_string(), fp128_to_ieee as Unary<B>);
+
     primops
 }
 
@@ -402,6 +438,11 @@ pub fn binary_primops<B: BV>() -> HashMap<String, Binary<B>> {
     primops.insert("fp_to_signed64".to_string(), fp_to_signed64 as Binary<B>);
     primops.insert("fp_to_signed128".to_string(), fp_to_signed128 as Binary<B>);
 
+    primops.insert("fp_to_unsigned16".to_string(), fp_to_unsigned16 as Binary<B>);
+    primops.insert("fp_to_unsigned32".to_string(), fp_to_unsigned32 as Binary<B>);
+    primops.insert("fp_to_unsigned64".to_string(), fp_to_unsigned64 as Binary<B>);
+    primops.insert("fp_to_unsigned128".to_string(), fp_to_unsigned128 as Binary<B>);
+
     primops.insert("fp_rem".to_string(), fp_rem as Binary<B>);
     primops.insert("fp_min".to_string(), fp_min as Binary<B>);
     primops.insert("fp_max".to_string(), fp_max as Binary<B>);
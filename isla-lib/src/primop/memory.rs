@@ -45,45 +45,61 @@ fn read_mem<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::default())
+    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::default(), info)
 }
 
 fn read_mem_ifetch<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::ifetch())
+    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::ifetch(), info)
 }
 
 fn read_mem_exclusive<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::exclusive())
+    frame.memory().read(args[0].clone(), args[2].clone(), args[3].clone(), solver, false, ReadOpts::exclusive(), info)
 }
 
 fn write_mem<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory_mut().write(args[0].clone(), args[2].clone(), args[4].clone(), solver, None, WriteOpts::default())
+    frame.memory_mut().write(
+        args[0].clone(),
+        args[2].clone(),
+        args[4].clone(),
+        solver,
+        None,
+        WriteOpts::default(),
+        info,
+    )
 }
 
 fn write_mem_exclusive<B: BV>(
     args: Vec<Val<B>>,
     solver: &mut Solver<B>,
     frame: &mut LocalFrame<B>,
-    _: SourceLoc,
+    info: SourceLoc,
 ) -> Result<Val<B>, ExecError> {
-    frame.memory_mut().write(args[0].clone(), args[2].clone(), args[4].clone(), solver, None, WriteOpts::exclusive())
+    frame.memory_mut().write(
+        args[0].clone(),
+        args[2].clone(),
+        args[4].clone(),
+        solver,
+        None,
+        WriteOpts::exclusive(),
+        info,
+    )
 }
 
 fn read_tag_bool<B: BV>(
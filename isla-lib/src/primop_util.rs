@@ -34,6 +34,8 @@
 
 use std::collections::{hash_map::Entry, HashMap};
 
+use num_bigint::BigInt;
+
 use crate::bitvector::b64::B64;
 use crate::bitvector::BV;
 use crate::error::ExecError;
@@ -189,10 +191,46 @@ pub fn smt_value<B: BV>(v: &Val<B>, info: SourceLoc) -> Result<Exp<Sym>, ExecErr
         Val::Bool(b) => Exp::Bool(*b),
         Val::Enum(e) => Exp::Enum(*e),
         Val::Symbolic(v) => Exp::Var(*v),
+        Val::BigInt(n) => Exp::Int(n.clone()),
         _ => return Err(ExecError::Type(format!("smt_value {:?}", &v), info)),
     })
 }
 
+/// The value of a concrete integer of any size.
+pub fn concrete_int<B: BV>(v: &Val<B>) -> Option<BigInt> {
+    match v {
+        Val::I128(n) => Some(BigInt::from(*n)),
+        Val::BigInt(n) => Some(n.clone()),
+        _ => None,
+    }
+}
+
+/// Integers are normally `I128`s, or 128-bit bitvectors when
+/// symbolic. Integers which do not fit are `BigInt`s, and symbolic
+/// integers derived from them are Int-sorted variables. This returns
+/// true for either, as any operation on them must use the SMT Int
+/// theory.
+pub fn is_int_theory<B: BV>(v: &Val<B>, solver: &mut Solver<B>) -> bool {
+    match v {
+        Val::BigInt(_) => true,
+        Val::Symbolic(v) => solver.is_int(*v),
+        _ => false,
+    }
+}
+
+/// Convert an integer into an Int-sorted SMT expression.
+pub fn smt_int<B: BV>(v: &Val<B>, solver: &mut Solver<B>, info: SourceLoc) -> Result<Exp<Sym>, ExecError> {
+    match v {
+        Val::I128(n) => Ok(Exp::Int(BigInt::from(*n))),
+        Val::BigInt(n) => Ok(Exp::Int(n.clone())),
+        Val::Symbolic(v) if solver.is_int(*v) => Ok(Exp::Var(*v)),
+        Val::Symbolic(v) if solver.length(*v) == Some(128) => {
+            Ok(Exp::IntUnary(smtlib::IntUnary::FromSigned(128), Box::new(Exp::Var(*v))))
+        }
+        _ => Err(ExecError::Type(format!("smt_int {:?}", &v), info)),
+    }
+}
+
 pub fn build_ite<B: BV>(
     b: Sym,
     lhs: &Val<B>,
@@ -276,6 +314,11 @@ pub fn build_ite<B: BV>(
             Ok(Val::SymbolicCtor(sym_id, possibilities))
         }
 
+        _ if is_int_theory(lhs, solver) || is_int_theory(rhs, solver) => {
+            let (lhs, rhs) = (smt_int(lhs, solver, info)?, smt_int(rhs, solver, info)?);
+            solver.define_const(Exp::Ite(Box::new(Exp::Var(b)), Box::new(lhs), Box::new(rhs)), info).into()
+        }
+
         _ => solver
             .define_const(
                 Exp::Ite(Box::new(Exp::Var(b)), Box::new(smt_value(lhs, info)?), Box::new(smt_value(rhs, info)?)),
@@ -330,6 +373,21 @@ pub fn ite_phi<B: BV>(
     }
 }
 
+/// The SMT type of a symbolic value of type `ty`, if it is
+/// represented by a single declared constant.
+fn primitive_smt_ty(ty: &Ty<Name>) -> Option<smtlib::Ty> {
+    match ty {
+        Ty::I64 => Some(smtlib::Ty::BitVec(64)),
+        Ty::I128 => Some(smtlib::Ty::BitVec(128)),
+        Ty::Bits(sz) if *sz > 0 => Some(smtlib::Ty::BitVec(*sz)),
+        Ty::Bool => Some(smtlib::Ty::Bool),
+        Ty::Bit => Some(smtlib::Ty::BitVec(1)),
+        Ty::Float(f) => Some(f.to_smt()),
+        Ty::RoundingMode => Some(smtlib::Ty::RoundingMode),
+        _ => None,
+    }
+}
+
 pub fn symbolic_from_typedefs<B: BV>(
     ty: &Ty<Name>,
     typedefs: Typedefs,
@@ -340,12 +398,6 @@ pub fn symbolic_from_typedefs<B: BV>(
         Ty::Unit => return Ok(Val::Unit),
         Ty::Bits(0) => return Ok(Val::Bits(B::zeros(0))),
 
-        Ty::I64 => smtlib::Ty::BitVec(64),
-        Ty::I128 => smtlib::Ty::BitVec(128),
-        Ty::Bits(sz) => smtlib::Ty::BitVec(*sz),
-        Ty::Bool => smtlib::Ty::Bool,
-        Ty::Bit => smtlib::Ty::BitVec(1),
-
         Ty::Struct(name) => {
             if let Some(field_types) = typedefs.structs.get(name) {
                 let field_values = field_types
@@ -389,17 +441,21 @@ pub fn symbolic_from_typedefs<B: BV>(
         }
 
         Ty::FixedVector(sz, ty) => {
+            if let Some(elem_ty) = primitive_smt_ty(ty) {
+                let syms = solver.declare_consts(vec![elem_ty; *sz as usize], info);
+                return Ok(Val::Vector(syms.into_iter().map(Val::Symbolic).collect()));
+            }
             let values =
                 (0..*sz).map(|_| symbolic_from_typedefs(ty, typedefs, solver, info)).collect::<Result<_, _>>()?;
             return Ok(Val::Vector(values));
         }
 
-        Ty::Float(f) => f.to_smt(),
-        Ty::RoundingMode => smtlib::Ty::RoundingMode,
-
-        // Some things we just can't represent symbolically, but we can continue in the hope that
-        // they never actually get used.
-        _ => return Ok(Val::Poison),
+        _ => match primitive_smt_ty(ty) {
+            Some(smt_ty) => smt_ty,
+            // Some things we just can't represent symbolically, but we can continue in the hope that
+            // they never actually get used.
+            None => return Ok(Val::Poison),
+        },
     };
 
     solver.declare_const(smt_ty, info).into()
@@ -27,11 +27,18 @@
 // (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+use std::collections::HashSet;
+use std::ops::Range;
+
 use crate::bitvector::BV;
+use crate::error::ExecError;
 use crate::ir::*;
 use crate::log;
-use crate::simplify::EventReferences;
-use crate::smt::{Solver, Sym};
+use crate::memory::Address;
+use crate::primop_util::{length_bits, smt_value};
+use crate::simplify::{write_events_with_opts, EventReferences, WriteOpts};
+use crate::smt::smtlib::{bits64, Exp};
+use crate::smt::{Event, EventOrder, Model, SmtResult, Solver, Sym};
 use crate::source_loc::SourceLoc;
 use crate::zencode;
 
@@ -87,3 +94,165 @@ pub fn call_info<B: BV>(f: Name, args: &[Val<B>], shared_state: &SharedState<B>,
         info.location_string(shared_state.symtab.files())
     )
 }
+
+/// Logs a write to a probed register, giving both its old and new
+/// values. Any symbolic variables in those values are followed by
+/// just the events from the trace that define them.
+pub fn register_write<B: BV>(
+    tid: usize,
+    reg: Name,
+    old_value: Option<&Val<B>>,
+    new_value: &Val<B>,
+    shared_state: &SharedState<B>,
+    solver: &Solver<B>,
+    info: SourceLoc,
+) {
+    let old_string = match old_value {
+        Some(v) => v.to_string(shared_state),
+        None => "uninitialized".to_string(),
+    };
+    log_from!(
+        tid,
+        log::PROBE,
+        &format!(
+            "Writing {} <- {} (was {}) at {}",
+            zencode::decode(shared_state.symtab.to_str(reg)),
+            new_value.to_string(shared_state),
+            old_string,
+            info.location_string(shared_state.symtab.files())
+        )
+    );
+
+    let mut symbols = new_value.symbolic_variables();
+    if let Some(v) = old_value {
+        symbols.extend(v.symbolic_variables())
+    }
+    if symbols.is_empty() {
+        return;
+    }
+
+    let events = solver.trace().to_vec_in(EventOrder::Execution);
+    let references = EventReferences::from_events(&events);
+    let dependencies: HashSet<Sym> = symbols.iter().flat_map(|v| references.dependencies(*v)).collect();
+    let definitions: Vec<Event<B>> = events
+        .into_iter()
+        .filter(|event| matches!(event.defines(), Some(v) if dependencies.contains(&v)))
+        .cloned()
+        .collect();
+
+    let mut buf = Vec::new();
+    if write_events_with_opts(&mut buf, &definitions, shared_state, &WriteOpts::smtlib()).is_ok() {
+        log_from!(tid, log::PROBE, String::from_utf8_lossy(&buf).trim())
+    }
+}
+
+/// Parse a watched range of addresses, given either as
+/// `<start>..<end>` with an exclusive end, or as `<address>[:<bytes>]`
+/// covering a single byte by default. Numbers are decimal, or
+/// hexadecimal with a `0x` prefix.
+pub fn parse_watchpoint(watchpoint: &str) -> Result<Range<Address>, String> {
+    let parse = |n: &str| {
+        match n.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => n.parse(),
+        }
+        .map_err(|e| format!("Could not parse {} in watchpoint {}: {}", n, watchpoint, e))
+    };
+    let range = if let Some((start, end)) = watchpoint.split_once("..") {
+        parse(start)?..parse(end)?
+    } else {
+        let (address, bytes) = match watchpoint.split_once(':') {
+            Some((address, bytes)) => (parse(address)?, parse(bytes)?),
+            None => (parse(watchpoint)?, 1),
+        };
+        let end = address.checked_add(bytes).ok_or_else(|| format!("Watchpoint {} overflows", watchpoint))?;
+        address..end
+    };
+    if range.is_empty() {
+        return Err(format!("Watchpoint {} is empty", watchpoint));
+    }
+    Ok(range)
+}
+
+/// Logs each memory access in `events` which may touch any of the
+/// watched address ranges, along with the taint info for its address
+/// when that is symbolic.
+pub fn watchpoints<B: BV>(
+    tid: usize,
+    watchpoints: &[Range<Address>],
+    events: &[Event<B>],
+    shared_state: &SharedState<B>,
+    solver: &mut Solver<B>,
+) -> Result<(), ExecError> {
+    use Exp::*;
+
+    for event in events {
+        let (access, address, bytes, info) = match event {
+            Event::ReadMem { address, bytes, opts, info, .. } => {
+                (if opts.is_ifetch { "Instruction fetch" } else { "Read" }, address, *bytes, *info)
+            }
+            Event::WriteMem { address, bytes, info, .. } if *bytes > 0 => ("Write", address, *bytes, *info),
+            _ => continue,
+        };
+
+        // Addresses are widened so the end of an access cannot wrap around
+        let address_exp = smt_value(address, info)?;
+        let width = length_bits(address, solver, info)?;
+        if width > 64 {
+            continue;
+        }
+        let start = ZeroExtend(65 - width, Box::new(address_exp.clone()));
+        let end = Bvadd(Box::new(start.clone()), Box::new(bits64(bytes as u64, 65)));
+
+        for range in watchpoints {
+            let touches = And(
+                Box::new(Bvult(Box::new(start.clone()), Box::new(bits64(range.end, 65)))),
+                Box::new(Bvult(Box::new(bits64(range.start, 65)), Box::new(end.clone()))),
+            );
+            match solver.check_sat_with(&touches) {
+                SmtResult::Sat => {
+                    let witness = match Model::new(solver).get_exp(&address_exp)? {
+                        Some(Bits64(bv)) => format!("{:#x}", bv.lower_u64()),
+                        other => format!("{:?}", other),
+                    };
+                    log_from!(
+                        tid,
+                        log::PROBE,
+                        &format!(
+                            "{} of {} bytes at {} may touch watchpoint {:#x}..{:#x} (e.g. at address {}) at {}",
+                            access,
+                            bytes,
+                            address.to_string(shared_state),
+                            range.start,
+                            range.end,
+                            witness,
+                            info.location_string(shared_state.symtab.files())
+                        )
+                    );
+                    if let Val::Symbolic(v) = address {
+                        taint_info(log::PROBE, *v, Some(shared_state), solver)
+                    }
+                }
+                SmtResult::Unknown => return Err(ExecError::Z3Unknown),
+                SmtResult::Unsat => (),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchpoint_ranges() {
+        assert_eq!(parse_watchpoint("0x1000..0x2000"), Ok(0x1000..0x2000));
+        assert_eq!(parse_watchpoint("0x1000:16"), Ok(0x1000..0x1010));
+        assert_eq!(parse_watchpoint("4096"), Ok(4096..4097));
+        assert!(parse_watchpoint("0x2000..0x1000").is_err());
+        assert!(parse_watchpoint("0xffffffffffffffff:2").is_err());
+        assert!(parse_watchpoint("0x10g0").is_err())
+    }
+}
@@ -30,6 +30,12 @@
 
 //! This module implements various routines for simplifying event
 //! traces, as well as printing the generated traces.
+//!
+//! The simplification passes over vectors of events expect them in
+//! [SIMPLIFY_ORDER], the order produced by the trace collectors, and
+//! leave the remaining events in that same order. Event trees are
+//! always stored in [EventOrder::Execution] order. The writers print
+//! in whichever order is requested by [WriteOpts].
 
 use std::borrow::{Borrow, BorrowMut, Cow};
 use std::cmp::Ordering;
@@ -37,13 +43,34 @@ use std::collections::{HashMap, HashSet};
 use std::io::Write;
 
 use crate::bitvector::{write_bits64, BV};
+use crate::config::AccessKind;
 use crate::ir::{BitsSegment, Loc, Name, SharedState, Symtab, Val, HAVE_EXCEPTION};
+use crate::smt::rewrite;
 use crate::smt::smtlib::{self, *};
 use crate::smt::Event::*;
-use crate::smt::{Accessor, Event, Sym};
+use crate::smt::{Accessor, EnumMember, Event, EventOrder, Sym};
 use crate::source_loc::SourceLoc;
+use crate::trace;
 use crate::zencode;
 
+mod read;
+mod sexp_lexer;
+lalrpop_mod!(
+    #[allow(clippy::all)]
+    sexp_parser,
+    "/simplify/sexp_parser.rs"
+);
+
+pub use read::{read_events, read_traces};
+
+/// The version of the trace format printed by [write_events], see
+/// `doc/trace-format.adoc`. It is incremented whenever an event form
+/// is removed or changes its meaning, so [read_events] can reject
+/// traces written by a later version of isla. Adding an event form
+/// does not change it. Traces without a version are read as
+/// version 1.
+pub const TRACE_FORMAT_VERSION: u32 = 1;
+
 /// `renumber_event` Renumbers all the symbolic variables in an event such that multiple event
 /// sequences can have disjoint variable identifiers. It takes two `u32` arguments `i` and `total`,
 /// such that `i` is the index of our event sequence in the range `0..(total - 1)` inclusive where
@@ -57,7 +84,7 @@ where
     match event {
         Smt(def, _, _) => renumber_def(def, f),
         Fork(_, v, _, _) => *v = Sym { id: f(v.id) },
-        Abstract { name: _, primitive: _, args, return_value } => {
+        Abstract { name: _, primitive: _, args, return_value, info: _ } => {
             for arg in args.iter_mut() {
                 renumber_val(arg, f)
             }
@@ -69,10 +96,26 @@ where
             }
             renumber_val(return_value, f)
         }
-        ReadReg(_, _, value) | WriteReg(_, _, value) | Instr(value) | AssumeReg(_, _, value) => renumber_val(value, f),
-        AddressAnnounce { address } => renumber_val(address, f),
-        Branch { address } => renumber_val(address, f),
-        ReadMem { value, read_kind, address, bytes: _, tag_value, opts: _, region: _ } => {
+        ReadReg(_, _, value, _) | WriteReg(_, _, value, _) | Instr(value) | AssumeReg(_, _, value) => {
+            renumber_val(value, f)
+        }
+        AddressAnnounce { address, info: _ } => renumber_val(address, f),
+        Branch { address, info: _ } => renumber_val(address, f),
+        BlockOp { address, .. } => renumber_val(address, f),
+        DecodeCall { args, .. } => {
+            for arg in args.iter_mut() {
+                renumber_val(arg, f)
+            }
+        }
+        DecodeReturn { return_value, .. } => renumber_val(return_value, f),
+        DecodeValue { args, value, .. } | DecodeTest { args, value, .. } => {
+            for arg in args.iter_mut() {
+                renumber_val(arg, f)
+            }
+            renumber_val(value, f)
+        }
+        Log { value, .. } => renumber_val(value, f),
+        ReadMem { value, read_kind, address, bytes: _, tag_value, opts: _, region: _, info: _ } => {
             renumber_val(value, f);
             renumber_val(read_kind, f);
             renumber_val(address, f);
@@ -80,7 +123,7 @@ where
                 renumber_val(v, f);
             }
         }
-        WriteMem { value: v, write_kind, address, data, bytes: _, tag_value, opts: _, region: _ } => {
+        WriteMem { value: v, write_kind, address, data, bytes: _, tag_value, opts: _, region: _, info: _ } => {
             *v = Sym { id: f(v.id) };
             renumber_val(write_kind, f);
             renumber_val(address, f);
@@ -89,7 +132,7 @@ where
                 renumber_val(v, f);
             }
         }
-        Cycle | MarkReg { .. } | Function { .. } | Assume(_) => (),
+        Cycle | Guard { .. } | LoopBound { .. } | Undefined { .. } | MarkReg { .. } | Function { .. } | Assume(_) => (),
     }
 }
 
@@ -117,7 +160,7 @@ where
             BitsSegment::Symbolic(v) => *v = Sym { id: f(v.id) },
             BitsSegment::Concrete(_) => (),
         }),
-        I64(_) | I128(_) | Bool(_) | Bits(_) | Enum(_) | String(_) | Unit | Ref(_) | Poison => (),
+        I64(_) | I128(_) | BigInt(_) | Bool(_) | Bits(_) | Enum(_) | String(_) | Unit | Ref(_) | Poison => (),
         List(vals) | Vector(vals) => vals.iter_mut().for_each(|val| renumber_val(val, f)),
         Struct(fields) => fields.iter_mut().for_each(|(_, val)| renumber_val(val, f)),
         Ctor(_, val) => renumber_val(val, f),
@@ -151,14 +194,15 @@ fn uses_in_exp(uses: &mut HashMap<Sym, u32>, exp: &Exp<Sym>) {
         Var(v) => {
             uses.insert(*v, uses.get(v).unwrap_or(&0) + 1);
         }
-        Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) => (),
+        Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) | Int(_) => (),
         Not(exp)
         | Bvnot(exp)
         | Bvneg(exp)
         | Extract(_, _, exp)
         | ZeroExtend(_, exp)
         | SignExtend(_, exp)
-        | FPUnary(_, exp) => uses_in_exp(uses, exp),
+        | FPUnary(_, exp)
+        | IntUnary(_, exp) => uses_in_exp(uses, exp),
         Eq(lhs, rhs)
         | Neq(lhs, rhs)
         | And(lhs, rhs)
@@ -189,7 +233,8 @@ fn uses_in_exp(uses: &mut HashMap<Sym, u32>, exp: &Exp<Sym>) {
         | Bvlshr(lhs, rhs)
         | Bvashr(lhs, rhs)
         | Concat(lhs, rhs)
-        | FPBinary(_, lhs, rhs) => {
+        | FPBinary(_, lhs, rhs)
+        | IntBinary(_, lhs, rhs) => {
             uses_in_exp(uses, lhs);
             uses_in_exp(uses, rhs)
         }
@@ -248,7 +293,7 @@ fn uses_in_value<B>(uses: &mut HashMap<Sym, u32>, val: &Val<B>) {
             }
             BitsSegment::Concrete(_) => (),
         }),
-        I64(_) | I128(_) | Bool(_) | Bits(_) | Enum(_) | String(_) | Unit | Ref(_) | Poison => (),
+        I64(_) | I128(_) | BigInt(_) | Bool(_) | Bits(_) | Enum(_) | String(_) | Unit | Ref(_) | Poison => (),
         List(vals) | Vector(vals) => vals.iter().for_each(|val| uses_in_value(uses, val)),
         Struct(fields) => fields.iter().for_each(|(_, val)| uses_in_value(uses, val)),
         Ctor(_, val) => uses_in_value(uses, val),
@@ -350,7 +395,7 @@ impl EventReferences {
 
         for event in events.iter() {
             match event.borrow() {
-                ReadReg(reg, accessor, value) => {
+                ReadReg(reg, accessor, value, _) => {
                     let mut uses = HashMap::new();
                     uses_in_value(&mut uses, value);
                     for (taint, _) in uses.iter() {
@@ -382,6 +427,255 @@ impl EventReferences {
     }
 }
 
+/// A pattern selecting events in a trace, as used by
+/// [minimize_for]. Patterns are written `<kind>[:<name>]`, where kind
+/// is one of `read-reg`, `write-reg`, `read-mem`, `write-mem`, or
+/// `abstract`, and the optional name restricts register events to a
+/// specific register and abstract events to a specific function.
+/// Memory events can be restricted to accesses of a specific number of
+/// bytes, one of the `access_kinds` declared in the ISA config, or
+/// both, as in `read-mem:8:acquire`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventPattern {
+    ReadReg(Option<Name>),
+    WriteReg(Option<Name>),
+    ReadMem(Option<u32>, Option<AccessKind>),
+    WriteMem(Option<u32>, Option<AccessKind>),
+    Abstract(Option<Name>),
+}
+
+impl EventPattern {
+    pub fn parse(pattern: &str, symtab: &Symtab, access_kinds: &[AccessKind]) -> Result<Self, String> {
+        let (kind, name) = match pattern.split_once(':') {
+            Some((kind, name)) => (kind, Some(name)),
+            None => (pattern, None),
+        };
+        let access = || {
+            let (mut bytes, mut access_kind) = (None, None);
+            for part in name.into_iter().flat_map(|name| name.split(':')) {
+                if let Ok(n) = part.parse() {
+                    bytes = Some(n)
+                } else if let Some(k) = access_kinds.iter().find(|k| k.name == part) {
+                    access_kind = Some(k.clone())
+                } else {
+                    return Err(format!(
+                        "Memory event pattern {} must be given a number of bytes or an access kind",
+                        pattern
+                    ));
+                }
+            }
+            Ok((bytes, access_kind))
+        };
+        let name = || match name {
+            Some(name) => match symtab.get(&zencode::encode(name)) {
+                Some(name) => Ok(Some(name)),
+                None => Err(format!("Unknown name {} in event pattern {}", name, pattern)),
+            },
+            None => Ok(None),
+        };
+        match kind {
+            "read-reg" => Ok(EventPattern::ReadReg(name()?)),
+            "write-reg" => Ok(EventPattern::WriteReg(name()?)),
+            "read-mem" => access().map(|(bytes, kind)| EventPattern::ReadMem(bytes, kind)),
+            "write-mem" => access().map(|(bytes, kind)| EventPattern::WriteMem(bytes, kind)),
+            "abstract" => Ok(EventPattern::Abstract(name()?)),
+            _ => Err(format!(
+                "Unknown event kind {}, expected read-reg, write-reg, read-mem, write-mem, or abstract",
+                kind
+            )),
+        }
+    }
+
+    pub fn matches<B>(&self, event: &Event<B>) -> bool {
+        let name_matches = |pattern: &Option<Name>, name: &Name| match pattern {
+            Some(p) => p == name,
+            None => true,
+        };
+        let access_matches = |p: &Option<u32>, k: &Option<AccessKind>, bytes: u32| {
+            (p.is_none() || *p == Some(bytes)) && k.as_ref().map(|k| k.matches(event)).unwrap_or(true)
+        };
+        match (self, event) {
+            (EventPattern::ReadReg(p), ReadReg(name, _, _, _)) => name_matches(p, name),
+            (EventPattern::WriteReg(p), WriteReg(name, _, _, _)) => name_matches(p, name),
+            (EventPattern::ReadMem(p, k), ReadMem { bytes, .. }) => access_matches(p, k, *bytes),
+            (EventPattern::WriteMem(p, k), WriteMem { bytes, .. }) => access_matches(p, k, *bytes),
+            (EventPattern::Abstract(p), Abstract { name, .. }) => name_matches(p, name),
+            _ => false,
+        }
+    }
+}
+
+/// The kinds of event, as used to hide events when printing traces
+/// (see [WriteOpts::hide_kinds]). Each is named after the form it is
+/// printed as, e.g. `read-reg` or `branch`, except that `decode`
+/// covers both decode calls and returns, and `function-assumption`
+/// both function assumptions and their uses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Smt,
+    Fork,
+    Function,
+    Abstract,
+    Decode,
+    FunctionAssumption,
+    ReadReg,
+    WriteReg,
+    AssumeReg,
+    MarkReg,
+    ReadMem,
+    WriteMem,
+    AddressAnnounce,
+    BranchAddress,
+    BlockOp,
+    Cycle,
+    Instr,
+    Guard,
+    LoopBound,
+    Undefined,
+    Log,
+    Assume,
+}
+
+impl EventKind {
+    const NAMES: &'static [(&'static str, EventKind)] = &[
+        ("smt", EventKind::Smt),
+        ("branch", EventKind::Fork),
+        ("function", EventKind::Function),
+        ("abstract", EventKind::Abstract),
+        ("decode", EventKind::Decode),
+        ("function-assumption", EventKind::FunctionAssumption),
+        ("read-reg", EventKind::ReadReg),
+        ("write-reg", EventKind::WriteReg),
+        ("assume-reg", EventKind::AssumeReg),
+        ("mark-reg", EventKind::MarkReg),
+        ("read-mem", EventKind::ReadMem),
+        ("write-mem", EventKind::WriteMem),
+        ("address-announce", EventKind::AddressAnnounce),
+        ("branch-address", EventKind::BranchAddress),
+        ("block-op", EventKind::BlockOp),
+        ("cycle", EventKind::Cycle),
+        ("instr", EventKind::Instr),
+        ("guard", EventKind::Guard),
+        ("loop-bound", EventKind::LoopBound),
+        ("undefined", EventKind::Undefined),
+        ("log", EventKind::Log),
+        ("assume", EventKind::Assume),
+    ];
+
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        match Self::NAMES.iter().find(|(name, _)| *name == kind) {
+            Some((_, kind)) => Ok(*kind),
+            None => {
+                let names: Vec<&str> = Self::NAMES.iter().map(|(name, _)| *name).collect();
+                Err(format!("Unknown event kind {}, expected one of {}", kind, names.join(", ")))
+            }
+        }
+    }
+
+    pub fn of<B>(event: &Event<B>) -> Self {
+        match event {
+            Smt(..) => EventKind::Smt,
+            Fork(..) => EventKind::Fork,
+            Function { .. } => EventKind::Function,
+            Abstract { .. } => EventKind::Abstract,
+            DecodeCall { .. } | DecodeReturn { .. } | DecodeValue { .. } | DecodeTest { .. } => EventKind::Decode,
+            AssumeFun { .. } | UseFunAssumption { .. } => EventKind::FunctionAssumption,
+            ReadReg(..) => EventKind::ReadReg,
+            WriteReg(..) => EventKind::WriteReg,
+            AssumeReg(..) => EventKind::AssumeReg,
+            MarkReg { .. } => EventKind::MarkReg,
+            ReadMem { .. } => EventKind::ReadMem,
+            WriteMem { .. } => EventKind::WriteMem,
+            AddressAnnounce { .. } => EventKind::AddressAnnounce,
+            Branch { .. } => EventKind::BranchAddress,
+            BlockOp { .. } => EventKind::BlockOp,
+            Cycle => EventKind::Cycle,
+            Instr(_) => EventKind::Instr,
+            Guard { .. } => EventKind::Guard,
+            LoopBound { .. } => EventKind::LoopBound,
+            Undefined { .. } => EventKind::Undefined,
+            Log { .. } => EventKind::Log,
+            Assume(_) => EventKind::Assume,
+        }
+    }
+}
+
+/// Removes every event that the events matching `pattern` do not
+/// transitively depend upon, leaving just the matching events, the
+/// SMT definitions and assertions over the symbols they depend on,
+/// and the register reads, memory reads, and abstract calls those
+/// symbols originate from. Instruction and cycle markers are kept for
+/// context. As only events are removed this works on traces in
+/// either [EventOrder]. Returns false (leaving the trace unchanged)
+/// if no event matches the pattern.
+pub fn minimize_for<B: BV, E: Borrow<Event<B>>>(events: &mut Vec<E>, pattern: &EventPattern) -> bool {
+    let mut uses = HashMap::new();
+    let mut found = false;
+    for event in events.iter().filter(|ev| pattern.matches((*ev).borrow())) {
+        calculate_more_uses(std::slice::from_ref(event), &mut uses);
+        found = true
+    }
+    if !found {
+        return false;
+    }
+
+    // Values read from memory depend on the address they were read
+    // from, and the results of abstract calls on their arguments.
+    let mut references = EventReferences::from_events(events);
+    for event in events.iter() {
+        let (results, inputs) = match event.borrow() {
+            ReadMem { value, address, .. } => (value.symbolic_variables(), address.symbolic_variables()),
+            Abstract { args, return_value, .. } => {
+                (return_value.symbolic_variables(), args.iter().flat_map(|arg| arg.symbolic_variables()).collect())
+            }
+            _ => continue,
+        };
+        for result in results {
+            let deps = references.references.entry(result).or_default();
+            for input in &inputs {
+                deps.insert(*input, 1);
+            }
+        }
+    }
+
+    let mut deps = HashSet::new();
+    for symbol in uses.keys() {
+        deps.extend(references.dependencies(*symbol))
+    }
+    let depends = |val: &Val<B>| val.symbolic_variables().iter().any(|v| deps.contains(v));
+
+    let keep: Vec<bool> = events
+        .iter()
+        .map(|event| {
+            let event = event.borrow();
+            pattern.matches(event)
+                || match event {
+                    Smt(Def::DeclareConst(v, _), _, _)
+                    | Smt(Def::DeclareFun(v, _, _), _, _)
+                    | Smt(Def::DefineConst(v, _), _, _) => deps.contains(v),
+                    Smt(Def::DefineEnum(..), _, _) => true,
+                    Smt(Def::Assert(exp), _, _) => {
+                        let mut assert_uses = HashMap::new();
+                        uses_in_exp(&mut assert_uses, exp);
+                        !assert_uses.is_empty() && assert_uses.keys().all(|v| deps.contains(v))
+                    }
+                    ReadReg(_, _, val, _) => depends(val),
+                    ReadMem { value, .. } => depends(value),
+                    Abstract { return_value, .. } => depends(return_value),
+                    Instr(_) | Cycle => true,
+                    _ => false,
+                }
+        })
+        .collect();
+
+    let mut i = 0;
+    events.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+    true
+}
+
 #[allow(clippy::unneeded_field_pattern)]
 fn calculate_more_uses<B, E: Borrow<Event<B>>>(events: &[E], uses: &mut HashMap<Sym, u32>) {
     for event in events.iter().rev() {
@@ -392,7 +686,7 @@ fn calculate_more_uses<B, E: Borrow<Event<B>>>(events: &[E], uses: &mut HashMap<
             Smt(Def::DefineConst(_, exp), _, _) => uses_in_exp(uses, exp),
             Smt(Def::DefineEnum(..), _, _) => (),
             Smt(Def::Assert(exp), _, _) => uses_in_exp(uses, exp),
-            Abstract { name: _, primitive: _, args, return_value } => {
+            Abstract { name: _, primitive: _, args, return_value, info: _ } => {
                 for arg in args {
                     uses_in_value(uses, arg)
                 }
@@ -404,9 +698,22 @@ fn calculate_more_uses<B, E: Borrow<Event<B>>>(events: &[E], uses: &mut HashMap<
                 }
                 uses_in_value(uses, return_value)
             }
-            ReadReg(_, _, val) => uses_in_value(uses, val),
-            WriteReg(_, _, val) => uses_in_value(uses, val),
-            ReadMem { value: val, read_kind, address, bytes: _, tag_value, opts: _, region: _ } => {
+            DecodeCall { args, .. } => {
+                for arg in args {
+                    uses_in_value(uses, arg)
+                }
+            }
+            DecodeReturn { return_value, .. } => uses_in_value(uses, return_value),
+            DecodeValue { args, value, .. } | DecodeTest { args, value, .. } => {
+                for arg in args {
+                    uses_in_value(uses, arg)
+                }
+                uses_in_value(uses, value)
+            }
+            Log { value, .. } => uses_in_value(uses, value),
+            ReadReg(_, _, val, _) => uses_in_value(uses, val),
+            WriteReg(_, _, val, _) => uses_in_value(uses, val),
+            ReadMem { value: val, read_kind, address, bytes: _, tag_value, opts: _, region: _, info: _ } => {
                 uses_in_value(uses, val);
                 uses_in_value(uses, read_kind);
                 uses_in_value(uses, address);
@@ -414,7 +721,7 @@ fn calculate_more_uses<B, E: Borrow<Event<B>>>(events: &[E], uses: &mut HashMap<
                     uses_in_value(uses, v);
                 }
             }
-            WriteMem { value: sym, write_kind, address, data, bytes: _, tag_value, opts: _, region: _ } => {
+            WriteMem { value: sym, write_kind, address, data, bytes: _, tag_value, opts: _, region: _, info: _ } => {
                 uses.insert(*sym, uses.get(sym).unwrap_or(&0) + 1);
                 uses_in_value(uses, write_kind);
                 uses_in_value(uses, address);
@@ -423,12 +730,15 @@ fn calculate_more_uses<B, E: Borrow<Event<B>>>(events: &[E], uses: &mut HashMap<
                     uses_in_value(uses, v);
                 }
             }
-            AddressAnnounce { address } => uses_in_value(uses, address),
-            Branch { address } => uses_in_value(uses, address),
+            AddressAnnounce { address, info: _ } => uses_in_value(uses, address),
+            Branch { address, info: _ } => uses_in_value(uses, address),
+            BlockOp { address, .. } => uses_in_value(uses, address),
             Fork(_, sym, _, _) => {
                 uses.insert(*sym, uses.get(sym).unwrap_or(&0) + 1);
             }
             Cycle => (),
+            Guard { .. } => (),
+            LoopBound { .. } | Undefined { .. } => (),
             Instr(val) => uses_in_value(uses, val),
             MarkReg { .. } => (),
             Function { .. } => (),
@@ -472,7 +782,7 @@ fn calculate_required_uses<B, E: Borrow<Event<B>>>(events: &[E]) -> HashMap<Sym,
                 uses.insert(*sym, uses.get(sym).unwrap_or(&0) + 1);
             }
             Smt(..) => (),
-            Abstract { name: _, primitive: _, args, return_value } => {
+            Abstract { name: _, primitive: _, args, return_value, info: _ } => {
                 for arg in args {
                     uses_in_value(&mut uses, arg)
                 }
@@ -484,9 +794,22 @@ fn calculate_required_uses<B, E: Borrow<Event<B>>>(events: &[E]) -> HashMap<Sym,
                 }
                 uses_in_value(&mut uses, return_value)
             }
-            ReadReg(_, _, val) => uses_in_value(&mut uses, val),
-            WriteReg(_, _, val) => uses_in_value(&mut uses, val),
-            ReadMem { value: val, read_kind, address, bytes: _, tag_value, opts: _, region: _ } => {
+            DecodeCall { args, .. } => {
+                for arg in args {
+                    uses_in_value(&mut uses, arg)
+                }
+            }
+            DecodeReturn { return_value, .. } => uses_in_value(&mut uses, return_value),
+            DecodeValue { args, value, .. } | DecodeTest { args, value, .. } => {
+                for arg in args {
+                    uses_in_value(&mut uses, arg)
+                }
+                uses_in_value(&mut uses, value)
+            }
+            Log { value, .. } => uses_in_value(&mut uses, value),
+            ReadReg(_, _, val, _) => uses_in_value(&mut uses, val),
+            WriteReg(_, _, val, _) => uses_in_value(&mut uses, val),
+            ReadMem { value: val, read_kind, address, bytes: _, tag_value, opts: _, region: _, info: _ } => {
                 uses_in_value(&mut uses, val);
                 uses_in_value(&mut uses, read_kind);
                 uses_in_value(&mut uses, address);
@@ -494,7 +817,7 @@ fn calculate_required_uses<B, E: Borrow<Event<B>>>(events: &[E]) -> HashMap<Sym,
                     uses_in_value(&mut uses, v);
                 }
             }
-            WriteMem { value: sym, write_kind, address, data, bytes: _, tag_value, opts: _, region: _ } => {
+            WriteMem { value: sym, write_kind, address, data, bytes: _, tag_value, opts: _, region: _, info: _ } => {
                 uses.insert(*sym, uses.get(sym).unwrap_or(&0) + 1);
                 uses_in_value(&mut uses, write_kind);
                 uses_in_value(&mut uses, address);
@@ -503,12 +826,15 @@ fn calculate_required_uses<B, E: Borrow<Event<B>>>(events: &[E]) -> HashMap<Sym,
                     uses_in_value(&mut uses, v);
                 }
             }
-            AddressAnnounce { address } => uses_in_value(&mut uses, address),
-            Branch { address } => uses_in_value(&mut uses, address),
+            AddressAnnounce { address, info: _ } => uses_in_value(&mut uses, address),
+            Branch { address, info: _ } => uses_in_value(&mut uses, address),
+            BlockOp { address, .. } => uses_in_value(&mut uses, address),
             Fork(_, sym, _, _) => {
                 uses.insert(*sym, uses.get(sym).unwrap_or(&0) + 1);
             }
             Cycle => (),
+            Guard { .. } => (),
+            LoopBound { .. } | Undefined { .. } => (),
             Instr(val) => uses_in_value(&mut uses, val),
             MarkReg { .. } => (),
             Function { .. } => (),
@@ -577,6 +903,10 @@ fn remove_unused_pass_tree<B>(event_tree: &mut EventTree<B>, uses: &HashMap<Sym,
     removed
 }
 
+/// The order of events expected and preserved by the simplification
+/// passes over event vectors.
+pub const SIMPLIFY_ORDER: EventOrder = EventOrder::Reverse;
+
 /// Removes register effects from before the first `(cycle)`
 /// event. When combined with `remove_unused` this will reduce the
 /// amount of initialization that appears in the trace.
@@ -635,8 +965,8 @@ fn restrict_to_accessor<B: BV>(val: &mut Val<B>, accessor: &[Accessor]) {
 pub fn remove_extra_register_fields<B: BV>(events: &mut Vec<Event<B>>) {
     for event in events.iter_mut() {
         match event {
-            WriteReg(_n, acc, v) => restrict_to_accessor(v, acc),
-            ReadReg(_n, acc, v) => restrict_to_accessor(v, acc),
+            WriteReg(_n, acc, v, _) => restrict_to_accessor(v, acc),
+            ReadReg(_n, acc, v, _) => restrict_to_accessor(v, acc),
             _ => (),
         }
     }
@@ -676,11 +1006,11 @@ fn record_affected_register_parts<V: Eq + std::hash::Hash + Copy>(
 
 pub fn remove_repeated_register_reads<B: BV>(events: &mut Vec<Event<B>>) {
     let mut recent_reads: HashMap<Name, HashMap<Vec<Accessor>, Val<B>>> = HashMap::new();
-    // Some contortions because the trace is in reverse order when simplifications are performed.
+    // Some contortions because the trace is in SIMPLIFY_ORDER (i.e. reversed) when simplifications are performed.
     let mut keep = vec![true; events.len()];
     for (i, event) in events.iter().enumerate().rev() {
         match event {
-            ReadReg(name, acc, v) => {
+            ReadReg(name, acc, v, _) => {
                 if let Some(regmap) = recent_reads.get(name) {
                     if let Some(last_value) = regmap.get(acc) {
                         if *v == *last_value {
@@ -693,7 +1023,7 @@ pub fn remove_repeated_register_reads<B: BV>(events: &mut Vec<Event<B>>) {
                 let regmap = recent_reads.entry(*name).or_insert_with(HashMap::new);
                 regmap.insert(acc.clone(), v.clone());
             }
-            WriteReg(name, acc, _v) => {
+            WriteReg(name, acc, _v, _) => {
                 remove_affected_register_parts(&mut recent_reads, *name, acc);
             }
             _ => (),
@@ -711,7 +1041,7 @@ fn remove_repeated_register_reads_core<B: BV>(
     events: &mut EventTree<B>,
 ) {
     events.prefix.retain(|event| match event {
-        ReadReg(name, acc, v) => {
+        ReadReg(name, acc, v, _) => {
             if let Some(regmap) = recent_reads.get(name) {
                 if let Some(last_value) = regmap.get(acc) {
                     if *v == *last_value {
@@ -724,7 +1054,7 @@ fn remove_repeated_register_reads_core<B: BV>(
             regmap.insert(acc.clone(), v.clone());
             true
         }
-        WriteReg(name, acc, _v) => {
+        WriteReg(name, acc, _v, _) => {
             remove_affected_register_parts(recent_reads, *name, acc);
             true
         }
@@ -747,8 +1077,8 @@ pub fn remove_unused_register_assumptions<B: BV>(events: &mut Vec<Event<B>>) {
                 let regmap = unused_assumptions.entry(*name).or_insert_with(HashMap::new);
                 regmap.insert(accessor.clone(), i);
             }
-            ReadReg(name, accessor, _v) => remove_affected_register_parts(&mut unused_assumptions, *name, accessor),
-            WriteReg(name, accessor, _v) => {
+            ReadReg(name, accessor, _v, _) => remove_affected_register_parts(&mut unused_assumptions, *name, accessor),
+            WriteReg(name, accessor, _v, _) => {
                 // Not strictly necessary in all cases, but keeps things simple
                 remove_affected_register_parts(&mut unused_assumptions, *name, accessor)
             }
@@ -781,10 +1111,10 @@ fn unused_register_assumptions<B: BV>(
                 let regmap = live_assumptions.entry(*name).or_insert_with(HashMap::new);
                 regmap.insert(accessor.clone(), (depth, i));
             }
-            ReadReg(name, accessor, _v) => {
+            ReadReg(name, accessor, _v, _) => {
                 record_affected_register_parts(&mut live_assumptions, used_assumptions, *name, accessor)
             }
-            WriteReg(name, accessor, _v) => {
+            WriteReg(name, accessor, _v, _) => {
                 // Not strictly necessary in all cases, but keeps things simple
                 record_affected_register_parts(&mut live_assumptions, used_assumptions, *name, accessor)
             }
@@ -832,7 +1162,7 @@ pub fn remove_unused_tree<B: BV>(event_tree: &mut EventTree<B>) {
 }
 
 fn propagate_forwards_used_once_core<B: BV, E: BorrowMut<Event<B>>>(
-    rev: bool,
+    order: EventOrder,
     cross_segment: &HashSet<Sym>,
     events: &mut Vec<E>,
 ) {
@@ -849,8 +1179,10 @@ fn propagate_forwards_used_once_core<B: BV, E: BorrowMut<Event<B>>>(
 
     let mut keep = vec![true; events.len()];
 
-    let it: Box<dyn Iterator<Item = (usize, &mut E)>> =
-        if rev { Box::new(events.iter_mut().enumerate().rev()) } else { Box::new(events.iter_mut().enumerate()) };
+    let it: Box<dyn Iterator<Item = (usize, &mut E)>> = match order {
+        EventOrder::Reverse => Box::new(events.iter_mut().enumerate().rev()),
+        EventOrder::Execution => Box::new(events.iter_mut().enumerate()),
+    };
     for (i, event) in it {
         match event.borrow_mut() {
             Event::Smt(Def::DefineConst(sym, exp), _, _) => {
@@ -878,7 +1210,37 @@ fn propagate_forwards_used_once_core<B: BV, E: BorrowMut<Event<B>>>(
 /// (expression))`, and if `v` is only used exactly once in subsequent
 /// events it will replace that use of `v` by the expression.
 pub fn propagate_forwards_used_once<B: BV, E: BorrowMut<Event<B>>>(events: &mut Vec<E>) {
-    propagate_forwards_used_once_core(true, &HashSet::new(), events);
+    propagate_forwards_used_once_core(SIMPLIFY_ORDER, &HashSet::new(), events);
+}
+
+/// Like [propagate_forwards_used_once], but for a trace covering
+/// several instructions, which only propagates definitions within
+/// each instruction's segment of the trace (as found by
+/// [crate::trace::instruction_segments]). This keeps the events for
+/// each instruction in its own segment, so the trace can still be
+/// split after simplification. The events should be in
+/// [SIMPLIFY_ORDER].
+pub fn propagate_forwards_used_once_segments<B: BV, E: BorrowMut<Event<B>>>(events: &mut Vec<E>) {
+    let mut execution: Vec<&Event<B>> = events.iter().map(|event| event.borrow()).collect();
+    SIMPLIFY_ORDER.convert(EventOrder::Execution, &mut execution);
+
+    let mut cross_syms = HashSet::new();
+    let mut defined = HashSet::new();
+    for segment in trace::instruction_segments(&execution) {
+        let segment = &execution[segment.events];
+        for (sym, n) in calculate_uses(segment) {
+            if n > 0 && defined.contains(&sym) {
+                cross_syms.insert(sym);
+            }
+        }
+        for event in segment {
+            if let Event::Smt(Def::DeclareConst(sym, _), _, _) | Event::Smt(Def::DefineConst(sym, _), _, _) = event {
+                defined.insert(*sym);
+            }
+        }
+    }
+
+    propagate_forwards_used_once_core(SIMPLIFY_ORDER, &cross_syms, events)
 }
 
 fn find_cross_segment_syms_descend<B: BV>(
@@ -914,7 +1276,7 @@ fn find_cross_segment_syms<B: BV>(event_tree: &EventTree<B>) -> HashSet<Sym> {
 }
 
 fn propagate_forwards_used_once_descent<B: BV>(cross_syms: &HashSet<Sym>, event_tree: &mut EventTree<B>) {
-    propagate_forwards_used_once_core(false, cross_syms, &mut event_tree.prefix);
+    propagate_forwards_used_once_core(EventOrder::Execution, cross_syms, &mut event_tree.prefix);
     for fork in &mut event_tree.forks {
         propagate_forwards_used_once_descent(cross_syms, fork);
     }
@@ -927,13 +1289,16 @@ pub fn propagate_forwards_used_once_tree<B: BV>(event_tree: &mut EventTree<B>) {
     propagate_forwards_used_once_descent(&cross_syms, event_tree);
 }
 
-/// Evaluate SMT subexpressions if all their arguments are constant
+/// Evaluate SMT subexpressions if all their arguments are constant,
+/// then apply the rewrite rules from [crate::smt::rewrite] to
+/// simplify any that remain
 pub fn eval<B: BV, E: BorrowMut<Event<B>>>(events: &mut Vec<E>) {
     for event in events.iter_mut() {
         match event.borrow_mut() {
             Event::Smt(Def::DefineConst(_, exp), _, _) | Event::Smt(Def::Assert(exp), _, _) => {
                 let e = std::mem::replace(exp, Exp::Bool(false));
                 *exp = e.eval();
+                rewrite::rewrite(exp)
             }
             _ => (),
         }
@@ -972,6 +1337,21 @@ pub fn commute_extract_tree<B: BV>(event_tree: &mut EventTree<B>) {
     event_tree.map(&commute_extract);
 }
 
+/// Simplify a trace (in [EventOrder::Execution] order) for a path
+/// which is still being executed, see
+/// [crate::smt::Trace::simplify_over]. Only simplifications which
+/// remain valid as events are added to the end of the trace are
+/// applied, so no definitions are removed, as later events could use
+/// them.
+pub fn simplify_partial_trace<B: BV>(events: &mut Vec<Event<B>>) {
+    EventOrder::Execution.convert(SIMPLIFY_ORDER, events);
+    remove_extra_register_fields(events);
+    remove_repeated_register_reads(events);
+    eval(events);
+    commute_extract(events);
+    SIMPLIFY_ORDER.convert(EventOrder::Execution, events)
+}
+
 fn accessor_to_string(acc: &[Accessor], symtab: &Symtab) -> String {
     acc.iter()
         .map(|elem| elem.to_string(symtab))
@@ -1089,11 +1469,15 @@ impl<B: BV> EventTree<B> {
         evtree
     }
 
+    /// Create an event tree from a single path, which must be in
+    /// [EventOrder::Execution] order.
     pub fn from_events<E: Borrow<Event<B>>>(events: &[E]) -> Self {
         let broken = break_into_forks(events);
         Self::from_broken_events(&broken)
     }
 
+    /// Add another path to the tree, again in [EventOrder::Execution]
+    /// order.
     pub fn add_events<E: Borrow<Event<B>>>(&mut self, events: &[E]) {
         let broken = break_into_forks(events);
 
@@ -1179,6 +1563,32 @@ pub struct WriteOpts {
     pub prefix: bool,
     /// Hide uninteresting parts of the trace
     pub hide_uninteresting: bool,
+    /// Annotate register, memory, and abstract call events with a
+    /// comment giving the Sail source location that generated them
+    pub source_locations: bool,
+    /// The order of the events passed to the writer
+    pub events_order: EventOrder,
+    /// The order in which events are printed. Ignored for event
+    /// trees, which are always printed in execution order.
+    pub print_order: EventOrder,
+    /// Kinds of event which are not printed
+    pub hide_kinds: HashSet<EventKind>,
+    /// Registers whose writes are not printed, e.g. counters which
+    /// are updated by every instruction
+    pub hide_register_writes: HashSet<Name>,
+    /// Print a run of identical consecutive abstract events once,
+    /// annotated with the length of the run
+    pub collapse_abstract: bool,
+    /// Print the values in events with [Val::write_pretty], rather
+    /// than in SMT-like syntax
+    pub pretty_values: bool,
+    /// Start each trace with `(format-version N)`, giving the
+    /// [TRACE_FORMAT_VERSION] it was written with
+    pub format_version: bool,
+    /// Names to print as `|name|` in place of some variables, such as
+    /// the symbolic segments of a partial instruction. Traces printed
+    /// with names cannot be read back by [read_traces].
+    pub variable_names: HashMap<Sym, String>,
 }
 
 impl WriteOpts {
@@ -1192,6 +1602,27 @@ impl WriteOpts {
             indent: 0,
             prefix: false,
             hide_uninteresting: false,
+            source_locations: false,
+            events_order: EventOrder::Execution,
+            print_order: EventOrder::Execution,
+            hide_kinds: HashSet::new(),
+            hide_register_writes: HashSet::new(),
+            collapse_abstract: false,
+            pretty_values: false,
+            format_version: false,
+            variable_names: HashMap::new(),
+        }
+    }
+}
+
+impl WriteOpts {
+    fn hides<B>(&self, event: &Event<B>) -> bool {
+        if self.hide_kinds.contains(&EventKind::of(event)) {
+            return true;
+        }
+        match event {
+            WriteReg(name, _, _, _) => self.hide_register_writes.contains(name),
+            _ => false,
         }
     }
 }
@@ -1207,10 +1638,38 @@ impl Default for WriteOpts {
             indent: 0,
             prefix: false,
             hide_uninteresting: false,
+            source_locations: false,
+            events_order: EventOrder::Execution,
+            print_order: EventOrder::Execution,
+            hide_kinds: HashSet::new(),
+            hide_register_writes: HashSet::new(),
+            collapse_abstract: false,
+            pretty_values: false,
+            format_version: false,
+            variable_names: HashMap::new(),
         }
     }
 }
 
+/// The location to annotate an event with when
+/// `WriteOpts::source_locations` is set. Forks, guards, and SMT
+/// definitions are excluded as they already print their location.
+fn annotation_location<B: BV>(event: &Event<B>) -> Option<SourceLoc> {
+    match event {
+        ReadReg(n, ..) if *n == HAVE_EXCEPTION => None,
+        Abstract { .. }
+        | ReadReg(..)
+        | WriteReg(..)
+        | ReadMem { .. }
+        | WriteMem { .. }
+        | AddressAnnounce { .. }
+        | Branch { .. }
+        | BlockOp { .. }
+        | Log { .. } => event.source_loc(),
+        _ => None,
+    }
+}
+
 #[allow(clippy::bool_to_int_with_if)]
 pub fn write_bits_prefix(buf: &mut dyn Write, prefix: &str, upper_case: bool, bits: &[bool]) -> std::io::Result<()> {
     write!(buf, "{}", prefix)?;
@@ -1263,16 +1722,20 @@ fn write_ty(buf: &mut dyn Write, ty: &Ty, symtab: &Symtab) -> std::io::Result<()
         }
         Float(ebits, sbits) => write!(buf, "(_ FloatingPoint {} {})", ebits, sbits),
         RoundingMode => write!(buf, "RoundingMode"),
+        Int => write!(buf, "Int"),
     }
 }
 
-trait WriteVar {
+pub trait WriteVar {
     fn write_var(&self, buf: &mut dyn Write, opts: &WriteOpts) -> std::io::Result<()>;
 }
 
 impl WriteVar for Sym {
     fn write_var(&self, buf: &mut dyn Write, opts: &WriteOpts) -> std::io::Result<()> {
-        write!(buf, "{}{}", opts.variable_prefix, self)
+        match opts.variable_names.get(self) {
+            Some(name) => write!(buf, "|{}|", name),
+            None => write!(buf, "{}{}", opts.variable_prefix, self),
+        }
     }
 }
 
@@ -1299,10 +1762,24 @@ impl WriteVar for Loc<String> {
     }
 }
 
-fn write_exp<B: BV, V: WriteVar>(
+/// Determines how enumeration members are named when writing
+/// expressions. Traces use the member names from the architecture.
+pub trait WriteEnum {
+    fn write_enum(&self, buf: &mut dyn Write, member: EnumMember) -> std::io::Result<()>;
+}
+
+impl<'ir, B: BV> WriteEnum for SharedState<'ir, B> {
+    fn write_enum(&self, buf: &mut dyn Write, member: EnumMember) -> std::io::Result<()> {
+        let members = self.type_info.enums.get(&member.enum_id.to_name()).expect("Failed to get enumeration");
+        let name = zencode::decode(self.symtab.to_str(members[member.member]));
+        write!(buf, "|{}|", name)
+    }
+}
+
+fn write_exp<E: WriteEnum + ?Sized, V: WriteVar>(
     buf: &mut dyn Write,
     exp: &Exp<V>,
-    shared_state: &SharedState<B>,
+    shared_state: &E,
     opts: &WriteOpts,
 ) -> std::io::Result<()> {
     use Exp::*;
@@ -1310,11 +1787,7 @@ fn write_exp<B: BV, V: WriteVar>(
         Var(v) => v.write_var(buf, opts),
         Bits(bv) => write_bits(buf, bv),
         Bits64(bv) => write_bits64(buf, bv.lower_u64(), bv.len()),
-        Enum(e) => {
-            let members = shared_state.type_info.enums.get(&e.enum_id.to_name()).expect("Failed to get enumeration");
-            let name = zencode::decode(shared_state.symtab.to_str(members[e.member]));
-            write!(buf, "|{}|", name)
-        }
+        Enum(e) => shared_state.write_enum(buf, *e),
         Bool(b) => write!(buf, "{}", b),
         Eq(lhs, rhs) => write_binop(buf, "=", lhs, rhs, shared_state, opts),
         Neq(lhs, rhs) => {
@@ -1505,14 +1978,37 @@ fn write_exp<B: BV, V: WriteVar>(
             write_exp(buf, z, shared_state, opts)?;
             write!(buf, ")")
         }
+        Int(n) if n.sign() == num_bigint::Sign::Minus => write!(buf, "(- {})", n.magnitude()),
+        Int(n) => write!(buf, "{}", n),
+        IntUnary(op, exp) => {
+            use smtlib::IntUnary::*;
+            match op {
+                Neg => write_unop(buf, "-", exp, shared_state, opts),
+                FromSigned(_) => write_unop(buf, "sbv_to_int", exp, shared_state, opts),
+                ToBits(sz) => write_unop(buf, &format!("(_ int_to_bv {})", sz), exp, shared_state, opts),
+            }
+        }
+        IntBinary(op, lhs, rhs) => {
+            use smtlib::IntBinary::*;
+            let op = match op {
+                Add => "+",
+                Sub => "-",
+                Mul => "*",
+                Le => "<=",
+                Lt => "<",
+                Ge => ">=",
+                Gt => ">",
+            };
+            write_binop(buf, op, lhs, rhs, shared_state, opts)
+        }
     }
 }
 
-fn write_unop<B: BV, V: WriteVar>(
+fn write_unop<E: WriteEnum + ?Sized, V: WriteVar>(
     buf: &mut dyn Write,
     op: &str,
     exp: &Exp<V>,
-    shared_state: &SharedState<B>,
+    shared_state: &E,
     opts: &WriteOpts,
 ) -> std::io::Result<()> {
     write!(buf, "({} ", op)?;
@@ -1520,12 +2016,12 @@ fn write_unop<B: BV, V: WriteVar>(
     write!(buf, ")")
 }
 
-fn write_binop<B: BV, V: WriteVar>(
+fn write_binop<E: WriteEnum + ?Sized, V: WriteVar>(
     buf: &mut dyn Write,
     op: &str,
     lhs: &Exp<V>,
     rhs: &Exp<V>,
-    shared_state: &SharedState<B>,
+    shared_state: &E,
     opts: &WriteOpts,
 ) -> std::io::Result<()> {
     write!(buf, "({} ", op)?;
@@ -1535,6 +2031,85 @@ fn write_binop<B: BV, V: WriteVar>(
     write!(buf, ")")
 }
 
+/// Write a single SMT expression over trace variables, using the same
+/// syntax as expressions within traces.
+pub fn write_smt_exp<E: WriteEnum + ?Sized, V: WriteVar>(
+    buf: &mut dyn Write,
+    exp: &Exp<V>,
+    shared_state: &E,
+    opts: &WriteOpts,
+) -> std::io::Result<()> {
+    write_exp(buf, exp, shared_state, opts)
+}
+
+/// Record the types of every constant and function declared or
+/// defined in `events`, which are in `order`.
+fn infer_event_types<B: BV>(
+    events: &[Event<B>],
+    order: EventOrder,
+    tcx: &mut Cow<HashMap<Sym, Ty>>,
+    ftcx: &mut Cow<HashMap<Sym, (Vec<Ty>, Ty)>>,
+) {
+    let events: Box<dyn Iterator<Item = &Event<B>>> = match order {
+        EventOrder::Execution => Box::new(events.iter()),
+        EventOrder::Reverse => Box::new(events.iter().rev()),
+    };
+    for event in events {
+        match event {
+            Smt(Def::DeclareConst(v, ty), _, _) => {
+                tcx.to_mut().insert(*v, ty.clone());
+            }
+            Smt(Def::DeclareFun(v, arg_tys, result_ty), _, _) => {
+                ftcx.to_mut().insert(*v, (arg_tys.clone(), result_ty.clone()));
+            }
+            Smt(Def::DefineConst(v, exp), _, _) => {
+                if let Some(ty) = exp.infer(tcx, ftcx) {
+                    tcx.to_mut().insert(*v, ty);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The number of abstract events at the start of `events` which are
+/// identical apart from their source locations, or 1 if the first
+/// event is not abstract.
+fn abstract_run_length<B: BV>(events: &[&Event<B>]) -> usize {
+    let same = |ev1: &Event<B>, ev2: &Event<B>| match (ev1, ev2) {
+        (
+            Abstract { name: n1, primitive: p1, args: a1, return_value: r1, .. },
+            Abstract { name: n2, primitive: p2, args: a2, return_value: r2, .. },
+        ) => n1 == n2 && p1 == p2 && a1 == a2 && r1 == r2,
+        _ => false,
+    };
+    match events.split_first() {
+        Some((first, rest)) if first.is_abstract() => 1 + rest.iter().take_while(|ev| same(first, ev)).count(),
+        _ => 1,
+    }
+}
+
+fn write_value<B: BV>(
+    buf: &mut dyn Write,
+    value: &Val<B>,
+    shared_state: &SharedState<B>,
+    opts: &WriteOpts,
+) -> std::io::Result<()> {
+    if opts.pretty_values {
+        value.write_pretty(buf, shared_state)
+    } else {
+        value.write(buf, shared_state)
+    }
+}
+
+fn value_string<B: BV>(value: &Val<B>, shared_state: &SharedState<B>, opts: &WriteOpts) -> String {
+    if opts.pretty_values {
+        value.to_pretty_string(shared_state)
+    } else {
+        value.to_string(shared_state)
+    }
+}
+
 pub fn write_events_in_context<B: BV>(
     buf: &mut dyn Write,
     events: &[Event<B>],
@@ -1549,8 +2124,27 @@ pub fn write_events_in_context<B: BV>(
 
     if !opts.just_smt {
         write!(buf, "{}(trace", indent).unwrap();
+        if opts.format_version && !opts.prefix {
+            write!(buf, "\n{}  (format-version {})", indent, TRACE_FORMAT_VERSION)?
+        }
+    }
+    if opts.types && opts.print_order == EventOrder::Reverse {
+        // Types of defined constants are inferred as they are printed,
+        // so when printing in reverse they must be inferred up front.
+        infer_event_types(events, opts.events_order, tcx, ftcx)
     }
-    for event in events.iter().filter(|ev| !opts.just_smt || ev.is_smt()) {
+
+    let events: Box<dyn Iterator<Item = &Event<B>>> =
+        if opts.events_order == opts.print_order { Box::new(events.iter()) } else { Box::new(events.iter().rev()) };
+    let events: Vec<&Event<B>> = events.filter(|ev| (!opts.just_smt || ev.is_smt()) && !opts.hides(ev)).collect();
+    let mut skip = 0;
+    for (i, event) in events.iter().copied().enumerate() {
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        let repeats = if opts.collapse_abstract { abstract_run_length(&events[i..]) } else { 1 };
+        skip = repeats - 1;
         require_newline = false;
         (match event {
             Fork(n, _, _, loc) => {
@@ -1566,38 +2160,73 @@ pub fn write_events_in_context<B: BV>(
                 }
             }
 
-            Abstract { name, primitive, args, return_value } => {
+            Abstract { name, primitive, args, return_value, info: _ } => {
                 let name = zencode::decode(symtab.to_str(*name));
                 if *primitive {
                     write!(buf, "\n{}  (abstract-primop |{}| ", indent, name)?;
                 } else {
                     write!(buf, "\n{}  (abstract-call |{}| ", indent, name)?;
                 }
-                return_value.write(buf, shared_state)?;
+                write_value(buf, return_value, shared_state, opts)?;
                 write!(buf, " ")?;
                 if let Some((last, elems)) = args.split_last() {
                     for elem in elems {
-                        elem.write(buf, shared_state)?;
+                        write_value(buf, elem, shared_state, opts)?;
                         write!(buf, " ")?
                     }
-                    last.write(buf, shared_state)?;
+                    write_value(buf, last, shared_state, opts)?;
                 } else {
                     write!(buf, "nil")?
                 }
                 write!(buf, ")")
             }
 
+            DecodeCall { name, args, info: _ } => {
+                write!(buf, "\n{}  (decode-call |{}|", indent, zencode::decode(symtab.to_str(*name)))?;
+                for arg in args {
+                    write!(buf, " ")?;
+                    write_value(buf, arg, shared_state, opts)?
+                }
+                write!(buf, ")")
+            }
+
+            DecodeReturn { name, return_value } => {
+                write!(buf, "\n{}  (decode-return |{}| ", indent, zencode::decode(symtab.to_str(*name)))?;
+                write_value(buf, return_value, shared_state, opts)?;
+                write!(buf, ")")
+            }
+
+            DecodeValue { name, args, value, info: _ } => {
+                write!(buf, "\n{}  (decode-value |{}| ", indent, zencode::decode(symtab.to_str(*name)))?;
+                write_value(buf, value, shared_state, opts)?;
+                for arg in args {
+                    write!(buf, " ")?;
+                    write_value(buf, arg, shared_state, opts)?
+                }
+                write!(buf, ")")
+            }
+
+            DecodeTest { args, value, info: _ } => {
+                write!(buf, "\n{}  (decode-test ", indent)?;
+                write_value(buf, value, shared_state, opts)?;
+                for arg in args {
+                    write!(buf, " ")?;
+                    write_value(buf, arg, shared_state, opts)?
+                }
+                write!(buf, ")")
+            }
+
             AssumeFun { name, args, return_value } => {
                 let name = zencode::decode(symtab.to_str(*name));
                 write!(buf, "\n{}  (function-assumption |{}| ", indent, name)?;
-                return_value.write(buf, shared_state)?;
+                write_value(buf, return_value, shared_state, opts)?;
                 write!(buf, " ")?;
                 if let Some((last, elems)) = args.split_last() {
                     for elem in elems {
-                        elem.write(buf, shared_state)?;
+                        write_value(buf, elem, shared_state, opts)?;
                         write!(buf, " ")?
                     }
-                    last.write(buf, shared_state)?;
+                    write_value(buf, last, shared_state, opts)?;
                 } else {
                     write!(buf, "nil")?
                 }
@@ -1607,14 +2236,14 @@ pub fn write_events_in_context<B: BV>(
             UseFunAssumption { name, args, return_value } => {
                 let name = zencode::decode(symtab.to_str(*name));
                 write!(buf, "\n{}  (use-function-assumption |{}| ", indent, name)?;
-                return_value.write(buf, shared_state)?;
+                write_value(buf, return_value, shared_state, opts)?;
                 write!(buf, " ")?;
                 if let Some((last, elems)) = args.split_last() {
                     for elem in elems {
-                        elem.write(buf, shared_state)?;
+                        write_value(buf, elem, shared_state, opts)?;
                         write!(buf, " ")?
                     }
-                    last.write(buf, shared_state)?;
+                    write_value(buf, last, shared_state, opts)?;
                 } else {
                     write!(buf, "nil")?
                 }
@@ -1630,7 +2259,9 @@ pub fn write_events_in_context<B: BV>(
                 match def {
                     Def::DeclareConst(v, ty) => {
                         tcx.to_mut().insert(*v, ty.clone());
-                        write!(buf, "(declare-const {}{} ", opts.variable_prefix, v)?;
+                        write!(buf, "(declare-const ")?;
+                        v.write_var(buf, opts)?;
+                        write!(buf, " ")?;
                         write_ty(buf, ty, symtab)?;
                         require_newline = true;
                         write!(buf, ") ; {:?}", loc)?
@@ -1690,34 +2321,37 @@ pub fn write_events_in_context<B: BV>(
 
             Smt(..) => Ok(()),
 
-            ReadMem { value, read_kind, address, bytes, tag_value, opts: _, region: _ } => {
+            ReadMem { value, read_kind, address, bytes, tag_value, opts: _, region, info: _ } => {
                 write!(buf, "\n{}  (read-mem ", indent)?;
-                value.write(buf, shared_state)?;
+                write_value(buf, value, shared_state, opts)?;
                 write!(buf, " ")?;
-                read_kind.write(buf, shared_state)?;
+                write_value(buf, read_kind, shared_state, opts)?;
                 write!(buf, " ")?;
-                address.write(buf, shared_state)?;
+                write_value(buf, address, shared_state, opts)?;
                 write!(buf, " {}", bytes)?;
                 match tag_value {
                     None => (),
                     Some(v) => {
                         write!(buf, " ")?;
-                        v.write(buf, shared_state)?
+                        write_value(buf, v, shared_state, opts)?
                     }
                 }
+                if event.is_callback() {
+                    write!(buf, " (callback |{}|)", region)?
+                }
                 write!(buf, ")")
             }
 
-            WriteMem { value, write_kind, address, data, bytes, tag_value, opts: _, region: _ } => {
+            WriteMem { value, write_kind, address, data, bytes, tag_value, opts: _, region, info: _ } => {
                 if *bytes == 0 && tag_value.is_some() {
                     write!(
                         buf,
                         "\n{}  (write-mem-tag v{} {} {} {})",
                         indent,
                         value,
-                        write_kind.to_string(shared_state),
-                        address.to_string(shared_state),
-                        tag_value.as_ref().unwrap().to_string(shared_state),
+                        value_string(write_kind, shared_state, opts),
+                        value_string(address, shared_state, opts),
+                        value_string(tag_value.as_ref().unwrap(), shared_state, opts),
                     )
                 } else {
                     write!(
@@ -1725,29 +2359,45 @@ pub fn write_events_in_context<B: BV>(
                         "\n{}  (write-mem v{} {} {} {} {}",
                         indent,
                         value,
-                        write_kind.to_string(shared_state),
-                        address.to_string(shared_state),
-                        data.to_string(shared_state),
+                        value_string(write_kind, shared_state, opts),
+                        value_string(address, shared_state, opts),
+                        value_string(data, shared_state, opts),
                         bytes
                     )?;
                     match tag_value {
                         None => (),
                         Some(v) => {
                             write!(buf, " ")?;
-                            v.write(buf, shared_state)?
+                            write_value(buf, v, shared_state, opts)?
                         }
                     }
+                    if event.is_callback() {
+                        write!(buf, " (callback |{}|)", region)?
+                    }
                     write!(buf, ")")
                 }
             }
 
-            AddressAnnounce { address } => {
-                write!(buf, "\n{} (address-announce {})", indent, address.to_string(shared_state))
+            AddressAnnounce { address, info: _ } => {
+                write!(buf, "\n{} (address-announce {})", indent, value_string(address, shared_state, opts))
             }
 
-            Branch { address } => write!(buf, "\n{}  (branch-address {})", indent, address.to_string(shared_state)),
+            Branch { address, info: _ } => {
+                write!(buf, "\n{}  (branch-address {})", indent, value_string(address, shared_state, opts))
+            }
+
+            BlockOp { name, address, bytes, info: _ } => {
+                write!(
+                    buf,
+                    "\n{}  (block-op |{}| {} {})",
+                    indent,
+                    zencode::decode(symtab.to_str(*name)),
+                    value_string(address, shared_state, opts),
+                    bytes
+                )
+            }
 
-            WriteReg(n, acc, v) => {
+            WriteReg(n, acc, v, _) => {
                 write!(
                     buf,
                     "\n{}  (write-reg |{}| {} ",
@@ -1755,11 +2405,11 @@ pub fn write_events_in_context<B: BV>(
                     zencode::decode(symtab.to_str(*n)),
                     accessor_to_string(acc, symtab)
                 )?;
-                v.write(buf, shared_state)?;
+                write_value(buf, v, shared_state, opts)?;
                 write!(buf, ")")
             }
 
-            ReadReg(n, acc, v) => {
+            ReadReg(n, acc, v, _) => {
                 if *n == HAVE_EXCEPTION {
                     Ok(())
                 } else {
@@ -1770,7 +2420,7 @@ pub fn write_events_in_context<B: BV>(
                         zencode::decode(symtab.to_str(*n)),
                         accessor_to_string(acc, symtab)
                     )?;
-                    v.write(buf, shared_state)?;
+                    write_value(buf, v, shared_state, opts)?;
                     write!(buf, ")")
                 }
             }
@@ -1784,7 +2434,38 @@ pub fn write_events_in_context<B: BV>(
 
             Cycle => write!(buf, "\n{}  (cycle)", indent),
 
-            Instr(value) => write!(buf, "\n{}  (instr {})", indent, value.to_string(shared_state)),
+            Guard { function, taken, info } => write!(
+                buf,
+                "\n{}  (guard |{}| {} \"{}\")",
+                indent,
+                zencode::decode(symtab.to_str(*function)),
+                taken,
+                info.location_string(symtab.files())
+            ),
+
+            LoopBound { function, bound, info } => write!(
+                buf,
+                "\n{}  (loop-bound |{}| {} \"{}\")",
+                indent,
+                zencode::decode(symtab.to_str(*function)),
+                bound,
+                info.location_string(symtab.files())
+            ),
+
+            Undefined { function, kind, info } => write!(
+                buf,
+                "\n{}  (undefined |{}| {} \"{}\")",
+                indent,
+                zencode::decode(symtab.to_str(*function)),
+                kind,
+                info.location_string(symtab.files())
+            ),
+
+            Log { label, value, .. } => {
+                write!(buf, "\n{}  (log {:?} {})", indent, label, value_string(value, shared_state, opts))
+            }
+
+            Instr(value) => write!(buf, "\n{}  (instr {})", indent, value_string(value, shared_state, opts)),
 
             Assume(constraint) => {
                 write!(buf, "\n{}  (assume ", indent)?;
@@ -1800,10 +2481,20 @@ pub fn write_events_in_context<B: BV>(
                     indent,
                     zencode::decode(symtab.to_str(*n)),
                     accessor_to_string(acc, symtab),
-                    v.to_string(shared_state)
+                    value_string(v, shared_state, opts)
                 )
             }
-        })?
+        })?;
+        if repeats > 1 {
+            write!(buf, " ; repeated {} times", repeats)?;
+            require_newline = true
+        }
+        if opts.source_locations {
+            if let Some(info) = annotation_location(event) {
+                write!(buf, " ; {}", info.short_location_string(symtab.files()))?;
+                require_newline = true
+            }
+        }
     }
     if require_newline {
         write!(buf, "\n{}", indent)?;
@@ -1874,7 +2565,12 @@ pub fn write_event_tree<B: BV>(
     shared_state: &SharedState<B>,
     opts: &WriteOpts,
 ) {
-    let mut opts = WriteOpts { prefix: true, ..opts.clone() };
+    let mut opts = WriteOpts {
+        prefix: true,
+        events_order: EventOrder::Execution,
+        print_order: EventOrder::Execution,
+        ..opts.clone()
+    };
     let tcx: HashMap<Sym, Ty> = HashMap::new();
     let ftcx: HashMap<Sym, (Vec<Ty>, Ty)> = HashMap::new();
 
@@ -1886,6 +2582,8 @@ pub fn write_event_tree<B: BV>(
 mod tests {
     use super::*;
     use crate::bitvector::b64::B64;
+    use crate::ir::IRTypeInfo;
+    use crate::smt::{EnumId, ReadOpts};
     use crate::source_loc::SourceLoc;
 
     #[test]
@@ -1934,6 +2632,34 @@ mod tests {
         evtree.add_events(&events2);
     }
 
+    #[test]
+    fn propagate_within_segments() {
+        use crate::smt::DefAttrs;
+        let v = Sym::from_u32(0);
+        let mut events: Vec<Event<B64>> = vec![
+            Event::Cycle,
+            Event::Smt(
+                Def::DefineConst(v, Exp::Bits64(B64::from_u64(0x123))),
+                DefAttrs::default(),
+                SourceLoc::unknown(),
+            ),
+            Event::Cycle,
+            Event::Smt(
+                Def::Assert(Exp::Eq(Box::new(Exp::Var(v)), Box::new(Exp::Bits64(B64::from_u64(0x123))))),
+                DefAttrs::default(),
+                SourceLoc::unknown(),
+            ),
+        ];
+        SIMPLIFY_ORDER.convert(EventOrder::Execution, &mut events);
+
+        let mut whole = events.clone();
+        propagate_forwards_used_once(&mut whole);
+        assert_eq!(whole.len(), 3);
+
+        propagate_forwards_used_once_segments(&mut events);
+        assert_eq!(events.len(), 4);
+    }
+
     #[test]
     fn remove_unused_in_one_branch() {
         use crate::smt::DefAttrs;
@@ -1971,7 +2697,7 @@ mod tests {
                 DefAttrs::default(),
                 SourceLoc::unknown(),
             ),
-            Event::WriteReg(Name::from_u32(0), vec![], Val::Symbolic(Sym::from_u32(4))),
+            Event::WriteReg(Name::from_u32(0), vec![], Val::Symbolic(Sym::from_u32(4)), SourceLoc::unknown()),
         ];
         let mut evtree = EventTree::from_events(&events1);
         evtree.add_events(&events2);
@@ -1987,7 +2713,7 @@ mod tests {
 
     #[test]
     fn remove_repeated_regs() {
-        let event = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)));
+        let event = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)), SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event.clone(), Event::Cycle, event];
         remove_repeated_register_reads(&mut events);
         assert_eq!(events.len(), 2);
@@ -1995,15 +2721,15 @@ mod tests {
 
         // We shouldn't see consecutive reads with different values,
         // but we want to keep them if we do.
-        let event_1 = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)));
-        let event_2 = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x456)));
+        let event_1 = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)), SourceLoc::unknown());
+        let event_2 = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x456)), SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event_1, Event::Cycle, event_2];
         remove_repeated_register_reads(&mut events);
         assert_eq!(events.len(), 3);
         assert!(matches!(events[1], Event::Cycle));
 
-        let event_r = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)));
-        let event_w = Event::WriteReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)));
+        let event_r = Event::ReadReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)), SourceLoc::unknown());
+        let event_w = Event::WriteReg(Name::from_u32(0), vec![], Val::Bits(B64::from_u64(0x123)), SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event_r.clone(), Event::Cycle, event_w, event_r];
         remove_repeated_register_reads(&mut events);
         assert_eq!(events.len(), 4);
@@ -2014,8 +2740,8 @@ mod tests {
         let val = Val::Bits(B64::from_u64(0x123));
         let val_2 = Val::Struct([(Name::from_u32(2), val)].iter().cloned().collect());
         let val_1 = Val::Struct([(Name::from_u32(1), val_2.clone())].iter().cloned().collect());
-        let event_r = Event::ReadReg(Name::from_u32(0), vec![field_1.clone(), field_2], val_1);
-        let event_w = Event::WriteReg(Name::from_u32(0), vec![field_1], val_2);
+        let event_r = Event::ReadReg(Name::from_u32(0), vec![field_1.clone(), field_2], val_1, SourceLoc::unknown());
+        let event_w = Event::WriteReg(Name::from_u32(0), vec![field_1], val_2, SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event_r.clone(), Event::Cycle, event_w, event_r];
         remove_repeated_register_reads(&mut events);
         assert_eq!(events.len(), 4);
@@ -2028,28 +2754,354 @@ mod tests {
         let field_2 = Accessor::Field(Name::from_u32(2));
         let val = Val::Bits(B64::from_u64(0x123)); // Values don't matter here
         let event_a = Event::AssumeReg(Name::from_u32(0), vec![field_1.clone(), field_2.clone()], val.clone());
-        let event_r = Event::ReadReg(Name::from_u32(0), vec![field_1.clone()], val.clone());
+        let event_r = Event::ReadReg(Name::from_u32(0), vec![field_1.clone()], val.clone(), SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event_r.clone(), event_a.clone()];
         remove_unused_register_assumptions(&mut events);
         assert_eq!(events.len(), 2);
 
         // We could remove the assumption here, but I decided to keep things simple
-        let event_w = Event::WriteReg(Name::from_u32(0), vec![], val.clone());
+        let event_w = Event::WriteReg(Name::from_u32(0), vec![], val.clone(), SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event_r.clone(), event_w.clone(), event_a.clone()];
         remove_unused_register_assumptions(&mut events);
         assert_eq!(events.len(), 3);
 
         // An earlier write shouldn't stop the assumption being removed
         // (important because a write will appear for each assume)
-        let event_w = Event::WriteReg(Name::from_u32(0), vec![], val.clone());
+        let event_w = Event::WriteReg(Name::from_u32(0), vec![], val.clone(), SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event_r.clone(), event_a.clone(), event_w.clone()];
         remove_unused_register_assumptions(&mut events);
         assert_eq!(events.len(), 3);
 
-        let event_r = Event::ReadReg(Name::from_u32(1), vec![field_1.clone(), field_2.clone()], val);
+        let event_r =
+            Event::ReadReg(Name::from_u32(1), vec![field_1.clone(), field_2.clone()], val, SourceLoc::unknown());
         let mut events: Vec<Event<B64>> = vec![event_r.clone(), event_a.clone()];
         remove_unused_register_assumptions(&mut events);
         assert_eq!(events.len(), 1);
-        assert!(matches!(events[0], Event::ReadReg(_, _, _)));
+        assert!(matches!(events[0], Event::ReadReg(_, _, _, _)));
+    }
+
+    #[test]
+    fn minimize_for_register_write() {
+        use crate::smt::DefAttrs;
+        use Exp::*;
+        let (r0, r1, r2, r3) = (Name::from_u32(0), Name::from_u32(1), Name::from_u32(2), Name::from_u32(3));
+        let (v0, v1, v2) = (Sym::from_u32(0), Sym::from_u32(1), Sym::from_u32(2));
+        let smt = |def| Event::Smt(def, DefAttrs::default(), SourceLoc::unknown());
+        let mut events: Vec<Event<B64>> = vec![
+            smt(Def::DeclareConst(v0, Ty::BitVec(64))),
+            Event::ReadReg(r0, vec![], Val::Symbolic(v0), SourceLoc::unknown()),
+            smt(Def::DeclareConst(v1, Ty::BitVec(64))),
+            Event::ReadReg(r1, vec![], Val::Symbolic(v1), SourceLoc::unknown()),
+            smt(Def::DefineConst(v2, Bvadd(Box::new(Var(v0)), Box::new(bits64(1, 64))))),
+            Event::WriteReg(r2, vec![], Val::Symbolic(v2), SourceLoc::unknown()),
+            Event::WriteReg(r3, vec![], Val::Symbolic(v1), SourceLoc::unknown()),
+        ];
+        assert!(!minimize_for(&mut events, &EventPattern::ReadMem(None, None)));
+        assert_eq!(events.len(), 7);
+        assert!(minimize_for(&mut events, &EventPattern::WriteReg(Some(r2))));
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[1], Event::ReadReg(r, _, _, _) if r == r0));
+        assert!(matches!(events[3], Event::WriteReg(r, _, _, _) if r == r2));
+    }
+
+    #[test]
+    fn log_events_survive_simplification() {
+        use crate::smt::DefAttrs;
+        use Exp::*;
+        let (v0, v1) = (Sym::from_u32(0), Sym::from_u32(1));
+        let smt = |def| Event::Smt(def, DefAttrs::default(), SourceLoc::unknown());
+        let mut events: Vec<Event<B64>> = vec![
+            smt(Def::DeclareConst(v0, Ty::BitVec(64))),
+            Event::ReadReg(Name::from_u32(0), vec![], Val::Symbolic(v0), SourceLoc::unknown()),
+            smt(Def::DefineConst(v1, Bvadd(Box::new(Var(v0)), Box::new(bits64(1, 64))))),
+            Event::Log { label: "sum".to_string(), value: Val::Symbolic(v1), info: SourceLoc::unknown() },
+        ];
+        EventOrder::Execution.convert(SIMPLIFY_ORDER, &mut events);
+        propagate_forwards_used_once(&mut events);
+        remove_unused(&mut events);
+        SIMPLIFY_ORDER.convert(EventOrder::Execution, &mut events);
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[2], Event::Smt(Def::DefineConst(v, _), _, _) if v == v1));
+        assert!(matches!(&events[3], Event::Log { label, .. } if label == "sum"));
+        assert_eq!(EventKind::of(&events[3]), EventKind::parse("log").unwrap());
+    }
+
+    #[test]
+    fn hide_initialization_preserves_order() {
+        let val = Val::Bits(B64::from_u64(0x123));
+        let event_init = Event::WriteReg(Name::from_u32(0), vec![], val.clone(), SourceLoc::unknown());
+        let event_r = Event::ReadReg(Name::from_u32(0), vec![], val, SourceLoc::unknown());
+        let mut events: Vec<Event<B64>> =
+            vec![Event::Instr(Val::Unit), event_init.clone(), Event::Cycle, event_r.clone(), event_init];
+        EventOrder::Execution.convert(SIMPLIFY_ORDER, &mut events);
+        hide_initialization(&mut events);
+        SIMPLIFY_ORDER.convert(EventOrder::Execution, &mut events);
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[0], Event::Instr(_)));
+        assert!(matches!(events[1], Event::Cycle));
+        assert!(matches!(events[2], Event::ReadReg(_, _, _, _)));
+        assert!(matches!(events[3], Event::WriteReg(_, _, _, _)));
+    }
+
+    #[test]
+    fn prune_events() {
+        let (r0, r1, f) = (Name::from_u32(0), Name::from_u32(1), Name::from_u32(2));
+        let call = |loc| Event::Abstract {
+            name: f,
+            primitive: false,
+            args: vec![Val::Bool(true)],
+            return_value: Val::Unit,
+            info: SourceLoc::new(0, loc, 0, loc, 0),
+        };
+        let events: Vec<Event<B64>> = vec![
+            call(1),
+            call(2),
+            call(3),
+            Event::WriteReg(r0, vec![], Val::Unit, SourceLoc::unknown()),
+            Event::WriteReg(r1, vec![], Val::Unit, SourceLoc::unknown()),
+            Event::Cycle,
+        ];
+        let events: Vec<&Event<B64>> = events.iter().collect();
+        assert_eq!(abstract_run_length(&events), 3);
+        assert_eq!(abstract_run_length(&events[2..]), 1);
+        assert_eq!(abstract_run_length(&events[3..]), 1);
+
+        let mut opts = WriteOpts::default();
+        opts.hide_kinds.insert(EventKind::parse("cycle").unwrap());
+        opts.hide_register_writes.insert(r1);
+        let shown: Vec<bool> = events.iter().map(|ev| !opts.hides(ev)).collect();
+        assert_eq!(shown, vec![true, true, true, true, false, false]);
+        assert!(EventKind::parse("cycles").is_err());
+    }
+
+    #[test]
+    fn pretty_values() {
+        let ir =
+            "enum zkind { zA, zB }\nstruct zpair { zfirst: %bv5, zsecond: %enum zkind }\nregister zR : %struct zpair\n";
+        let mut symtab = Symtab::new();
+        let defs = crate::ir_parse::parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let type_info = IRTypeInfo::new(&defs);
+        let (r, first, second, kind) =
+            (symtab.lookup("zR"), symtab.lookup("zfirst"), symtab.lookup("zsecond"), symtab.lookup("zkind"));
+        let shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let mut fields = HashMap::default();
+        fields.insert(first, Val::Bits(B64::new(0x1f, 5)));
+        fields.insert(second, Val::Enum(EnumMember { enum_id: EnumId::from_name(kind), member: 1 }));
+        let events = vec![Event::WriteReg(r, Vec::new(), Val::Struct(fields), SourceLoc::unknown())];
+
+        let mut buf = Vec::new();
+        let opts = WriteOpts { pretty_values: true, ..WriteOpts::default() };
+        write_events_with_opts(&mut buf, &events, &shared_state, &opts).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "(trace\n  (write-reg |R| nil pair { first = 0x1f:5, second = B }))\n"
+        )
+    }
+
+    #[test]
+    fn event_patterns() {
+        let mut symtab = Symtab::new();
+        let sp = symtab.intern("zSP");
+        assert_eq!(EventPattern::parse("write-reg:SP", &symtab, &[]), Ok(EventPattern::WriteReg(Some(sp))));
+        assert_eq!(EventPattern::parse("read-mem", &symtab, &[]), Ok(EventPattern::ReadMem(None, None)));
+        assert_eq!(EventPattern::parse("read-mem:16", &symtab, &[]), Ok(EventPattern::ReadMem(Some(16), None)));
+        assert!(EventPattern::parse("write-mem:SP", &symtab, &[]).is_err());
+        assert!(EventPattern::parse("write-reg:XZR", &symtab, &[]).is_err());
+
+        let read_kind = symtab.intern("zread_kind");
+        let member = |member| EnumMember { enum_id: EnumId::from_name(read_kind), member };
+        let kinds = [AccessKind::new("acquire".to_string(), vec![member(1), member(2)])];
+        let pattern = EventPattern::parse("read-mem:8:acquire", &symtab, &kinds).unwrap();
+        assert_eq!(pattern, EventPattern::ReadMem(Some(8), Some(kinds[0].clone())));
+        assert!(EventPattern::parse("read-mem:release", &symtab, &kinds).is_err());
+
+        let read = |kind, bytes| ReadMem::<B64> {
+            value: Val::Bits(B64::zeros(64)),
+            read_kind: Val::Enum(member(kind)),
+            address: Val::Bits(B64::zeros(64)),
+            bytes,
+            tag_value: None,
+            opts: ReadOpts::default(),
+            region: "default",
+            info: SourceLoc::unknown(),
+        };
+        assert!(pattern.matches(&read(2, 8)));
+        assert!(!pattern.matches(&read(0, 8)));
+        assert!(!pattern.matches(&read(2, 4)))
+    }
+
+    #[test]
+    fn variable_names() {
+        use crate::smt::DefAttrs;
+        let defs: Vec<crate::ir::Def<Name, B64>> = Vec::new();
+        let shared_state = SharedState::new(
+            Symtab::new(),
+            &defs,
+            IRTypeInfo::new(&defs),
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let (v0, v1) = (Sym::from_u32(0), Sym::from_u32(1));
+        let smt = |def| Event::Smt(def, DefAttrs::default(), SourceLoc::unknown());
+        let events: Vec<Event<B64>> = vec![
+            smt(Def::DeclareConst(v0, Ty::BitVec(2))),
+            smt(Def::DefineConst(v1, Exp::Eq(Box::new(Exp::Var(v0)), Box::new(Exp::Bits64(B64::new(0, 2)))))),
+        ];
+
+        let mut buf = Vec::new();
+        let opts = WriteOpts { variable_names: HashMap::from([(v0, "rn".to_string())]), ..WriteOpts::default() };
+        write_events_with_opts(&mut buf, &events, &shared_state, &opts).unwrap();
+        let trace = String::from_utf8(buf).unwrap();
+        assert!(trace.contains("(declare-const |rn| (_ BitVec 2))"));
+        assert!(trace.contains("(define-const v1 (= |rn| #b00))"))
+    }
+
+    #[test]
+    fn read_written_events() {
+        use crate::smt::{DefAttrs, ReadOpts, WriteOpts as MemWriteOpts};
+        use Exp::*;
+        let ir = "enum zkind { zA, zB }\nstruct zpair { zfirst: %bv5, zsecond: %enum zkind }\nregister zR : %struct zpair\nregister zPC : %bv64\n";
+        let mut symtab = Symtab::new();
+        let defs = crate::ir_parse::parse_ir::<B64>(ir, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let type_info = IRTypeInfo::new(&defs);
+        let (r, pc, first, second, kind) = (
+            symtab.lookup("zR"),
+            symtab.lookup("zPC"),
+            symtab.lookup("zfirst"),
+            symtab.lookup("zsecond"),
+            symtab.lookup("zkind"),
+        );
+        let shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let (v0, v1, v2) = (Sym::from_u32(0), Sym::from_u32(1), Sym::from_u32(2));
+        let (v3, v4) = (Sym::from_u32(3), Sym::from_u32(4));
+        let big = num_bigint::BigInt::from(1) << 100u32;
+        let smt = |def| Event::Smt(def, DefAttrs::default(), SourceLoc::unknown());
+        let member = Val::Enum(EnumMember { enum_id: EnumId::from_name(kind), member: 1 });
+        // Struct fields are printed in hash order, so use just one
+        let mut fields = HashMap::default();
+        fields.insert(second, member.clone());
+        let events: Vec<Event<B64>> = vec![
+            Event::Instr(Val::Bits(B64::new(0x01010203, 32))),
+            smt(Def::DeclareConst(v0, Ty::BitVec(64))),
+            smt(Def::DefineEnum(kind, 2)),
+            Event::ReadReg(pc, vec![], Val::Symbolic(v0), SourceLoc::unknown()),
+            smt(Def::DefineConst(v1, Bvadd(Box::new(Var(v0)), Box::new(bits64(4, 64))))),
+            smt(Def::DefineConst(
+                v2,
+                Ite(Box::new(Bool(true)), Box::new(Var(v1)), Box::new(Extract(63, 0, Box::new(Var(v0))))),
+            )),
+            Event::Fork(0, Sym::from_u32(0), 0, SourceLoc::unknown()),
+            Event::ReadMem {
+                value: Val::Symbolic(v2),
+                read_kind: member.clone(),
+                address: Val::Symbolic(v1),
+                bytes: 8,
+                tag_value: None,
+                opts: ReadOpts::default(),
+                region: "default",
+                info: SourceLoc::unknown(),
+            },
+            Event::WriteMem {
+                value: v2,
+                write_kind: Val::Unit,
+                address: Val::Bits(B64::new(0x1000, 64)),
+                data: Val::Bits(B64::new(0xff, 8)),
+                bytes: 1,
+                tag_value: None,
+                opts: MemWriteOpts::callback(),
+                region: "uart",
+                info: SourceLoc::unknown(),
+            },
+            Event::WriteReg(r, vec![Accessor::Field(first)], Val::Struct(fields), SourceLoc::unknown()),
+            Event::Abstract {
+                name: kind,
+                primitive: false,
+                args: vec![],
+                return_value: Val::List(vec![Val::I128(-3), Val::BigInt(-big.clone()), Val::Bool(false)]),
+                info: SourceLoc::unknown(),
+            },
+            Event::Log { label: "a \"label\"".to_string(), value: Val::I64(7), info: SourceLoc::unknown() },
+            Event::DecodeValue {
+                name: first,
+                args: vec![Val::Symbolic(v0), Val::I64(4)],
+                value: Val::Bits(B64::new(3, 5)),
+                info: SourceLoc::unknown(),
+            },
+            Event::DecodeTest {
+                args: vec![Val::Symbolic(v2), Val::Bits(B64::new(1, 64))],
+                value: Val::Bool(false),
+                info: SourceLoc::unknown(),
+            },
+            Event::Assume(Eq(Box::new(Var(Loc::Id("zPC".to_string()))), Box::new(bits64(0, 64)))),
+            smt(Def::DefineConst(
+                v3,
+                IntBinary(
+                    smtlib::IntBinary::Add,
+                    Box::new(IntUnary(smtlib::IntUnary::FromSigned(64), Box::new(Var(v0)))),
+                    Box::new(Int((-5).into())),
+                ),
+            )),
+            smt(Def::DefineConst(
+                v4,
+                IntUnary(
+                    smtlib::IntUnary::ToBits(64),
+                    Box::new(IntBinary(smtlib::IntBinary::Mul, Box::new(Var(v3)), Box::new(Int(big)))),
+                ),
+            )),
+            Event::Cycle,
+        ];
+
+        let write = |events: &[Event<B64>], opts: &WriteOpts| {
+            let mut buf = Vec::new();
+            write_events_with_opts(&mut buf, events, &shared_state, opts).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let opts = WriteOpts { format_version: true, ..WriteOpts::default() };
+        let written = write(&events, &opts);
+        assert!(written.starts_with("(trace\n  (format-version 1)"));
+        let read = read_events(&written, &shared_state).unwrap();
+        assert_eq!(read.len(), events.len());
+        assert_eq!(write(&read, &opts), written);
+        assert!(read[8].is_callback());
+
+        let typed = write(&events, &WriteOpts { types: true, ..WriteOpts::default() });
+        assert_eq!(
+            write(&read_events(&typed, &shared_state).unwrap(), &WriteOpts::default()),
+            write(&events, &WriteOpts::default())
+        );
+
+        assert!(read_events::<B64>("(trace\n  (format-version 99))", &shared_state).is_err());
+        assert!(read_events::<B64>("(trace (frobnicate))", &shared_state).is_err());
+        assert_eq!(read_traces::<B64>("(trace)\n(trace (cycle))", &shared_state).unwrap().len(), 2);
+
+        let commented = "; saved\n(trace ; first\n  (log \"\\u{3bb} (x);\\n\" #x01)\n  (cycle)) ; end";
+        match read_events::<B64>(commented, &shared_state).unwrap().as_slice() {
+            [Event::Log { label, .. }, Event::Cycle] => assert_eq!(label, "\u{3bb} (x);\n"),
+            events => panic!("Unexpected events {:?}", events),
+        }
+        for malformed in ["(trace (cycle)", "(trace))", "(trace (log \"a #x01))", "(trace (log \"\\u{d800}\" #x01))"] {
+            assert!(read_traces::<B64>(malformed, &shared_state).is_err(), "{}", malformed)
+        }
     }
 }
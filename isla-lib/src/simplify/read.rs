@@ -0,0 +1,822 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+// Copyright (c) 2020 Brian Campbell
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Reading traces in the format printed by [super::write_events], so
+//! traces saved as text can be loaded again. The grammar is described
+//! in `doc/trace-format.adoc`, and is versioned by
+//! [super::TRACE_FORMAT_VERSION].
+//!
+//! Some information in events is not printed, so reading a trace
+//! gives events which print identically, rather than the events which
+//! were originally written. In particular source locations attached
+//! to SMT definitions, the condition variable of each fork, and the
+//! exclusive and instruction fetch flags of memory accesses are
+//! lost.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use num_bigint::BigInt;
+
+use crate::bitvector::b64::B64;
+use crate::bitvector::BV;
+use crate::ir::{BitsSegment, Loc, Name, SharedState, UndefinedKind, Val};
+use crate::memory::DEFAULT_REGION_NAME;
+use crate::smt::smtlib::{self, *};
+use crate::smt::{intern_region, Accessor, DefAttrs, EnumId, EnumMember, Event, ReadOpts, Sym, WriteOpts};
+use crate::source_loc::SourceLoc;
+use crate::zencode;
+
+use super::sexp_lexer::SexpLexer;
+use super::sexp_parser::SexpsParser;
+use super::TRACE_FORMAT_VERSION;
+
+pub enum Sexp {
+    Atom(String),
+    /// A `|quoted|` symbol
+    Quoted(String),
+    Str(String),
+    List(Vec<Sexp>),
+}
+
+impl fmt::Display for Sexp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sexp::Atom(s) => write!(f, "{}", s),
+            Sexp::Quoted(s) => write!(f, "|{}|", s),
+            Sexp::Str(s) => write!(f, "{:?}", s),
+            Sexp::List(xs) => {
+                write!(f, "(")?;
+                for (i, x) in xs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?
+                    }
+                    write!(f, "{}", x)?
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn parse_sexps(input: &str) -> Result<Vec<Sexp>, String> {
+    SexpsParser::new().parse(SexpLexer::new(input)).map_err(|err| format!("Could not parse trace: {}", err))
+}
+
+fn is_atom(sexp: &Sexp, atom: &str) -> bool {
+    matches!(sexp, Sexp::Atom(s) if s == atom)
+}
+
+fn read_sym(sexp: &Sexp) -> Option<Sym> {
+    match sexp {
+        Sexp::Atom(s) => s.strip_prefix('v').and_then(|n| n.parse().ok()).map(Sym::from_u32),
+        _ => None,
+    }
+}
+
+fn read_num<T: std::str::FromStr>(sexp: &Sexp) -> Result<T, String> {
+    match sexp {
+        Sexp::Atom(s) => s.parse().map_err(|_| format!("Expected a number, found {}", s)),
+        _ => Err(format!("Expected a number, found {}", sexp)),
+    }
+}
+
+fn read_bool(sexp: &Sexp) -> Result<bool, String> {
+    match sexp {
+        Sexp::Atom(s) if s == "true" => Ok(true),
+        Sexp::Atom(s) if s == "false" => Ok(false),
+        _ => Err(format!("Expected a boolean, found {}", sexp)),
+    }
+}
+
+fn read_str(sexp: &Sexp) -> Result<&str, String> {
+    match sexp {
+        Sexp::Str(s) => Ok(s),
+        _ => Err(format!("Expected a string, found {}", sexp)),
+    }
+}
+
+/// Parse an SMT bitvector literal (`#x...` or `#b...`), least
+/// significant bit first.
+fn read_bits(s: &str) -> Option<Vec<bool>> {
+    let mut bits = Vec::new();
+    if let Some(hex) = s.strip_prefix("#x") {
+        for c in hex.chars().rev() {
+            let digit = c.to_digit(16)?;
+            bits.extend((0..4).map(|i| (digit >> i) & 1 == 1))
+        }
+    } else if let Some(bin) = s.strip_prefix("#b") {
+        for c in bin.chars().rev() {
+            bits.push(match c {
+                '0' => false,
+                '1' => true,
+                _ => return None,
+            })
+        }
+    } else {
+        return None;
+    }
+    Some(bits)
+}
+
+fn bits_exp<V>(bits: Vec<bool>) -> Exp<V> {
+    if bits.len() <= 64 {
+        let value = bits.iter().rev().fold(0, |acc, bit| (acc << 1) | u64::from(*bit));
+        Exp::Bits64(B64::new(value, bits.len() as u32))
+    } else {
+        Exp::Bits(bits)
+    }
+}
+
+type Binop<V> = fn(Box<Exp<V>>, Box<Exp<V>>) -> Exp<V>;
+
+fn binop<V>(op: &str) -> Option<Binop<V>> {
+    use Exp::*;
+    Some(match op {
+        "=" => Eq,
+        "and" => And,
+        "or" => Or,
+        "bvand" => Bvand,
+        "bvor" => Bvor,
+        "bvxor" => Bvxor,
+        "bvnand" => Bvnand,
+        "bvnor" => Bvnor,
+        "bvxnor" => Bvxnor,
+        "bvadd" => Bvadd,
+        "bvsub" => Bvsub,
+        "bvmul" => Bvmul,
+        "bvudiv" => Bvudiv,
+        "bvsdiv" => Bvsdiv,
+        "bvurem" => Bvurem,
+        "bvsrem" => Bvsrem,
+        "bvsmod" => Bvsmod,
+        "bvult" => Bvult,
+        "bvslt" => Bvslt,
+        "bvule" => Bvule,
+        "bvsle" => Bvsle,
+        "bvuge" => Bvuge,
+        "bvsge" => Bvsge,
+        "bvugt" => Bvugt,
+        "bvsgt" => Bvsgt,
+        "bvshl" => Bvshl,
+        "bvlshr" => Bvlshr,
+        "bvashr" => Bvashr,
+        "concat" => Concat,
+        "select" => Select,
+        _ => return None,
+    })
+}
+
+fn fp_unary(op: &str) -> Option<FPUnary> {
+    use FPUnary::*;
+    Some(match op {
+        "fp.abs" => Abs,
+        "fp.neg" => Neg,
+        "fp.isNormal" => IsNormal,
+        "fp.isSubnormal" => IsSubnormal,
+        "fp.isZero" => IsZero,
+        "fp.isInfinite" => IsInfinite,
+        "fp.isNaN" => IsNaN,
+        "fp.isNegative" => IsNegative,
+        "fp.isPositive" => IsPositive,
+        _ => return None,
+    })
+}
+
+fn fp_binary(op: &str) -> Option<FPBinary> {
+    use FPBinary::*;
+    Some(match op {
+        "fp.rem" => Rem,
+        "fp.min" => Min,
+        "fp.max" => Max,
+        "fp.leq" => Leq,
+        "fp.lt" => Lt,
+        "fp.geq" => Geq,
+        "fp.gt" => Gt,
+        "fp.eq" => Eq,
+        _ => return None,
+    })
+}
+
+fn fp_rounding_binary(op: &str) -> Option<FPRoundingBinary> {
+    use FPRoundingBinary::*;
+    Some(match op {
+        "fp.add" => Add,
+        "fp.sub" => Sub,
+        "fp.mul" => Mul,
+        "fp.div" => Div,
+        _ => return None,
+    })
+}
+
+fn int_binary(op: &str) -> Option<IntBinary> {
+    use IntBinary::*;
+    match op {
+        "+" => Some(Add),
+        "-" => Some(Sub),
+        "*" => Some(Mul),
+        "<=" => Some(Le),
+        "<" => Some(Lt),
+        ">=" => Some(Ge),
+        ">" => Some(Gt),
+        _ => None,
+    }
+}
+
+/// Read a non-negative integer literal, negative literals are written
+/// as `(- n)`
+fn read_int(s: &str) -> Option<BigInt> {
+    if !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn rounding_mode(mode: &str) -> Option<FPRoundingMode> {
+    use FPRoundingMode::*;
+    Some(match mode {
+        "roundNearestTiesToEven" => RoundNearestTiesToEven,
+        "roundNearestTiesToAway" => RoundNearestTiesToAway,
+        "roundTowardPositive" => RoundTowardPositive,
+        "roundTowardNegative" => RoundTowardNegative,
+        "roundTowardZero" => RoundTowardZero,
+        _ => return None,
+    })
+}
+
+struct Reader<'a, 'ir, B> {
+    shared_state: &'a SharedState<'ir, B>,
+    tcx: HashMap<Sym, Ty>,
+    ftcx: HashMap<Sym, (Vec<Ty>, Ty)>,
+}
+
+impl<'a, 'ir, B: BV> Reader<'a, 'ir, B> {
+    /// Find an identifier, which is printed with its name decoded
+    fn name(&self, sexp: &Sexp) -> Result<Name, String> {
+        let symtab = &self.shared_state.symtab;
+        match sexp {
+            Sexp::Quoted(id) => symtab
+                .get(&zencode::encode(id))
+                .or_else(|| symtab.get(id))
+                .ok_or_else(|| format!("Unknown identifier |{}|", id)),
+            _ => Err(format!("Expected an identifier, found {}", sexp)),
+        }
+    }
+
+    fn enum_member(&self, sexp: &Sexp) -> Result<EnumMember, String> {
+        let name = self.name(sexp)?;
+        match self.shared_state.type_info.enum_members.get(&name) {
+            Some((member, _, enum_name)) => Ok(EnumMember { enum_id: EnumId::from_name(*enum_name), member: *member }),
+            None => Err(format!("{} is not an enumeration member", sexp)),
+        }
+    }
+
+    /// Read a location string, as printed by [SourceLoc::location_string]
+    fn source_loc(&self, sexp: &Sexp) -> Result<SourceLoc, String> {
+        let s = read_str(sexp)?;
+        let parts: Vec<&str> = s.rsplitn(4, ' ').collect();
+        let pos = |p: &str| -> Option<(u32, u16)> {
+            let (line, col) = p.split_once(':')?;
+            Some((line.parse().ok()?, col.parse().ok()?))
+        };
+        let (end, start, file) = match parts.as_slice() {
+            [end, "-", start] => (end, start, None),
+            [end, "-", start, file] => (end, start, Some(*file)),
+            _ => return Err(format!("Invalid source location {}", sexp)),
+        };
+        let ((line1, char1), (line2, char2)) =
+            pos(start).zip(pos(end)).ok_or_else(|| format!("Invalid source location {}", sexp))?;
+        let file = file.and_then(|file| self.shared_state.symtab.files().iter().position(|f| *f == file));
+        Ok(match file {
+            Some(file) => SourceLoc::new(file as i16, line1, char1, line2, char2),
+            None => SourceLoc::unknown(),
+        })
+    }
+
+    fn accessor(&self, sexp: &Sexp) -> Result<Vec<Accessor>, String> {
+        match sexp {
+            _ if is_atom(sexp, "nil") => Ok(Vec::new()),
+            Sexp::List(fields) => fields
+                .iter()
+                .map(|field| match field {
+                    Sexp::List(xs) if xs.len() == 3 && is_atom(&xs[0], "_") && is_atom(&xs[1], "field") => {
+                        Ok(Accessor::Field(self.name(&xs[2])?))
+                    }
+                    _ => Err(format!("Invalid accessor {}", field)),
+                })
+                .collect(),
+            _ => Err(format!("Invalid accessor {}", sexp)),
+        }
+    }
+
+    fn ty(&self, sexp: &Sexp) -> Result<Ty, String> {
+        match sexp {
+            _ if is_atom(sexp, "Bool") => Ok(Ty::Bool),
+            _ if is_atom(sexp, "RoundingMode") => Ok(Ty::RoundingMode),
+            _ if is_atom(sexp, "Int") => Ok(Ty::Int),
+            Sexp::Quoted(_) => Ok(Ty::Enum(EnumId::from_name(self.name(sexp)?))),
+            Sexp::List(xs) => match xs.as_slice() {
+                [u, bv, sz] if is_atom(u, "_") && is_atom(bv, "BitVec") => Ok(Ty::BitVec(read_num(sz)?)),
+                [u, fp, ebits, sbits] if is_atom(u, "_") && is_atom(fp, "FloatingPoint") => {
+                    Ok(Ty::Float(read_num(ebits)?, read_num(sbits)?))
+                }
+                [array, dom, codom] if is_atom(array, "Array") => {
+                    Ok(Ty::Array(Box::new(self.ty(dom)?), Box::new(self.ty(codom)?)))
+                }
+                _ => Err(format!("Invalid SMT type {}", sexp)),
+            },
+            _ => Err(format!("Invalid SMT type {}", sexp)),
+        }
+    }
+
+    fn exp<V>(
+        &self,
+        sexp: &Sexp,
+        var: &dyn Fn(&Sexp) -> Option<V>,
+        infer: &dyn Fn(&Exp<V>) -> Option<Ty>,
+    ) -> Result<Exp<V>, String> {
+        use Exp::*;
+        if let Some(v) = var(sexp) {
+            return Ok(Var(v));
+        }
+        let exp = |sexp: &Sexp| self.exp(sexp, var, infer).map(Box::new);
+        let invalid = || format!("Invalid SMT expression {}", sexp);
+        match sexp {
+            Sexp::Atom(s) if s == "true" => Ok(Bool(true)),
+            Sexp::Atom(s) if s == "false" => Ok(Bool(false)),
+            Sexp::Atom(s) => {
+                if let Some(bits) = read_bits(s) {
+                    Ok(bits_exp(bits))
+                } else if let Some(n) = read_int(s) {
+                    Ok(Int(n))
+                } else {
+                    rounding_mode(s).map(FPRoundingMode).ok_or_else(invalid)
+                }
+            }
+            Sexp::Quoted(_) => Ok(Enum(self.enum_member(sexp)?)),
+            Sexp::Str(_) => Err(invalid()),
+            Sexp::List(xs) => match xs.as_slice() {
+                [Sexp::Atom(op), arg] if op == "not" => Ok(Not(exp(arg)?)),
+                [Sexp::Atom(op), arg] if op == "bvnot" => Ok(Bvnot(exp(arg)?)),
+                [Sexp::Atom(op), arg] if op == "bvneg" => Ok(Bvneg(exp(arg)?)),
+                [Sexp::Atom(op), Sexp::Atom(n)] if op == "-" && read_int(n).is_some() => Ok(Int(-read_int(n).unwrap())),
+                [Sexp::Atom(op), arg] if op == "-" => Ok(IntUnary(smtlib::IntUnary::Neg, exp(arg)?)),
+                [Sexp::Atom(op), arg] if op == "sbv_to_int" => {
+                    let arg = exp(arg)?;
+                    match infer(&arg) {
+                        Some(Ty::BitVec(sz)) => Ok(IntUnary(smtlib::IntUnary::FromSigned(sz), arg)),
+                        _ => Err(invalid()),
+                    }
+                }
+                [Sexp::Atom(op), lhs, rhs] if int_binary(op).is_some() => {
+                    Ok(IntBinary(int_binary(op).unwrap(), exp(lhs)?, exp(rhs)?))
+                }
+                [Sexp::Atom(op), lhs, rhs] if binop::<V>(op).is_some() => Ok(binop(op).unwrap()(exp(lhs)?, exp(rhs)?)),
+                [Sexp::Atom(op), c, t, e] if op == "ite" => Ok(Ite(exp(c)?, exp(t)?, exp(e)?)),
+                [Sexp::Atom(op), a, i, v] if op == "store" => Ok(Store(exp(a)?, exp(i)?, exp(v)?)),
+                [Sexp::Atom(op), args @ ..] if op == "distinct" => {
+                    Ok(Distinct(args.iter().map(|arg| self.exp(arg, var, infer)).collect::<Result<_, _>>()?))
+                }
+                [u, Sexp::Atom(c), ebits, sbits] if is_atom(u, "_") => {
+                    let c = match c.as_str() {
+                        "NaN" => smtlib::FPConstant::NaN,
+                        "+oo" => smtlib::FPConstant::Inf { negative: false },
+                        "-oo" => smtlib::FPConstant::Inf { negative: true },
+                        "+zero" => smtlib::FPConstant::Zero { negative: false },
+                        "-zero" => smtlib::FPConstant::Zero { negative: true },
+                        _ => return Err(invalid()),
+                    };
+                    Ok(FPConstant(c, read_num(ebits)?, read_num(sbits)?))
+                }
+                [Sexp::Atom(op), arg] if fp_unary(op).is_some() => Ok(FPUnary(fp_unary(op).unwrap(), exp(arg)?)),
+                [Sexp::Atom(op), rm, arg] if op == "fp.sqrt" => {
+                    Ok(FPRoundingUnary(smtlib::FPRoundingUnary::Sqrt, exp(rm)?, exp(arg)?))
+                }
+                [Sexp::Atom(op), rm, arg] if op == "fp.roundToIntegral" => {
+                    Ok(FPRoundingUnary(smtlib::FPRoundingUnary::RoundToIntegral, exp(rm)?, exp(arg)?))
+                }
+                [Sexp::Atom(op), lhs, rhs] if fp_binary(op).is_some() => {
+                    Ok(FPBinary(fp_binary(op).unwrap(), exp(lhs)?, exp(rhs)?))
+                }
+                [Sexp::Atom(op), rm, lhs, rhs] if fp_rounding_binary(op).is_some() => {
+                    Ok(FPRoundingBinary(fp_rounding_binary(op).unwrap(), exp(rm)?, exp(lhs)?, exp(rhs)?))
+                }
+                [Sexp::Atom(op), rm, x, y, z] if op == "fp.fma" => Ok(FPfma(exp(rm)?, exp(x)?, exp(y)?, exp(z)?)),
+                [Sexp::List(indexed), args @ ..] => self.indexed_exp(sexp, indexed, args, var, infer),
+                [f, args @ ..] if read_sym(f).is_some() => Ok(App(
+                    read_sym(f).unwrap(),
+                    args.iter().map(|arg| self.exp(arg, var, infer)).collect::<Result<_, _>>()?,
+                )),
+                _ => Err(invalid()),
+            },
+        }
+    }
+
+    /// Read an application of an indexed operator, such as `((_ extract 7 0) x)`
+    fn indexed_exp<V>(
+        &self,
+        sexp: &Sexp,
+        indexed: &[Sexp],
+        args: &[Sexp],
+        var: &dyn Fn(&Sexp) -> Option<V>,
+        infer: &dyn Fn(&Exp<V>) -> Option<Ty>,
+    ) -> Result<Exp<V>, String> {
+        use smtlib::FPRoundingUnary::*;
+        use Exp::*;
+        let exp = |sexp: &Sexp| self.exp(sexp, var, infer).map(Box::new);
+        let invalid = || format!("Invalid SMT expression {}", sexp);
+        match (indexed, args) {
+            ([u, op, i, j], [arg]) if is_atom(u, "_") && is_atom(op, "extract") => {
+                Ok(Extract(read_num(i)?, read_num(j)?, exp(arg)?))
+            }
+            ([u, op, n], [arg]) if is_atom(u, "_") && is_atom(op, "zero_extend") => {
+                Ok(ZeroExtend(read_num(n)?, exp(arg)?))
+            }
+            ([u, op, n], [arg]) if is_atom(u, "_") && is_atom(op, "sign_extend") => {
+                Ok(SignExtend(read_num(n)?, exp(arg)?))
+            }
+            ([u, op, ebits, sbits], [arg]) if is_atom(u, "_") && is_atom(op, "to_fp") => {
+                Ok(FPUnary(smtlib::FPUnary::FromIEEE(read_num(ebits)?, read_num(sbits)?), exp(arg)?))
+            }
+            ([u, op, ebits, sbits], [rm, arg]) if is_atom(u, "_") && is_atom(op, "to_fp") => {
+                // Converting from a float and from a signed bitvector
+                // are printed the same, so use the argument's type
+                let arg = exp(arg)?;
+                let (ebits, sbits) = (read_num(ebits)?, read_num(sbits)?);
+                let op = if let Some(Ty::BitVec(_)) = infer(&arg) {
+                    FromSigned(ebits, sbits)
+                } else {
+                    Convert(ebits, sbits)
+                };
+                Ok(FPRoundingUnary(op, exp(rm)?, arg))
+            }
+            ([u, op, ebits, sbits], [rm, arg]) if is_atom(u, "_") && is_atom(op, "to_fp_unsigned") => {
+                Ok(FPRoundingUnary(FromUnsigned(read_num(ebits)?, read_num(sbits)?), exp(rm)?, exp(arg)?))
+            }
+            ([u, op, sz], [rm, arg]) if is_atom(u, "_") && is_atom(op, "fp.to_sbv") => {
+                Ok(FPRoundingUnary(ToSigned(read_num(sz)?), exp(rm)?, exp(arg)?))
+            }
+            ([u, op, sz], [rm, arg]) if is_atom(u, "_") && is_atom(op, "fp.to_ubv") => {
+                Ok(FPRoundingUnary(ToUnsigned(read_num(sz)?), exp(rm)?, exp(arg)?))
+            }
+            ([u, op, sz], [arg]) if is_atom(u, "_") && is_atom(op, "int_to_bv") => {
+                Ok(IntUnary(smtlib::IntUnary::ToBits(read_num(sz)?), exp(arg)?))
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    fn smt_exp(&self, sexp: &Sexp) -> Result<Exp<Sym>, String> {
+        self.exp(sexp, &read_sym, &|exp| exp.infer(&self.tcx, &self.ftcx))
+    }
+
+    /// Read a register location, as used by `assume` events
+    fn loc(&self, sexp: &Sexp) -> Option<Loc<String>> {
+        match sexp {
+            Sexp::List(xs) => match xs.as_slice() {
+                [Sexp::Quoted(reg), acc] => {
+                    let mut loc = Loc::Id(zencode::encode(reg));
+                    for accessor in self.accessor(acc).ok()?.iter().rev() {
+                        let Accessor::Field(field) = accessor;
+                        loc = Loc::Field(Box::new(loc), self.shared_state.symtab.to_str(*field).to_string())
+                    }
+                    Some(loc)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn value(&self, sexp: &Sexp) -> Result<Val<B>, String> {
+        let invalid = || format!("Invalid value {}", sexp);
+        if let Some(v) = read_sym(sexp) {
+            return Ok(Val::Symbolic(v));
+        }
+        match sexp {
+            Sexp::Atom(s) if s == "true" => Ok(Val::Bool(true)),
+            Sexp::Atom(s) if s == "false" => Ok(Val::Bool(false)),
+            Sexp::Atom(s) => B::from_str(s).map(Val::Bits).ok_or_else(invalid),
+            Sexp::Quoted(_) => Ok(Val::Enum(self.enum_member(sexp)?)),
+            Sexp::Str(s) => Ok(Val::String(s.clone())),
+            Sexp::List(xs) => match xs.as_slice() {
+                [u, Sexp::Atom(n), sz] if is_atom(u, "_") && n.starts_with("bv") => {
+                    let n: i128 = n[2..].parse().map_err(|_| invalid())?;
+                    match read_num::<u32>(sz)? {
+                        64 => Ok(Val::I64(n as i64)),
+                        128 => Ok(Val::I128(n)),
+                        _ => Err(invalid()),
+                    }
+                }
+                [u, k, Sexp::Atom(n)] if is_atom(u, "_") && is_atom(k, "int") => {
+                    n.parse().map(Val::BigInt).map_err(|_| invalid())
+                }
+                [u, k] if is_atom(u, "_") && is_atom(k, "unit") => Ok(Val::Unit),
+                [u, k] if is_atom(u, "_") && is_atom(k, "poison") => Ok(Val::Poison),
+                [u, k, reg] if is_atom(u, "_") && is_atom(k, "reg") => Ok(Val::Ref(self.name(reg)?)),
+                [u, k, nil] if is_atom(u, "_") && (is_atom(k, "list") || is_atom(k, "vec")) && is_atom(nil, "nil") => {
+                    Ok(if is_atom(k, "list") { Val::List(Vec::new()) } else { Val::Vector(Vec::new()) })
+                }
+                [u, k, elems @ ..] if is_atom(u, "_") && (is_atom(k, "list") || is_atom(k, "vec")) => {
+                    let elems = elems.iter().map(|elem| self.value(elem)).collect::<Result<_, _>>()?;
+                    Ok(if is_atom(k, "list") { Val::List(elems) } else { Val::Vector(elems) })
+                }
+                [u, k, fields @ ..] if is_atom(u, "_") && is_atom(k, "struct") => {
+                    let mut map = HashMap::default();
+                    for (field, value) in self.fields(fields)? {
+                        map.insert(field, value);
+                    }
+                    Ok(Val::Struct(map))
+                }
+                [u, k, v, ctors @ ..] if is_atom(u, "_") && is_atom(k, "ctor") => {
+                    let v = read_sym(v).ok_or_else(invalid)?;
+                    Ok(Val::SymbolicCtor(v, self.fields(ctors)?.into_iter().collect()))
+                }
+                [ctor @ Sexp::Quoted(_), v] => Ok(Val::Ctor(self.name(ctor)?, Box::new(self.value(v)?))),
+                // Mixed bitvectors are printed as (x @ y @ ...)
+                segments if segments.len() % 2 == 1 && segments.iter().skip(1).step_by(2).all(|s| is_atom(s, "@")) => {
+                    let segments = segments
+                        .iter()
+                        .step_by(2)
+                        .map(|segment| match (read_sym(segment), segment) {
+                            (Some(v), _) => Ok(BitsSegment::Symbolic(v)),
+                            (None, Sexp::Atom(s)) => B::from_str(s).map(BitsSegment::Concrete).ok_or_else(invalid),
+                            _ => Err(invalid()),
+                        })
+                        .collect::<Result<_, _>>()?;
+                    Ok(Val::MixedBits(segments))
+                }
+                _ => Err(invalid()),
+            },
+        }
+    }
+
+    /// Read the `(|name| value) ...` fields of a struct, or `nil`
+    fn fields(&self, fields: &[Sexp]) -> Result<Vec<(Name, Val<B>)>, String> {
+        match fields {
+            [nil] if is_atom(nil, "nil") => Ok(Vec::new()),
+            _ => fields
+                .iter()
+                .map(|field| match field {
+                    Sexp::List(xs) if xs.len() == 2 => Ok((self.name(&xs[0])?, self.value(&xs[1])?)),
+                    _ => Err(format!("Invalid field {}", field)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Read the arguments of an abstract call, which are `nil` if
+    /// there are none
+    fn args(&self, args: &[Sexp]) -> Result<Vec<Val<B>>, String> {
+        match args {
+            [nil] if is_atom(nil, "nil") => Ok(Vec::new()),
+            _ => args.iter().map(|arg| self.value(arg)).collect(),
+        }
+    }
+
+    /// Read a memory callback annotation, `(callback |region|)`
+    fn callback(sexp: &Sexp) -> Option<&str> {
+        match sexp {
+            Sexp::List(xs) => match xs.as_slice() {
+                [callback, Sexp::Quoted(region)] if is_atom(callback, "callback") => Some(region),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn event(&mut self, sexp: &Sexp) -> Result<Event<B>, String> {
+        use Event::*;
+        let invalid = || format!("Invalid event {}", sexp);
+        let xs = match sexp {
+            Sexp::List(xs) => xs.as_slice(),
+            _ => return Err(invalid()),
+        };
+        let (head, args) = match xs.split_first() {
+            Some((Sexp::Atom(head), args)) => (head.as_str(), args),
+            _ => return Err(invalid()),
+        };
+        let smt = |def| Smt(def, DefAttrs::default(), SourceLoc::unknown());
+        let unknown = SourceLoc::unknown();
+        Ok(match (head, args) {
+            ("branch", [n, loc]) => Fork(read_num(n)?, Sym::from_u32(0), 0, self.source_loc(loc)?),
+            ("call", [f]) => Function { name: self.name(f)?, call: true },
+            ("return", [f]) => Function { name: self.name(f)?, call: false },
+            ("abstract-primop" | "abstract-call", [f, ret, args @ ..]) => Abstract {
+                name: self.name(f)?,
+                primitive: head == "abstract-primop",
+                args: self.args(args)?,
+                return_value: self.value(ret)?,
+                info: unknown,
+            },
+            ("decode-call", [f, args @ ..]) => DecodeCall {
+                name: self.name(f)?,
+                args: args.iter().map(|arg| self.value(arg)).collect::<Result<_, _>>()?,
+                info: unknown,
+            },
+            ("decode-return", [f, ret]) => DecodeReturn { name: self.name(f)?, return_value: self.value(ret)? },
+            ("decode-value", [x, value, args @ ..]) => DecodeValue {
+                name: self.name(x)?,
+                args: args.iter().map(|arg| self.value(arg)).collect::<Result<_, _>>()?,
+                value: self.value(value)?,
+                info: unknown,
+            },
+            ("decode-test", [value, args @ ..]) => DecodeTest {
+                args: args.iter().map(|arg| self.value(arg)).collect::<Result<_, _>>()?,
+                value: self.value(value)?,
+                info: unknown,
+            },
+            ("function-assumption", [f, ret, args @ ..]) => {
+                AssumeFun { name: self.name(f)?, args: self.args(args)?, return_value: self.value(ret)? }
+            }
+            ("use-function-assumption", [f, ret, args @ ..]) => {
+                UseFunAssumption { name: self.name(f)?, args: self.args(args)?, return_value: self.value(ret)? }
+            }
+            ("declare-const", [v, ty]) => {
+                let (v, ty) = (read_sym(v).ok_or_else(invalid)?, self.ty(ty)?);
+                self.tcx.insert(v, ty.clone());
+                smt(Def::DeclareConst(v, ty))
+            }
+            ("declare_fun", [v, Sexp::List(arg_tys), result_ty]) => {
+                let v = read_sym(v).ok_or_else(invalid)?;
+                let arg_tys = arg_tys.iter().map(|ty| self.ty(ty)).collect::<Result<Vec<_>, _>>()?;
+                let result_ty = self.ty(result_ty)?;
+                self.ftcx.insert(v, (arg_tys.clone(), result_ty.clone()));
+                smt(Def::DeclareFun(v, arg_tys, result_ty))
+            }
+            ("define-const", [v, exp]) | ("define-const", [v, _, exp]) => {
+                let (v, exp) = (read_sym(v).ok_or_else(invalid)?, self.smt_exp(exp)?);
+                if let Some(ty) = exp.infer(&self.tcx, &self.ftcx) {
+                    self.tcx.insert(v, ty);
+                }
+                smt(Def::DefineConst(v, exp))
+            }
+            ("define-enum", [name, size, _]) => smt(Def::DefineEnum(self.name(name)?, read_num(size)?)),
+            ("assert", [exp]) => smt(Def::Assert(self.smt_exp(exp)?)),
+            ("read-mem", [value, kind, address, bytes, rest @ ..]) => {
+                let (tag, callback) = match rest {
+                    [] => (None, None),
+                    [x] => match Self::callback(x) {
+                        Some(region) => (None, Some(region)),
+                        None => (Some(x), None),
+                    },
+                    [tag, x] => (Some(tag), Some(Self::callback(x).ok_or_else(invalid)?)),
+                    _ => return Err(invalid()),
+                };
+                ReadMem {
+                    value: self.value(value)?,
+                    read_kind: self.value(kind)?,
+                    address: self.value(address)?,
+                    bytes: read_num(bytes)?,
+                    tag_value: tag.map(|tag| self.value(tag)).transpose()?,
+                    opts: ReadOpts { is_callback: callback.is_some(), ..ReadOpts::default() },
+                    region: callback.map_or(DEFAULT_REGION_NAME, |region| intern_region(region.to_string())),
+                    info: unknown,
+                }
+            }
+            ("write-mem-tag", [v, kind, address, tag]) => WriteMem {
+                value: read_sym(v).ok_or_else(invalid)?,
+                write_kind: self.value(kind)?,
+                address: self.value(address)?,
+                data: Val::Bits(B::zero_width()),
+                bytes: 0,
+                tag_value: Some(self.value(tag)?),
+                opts: WriteOpts::default(),
+                region: DEFAULT_REGION_NAME,
+                info: unknown,
+            },
+            ("write-mem", [v, kind, address, data, bytes, rest @ ..]) => {
+                let (tag, callback) = match rest {
+                    [] => (None, None),
+                    [x] => match Self::callback(x) {
+                        Some(region) => (None, Some(region)),
+                        None => (Some(x), None),
+                    },
+                    [tag, x] => (Some(tag), Some(Self::callback(x).ok_or_else(invalid)?)),
+                    _ => return Err(invalid()),
+                };
+                WriteMem {
+                    value: read_sym(v).ok_or_else(invalid)?,
+                    write_kind: self.value(kind)?,
+                    address: self.value(address)?,
+                    data: self.value(data)?,
+                    bytes: read_num(bytes)?,
+                    tag_value: tag.map(|tag| self.value(tag)).transpose()?,
+                    opts: if callback.is_some() { WriteOpts::callback() } else { WriteOpts::default() },
+                    region: callback.map_or(DEFAULT_REGION_NAME, |region| intern_region(region.to_string())),
+                    info: unknown,
+                }
+            }
+            ("address-announce", [address]) => AddressAnnounce { address: self.value(address)?, info: unknown },
+            ("branch-address", [address]) => Branch { address: self.value(address)?, info: unknown },
+            ("block-op", [name, address, bytes]) => BlockOp {
+                name: self.name(name)?,
+                address: self.value(address)?,
+                bytes: read_num(bytes)?,
+                info: unknown,
+            },
+            ("write-reg", [reg, acc, value]) => {
+                WriteReg(self.name(reg)?, self.accessor(acc)?, self.value(value)?, unknown)
+            }
+            ("read-reg", [reg, acc, value]) => {
+                ReadReg(self.name(reg)?, self.accessor(acc)?, self.value(value)?, unknown)
+            }
+            ("assume-reg", [reg, acc, value]) => AssumeReg(self.name(reg)?, self.accessor(acc)?, self.value(value)?),
+            ("mark-reg", [reg, mark]) => MarkReg { regs: vec![self.name(reg)?], mark: read_str(mark)?.to_string() },
+            ("cycle", []) => Cycle,
+            ("guard", [f, taken, loc]) => {
+                Guard { function: self.name(f)?, taken: read_bool(taken)?, info: self.source_loc(loc)? }
+            }
+            ("loop-bound", [f, bound, loc]) => {
+                LoopBound { function: self.name(f)?, bound: read_num(bound)?, info: self.source_loc(loc)? }
+            }
+            ("undefined", [f, kind, loc]) => {
+                let kind = match kind {
+                    _ if is_atom(kind, "undefined") => UndefinedKind::Undefined,
+                    _ if is_atom(kind, "builtin") => UndefinedKind::Builtin,
+                    _ if is_atom(kind, "arbitrary") => UndefinedKind::Arbitrary,
+                    _ => return Err(invalid()),
+                };
+                Undefined { function: self.name(f)?, kind, info: self.source_loc(loc)? }
+            }
+            ("log", [label, value]) => {
+                Log { label: read_str(label)?.to_string(), value: self.value(value)?, info: unknown }
+            }
+            ("instr", [value]) => Instr(self.value(value)?),
+            ("assume", [exp]) => Assume(self.exp(exp, &|sexp| self.loc(sexp), &|_| None)?),
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+/// Read every `(trace ...)` in the input, as written by
+/// [super::write_events] or [super::write_events_with_opts], with the
+/// events of each trace in the order they were printed. Traces
+/// written with `pretty_values`, `collapse_abstract`, or `just_smt`
+/// cannot be read, nor can event trees.
+pub fn read_traces<B: BV>(input: &str, shared_state: &SharedState<B>) -> Result<Vec<Vec<Event<B>>>, String> {
+    let mut traces = Vec::new();
+    for sexp in parse_sexps(input)? {
+        let events = match &sexp {
+            Sexp::List(xs) if !xs.is_empty() && is_atom(&xs[0], "trace") => &xs[1..],
+            _ => return Err(format!("Expected (trace ...), found {}", sexp)),
+        };
+        let events = match events.split_first() {
+            Some((Sexp::List(header), events)) if header.len() == 2 && is_atom(&header[0], "format-version") => {
+                let version: u32 = read_num(&header[1])?;
+                if version > TRACE_FORMAT_VERSION {
+                    return Err(format!(
+                        "Trace has format version {}, but only versions up to {} can be read",
+                        version, TRACE_FORMAT_VERSION
+                    ));
+                }
+                events
+            }
+            _ => events,
+        };
+        let mut reader = Reader { shared_state, tcx: HashMap::new(), ftcx: HashMap::new() };
+        let mut trace = Vec::new();
+        for event in events {
+            trace.push(reader.event(event)?)
+        }
+        traces.push(trace)
+    }
+    Ok(traces)
+}
+
+/// Read a single trace, as written by [super::write_events].
+pub fn read_events<B: BV>(input: &str, shared_state: &SharedState<B>) -> Result<Vec<Event<B>>, String> {
+    let mut traces = read_traces(input, shared_state)?;
+    match traces.len() {
+        1 => Ok(traces.pop().unwrap()),
+        n => Err(format!("Expected a single trace, found {}", n)),
+    }
+}
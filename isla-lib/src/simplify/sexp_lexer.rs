@@ -0,0 +1,138 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+// Copyright (c) 2020 Brian Campbell
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The lexer for [super::sexp_parser], which reads the S-expressions
+//! making up a trace. Text from a `;` to the end of a line is a
+//! comment.
+
+use regex::Regex;
+use std::fmt;
+
+use crate::lexer::*;
+
+pub struct SexpLexer<'input> {
+    lexer: Lexer<'input>,
+}
+
+impl<'input> SexpLexer<'input> {
+    pub fn new(input: &'input str) -> Self {
+        SexpLexer { lexer: Lexer::new(input) }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Tok<'input> {
+    Atom(&'input str),
+    /// A `|quoted|` symbol, without the bars
+    Quoted(&'input str),
+    /// A string literal, without the quotes and with its escapes
+    /// still in place, see [unescape]
+    Str(&'input str),
+    Lparen,
+    Rparen,
+}
+
+impl<'input> fmt::Display for Tok<'input> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+lazy_static! {
+    static ref KEYWORDS: KeywordTable<Tok<'static>> = {
+        use Tok::*;
+        KeywordTable::new(vec![("(", Lparen), (")", Rparen)])
+    };
+    pub static ref ATOM_REGEX: Regex = Regex::new(r#"^[^\s()|";]+"#).unwrap();
+    pub static ref QUOTED_REGEX: Regex = Regex::new(r"^\|[^|]*\|").unwrap();
+    pub static ref STRING_REGEX: Regex = Regex::new(r#"^"(?s:[^"\\]|\\.)*""#).unwrap();
+}
+
+pub type Span<'input> = Result<(usize, Tok<'input>, usize), LexError>;
+
+impl<'input> Iterator for SexpLexer<'input> {
+    type Item = Span<'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use Tok::*;
+        self.lexer.consume_whitespace()?;
+        while self.lexer.buf.starts_with(';') {
+            self.lexer.consume_to_newline()?;
+            self.lexer.consume_whitespace()?
+        }
+
+        if let Some(keyword) = self.lexer.consume_keyword(&KEYWORDS) {
+            return Some(Ok(keyword));
+        }
+
+        match self.lexer.consume_regex(&QUOTED_REGEX) {
+            None => (),
+            Some((from, id, to)) => return Some(Ok((from, Quoted(&id[1..(id.len() - 1)]), to))),
+        }
+
+        match self.lexer.consume_regex(&STRING_REGEX) {
+            None => (),
+            Some((from, s, to)) => return Some(Ok((from, Str(&s[1..(s.len() - 1)]), to))),
+        }
+
+        match self.lexer.consume_regex(&ATOM_REGEX) {
+            None => (),
+            Some((from, atom, to)) => return Some(Ok((from, Atom(atom), to))),
+        }
+
+        Some(Err(LexError { pos: self.lexer.pos }))
+    }
+}
+
+/// Replace the escapes in a string literal, which is printed using
+/// Rust's `{:?}` formatting
+pub fn unescape(s: &str) -> Option<String> {
+    let mut chars = s.chars().peekable();
+    let mut unescaped = String::new();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => unescaped.push('\n'),
+            't' => unescaped.push('\t'),
+            'r' => unescaped.push('\r'),
+            '0' => unescaped.push('\0'),
+            'u' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let code: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                unescaped.push(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)?)
+            }
+            c => unescaped.push(c),
+        }
+    }
+    Some(unescaped)
+}
@@ -29,39 +29,39 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 //! This module defines an interface with the SMT solver, primarily
-//! via the [Solver] type. It provides a safe abstraction over the
-//! [z3_sys] crate. In addition, all the interaction with the SMT
+//! via the [Solver] type. With the default `z3` feature this is a
+//! safe abstraction over the `z3_sys` crate, otherwise it is a
+//! solver-free backend which only records the SMT-LIB script (see
+//! the `text` module), which lets the rest of the library be built
+//! without native code, e.g. for `wasm32-unknown-unknown`. In
+//! addition, all the interaction with the SMT
 //! solver is logged as a [Trace] in an SMTLIB-like format, expanded
 //! with additional events marking e.g. memory events, the start and
 //! end of processor cycles, etc (see the [Event] type). Points in
 //! these traces can be snapshotted and shared between threads via the
 //! [Checkpoint] type.
 
-use ahash;
-use libc::{c_int, c_uint};
-use serde::{Deserialize, Serialize};
-use z3_sys::*;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use std::collections::HashMap;
-use std::convert::TryInto;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
-use std::ffi::{CStr, CString};
 use std::fmt;
 use std::io::Write;
 use std::mem;
-use std::ptr;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use crate::bitvector::b64::B64;
 use crate::bitvector::BV;
 use crate::error::ExecError;
-use crate::ir::{Loc, Name, Symtab, Val};
+use crate::ir::{Loc, Name, Symtab, UndefinedKind, Val};
 use crate::source_loc::SourceLoc;
 use crate::zencode;
 
 /// A newtype wrapper for symbolic variables, which are `u32` under
 /// the hood.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Sym {
     pub(crate) id: u32,
 }
@@ -84,12 +84,12 @@ impl fmt::Display for Sym {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct EnumId {
     id: Name,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EnumMember {
     pub enum_id: EnumId,
     pub member: usize,
@@ -110,9 +110,20 @@ impl EnumId {
     }
 }
 
+pub mod rewrite;
 pub mod smtlib;
 use smtlib::*;
 
+#[cfg(not(feature = "z3"))]
+mod text;
+#[cfg(feature = "z3")]
+mod z3;
+
+#[cfg(not(feature = "z3"))]
+pub use text::{finalize_solver, global_set_param_value, Config, Context, Model, Solver, SolverSnapshot};
+#[cfg(feature = "z3")]
+pub use z3::{finalize_solver, global_set_param_value, Config, Context, Model, Solver, SolverSnapshot};
+
 /// Snapshot of interaction with underlying solver that can be
 /// efficiently cloned and shared between threads.
 #[derive(Clone, Default)]
@@ -120,16 +131,53 @@ pub struct Checkpoint<B> {
     num: usize,
     next_var: u32,
     trace: Arc<Option<Trace<B>>>,
+    memory_accesses: usize,
 }
 
 impl<B> Checkpoint<B> {
     pub fn new() -> Self {
-        Checkpoint { num: 0, next_var: 0, trace: Arc::new(None) }
+        Checkpoint { num: 0, next_var: 0, trace: Arc::new(None), memory_accesses: 0 }
+    }
+
+    /// Create a checkpoint from a trace of events in
+    /// [EventOrder::Execution] order, e.g. one loaded with
+    /// [crate::trace::load_traces]. A solver created from it with
+    /// [Solver::from_checkpoint] replays the events, so exploration
+    /// can continue from the end of the trace.
+    pub fn from_events(events: Vec<Event<B>>) -> Self {
+        let next_var = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Smt(Def::DeclareConst(v, _) | Def::DeclareFun(v, _, _) | Def::DefineConst(v, _), _, _) => {
+                    Some(v.id + 1)
+                }
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let memory_accesses =
+            events.iter().filter(|event| matches!(event, Event::ReadMem { .. } | Event::WriteMem { .. })).count();
+        let trace = Trace {
+            checkpoints: 0,
+            head: events,
+            tail: Arc::new(None),
+            tail_len: 0,
+            tail_memory_accesses: 0,
+            simplified_len: 0,
+        };
+        Checkpoint { num: 1, next_var, trace: Arc::new(Some(trace)), memory_accesses }
     }
 
     pub fn trace(&self) -> &Option<Trace<B>> {
         &self.trace
     }
+
+    /// The number of memory reads and writes in the trace up to the
+    /// checkpoint, which is counted as the checkpoints are taken
+    /// rather than by walking the trace.
+    pub fn memory_accesses(&self) -> usize {
+        self.memory_accesses
+    }
 }
 
 /// For the concurrency models, register accesses must be logged at a
@@ -155,34 +203,43 @@ impl Accessor {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ReadOpts {
     pub is_exclusive: bool,
     pub is_ifetch: bool,
+    /// The value was produced by region callbacks (see
+    /// `memory::RegionCallbacks`) rather than read from memory
+    pub is_callback: bool,
 }
 
 impl ReadOpts {
     pub fn ifetch() -> Self {
-        ReadOpts { is_exclusive: false, is_ifetch: true }
+        ReadOpts { is_exclusive: false, is_ifetch: true, is_callback: false }
     }
 
     pub fn exclusive() -> Self {
-        ReadOpts { is_exclusive: true, is_ifetch: false }
+        ReadOpts { is_exclusive: true, is_ifetch: false, is_callback: false }
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct WriteOpts {
     is_exclusive: bool,
+    is_callback: bool,
 }
 
 impl WriteOpts {
     pub fn exclusive() -> Self {
-        WriteOpts { is_exclusive: true }
+        WriteOpts { is_exclusive: true, is_callback: false }
+    }
+
+    /// The options for a write which was handled by region callbacks
+    pub fn callback() -> Self {
+        WriteOpts { is_exclusive: false, is_callback: true }
     }
 }
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct DefAttrs {
     attrs: u8,
 }
@@ -204,7 +261,33 @@ impl DefAttrs {
     }
 }
 
-#[derive(Clone, Debug)]
+/// The name of the memory region accessed by a memory event. This is
+/// an alias so serde does not try to borrow it when deserializing.
+pub type RegionName = &'static str;
+
+fn deserialize_region<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RegionName, D::Error> {
+    Ok(intern_region(String::deserialize(deserializer)?))
+}
+
+/// Memory region names in events are static strings, so when events
+/// are deserialized or read from a trace each distinct name is leaked
+/// once and shared.
+pub(crate) fn intern_region(name: String) -> RegionName {
+    lazy_static! {
+        static ref REGIONS: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+    }
+    let mut regions = REGIONS.lock().unwrap();
+    match regions.get(name.as_str()) {
+        Some(region) => region,
+        None => {
+            let region: &'static str = Box::leak(name.into_boxed_str());
+            regions.insert(region);
+            region
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Event<B> {
     Smt(Def, DefAttrs, SourceLoc),
     /// Fork ID, assertion, branch number, source location
@@ -220,9 +303,12 @@ pub enum Event<B> {
         primitive: bool,
         args: Vec<Val<B>>,
         return_value: Val<B>,
+        info: SourceLoc,
     },
-    ReadReg(Name, Vec<Accessor>, Val<B>),
-    WriteReg(Name, Vec<Accessor>, Val<B>),
+    /// Register, accessor, value, and the location of the read
+    ReadReg(Name, Vec<Accessor>, Val<B>, SourceLoc),
+    /// Register, accessor, value, and the location of the write
+    WriteReg(Name, Vec<Accessor>, Val<B>, SourceLoc),
     AssumeReg(Name, Vec<Accessor>, Val<B>),
     ReadMem {
         value: Val<B>,
@@ -231,7 +317,9 @@ pub enum Event<B> {
         bytes: u32,
         tag_value: Option<Val<B>>,
         opts: ReadOpts,
-        region: &'static str,
+        #[serde(deserialize_with = "deserialize_region")]
+        region: RegionName,
+        info: SourceLoc,
     },
     WriteMem {
         value: Sym,
@@ -241,7 +329,9 @@ pub enum Event<B> {
         bytes: u32,
         tag_value: Option<Val<B>>,
         opts: WriteOpts,
-        region: &'static str,
+        #[serde(deserialize_with = "deserialize_region")]
+        region: RegionName,
+        info: SourceLoc,
     },
     MarkReg {
         regs: Vec<Name>,
@@ -249,11 +339,79 @@ pub enum Event<B> {
     },
     AddressAnnounce {
         address: Val<B>,
+        info: SourceLoc,
     },
     Branch {
         address: Val<B>,
+        info: SourceLoc,
+    },
+    /// A call to one of the block functions from the ISA config,
+    /// affecting the `bytes` bytes of memory starting at the
+    /// block-aligned `address`
+    BlockOp {
+        name: Name,
+        address: Val<B>,
+        bytes: u32,
+        info: SourceLoc,
     },
     Cycle,
+    /// The outcome of a concrete branch in one of the functions
+    /// given by `TaskState::with_guard_functions`
+    Guard {
+        function: Name,
+        taken: bool,
+        info: SourceLoc,
+    },
+    /// A path was stopped because it exceeded the loop bound given by
+    /// `TaskState::with_loop_bounds`, so its exploration is incomplete
+    LoopBound {
+        function: Name,
+        bound: usize,
+        info: SourceLoc,
+    },
+    /// An under-specified construct evaluated in `function`, recorded
+    /// when `TaskState::with_undefined_audit` is set
+    Undefined {
+        function: Name,
+        kind: UndefinedKind,
+        info: SourceLoc,
+    },
+    /// A call made while decoding, recorded for the functions given
+    /// by `TaskState::with_decode_trace`
+    DecodeCall {
+        name: Name,
+        args: Vec<Val<B>>,
+        info: SourceLoc,
+    },
+    /// The value returned by a call recorded as a `DecodeCall`
+    DecodeReturn {
+        name: Name,
+        return_value: Val<B>,
+    },
+    /// A slice, or the result of a primop, assigned to the local
+    /// variable `name` by one of the functions given by
+    /// `TaskState::with_decode_trace`, with the arguments it was
+    /// computed from
+    DecodeValue {
+        name: Name,
+        args: Vec<Val<B>>,
+        value: Val<B>,
+        info: SourceLoc,
+    },
+    /// A branch condition tested by one of the functions given by
+    /// `TaskState::with_decode_trace`, with the values it compares
+    DecodeTest {
+        args: Vec<Val<B>>,
+        value: Val<B>,
+        info: SourceLoc,
+    },
+    /// A user-defined event recorded by the model itself, via the
+    /// `isla_log_event` primop
+    Log {
+        label: String,
+        value: Val<B>,
+        info: SourceLoc,
+    },
     Instr(Val<B>),
     Assume(Exp<Loc<String>>),
     AssumeFun {
@@ -278,6 +436,31 @@ impl<B: BV> Event<B> {
         }
     }
 
+    /// The location in the model that generated this event, if the
+    /// event records one
+    pub fn source_loc(&self) -> Option<SourceLoc> {
+        match self {
+            Event::Smt(_, _, info)
+            | Event::Fork(_, _, _, info)
+            | Event::Abstract { info, .. }
+            | Event::ReadReg(_, _, _, info)
+            | Event::WriteReg(_, _, _, info)
+            | Event::ReadMem { info, .. }
+            | Event::WriteMem { info, .. }
+            | Event::AddressAnnounce { info, .. }
+            | Event::Branch { info, .. }
+            | Event::BlockOp { info, .. }
+            | Event::Guard { info, .. }
+            | Event::LoopBound { info, .. }
+            | Event::Undefined { info, .. }
+            | Event::DecodeCall { info, .. }
+            | Event::DecodeValue { info, .. }
+            | Event::DecodeTest { info, .. }
+            | Event::Log { info, .. } => Some(*info),
+            _ => None,
+        }
+    }
+
     pub fn is_smt(&self) -> bool {
         matches!(self, Event::Smt(..))
     }
@@ -299,7 +482,7 @@ impl<B: BV> Event<B> {
     }
 
     pub fn is_read_reg_of(&self, name: Name) -> bool {
-        if let Event::ReadReg(reg, _, _) = self {
+        if let Event::ReadReg(reg, _, _, _) = self {
             *reg == name
         } else {
             false
@@ -307,7 +490,7 @@ impl<B: BV> Event<B> {
     }
 
     pub fn is_write_reg_of(&self, name: Name) -> bool {
-        if let Event::WriteReg(reg, _, _) = self {
+        if let Event::WriteReg(reg, _, _, _) = self {
             *reg == name
         } else {
             false
@@ -316,8 +499,8 @@ impl<B: BV> Event<B> {
 
     pub fn reg_value(&self) -> Option<&Val<B>> {
         match self {
-            Event::ReadReg(_, _, value) => Some(value),
-            Event::WriteReg(_, _, value) => Some(value),
+            Event::ReadReg(_, _, value, _) => Some(value),
+            Event::WriteReg(_, _, value, _) => Some(value),
             _ => None,
         }
     }
@@ -337,6 +520,21 @@ impl<B: BV> Event<B> {
     pub fn is_branch(&self) -> bool {
         matches!(self, Event::Branch { .. })
     }
+
+    pub fn is_decode(&self) -> bool {
+        matches!(
+            self,
+            Event::DecodeCall { .. }
+                | Event::DecodeReturn { .. }
+                | Event::DecodeValue { .. }
+                | Event::DecodeTest { .. }
+        )
+    }
+
+    pub fn is_block_op(&self) -> bool {
+        matches!(self, Event::BlockOp { .. })
+    }
+
     pub fn is_fork(&self) -> bool {
         matches!(self, Event::Fork(_, _, _, _))
     }
@@ -368,6 +566,15 @@ impl<B: BV> Event<B> {
         }
     }
 
+    /// Returns true for memory events handled by region callbacks
+    pub fn is_callback(&self) -> bool {
+        match self {
+            Event::ReadMem { opts, .. } => opts.is_callback,
+            Event::WriteMem { opts, .. } => opts.is_callback,
+            _ => false,
+        }
+    }
+
     pub fn is_abstract(&self) -> bool {
         matches!(self, Event::Abstract { .. })
     }
@@ -386,8 +593,8 @@ impl<B: BV> Event<B> {
 /// "ESR_EL1.ISS"
 pub fn register_name_string<'ir, B>(ev: &Event<B>, symtab: &'ir Symtab) -> Option<String> {
     let pair = match ev {
-        Event::WriteReg(name, accessors, _) => Some((name, accessors)),
-        Event::ReadReg(name, accessors, _) => Some((name, accessors)),
+        Event::WriteReg(name, accessors, _, _) => Some((name, accessors)),
+        Event::ReadReg(name, accessors, _, _) => Some((name, accessors)),
         _ => None,
     };
 
@@ -410,6 +617,49 @@ pub fn register_name_string<'ir, B>(ev: &Event<B>, symtab: &'ir Symtab) -> Optio
 
 pub type EvPath<B> = Vec<Event<B>>;
 
+/// The orientation of a sequence of events. A [Trace] is built up by
+/// pushing events as they happen, but [Trace::to_vec] (and hence the
+/// trace collectors in [crate::executor]) return them newest first,
+/// in [EventOrder::Reverse] order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EventOrder {
+    /// Oldest event first, i.e. the order in which they were executed
+    Execution,
+    /// Newest event first
+    Reverse,
+}
+
+impl EventOrder {
+    /// Re-orient `events`, which are currently in this order, so they
+    /// are in `order`.
+    pub fn convert<T>(self, order: EventOrder, events: &mut [T]) {
+        if self != order {
+            events.reverse()
+        }
+    }
+}
+
+impl FromStr for EventOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "execution" => Ok(EventOrder::Execution),
+            "reverse" => Ok(EventOrder::Reverse),
+            _ => Err(format!("Unknown event order '{}', expected 'execution' or 'reverse'", s)),
+        }
+    }
+}
+
+impl fmt::Display for EventOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventOrder::Execution => write!(f, "execution"),
+            EventOrder::Reverse => write!(f, "reverse"),
+        }
+    }
+}
+
 /// Abstractly represents a sequence of events in such a way that
 /// checkpoints can be created and shared.
 #[derive(Debug)]
@@ -417,23 +667,97 @@ pub struct Trace<B> {
     checkpoints: usize,
     head: Vec<Event<B>>,
     tail: Arc<Option<Trace<B>>>,
+    // The number of events and memory accesses in the tail, and the
+    // number of events in the whole trace when it was last simplified
+    // by [Trace::simplify_over]
+    tail_len: usize,
+    tail_memory_accesses: usize,
+    simplified_len: usize,
 }
 
 impl<B: BV> Trace<B> {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Trace { checkpoints: 0, head: Vec::new(), tail: Arc::new(None) }
+        Trace {
+            checkpoints: 0,
+            head: Vec::new(),
+            tail: Arc::new(None),
+            tail_len: 0,
+            tail_memory_accesses: 0,
+            simplified_len: 0,
+        }
     }
 
     pub fn checkpoint(&mut self, next_var: u32) -> Checkpoint<B> {
         let mut head = Vec::new();
         mem::swap(&mut self.head, &mut head);
-        let tail = Arc::new(Some(Trace { checkpoints: self.checkpoints, head, tail: self.tail.clone() }));
+        let head_len = head.len();
+        let head_memory_accesses = head.iter().filter(|event| event.is_memory_read_or_write()).count();
+        let tail = Arc::new(Some(Trace {
+            checkpoints: self.checkpoints,
+            head,
+            tail: self.tail.clone(),
+            tail_len: self.tail_len,
+            tail_memory_accesses: self.tail_memory_accesses,
+            simplified_len: self.simplified_len,
+        }));
         self.checkpoints += 1;
         self.tail = tail.clone();
-        Checkpoint { num: self.checkpoints, trace: tail, next_var }
+        self.tail_len += head_len;
+        self.tail_memory_accesses += head_memory_accesses;
+        Checkpoint { num: self.checkpoints, trace: tail, next_var, memory_accesses: self.tail_memory_accesses }
+    }
+
+    /// The number of events in the trace.
+    pub fn len(&self) -> usize {
+        self.tail_len + self.head.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// An estimate of the memory used by the events in the trace, in
+    /// bytes, not counting any memory they own.
+    pub fn size_estimate(&self) -> usize {
+        self.len() * mem::size_of::<Event<B>>()
+    }
+
+    /// If the estimated size of the trace exceeds `budget` bytes, and
+    /// the trace has at least doubled in length since it was last
+    /// simplified, apply `simplify` to the whole trace (in
+    /// [EventOrder::Execution] order). The simplified trace is no
+    /// longer shared with any checkpoints taken before, and the
+    /// simplification must keep every SMT definition the rest of the
+    /// path might use, as the trace is replayed when resuming from a
+    /// later checkpoint. Returns true if the trace was simplified.
+    pub fn simplify_over<F>(&mut self, budget: usize, simplify: F) -> bool
+    where
+        F: FnOnce(&mut Vec<Event<B>>),
+    {
+        if self.size_estimate() <= budget || self.len() < 2 * self.simplified_len {
+            return false;
+        }
+        let mut events: Vec<Event<B>> = self.to_vec_in(EventOrder::Execution).into_iter().cloned().collect();
+        simplify(&mut events);
+        *self = Trace {
+            checkpoints: 0,
+            simplified_len: events.len(),
+            head: events,
+            tail: Arc::new(None),
+            tail_len: 0,
+            tail_memory_accesses: 0,
+        };
+        true
+    }
+
+    /// Returns the events added since the last checkpoint, in
+    /// [EventOrder::Execution] order.
+    pub fn head(&self) -> &[Event<B>] {
+        &self.head
     }
 
+    /// Returns the events in the trace in [EventOrder::Reverse] order.
     pub fn to_vec<'a>(&'a self) -> Vec<&'a Event<B>> {
         let mut vec: Vec<&'a Event<B>> = Vec::new();
 
@@ -452,1357 +776,446 @@ impl<B: BV> Trace<B> {
             }
         }
     }
-}
 
-/// Config is a wrapper around the `Z3_config` type from the C
-/// API. `Z3_del_config` is called when it is dropped.
-pub struct Config {
-    z3_cfg: Z3_config,
+    /// Returns the events in the trace in the given order.
+    pub fn to_vec_in(&self, order: EventOrder) -> Vec<&Event<B>> {
+        let mut vec = self.to_vec();
+        EventOrder::Reverse.convert(order, &mut vec);
+        vec
+    }
 }
 
-impl Config {
-    pub fn new() -> Self {
-        unsafe { Config { z3_cfg: Z3_mk_config() } }
-    }
+static FORK_CHECKS: AtomicU64 = AtomicU64::new(0);
+static CONCURRENT_FORK_CHECKS: AtomicU64 = AtomicU64::new(0);
+static FORK_CHECK_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Process-wide counts of the paired feasibility checks made when a
+/// path reaches a symbolic branch, and the total wall-clock time they
+/// took.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkCheckCounts {
+    pub checks: u64,
+    pub concurrent_checks: u64,
+    pub micros: u64,
 }
 
-impl Drop for Config {
-    fn drop(&mut self) {
-        unsafe { Z3_del_config(self.z3_cfg) }
+impl ForkCheckCounts {
+    /// The mean wall-clock time of a fork check in microseconds.
+    pub fn mean_micros(&self) -> f64 {
+        if self.checks == 0 {
+            0.0
+        } else {
+            self.micros as f64 / self.checks as f64
+        }
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self::new()
+impl fmt::Display for ForkCheckCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} fork checks ({} concurrent) in {:.1}ms, {:.1}us per check",
+            self.checks,
+            self.concurrent_checks,
+            self.micros as f64 / 1000.0,
+            self.mean_micros()
+        )
     }
 }
 
-impl Config {
-    pub fn set_param_value(&mut self, id: &str, value: &str) {
-        let id = CString::new(id).unwrap();
-        let value = CString::new(value).unwrap();
-        unsafe { Z3_set_param_value(self.z3_cfg, id.as_ptr(), value.as_ptr()) }
+/// Returns the fork check counts accumulated by every solver in this
+/// process so far.
+pub fn fork_check_counts() -> ForkCheckCounts {
+    ForkCheckCounts {
+        checks: FORK_CHECKS.load(Ordering::Relaxed),
+        concurrent_checks: CONCURRENT_FORK_CHECKS.load(Ordering::Relaxed),
+        micros: FORK_CHECK_MICROS.load(Ordering::Relaxed),
     }
 }
 
-pub fn global_set_param_value(id: &str, value: &str) {
-    let id = CString::new(id).unwrap();
-    let value = CString::new(value).unwrap();
-    unsafe { Z3_global_param_set(id.as_ptr(), value.as_ptr()) }
-}
+static CHECKPOINT_RESTORES: AtomicU64 = AtomicU64::new(0);
+static CHECKPOINT_EVENTS_REPLAYED: AtomicU64 = AtomicU64::new(0);
+static CHECKPOINT_RESTORE_MICROS: AtomicU64 = AtomicU64::new(0);
 
-/// Context is a wrapper around `Z3_context`.
-pub struct Context {
-    z3_ctx: Z3_context,
+fn record_restore(replayed: usize, since: Instant) {
+    CHECKPOINT_RESTORES.fetch_add(1, Ordering::Relaxed);
+    CHECKPOINT_EVENTS_REPLAYED.fetch_add(replayed as u64, Ordering::Relaxed);
+    CHECKPOINT_RESTORE_MICROS.fetch_add(since.elapsed().as_micros() as u64, Ordering::Relaxed);
 }
 
-impl Context {
-    pub fn new(cfg: Config) -> Self {
-        unsafe { Context { z3_ctx: Z3_mk_context_rc(cfg.z3_cfg) } }
-    }
+/// Process-wide counts of the solvers created from checkpoints when
+/// tasks start, the number of events replayed into them, and the
+/// total wall-clock time taken, including any time spent copying
+/// solvers in and out of a [CheckpointCache].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointRestoreCounts {
+    pub restores: u64,
+    pub events_replayed: u64,
+    pub micros: u64,
+}
 
-    fn error(&self) -> ExecError {
-        unsafe {
-            let code = Z3_get_error_code(self.z3_ctx);
-            let msg = Z3_get_error_msg(self.z3_ctx, code);
-            let str: String = CStr::from_ptr(msg).to_string_lossy().to_string();
-            ExecError::Z3Error(str)
-        }
+impl fmt::Display for CheckpointRestoreCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mean = if self.restores == 0 { 0.0 } else { self.micros as f64 / self.restores as f64 };
+        write!(
+            f,
+            "{} checkpoint restores replaying {} events in {:.1}ms, {:.1}us per restore",
+            self.restores,
+            self.events_replayed,
+            self.micros as f64 / 1000.0,
+            mean
+        )
     }
 }
 
-impl Drop for Context {
-    fn drop(&mut self) {
-        unsafe { Z3_del_context(self.z3_ctx) }
+/// Returns the checkpoint restore counts accumulated by every task in
+/// this process so far.
+pub fn checkpoint_restore_counts() -> CheckpointRestoreCounts {
+    CheckpointRestoreCounts {
+        restores: CHECKPOINT_RESTORES.load(Ordering::Relaxed),
+        events_replayed: CHECKPOINT_EVENTS_REPLAYED.load(Ordering::Relaxed),
+        micros: CHECKPOINT_RESTORE_MICROS.load(Ordering::Relaxed),
     }
 }
 
-struct Enum {
-    sort: Z3_sort,
-    consts: Vec<Z3_func_decl>,
-    testers: Vec<Z3_func_decl>,
+/// A checkpoint whose solver state is held by a [CheckpointCache]
+struct CachedCheckpoint<B> {
+    // Keeps the trace alive, so its address cannot be reused by
+    // another checkpoint while it is the key of this entry
+    _trace: Arc<Option<Trace<B>>>,
+    snapshot: Arc<Mutex<SolverSnapshot>>,
 }
 
-struct Enums<'ctx> {
-    enums: HashMap<Name, Enum, ahash::RandomState>,
-    ctx: &'ctx Context,
+struct CachedCheckpoints<B> {
+    entries: HashMap<usize, CachedCheckpoint<B>>,
+    // Keys in insertion order, oldest first, for eviction
+    order: VecDeque<usize>,
 }
 
-impl<'ctx> Enums<'ctx> {
-    fn new(ctx: &'ctx Context) -> Self {
-        Enums { enums: HashMap::default(), ctx }
-    }
-
-    fn add_enum(&mut self, name: Name, z3_name: Sym, members: &[Sym]) {
-        unsafe {
-            let ctx = self.ctx.z3_ctx;
-            let size = members.len();
-
-            let z3_name = Z3_mk_int_symbol(ctx, z3_name.id as c_int);
-            let members: Vec<Z3_symbol> = members.iter().map(|m| Z3_mk_int_symbol(ctx, m.id as c_int)).collect();
-
-            let mut consts = mem::ManuallyDrop::new(Vec::with_capacity(size));
-            let mut testers = mem::ManuallyDrop::new(Vec::with_capacity(size));
-
-            let sort = Z3_mk_enumeration_sort(
-                ctx,
-                z3_name,
-                size as c_uint,
-                members.as_ptr(),
-                consts.as_mut_ptr(),
-                testers.as_mut_ptr(),
-            );
-
-            let consts = Vec::from_raw_parts(consts.as_mut_ptr(), size, size);
-            let testers = Vec::from_raw_parts(testers.as_mut_ptr(), size, size);
-
-            for i in 0..size {
-                Z3_inc_ref(ctx, Z3_func_decl_to_ast(ctx, consts[i]));
-                Z3_inc_ref(ctx, Z3_func_decl_to_ast(ctx, testers[i]))
-            }
-            Z3_inc_ref(ctx, Z3_sort_to_ast(ctx, sort));
+/// Solver states for checkpoints which have already been restored,
+/// shared between the threads running tasks. Tasks forked from the
+/// same path share the checkpoints of its trace up to the fork, so
+/// rather than replaying every event of its checkpoint into a new
+/// solver, a task's solver can be copied from the most recent cached
+/// checkpoint in its trace, with only the newer events replayed. This
+/// is only worthwhile when those prefixes are long, as copying a
+/// solver has a cost of its own. Unlike [crate::executor::FunctionCache]
+/// no results are reused, only the state of the solver.
+pub struct CheckpointCache<B> {
+    capacity: usize,
+    min_events: usize,
+    cached: Mutex<CachedCheckpoints<B>>,
+    hits: AtomicU64,
+    prefix_hits: AtomicU64,
+    misses: AtomicU64,
+}
 
-            self.enums.insert(name, Enum { sort, consts, testers });
-        }
-    }
+/// How often a [CheckpointCache] was used. A prefix hit restores a
+/// checkpoint which extends a cached one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointCacheCounts {
+    pub entries: u64,
+    pub hits: u64,
+    pub prefix_hits: u64,
+    pub misses: u64,
 }
 
-impl<'ctx> Drop for Enums<'ctx> {
-    fn drop(&mut self) {
-        unsafe {
-            let ctx = self.ctx.z3_ctx;
-            for (_, e) in self.enums.drain() {
-                for i in 0..e.consts.len() {
-                    Z3_dec_ref(ctx, Z3_func_decl_to_ast(ctx, e.consts[i]));
-                    Z3_dec_ref(ctx, Z3_func_decl_to_ast(ctx, e.testers[i]))
-                }
-                Z3_dec_ref(ctx, Z3_sort_to_ast(ctx, e.sort))
-            }
-        }
+impl fmt::Display for CheckpointCacheCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} cached checkpoints, {} hits, {} prefix hits, and {} misses",
+            self.entries, self.hits, self.prefix_hits, self.misses
+        )
     }
 }
 
-struct Sort<'ctx> {
-    z3_sort: Z3_sort,
-    ctx: &'ctx Context,
+fn trace_key<B>(trace: &Option<Trace<B>>) -> usize {
+    trace as *const Option<Trace<B>> as usize
 }
 
-impl<'ctx> Sort<'ctx> {
-    fn float(ctx: &'ctx Context, ebits: u32, sbits: u32) -> Self {
-        assert!(ebits > 1 && sbits > 2);
-
-        unsafe {
-            let z3_sort = Z3_mk_fpa_sort(ctx.z3_ctx, ebits, sbits);
-            Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
-            Sort { z3_sort, ctx }
-        }
-    }
-
-    fn bitvec(ctx: &'ctx Context, sz: u32) -> Self {
-        unsafe {
-            let z3_sort = Z3_mk_bv_sort(ctx.z3_ctx, sz);
-            Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
-            Sort { z3_sort, ctx }
-        }
-    }
-
-    fn new(ctx: &'ctx Context, enums: &Enums<'ctx>, ty: &Ty) -> Self {
-        unsafe {
-            match ty {
-                Ty::Bool => {
-                    let z3_sort = Z3_mk_bool_sort(ctx.z3_ctx);
-                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
-                    Sort { z3_sort, ctx }
-                }
-                Ty::BitVec(sz) => Self::bitvec(ctx, *sz),
-                Ty::Enum(e) => {
-                    let z3_sort = enums.enums[&e.id].sort;
-                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
-                    Sort { z3_sort, ctx }
-                }
-                Ty::Array(dom, codom) => {
-                    let dom_s = Self::new(ctx, enums, dom);
-                    let codom_s = Self::new(ctx, enums, codom);
-                    let z3_sort = Z3_mk_array_sort(ctx.z3_ctx, dom_s.z3_sort, codom_s.z3_sort);
-                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
-                    Sort { z3_sort, ctx }
-                }
-                Ty::Float(ebits, sbits) => Self::float(ctx, *ebits, *sbits),
-                Ty::RoundingMode => {
-                    let z3_sort = Z3_mk_fpa_rounding_mode_sort(ctx.z3_ctx);
-                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
-                    Sort { z3_sort, ctx }
-                }
+impl<B: BV> CheckpointCache<B> {
+    /// Create a cache holding at most `capacity` checkpoints, each
+    /// with a copy of a solver in its own context. The oldest
+    /// checkpoint is evicted when it is full.
+    pub fn new(capacity: usize) -> Self {
+        CheckpointCache {
+            capacity,
+            min_events: 1000,
+            cached: Mutex::new(CachedCheckpoints { entries: HashMap::new(), order: VecDeque::new() }),
+            hits: AtomicU64::new(0),
+            prefix_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Only cache checkpoints when restoring them replays at least
+    /// `min_events` events (1000 by default). Copying a solver into
+    /// and out of the cache has a fixed cost of a few milliseconds,
+    /// so shorter traces are faster to replay.
+    pub fn with_min_events(self, min_events: usize) -> Self {
+        CheckpointCache { min_events, ..self }
+    }
+
+    /// Find the most recent cached checkpoint in `trace`, returning
+    /// how many newer checkpoints there are along with its solver.
+    fn lookup(&self, trace: &Option<Trace<B>>) -> Option<(usize, Arc<Mutex<SolverSnapshot>>)> {
+        let cached = self.cached.lock().unwrap();
+        let mut newer = 0;
+        let mut next = trace;
+        while let Some(tr) = next {
+            if let Some(checkpoint) = cached.entries.get(&trace_key(next)) {
+                return Some((newer, checkpoint.snapshot.clone()));
             }
+            newer += 1;
+            next = &*tr.tail
         }
+        None
     }
-}
 
-impl<'ctx> Drop for Sort<'ctx> {
-    fn drop(&mut self) {
-        unsafe {
-            let ctx = self.ctx.z3_ctx;
-            Z3_dec_ref(ctx, Z3_sort_to_ast(ctx, self.z3_sort))
+    fn insert(&self, trace: Arc<Option<Trace<B>>>, snapshot: SolverSnapshot) {
+        let mut cached = self.cached.lock().unwrap();
+        let key = trace_key(&trace);
+        if cached.entries.contains_key(&key) {
+            return;
         }
-    }
-}
-
-struct FuncDecl<'ctx> {
-    z3_func_decl: Z3_func_decl,
-    ctx: &'ctx Context,
-}
-
-impl<'ctx> FuncDecl<'ctx> {
-    fn new(ctx: &'ctx Context, v: Sym, enums: &Enums<'ctx>, arg_tys: &[Ty], ty: &Ty) -> Self {
-        unsafe {
-            let name = Z3_mk_int_symbol(ctx.z3_ctx, v.id as c_int);
-            let arg_sorts: Vec<Sort> = arg_tys.iter().map(|ty| Sort::new(ctx, enums, ty)).collect();
-            let arg_z3_sorts: Vec<Z3_sort> = arg_sorts.iter().map(|s| s.z3_sort).collect();
-            let args: u32 = arg_sorts.len() as u32;
-            let z3_func_decl =
-                Z3_mk_func_decl(ctx.z3_ctx, name, args, arg_z3_sorts.as_ptr(), Sort::new(ctx, enums, ty).z3_sort);
-            Z3_inc_ref(ctx.z3_ctx, Z3_func_decl_to_ast(ctx.z3_ctx, z3_func_decl));
-            FuncDecl { z3_func_decl, ctx }
+        if cached.order.len() >= self.capacity {
+            if let Some(oldest) = cached.order.pop_front() {
+                cached.entries.remove(&oldest);
+            }
         }
-    }
-}
+        cached.order.push_back(key);
+        cached.entries.insert(key, CachedCheckpoint { _trace: trace, snapshot: Arc::new(Mutex::new(snapshot)) });
+    }
+
+    /// Create a solver from a checkpoint, as [Solver::from_checkpoint]
+    /// does, starting from the most recent cached checkpoint in its
+    /// trace. The checkpoint is then cached itself if at least half
+    /// of its events had to be replayed, so the cached checkpoints in
+    /// a long trace are spread out rather than one per fork.
+    pub fn restore<'ctx>(
+        &self,
+        ctx: &'ctx Context,
+        Checkpoint { num, next_var, trace, memory_accesses }: Checkpoint<B>,
+    ) -> Solver<'ctx, B> {
+        let now = Instant::now();
+        let cached = self.lookup(&trace);
+        let (mut solver, elaborated) = match &cached {
+            Some((newer, snapshot)) => (Solver::from_snapshot(ctx, &snapshot.lock().unwrap()), num - newer),
+            None => (Solver::new(ctx), 0),
+        };
+        let replayed = solver.replay(num, trace.clone(), elaborated);
+        solver.next_var = next_var;
+        solver.trace.tail_memory_accesses = memory_accesses;
 
-impl<'ctx> Drop for FuncDecl<'ctx> {
-    fn drop(&mut self) {
-        unsafe {
-            let ctx = self.ctx.z3_ctx;
-            Z3_dec_ref(ctx, Z3_func_decl_to_ast(ctx, self.z3_func_decl))
+        match cached {
+            Some((0, _)) => self.hits.fetch_add(1, Ordering::Relaxed),
+            Some(_) => self.prefix_hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        if self.capacity > 0 && replayed >= self.min_events.max(1) && 2 * replayed >= solver.trace.tail_len {
+            self.insert(trace, solver.snapshot())
         }
+        record_restore(replayed, now);
+        solver
     }
-}
 
-struct Ast<'ctx> {
-    z3_ast: Z3_ast,
-    ctx: &'ctx Context,
-}
-
-impl<'ctx> Clone for Ast<'ctx> {
-    fn clone(&self) -> Self {
-        unsafe {
-            let z3_ast = self.z3_ast;
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
+    pub fn counts(&self) -> CheckpointCacheCounts {
+        CheckpointCacheCounts {
+            entries: self.cached.lock().unwrap().entries.len() as u64,
+            hits: self.hits.load(Ordering::Relaxed),
+            prefix_hits: self.prefix_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
         }
     }
 }
 
-macro_rules! z3_nullary_op {
-    ($i:ident, $ctx:ident) => {
-        unsafe {
-            let z3_ast = $i($ctx.z3_ctx);
-            Z3_inc_ref($ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: $ctx }
-        }
-    };
-}
-
-macro_rules! z3_unary_op {
-    ($i:ident, $arg:ident) => {
-        unsafe {
-            let z3_ast = $i($arg.ctx.z3_ctx, $arg.z3_ast);
-            Z3_inc_ref($arg.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: $arg.ctx }
-        }
-    };
-}
-
-macro_rules! z3_binary_op {
-    ($i:ident, $lhs:ident, $rhs:ident) => {
-        unsafe {
-            let z3_ast = $i($lhs.ctx.z3_ctx, $lhs.z3_ast, $rhs.z3_ast);
-            Z3_inc_ref($lhs.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: $lhs.ctx }
-        }
-    };
-}
-
-macro_rules! z3_float_binary_op {
-    ($i:ident, $rm:ident, $lhs:ident, $rhs:ident) => {
-        unsafe {
-            let z3_ast = $i($lhs.ctx.z3_ctx, $rm.z3_ast, $lhs.z3_ast, $rhs.z3_ast);
-            Z3_inc_ref($lhs.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: $rm.ctx }
-        }
-    };
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtResult {
+    Sat,
+    Unsat,
+    Unknown,
 }
 
-impl<'ctx> Ast<'ctx> {
-    fn mk_constant(fd: &FuncDecl<'ctx>) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_app(fd.ctx.z3_ctx, fd.z3_func_decl, 0, ptr::null());
-            Z3_inc_ref(fd.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: fd.ctx }
-        }
-    }
-
-    fn mk_app(fd: &FuncDecl<'ctx>, args: &[Ast<'ctx>]) -> Self {
-        unsafe {
-            let z3_args: Vec<Z3_ast> = args.iter().map(|ast| ast.z3_ast).collect();
-            let len = z3_args.len() as u32;
-            let z3_ast = Z3_mk_app(fd.ctx.z3_ctx, fd.z3_func_decl, len, z3_args.as_ptr());
-            Z3_inc_ref(fd.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: fd.ctx }
-        }
-    }
-
-    fn mk_enum_member(enums: &Enums<'ctx>, enum_id: EnumId, member: usize) -> Self {
-        unsafe {
-            let func_decl = enums.enums[&enum_id.id].consts[member];
-            let z3_ast = Z3_mk_app(enums.ctx.z3_ctx, func_decl, 0, ptr::null());
-            Z3_inc_ref(enums.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: enums.ctx }
-        }
-    }
+use SmtResult::*;
 
-    fn mk_bv_u64(ctx: &'ctx Context, sz: u32, bits: u64) -> Self {
-        unsafe {
-            let sort = Sort::bitvec(ctx, sz);
-            let z3_ast = Z3_mk_unsigned_int64(ctx.z3_ctx, bits, sort.z3_sort);
-            Z3_inc_ref(ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx }
+impl SmtResult {
+    pub fn is_sat(self) -> Result<bool, ExecError> {
+        match self {
+            Sat => Ok(true),
+            Unsat => Ok(false),
+            Unknown => Err(ExecError::Z3Unknown),
         }
     }
 
-    fn mk_bv(ctx: &'ctx Context, sz: u32, bits: &[bool]) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_bv_numeral(ctx.z3_ctx, sz, bits.as_ptr());
-            Z3_inc_ref(ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx }
+    pub fn is_unsat(self) -> Result<bool, ExecError> {
+        match self {
+            Sat => Ok(false),
+            Unsat => Ok(true),
+            Unknown => Err(ExecError::Z3Unknown),
         }
     }
 
-    fn mk_fpa_nan(ctx: &'ctx Context, ebits: u32, sbits: u32) -> Self {
-        unsafe {
-            let sort = Sort::float(ctx, ebits, sbits);
-            let z3_ast = Z3_mk_fpa_nan(ctx.z3_ctx, sort.z3_sort);
-            Z3_inc_ref(ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx }
-        }
+    pub fn is_unknown(self) -> bool {
+        self == Unknown
     }
+}
 
-    fn mk_fpa_zero(ctx: &'ctx Context, ebits: u32, sbits: u32, negative: bool) -> Self {
-        unsafe {
-            let sort = Sort::float(ctx, ebits, sbits);
-            let z3_ast = Z3_mk_fpa_zero(ctx.z3_ctx, sort.z3_sort, negative);
-            Z3_inc_ref(ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx }
-        }
+impl<'ctx, B: BV> Solver<'ctx, B> {
+    pub fn fresh(&mut self) -> Sym {
+        let n = self.next_var;
+        self.next_var += 1;
+        Sym { id: n }
     }
 
-    fn mk_fpa_inf(ctx: &'ctx Context, ebits: u32, sbits: u32, negative: bool) -> Self {
-        unsafe {
-            let sort = Sort::float(ctx, ebits, sbits);
-            let z3_ast = Z3_mk_fpa_inf(ctx.z3_ctx, sort.z3_sort, negative);
-            Z3_inc_ref(ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx }
-        }
+    pub fn with_def_attrs<F, A>(&mut self, attrs: DefAttrs, f: F) -> A
+    where
+        F: Fn(&mut Self) -> A,
+    {
+        let old_attrs = self.def_attrs;
+        self.def_attrs = attrs;
+        let x = f(self);
+        self.def_attrs = old_attrs;
+        x
     }
 
-    fn mk_bool(ctx: &'ctx Context, b: bool) -> Self {
-        unsafe {
-            let z3_ast = if b { Z3_mk_true(ctx.z3_ctx) } else { Z3_mk_false(ctx.z3_ctx) };
-            Z3_inc_ref(ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx }
-        }
+    pub fn set_def_attrs(&mut self, attrs: DefAttrs) {
+        self.def_attrs = attrs
     }
 
-    fn mk_fpa_round_to_integral(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_round_to_integral, self, rhs)
+    pub fn clear_def_attrs(&mut self) {
+        self.def_attrs = DefAttrs::default()
     }
 
-    fn mk_fpa_round_nearest_ties_to_even(ctx: &'ctx Context) -> Self {
-        z3_nullary_op!(Z3_mk_fpa_round_nearest_ties_to_even, ctx)
+    /// Add a definition to the solver and its trace.
+    ///
+    /// # Panics
+    ///
+    /// Without the `z3` feature, panics if the type of a defined
+    /// constant cannot be inferred, which never happens for the
+    /// definitions made by the symbolic executor. See
+    /// [Solver::try_add_event] for definitions from elsewhere.
+    pub fn add(&mut self, mut def: Def) {
+        rewrite::rewrite_def(&mut def);
+        self.add_def_internal(&def);
+        self.trace.head.push(Event::Smt(def, self.def_attrs, SourceLoc::unknown()))
     }
 
-    fn mk_fpa_round_nearest_ties_to_away(ctx: &'ctx Context) -> Self {
-        z3_nullary_op!(Z3_mk_fpa_round_nearest_ties_to_away, ctx)
+    pub fn add_with_location(&mut self, mut def: Def, info: SourceLoc) {
+        rewrite::rewrite_def(&mut def);
+        self.add_def_internal(&def);
+        self.trace.head.push(Event::Smt(def, self.def_attrs, info))
     }
 
-    fn mk_fpa_round_toward_positive(ctx: &'ctx Context) -> Self {
-        z3_nullary_op!(Z3_mk_fpa_round_toward_positive, ctx)
+    pub fn declare_const(&mut self, ty: Ty, info: SourceLoc) -> Sym {
+        let sym = self.fresh();
+        self.add_with_location(Def::DeclareConst(sym, ty), info);
+        sym
     }
 
-    fn mk_fpa_round_toward_negative(ctx: &'ctx Context) -> Self {
-        z3_nullary_op!(Z3_mk_fpa_round_toward_negative, ctx)
+    /// Declare a fresh constant for each type in `tys`, returning the
+    /// new symbols in the same order. Equivalent to calling
+    /// [Solver::declare_const] for each type, but the trace is only
+    /// grown once.
+    pub fn declare_consts(&mut self, tys: Vec<Ty>, info: SourceLoc) -> Vec<Sym> {
+        self.trace.head.reserve(tys.len());
+        tys.into_iter()
+            .map(|ty| {
+                let sym = self.fresh();
+                self.declare_const_internal(sym, &ty);
+                self.trace.head.push(Event::Smt(Def::DeclareConst(sym, ty), self.def_attrs, info));
+                sym
+            })
+            .collect()
     }
 
-    fn mk_fpa_round_toward_zero(ctx: &'ctx Context) -> Self {
-        z3_nullary_op!(Z3_mk_fpa_round_toward_zero, ctx)
+    pub fn define_const(&mut self, exp: Exp<Sym>, info: SourceLoc) -> Sym {
+        let sym = self.fresh();
+        self.add_with_location(Def::DefineConst(sym, exp), info);
+        sym
     }
 
-    fn mk_not(&self) -> Self {
-        z3_unary_op!(Z3_mk_not, self)
+    pub fn choice(&mut self, exp1: Exp<Sym>, exp2: Exp<Sym>, info: SourceLoc) -> Sym {
+        use Exp::*;
+        let b = self.declare_const(Ty::Bool, info);
+        self.define_const(Ite(Box::new(Var(b)), Box::new(exp1), Box::new(exp2)), info)
     }
 
-    fn mk_eq(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_eq, self, rhs)
+    pub fn assert_eq(&mut self, lhs: Exp<Sym>, rhs: Exp<Sym>) {
+        self.add(Def::Assert(Exp::Eq(Box::new(lhs), Box::new(rhs))))
     }
 
-    fn mk_and(&self, rhs: &Ast<'ctx>) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_and(self.ctx.z3_ctx, 2, &[self.z3_ast, rhs.z3_ast] as *const Z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
+    pub fn assert(&mut self, exp: Exp<Sym>) {
+        self.add(Def::Assert(exp))
     }
 
-    fn mk_or(&self, rhs: &Ast<'ctx>) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_or(self.ctx.z3_ctx, 2, &[self.z3_ast, rhs.z3_ast] as *const Z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
+    /// Assert every expression in `exps`. The solver receives their
+    /// conjunction in a single call, while the trace still records
+    /// one assertion per expression.
+    pub fn assert_all(&mut self, mut exps: Vec<Exp<Sym>>) {
+        if exps.is_empty() {
+            return;
         }
-    }
-
-    fn extract(&self, hi: u32, lo: u32) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_extract(self.ctx.z3_ctx, hi, lo, self.z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
+        exps.iter_mut().for_each(rewrite::rewrite);
+        self.assert_all_internal(&exps);
+        self.trace.head.reserve(exps.len());
+        for exp in exps {
+            self.trace.head.push(Event::Smt(Def::Assert(exp), self.def_attrs, SourceLoc::unknown()))
         }
     }
 
-    fn zero_extend(&self, i: u32) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_zero_ext(self.ctx.z3_ctx, i, self.z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
+    pub fn cycle_count(&mut self) {
+        self.cycles += 1;
+        self.add_event(Event::Cycle)
     }
 
-    fn sign_extend(&self, i: u32) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_sign_ext(self.ctx.z3_ctx, i, self.z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
+    pub fn get_cycle_count(&self) -> i128 {
+        self.cycles
     }
 
-    fn ite(&self, true_exp: &Ast<'ctx>, false_exp: &Ast<'ctx>) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_ite(self.ctx.z3_ctx, self.z3_ast, true_exp.z3_ast, false_exp.z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
+    fn add_def_internal(&mut self, def: &Def) {
+        if let Err(err) = self.add_internal(def) {
+            panic!("{}", err)
         }
     }
 
-    fn mk_bvnot(&self) -> Self {
-        z3_unary_op!(Z3_mk_bvnot, self)
-    }
-
-    fn mk_bvand(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvand, self, rhs)
-    }
-
-    fn mk_bvor(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvor, self, rhs)
-    }
-
-    fn mk_bvxor(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvxor, self, rhs)
-    }
-
-    fn mk_bvnand(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvnand, self, rhs)
-    }
-
-    fn mk_bvnor(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvnor, self, rhs)
+    fn add_event_internal(&mut self, event: &Event<B>) {
+        if let Event::Smt(def, _, _) = event {
+            self.add_def_internal(def)
+        };
     }
 
-    fn mk_bvxnor(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvxnor, self, rhs)
+    pub fn add_event(&mut self, event: Event<B>) {
+        self.add_event_internal(&event);
+        self.trace.head.push(event)
     }
 
-    fn mk_bvneg(&self) -> Self {
-        z3_unary_op!(Z3_mk_bvneg, self)
+    /// As [Solver::add_event], but returning an error rather than
+    /// panicking if the event defines a constant whose type cannot be
+    /// inferred, for events which were not produced by the symbolic
+    /// executor, such as those in a saved trace. The event is only
+    /// added to the trace if this succeeds.
+    pub fn try_add_event(&mut self, event: Event<B>) -> Result<(), ExecError> {
+        if let Event::Smt(def, _, _) = &event {
+            self.add_internal(def)?
+        }
+        self.trace.head.push(event);
+        Ok(())
     }
 
-    fn mk_bvadd(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvadd, self, rhs)
+    pub fn trace_call(&mut self, name: Name) {
+        self.add_event(Event::Function { name, call: true })
     }
 
-    fn mk_bvsub(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvsub, self, rhs)
+    pub fn trace_return(&mut self, name: Name) {
+        self.add_event(Event::Function { name, call: false })
     }
 
-    fn mk_bvmul(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvmul, self, rhs)
-    }
-
-    fn mk_bvudiv(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvudiv, self, rhs)
-    }
-
-    fn mk_bvsdiv(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvsdiv, self, rhs)
-    }
-
-    fn mk_bvurem(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvurem, self, rhs)
-    }
-
-    fn mk_bvsrem(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvsrem, self, rhs)
-    }
-
-    fn mk_bvsmod(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvsmod, self, rhs)
-    }
-
-    fn mk_bvult(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvult, self, rhs)
-    }
-
-    fn mk_bvslt(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvslt, self, rhs)
-    }
-
-    fn mk_bvule(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvule, self, rhs)
-    }
-
-    fn mk_bvsle(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvsle, self, rhs)
-    }
-
-    fn mk_bvuge(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvuge, self, rhs)
-    }
-
-    fn mk_bvsge(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvsge, self, rhs)
-    }
-
-    fn mk_bvugt(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvugt, self, rhs)
-    }
-
-    fn mk_bvsgt(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvsgt, self, rhs)
-    }
-
-    fn mk_bvshl(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvshl, self, rhs)
-    }
-
-    fn mk_bvlshr(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvlshr, self, rhs)
-    }
-
-    fn mk_bvashr(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_bvashr, self, rhs)
-    }
-
-    fn mk_concat(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_concat, self, rhs)
-    }
-
-    fn mk_select(&self, index: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_select, self, index)
-    }
-
-    fn mk_fpa_abs(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_abs, self)
-    }
-
-    fn mk_fpa_neg(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_neg, self)
-    }
-
-    fn mk_fpa_add(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
-        z3_float_binary_op!(Z3_mk_fpa_add, self, lhs, rhs)
-    }
-
-    fn mk_fpa_div(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
-        z3_float_binary_op!(Z3_mk_fpa_div, self, lhs, rhs)
-    }
-
-    fn mk_fpa_eq(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_eq, self, rhs)
-    }
-
-    fn mk_fpa_fma(&self, t1: &Ast<'ctx>, t2: &Ast<'ctx>, t3: &Ast<'ctx>) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_fpa_fma(self.ctx.z3_ctx, self.z3_ast, t1.z3_ast, t2.z3_ast, t3.z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_fpa_geq(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_geq, self, rhs)
-    }
-
-    fn mk_fpa_gt(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_gt, self, rhs)
-    }
-
-    fn mk_fpa_leq(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_leq, self, rhs)
-    }
-
-    fn mk_fpa_lt(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_lt, self, rhs)
-    }
-
-    fn mk_fpa_rem(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_rem, self, rhs)
-    }
-
-    fn mk_fpa_max(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_max, self, rhs)
-    }
-
-    fn mk_fpa_min(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_min, self, rhs)
-    }
-
-    fn mk_fpa_mul(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
-        z3_float_binary_op!(Z3_mk_fpa_mul, self, lhs, rhs)
-    }
-
-    fn mk_fpa_sqrt(&self, rhs: &Ast<'ctx>) -> Self {
-        z3_binary_op!(Z3_mk_fpa_sqrt, self, rhs)
-    }
-
-    fn mk_fpa_sub(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
-        z3_float_binary_op!(Z3_mk_fpa_sub, self, lhs, rhs)
-    }
-
-    fn mk_fpa_is_normal(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_is_normal, self)
-    }
-
-    fn mk_fpa_is_subnormal(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_is_subnormal, self)
-    }
-
-    fn mk_fpa_is_zero(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_is_zero, self)
-    }
-
-    fn mk_fpa_is_infinite(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_is_infinite, self)
-    }
-
-    fn mk_fpa_is_nan(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_is_nan, self)
-    }
-
-    fn mk_fpa_is_negative(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_is_negative, self)
-    }
-
-    fn mk_fpa_is_positive(&self) -> Self {
-        z3_unary_op!(Z3_mk_fpa_is_positive, self)
-    }
-
-    fn mk_fpa_to_fp_bv(&self, ebits: u32, sbits: u32) -> Self {
-        unsafe {
-            let sort = Sort::float(self.ctx, ebits, sbits);
-            let z3_ast = Z3_mk_fpa_to_fp_bv(self.ctx.z3_ctx, self.z3_ast, sort.z3_sort);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_fpa_to_fp_float(&self, exp: &Ast<'ctx>, ebits: u32, sbits: u32) -> Self {
-        unsafe {
-            let sort = Sort::float(self.ctx, ebits, sbits);
-            let z3_ast = Z3_mk_fpa_to_fp_float(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sort.z3_sort);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_fpa_to_fp_signed(&self, exp: &Ast<'ctx>, ebits: u32, sbits: u32) -> Self {
-        unsafe {
-            let sort = Sort::float(self.ctx, ebits, sbits);
-            let z3_ast = Z3_mk_fpa_to_fp_signed(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sort.z3_sort);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_fpa_to_fp_unsigned(&self, exp: &Ast<'ctx>, ebits: u32, sbits: u32) -> Self {
-        unsafe {
-            let sort = Sort::float(self.ctx, ebits, sbits);
-            let z3_ast = Z3_mk_fpa_to_fp_unsigned(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sort.z3_sort);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_fpa_to_sbv(&self, exp: &Ast<'ctx>, sz: u32) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_fpa_to_sbv(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sz);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_fpa_to_ubv(&self, exp: &Ast<'ctx>, sz: u32) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_fpa_to_ubv(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sz);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_store(&self, index: &Ast<'ctx>, val: &Ast<'ctx>) -> Self {
-        unsafe {
-            let z3_ast = Z3_mk_store(self.ctx.z3_ctx, self.z3_ast, index.z3_ast, val.z3_ast);
-            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx: self.ctx }
-        }
-    }
-
-    fn mk_distinct(ctx: &'ctx Context, args: &[Ast<'ctx>]) -> Self {
-        unsafe {
-            let z3_args: Vec<Z3_ast> = args.iter().map(|ast| ast.z3_ast).collect();
-            let len = z3_args.len() as u32;
-            let z3_ast = Z3_mk_distinct(ctx.z3_ctx, len, z3_args.as_ptr());
-            Z3_inc_ref(ctx.z3_ctx, z3_ast);
-            Ast { z3_ast, ctx }
-        }
-    }
-
-    fn get_bool_value(&self) -> Option<bool> {
-        unsafe {
-            match Z3_get_bool_value(self.ctx.z3_ctx, self.z3_ast) {
-                Z3_L_TRUE => Some(true),
-                Z3_L_FALSE => Some(false),
-                _ => None,
-            }
-        }
-    }
-
-    fn get_numeral_u64(&self) -> Result<u64, ExecError> {
-        let mut v: u64 = 0;
-        unsafe {
-            if Z3_get_numeral_uint64(self.ctx.z3_ctx, self.z3_ast, &mut v) {
-                Ok(v)
-            } else {
-                Err(self.ctx.error())
-            }
-        }
-    }
-}
-
-impl<'ctx> Drop for Ast<'ctx> {
-    fn drop(&mut self) {
-        unsafe { Z3_dec_ref(self.ctx.z3_ctx, self.z3_ast) }
-    }
-}
-
-/// The Solver type handles all interaction with Z3. It mimics
-/// interacting with Z3 via the subset of the SMTLIB 2.0 format we
-/// care about.
-///
-/// For example:
-/// ```
-/// # use isla_lib::bitvector::b64::B64;
-/// # use isla_lib::smt::smtlib::Exp::*;
-/// # use isla_lib::smt::smtlib::Def::*;
-/// # use isla_lib::smt::smtlib::*;
-/// # use isla_lib::smt::*;
-/// # let x = Sym::from_u32(0);
-/// let cfg = Config::new();
-/// let ctx = Context::new(cfg);
-/// let mut solver = Solver::<B64>::new(&ctx);
-/// // (declare-const v0 Bool)
-/// solver.add(DeclareConst(x, Ty::Bool));
-/// // (assert v0)
-/// solver.add(Assert(Var(x)));
-/// // (check-sat)
-/// assert!(solver.check_sat() == SmtResult::Sat)
-/// ```
-///
-/// The other thing the Solver type does is maintain a trace of
-/// interactions with Z3, which can be checkpointed and replayed by
-/// another solver. This `Checkpoint` type is safe to be sent between
-/// threads.
-///
-/// For example:
-/// ```
-/// # use isla_lib::bitvector::b64::B64;
-/// # use isla_lib::smt::smtlib::Exp::*;
-/// # use isla_lib::smt::smtlib::Def::*;
-/// # use isla_lib::smt::smtlib::*;
-/// # use isla_lib::smt::*;
-/// # let x = Sym::from_u32(0);
-/// let point = {
-///     let cfg = Config::new();
-///     let ctx = Context::new(cfg);
-///     let mut solver = Solver::<B64>::new(&ctx);
-///     solver.add(DeclareConst(x, Ty::Bool));
-///     solver.add(Assert(Var(x)));
-///     solver.add(Assert(Not(Box::new(Var(x)))));
-///     checkpoint(&mut solver)
-/// };
-/// let cfg = Config::new();
-/// let ctx = Context::new(cfg);
-/// let mut solver = Solver::from_checkpoint(&ctx, point);
-/// assert!(solver.check_sat() == SmtResult::Unsat);
-pub struct Solver<'ctx, B> {
-    trace: Trace<B>,
-    next_var: u32,
-    def_attrs: DefAttrs,
-    cycles: i128,
-    decls: HashMap<Sym, Ast<'ctx>>,
-    func_decls: HashMap<Sym, FuncDecl<'ctx>>,
-    enums: Enums<'ctx>,
-    z3_solver: Z3_solver,
-    ctx: &'ctx Context,
-}
-
-impl<'ctx, B> Drop for Solver<'ctx, B> {
-    fn drop(&mut self) {
-        unsafe {
-            Z3_solver_dec_ref(self.ctx.z3_ctx, self.z3_solver);
-        }
-    }
-}
-
-/// Interface for extracting information from Z3 models.
-///
-/// Model generation should be turned on in advance.  This is
-/// currently Z3's default, but it's best to make sure:
-///
-/// ```
-/// # use isla_lib::bitvector::b64::B64;
-/// # use isla_lib::smt::smtlib::Exp::*;
-/// # use isla_lib::smt::smtlib::Def::*;
-/// # use isla_lib::smt::smtlib::*;
-/// # use isla_lib::smt::*;
-/// # let x = Sym::from_u32(0);
-/// let mut cfg = Config::new();
-/// cfg.set_param_value("model", "true");
-/// let ctx = Context::new(cfg);
-/// let mut solver = Solver::<B64>::new(&ctx);
-/// solver.add(DeclareConst(x, Ty::BitVec(4)));
-/// solver.add(Assert(Bvsgt(Box::new(Var(x)), Box::new(Bits(vec![false,false,true,false])))));
-/// assert!(solver.check_sat() == SmtResult::Sat);
-/// let mut model = Model::new(&solver);
-/// let var0 = model.get_var(x).unwrap().unwrap();
-/// ```
-pub struct Model<'ctx, B> {
-    z3_model: Z3_model,
-    solver: &'ctx Solver<'ctx, B>,
-    ctx: &'ctx Context,
-    complete_model: bool,
-}
-
-impl<'ctx, B> Drop for Model<'ctx, B> {
-    fn drop(&mut self) {
-        unsafe {
-            Z3_model_dec_ref(self.ctx.z3_ctx, self.z3_model);
-        }
-    }
-}
-
-// This implements Debug rather than Display because it displays the internal
-// variable names (albeit with the same numbers that appear in the trace).
-impl<'ctx, B> fmt::Debug for Model<'ctx, B> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        unsafe {
-            let z3_string = CStr::from_ptr(Z3_model_to_string(self.ctx.z3_ctx, self.z3_model));
-            write!(f, "{}", z3_string.to_string_lossy())
-        }
-    }
-}
-
-impl<'ctx, B: BV> Model<'ctx, B> {
-    pub fn new(solver: &'ctx Solver<'ctx, B>) -> Self {
-        unsafe {
-            let z3_model = Z3_solver_get_model(solver.ctx.z3_ctx, solver.z3_solver);
-            Z3_model_inc_ref(solver.ctx.z3_ctx, z3_model);
-            Model { z3_model, solver, ctx: solver.ctx, complete_model: false }
-        }
-    }
-
-    pub fn set_complete_model(&mut self, b: bool) {
-        self.complete_model = b;
-    }
-
-    #[allow(clippy::needless_range_loop)]
-    fn get_large_bv(&mut self, ast: Ast, size: u32) -> Result<Vec<bool>, ExecError> {
-        let mut i = 0;
-        let size = size.try_into().unwrap();
-        let mut result = vec![false; size];
-        while i < size {
-            let hi = std::cmp::min(size, i + 64);
-            let hi32: u32 = hi.try_into().unwrap();
-            let extract_ast = ast.extract(hi32 - 1, i.try_into().unwrap());
-            let result_ast: Ast;
-
-            unsafe {
-                let mut result_z3_ast: Z3_ast = ptr::null_mut();
-                if !Z3_model_eval(self.ctx.z3_ctx, self.z3_model, extract_ast.z3_ast, true, &mut result_z3_ast) {
-                    return Err(self.ctx.error());
-                }
-                Z3_inc_ref(self.ctx.z3_ctx, result_z3_ast);
-                result_ast = Ast { z3_ast: result_z3_ast, ctx: self.ctx };
-            }
-            let v = result_ast.get_numeral_u64()?;
-            for j in i..hi {
-                result[j] = (v >> (j - i) & 1) == 1;
-            }
-            i += 64;
-        }
-        Ok(result)
-    }
-
-    pub fn get_var(&mut self, var: Sym) -> Result<Option<Exp<Sym>>, ExecError> {
-        let var_ast = match self.solver.decls.get(&var) {
-            None => return Err(ExecError::Type(format!("Unbound variable {:?}", &var), SourceLoc::unknown())),
-            Some(ast) => ast.clone(),
-        };
-        self.get_ast(var_ast)
-    }
-
-    pub fn get_exp(&mut self, exp: &Exp<Sym>) -> Result<Option<Exp<Sym>>, ExecError> {
-        let ast = self.solver.translate_exp(exp);
-        self.get_ast(ast)
-    }
-
-    // Requiring the model to be mutable as I expect Z3 will alter the underlying data
-    fn get_ast(&mut self, var_ast: Ast) -> Result<Option<Exp<Sym>>, ExecError> {
-        unsafe {
-            let z3_ctx = self.ctx.z3_ctx;
-            let mut z3_ast: Z3_ast = ptr::null_mut();
-            if !Z3_model_eval(z3_ctx, self.z3_model, var_ast.z3_ast, self.complete_model, &mut z3_ast) {
-                return Err(self.ctx.error());
-            }
-            Z3_inc_ref(z3_ctx, z3_ast);
-
-            let ast = Ast { z3_ast, ctx: self.ctx };
-
-            let sort = Z3_get_sort(z3_ctx, ast.z3_ast);
-            Z3_inc_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, sort));
-            let sort_kind = Z3_get_sort_kind(z3_ctx, sort);
-
-            let result = if sort_kind == SortKind::BV && Z3_is_numeral_ast(z3_ctx, z3_ast) {
-                let size = Z3_get_bv_sort_size(z3_ctx, sort);
-                if size > 64 {
-                    let v = self.get_large_bv(ast, size)?;
-                    Ok(Some(Exp::Bits(v)))
-                } else {
-                    let result = ast.get_numeral_u64()?;
-                    Ok(Some(Exp::Bits64(B64::new(result, size))))
-                }
-            } else if sort_kind == SortKind::Bool && Z3_is_numeral_ast(z3_ctx, z3_ast) {
-                Ok(Some(Exp::Bool(ast.get_bool_value().unwrap())))
-            } else if sort_kind == SortKind::Bool || sort_kind == SortKind::BV {
-                // Model did not need to assign an interpretation to this variable
-                Ok(None)
-            } else if sort_kind == SortKind::Datatype {
-                let func_decl = Z3_get_app_decl(z3_ctx, Z3_to_app(z3_ctx, z3_ast));
-                Z3_inc_ref(z3_ctx, Z3_func_decl_to_ast(z3_ctx, func_decl));
-
-                let mut result = Ok(None);
-
-                // Scan all enumerations to find the enum_id (which is
-                // the size of the enum) and member number.
-                'outer: for (enum_id, enumeration) in self.solver.enums.enums.iter() {
-                    for (i, member) in enumeration.consts.iter().enumerate() {
-                        if Z3_is_eq_func_decl(z3_ctx, func_decl, *member) {
-                            result = Ok(Some(Exp::Enum(EnumMember { enum_id: EnumId { id: *enum_id }, member: i })));
-                            break 'outer;
-                        }
-                    }
-                }
-
-                Z3_dec_ref(z3_ctx, Z3_func_decl_to_ast(z3_ctx, func_decl));
-                result
-            } else {
-                Err(ExecError::Type("get_ast".to_string(), SourceLoc::unknown()))
-            };
-
-            Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, sort));
-            result
-        }
-    }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum SmtResult {
-    Sat,
-    Unsat,
-    Unknown,
-}
-
-use SmtResult::*;
-
-impl SmtResult {
-    pub fn is_sat(self) -> Result<bool, ExecError> {
-        match self {
-            Sat => Ok(true),
-            Unsat => Ok(false),
-            Unknown => Err(ExecError::Z3Unknown),
-        }
-    }
-
-    pub fn is_unsat(self) -> Result<bool, ExecError> {
-        match self {
-            Sat => Ok(false),
-            Unsat => Ok(true),
-            Unknown => Err(ExecError::Z3Unknown),
-        }
-    }
-
-    pub fn is_unknown(self) -> bool {
-        self == Unknown
-    }
-}
-
-static QFAUFBV_STR: &[u8] = b"qfaufbv\0";
-
-impl<'ctx, B: BV> Solver<'ctx, B> {
-    pub fn new(ctx: &'ctx Context) -> Self {
-        unsafe {
-            let mut major: c_uint = 0;
-            let mut minor: c_uint = 0;
-            let mut build: c_uint = 0;
-            let mut revision: c_uint = 0;
-            Z3_get_version(&mut major, &mut minor, &mut build, &mut revision);
-
-            // The QF_AUFBV solver has good performance on our problems, but we need to initialise it
-            // using a tactic rather than the logic name to ensure that the enumerations are supported,
-            // otherwise Z3 may crash.
-            let qfaufbv_tactic = Z3_mk_tactic(ctx.z3_ctx, CStr::from_bytes_with_nul_unchecked(QFAUFBV_STR).as_ptr());
-            Z3_tactic_inc_ref(ctx.z3_ctx, qfaufbv_tactic);
-            let z3_solver = Z3_mk_solver_from_tactic(ctx.z3_ctx, qfaufbv_tactic);
-            Z3_solver_inc_ref(ctx.z3_ctx, z3_solver);
-
-            Solver {
-                ctx,
-                z3_solver,
-                next_var: 0,
-                def_attrs: DefAttrs::default(),
-                cycles: 0,
-                trace: Trace::new(),
-                decls: HashMap::new(),
-                func_decls: HashMap::new(),
-                enums: Enums::new(ctx),
-            }
-        }
-    }
-
-    pub fn fresh(&mut self) -> Sym {
-        let n = self.next_var;
-        self.next_var += 1;
-        Sym { id: n }
-    }
-
-    fn translate_exp(&self, exp: &Exp<Sym>) -> Ast<'ctx> {
-        use Exp::*;
-        match exp {
-            Var(v) => match self.decls.get(v) {
-                None => panic!("Could not get Z3 func_decl {}", *v),
-                Some(ast) => ast.clone(),
-            },
-            Bits(bv) => Ast::mk_bv(self.ctx, bv.len().try_into().unwrap(), bv),
-            Bits64(bv) => Ast::mk_bv_u64(self.ctx, bv.len(), bv.lower_u64()),
-            Enum(e) => Ast::mk_enum_member(&self.enums, e.enum_id, e.member),
-            Bool(b) => Ast::mk_bool(self.ctx, *b),
-            Not(exp) => Ast::mk_not(&self.translate_exp(exp)),
-            Eq(lhs, rhs) => Ast::mk_eq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Neq(lhs, rhs) => Ast::mk_not(&Ast::mk_eq(&self.translate_exp(lhs), &self.translate_exp(rhs))),
-            And(lhs, rhs) => Ast::mk_and(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Or(lhs, rhs) => Ast::mk_or(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvnot(exp) => Ast::mk_bvnot(&self.translate_exp(exp)),
-            Bvand(lhs, rhs) => Ast::mk_bvand(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvor(lhs, rhs) => Ast::mk_bvor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvxor(lhs, rhs) => Ast::mk_bvxor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvnand(lhs, rhs) => Ast::mk_bvnand(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvnor(lhs, rhs) => Ast::mk_bvnor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvxnor(lhs, rhs) => Ast::mk_bvxnor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvneg(exp) => Ast::mk_bvneg(&self.translate_exp(exp)),
-            Bvadd(lhs, rhs) => Ast::mk_bvadd(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvsub(lhs, rhs) => Ast::mk_bvsub(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvmul(lhs, rhs) => Ast::mk_bvmul(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvudiv(lhs, rhs) => Ast::mk_bvudiv(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvsdiv(lhs, rhs) => Ast::mk_bvsdiv(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvurem(lhs, rhs) => Ast::mk_bvurem(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvsrem(lhs, rhs) => Ast::mk_bvsrem(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvsmod(lhs, rhs) => Ast::mk_bvsmod(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvult(lhs, rhs) => Ast::mk_bvult(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvslt(lhs, rhs) => Ast::mk_bvslt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvule(lhs, rhs) => Ast::mk_bvule(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvsle(lhs, rhs) => Ast::mk_bvsle(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvuge(lhs, rhs) => Ast::mk_bvuge(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvsge(lhs, rhs) => Ast::mk_bvsge(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvugt(lhs, rhs) => Ast::mk_bvugt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvsgt(lhs, rhs) => Ast::mk_bvsgt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Extract(hi, lo, bv) => self.translate_exp(bv).extract(*hi, *lo),
-            ZeroExtend(i, bv) => self.translate_exp(bv).zero_extend(*i),
-            SignExtend(i, bv) => self.translate_exp(bv).sign_extend(*i),
-            Bvshl(lhs, rhs) => Ast::mk_bvshl(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvlshr(lhs, rhs) => Ast::mk_bvlshr(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Bvashr(lhs, rhs) => Ast::mk_bvashr(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Concat(lhs, rhs) => Ast::mk_concat(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-            Ite(cond, t, f) => self.translate_exp(cond).ite(&self.translate_exp(t), &self.translate_exp(f)),
-            App(f, args) => {
-                let args_ast: Vec<_> = args.iter().map(|arg| self.translate_exp(arg)).collect();
-                match self.func_decls.get(f) {
-                    None => panic!("Could not get Z3 func_decl {}", *f),
-                    Some(fd) => Ast::mk_app(fd, &args_ast),
-                }
-            }
-            Select(array, index) => Ast::mk_select(&self.translate_exp(array), &self.translate_exp(index)),
-            Store(array, index, val) => {
-                Ast::mk_store(&self.translate_exp(array), &self.translate_exp(index), &self.translate_exp(val))
-            }
-            Distinct(exps) => {
-                let exps_ast: Vec<_> = exps.iter().map(|exp| self.translate_exp(exp)).collect();
-                Ast::mk_distinct(self.ctx, &exps_ast)
-            }
-            &FPConstant(c, ebits, sbits) => {
-                use smtlib::FPConstant::*;
-                match c {
-                    NaN => Ast::mk_fpa_nan(self.ctx, ebits, sbits),
-                    Inf { negative } => Ast::mk_fpa_inf(self.ctx, ebits, sbits, negative),
-                    Zero { negative } => Ast::mk_fpa_zero(self.ctx, ebits, sbits, negative),
-                }
-            }
-            FPRoundingMode(rm) => {
-                use smtlib::FPRoundingMode::*;
-                match rm {
-                    RoundNearestTiesToEven => Ast::mk_fpa_round_nearest_ties_to_even(self.ctx),
-                    RoundNearestTiesToAway => Ast::mk_fpa_round_nearest_ties_to_away(self.ctx),
-                    RoundTowardPositive => Ast::mk_fpa_round_toward_positive(self.ctx),
-                    RoundTowardNegative => Ast::mk_fpa_round_toward_negative(self.ctx),
-                    RoundTowardZero => Ast::mk_fpa_round_toward_zero(self.ctx),
-                }
-            }
-            FPUnary(op, exp) => {
-                use smtlib::FPUnary::*;
-                match op {
-                    Abs => Ast::mk_fpa_abs(&self.translate_exp(exp)),
-                    Neg => Ast::mk_fpa_neg(&self.translate_exp(exp)),
-                    IsNormal => Ast::mk_fpa_is_normal(&self.translate_exp(exp)),
-                    IsSubnormal => Ast::mk_fpa_is_subnormal(&self.translate_exp(exp)),
-                    IsZero => Ast::mk_fpa_is_zero(&self.translate_exp(exp)),
-                    IsInfinite => Ast::mk_fpa_is_infinite(&self.translate_exp(exp)),
-                    IsNaN => Ast::mk_fpa_is_nan(&self.translate_exp(exp)),
-                    IsNegative => Ast::mk_fpa_is_negative(&self.translate_exp(exp)),
-                    IsPositive => Ast::mk_fpa_is_positive(&self.translate_exp(exp)),
-                    FromIEEE(ebits, sbits) => Ast::mk_fpa_to_fp_bv(&self.translate_exp(exp), *ebits, *sbits),
-                }
-            }
-            FPRoundingUnary(op, rm, exp) => {
-                use smtlib::FPRoundingUnary::*;
-                match op {
-                    Sqrt => Ast::mk_fpa_sqrt(&self.translate_exp(rm), &self.translate_exp(exp)),
-                    RoundToIntegral => Ast::mk_fpa_round_to_integral(&self.translate_exp(rm), &self.translate_exp(exp)),
-                    Convert(ebits, sbits) => {
-                        Ast::mk_fpa_to_fp_float(&self.translate_exp(rm), &self.translate_exp(exp), *ebits, *sbits)
-                    }
-                    FromSigned(ebits, sbits) => {
-                        Ast::mk_fpa_to_fp_signed(&self.translate_exp(rm), &self.translate_exp(exp), *ebits, *sbits)
-                    }
-                    FromUnsigned(ebits, sbits) => {
-                        Ast::mk_fpa_to_fp_unsigned(&self.translate_exp(rm), &self.translate_exp(exp), *ebits, *sbits)
-                    }
-                    ToSigned(sz) => Ast::mk_fpa_to_sbv(&self.translate_exp(rm), &self.translate_exp(exp), *sz),
-                    ToUnsigned(sz) => Ast::mk_fpa_to_ubv(&self.translate_exp(rm), &self.translate_exp(exp), *sz),
-                }
-            }
-            FPBinary(op, lhs, rhs) => {
-                use smtlib::FPBinary::*;
-                match op {
-                    Rem => Ast::mk_fpa_rem(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Min => Ast::mk_fpa_min(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Max => Ast::mk_fpa_max(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Leq => Ast::mk_fpa_leq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Lt => Ast::mk_fpa_lt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Geq => Ast::mk_fpa_geq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Gt => Ast::mk_fpa_gt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Eq => Ast::mk_fpa_eq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
-                }
-            }
-            FPRoundingBinary(op, rm, lhs, rhs) => {
-                use smtlib::FPRoundingBinary::*;
-                match op {
-                    Add => Ast::mk_fpa_add(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Sub => Ast::mk_fpa_sub(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Mul => Ast::mk_fpa_mul(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
-                    Div => Ast::mk_fpa_div(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
-                }
-            }
-            FPfma(rm, x, y, z) => Ast::mk_fpa_fma(
-                &self.translate_exp(rm),
-                &self.translate_exp(x),
-                &self.translate_exp(y),
-                &self.translate_exp(z),
-            ),
-        }
-    }
-
-    fn z3_assert(&mut self, exp: &Exp<Sym>) {
-        let ast = self.translate_exp(exp);
-        unsafe {
-            Z3_solver_assert(self.ctx.z3_ctx, self.z3_solver, ast.z3_ast);
-        }
-    }
-
-    pub fn get_enum(&mut self, id: Name, size: usize) -> EnumId {
-        if !self.enums.enums.contains_key(&id) {
-            self.add(Def::DefineEnum(id, size))
-        };
-        EnumId { id }
-    }
-
-    fn add_internal(&mut self, def: &Def) {
-        match &def {
-            Def::Assert(exp) => self.z3_assert(exp),
-            Def::DeclareConst(v, ty) => {
-                let fd = FuncDecl::new(self.ctx, *v, &self.enums, &[], ty);
-                self.decls.insert(*v, Ast::mk_constant(&fd));
-            }
-            Def::DeclareFun(v, arg_tys, result_ty) => {
-                let fd = FuncDecl::new(self.ctx, *v, &self.enums, arg_tys, result_ty);
-                self.func_decls.insert(*v, fd);
-            }
-            Def::DefineConst(v, exp) => {
-                let ast = self.translate_exp(exp);
-                self.decls.insert(*v, ast);
-            }
-            Def::DefineEnum(name, size) => {
-                if !self.enums.enums.contains_key(name) {
-                    let z3_name = self.fresh();
-                    let members: Vec<Sym> = (0..*size).map(|_| self.fresh()).collect();
-                    self.enums.add_enum(*name, z3_name, &members)
-                }
-            }
-        }
-    }
-
-    pub fn length(&mut self, v: Sym) -> Option<u32> {
-        match self.decls.get(&v) {
-            Some(ast) => unsafe {
-                let z3_ctx = self.ctx.z3_ctx;
-                let z3_sort = Z3_get_sort(z3_ctx, ast.z3_ast);
-                Z3_inc_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
-                if Z3_get_sort_kind(z3_ctx, z3_sort) == SortKind::BV {
-                    let sz = Z3_get_bv_sort_size(z3_ctx, z3_sort);
-                    Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
-                    Some(sz)
-                } else {
-                    Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
-                    None
-                }
-            },
-            None => None,
-        }
-    }
-
-    pub fn is_bitvector(&mut self, v: Sym) -> bool {
-        match self.decls.get(&v) {
-            Some(ast) => unsafe {
-                let z3_ctx = self.ctx.z3_ctx;
-                let z3_sort = Z3_get_sort(z3_ctx, ast.z3_ast);
-                Z3_inc_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
-                let result = Z3_get_sort_kind(z3_ctx, z3_sort) == SortKind::BV;
-                Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
-                result
-            },
-            None => false,
-        }
-    }
-
-    pub fn with_def_attrs<F, A>(&mut self, attrs: DefAttrs, f: F) -> A
-    where
-        F: Fn(&mut Self) -> A,
-    {
-        let old_attrs = self.def_attrs;
-        self.def_attrs = attrs;
-        let x = f(self);
-        self.def_attrs = old_attrs;
-        x
-    }
-
-    pub fn set_def_attrs(&mut self, attrs: DefAttrs) {
-        self.def_attrs = attrs
-    }
-
-    pub fn clear_def_attrs(&mut self) {
-        self.def_attrs = DefAttrs::default()
-    }
-
-    pub fn add(&mut self, def: Def) {
-        self.add_internal(&def);
-        self.trace.head.push(Event::Smt(def, self.def_attrs, SourceLoc::unknown()))
-    }
-
-    pub fn add_with_location(&mut self, def: Def, info: SourceLoc) {
-        self.add_internal(&def);
-        self.trace.head.push(Event::Smt(def, self.def_attrs, info))
-    }
-
-    pub fn declare_const(&mut self, ty: Ty, info: SourceLoc) -> Sym {
-        let sym = self.fresh();
-        self.add_with_location(Def::DeclareConst(sym, ty), info);
-        sym
-    }
-
-    pub fn define_const(&mut self, exp: Exp<Sym>, info: SourceLoc) -> Sym {
-        let sym = self.fresh();
-        self.add_with_location(Def::DefineConst(sym, exp), info);
-        sym
-    }
-
-    pub fn choice(&mut self, exp1: Exp<Sym>, exp2: Exp<Sym>, info: SourceLoc) -> Sym {
-        use Exp::*;
-        let b = self.declare_const(Ty::Bool, info);
-        self.define_const(Ite(Box::new(Var(b)), Box::new(exp1), Box::new(exp2)), info)
-    }
-
-    pub fn assert_eq(&mut self, lhs: Exp<Sym>, rhs: Exp<Sym>) {
-        self.add(Def::Assert(Exp::Eq(Box::new(lhs), Box::new(rhs))))
-    }
-
-    pub fn assert(&mut self, exp: Exp<Sym>) {
-        self.add(Def::Assert(exp))
-    }
-
-    pub fn cycle_count(&mut self) {
-        self.cycles += 1;
-        self.add_event(Event::Cycle)
-    }
-
-    pub fn get_cycle_count(&self) -> i128 {
-        self.cycles
-    }
-
-    fn add_event_internal(&mut self, event: &Event<B>) {
-        if let Event::Smt(def, _, _) = event {
-            self.add_internal(def)
-        };
-    }
-
-    pub fn add_event(&mut self, event: Event<B>) {
-        self.add_event_internal(&event);
-        self.trace.head.push(event)
-    }
-
-    pub fn trace_call(&mut self, name: Name) {
-        self.add_event(Event::Function { name, call: true })
-    }
-
-    pub fn trace_return(&mut self, name: Name) {
-        self.add_event(Event::Function { name, call: false })
-    }
-
-    fn replay(&mut self, num: usize, trace: Arc<Option<Trace<B>>>) {
+    /// Replay the events in the `num` checkpoints of `trace`, except
+    /// for those in the oldest `elaborated` checkpoints, which the
+    /// solver must already contain (see [CheckpointCache]). Returns
+    /// the number of events replayed.
+    fn replay(&mut self, num: usize, trace: Arc<Option<Trace<B>>>, elaborated: usize) -> usize {
         // Some extra work would be required to replay on top of
         // another trace, so until we need to do that we'll check it's
         // empty:
@@ -1819,80 +1232,60 @@ impl<'ctx, B: BV> Solver<'ctx, B> {
             }
         }
         assert!(checkpoints.len() == num);
-        for events in checkpoints.iter().rev() {
+        let mut replayed = 0;
+        for events in checkpoints.iter().rev().skip(elaborated) {
             for event in *events {
                 self.add_event_internal(event)
             }
+            replayed += events.len()
         }
         self.trace.checkpoints = num;
-        self.trace.tail = trace
+        self.trace.tail_len = checkpoints.iter().map(|events| events.len()).sum();
+        self.trace.tail = trace;
+        replayed
     }
 
-    pub fn from_checkpoint(ctx: &'ctx Context, Checkpoint { num, next_var, trace }: Checkpoint<B>) -> Self {
+    pub fn from_checkpoint(
+        ctx: &'ctx Context,
+        Checkpoint { num, next_var, trace, memory_accesses }: Checkpoint<B>,
+    ) -> Self {
+        let now = Instant::now();
         let mut solver = Solver::new(ctx);
-        solver.replay(num, trace);
+        let replayed = solver.replay(num, trace, 0);
         solver.next_var = next_var;
+        solver.trace.tail_memory_accesses = memory_accesses;
+        record_restore(replayed, now);
         solver
     }
 
-    pub fn check_sat_with(&mut self, exp: &Exp<Sym>) -> SmtResult {
-        let ast = self.translate_exp(exp);
-        unsafe {
-            let result = Z3_solver_check_assumptions(self.ctx.z3_ctx, self.z3_solver, 1, &ast.z3_ast);
-            if result == Z3_L_TRUE {
-                Sat
-            } else if result == Z3_L_FALSE {
-                Unsat
-            } else {
-                Unknown
-            }
-        }
-    }
-
     pub fn trace(&self) -> &Trace<B> {
         &self.trace
     }
 
-    pub fn check_sat(&mut self) -> SmtResult {
-        unsafe {
-            let result = Z3_solver_check(self.ctx.z3_ctx, self.z3_solver);
-            if result == Z3_L_TRUE {
-                Sat
-            } else if result == Z3_L_FALSE {
-                Unsat
-            } else {
-                Unknown
-            }
-        }
-    }
-
-    pub fn dump_solver(&mut self, filename: &str) {
-        let mut file = std::fs::File::create(filename).expect("Failed to open solver dump file");
-        unsafe {
-            let s = Z3_solver_to_string(self.ctx.z3_ctx, self.z3_solver);
-            let cs = CStr::from_ptr(s);
-            file.write_all(cs.to_bytes()).expect("Failed to write solver dump");
-        }
-    }
-
-    pub fn dump_solver_with(&mut self, filename: &str, exp: &Exp<Sym>) {
-        let mut file = std::fs::File::create(filename).expect("Failed to open solver dump file");
-        unsafe {
-            let s = Z3_solver_to_string(self.ctx.z3_ctx, self.z3_solver);
-            let cs = CStr::from_ptr(s);
-            file.write_all(cs.to_bytes()).expect("Failed to write solver dump");
-            writeln!(file, "{}", self.exp_to_str(exp)).expect("Failed to write exp");
-        }
+    /// Check whether each of two expressions is satisfiable together
+    /// with the current assertions, as when deciding which sides of a
+    /// symbolic branch are feasible. If `concurrent` is set, the two
+    /// checks are run at the same time on separate threads (see
+    /// [Solver::check_sat_pair]), otherwise one after the other.
+    pub fn check_fork(&mut self, left: &Exp<Sym>, right: &Exp<Sym>, concurrent: bool) -> (SmtResult, SmtResult) {
+        let now = Instant::now();
+        let results = if concurrent {
+            CONCURRENT_FORK_CHECKS.fetch_add(1, Ordering::Relaxed);
+            self.check_sat_pair(left, right)
+        } else {
+            (self.check_sat_with(left), self.check_sat_with(right))
+        };
+        FORK_CHECKS.fetch_add(1, Ordering::Relaxed);
+        FORK_CHECK_MICROS.fetch_add(now.elapsed().as_micros() as u64, Ordering::Relaxed);
+        results
     }
 
-    pub fn exp_to_str(&mut self, exp: &Exp<Sym>) -> String {
-        let ast = self.translate_exp(exp);
-        let cs;
-        unsafe {
-            let s = Z3_ast_to_string(ast.ctx.z3_ctx, ast.z3_ast);
-            cs = CStr::from_ptr(s);
-        }
-        cs.to_string_lossy().to_string()
+    /// See [Trace::simplify_over].
+    pub fn simplify_trace_over<F>(&mut self, budget: usize, simplify: F) -> bool
+    where
+        F: FnOnce(&mut Vec<Event<B>>),
+    {
+        self.trace.simplify_over(budget, simplify)
     }
 }
 
@@ -1900,17 +1293,6 @@ pub fn checkpoint<B: BV>(solver: &mut Solver<B>) -> Checkpoint<B> {
     solver.trace.checkpoint(solver.next_var)
 }
 
-/// This function just calls Z3_finalize_memory(). It's useful because
-/// by calling it before we exit, we can check whether we are leaking
-/// memory while interacting with Z3 objects.
-///
-/// # Safety
-///
-/// Shoud only be called just before exiting.
-pub unsafe fn finalize_solver() {
-    Z3_finalize_memory()
-}
-
 #[cfg(test)]
 mod tests {
     use crate::bitvector::b64::B64;
@@ -1948,6 +1330,106 @@ mod tests {
         assert!(solver.check_sat() == Unsat);
     }
 
+    #[test]
+    fn replay_events() {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let v = solver.declare_const(Ty::BitVec(4), SourceLoc::unknown());
+        solver.add(Assert(Bvult(Box::new(Var(v)), Box::new(smtlib::bits64(2, 4)))));
+        let read = |value| Event::ReadMem {
+            value,
+            read_kind: Val::Unit,
+            address: Val::Bits(B64::from_u64(0x1000)),
+            bytes: 1,
+            tag_value: None,
+            opts: ReadOpts::default(),
+            region: "replayed",
+            info: SourceLoc::unknown(),
+        };
+        solver.add_event(read(Val::Symbolic(v)));
+
+        let events: Vec<Event<B64>> = solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect();
+        let events: Vec<Event<B64>> = bincode::deserialize(&bincode::serialize(&events).unwrap()).unwrap();
+        assert!(events[2].in_region("replayed"));
+
+        let loaded = Checkpoint::from_events(events);
+        assert_eq!(loaded.memory_accesses(), 1);
+        let mut replayed = Solver::from_checkpoint(&ctx, loaded);
+        let w = replayed.declare_const(Ty::BitVec(4), SourceLoc::unknown());
+        assert!(w != v);
+        assert!(replayed.check_sat_with(&Eq(Box::new(Var(v)), Box::new(smtlib::bits64(1, 4)))) == Sat);
+        assert!(replayed.check_sat_with(&Eq(Box::new(Var(v)), Box::new(smtlib::bits64(3, 4)))) == Unsat);
+        assert_eq!(replayed.trace().to_vec().len(), 4);
+
+        // Memory accesses are counted across checkpoints and replays
+        replayed.add_event(read(Val::Symbolic(w)));
+        let first = checkpoint(&mut replayed);
+        assert_eq!(first.memory_accesses(), 2);
+        let mut resumed = Solver::from_checkpoint(&ctx, first);
+        resumed.add_event(read(Val::Symbolic(w)));
+        assert_eq!(checkpoint(&mut resumed).memory_accesses(), 3)
+    }
+
+    #[test]
+    fn simplify_trace_over_budget() {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        solver.add_event(Event::Cycle);
+        let checkpoint = checkpoint(&mut solver);
+        solver.add_event(Event::Cycle);
+        solver.add_event(Event::Instr(Val::Unit));
+        assert_eq!(solver.trace().len(), 3);
+
+        let size = solver.trace().size_estimate();
+        assert!(!solver.simplify_trace_over(size, |_| panic!("under budget")));
+        assert!(solver.simplify_trace_over(0, |events| events.retain(|ev| !ev.is_instr())));
+        assert_eq!(solver.trace().to_vec().len(), 2);
+        // The trace must double in length before it is simplified again
+        solver.add_event(Event::Cycle);
+        assert!(!solver.simplify_trace_over(0, |_| panic!("simplified twice")));
+        solver.add_event(Event::Cycle);
+        assert!(solver.simplify_trace_over(0, |_| ()));
+
+        let replayed = Solver::from_checkpoint(&ctx, checkpoint);
+        assert_eq!(replayed.trace().len(), 1)
+    }
+
+    #[test]
+    fn concurrent_fork_checks() {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let v = solver.declare_const(Ty::BitVec(4), SourceLoc::unknown());
+        solver.add(Assert(Bvult(Box::new(Var(v)), Box::new(smtlib::bits64(2, 4)))));
+        let is_one = Eq(Box::new(Var(v)), Box::new(smtlib::bits64(1, 4)));
+        let is_three = Eq(Box::new(Var(v)), Box::new(smtlib::bits64(3, 4)));
+        for concurrent in [false, true] {
+            assert_eq!(solver.check_fork(&is_one, &is_three, concurrent), (Sat, Unsat));
+            assert_eq!(solver.check_fork(&is_three, &is_one, concurrent), (Unsat, Sat))
+        }
+        // The solver is unchanged by the checks
+        assert_eq!(solver.check_sat_with(&is_one), Sat);
+
+        // Later solvers made in the same context share its pair worker
+        drop(solver);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let v = solver.declare_const(Ty::BitVec(4), SourceLoc::unknown());
+        let is_one = Eq(Box::new(Var(v)), Box::new(smtlib::bits64(1, 4)));
+        solver.add(Assert(is_one.clone()));
+        assert_eq!(solver.check_fork(&is_one, &Not(Box::new(is_one.clone())), true), (Sat, Unsat));
+
+        // The pair worker's context has the same parameters
+        let mut cfg = Config::new();
+        cfg.set_param_value("model", "true");
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let v = solver.declare_const(Ty::BitVec(4), SourceLoc::unknown());
+        let is_one = Eq(Box::new(Var(v)), Box::new(smtlib::bits64(1, 4)));
+        assert_eq!(solver.check_fork(&is_one, &Not(Box::new(is_one.clone())), true), (Sat, Sat))
+    }
+
     #[test]
     fn get_const() {
         let mut cfg = Config::new();
@@ -1983,6 +1465,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn checkpoint_cache() {
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let e = solver.get_enum(Name::from_u32(0), 3);
+        let x = solver.declare_const(Ty::BitVec(4), SourceLoc::unknown());
+        let y = solver.declare_const(Ty::Enum(e), SourceLoc::unknown());
+        solver.add(Assert(Bvult(Box::new(Var(x)), Box::new(smtlib::bits64(8, 4)))));
+        let prefix = checkpoint(&mut solver);
+        let z = solver.define_const(Bvadd(Box::new(Var(x)), Box::new(smtlib::bits64(1, 4))), SourceLoc::unknown());
+        solver.add(Assert(Neq(Box::new(Var(z)), Box::new(smtlib::bits64(3, 4)))));
+        let point = checkpoint(&mut solver);
+        let len = solver.trace().len();
+
+        let is = |v: Sym, n: u64| Eq(Box::new(Var(v)), Box::new(smtlib::bits64(n, 4)));
+        let cache = CheckpointCache::new(2).with_min_events(0);
+        let mut restored = cache.restore(&ctx, prefix);
+        assert_eq!(restored.check_sat_with(&is(x, 9)), Unsat);
+        assert_eq!(restored.check_sat_with(&is(x, 2)), Sat);
+
+        // The second checkpoint extends the first, so only the events
+        // after it are replayed, on another thread
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let ctx = Context::new(Config::new());
+                let mut restored = cache.restore(&ctx, point.clone());
+                assert_eq!(restored.check_sat_with(&is(x, 2)), Unsat);
+                assert_eq!(restored.check_sat_with(&is(z, 2)), Sat);
+                assert_eq!(restored.trace().len(), len);
+                let e = restored.get_enum(Name::from_u32(0), 3);
+                let w = restored.declare_const(Ty::Enum(e), SourceLoc::unknown());
+                assert!(w.id > z.id);
+                assert_eq!(restored.check_sat_with(&Eq(Box::new(Var(w)), Box::new(Var(y)))), Sat)
+            });
+        });
+        let mut restored = cache.restore(&ctx, point.clone());
+        assert_eq!(restored.check_sat_with(&is(x, 2)), Unsat);
+        // Only the events after the prefix were replayed, so the
+        // checkpoint was not worth caching
+        assert_eq!(cache.counts(), CheckpointCacheCounts { entries: 1, hits: 0, prefix_hits: 2, misses: 1 });
+
+        let cache = CheckpointCache::new(1).with_min_events(0);
+        cache.restore(&ctx, point.clone());
+        cache.restore(&ctx, point.clone());
+        assert_eq!(cache.counts(), CheckpointCacheCounts { entries: 1, hits: 1, prefix_hits: 0, misses: 1 });
+
+        // Short traces are not cached by default
+        let cache = CheckpointCache::new(1);
+        cache.restore(&ctx, point);
+        assert_eq!(cache.counts().entries, 0)
+    }
+
     #[test]
     fn get_enum_const() {
         let mut cfg = Config::new();
@@ -2008,6 +1543,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unsat_core() {
+        use super::smtlib::bits64;
+        let cfg = Config::new();
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        solver.add(DeclareConst(Sym::from_u32(0), Ty::BitVec(4)));
+        solver.add(DeclareConst(Sym::from_u32(1), Ty::BitVec(4)));
+        solver.add(Assert(Bvult(Box::new(var(0)), Box::new(bits64(4, 4)))));
+        let assumptions = [
+            Eq(Box::new(var(1)), Box::new(bits64(1, 4))),
+            Eq(Box::new(var(0)), Box::new(bits64(8, 4))),
+            Eq(Box::new(var(1)), Box::new(bits64(2, 4))),
+        ];
+        assert_eq!(solver.unsat_core(&assumptions[..1]), None);
+        let core = solver.unsat_core(&assumptions[1..]).unwrap();
+        assert_eq!(core, vec![0]);
+        let core = solver.unsat_core(&[assumptions[0].clone(), assumptions[2].clone()]).unwrap();
+        assert_eq!(core, vec![0, 1]);
+        assert!(solver.check_sat() == Sat)
+    }
+
+    #[test]
+    fn get_int_const() {
+        let mut cfg = Config::new();
+        cfg.set_param_value("model", "true");
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let big = num_bigint::BigInt::from(1) << 130u32;
+        solver.add(DeclareConst(Sym::from_u32(0), Ty::Int));
+        solver.add(DeclareConst(Sym::from_u32(1), Ty::BitVec(8)));
+        solver.add(Assert(IntBinary(smtlib::IntBinary::Gt, Box::new(var(0)), Box::new(Int(big.clone())))));
+        solver.add(Assert(IntBinary(smtlib::IntBinary::Lt, Box::new(var(0)), Box::new(Int(big.clone() + 2)))));
+        solver.add(Assert(Eq(Box::new(var(1)), Box::new(IntUnary(smtlib::IntUnary::ToBits(8), Box::new(var(0)))))));
+        assert!(solver.check_sat() == Sat);
+        let mut model = Model::new(&solver);
+        assert_eq!(model.get_var(Sym::from_u32(0)).unwrap(), Some(Int(big + 1)));
+        assert_eq!(model.get_var(Sym::from_u32(1)).unwrap(), Some(Bits64(B64::new(1, 8))));
+    }
+
     #[test]
     fn smt_func() {
         let mut cfg = Config::new();
@@ -2046,4 +1621,26 @@ mod tests {
         )));
         assert!(solver.check_sat() == Unsat);
     }
+
+    #[test]
+    fn batched() {
+        let mut cfg = Config::new();
+        cfg.set_param_value("model", "true");
+        let ctx = Context::new(cfg);
+        let mut solver = Solver::<B64>::new(&ctx);
+        let vs = solver.declare_consts(vec![Ty::BitVec(4), Ty::BitVec(4), Ty::Bool], SourceLoc::unknown());
+        solver.assert_all(vec![
+            Eq(Box::new(Var(vs[0])), Box::new(bv!("0110"))),
+            Eq(Box::new(Var(vs[1])), Box::new(Var(vs[0]))),
+            Var(vs[2]),
+        ]);
+        assert!(solver.check_sat() == Sat);
+        {
+            let mut model = Model::new(&solver);
+            assert!(matches!(model.get_var(vs[1]).unwrap().unwrap(), Bits64(bv) if bv == B64::new(0b0110, 4)));
+        }
+        assert_eq!(solver.trace().head.len(), 6);
+        solver.assert_all(vec![Not(Box::new(Var(vs[2])))]);
+        assert!(solver.check_sat() == Unsat);
+    }
 }
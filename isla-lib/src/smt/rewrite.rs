@@ -0,0 +1,330 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! This module implements a set of rewrite rules which simplify SMT
+//! expressions before they are sent to the solver. The generated
+//! SMT often contains patterns like concatenations of adjacent
+//! extracts, extends of zero, or masks of masks, which are cheap to
+//! remove here but which the solver would otherwise have to deal
+//! with.
+//!
+//! Each rule takes an expression and either returns the rewritten
+//! expression, or gives back the original unchanged as an error, so
+//! rules can be tested in isolation.
+
+use std::mem;
+
+use super::smtlib::{Def, Exp};
+use crate::bitvector::b64::B64;
+use crate::bitvector::BV;
+
+pub type Rule<V> = fn(Exp<V>) -> Result<Exp<V>, Exp<V>>;
+
+/// The width of a bitvector expression, where it can be determined
+/// without a typing context
+fn width<V>(exp: &Exp<V>) -> Option<u32> {
+    use Exp::*;
+    match exp {
+        Bits(bv) => Some(bv.len() as u32),
+        Bits64(bv) => Some(bv.len()),
+        Extract(hi, lo, _) => Some(hi - lo + 1),
+        ZeroExtend(len, exp) | SignExtend(len, exp) => Some(width(exp)? + len),
+        Concat(lhs, rhs) => Some(width(lhs)? + width(rhs)?),
+        Bvnot(exp) | Bvneg(exp) => width(exp),
+        Bvand(lhs, rhs) | Bvor(lhs, rhs) | Bvxor(lhs, rhs) | Bvadd(lhs, rhs) | Bvsub(lhs, rhs) => {
+            width(lhs).or_else(|| width(rhs))
+        }
+        _ => None,
+    }
+}
+
+fn zeros<V>(len: u32) -> Exp<V> {
+    if len <= 64 {
+        Exp::Bits64(B64::zeros(len))
+    } else {
+        Exp::Bits(vec![false; len as usize])
+    }
+}
+
+fn is_zeros<V>(exp: &Exp<V>) -> bool {
+    match exp {
+        Exp::Bits64(bv) => bv.is_zero(),
+        Exp::Bits(bv) => bv.iter().all(|b| !b),
+        _ => false,
+    }
+}
+
+fn is_ones<V>(exp: &Exp<V>) -> bool {
+    match exp {
+        Exp::Bits64(bv) => *bv == B64::ones(bv.len()),
+        Exp::Bits(bv) => bv.iter().all(|b| *b),
+        _ => false,
+    }
+}
+
+/// `(extract hi lo (extract _ lo2 x))` becomes `(extract (+ hi lo2) (+ lo lo2) x)`
+pub fn extract_extract<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::Extract(hi, lo, inner) => match *inner {
+            Exp::Extract(_, lo2, x) => Ok(Exp::Extract(hi + lo2, lo + lo2, x)),
+            inner => Err(Exp::Extract(hi, lo, Box::new(inner))),
+        },
+        _ => Err(exp),
+    }
+}
+
+/// An extract of every bit of `x` is just `x`
+pub fn extract_full<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::Extract(hi, 0, x) if width(&x) == Some(hi + 1) => Ok(*x),
+        _ => Err(exp),
+    }
+}
+
+/// An extract from a zero extension either lies entirely within the
+/// original bits, or entirely within the added zeros
+pub fn extract_zero_extend<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::Extract(hi, lo, inner) => match *inner {
+            Exp::ZeroExtend(len, x) => match width(&x) {
+                Some(w) if hi < w => Ok(Exp::Extract(hi, lo, x)),
+                Some(w) if lo >= w => Ok(zeros(hi - lo + 1)),
+                _ => Err(Exp::Extract(hi, lo, Box::new(Exp::ZeroExtend(len, x)))),
+            },
+            inner => Err(Exp::Extract(hi, lo, Box::new(inner))),
+        },
+        _ => Err(exp),
+    }
+}
+
+/// `(concat (extract hi n x) (extract (- n 1) lo x))` becomes `(extract hi lo x)`
+pub fn concat_extracts<V: PartialEq>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::Concat(lhs, rhs) => match (*lhs, *rhs) {
+            (Exp::Extract(hi, lo1, x), Exp::Extract(hi2, lo, y))
+                if lo1 == hi2 + 1 && matches!((&*x, &*y), (Exp::Var(v1), Exp::Var(v2)) if v1 == v2) =>
+            {
+                Ok(Exp::Extract(hi, lo, x))
+            }
+            (lhs, rhs) => Err(Exp::Concat(Box::new(lhs), Box::new(rhs))),
+        },
+        _ => Err(exp),
+    }
+}
+
+/// Concatenating zeros onto the front of `x` is a zero extension
+pub fn concat_zeros<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::Concat(lhs, rhs) if is_zeros(&lhs) => match width(&lhs) {
+            Some(len) => Ok(Exp::ZeroExtend(len, rhs)),
+            None => Err(Exp::Concat(lhs, rhs)),
+        },
+        _ => Err(exp),
+    }
+}
+
+/// Zero extending by nothing does nothing, and zero extending zero
+/// gives a wider zero
+pub fn zero_extend_zeros<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::ZeroExtend(0, x) => Ok(*x),
+        Exp::ZeroExtend(len, x) if is_zeros(&x) => match width(&x) {
+            Some(w) => Ok(zeros(w + len)),
+            None => Err(Exp::ZeroExtend(len, x)),
+        },
+        _ => Err(exp),
+    }
+}
+
+/// `(bvand (bvand x c1) c2)` becomes `(bvand x c)` where `c` is the
+/// conjunction of the constant masks `c1` and `c2`
+pub fn mask_mask<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    let Exp::Bvand(lhs, rhs) = exp else { return Err(exp) };
+    let (inner, c2) = match (*lhs, *rhs) {
+        (inner @ Exp::Bvand(..), Exp::Bits64(c2)) | (Exp::Bits64(c2), inner @ Exp::Bvand(..)) => (inner, c2),
+        (lhs, rhs) => return Err(Exp::Bvand(Box::new(lhs), Box::new(rhs))),
+    };
+    let Exp::Bvand(x, c1) = inner else { unreachable!() };
+    match (*x, *c1) {
+        (x, Exp::Bits64(c1)) | (Exp::Bits64(c1), x) => Ok(Exp::Bvand(Box::new(x), Box::new(Exp::Bits64(c1 & c2)))),
+        (x, c1) => Err(Exp::Bvand(Box::new(Exp::Bvand(Box::new(x), Box::new(c1))), Box::new(Exp::Bits64(c2)))),
+    }
+}
+
+/// Masks and bitwise ors with all zeros or all ones are either
+/// constant or leave their other argument unchanged
+pub fn bitwise_identity<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::Bvand(lhs, rhs) => match (is_zeros(&lhs) || is_ones(&rhs), is_zeros(&rhs) || is_ones(&lhs)) {
+            (true, _) => Ok(*lhs),
+            (_, true) => Ok(*rhs),
+            _ => Err(Exp::Bvand(lhs, rhs)),
+        },
+        Exp::Bvor(lhs, rhs) => match (is_ones(&lhs) || is_zeros(&rhs), is_ones(&rhs) || is_zeros(&lhs)) {
+            (true, _) => Ok(*lhs),
+            (_, true) => Ok(*rhs),
+            _ => Err(Exp::Bvor(lhs, rhs)),
+        },
+        _ => Err(exp),
+    }
+}
+
+/// Boolean and bitwise negations cancel out
+pub fn double_negation<V>(exp: Exp<V>) -> Result<Exp<V>, Exp<V>> {
+    match exp {
+        Exp::Not(inner) => match *inner {
+            Exp::Not(x) => Ok(*x),
+            inner => Err(Exp::Not(Box::new(inner))),
+        },
+        Exp::Bvnot(inner) => match *inner {
+            Exp::Bvnot(x) => Ok(*x),
+            inner => Err(Exp::Bvnot(Box::new(inner))),
+        },
+        _ => Err(exp),
+    }
+}
+
+/// All the rewrite rules, in the order they are tried
+pub fn rules<V: PartialEq>() -> [Rule<V>; 9] {
+    [
+        extract_extract,
+        extract_full,
+        extract_zero_extend,
+        concat_extracts,
+        concat_zeros,
+        zero_extend_zeros,
+        mask_mask,
+        bitwise_identity,
+        double_negation,
+    ]
+}
+
+/// Apply the rewrite rules to a single expression node until none of
+/// them match
+fn rewrite_node<V: PartialEq>(exp: &mut Exp<V>) {
+    let rules = rules();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for rule in &rules {
+            match rule(mem::replace(exp, Exp::Bool(false))) {
+                Ok(rewritten) => {
+                    *exp = rewritten;
+                    changed = true
+                }
+                Err(unchanged) => *exp = unchanged,
+            }
+        }
+    }
+}
+
+/// Simplify an expression by applying the rewrite rules bottom-up to
+/// each of its subexpressions
+pub fn rewrite<V: PartialEq>(exp: &mut Exp<V>) {
+    exp.modify(&mut rewrite_node)
+}
+
+/// Simplify the expression in a definition or assertion
+pub fn rewrite_def(def: &mut Def) {
+    match def {
+        Def::DefineConst(_, exp) | Def::Assert(exp) => rewrite(exp),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Exp::*;
+
+    fn bv(bits: u64, len: u32) -> Exp<u32> {
+        Bits64(B64::new(bits, len))
+    }
+
+    fn var(v: u32) -> Box<Exp<u32>> {
+        Box::new(Var(v))
+    }
+
+    fn is<V: std::fmt::Debug>(result: Result<Exp<V>, Exp<V>>, expected: &str) -> bool {
+        matches!(result, Ok(exp) if format!("{:?}", exp) == expected)
+    }
+
+    #[test]
+    fn extract_rules() {
+        assert!(is(extract_extract(Extract(3, 1, Box::new(Extract(15, 8, var(0))))), "Extract(11, 9, Var(0))"));
+        assert!(is(extract_full(Extract(7, 0, Box::new(Extract(15, 8, var(0))))), "Extract(15, 8, Var(0))"));
+        assert!(extract_full(Extract(7, 0, var(0))).is_err());
+
+        let extended = || Box::new(ZeroExtend(32, Box::new(Extract(31, 0, var(0)))));
+        assert!(is(extract_zero_extend(Extract(15, 0, extended())), "Extract(15, 0, Extract(31, 0, Var(0)))"));
+        assert!(is(extract_zero_extend(Extract(63, 32, extended())), &format!("{:?}", bv(0, 32))));
+        assert!(extract_zero_extend(Extract(47, 16, extended())).is_err())
+    }
+
+    #[test]
+    fn concat_rules() {
+        let concat = |hi, lo, x, y| Concat(Box::new(Extract(hi, lo, var(x))), Box::new(Extract(lo - 1, 0, var(y))));
+        assert!(is(concat_extracts(concat(31, 8, 0, 0)), "Extract(31, 0, Var(0))"));
+        assert!(concat_extracts(concat(31, 8, 0, 1)).is_err());
+
+        assert!(is(concat_zeros(Concat(Box::new(bv(0, 8)), var(0))), "ZeroExtend(8, Var(0))"));
+        assert!(concat_zeros(Concat(Box::new(bv(1, 8)), var(0))).is_err());
+        assert!(is(zero_extend_zeros(ZeroExtend(0, var(0))), "Var(0)"));
+        assert!(is(
+            zero_extend_zeros(ZeroExtend(64, Box::new(bv(0, 64)))),
+            &format!("{:?}", Bits::<u32>(vec![false; 128]))
+        ))
+    }
+
+    #[test]
+    fn bitwise_rules() {
+        let masked = Bvand(Box::new(Bvand(var(0), Box::new(bv(0xff00, 16)))), Box::new(bv(0x0ff0, 16)));
+        assert!(is(mask_mask(masked), &format!("{:?}", Bvand(var(0), Box::new(bv(0x0f00, 16))))));
+        assert!(mask_mask(Bvand(Box::new(Bvand(var(0), var(1))), Box::new(bv(1, 16)))).is_err());
+
+        assert!(is(bitwise_identity(Bvand(var(0), Box::new(bv(0xffff, 16)))), "Var(0)"));
+        assert!(is(bitwise_identity(Bvand(Box::new(bv(0, 16)), var(0))), &format!("{:?}", bv(0, 16))));
+        assert!(is(bitwise_identity(Bvor(Box::new(bv(0, 16)), var(0))), "Var(0)"));
+        assert!(bitwise_identity(Bvor(var(1), var(0))).is_err());
+
+        assert!(is(double_negation(Not(Box::new(Not(var(0))))), "Var(0)"));
+        assert!(double_negation(Bvnot(var(0))).is_err())
+    }
+
+    #[test]
+    fn rewrite_nested() {
+        // (bvand (concat #x00 (extract 7 0 (extract 15 0 v0))) #xffff) => (zero_extend 8 (extract 7 0 v0))
+        let mut exp = Bvand(
+            Box::new(Concat(Box::new(bv(0, 8)), Box::new(Extract(7, 0, Box::new(Extract(15, 0, var(0))))))),
+            Box::new(bv(0xffff, 16)),
+        );
+        rewrite(&mut exp);
+        assert_eq!(format!("{:?}", exp), "ZeroExtend(8, Extract(7, 0, Var(0)))")
+    }
+}
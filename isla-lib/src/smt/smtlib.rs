@@ -35,12 +35,15 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::{Add, BitAnd, BitOr, BitXor, Shl, Shr, Sub};
 
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
 use super::{EnumId, EnumMember, Sym};
 use crate::bitvector::b64::B64;
 use crate::bitvector::{ParsedBits, BV};
 use crate::ir::Name;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Ty {
     Bool,
     BitVec(u32),
@@ -48,9 +51,10 @@ pub enum Ty {
     Array(Box<Ty>, Box<Ty>),
     Float(u32, u32),
     RoundingMode,
+    Int,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FPRoundingMode {
     RoundNearestTiesToEven,
     RoundNearestTiesToAway,
@@ -59,7 +63,7 @@ pub enum FPRoundingMode {
     RoundTowardZero,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FPConstant {
     NaN,
     /// If negative is true, then -∞ rather than +∞, and similarly for the Zero constructor
@@ -71,7 +75,7 @@ pub enum FPConstant {
     },
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FPUnary {
     Abs,
     Neg,
@@ -90,14 +94,14 @@ impl FPUnary {
     fn result_ty(self) -> Option<Ty> {
         use FPUnary::*;
         match self {
-            FromIEEE(sbits, ebits) => Some(Ty::Float(ebits, sbits)),
+            FromIEEE(ebits, sbits) => Some(Ty::Float(ebits, sbits)),
             IsNormal | IsSubnormal | IsZero | IsInfinite | IsNaN | IsNegative | IsPositive => Some(Ty::Bool),
             Abs | Neg => None,
         }
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FPRoundingUnary {
     Sqrt,
     RoundToIntegral,
@@ -124,7 +128,7 @@ impl FPRoundingUnary {
 /// Note that SMTLIB is slightly inconsistent w.r.t. whether it uses
 /// le or leq as a suffix for less than or equal to between bitvectors
 /// and floating point. We follow SMTLIB exactly here.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FPBinary {
     Rem,
     Min,
@@ -144,7 +148,7 @@ impl FPBinary {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FPRoundingBinary {
     Add,
     Sub,
@@ -152,7 +156,48 @@ pub enum FPRoundingBinary {
     Div,
 }
 
-#[derive(Clone, Debug)]
+/// Operations on mathematical integers, used when a Sail integer
+/// does not fit in the 128-bit bitvectors which otherwise represent
+/// them.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum IntUnary {
+    Neg,
+    /// The integer value of a two's complement bitvector with the
+    /// given width
+    FromSigned(u32),
+    /// The integer modulo 2^n, as a bitvector of width n
+    ToBits(u32),
+}
+
+impl IntUnary {
+    fn result_ty(self) -> Ty {
+        use IntUnary::*;
+        match self {
+            Neg | FromSigned(_) => Ty::Int,
+            ToBits(sz) => Ty::BitVec(sz),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum IntBinary {
+    Add,
+    Sub,
+    Mul,
+    Le,
+    Lt,
+    Ge,
+    Gt,
+}
+
+impl IntBinary {
+    fn is_predicate(self) -> bool {
+        use IntBinary::*;
+        matches!(self, Le | Lt | Ge | Gt)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Exp<V> {
     Var(V),
     Bits(Vec<bool>),
@@ -207,6 +252,9 @@ pub enum Exp<V> {
     FPBinary(FPBinary, Box<Exp<V>>, Box<Exp<V>>),
     FPRoundingBinary(FPRoundingBinary, Box<Exp<V>>, Box<Exp<V>>, Box<Exp<V>>),
     FPfma(Box<Exp<V>>, Box<Exp<V>>, Box<Exp<V>>, Box<Exp<V>>),
+    Int(BigInt),
+    IntUnary(IntUnary, Box<Exp<V>>),
+    IntBinary(IntBinary, Box<Exp<V>>, Box<Exp<V>>),
 }
 
 #[allow(clippy::needless_range_loop)]
@@ -424,14 +472,15 @@ impl<V> Exp<V> {
     {
         use Exp::*;
         match self {
-            Var(_) | Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) => (),
+            Var(_) | Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) | Int(_) => (),
             Not(exp)
             | Bvnot(exp)
             | Bvneg(exp)
             | Extract(_, _, exp)
             | ZeroExtend(_, exp)
             | SignExtend(_, exp)
-            | FPUnary(_, exp) => exp.modify(f),
+            | FPUnary(_, exp)
+            | IntUnary(_, exp) => exp.modify(f),
             Eq(lhs, rhs)
             | Neq(lhs, rhs)
             | And(lhs, rhs)
@@ -462,7 +511,8 @@ impl<V> Exp<V> {
             | Bvlshr(lhs, rhs)
             | Bvashr(lhs, rhs)
             | Concat(lhs, rhs)
-            | FPBinary(_, lhs, rhs) => {
+            | FPBinary(_, lhs, rhs)
+            | IntBinary(_, lhs, rhs) => {
                 lhs.modify(f);
                 rhs.modify(f);
             }
@@ -517,14 +567,15 @@ impl<V> Exp<V> {
         use Exp::*;
         f(self);
         match self {
-            Var(_) | Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) => (),
+            Var(_) | Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) | Int(_) => (),
             Not(exp)
             | Bvnot(exp)
             | Bvneg(exp)
             | Extract(_, _, exp)
             | ZeroExtend(_, exp)
             | SignExtend(_, exp)
-            | FPUnary(_, exp) => exp.modify(f),
+            | FPUnary(_, exp)
+            | IntUnary(_, exp) => exp.modify(f),
             Eq(lhs, rhs)
             | Neq(lhs, rhs)
             | And(lhs, rhs)
@@ -555,7 +606,8 @@ impl<V> Exp<V> {
             | Bvlshr(lhs, rhs)
             | Bvashr(lhs, rhs)
             | Concat(lhs, rhs)
-            | FPBinary(_, lhs, rhs) => {
+            | FPBinary(_, lhs, rhs)
+            | IntBinary(_, lhs, rhs) => {
                 lhs.modify(f);
                 rhs.modify(f);
             }
@@ -706,6 +758,9 @@ impl<'a, V: 'a> Exp<V> {
                 Box::new(y.map_var(f)?),
                 Box::new(z.map_var(f)?),
             )),
+            Int(n) => Ok(Int(n.clone())),
+            IntUnary(op, exp) => Ok(IntUnary(*op, Box::new(exp.map_var(f)?))),
+            IntBinary(op, lhs, rhs) => Ok(IntBinary(*op, Box::new(lhs.map_var(f)?), Box::new(rhs.map_var(f)?))),
         }
     }
 }
@@ -727,14 +782,15 @@ impl Exp<Sym> {
             Var(v) => {
                 vars.insert(*v);
             }
-            Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) => (),
+            Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) | Int(_) => (),
             Not(exp)
             | Bvnot(exp)
             | Bvneg(exp)
             | Extract(_, _, exp)
             | ZeroExtend(_, exp)
             | SignExtend(_, exp)
-            | FPUnary(_, exp) => exp.collect_variables(vars),
+            | FPUnary(_, exp)
+            | IntUnary(_, exp) => exp.collect_variables(vars),
             Eq(lhs, rhs)
             | Neq(lhs, rhs)
             | And(lhs, rhs)
@@ -765,7 +821,8 @@ impl Exp<Sym> {
             | Bvlshr(lhs, rhs)
             | Bvashr(lhs, rhs)
             | Concat(lhs, rhs)
-            | FPBinary(_, lhs, rhs) => {
+            | FPBinary(_, lhs, rhs)
+            | IntBinary(_, lhs, rhs) => {
                 lhs.collect_variables(vars);
                 rhs.collect_variables(vars);
             }
@@ -829,14 +886,15 @@ impl Exp<Sym> {
                     }
                 }
             }
-            Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) => (),
+            Bits(_) | Bits64(_) | Enum(_) | Bool(_) | FPConstant(..) | FPRoundingMode(_) | Int(_) => (),
             Not(exp)
             | Bvnot(exp)
             | Bvneg(exp)
             | Extract(_, _, exp)
             | ZeroExtend(_, exp)
             | SignExtend(_, exp)
-            | FPUnary(_, exp) => exp.subst_once_in_place(substs),
+            | FPUnary(_, exp)
+            | IntUnary(_, exp) => exp.subst_once_in_place(substs),
             Eq(lhs, rhs)
             | Neq(lhs, rhs)
             | And(lhs, rhs)
@@ -867,7 +925,8 @@ impl Exp<Sym> {
             | Bvlshr(lhs, rhs)
             | Bvashr(lhs, rhs)
             | Concat(lhs, rhs)
-            | FPBinary(_, lhs, rhs) => {
+            | FPBinary(_, lhs, rhs)
+            | IntBinary(_, lhs, rhs) => {
                 lhs.subst_once_in_place(substs);
                 rhs.subst_once_in_place(substs);
             }
@@ -995,11 +1054,14 @@ impl Exp<Sym> {
             }
             FPRoundingBinary(_, _, lhs, _) => lhs.infer(tcx, ftcx),
             FPfma(_, x, _, _) => x.infer(tcx, ftcx),
+            Int(_) => Some(Ty::Int),
+            IntUnary(op, _) => Some(op.result_ty()),
+            IntBinary(op, _, _) => Some(if op.is_predicate() { Ty::Bool } else { Ty::Int }),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Def {
     DeclareConst(Sym, Ty),
     DeclareFun(Sym, Vec<Ty>, Ty),
@@ -0,0 +1,377 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A solver-free backend for the [Solver] type, used when the `z3`
+//! feature is disabled. It records every definition as an SMT-LIB
+//! script, which can be written out with [Solver::dump_solver], and
+//! tracks the types of declared variables, but it cannot decide
+//! satisfiability so [Solver::check_sat] always returns
+//! [SmtResult::Unknown]. This is enough to build and replay traces,
+//! e.g. in a browser, without linking against Z3.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::marker::PhantomData;
+
+use super::smtlib::*;
+use super::{DefAttrs, EnumId, EnumMember, SmtResult, Sym, Trace};
+use crate::bitvector::BV;
+use crate::error::ExecError;
+use crate::ir::Name;
+use crate::simplify::{self, WriteEnum, WriteOpts};
+use crate::source_loc::SourceLoc;
+
+/// There are no solver parameters without Z3, so any that are set
+/// are ignored.
+#[derive(Default)]
+pub struct Config {}
+
+impl Config {
+    pub fn new() -> Self {
+        Config {}
+    }
+
+    pub fn set_param_value(&mut self, _id: &str, _value: &str) {}
+}
+
+pub fn global_set_param_value(_id: &str, _value: &str) {}
+
+pub struct Context {}
+
+impl Context {
+    pub fn new(_cfg: Config) -> Self {
+        Context {}
+    }
+}
+
+/// The Solver type records each definition in SMT-LIB format. See
+/// the `z3` backend for a description of the interface.
+pub struct Solver<'ctx, B> {
+    pub(super) trace: Trace<B>,
+    pub(super) next_var: u32,
+    pub(super) def_attrs: DefAttrs,
+    pub(super) cycles: i128,
+    tcx: HashMap<Sym, Ty>,
+    ftcx: HashMap<Sym, (Vec<Ty>, Ty)>,
+    enums: HashMap<Name, (Sym, Vec<Sym>)>,
+    script: Vec<u8>,
+    ctx: PhantomData<&'ctx Context>,
+}
+
+/// Without a solver there are no models, so every query fails with
+/// [ExecError::NoModel].
+pub struct Model<'ctx, B> {
+    solver: PhantomData<&'ctx Solver<'ctx, B>>,
+}
+
+impl<'ctx, B> fmt::Debug for Model<'ctx, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(model)")
+    }
+}
+
+impl<'ctx, B: BV> Model<'ctx, B> {
+    pub fn new(_solver: &'ctx Solver<'ctx, B>) -> Self {
+        Model { solver: PhantomData }
+    }
+
+    pub fn set_complete_model(&mut self, _b: bool) {}
+
+    pub fn get_var(&mut self, _var: Sym) -> Result<Option<Exp<Sym>>, ExecError> {
+        Err(ExecError::NoModel)
+    }
+
+    pub fn get_exp(&mut self, _exp: &Exp<Sym>) -> Result<Option<Exp<Sym>>, ExecError> {
+        Err(ExecError::NoModel)
+    }
+}
+
+impl<'ctx, B> WriteEnum for Solver<'ctx, B> {
+    fn write_enum(&self, buf: &mut dyn Write, member: EnumMember) -> std::io::Result<()> {
+        let (_, members) = &self.enums[&member.enum_id.to_name()];
+        write!(buf, "v{}", members[member.member])
+    }
+}
+
+/// The declarations and script of a [Solver], which can be copied
+/// into new solvers with [Solver::from_snapshot].
+#[derive(Clone)]
+pub struct SolverSnapshot {
+    tcx: HashMap<Sym, Ty>,
+    ftcx: HashMap<Sym, (Vec<Ty>, Ty)>,
+    enums: HashMap<Name, (Sym, Vec<Sym>)>,
+    script: Vec<u8>,
+}
+
+impl<'ctx, B: BV> Solver<'ctx, B> {
+    /// Copy the solver's declarations and script. The trace is not
+    /// included.
+    pub fn snapshot(&self) -> SolverSnapshot {
+        SolverSnapshot {
+            tcx: self.tcx.clone(),
+            ftcx: self.ftcx.clone(),
+            enums: self.enums.clone(),
+            script: self.script.clone(),
+        }
+    }
+
+    /// Create a solver with the declarations and script in
+    /// `snapshot`, and an empty trace.
+    pub fn from_snapshot(ctx: &'ctx Context, snapshot: &SolverSnapshot) -> Self {
+        let SolverSnapshot { tcx, ftcx, enums, script } = snapshot.clone();
+        Solver { tcx, ftcx, enums, script, ..Solver::new(ctx) }
+    }
+
+    pub fn new(_ctx: &'ctx Context) -> Self {
+        Solver {
+            trace: Trace::new(),
+            next_var: 0,
+            def_attrs: DefAttrs::default(),
+            cycles: 0,
+            tcx: HashMap::new(),
+            ftcx: HashMap::new(),
+            enums: HashMap::new(),
+            script: Vec::new(),
+            ctx: PhantomData,
+        }
+    }
+
+    fn write_sort(&mut self, ty: &Ty) {
+        let mut buf = Vec::new();
+        write_sort(&mut buf, ty, &self.enums).unwrap();
+        self.script.extend_from_slice(&buf)
+    }
+
+    fn write_exp(&mut self, exp: &Exp<Sym>) {
+        let mut buf = Vec::new();
+        simplify::write_smt_exp(&mut buf, exp, self, &WriteOpts::smtlib()).unwrap();
+        self.script.extend_from_slice(&buf)
+    }
+
+    pub(super) fn assert_all_internal(&mut self, exps: &[Exp<Sym>]) {
+        for exp in exps {
+            write!(self.script, "(assert ").unwrap();
+            self.write_exp(exp);
+            writeln!(self.script, ")").unwrap()
+        }
+    }
+
+    pub(super) fn declare_const_internal(&mut self, v: Sym, ty: &Ty) {
+        write!(self.script, "(declare-const v{} ", v).unwrap();
+        self.write_sort(ty);
+        writeln!(self.script, ")").unwrap();
+        self.tcx.insert(v, ty.clone());
+    }
+
+    pub fn get_enum(&mut self, id: Name, size: usize) -> EnumId {
+        if !self.enums.contains_key(&id) {
+            self.add(Def::DefineEnum(id, size))
+        };
+        EnumId { id }
+    }
+
+    pub(super) fn add_internal(&mut self, def: &Def) -> Result<(), ExecError> {
+        match &def {
+            Def::Assert(exp) => self.assert_all_internal(std::slice::from_ref(exp)),
+            Def::DeclareConst(v, ty) => self.declare_const_internal(*v, ty),
+            Def::DeclareFun(v, arg_tys, result_ty) => {
+                write!(self.script, "(declare-fun v{} (", v).unwrap();
+                for (i, ty) in arg_tys.iter().enumerate() {
+                    if i > 0 {
+                        write!(self.script, " ").unwrap()
+                    }
+                    self.write_sort(ty)
+                }
+                write!(self.script, ") ").unwrap();
+                self.write_sort(result_ty);
+                writeln!(self.script, ")").unwrap();
+                self.ftcx.insert(*v, (arg_tys.clone(), result_ty.clone()));
+            }
+            Def::DefineConst(v, exp) => {
+                let Some(ty) = exp.infer(&self.tcx, &self.ftcx) else {
+                    return Err(ExecError::Type(format!("Could not infer the type of v{}", *v), SourceLoc::unknown()));
+                };
+                write!(self.script, "(define-fun v{} () ", v).unwrap();
+                self.write_sort(&ty);
+                write!(self.script, " ").unwrap();
+                self.write_exp(exp);
+                writeln!(self.script, ")").unwrap();
+                self.tcx.insert(*v, ty);
+            }
+            Def::DefineEnum(name, size) => {
+                if !self.enums.contains_key(name) {
+                    let sort_name = self.fresh();
+                    let members: Vec<Sym> = (0..*size).map(|_| self.fresh()).collect();
+                    write!(self.script, "(declare-datatypes ((v{} 0)) ((", sort_name).unwrap();
+                    for (i, member) in members.iter().enumerate() {
+                        if i > 0 {
+                            write!(self.script, " ").unwrap()
+                        }
+                        write!(self.script, "(v{})", member).unwrap()
+                    }
+                    writeln!(self.script, ")))").unwrap();
+                    self.enums.insert(*name, (sort_name, members));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn length(&mut self, v: Sym) -> Option<u32> {
+        match self.tcx.get(&v) {
+            Some(Ty::BitVec(sz)) => Some(*sz),
+            _ => None,
+        }
+    }
+
+    pub fn is_bitvector(&mut self, v: Sym) -> bool {
+        matches!(self.tcx.get(&v), Some(Ty::BitVec(_)))
+    }
+
+    pub fn is_int(&mut self, v: Sym) -> bool {
+        matches!(self.tcx.get(&v), Some(Ty::Int))
+    }
+
+    pub fn check_sat_with(&mut self, _exp: &Exp<Sym>) -> SmtResult {
+        SmtResult::Unknown
+    }
+
+    pub fn check_sat_pair(&mut self, _left: &Exp<Sym>, _right: &Exp<Sym>) -> (SmtResult, SmtResult) {
+        (SmtResult::Unknown, SmtResult::Unknown)
+    }
+
+    /// Always `None`, as no assumptions can be shown unsatisfiable
+    /// without a solver.
+    pub fn unsat_core(&mut self, _assumptions: &[Exp<Sym>]) -> Option<Vec<usize>> {
+        None
+    }
+
+    pub fn check_sat(&mut self) -> SmtResult {
+        SmtResult::Unknown
+    }
+
+    pub fn dump_solver(&mut self, filename: &str) {
+        let mut file = std::fs::File::create(filename).expect("Failed to open solver dump file");
+        file.write_all(&self.script).expect("Failed to write solver dump");
+    }
+
+    pub fn dump_solver_with(&mut self, filename: &str, exp: &Exp<Sym>) {
+        let mut file = std::fs::File::create(filename).expect("Failed to open solver dump file");
+        file.write_all(&self.script).expect("Failed to write solver dump");
+        writeln!(file, "{}", self.exp_to_str(exp)).expect("Failed to write exp");
+    }
+
+    pub fn exp_to_str(&mut self, exp: &Exp<Sym>) -> String {
+        let mut buf = Vec::new();
+        simplify::write_smt_exp(&mut buf, exp, self, &WriteOpts::smtlib()).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+fn write_sort(buf: &mut dyn Write, ty: &Ty, enums: &HashMap<Name, (Sym, Vec<Sym>)>) -> std::io::Result<()> {
+    match ty {
+        Ty::Bool => write!(buf, "Bool"),
+        Ty::BitVec(sz) => write!(buf, "(_ BitVec {})", sz),
+        Ty::Enum(e) => write!(buf, "v{}", enums[&e.to_name()].0),
+        Ty::Array(dom, codom) => {
+            write!(buf, "(Array ")?;
+            write_sort(buf, dom, enums)?;
+            write!(buf, " ")?;
+            write_sort(buf, codom, enums)?;
+            write!(buf, ")")
+        }
+        Ty::Float(ebits, sbits) => write!(buf, "(_ FloatingPoint {} {})", ebits, sbits),
+        Ty::RoundingMode => write!(buf, "RoundingMode"),
+        Ty::Int => write!(buf, "Int"),
+    }
+}
+
+/// There is no solver state to release, so this does nothing.
+///
+/// # Safety
+///
+/// Always safe, but kept unsafe to match the Z3 backend.
+pub unsafe fn finalize_solver() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::smt::Event;
+    use crate::source_loc::SourceLoc;
+
+    fn script<B: BV>(solver: &Solver<B>) -> String {
+        String::from_utf8(solver.script.clone()).unwrap()
+    }
+
+    #[test]
+    fn text_script() {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let x = solver.declare_const(Ty::BitVec(4), SourceLoc::unknown());
+        let y = solver.define_const(Exp::Bvadd(Box::new(Exp::Var(x)), Box::new(bits64(1, 4))), SourceLoc::unknown());
+        solver.assert(Exp::Eq(Box::new(Exp::Var(y)), Box::new(bits64(0, 4))));
+        let e = solver.get_enum(Name::from_u32(0), 2);
+        let z = solver.declare_const(Ty::Enum(e), SourceLoc::unknown());
+
+        assert_eq!(solver.length(y), Some(4));
+        assert!(solver.is_bitvector(x) && !solver.is_int(x) && !solver.is_bitvector(z));
+        assert_eq!(solver.check_sat(), SmtResult::Unknown);
+        assert!(Model::new(&solver).get_var(x).is_err());
+        assert_eq!(
+            script(&solver),
+            "(declare-const v0 (_ BitVec 4))\n\
+             (define-fun v1 () (_ BitVec 4) (bvadd v0 #x1))\n\
+             (assert (= v1 #x0))\n\
+             (declare-datatypes ((v2 0)) (((v3) (v4))))\n\
+             (declare-const v5 v2)\n"
+        );
+
+        let snapshot = solver.snapshot();
+        let mut restored = Solver::<B64>::from_snapshot(&ctx, &snapshot);
+        assert_eq!(restored.length(y), Some(4));
+        assert_eq!(script(&restored), script(&solver));
+        assert!(restored.trace().to_vec().is_empty())
+    }
+
+    #[test]
+    fn text_untyped_definition() {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let undeclared = Exp::Var(Sym::from_u32(7));
+        let def = Event::Smt(Def::DefineConst(Sym::from_u32(8), undeclared), DefAttrs::default(), SourceLoc::unknown());
+        assert!(matches!(solver.try_add_event(def), Err(ExecError::Type(..))));
+        assert!(solver.trace().to_vec().is_empty());
+        assert!(script(&solver).is_empty());
+
+        assert!(solver.try_add_event(Event::Cycle).is_ok());
+        assert_eq!(solver.trace().to_vec().len(), 1)
+    }
+}
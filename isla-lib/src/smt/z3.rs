@@ -0,0 +1,1789 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+// Copyright (c) 2020 Brian Campbell
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The Z3 backend for the [Solver] type, a safe abstraction over the
+//! [z3_sys] crate. This is used when the `z3` feature is enabled,
+//! which it is by default.
+
+use libc::{c_int, c_uint};
+use num_bigint::BigInt;
+use z3_sys::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::io::Write;
+use std::mem;
+use std::ptr;
+use std::sync::mpsc;
+use std::thread;
+
+use super::smtlib::{self, *};
+use super::SmtResult::*;
+use super::{DefAttrs, EnumId, EnumMember, SmtResult, Sym, Trace};
+use crate::bitvector::b64::B64;
+use crate::bitvector::BV;
+use crate::error::ExecError;
+use crate::ir::Name;
+use crate::source_loc::SourceLoc;
+
+/// Config is a wrapper around the `Z3_config` type from the C
+/// API. `Z3_del_config` is called when it is dropped.
+pub struct Config {
+    z3_cfg: Z3_config,
+    params: Vec<(CString, CString)>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        unsafe { Config { z3_cfg: Z3_mk_config(), params: Vec::new() } }
+    }
+}
+
+impl Drop for Config {
+    fn drop(&mut self) {
+        unsafe { Z3_del_config(self.z3_cfg) }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    pub fn set_param_value(&mut self, id: &str, value: &str) {
+        let id = CString::new(id).unwrap();
+        let value = CString::new(value).unwrap();
+        unsafe { Z3_set_param_value(self.z3_cfg, id.as_ptr(), value.as_ptr()) }
+        self.params.push((id, value))
+    }
+}
+
+pub fn global_set_param_value(id: &str, value: &str) {
+    let id = CString::new(id).unwrap();
+    let value = CString::new(value).unwrap();
+    unsafe { Z3_global_param_set(id.as_ptr(), value.as_ptr()) }
+}
+
+/// Context is a wrapper around `Z3_context`.
+pub struct Context {
+    z3_ctx: Z3_context,
+    // The parameters the context was made with, for the context of
+    // its pair worker
+    params: Vec<(CString, CString)>,
+    pair_worker: RefCell<Option<PairWorker>>,
+}
+
+impl Context {
+    pub fn new(cfg: Config) -> Self {
+        let z3_ctx = unsafe { Z3_mk_context_rc(cfg.z3_cfg) };
+        Context { z3_ctx, params: cfg.params.clone(), pair_worker: RefCell::new(None) }
+    }
+
+    fn error(&self) -> ExecError {
+        unsafe {
+            let code = Z3_get_error_code(self.z3_ctx);
+            let msg = Z3_get_error_msg(self.z3_ctx, code);
+            let str: String = CStr::from_ptr(msg).to_string_lossy().to_string();
+            ExecError::Z3Error(str)
+        }
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe { Z3_del_context(self.z3_ctx) }
+    }
+}
+
+struct Enum {
+    sort: Z3_sort,
+    consts: Vec<Z3_func_decl>,
+    testers: Vec<Z3_func_decl>,
+}
+
+struct Enums<'ctx> {
+    enums: HashMap<Name, Enum, ahash::RandomState>,
+    ctx: &'ctx Context,
+}
+
+impl<'ctx> Enums<'ctx> {
+    fn new(ctx: &'ctx Context) -> Self {
+        Enums { enums: HashMap::default(), ctx }
+    }
+
+    fn add_enum(&mut self, name: Name, z3_name: Sym, members: &[Sym]) {
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            let size = members.len();
+
+            let z3_name = Z3_mk_int_symbol(ctx, z3_name.id as c_int);
+            let members: Vec<Z3_symbol> = members.iter().map(|m| Z3_mk_int_symbol(ctx, m.id as c_int)).collect();
+
+            let mut consts = mem::ManuallyDrop::new(Vec::with_capacity(size));
+            let mut testers = mem::ManuallyDrop::new(Vec::with_capacity(size));
+
+            let sort = Z3_mk_enumeration_sort(
+                ctx,
+                z3_name,
+                size as c_uint,
+                members.as_ptr(),
+                consts.as_mut_ptr(),
+                testers.as_mut_ptr(),
+            );
+
+            let consts = Vec::from_raw_parts(consts.as_mut_ptr(), size, size);
+            let testers = Vec::from_raw_parts(testers.as_mut_ptr(), size, size);
+
+            for i in 0..size {
+                Z3_inc_ref(ctx, Z3_func_decl_to_ast(ctx, consts[i]));
+                Z3_inc_ref(ctx, Z3_func_decl_to_ast(ctx, testers[i]))
+            }
+            Z3_inc_ref(ctx, Z3_sort_to_ast(ctx, sort));
+
+            self.enums.insert(name, Enum { sort, consts, testers });
+        }
+    }
+}
+
+impl<'ctx> Drop for Enums<'ctx> {
+    fn drop(&mut self) {
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            for (_, e) in self.enums.drain() {
+                for i in 0..e.consts.len() {
+                    Z3_dec_ref(ctx, Z3_func_decl_to_ast(ctx, e.consts[i]));
+                    Z3_dec_ref(ctx, Z3_func_decl_to_ast(ctx, e.testers[i]))
+                }
+                Z3_dec_ref(ctx, Z3_sort_to_ast(ctx, e.sort))
+            }
+        }
+    }
+}
+
+struct Sort<'ctx> {
+    z3_sort: Z3_sort,
+    ctx: &'ctx Context,
+}
+
+impl<'ctx> Sort<'ctx> {
+    fn float(ctx: &'ctx Context, ebits: u32, sbits: u32) -> Self {
+        assert!(ebits > 1 && sbits > 2);
+
+        unsafe {
+            let z3_sort = Z3_mk_fpa_sort(ctx.z3_ctx, ebits, sbits);
+            Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
+            Sort { z3_sort, ctx }
+        }
+    }
+
+    fn bitvec(ctx: &'ctx Context, sz: u32) -> Self {
+        unsafe {
+            let z3_sort = Z3_mk_bv_sort(ctx.z3_ctx, sz);
+            Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
+            Sort { z3_sort, ctx }
+        }
+    }
+
+    fn int(ctx: &'ctx Context) -> Self {
+        unsafe {
+            let z3_sort = Z3_mk_int_sort(ctx.z3_ctx);
+            Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
+            Sort { z3_sort, ctx }
+        }
+    }
+
+    fn new(ctx: &'ctx Context, enums: &Enums<'ctx>, ty: &Ty) -> Self {
+        unsafe {
+            match ty {
+                Ty::Bool => {
+                    let z3_sort = Z3_mk_bool_sort(ctx.z3_ctx);
+                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
+                    Sort { z3_sort, ctx }
+                }
+                Ty::BitVec(sz) => Self::bitvec(ctx, *sz),
+                Ty::Enum(e) => {
+                    let z3_sort = enums.enums[&e.id].sort;
+                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
+                    Sort { z3_sort, ctx }
+                }
+                Ty::Array(dom, codom) => {
+                    let dom_s = Self::new(ctx, enums, dom);
+                    let codom_s = Self::new(ctx, enums, codom);
+                    let z3_sort = Z3_mk_array_sort(ctx.z3_ctx, dom_s.z3_sort, codom_s.z3_sort);
+                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
+                    Sort { z3_sort, ctx }
+                }
+                Ty::Float(ebits, sbits) => Self::float(ctx, *ebits, *sbits),
+                Ty::RoundingMode => {
+                    let z3_sort = Z3_mk_fpa_rounding_mode_sort(ctx.z3_ctx);
+                    Z3_inc_ref(ctx.z3_ctx, Z3_sort_to_ast(ctx.z3_ctx, z3_sort));
+                    Sort { z3_sort, ctx }
+                }
+                Ty::Int => Self::int(ctx),
+            }
+        }
+    }
+}
+
+impl<'ctx> Drop for Sort<'ctx> {
+    fn drop(&mut self) {
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            Z3_dec_ref(ctx, Z3_sort_to_ast(ctx, self.z3_sort))
+        }
+    }
+}
+
+struct FuncDecl<'ctx> {
+    z3_func_decl: Z3_func_decl,
+    ctx: &'ctx Context,
+}
+
+impl<'ctx> FuncDecl<'ctx> {
+    fn new(ctx: &'ctx Context, v: Sym, enums: &Enums<'ctx>, arg_tys: &[Ty], ty: &Ty) -> Self {
+        unsafe {
+            let name = Z3_mk_int_symbol(ctx.z3_ctx, v.id as c_int);
+            let arg_sorts: Vec<Sort> = arg_tys.iter().map(|ty| Sort::new(ctx, enums, ty)).collect();
+            let arg_z3_sorts: Vec<Z3_sort> = arg_sorts.iter().map(|s| s.z3_sort).collect();
+            let args: u32 = arg_sorts.len() as u32;
+            let z3_func_decl =
+                Z3_mk_func_decl(ctx.z3_ctx, name, args, arg_z3_sorts.as_ptr(), Sort::new(ctx, enums, ty).z3_sort);
+            Z3_inc_ref(ctx.z3_ctx, Z3_func_decl_to_ast(ctx.z3_ctx, z3_func_decl));
+            FuncDecl { z3_func_decl, ctx }
+        }
+    }
+}
+
+impl<'ctx> Drop for FuncDecl<'ctx> {
+    fn drop(&mut self) {
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            Z3_dec_ref(ctx, Z3_func_decl_to_ast(ctx, self.z3_func_decl))
+        }
+    }
+}
+
+struct Ast<'ctx> {
+    z3_ast: Z3_ast,
+    ctx: &'ctx Context,
+}
+
+impl<'ctx> Clone for Ast<'ctx> {
+    fn clone(&self) -> Self {
+        unsafe {
+            let z3_ast = self.z3_ast;
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+}
+
+macro_rules! z3_nullary_op {
+    ($i:ident, $ctx:ident) => {
+        unsafe {
+            let z3_ast = $i($ctx.z3_ctx);
+            Z3_inc_ref($ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: $ctx }
+        }
+    };
+}
+
+macro_rules! z3_unary_op {
+    ($i:ident, $arg:ident) => {
+        unsafe {
+            let z3_ast = $i($arg.ctx.z3_ctx, $arg.z3_ast);
+            Z3_inc_ref($arg.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: $arg.ctx }
+        }
+    };
+}
+
+macro_rules! z3_binary_op {
+    ($i:ident, $lhs:ident, $rhs:ident) => {
+        unsafe {
+            let z3_ast = $i($lhs.ctx.z3_ctx, $lhs.z3_ast, $rhs.z3_ast);
+            Z3_inc_ref($lhs.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: $lhs.ctx }
+        }
+    };
+}
+
+macro_rules! z3_float_binary_op {
+    ($i:ident, $rm:ident, $lhs:ident, $rhs:ident) => {
+        unsafe {
+            let z3_ast = $i($lhs.ctx.z3_ctx, $rm.z3_ast, $lhs.z3_ast, $rhs.z3_ast);
+            Z3_inc_ref($lhs.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: $rm.ctx }
+        }
+    };
+}
+
+impl<'ctx> Ast<'ctx> {
+    fn mk_app(fd: &FuncDecl<'ctx>, args: &[Ast<'ctx>]) -> Self {
+        unsafe {
+            let z3_args: Vec<Z3_ast> = args.iter().map(|ast| ast.z3_ast).collect();
+            let len = z3_args.len() as u32;
+            let z3_ast = Z3_mk_app(fd.ctx.z3_ctx, fd.z3_func_decl, len, z3_args.as_ptr());
+            Z3_inc_ref(fd.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: fd.ctx }
+        }
+    }
+
+    fn mk_enum_member(enums: &Enums<'ctx>, enum_id: EnumId, member: usize) -> Self {
+        unsafe {
+            let func_decl = enums.enums[&enum_id.id].consts[member];
+            let z3_ast = Z3_mk_app(enums.ctx.z3_ctx, func_decl, 0, ptr::null());
+            Z3_inc_ref(enums.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: enums.ctx }
+        }
+    }
+
+    fn mk_bv_u64(ctx: &'ctx Context, sz: u32, bits: u64) -> Self {
+        unsafe {
+            let sort = Sort::bitvec(ctx, sz);
+            let z3_ast = Z3_mk_unsigned_int64(ctx.z3_ctx, bits, sort.z3_sort);
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn mk_bv(ctx: &'ctx Context, sz: u32, bits: &[bool]) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_bv_numeral(ctx.z3_ctx, sz, bits.as_ptr());
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn mk_fpa_nan(ctx: &'ctx Context, ebits: u32, sbits: u32) -> Self {
+        unsafe {
+            let sort = Sort::float(ctx, ebits, sbits);
+            let z3_ast = Z3_mk_fpa_nan(ctx.z3_ctx, sort.z3_sort);
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn mk_fpa_zero(ctx: &'ctx Context, ebits: u32, sbits: u32, negative: bool) -> Self {
+        unsafe {
+            let sort = Sort::float(ctx, ebits, sbits);
+            let z3_ast = Z3_mk_fpa_zero(ctx.z3_ctx, sort.z3_sort, negative);
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn mk_fpa_inf(ctx: &'ctx Context, ebits: u32, sbits: u32, negative: bool) -> Self {
+        unsafe {
+            let sort = Sort::float(ctx, ebits, sbits);
+            let z3_ast = Z3_mk_fpa_inf(ctx.z3_ctx, sort.z3_sort, negative);
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn mk_bool(ctx: &'ctx Context, b: bool) -> Self {
+        unsafe {
+            let z3_ast = if b { Z3_mk_true(ctx.z3_ctx) } else { Z3_mk_false(ctx.z3_ctx) };
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn mk_fpa_round_to_integral(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_round_to_integral, self, rhs)
+    }
+
+    fn mk_fpa_round_nearest_ties_to_even(ctx: &'ctx Context) -> Self {
+        z3_nullary_op!(Z3_mk_fpa_round_nearest_ties_to_even, ctx)
+    }
+
+    fn mk_fpa_round_nearest_ties_to_away(ctx: &'ctx Context) -> Self {
+        z3_nullary_op!(Z3_mk_fpa_round_nearest_ties_to_away, ctx)
+    }
+
+    fn mk_fpa_round_toward_positive(ctx: &'ctx Context) -> Self {
+        z3_nullary_op!(Z3_mk_fpa_round_toward_positive, ctx)
+    }
+
+    fn mk_fpa_round_toward_negative(ctx: &'ctx Context) -> Self {
+        z3_nullary_op!(Z3_mk_fpa_round_toward_negative, ctx)
+    }
+
+    fn mk_fpa_round_toward_zero(ctx: &'ctx Context) -> Self {
+        z3_nullary_op!(Z3_mk_fpa_round_toward_zero, ctx)
+    }
+
+    fn mk_not(&self) -> Self {
+        z3_unary_op!(Z3_mk_not, self)
+    }
+
+    fn mk_eq(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_eq, self, rhs)
+    }
+
+    fn mk_and(&self, rhs: &Ast<'ctx>) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_and(self.ctx.z3_ctx, 2, &[self.z3_ast, rhs.z3_ast] as *const Z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_or(&self, rhs: &Ast<'ctx>) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_or(self.ctx.z3_ctx, 2, &[self.z3_ast, rhs.z3_ast] as *const Z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn extract(&self, hi: u32, lo: u32) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_extract(self.ctx.z3_ctx, hi, lo, self.z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn zero_extend(&self, i: u32) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_zero_ext(self.ctx.z3_ctx, i, self.z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn sign_extend(&self, i: u32) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_sign_ext(self.ctx.z3_ctx, i, self.z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn ite(&self, true_exp: &Ast<'ctx>, false_exp: &Ast<'ctx>) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_ite(self.ctx.z3_ctx, self.z3_ast, true_exp.z3_ast, false_exp.z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_bvnot(&self) -> Self {
+        z3_unary_op!(Z3_mk_bvnot, self)
+    }
+
+    fn mk_bvand(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvand, self, rhs)
+    }
+
+    fn mk_bvor(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvor, self, rhs)
+    }
+
+    fn mk_bvxor(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvxor, self, rhs)
+    }
+
+    fn mk_bvnand(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvnand, self, rhs)
+    }
+
+    fn mk_bvnor(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvnor, self, rhs)
+    }
+
+    fn mk_bvxnor(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvxnor, self, rhs)
+    }
+
+    fn mk_bvneg(&self) -> Self {
+        z3_unary_op!(Z3_mk_bvneg, self)
+    }
+
+    fn mk_bvadd(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvadd, self, rhs)
+    }
+
+    fn mk_bvsub(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvsub, self, rhs)
+    }
+
+    fn mk_bvmul(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvmul, self, rhs)
+    }
+
+    fn mk_bvudiv(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvudiv, self, rhs)
+    }
+
+    fn mk_bvsdiv(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvsdiv, self, rhs)
+    }
+
+    fn mk_bvurem(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvurem, self, rhs)
+    }
+
+    fn mk_bvsrem(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvsrem, self, rhs)
+    }
+
+    fn mk_bvsmod(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvsmod, self, rhs)
+    }
+
+    fn mk_bvult(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvult, self, rhs)
+    }
+
+    fn mk_bvslt(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvslt, self, rhs)
+    }
+
+    fn mk_bvule(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvule, self, rhs)
+    }
+
+    fn mk_bvsle(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvsle, self, rhs)
+    }
+
+    fn mk_bvuge(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvuge, self, rhs)
+    }
+
+    fn mk_bvsge(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvsge, self, rhs)
+    }
+
+    fn mk_bvugt(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvugt, self, rhs)
+    }
+
+    fn mk_bvsgt(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvsgt, self, rhs)
+    }
+
+    fn mk_bvshl(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvshl, self, rhs)
+    }
+
+    fn mk_bvlshr(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvlshr, self, rhs)
+    }
+
+    fn mk_bvashr(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_bvashr, self, rhs)
+    }
+
+    fn mk_concat(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_concat, self, rhs)
+    }
+
+    fn mk_select(&self, index: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_select, self, index)
+    }
+
+    fn mk_fpa_abs(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_abs, self)
+    }
+
+    fn mk_fpa_neg(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_neg, self)
+    }
+
+    fn mk_fpa_add(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
+        z3_float_binary_op!(Z3_mk_fpa_add, self, lhs, rhs)
+    }
+
+    fn mk_fpa_div(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
+        z3_float_binary_op!(Z3_mk_fpa_div, self, lhs, rhs)
+    }
+
+    fn mk_fpa_eq(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_eq, self, rhs)
+    }
+
+    fn mk_fpa_fma(&self, t1: &Ast<'ctx>, t2: &Ast<'ctx>, t3: &Ast<'ctx>) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_fpa_fma(self.ctx.z3_ctx, self.z3_ast, t1.z3_ast, t2.z3_ast, t3.z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_fpa_geq(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_geq, self, rhs)
+    }
+
+    fn mk_fpa_gt(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_gt, self, rhs)
+    }
+
+    fn mk_fpa_leq(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_leq, self, rhs)
+    }
+
+    fn mk_fpa_lt(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_lt, self, rhs)
+    }
+
+    fn mk_fpa_rem(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_rem, self, rhs)
+    }
+
+    fn mk_fpa_max(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_max, self, rhs)
+    }
+
+    fn mk_fpa_min(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_min, self, rhs)
+    }
+
+    fn mk_fpa_mul(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
+        z3_float_binary_op!(Z3_mk_fpa_mul, self, lhs, rhs)
+    }
+
+    fn mk_fpa_sqrt(&self, rhs: &Ast<'ctx>) -> Self {
+        z3_binary_op!(Z3_mk_fpa_sqrt, self, rhs)
+    }
+
+    fn mk_fpa_sub(&self, lhs: &Ast<'ctx>, rhs: &Ast<'ctx>) -> Self {
+        z3_float_binary_op!(Z3_mk_fpa_sub, self, lhs, rhs)
+    }
+
+    fn mk_fpa_is_normal(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_is_normal, self)
+    }
+
+    fn mk_fpa_is_subnormal(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_is_subnormal, self)
+    }
+
+    fn mk_fpa_is_zero(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_is_zero, self)
+    }
+
+    fn mk_fpa_is_infinite(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_is_infinite, self)
+    }
+
+    fn mk_fpa_is_nan(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_is_nan, self)
+    }
+
+    fn mk_fpa_is_negative(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_is_negative, self)
+    }
+
+    fn mk_fpa_is_positive(&self) -> Self {
+        z3_unary_op!(Z3_mk_fpa_is_positive, self)
+    }
+
+    fn mk_fpa_to_fp_bv(&self, ebits: u32, sbits: u32) -> Self {
+        unsafe {
+            let sort = Sort::float(self.ctx, ebits, sbits);
+            let z3_ast = Z3_mk_fpa_to_fp_bv(self.ctx.z3_ctx, self.z3_ast, sort.z3_sort);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_fpa_to_fp_float(&self, exp: &Ast<'ctx>, ebits: u32, sbits: u32) -> Self {
+        unsafe {
+            let sort = Sort::float(self.ctx, ebits, sbits);
+            let z3_ast = Z3_mk_fpa_to_fp_float(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sort.z3_sort);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_fpa_to_fp_signed(&self, exp: &Ast<'ctx>, ebits: u32, sbits: u32) -> Self {
+        unsafe {
+            let sort = Sort::float(self.ctx, ebits, sbits);
+            let z3_ast = Z3_mk_fpa_to_fp_signed(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sort.z3_sort);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_fpa_to_fp_unsigned(&self, exp: &Ast<'ctx>, ebits: u32, sbits: u32) -> Self {
+        unsafe {
+            let sort = Sort::float(self.ctx, ebits, sbits);
+            let z3_ast = Z3_mk_fpa_to_fp_unsigned(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sort.z3_sort);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_fpa_to_sbv(&self, exp: &Ast<'ctx>, sz: u32) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_fpa_to_sbv(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sz);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_fpa_to_ubv(&self, exp: &Ast<'ctx>, sz: u32) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_fpa_to_ubv(self.ctx.z3_ctx, self.z3_ast, exp.z3_ast, sz);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_store(&self, index: &Ast<'ctx>, val: &Ast<'ctx>) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_store(self.ctx.z3_ctx, self.z3_ast, index.z3_ast, val.z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_int(ctx: &'ctx Context, n: &BigInt) -> Self {
+        unsafe {
+            let sort = Sort::int(ctx);
+            let numeral = CString::new(n.to_string()).unwrap();
+            let z3_ast = Z3_mk_numeral(ctx.z3_ctx, numeral.as_ptr(), sort.z3_sort);
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn mk_int_neg(&self) -> Self {
+        z3_unary_op!(Z3_mk_unary_minus, self)
+    }
+
+    fn mk_sbv_to_int(&self) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_bv2int(self.ctx.z3_ctx, self.z3_ast, true);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_int_to_bv(&self, sz: u32) -> Self {
+        unsafe {
+            let z3_ast = Z3_mk_int2bv(self.ctx.z3_ctx, sz, self.z3_ast);
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn mk_int_arith(&self, op: IntBinary, rhs: &Ast<'ctx>) -> Self {
+        unsafe {
+            let args = [self.z3_ast, rhs.z3_ast];
+            let z3_ast = match op {
+                IntBinary::Add => Z3_mk_add(self.ctx.z3_ctx, 2, args.as_ptr()),
+                IntBinary::Sub => Z3_mk_sub(self.ctx.z3_ctx, 2, args.as_ptr()),
+                IntBinary::Mul => Z3_mk_mul(self.ctx.z3_ctx, 2, args.as_ptr()),
+                IntBinary::Le => Z3_mk_le(self.ctx.z3_ctx, self.z3_ast, rhs.z3_ast),
+                IntBinary::Lt => Z3_mk_lt(self.ctx.z3_ctx, self.z3_ast, rhs.z3_ast),
+                IntBinary::Ge => Z3_mk_ge(self.ctx.z3_ctx, self.z3_ast, rhs.z3_ast),
+                IntBinary::Gt => Z3_mk_gt(self.ctx.z3_ctx, self.z3_ast, rhs.z3_ast),
+            };
+            Z3_inc_ref(self.ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx: self.ctx }
+        }
+    }
+
+    fn get_numeral_int(&self) -> Result<BigInt, ExecError> {
+        unsafe {
+            let s = CStr::from_ptr(Z3_get_numeral_string(self.ctx.z3_ctx, self.z3_ast));
+            s.to_str().ok().and_then(|s| s.parse().ok()).ok_or_else(|| self.ctx.error())
+        }
+    }
+
+    fn mk_distinct(ctx: &'ctx Context, args: &[Ast<'ctx>]) -> Self {
+        unsafe {
+            let z3_args: Vec<Z3_ast> = args.iter().map(|ast| ast.z3_ast).collect();
+            let len = z3_args.len() as u32;
+            let z3_ast = Z3_mk_distinct(ctx.z3_ctx, len, z3_args.as_ptr());
+            Z3_inc_ref(ctx.z3_ctx, z3_ast);
+            Ast { z3_ast, ctx }
+        }
+    }
+
+    fn get_bool_value(&self) -> Option<bool> {
+        unsafe {
+            match Z3_get_bool_value(self.ctx.z3_ctx, self.z3_ast) {
+                Z3_L_TRUE => Some(true),
+                Z3_L_FALSE => Some(false),
+                _ => None,
+            }
+        }
+    }
+
+    fn get_numeral_u64(&self) -> Result<u64, ExecError> {
+        let mut v: u64 = 0;
+        unsafe {
+            if Z3_get_numeral_uint64(self.ctx.z3_ctx, self.z3_ast, &mut v) {
+                Ok(v)
+            } else {
+                Err(self.ctx.error())
+            }
+        }
+    }
+}
+
+impl<'ctx> Drop for Ast<'ctx> {
+    fn drop(&mut self) {
+        unsafe { Z3_dec_ref(self.ctx.z3_ctx, self.z3_ast) }
+    }
+}
+
+/// The Solver type handles all interaction with Z3. It mimics
+/// interacting with Z3 via the subset of the SMTLIB 2.0 format we
+/// care about.
+///
+/// For example:
+/// ```
+/// # use isla_lib::bitvector::b64::B64;
+/// # use isla_lib::smt::smtlib::Exp::*;
+/// # use isla_lib::smt::smtlib::Def::*;
+/// # use isla_lib::smt::smtlib::*;
+/// # use isla_lib::smt::*;
+/// # let x = Sym::from_u32(0);
+/// let cfg = Config::new();
+/// let ctx = Context::new(cfg);
+/// let mut solver = Solver::<B64>::new(&ctx);
+/// // (declare-const v0 Bool)
+/// solver.add(DeclareConst(x, Ty::Bool));
+/// // (assert v0)
+/// solver.add(Assert(Var(x)));
+/// // (check-sat)
+/// assert!(solver.check_sat() == SmtResult::Sat)
+/// ```
+///
+/// The other thing the Solver type does is maintain a trace of
+/// interactions with Z3, which can be checkpointed and replayed by
+/// another solver. This `Checkpoint` type is safe to be sent between
+/// threads.
+///
+/// For example:
+/// ```
+/// # use isla_lib::bitvector::b64::B64;
+/// # use isla_lib::smt::smtlib::Exp::*;
+/// # use isla_lib::smt::smtlib::Def::*;
+/// # use isla_lib::smt::smtlib::*;
+/// # use isla_lib::smt::*;
+/// # let x = Sym::from_u32(0);
+/// let point = {
+///     let cfg = Config::new();
+///     let ctx = Context::new(cfg);
+///     let mut solver = Solver::<B64>::new(&ctx);
+///     solver.add(DeclareConst(x, Ty::Bool));
+///     solver.add(Assert(Var(x)));
+///     solver.add(Assert(Not(Box::new(Var(x)))));
+///     checkpoint(&mut solver)
+/// };
+/// let cfg = Config::new();
+/// let ctx = Context::new(cfg);
+/// let mut solver = Solver::from_checkpoint(&ctx, point);
+/// assert!(solver.check_sat() == SmtResult::Unsat);
+pub struct Solver<'ctx, B> {
+    pub(super) trace: Trace<B>,
+    pub(super) next_var: u32,
+    pub(super) def_attrs: DefAttrs,
+    pub(super) cycles: i128,
+    decls: HashMap<Sym, Ast<'ctx>>,
+    func_decls: HashMap<Sym, FuncDecl<'ctx>>,
+    sorts: HashMap<Ty, Sort<'ctx>>,
+    enums: Enums<'ctx>,
+    z3_solver: Z3_solver,
+    ctx: &'ctx Context,
+}
+
+/// A thread which runs one of each pair of checks made by
+/// [Solver::check_sat_pair] on the solvers of a [Context]. The worker
+/// has a context of its own, made with the same parameters, which
+/// solvers are copied into while the worker is waiting for a check.
+/// The thread is started when the first pair of checks is made, and
+/// stops when its context is dropped.
+struct PairWorker {
+    z3_ctx: Z3_context,
+    checks: Option<mpsc::Sender<PairCheck>>,
+    results: mpsc::Receiver<Z3_lbool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+/// A context which is owned by a [PairWorker] thread
+struct PairContext(Z3_context);
+
+unsafe impl Send for PairContext {}
+
+impl PairContext {
+    fn delete(self) {
+        unsafe { Z3_del_context(self.0) }
+    }
+}
+
+impl PairWorker {
+    fn new(params: &[(CString, CString)]) -> Self {
+        let z3_ctx = unsafe {
+            let z3_cfg = Z3_mk_config();
+            for (id, value) in params {
+                Z3_set_param_value(z3_cfg, id.as_ptr(), value.as_ptr())
+            }
+            let z3_ctx = Z3_mk_context_rc(z3_cfg);
+            Z3_del_config(z3_cfg);
+            z3_ctx
+        };
+        let (checks, pending) = mpsc::channel::<PairCheck>();
+        let (finished, results) = mpsc::channel();
+        let owned = PairContext(z3_ctx);
+        let handle = thread::spawn(move || {
+            for check in pending {
+                if finished.send(check.run()).is_err() {
+                    break;
+                }
+            }
+            owned.delete()
+        });
+        PairWorker { z3_ctx, checks: Some(checks), results, handle: Some(handle) }
+    }
+
+    /// Start a check on the worker thread, or run it on this thread
+    /// if the worker has stopped. The result must be collected with
+    /// [PairWorker::finish] before the worker's context is used again.
+    fn start(&self, check: PairCheck) -> Option<Z3_lbool> {
+        match &self.checks {
+            Some(checks) => checks.send(check).err().map(|mpsc::SendError(check)| check.run()),
+            None => Some(check.run()),
+        }
+    }
+
+    fn finish(&self, started: Option<Z3_lbool>) -> SmtResult {
+        match started {
+            Some(result) => smt_result(result),
+            None => self.results.recv().map_or(Unknown, smt_result),
+        }
+    }
+}
+
+impl Drop for PairWorker {
+    fn drop(&mut self) {
+        self.checks = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// The Z3 objects for a check running on a [PairWorker]. Each is owned
+/// by the worker's context, which only the worker uses until the
+/// check finishes.
+struct PairCheck {
+    z3_ctx: Z3_context,
+    z3_solver: Z3_solver,
+    z3_ast: Z3_ast,
+}
+
+unsafe impl Send for PairCheck {}
+
+impl PairCheck {
+    fn run(self) -> Z3_lbool {
+        unsafe {
+            let result = Z3_solver_check_assumptions(self.z3_ctx, self.z3_solver, 1, &self.z3_ast);
+            Z3_dec_ref(self.z3_ctx, self.z3_ast);
+            Z3_solver_dec_ref(self.z3_ctx, self.z3_solver);
+            result
+        }
+    }
+}
+
+fn smt_result(result: Z3_lbool) -> SmtResult {
+    if result == Z3_L_TRUE {
+        Sat
+    } else if result == Z3_L_FALSE {
+        Unsat
+    } else {
+        Unknown
+    }
+}
+
+impl<'ctx, B> Drop for Solver<'ctx, B> {
+    fn drop(&mut self) {
+        unsafe {
+            Z3_solver_dec_ref(self.ctx.z3_ctx, self.z3_solver);
+        }
+    }
+}
+
+/// The assertions and declarations of a [Solver], copied into a
+/// context of their own by [Solver::snapshot] so they outlive the
+/// solver and can be copied into new solvers with
+/// [Solver::from_snapshot], on any thread. The snapshot's context
+/// must only be used by one thread at a time.
+pub struct SolverSnapshot {
+    z3_solver: Z3_solver,
+    // The declarations, then the function declarations, then each
+    // enumeration's sort followed by its constants and testers
+    z3_asts: Z3_ast_vector,
+    decls: Vec<Sym>,
+    func_decls: Vec<Sym>,
+    enums: Vec<(Name, usize)>,
+    ctx: Context,
+}
+
+unsafe impl Send for SolverSnapshot {}
+
+impl Drop for SolverSnapshot {
+    fn drop(&mut self) {
+        unsafe {
+            Z3_ast_vector_dec_ref(self.ctx.z3_ctx, self.z3_asts);
+            Z3_solver_dec_ref(self.ctx.z3_ctx, self.z3_solver)
+        }
+    }
+}
+
+/// Interface for extracting information from Z3 models.
+///
+/// Model generation should be turned on in advance.  This is
+/// currently Z3's default, but it's best to make sure:
+///
+/// ```
+/// # use isla_lib::bitvector::b64::B64;
+/// # use isla_lib::smt::smtlib::Exp::*;
+/// # use isla_lib::smt::smtlib::Def::*;
+/// # use isla_lib::smt::smtlib::*;
+/// # use isla_lib::smt::*;
+/// # let x = Sym::from_u32(0);
+/// let mut cfg = Config::new();
+/// cfg.set_param_value("model", "true");
+/// let ctx = Context::new(cfg);
+/// let mut solver = Solver::<B64>::new(&ctx);
+/// solver.add(DeclareConst(x, Ty::BitVec(4)));
+/// solver.add(Assert(Bvsgt(Box::new(Var(x)), Box::new(Bits(vec![false,false,true,false])))));
+/// assert!(solver.check_sat() == SmtResult::Sat);
+/// let mut model = Model::new(&solver);
+/// let var0 = model.get_var(x).unwrap().unwrap();
+/// ```
+pub struct Model<'ctx, B> {
+    z3_model: Z3_model,
+    solver: &'ctx Solver<'ctx, B>,
+    ctx: &'ctx Context,
+    complete_model: bool,
+}
+
+impl<'ctx, B> Drop for Model<'ctx, B> {
+    fn drop(&mut self) {
+        unsafe {
+            Z3_model_dec_ref(self.ctx.z3_ctx, self.z3_model);
+        }
+    }
+}
+
+// This implements Debug rather than Display because it displays the internal
+// variable names (albeit with the same numbers that appear in the trace).
+impl<'ctx, B> fmt::Debug for Model<'ctx, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe {
+            let z3_string = CStr::from_ptr(Z3_model_to_string(self.ctx.z3_ctx, self.z3_model));
+            write!(f, "{}", z3_string.to_string_lossy())
+        }
+    }
+}
+
+impl<'ctx, B: BV> Model<'ctx, B> {
+    pub fn new(solver: &'ctx Solver<'ctx, B>) -> Self {
+        unsafe {
+            let z3_model = Z3_solver_get_model(solver.ctx.z3_ctx, solver.z3_solver);
+            Z3_model_inc_ref(solver.ctx.z3_ctx, z3_model);
+            Model { z3_model, solver, ctx: solver.ctx, complete_model: false }
+        }
+    }
+
+    pub fn set_complete_model(&mut self, b: bool) {
+        self.complete_model = b;
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    fn get_large_bv(&mut self, ast: Ast, size: u32) -> Result<Vec<bool>, ExecError> {
+        let mut i = 0;
+        let size = size.try_into().unwrap();
+        let mut result = vec![false; size];
+        while i < size {
+            let hi = std::cmp::min(size, i + 64);
+            let hi32: u32 = hi.try_into().unwrap();
+            let extract_ast = ast.extract(hi32 - 1, i.try_into().unwrap());
+            let result_ast: Ast;
+
+            unsafe {
+                let mut result_z3_ast: Z3_ast = ptr::null_mut();
+                if !Z3_model_eval(self.ctx.z3_ctx, self.z3_model, extract_ast.z3_ast, true, &mut result_z3_ast) {
+                    return Err(self.ctx.error());
+                }
+                Z3_inc_ref(self.ctx.z3_ctx, result_z3_ast);
+                result_ast = Ast { z3_ast: result_z3_ast, ctx: self.ctx };
+            }
+            let v = result_ast.get_numeral_u64()?;
+            for j in i..hi {
+                result[j] = (v >> (j - i) & 1) == 1;
+            }
+            i += 64;
+        }
+        Ok(result)
+    }
+
+    pub fn get_var(&mut self, var: Sym) -> Result<Option<Exp<Sym>>, ExecError> {
+        let var_ast = match self.solver.decls.get(&var) {
+            None => return Err(ExecError::Type(format!("Unbound variable {:?}", &var), SourceLoc::unknown())),
+            Some(ast) => ast.clone(),
+        };
+        self.get_ast(var_ast)
+    }
+
+    pub fn get_exp(&mut self, exp: &Exp<Sym>) -> Result<Option<Exp<Sym>>, ExecError> {
+        let ast = self.solver.translate_exp(exp);
+        self.get_ast(ast)
+    }
+
+    // Requiring the model to be mutable as I expect Z3 will alter the underlying data
+    fn get_ast(&mut self, var_ast: Ast) -> Result<Option<Exp<Sym>>, ExecError> {
+        unsafe {
+            let z3_ctx = self.ctx.z3_ctx;
+            let mut z3_ast: Z3_ast = ptr::null_mut();
+            if !Z3_model_eval(z3_ctx, self.z3_model, var_ast.z3_ast, self.complete_model, &mut z3_ast) {
+                return Err(self.ctx.error());
+            }
+            Z3_inc_ref(z3_ctx, z3_ast);
+
+            let ast = Ast { z3_ast, ctx: self.ctx };
+
+            let sort = Z3_get_sort(z3_ctx, ast.z3_ast);
+            Z3_inc_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, sort));
+            let sort_kind = Z3_get_sort_kind(z3_ctx, sort);
+
+            let result = if sort_kind == SortKind::BV && Z3_is_numeral_ast(z3_ctx, z3_ast) {
+                let size = Z3_get_bv_sort_size(z3_ctx, sort);
+                if size > 64 {
+                    let v = self.get_large_bv(ast, size)?;
+                    Ok(Some(Exp::Bits(v)))
+                } else {
+                    let result = ast.get_numeral_u64()?;
+                    Ok(Some(Exp::Bits64(B64::new(result, size))))
+                }
+            } else if sort_kind == SortKind::Bool && Z3_is_numeral_ast(z3_ctx, z3_ast) {
+                Ok(Some(Exp::Bool(ast.get_bool_value().unwrap())))
+            } else if sort_kind == SortKind::Int && Z3_is_numeral_ast(z3_ctx, z3_ast) {
+                Ok(Some(Exp::Int(ast.get_numeral_int()?)))
+            } else if sort_kind == SortKind::Bool || sort_kind == SortKind::BV || sort_kind == SortKind::Int {
+                // Model did not need to assign an interpretation to this variable
+                Ok(None)
+            } else if sort_kind == SortKind::Datatype {
+                let func_decl = Z3_get_app_decl(z3_ctx, Z3_to_app(z3_ctx, z3_ast));
+                Z3_inc_ref(z3_ctx, Z3_func_decl_to_ast(z3_ctx, func_decl));
+
+                let mut result = Ok(None);
+
+                // Scan all enumerations to find the enum_id (which is
+                // the size of the enum) and member number.
+                'outer: for (enum_id, enumeration) in self.solver.enums.enums.iter() {
+                    for (i, member) in enumeration.consts.iter().enumerate() {
+                        if Z3_is_eq_func_decl(z3_ctx, func_decl, *member) {
+                            result = Ok(Some(Exp::Enum(EnumMember { enum_id: EnumId { id: *enum_id }, member: i })));
+                            break 'outer;
+                        }
+                    }
+                }
+
+                Z3_dec_ref(z3_ctx, Z3_func_decl_to_ast(z3_ctx, func_decl));
+                result
+            } else {
+                Err(ExecError::Type("get_ast".to_string(), SourceLoc::unknown()))
+            };
+
+            Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, sort));
+            result
+        }
+    }
+}
+
+static QFAUFBV_STR: &[u8] = b"qfaufbv\0";
+
+impl<'ctx, B: BV> Solver<'ctx, B> {
+    pub fn new(ctx: &'ctx Context) -> Self {
+        unsafe {
+            let mut major: c_uint = 0;
+            let mut minor: c_uint = 0;
+            let mut build: c_uint = 0;
+            let mut revision: c_uint = 0;
+            Z3_get_version(&mut major, &mut minor, &mut build, &mut revision);
+
+            // The QF_AUFBV solver has good performance on our problems, but we need to initialise it
+            // using a tactic rather than the logic name to ensure that the enumerations are supported,
+            // otherwise Z3 may crash.
+            let qfaufbv_tactic = Z3_mk_tactic(ctx.z3_ctx, CStr::from_bytes_with_nul_unchecked(QFAUFBV_STR).as_ptr());
+            Z3_tactic_inc_ref(ctx.z3_ctx, qfaufbv_tactic);
+            let z3_solver = Z3_mk_solver_from_tactic(ctx.z3_ctx, qfaufbv_tactic);
+            Z3_solver_inc_ref(ctx.z3_ctx, z3_solver);
+
+            Solver {
+                ctx,
+                z3_solver,
+                next_var: 0,
+                def_attrs: DefAttrs::default(),
+                cycles: 0,
+                trace: Trace::new(),
+                decls: HashMap::new(),
+                func_decls: HashMap::new(),
+                sorts: HashMap::new(),
+                enums: Enums::new(ctx),
+            }
+        }
+    }
+
+    /// Copy the solver's assertions and declarations into a new
+    /// context. The trace is not included.
+    pub fn snapshot(&self) -> SolverSnapshot {
+        let ctx = Context::new(Config::new());
+        unsafe {
+            let src = self.ctx.z3_ctx;
+            let z3_asts = Z3_mk_ast_vector(src);
+            Z3_ast_vector_inc_ref(src, z3_asts);
+            let decls = self
+                .decls
+                .iter()
+                .map(|(v, ast)| {
+                    Z3_ast_vector_push(src, z3_asts, ast.z3_ast);
+                    *v
+                })
+                .collect();
+            let func_decls = self
+                .func_decls
+                .iter()
+                .map(|(v, fd)| {
+                    Z3_ast_vector_push(src, z3_asts, Z3_func_decl_to_ast(src, fd.z3_func_decl));
+                    *v
+                })
+                .collect();
+            let enums = self
+                .enums
+                .enums
+                .iter()
+                .map(|(name, e)| {
+                    Z3_ast_vector_push(src, z3_asts, Z3_sort_to_ast(src, e.sort));
+                    for fd in e.consts.iter().chain(e.testers.iter()) {
+                        Z3_ast_vector_push(src, z3_asts, Z3_func_decl_to_ast(src, *fd))
+                    }
+                    (*name, e.consts.len())
+                })
+                .collect();
+
+            // Translating all the ASTs together preserves the sharing between them
+            let translated = Z3_ast_vector_translate(src, z3_asts, ctx.z3_ctx);
+            Z3_ast_vector_inc_ref(ctx.z3_ctx, translated);
+            Z3_ast_vector_dec_ref(src, z3_asts);
+            let z3_solver = Z3_solver_translate(src, self.z3_solver, ctx.z3_ctx);
+            Z3_solver_inc_ref(ctx.z3_ctx, z3_solver);
+
+            SolverSnapshot { z3_solver, z3_asts: translated, decls, func_decls, enums, ctx }
+        }
+    }
+
+    /// Create a solver with the assertions and declarations in
+    /// `snapshot`, and an empty trace.
+    pub fn from_snapshot(ctx: &'ctx Context, snapshot: &SolverSnapshot) -> Self {
+        unsafe {
+            let dst = ctx.z3_ctx;
+            let z3_solver = Z3_solver_translate(snapshot.ctx.z3_ctx, snapshot.z3_solver, dst);
+            Z3_solver_inc_ref(dst, z3_solver);
+            let z3_asts = Z3_ast_vector_translate(snapshot.ctx.z3_ctx, snapshot.z3_asts, dst);
+            Z3_ast_vector_inc_ref(dst, z3_asts);
+
+            let mut i = 0;
+            let mut next_ast = || {
+                let z3_ast = Z3_ast_vector_get(dst, z3_asts, i);
+                Z3_inc_ref(dst, z3_ast);
+                i += 1;
+                z3_ast
+            };
+            let decls = snapshot.decls.iter().map(|v| (*v, Ast { z3_ast: next_ast(), ctx })).collect();
+            let func_decls = snapshot
+                .func_decls
+                .iter()
+                .map(|v| (*v, FuncDecl { z3_func_decl: Z3_to_func_decl(dst, next_ast()), ctx }))
+                .collect();
+            let mut enums = Enums::new(ctx);
+            for (name, size) in &snapshot.enums {
+                let sort = next_ast() as Z3_sort;
+                let consts = (0..*size).map(|_| Z3_to_func_decl(dst, next_ast())).collect();
+                let testers = (0..*size).map(|_| Z3_to_func_decl(dst, next_ast())).collect();
+                enums.enums.insert(*name, Enum { sort, consts, testers });
+            }
+            Z3_ast_vector_dec_ref(dst, z3_asts);
+
+            Solver {
+                ctx,
+                z3_solver,
+                next_var: 0,
+                def_attrs: DefAttrs::default(),
+                cycles: 0,
+                trace: Trace::new(),
+                decls,
+                func_decls,
+                sorts: HashMap::new(),
+                enums,
+            }
+        }
+    }
+
+    fn translate_exp(&self, exp: &Exp<Sym>) -> Ast<'ctx> {
+        use Exp::*;
+        match exp {
+            Var(v) => match self.decls.get(v) {
+                None => panic!("Could not get Z3 func_decl {}", *v),
+                Some(ast) => ast.clone(),
+            },
+            Bits(bv) => Ast::mk_bv(self.ctx, bv.len().try_into().unwrap(), bv),
+            Bits64(bv) => Ast::mk_bv_u64(self.ctx, bv.len(), bv.lower_u64()),
+            Enum(e) => Ast::mk_enum_member(&self.enums, e.enum_id, e.member),
+            Bool(b) => Ast::mk_bool(self.ctx, *b),
+            Not(exp) => Ast::mk_not(&self.translate_exp(exp)),
+            Eq(lhs, rhs) => Ast::mk_eq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Neq(lhs, rhs) => Ast::mk_not(&Ast::mk_eq(&self.translate_exp(lhs), &self.translate_exp(rhs))),
+            And(lhs, rhs) => Ast::mk_and(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Or(lhs, rhs) => Ast::mk_or(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvnot(exp) => Ast::mk_bvnot(&self.translate_exp(exp)),
+            Bvand(lhs, rhs) => Ast::mk_bvand(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvor(lhs, rhs) => Ast::mk_bvor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvxor(lhs, rhs) => Ast::mk_bvxor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvnand(lhs, rhs) => Ast::mk_bvnand(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvnor(lhs, rhs) => Ast::mk_bvnor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvxnor(lhs, rhs) => Ast::mk_bvxnor(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvneg(exp) => Ast::mk_bvneg(&self.translate_exp(exp)),
+            Bvadd(lhs, rhs) => Ast::mk_bvadd(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvsub(lhs, rhs) => Ast::mk_bvsub(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvmul(lhs, rhs) => Ast::mk_bvmul(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvudiv(lhs, rhs) => Ast::mk_bvudiv(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvsdiv(lhs, rhs) => Ast::mk_bvsdiv(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvurem(lhs, rhs) => Ast::mk_bvurem(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvsrem(lhs, rhs) => Ast::mk_bvsrem(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvsmod(lhs, rhs) => Ast::mk_bvsmod(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvult(lhs, rhs) => Ast::mk_bvult(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvslt(lhs, rhs) => Ast::mk_bvslt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvule(lhs, rhs) => Ast::mk_bvule(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvsle(lhs, rhs) => Ast::mk_bvsle(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvuge(lhs, rhs) => Ast::mk_bvuge(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvsge(lhs, rhs) => Ast::mk_bvsge(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvugt(lhs, rhs) => Ast::mk_bvugt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvsgt(lhs, rhs) => Ast::mk_bvsgt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Extract(hi, lo, bv) => self.translate_exp(bv).extract(*hi, *lo),
+            ZeroExtend(i, bv) => self.translate_exp(bv).zero_extend(*i),
+            SignExtend(i, bv) => self.translate_exp(bv).sign_extend(*i),
+            Bvshl(lhs, rhs) => Ast::mk_bvshl(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvlshr(lhs, rhs) => Ast::mk_bvlshr(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Bvashr(lhs, rhs) => Ast::mk_bvashr(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Concat(lhs, rhs) => Ast::mk_concat(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+            Ite(cond, t, f) => self.translate_exp(cond).ite(&self.translate_exp(t), &self.translate_exp(f)),
+            App(f, args) => {
+                let args_ast: Vec<_> = args.iter().map(|arg| self.translate_exp(arg)).collect();
+                match self.func_decls.get(f) {
+                    None => panic!("Could not get Z3 func_decl {}", *f),
+                    Some(fd) => Ast::mk_app(fd, &args_ast),
+                }
+            }
+            Select(array, index) => Ast::mk_select(&self.translate_exp(array), &self.translate_exp(index)),
+            Store(array, index, val) => {
+                Ast::mk_store(&self.translate_exp(array), &self.translate_exp(index), &self.translate_exp(val))
+            }
+            Distinct(exps) => {
+                let exps_ast: Vec<_> = exps.iter().map(|exp| self.translate_exp(exp)).collect();
+                Ast::mk_distinct(self.ctx, &exps_ast)
+            }
+            &FPConstant(c, ebits, sbits) => {
+                use smtlib::FPConstant::*;
+                match c {
+                    NaN => Ast::mk_fpa_nan(self.ctx, ebits, sbits),
+                    Inf { negative } => Ast::mk_fpa_inf(self.ctx, ebits, sbits, negative),
+                    Zero { negative } => Ast::mk_fpa_zero(self.ctx, ebits, sbits, negative),
+                }
+            }
+            FPRoundingMode(rm) => {
+                use smtlib::FPRoundingMode::*;
+                match rm {
+                    RoundNearestTiesToEven => Ast::mk_fpa_round_nearest_ties_to_even(self.ctx),
+                    RoundNearestTiesToAway => Ast::mk_fpa_round_nearest_ties_to_away(self.ctx),
+                    RoundTowardPositive => Ast::mk_fpa_round_toward_positive(self.ctx),
+                    RoundTowardNegative => Ast::mk_fpa_round_toward_negative(self.ctx),
+                    RoundTowardZero => Ast::mk_fpa_round_toward_zero(self.ctx),
+                }
+            }
+            FPUnary(op, exp) => {
+                use smtlib::FPUnary::*;
+                match op {
+                    Abs => Ast::mk_fpa_abs(&self.translate_exp(exp)),
+                    Neg => Ast::mk_fpa_neg(&self.translate_exp(exp)),
+                    IsNormal => Ast::mk_fpa_is_normal(&self.translate_exp(exp)),
+                    IsSubnormal => Ast::mk_fpa_is_subnormal(&self.translate_exp(exp)),
+                    IsZero => Ast::mk_fpa_is_zero(&self.translate_exp(exp)),
+                    IsInfinite => Ast::mk_fpa_is_infinite(&self.translate_exp(exp)),
+                    IsNaN => Ast::mk_fpa_is_nan(&self.translate_exp(exp)),
+                    IsNegative => Ast::mk_fpa_is_negative(&self.translate_exp(exp)),
+                    IsPositive => Ast::mk_fpa_is_positive(&self.translate_exp(exp)),
+                    FromIEEE(ebits, sbits) => Ast::mk_fpa_to_fp_bv(&self.translate_exp(exp), *ebits, *sbits),
+                }
+            }
+            FPRoundingUnary(op, rm, exp) => {
+                use smtlib::FPRoundingUnary::*;
+                match op {
+                    Sqrt => Ast::mk_fpa_sqrt(&self.translate_exp(rm), &self.translate_exp(exp)),
+                    RoundToIntegral => Ast::mk_fpa_round_to_integral(&self.translate_exp(rm), &self.translate_exp(exp)),
+                    Convert(ebits, sbits) => {
+                        Ast::mk_fpa_to_fp_float(&self.translate_exp(rm), &self.translate_exp(exp), *ebits, *sbits)
+                    }
+                    FromSigned(ebits, sbits) => {
+                        Ast::mk_fpa_to_fp_signed(&self.translate_exp(rm), &self.translate_exp(exp), *ebits, *sbits)
+                    }
+                    FromUnsigned(ebits, sbits) => {
+                        Ast::mk_fpa_to_fp_unsigned(&self.translate_exp(rm), &self.translate_exp(exp), *ebits, *sbits)
+                    }
+                    ToSigned(sz) => Ast::mk_fpa_to_sbv(&self.translate_exp(rm), &self.translate_exp(exp), *sz),
+                    ToUnsigned(sz) => Ast::mk_fpa_to_ubv(&self.translate_exp(rm), &self.translate_exp(exp), *sz),
+                }
+            }
+            FPBinary(op, lhs, rhs) => {
+                use smtlib::FPBinary::*;
+                match op {
+                    Rem => Ast::mk_fpa_rem(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Min => Ast::mk_fpa_min(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Max => Ast::mk_fpa_max(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Leq => Ast::mk_fpa_leq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Lt => Ast::mk_fpa_lt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Geq => Ast::mk_fpa_geq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Gt => Ast::mk_fpa_gt(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Eq => Ast::mk_fpa_eq(&self.translate_exp(lhs), &self.translate_exp(rhs)),
+                }
+            }
+            FPRoundingBinary(op, rm, lhs, rhs) => {
+                use smtlib::FPRoundingBinary::*;
+                match op {
+                    Add => Ast::mk_fpa_add(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Sub => Ast::mk_fpa_sub(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Mul => Ast::mk_fpa_mul(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
+                    Div => Ast::mk_fpa_div(&self.translate_exp(rm), &self.translate_exp(lhs), &self.translate_exp(rhs)),
+                }
+            }
+            FPfma(rm, x, y, z) => Ast::mk_fpa_fma(
+                &self.translate_exp(rm),
+                &self.translate_exp(x),
+                &self.translate_exp(y),
+                &self.translate_exp(z),
+            ),
+            Int(n) => Ast::mk_int(self.ctx, n),
+            IntUnary(op, exp) => {
+                use smtlib::IntUnary::*;
+                match op {
+                    Neg => Ast::mk_int_neg(&self.translate_exp(exp)),
+                    FromSigned(_) => Ast::mk_sbv_to_int(&self.translate_exp(exp)),
+                    ToBits(sz) => Ast::mk_int_to_bv(&self.translate_exp(exp), *sz),
+                }
+            }
+            IntBinary(op, lhs, rhs) => Ast::mk_int_arith(&self.translate_exp(lhs), *op, &self.translate_exp(rhs)),
+        }
+    }
+
+    fn z3_assert(&mut self, exp: &Exp<Sym>) {
+        let ast = self.translate_exp(exp);
+        unsafe {
+            Z3_solver_assert(self.ctx.z3_ctx, self.z3_solver, ast.z3_ast);
+        }
+    }
+
+    /// Assert the conjunction of several expressions with a single
+    /// call to the underlying solver.
+    pub(super) fn assert_all_internal(&mut self, exps: &[Exp<Sym>]) {
+        if exps.len() == 1 {
+            return self.z3_assert(&exps[0]);
+        }
+        let asts: Vec<Ast<'ctx>> = exps.iter().map(|exp| self.translate_exp(exp)).collect();
+        let z3_asts: Vec<Z3_ast> = asts.iter().map(|ast| ast.z3_ast).collect();
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            let conj = Z3_mk_and(ctx, z3_asts.len() as c_uint, z3_asts.as_ptr());
+            Z3_inc_ref(ctx, conj);
+            Z3_solver_assert(ctx, self.z3_solver, conj);
+            Z3_dec_ref(ctx, conj);
+        }
+    }
+
+    /// Declare a constant, re-using the sort from any previous
+    /// declaration of the same type.
+    pub(super) fn declare_const_internal(&mut self, v: Sym, ty: &Ty) {
+        if !self.sorts.contains_key(ty) {
+            self.sorts.insert(ty.clone(), Sort::new(self.ctx, &self.enums, ty));
+        }
+        let sort = &self.sorts[ty];
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            let name = Z3_mk_int_symbol(ctx, v.id as c_int);
+            let z3_ast = Z3_mk_const(ctx, name, sort.z3_sort);
+            Z3_inc_ref(ctx, z3_ast);
+            self.decls.insert(v, Ast { z3_ast, ctx: self.ctx });
+        }
+    }
+
+    pub fn get_enum(&mut self, id: Name, size: usize) -> EnumId {
+        if !self.enums.enums.contains_key(&id) {
+            self.add(Def::DefineEnum(id, size))
+        };
+        EnumId { id }
+    }
+
+    pub(super) fn add_internal(&mut self, def: &Def) -> Result<(), ExecError> {
+        match &def {
+            Def::Assert(exp) => self.z3_assert(exp),
+            Def::DeclareConst(v, ty) => self.declare_const_internal(*v, ty),
+            Def::DeclareFun(v, arg_tys, result_ty) => {
+                let fd = FuncDecl::new(self.ctx, *v, &self.enums, arg_tys, result_ty);
+                self.func_decls.insert(*v, fd);
+            }
+            Def::DefineConst(v, exp) => {
+                let ast = self.translate_exp(exp);
+                self.decls.insert(*v, ast);
+            }
+            Def::DefineEnum(name, size) => {
+                if !self.enums.enums.contains_key(name) {
+                    let z3_name = self.fresh();
+                    let members: Vec<Sym> = (0..*size).map(|_| self.fresh()).collect();
+                    self.enums.add_enum(*name, z3_name, &members)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn length(&mut self, v: Sym) -> Option<u32> {
+        match self.decls.get(&v) {
+            Some(ast) => unsafe {
+                let z3_ctx = self.ctx.z3_ctx;
+                let z3_sort = Z3_get_sort(z3_ctx, ast.z3_ast);
+                Z3_inc_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
+                if Z3_get_sort_kind(z3_ctx, z3_sort) == SortKind::BV {
+                    let sz = Z3_get_bv_sort_size(z3_ctx, z3_sort);
+                    Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
+                    Some(sz)
+                } else {
+                    Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    pub fn is_bitvector(&mut self, v: Sym) -> bool {
+        match self.decls.get(&v) {
+            Some(ast) => unsafe {
+                let z3_ctx = self.ctx.z3_ctx;
+                let z3_sort = Z3_get_sort(z3_ctx, ast.z3_ast);
+                Z3_inc_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
+                let result = Z3_get_sort_kind(z3_ctx, z3_sort) == SortKind::BV;
+                Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
+                result
+            },
+            None => false,
+        }
+    }
+
+    pub fn is_int(&mut self, v: Sym) -> bool {
+        match self.decls.get(&v) {
+            Some(ast) => unsafe {
+                let z3_ctx = self.ctx.z3_ctx;
+                let z3_sort = Z3_get_sort(z3_ctx, ast.z3_ast);
+                Z3_inc_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
+                let result = Z3_get_sort_kind(z3_ctx, z3_sort) == SortKind::Int;
+                Z3_dec_ref(z3_ctx, Z3_sort_to_ast(z3_ctx, z3_sort));
+                result
+            },
+            None => false,
+        }
+    }
+
+    pub fn check_sat_with(&mut self, exp: &Exp<Sym>) -> SmtResult {
+        let ast = self.translate_exp(exp);
+        unsafe {
+            let result = Z3_solver_check_assumptions(self.ctx.z3_ctx, self.z3_solver, 1, &ast.z3_ast);
+            if result == Z3_L_TRUE {
+                Sat
+            } else if result == Z3_L_FALSE {
+                Unsat
+            } else {
+                Unknown
+            }
+        }
+    }
+
+    /// Check the solver's assertions together with each of two
+    /// expressions at the same time. The solver is copied into the
+    /// context of the solver context's [PairWorker], which checks
+    /// `right` while this thread checks `left`.
+    pub fn check_sat_pair(&mut self, left: &Exp<Sym>, right: &Exp<Sym>) -> (SmtResult, SmtResult) {
+        let left = self.translate_exp(left);
+        let right = self.translate_exp(right);
+        let mut pair_worker = self.ctx.pair_worker.borrow_mut();
+        let pair_worker = pair_worker.get_or_insert_with(|| PairWorker::new(&self.ctx.params));
+        let pair_ctx = pair_worker.z3_ctx;
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            let z3_solver = Z3_solver_translate(ctx, self.z3_solver, pair_ctx);
+            Z3_solver_inc_ref(pair_ctx, z3_solver);
+            let z3_ast = Z3_translate(ctx, right.z3_ast, pair_ctx);
+            Z3_inc_ref(pair_ctx, z3_ast);
+
+            let started = pair_worker.start(PairCheck { z3_ctx: pair_ctx, z3_solver, z3_ast });
+            let left = Z3_solver_check_assumptions(ctx, self.z3_solver, 1, &left.z3_ast);
+            (smt_result(left), pair_worker.finish(started))
+        }
+    }
+
+    /// Check the solver's assertions together with the given
+    /// assumptions. If they are unsatisfiable, return the indices of
+    /// the assumptions in an unsat core, otherwise `None`.
+    pub fn unsat_core(&mut self, assumptions: &[Exp<Sym>]) -> Option<Vec<usize>> {
+        let asts: Vec<Ast> = assumptions.iter().map(|exp| self.translate_exp(exp)).collect();
+        unsafe {
+            let ctx = self.ctx.z3_ctx;
+            // The QF_AUFBV tactic solver cannot produce unsat cores,
+            // so the assertions are copied into a general solver.
+            // That solver only reports cores in terms of Boolean
+            // constants, so each assumption is implied by a fresh
+            // proxy.
+            let core_solver = Z3_mk_solver(ctx);
+            Z3_solver_inc_ref(ctx, core_solver);
+            let assertions = Z3_solver_get_assertions(ctx, self.z3_solver);
+            Z3_ast_vector_inc_ref(ctx, assertions);
+            for i in 0..Z3_ast_vector_size(ctx, assertions) {
+                Z3_solver_assert(ctx, core_solver, Z3_ast_vector_get(ctx, assertions, i))
+            }
+            Z3_ast_vector_dec_ref(ctx, assertions);
+
+            let prefix = CString::new("core").unwrap();
+            let bool_sort = Z3_mk_bool_sort(ctx);
+            let proxies: Vec<Z3_ast> = asts
+                .iter()
+                .map(|ast| {
+                    let proxy = Z3_mk_fresh_const(ctx, prefix.as_ptr(), bool_sort);
+                    Z3_inc_ref(ctx, proxy);
+                    let implies = Z3_mk_implies(ctx, proxy, ast.z3_ast);
+                    Z3_inc_ref(ctx, implies);
+                    Z3_solver_assert(ctx, core_solver, implies);
+                    Z3_dec_ref(ctx, implies);
+                    proxy
+                })
+                .collect();
+
+            let result = Z3_solver_check_assumptions(ctx, core_solver, proxies.len() as u32, proxies.as_ptr());
+            let indices = if result == Z3_L_FALSE {
+                let core = Z3_solver_get_unsat_core(ctx, core_solver);
+                Z3_ast_vector_inc_ref(ctx, core);
+                let mut indices = Vec::new();
+                for i in 0..Z3_ast_vector_size(ctx, core) {
+                    let ast = Z3_ast_vector_get(ctx, core, i);
+                    if let Some(j) = proxies.iter().position(|proxy| Z3_is_eq_ast(ctx, *proxy, ast)) {
+                        indices.push(j)
+                    }
+                }
+                Z3_ast_vector_dec_ref(ctx, core);
+                indices.sort_unstable();
+                Some(indices)
+            } else {
+                None
+            };
+
+            for proxy in proxies {
+                Z3_dec_ref(ctx, proxy)
+            }
+            Z3_solver_dec_ref(ctx, core_solver);
+            indices
+        }
+    }
+
+    pub fn check_sat(&mut self) -> SmtResult {
+        unsafe {
+            let result = Z3_solver_check(self.ctx.z3_ctx, self.z3_solver);
+            if result == Z3_L_TRUE {
+                Sat
+            } else if result == Z3_L_FALSE {
+                Unsat
+            } else {
+                Unknown
+            }
+        }
+    }
+
+    pub fn dump_solver(&mut self, filename: &str) {
+        let mut file = std::fs::File::create(filename).expect("Failed to open solver dump file");
+        unsafe {
+            let s = Z3_solver_to_string(self.ctx.z3_ctx, self.z3_solver);
+            let cs = CStr::from_ptr(s);
+            file.write_all(cs.to_bytes()).expect("Failed to write solver dump");
+        }
+    }
+
+    pub fn dump_solver_with(&mut self, filename: &str, exp: &Exp<Sym>) {
+        let mut file = std::fs::File::create(filename).expect("Failed to open solver dump file");
+        unsafe {
+            let s = Z3_solver_to_string(self.ctx.z3_ctx, self.z3_solver);
+            let cs = CStr::from_ptr(s);
+            file.write_all(cs.to_bytes()).expect("Failed to write solver dump");
+            writeln!(file, "{}", self.exp_to_str(exp)).expect("Failed to write exp");
+        }
+    }
+
+    pub fn exp_to_str(&mut self, exp: &Exp<Sym>) -> String {
+        let ast = self.translate_exp(exp);
+        let cs;
+        unsafe {
+            let s = Z3_ast_to_string(ast.ctx.z3_ctx, ast.z3_ast);
+            cs = CStr::from_ptr(s);
+        }
+        cs.to_string_lossy().to_string()
+    }
+}
+
+/// This function just calls Z3_finalize_memory(). It's useful because
+/// by calling it before we exit, we can check whether we are leaking
+/// memory while interacting with Z3 objects.
+///
+/// # Safety
+///
+/// Shoud only be called just before exiting.
+pub unsafe fn finalize_solver() {
+    Z3_finalize_memory()
+}
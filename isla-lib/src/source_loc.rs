@@ -57,6 +57,10 @@ impl SourceLoc {
         SourceLoc { file: -1, line1: n, char1: 0, line2: 0, char2: 0 }
     }
 
+    pub fn is_unknown(self) -> bool {
+        self.file < 0
+    }
+
     pub fn command_line() -> Self {
         SourceLoc { file: -2, line1: 0, char1: 0, line2: 0, char2: 0 }
     }
@@ -215,6 +219,16 @@ impl SourceLoc {
         }
     }
 
+    /// A `file:line` string for the start of the location, as used
+    /// when annotating trace events
+    pub fn short_location_string(self, files: &[&str]) -> String {
+        if let Some(file) = TryInto::<usize>::try_into(self.file).ok().and_then(|i| files.get(i)) {
+            format!("{}:{}", file, self.line1)
+        } else {
+            format!("{}", self.line1)
+        }
+    }
+
     pub fn message_file_contents(
         self,
         buf_name: &str,
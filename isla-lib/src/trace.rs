@@ -32,11 +32,17 @@
 
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::Path;
 
+use crate::abstract_interp::KnownBits;
 use crate::bitvector::BV;
-use crate::ir::{RegisterField, Val};
+use crate::ir::{Name, RegisterField, Val};
+use crate::register::RegisterBindings;
 use crate::smt::smtlib::{self, Def, Exp};
-use crate::smt::{Event, Sym};
+use crate::smt::{Event, SmtResult, Solver, Sym};
 
 pub enum RegisterValue<B> {
     Symbolic(Exp<Sym>),
@@ -77,7 +83,7 @@ pub fn initial_register_state<B: BV, E: Borrow<Event<B>>>(events: &[E]) -> Regis
                 defs.insert(*v, exp);
             }
 
-            AssumeReg(reg, accessor, value) | WriteReg(reg, accessor, value) => {
+            AssumeReg(reg, accessor, value) | WriteReg(reg, accessor, value, _) => {
                 let reg_field = (*reg, accessor.clone());
                 match value {
                     Val::Symbolic(v) => {
@@ -143,3 +149,391 @@ pub fn initial_register_state<B: BV, E: Borrow<Event<B>>>(events: &[E]) -> Regis
 
     RegisterState { values: regs, decls: used_decls, defs: used_defs, asserts }
 }
+
+/// The magic number at the start of every zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+fn is_compressed_path(path: &Path) -> bool {
+    matches!(path.extension(), Some(ext) if ext == "zst")
+}
+
+/// Create a file to write output to, which is compressed with zstd
+/// if its name ends with `.zst`. The compressed stream is completed
+/// when the writer is dropped.
+pub fn create_output<P: AsRef<Path>>(path: P) -> Result<Box<dyn Write>, String> {
+    let path = path.as_ref();
+    let fd = File::create(path).map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+    if is_compressed_path(path) {
+        compressed_writer(fd).map_err(|e| format!("Could not create {}: {}", path.display(), e))
+    } else {
+        Ok(Box::new(BufWriter::new(fd)))
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn compressed_writer(fd: File) -> Result<Box<dyn Write>, String> {
+    let encoder = zstd::Encoder::new(fd, 0).map_err(|e| e.to_string())?;
+    Ok(Box::new(BufWriter::new(encoder.auto_finish())))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compressed_writer(_: File) -> Result<Box<dyn Write>, String> {
+    Err("isla was built without zstd support".to_string())
+}
+
+/// Open a file for reading, transparently decompressing it if it
+/// was compressed with zstd (e.g. by [create_output]).
+pub fn open_input<P: AsRef<Path>>(path: P) -> Result<Box<dyn Read>, String> {
+    let path = path.as_ref();
+    let fd = File::open(path).map_err(|e| format!("Could not open {}: {}", path.display(), e))?;
+    let mut reader = BufReader::new(fd);
+    let compressed = reader
+        .fill_buf()
+        .map(|buf| buf.starts_with(&ZSTD_MAGIC))
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    if compressed {
+        compressed_reader(reader).map_err(|e| format!("Could not read {}: {}", path.display(), e))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn compressed_reader(reader: BufReader<File>) -> Result<Box<dyn Read>, String> {
+    Ok(Box::new(zstd::Decoder::with_buffer(reader).map_err(|e| e.to_string())?))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compressed_reader(_: BufReader<File>) -> Result<Box<dyn Read>, String> {
+    Err("isla was built without zstd support".to_string())
+}
+
+/// Save the traces for a set of paths, each in
+/// [crate::smt::EventOrder::Execution] order, to a file, which is
+/// compressed if its name ends with `.zst` (see [create_output]).
+/// They can be loaded with [load_traces] and replayed into a solver
+/// with [crate::smt::Checkpoint::from_events]. Names in the events
+/// are only meaningful with the same architecture's symbol table.
+pub fn save_traces<B: BV, P: AsRef<Path>>(traces: &[Vec<Event<B>>], path: P) -> Result<(), String> {
+    let path = path.as_ref();
+    let output = create_output(path)?;
+    bincode::serialize_into(output, traces).map_err(|e| format!("Could not write traces to {}: {}", path.display(), e))
+}
+
+/// Load traces saved by [save_traces], whether or not they are
+/// compressed.
+pub fn load_traces<B: BV, P: AsRef<Path>>(path: P) -> Result<Vec<Vec<Event<B>>>, String> {
+    let path = path.as_ref();
+    let input = open_input(path)?;
+    bincode::deserialize_from(input).map_err(|e| format!("Could not read traces from {}: {}", path.display(), e))
+}
+
+/// Parse traces saved by [save_traces] from the contents of the file,
+/// for when it was read by other means (e.g. fetched by a browser).
+pub fn parse_traces<B: BV>(bytes: &[u8]) -> Result<Vec<Vec<Event<B>>>, String> {
+    if bytes.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        {
+            let bytes = zstd::decode_all(bytes).map_err(|e| format!("Could not decompress traces: {}", e))?;
+            return bincode::deserialize(&bytes).map_err(|e| format!("Could not read traces: {}", e));
+        }
+        #[cfg(not(feature = "zstd"))]
+        return Err("Could not read traces: isla was built without zstd support".to_string());
+    }
+    bincode::deserialize(bytes).map_err(|e| format!("Could not read traces: {}", e))
+}
+
+/// The value of each register at the end of a trace (in execution
+/// order), taken from the last event which reads, writes, or assumes
+/// the whole register.
+pub fn final_register_values<B: BV, E: Borrow<Event<B>>>(events: &[E]) -> HashMap<Name, Val<B>> {
+    let mut values = HashMap::new();
+    for event in events {
+        match event.borrow() {
+            Event::ReadReg(name, accessors, value, _)
+            | Event::WriteReg(name, accessors, value, _)
+            | Event::AssumeReg(name, accessors, value)
+                if accessors.is_empty() =>
+            {
+                values.insert(*name, value.clone());
+            }
+            _ => (),
+        }
+    }
+    values
+}
+
+/// Each constant defined in a trace (in execution order), with the
+/// definitions of any constants it uses substituted in, so that it is
+/// expressed in terms of the declared constants alone.
+pub fn inline_definitions<B: BV, E: Borrow<Event<B>>>(events: &[E]) -> HashMap<Sym, Exp<Sym>> {
+    let mut defs: HashMap<Sym, Exp<Sym>> = HashMap::new();
+    for event in events {
+        if let Event::Smt(Def::DefineConst(v, exp), _, _) = event.borrow() {
+            let mut exp = exp.clone();
+            exp.modify(&mut |e| {
+                if let Exp::Var(x) = e {
+                    if let Some(def) = defs.get(x) {
+                        *e = def.clone()
+                    }
+                }
+            });
+            defs.insert(*v, exp);
+        }
+    }
+    defs
+}
+
+/// A register whose value at the end of a path differs from its
+/// value at the start, see [register_deltas].
+#[derive(Clone, Debug)]
+pub struct RegisterDelta<B> {
+    pub name: Name,
+    /// The value at the start of the path, or `None` if the register
+    /// was uninitialized and written before it was read.
+    pub before: Option<Val<B>>,
+    pub after: Val<B>,
+}
+
+/// Find the registers whose values changed along a path (in execution
+/// order), given the register bindings the path started from and the
+/// register values at its end (e.g. from the final frame, or
+/// [final_register_values]). The start of the path is taken to be the
+/// end of its first cycle, which is reserved for initialization, so
+/// registers are compared against their values after initialization,
+/// or the value they were first read as. Two symbolic values are the
+/// same if they are equal once their definitions are inlined (see
+/// [inline_definitions]).
+pub fn register_deltas<B: BV, E: Borrow<Event<B>>>(
+    events: &[E],
+    initial: &RegisterBindings<B>,
+    after: &HashMap<Name, Val<B>>,
+) -> Vec<RegisterDelta<B>> {
+    let mut before: HashMap<Name, Val<B>> = HashMap::new();
+    let mut written: HashSet<Name> = HashSet::new();
+    let mut init_cycle = events.iter().any(|ev| ev.borrow().is_cycle());
+    for event in events {
+        match event.borrow() {
+            Event::Cycle => init_cycle = false,
+            Event::WriteReg(name, accessors, value, _) | Event::AssumeReg(name, accessors, value)
+                if init_cycle && accessors.is_empty() =>
+            {
+                before.insert(*name, value.clone());
+            }
+            Event::ReadReg(name, accessors, value, _)
+                if !init_cycle && accessors.is_empty() && !written.contains(name) =>
+            {
+                before.entry(*name).or_insert_with(|| value.clone());
+            }
+            Event::WriteReg(name, _, _, _) if !init_cycle => {
+                written.insert(*name);
+            }
+            _ => (),
+        }
+    }
+
+    let defs = inline_definitions(events);
+    let same = |v1: &Val<B>, v2: &Val<B>| match (v1, v2) {
+        (Val::Symbolic(x1), Val::Symbolic(x2)) => {
+            let inline = |x: &Sym| defs.get(x).cloned().unwrap_or(Exp::Var(*x));
+            x1 == x2 || inline(x1) == inline(x2)
+        }
+        _ => v1 == v2,
+    };
+
+    let mut deltas: Vec<RegisterDelta<B>> = after
+        .iter()
+        .filter_map(|(name, value)| {
+            let old = before.get(name).or_else(|| initial.get_last_if_initialized(*name));
+            match old {
+                Some(old) if same(old, value) => None,
+                _ => Some(RegisterDelta { name: *name, before: old.cloned(), after: value.clone() }),
+            }
+        })
+        .collect();
+    deltas.sort_by_key(|delta| delta.name);
+    deltas
+}
+
+/// Find which bits of each register written along a path (in
+/// execution order) are the same in every model of the path, using
+/// the solver the path was executed with. Each register is taken at
+/// its final value (see [final_register_values]), and registers which
+/// do not hold bitvectors are left out. Every symbolic bit costs two
+/// solver checks, one for each value it could take.
+pub fn known_register_bits<B: BV, E: Borrow<Event<B>>>(
+    events: &[E],
+    solver: &mut Solver<B>,
+) -> HashMap<Name, KnownBits<B>> {
+    let written: HashSet<Name> = events
+        .iter()
+        .filter_map(|event| match event.borrow() {
+            Event::WriteReg(name, _, _, _) => Some(*name),
+            _ => None,
+        })
+        .collect();
+
+    let mut known = HashMap::new();
+    for (name, value) in final_register_values(events) {
+        if !written.contains(&name) {
+            continue;
+        }
+        let v = match value {
+            Val::Bits(bv) => {
+                known.insert(name, KnownBits::exact(bv));
+                continue;
+            }
+            Val::Symbolic(v) => v,
+            _ => continue,
+        };
+        let len = match solver.length(v) {
+            Some(len) if len <= B::MAX_WIDTH => len,
+            _ => continue,
+        };
+        let mut bits = KnownBits::unknown(len);
+        for i in 0..len {
+            let bit_is =
+                |b: bool| Exp::Eq(Box::new(Exp::Extract(i, i, Box::new(Exp::Var(v)))), Box::new(Exp::Bits(vec![b])));
+            let can_be_0 = solver.check_sat_with(&bit_is(false)) != SmtResult::Unsat;
+            let can_be_1 = solver.check_sat_with(&bit_is(true)) != SmtResult::Unsat;
+            if can_be_0 != can_be_1 {
+                bits = bits.set_slice(i, KnownBits::exact(B::new(can_be_1 as u64, 1))).unwrap()
+            }
+        }
+        known.insert(name, bits);
+    }
+    known
+}
+
+/// The events of a trace belonging to a single instruction, see
+/// [instruction_segments].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceSegment {
+    /// The range of the segment's events in the trace
+    pub events: Range<usize>,
+    /// The position of the segment's `Instr` event, if it has one
+    pub instr: Option<usize>,
+}
+
+/// Split a trace (in execution order) into the events for each
+/// instruction. Segment 0 holds the initialization before the first
+/// `Cycle` event, and is empty if the trace starts with one. Every
+/// other segment starts at a `Cycle` event, or at an `Instr` event
+/// when no `Cycle` has announced that instruction, so traces from
+/// models which only announce opcodes are still split. Every event
+/// belongs to exactly one segment.
+pub fn instruction_segments<B: BV, E: Borrow<Event<B>>>(events: &[E]) -> Vec<TraceSegment> {
+    let mut segments = vec![TraceSegment { events: 0..0, instr: None }];
+    for (i, event) in events.iter().enumerate() {
+        let current = segments.last_mut().unwrap();
+        let starts_segment = match event.borrow() {
+            Event::Cycle => true,
+            Event::Instr(_) => current.instr.is_some() || segments.len() == 1,
+            _ => false,
+        };
+        if starts_segment {
+            segments.push(TraceSegment { events: i..i, instr: None })
+        }
+        let current = segments.last_mut().unwrap();
+        if event.borrow().is_instr() && current.instr.is_none() {
+            current.instr = Some(i)
+        }
+        current.events.end = i + 1
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitvector::b64::B64;
+    use crate::smt::{Config, Context, DefAttrs};
+    use crate::source_loc::SourceLoc;
+
+    fn instr(opcode: u64) -> Event<B64> {
+        Event::Instr(Val::Bits(B64::new(opcode, 32)))
+    }
+
+    #[test]
+    fn segments_by_cycle_and_instr() {
+        let def = Event::Smt(
+            Def::DeclareConst(Sym::from_u32(0), smtlib::Ty::Bool),
+            DefAttrs::default(),
+            SourceLoc::unknown(),
+        );
+        let events: Vec<Event<B64>> =
+            vec![def.clone(), Event::Cycle, instr(1), def.clone(), Event::Cycle, def, instr(2), instr(3)];
+
+        let segments = instruction_segments(&events);
+        assert_eq!(
+            segments,
+            vec![
+                TraceSegment { events: 0..1, instr: None },
+                TraceSegment { events: 1..4, instr: Some(2) },
+                TraceSegment { events: 4..7, instr: Some(6) },
+                TraceSegment { events: 7..8, instr: Some(7) },
+            ]
+        );
+
+        let segments = instruction_segments::<B64, _>(&events[1..4]);
+        assert_eq!(segments[0], TraceSegment { events: 0..0, instr: None });
+        assert_eq!(segments.len(), 2);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed_traces_round_trip() {
+        let traces: Vec<Vec<Event<B64>>> = vec![vec![Event::Cycle, instr(1)], vec![instr(2)]];
+        let path = std::env::temp_dir().join(format!("isla-traces-{}.zst", std::process::id()));
+        save_traces(&traces, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(&ZSTD_MAGIC));
+        let loaded: Vec<Vec<Event<B64>>> = load_traces(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert!(loaded[0][0].is_cycle());
+        let parsed: Vec<Vec<Event<B64>>> = parse_traces(&bytes).unwrap();
+        assert_eq!(parsed[1].len(), 1);
+        std::fs::remove_file(&path).unwrap()
+    }
+
+    #[test]
+    fn deltas_compare_inlined_values() {
+        let (r0, r1, r2) = (Name::from_u32(0), Name::from_u32(1), Name::from_u32(2));
+        let (v0, v1) = (Sym::from_u32(0), Sym::from_u32(1));
+        let events: Vec<Event<B64>> = vec![
+            Event::ReadReg(r0, vec![], Val::Symbolic(v0), SourceLoc::unknown()),
+            Event::Smt(Def::DefineConst(v1, Exp::Var(v0)), DefAttrs::default(), SourceLoc::unknown()),
+            Event::WriteReg(r1, vec![], Val::Symbolic(v1), SourceLoc::unknown()),
+            Event::ReadReg(r1, vec![], Val::Symbolic(v1), SourceLoc::unknown()),
+        ];
+        let after: HashMap<Name, Val<B64>> =
+            [(r0, Val::Symbolic(v1)), (r1, Val::Symbolic(v1)), (r2, Val::Bits(B64::new(1, 8)))].into_iter().collect();
+
+        let deltas = register_deltas(&events, &RegisterBindings::new(), &after);
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].name, r1);
+        assert!(deltas[0].before.is_none());
+        assert_eq!(deltas[1].name, r2);
+        assert_eq!(deltas[1].after, Val::Bits(B64::new(1, 8)));
+    }
+
+    #[test]
+    fn known_bits_of_written_registers() {
+        let ctx = Context::new(Config::new());
+        let mut solver = Solver::<B64>::new(&ctx);
+        let x = solver.declare_const(smtlib::Ty::BitVec(4), SourceLoc::unknown());
+        let low = Exp::Extract(1, 0, Box::new(Exp::Var(x)));
+        solver.assert_eq(low, smtlib::bits64(1, 2));
+
+        let (r0, r1, r2) = (Name::from_u32(0), Name::from_u32(1), Name::from_u32(2));
+        let events: Vec<Event<B64>> = vec![
+            Event::ReadReg(r2, vec![], Val::Symbolic(x), SourceLoc::unknown()),
+            Event::WriteReg(r0, vec![], Val::Symbolic(x), SourceLoc::unknown()),
+            Event::WriteReg(r1, vec![], Val::Bits(B64::new(3, 2)), SourceLoc::unknown()),
+        ];
+        let known = known_register_bits(&events, &mut solver);
+        assert_eq!(known.len(), 2);
+        assert_eq!(known[&r0].to_string(), "#b??01");
+        assert_eq!(known[&r1].to_string(), "#b11");
+    }
+}
@@ -410,12 +410,12 @@ fn event_view<'ev, B: BV>(ev: &'ev Event<B>, opcode: B, shared_state: &SharedSta
                 .with_special("address", address)
                 .with_value(write_kind),
         ),
-        Event::Abstract { name: outcome_name, primitive, args, return_value } if *primitive => {
+        Event::Abstract { name: outcome_name, primitive, args, return_value, info: _ } if *primitive => {
             // This will be the original name of the outcome in the Sail source
             let outcome_name = zencode::decode(shared_state.symtab.to_str_demangled(*outcome_name));
             Some(View::new(opcode).with_name(outcome_name).with_values(args).with_special("return", return_value))
         }
-        Event::ReadReg(_, _, value) | Event::WriteReg(_, _, value) => Some(View::new(opcode).with_value(value)),
+        Event::ReadReg(_, _, value, _) | Event::WriteReg(_, _, value, _) => Some(View::new(opcode).with_value(value)),
         _ => None,
     }
 }
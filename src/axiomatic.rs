@@ -151,6 +151,7 @@ fn isla_main() -> i32 {
     opts.optopt("", "pc-limit", "Limit the number of times each instruction can be visited", "<n>");
     opts.optopt("", "pc-limit-mode", "What to do when the pc-limit is exceeded (default error)", "<error|discard>");
     opts.optopt("", "memory", "Add a max memory consumption (in megabytes)", "<n>");
+    opts::exploration_opts(&mut opts);
     opts.reqopt("m", "model", "Memory model in cat format", "<path>");
     opts.optflag("", "ifetch", "Generate ifetch events");
     opts.optflag("", "armv8-page-tables", "Automatically set up ARMv8 page tables");
@@ -209,7 +210,7 @@ fn isla_main() -> i32 {
 
     let mut hasher = Sha256::new();
     let (matches, orig_arch) = opts::parse::<B64>(&mut hasher, &opts);
-    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path } =
+    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path, progress } =
         opts::parse_with_arch(&mut hasher, &opts, &matches, &orig_arch);
     let use_model_reg_init = !matches.opt_present("no-model-reg-init");
 
@@ -221,6 +222,7 @@ fn isla_main() -> i32 {
         type_info: ftype_info,
         isa_config: _,
         source_path: _,
+        progress: _,
     } = opts::parse_with_arch(&mut hasher, &opts, &matches, &orig_arch);
 
     let iarch = initialize_architecture(
@@ -295,6 +297,10 @@ fn isla_main() -> i32 {
         }
     };
 
+    // These options are shared with isla-footprint, and control the
+    // symbolic execution of each thread
+    let exploration = opts::parse_exploration_opts(&matches, &iarch.shared_state.symtab);
+
     let graph_flatten = matches.opt_present("graph-flatten");
     let graph_dbg_info = matches.opt_present("graph-debug");
     let graph_human_readable = matches.opt_present("graph-human-readable");
@@ -492,6 +498,7 @@ fn isla_main() -> i32 {
             let check_sat_using = check_sat_using.as_deref();
             let isla_litmus_path = isla_litmus_path.as_ref();
             let litmus_translator_path = litmus_translator_path.as_ref();
+            let exploration = &exploration;
 
             scope.spawn(move || {
                 for (i, litmus_file) in GroupIndex::new(tests, group_id, thread_groups).enumerate() {
@@ -605,6 +612,11 @@ fn isla_main() -> i32 {
                         armv8_page_tables,
                         merge_translations,
                         remove_uninteresting_translates,
+                        watchpoints: exploration.watchpoints.clone(),
+                        loop_bound: exploration.loop_bound,
+                        function_loop_bounds: exploration.function_loop_bounds.clone(),
+                        merge_paths: exploration.merge_paths,
+                        progress,
                     };
 
                     let mut graph_show_regs: HashSet<String> =
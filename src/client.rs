@@ -228,11 +228,15 @@ fn isla_main() -> i32 {
 
     let mut hasher = Sha256::new();
     let (matches, arch) = opts::parse(&mut hasher, &opts);
-    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path: _ } =
+    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path: _, progress } =
         opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
+    if progress.is_some() {
+        eprintln!("--progress is not supported by isla-client");
+        return 1;
+    }
     let use_model_reg_init = !matches.opt_present("no-model-reg-init");
 
-    let Initialized { regs, lets, shared_state } = initialize_architecture(
+    let Initialized { regs, lets, shared_state, .. } = initialize_architecture(
         &mut arch,
         symtab,
         type_info,
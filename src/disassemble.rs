@@ -0,0 +1,81 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Annotates opcodes with their disassembly, for the instruction set
+//! named by the `disassembler` key in the configuration. This uses
+//! the Capstone library, so it is only available when isla is built
+//! with the `disassemble` feature. Otherwise [disassemble] always
+//! returns `None`.
+
+/// Disassemble the bytes of a single instruction, in memory order,
+/// returning its mnemonic and operands. Returns `None` if the
+/// instruction set is not supported or the bytes do not decode.
+#[cfg(feature = "disassemble")]
+pub fn disassemble(bytes: &[u8], disassembler: &str, little_endian: bool) -> Option<String> {
+    use capstone::{Arch, Capstone, Endian, Mode, NO_EXTRA_MODE};
+
+    let (arch, mode) = match disassembler {
+        "aarch64" => (Arch::ARM64, Mode::Arm),
+        "arm" => (Arch::ARM, Mode::Arm),
+        "thumb" => (Arch::ARM, Mode::Thumb),
+        "x86" => (Arch::X86, Mode::Mode32),
+        "x86_64" => (Arch::X86, Mode::Mode64),
+        "mips32" => (Arch::MIPS, Mode::Mips32),
+        "mips64" => (Arch::MIPS, Mode::Mips64),
+        "ppc32" => (Arch::PPC, Mode::Mode32),
+        "ppc64" => (Arch::PPC, Mode::Mode64),
+        _ => return None,
+    };
+    // x86 has no big-endian mode
+    let endian = if arch == Arch::X86 {
+        None
+    } else if little_endian {
+        Some(Endian::Little)
+    } else {
+        Some(Endian::Big)
+    };
+
+    let cs = Capstone::new_raw(arch, mode, NO_EXTRA_MODE, endian).ok()?;
+    let instrs = cs.disasm_count(bytes, 0, 1).ok()?;
+    let instr = instrs.iter().next()?;
+    // Only annotate opcodes which decode as exactly one instruction
+    if instr.bytes().len() != bytes.len() {
+        return None;
+    }
+    match (instr.mnemonic(), instr.op_str()) {
+        (Some(mnemonic), Some(operands)) if !operands.is_empty() => Some(format!("{} {}", mnemonic, operands)),
+        (Some(mnemonic), _) => Some(mnemonic.to_string()),
+        (None, _) => None,
+    }
+}
+
+#[cfg(not(feature = "disassemble"))]
+pub fn disassemble(_bytes: &[u8], _disassembler: &str, _little_endian: bool) -> Option<String> {
+    None
+}
@@ -47,7 +47,7 @@ use isla_lib::ir::*;
 use isla_lib::ir_lexer::new_ir_lexer;
 use isla_lib::smt;
 use isla_lib::smt::smtlib::Exp;
-use isla_lib::smt::{Event, Model, SmtResult, Solver};
+use isla_lib::smt::{Event, EventOrder, Model, SmtResult, Solver};
 use isla_lib::source_loc::SourceLoc;
 use isla_lib::value_parser::ValParser;
 use isla_lib::zencode;
@@ -87,10 +87,11 @@ fn isla_main() -> i32 {
     );
     opts.optopt("", "timeout", "Add a timeout (in seconds)", "<n>");
     opts.optflag("", "executable", "make trace executable");
+    opts::exploration_opts(&mut opts);
 
     let mut hasher = Sha256::new();
     let (matches, arch) = opts::parse::<B129>(&mut hasher, &opts);
-    let CommonOpts { num_threads, mut arch, mut symtab, type_info, isa_config, source_path: _ } =
+    let CommonOpts { num_threads, mut arch, mut symtab, type_info, isa_config, source_path: _, progress } =
         opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
 
     let timeout: Option<u64> = match matches.opt_get("timeout") {
@@ -118,7 +119,7 @@ fn isla_main() -> i32 {
 
     let use_model_reg_init = !matches.opt_present("no-model-reg-init");
 
-    let Initialized { regs, lets, shared_state } =
+    let Initialized { regs, lets, shared_state, .. } =
         initialize_architecture(&mut arch, symtab, type_info, &isa_config, assertion_mode, use_model_reg_init);
 
     let kill_conditions = StopConditions::parse(matches.opt_strs("kill-at"), &shared_state, StopAction::Kill);
@@ -159,7 +160,8 @@ fn isla_main() -> i32 {
     let smt_ctx = smt::Context::new(smt_cfg);
     let mut solver = Solver::new(&smt_ctx);
 
-    let task_state = TaskState::new();
+    let exploration = opts::parse_exploration_opts(&matches, &shared_state.symtab);
+    let task_state = exploration.task_state(TaskState::new().with_progress(progress));
 
     frame.add_lets(&lets).add_regs(&regs);
 
@@ -197,8 +199,11 @@ fn isla_main() -> i32 {
             simplify::commute_extract(&mut events);
             simplify::eval(&mut events);
         }
-        let events: Vec<Event<B129>> = events.drain(..).rev().collect();
-        let write_opts = WriteOpts { define_enum: !matches.opt_present("simplify"), ..WriteOpts::default() };
+        let write_opts = WriteOpts {
+            define_enum: !matches.opt_present("simplify"),
+            events_order: simplify::SIMPLIFY_ORDER,
+            ..WriteOpts::default()
+        };
         simplify::write_events_with_opts(handle, &events, &shared_state, &write_opts).unwrap();
     };
 
@@ -208,11 +213,11 @@ fn isla_main() -> i32 {
     loop {
         match queue.pop() {
             Some(Ok((_, result, mut events))) if tree => {
-                events.insert(0, Event::WriteReg(final_result_register, vec![], result.clone()));
+                events.insert(0, Event::WriteReg(final_result_register, vec![], result.clone(), SourceLoc::unknown()));
                 let stdout = std::io::stdout();
                 let mut handle = stdout.lock();
                 writeln!(handle, "Result: {}", result.to_string(&shared_state)).unwrap();
-                let events: Vec<Event<B129>> = events.drain(..).rev().collect();
+                executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
                 if let Some(ref mut evtree) = evtree {
                     evtree.add_events(&events)
                 } else {
@@ -220,7 +225,7 @@ fn isla_main() -> i32 {
                 }
             }
             Some(Ok((_, result, mut events))) => {
-                events.insert(0, Event::WriteReg(final_result_register, vec![], result.clone()));
+                events.insert(0, Event::WriteReg(final_result_register, vec![], result.clone(), SourceLoc::unknown()));
                 let stdout = std::io::stdout();
                 let mut handle = stdout.lock();
                 writeln!(handle, "Result: {}", result.to_string(&shared_state)).unwrap();
@@ -28,41 +28,67 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use crossbeam::queue::SegQueue;
+use getopts::Matches;
+use serde_json::json;
 use sha2::{Digest, Sha256};
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs::File;
 use std::io::{BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use toml;
 
-use isla_axiomatic::footprint_analysis::footprint_analysis;
-use isla_axiomatic::litmus::assemble_instruction;
+use isla::io;
+use isla_axiomatic::footprint_analysis::{
+    check_footprint, conditional_effects, encoding_constraints, exception_conditions, footprint_analysis,
+    pair_dependencies, store_to_load_forwarding, ConditionalEffect, Effect, EncodingConstraint, EncodingOutcome,
+    Footprint, Forwarding, ForwardingPair, PairDependencies,
+};
+use isla_axiomatic::litmus::{assemble_instruction, assemble_sequence};
 use isla_axiomatic::page_table;
 use isla_axiomatic::page_table::setup::PageTableSetup;
 use isla_elf::arch::AArch64;
 use isla_elf::elf;
 use isla_elf::relocation_types::SymbolicRelocation;
-use isla_lib::bitvector::{b129::B129, BV};
-use isla_lib::error::IslaError;
+use isla_lib::abstract_interp::{abstract_footprint, AbsVal, KnownBits};
+use isla_lib::bitvector::{b129::B129, bzhi_u64, BV};
+use isla_lib::config::{AlignmentCheck, ISAConfig};
+use isla_lib::error::{ExecError, IslaError};
 use isla_lib::executor;
-use isla_lib::executor::{LocalFrame, StopAction, StopConditions, TaskState};
-use isla_lib::init::{initialize_architecture, InitArchWithConfig};
+use isla_lib::executor::{
+    Backtrace, Collector, FramePath, LocalFrame, Progress, StopAction, StopConditions, Strategy, TaskState, TraceError,
+    TraceQueue,
+};
+use isla_lib::init::{initialize_architecture, InitArchWithConfig, LetStatus};
+use isla_lib::ir::dead_code::{boolean_lets, dead_code};
+use isla_lib::ir::static_footprint::{call_tree, static_footprint};
 use isla_lib::ir::*;
 use isla_lib::log;
-use isla_lib::memory::Memory;
-use isla_lib::register::Register;
+use isla_lib::memory::{AccessAssertion, Endianness, Memory, ReadResolver, RegionCallbacks};
+use isla_lib::postprocess::{self, PostProcessors};
+use isla_lib::primop_util::smt_value;
+use isla_lib::register::{Register, RegisterBindings};
 use isla_lib::simplify;
-use isla_lib::simplify::{EventTree, WriteOpts};
+use isla_lib::simplify::{EventKind, EventPattern, EventTree, WriteOpts, WriteVar};
 use isla_lib::smt;
-use isla_lib::smt::{smtlib, Checkpoint, EvPath, Event, Solver};
+use isla_lib::smt::smtlib::bits64;
+use isla_lib::smt::{smtlib, Checkpoint, CheckpointCache, EvPath, Event, EventOrder, SmtResult, Solver, Sym};
 use isla_lib::smt_parser;
 use isla_lib::source_loc::SourceLoc;
+use isla_lib::trace;
 use isla_lib::zencode;
 
+mod disassemble;
+mod gdb;
+mod golden;
+mod manifest;
 mod opts;
+use manifest::{Manifest, Outcome};
 use opts::CommonOpts;
 
 fn main() {
@@ -98,67 +124,774 @@ fn instruction_to_string<B: BV>(opcode: &[InstructionSegment<B>]) -> String {
     s
 }
 
+/// Footprint functions either take the opcode or, for set-up and
+/// tear-down functions, a single unit argument.
+fn function_arg<B: BV>(args: &[(Name, &Ty<Name>)], opcode_val: &Val<B>) -> Val<B> {
+    match args {
+        [(_, Ty::Unit)] => Val::Unit,
+        _ => opcode_val.clone(),
+    }
+}
+
+/// Parse a partial instruction, consisting of whitespace separated
+/// binary segments and `name:size` symbolic segments. Binary segments
+/// may contain `x` for don't-care bits, which are fixed to zero rather
+/// than made symbolic, so they add no paths.
+fn parse_partial_instruction<B: BV>(instruction: &str) -> Result<Vec<InstructionSegment<B>>, String> {
+    instruction
+        .split_ascii_whitespace()
+        .map(|s| {
+            let bits =
+                if s.chars().all(|c| c == '0' || c == '1' || c == 'x') { s.replace('x', "0") } else { s.to_string() };
+            B::from_str(&format!("0b{}", bits))
+                .map(InstructionSegment::Concrete)
+                .or_else(|| {
+                    let mut it = s.split(':');
+                    let name = it.next()?;
+                    let size = it.next()?;
+                    size.parse().ok().map(|size| InstructionSegment::Symbolic(name.to_string(), size))
+                })
+                .ok_or_else(|| format!("Unable to parse instruction segment {}", s))
+        })
+        .collect()
+}
+
+/// The variables standing for the symbolic segments of an
+/// instruction, in the order they first appear
+type SegmentVars = Vec<(String, u32, Sym)>;
+
+fn print_segments(segments: &SegmentVars) {
+    print!("(segments");
+    for (name, size, v) in segments {
+        print!("\n  (|{}| {} v{})", name, size, v)
+    }
+    println!(")")
+}
+
+/// Names to print for the variables of symbolic segments. A name which
+/// stands for more than one variable, such as a segment of both `-i`
+/// and `--forward-load` which is not shared, is left out so its
+/// variables are printed as usual.
+fn segment_names(segments: &[(String, u32, Sym)]) -> HashMap<Sym, String> {
+    let mut vars: HashMap<&String, HashSet<Sym>> = HashMap::new();
+    for (name, _, v) in segments {
+        vars.entry(name).or_default().insert(*v);
+    }
+    vars.into_iter()
+        .filter_map(|(name, vs)| match vs.into_iter().collect::<Vec<_>>()[..] {
+            [v] => Some((v, name.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replay the SMT definitions from a path with contradictory
+/// assertions, in execution order, and find an unsat core of those
+/// assertions. Assertions from instruction constraints are given as
+/// the user wrote them, and others as they are written in traces.
+fn unsat_core<B: BV>(
+    events: &[Event<B>],
+    asserted: &[(String, smtlib::Exp<Sym>)],
+    shared_state: &SharedState<B>,
+) -> Option<Vec<String>> {
+    let write_exp = |exp: &smtlib::Exp<Sym>| {
+        let mut buf = Vec::new();
+        simplify::write_smt_exp(&mut buf, exp, shared_state, &WriteOpts::default()).unwrap();
+        String::from_utf8_lossy(&buf).into_owned()
+    };
+    let constraints: Vec<(&String, String)> =
+        asserted.iter().map(|(constraint, exp)| (constraint, write_exp(exp))).collect();
+
+    let solver_cfg = smt::Config::new();
+    let solver_ctx = smt::Context::new(solver_cfg);
+    let mut solver = Solver::<B>::new(&solver_ctx);
+    let mut assertions = Vec::new();
+    let mut definitions = HashMap::new();
+    for event in events {
+        match event {
+            Event::Smt(smtlib::Def::Assert(exp), _, _) => assertions.push(exp),
+            Event::Smt(def, _, _) => {
+                if let smtlib::Def::DefineConst(v, exp) = def {
+                    definitions.insert(*v, exp);
+                }
+                solver.add(def.clone())
+            }
+            _ => (),
+        }
+    }
+    let assertions: Vec<smtlib::Exp<Sym>> = assertions.into_iter().cloned().collect();
+
+    let mut core = Vec::new();
+    for i in solver.unsat_core(&assertions)? {
+        let exp = write_exp(&assertions[i]);
+        let assertion = match constraints.iter().find(|(_, constraint_exp)| *constraint_exp == exp) {
+            Some((constraint, _)) => format!("(instruction-constraint {})", constraint),
+            None => {
+                // Branch conditions are usually asserted via a defined
+                // variable, so show what that variable stands for
+                let mut exp = &assertions[i];
+                while let Some(definition) = match exp {
+                    smtlib::Exp::Var(v) => definitions.get(v),
+                    _ => None,
+                } {
+                    exp = definition
+                }
+                format!("(assert {})", write_exp(exp))
+            }
+        };
+        if !core.contains(&assertion) {
+            core.push(assertion)
+        }
+    }
+    Some(core)
+}
+
+type DeadPaths<B> = SegQueue<Vec<Event<B>>>;
+
+/// A collector like `executor::trace_collector`, which also keeps the
+/// events of each path that ended because its assertions became
+/// contradictory, in execution order.
+fn dead_path_collector<'ir, B: BV>(dead_paths: Arc<DeadPaths<B>>) -> Box<Collector<'ir, B, TraceQueue<B>>> {
+    Box::new(move |tid, task_id, result, shared_state, solver, collected| {
+        if let Err((ExecError::Dead, _)) = result {
+            dead_paths.push(solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect())
+        }
+        executor::trace_collector(tid, task_id, result, shared_state, solver, collected)
+    })
+}
+
+/// Find the distinct unsat cores of the contradictory paths, sorted
+/// so the output does not depend on the order paths finished in.
+fn dead_path_cores<B: BV>(
+    dead_paths: &DeadPaths<B>,
+    asserted: &[(String, smtlib::Exp<Sym>)],
+    shared_state: &SharedState<B>,
+) -> Vec<Vec<String>> {
+    let mut cores = Vec::new();
+    while let Some(events) = dead_paths.pop() {
+        if let Some(core) = unsat_core(&events, asserted, shared_state) {
+            if !cores.contains(&core) {
+                cores.push(core)
+            }
+        }
+    }
+    cores.sort();
+    cores
+}
+
+type EncodingPaths<B> = SegQueue<(EvPath<B>, Option<String>)>;
+
+/// A collector for `--encoding-constraints`, which keeps the events
+/// of every feasible path in execution order, including those that
+/// end in an error, along with the error message.
+fn encoding_collector<'ir, B: BV>(
+    _: usize,
+    _: usize,
+    result: Result<(Val<B>, LocalFrame<'ir, B>), (ExecError, Backtrace)>,
+    _: &SharedState<'ir, B>,
+    mut solver: Solver<B>,
+    collected: &EncodingPaths<B>,
+) {
+    let error = match result {
+        Ok(_) | Err((ExecError::Exit, _)) => None,
+        Err((ExecError::Dead, _)) => return,
+        Err((err, _)) if solver.check_sat() == SmtResult::Sat => Some(err.to_string()),
+        Err(_) => return,
+    };
+    collected.push((solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect(), error))
+}
+
+type KnownBitsPaths<B> = SegQueue<Result<HashMap<Name, KnownBits<B>>, String>>;
+
+/// A collector for `--known-bits`, which finds the bits of each
+/// register written by a path that are fixed by its constraints,
+/// while the path's solver is still available.
+fn known_bits_collector<'ir, B: BV>(
+    _: usize,
+    _: usize,
+    result: Result<(Val<B>, LocalFrame<'ir, B>), (ExecError, Backtrace)>,
+    _: &SharedState<'ir, B>,
+    mut solver: Solver<B>,
+    collected: &KnownBitsPaths<B>,
+) {
+    match result {
+        Ok(_) | Err((ExecError::Exit, _)) => {
+            let events: Vec<Event<B>> = solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect();
+            collected.push(Ok(trace::known_register_bits(&events, &mut solver)))
+        }
+        Err((ExecError::Dead, _)) => (),
+        Err((err, _)) => collected.push(Err(err.to_string())),
+    }
+}
+
+/// The result of checking the `--assert-final` constraints at the end
+/// of a path: `None` if they cannot hold, otherwise the value of each
+/// register they mention in a model.
+type FinalAssertionPaths = SegQueue<Result<Option<Vec<(String, String)>>, String>>;
+
+/// The value of a register location, given the final value of each
+/// register from a trace (see [trace::final_register_values]).
+fn final_loc_value<B: BV>(loc: &Loc<Name>, values: &HashMap<Name, Val<B>>) -> Option<Val<B>> {
+    match loc {
+        Loc::Id(id) => values.get(id).cloned(),
+        Loc::Field(loc, field) => match final_loc_value(loc, values)? {
+            Val::Struct(members) => members.get(field).cloned(),
+            _ => None,
+        },
+        Loc::Addr(_) => None,
+    }
+}
+
+/// A collector for `--assert-final`, which checks whether the given
+/// constraints over registers can hold at the end of each path. A
+/// path which ends by exiting has no final frame, so its register
+/// values are taken from the trace, and only the registers it
+/// accessed can be constrained.
+fn final_assertion_collector<'ir, B: BV>(
+    constraints: Arc<Vec<smtlib::Exp<Loc<String>>>>,
+) -> Box<Collector<'ir, B, FinalAssertionPaths>> {
+    Box::new(move |_, _, result, shared_state, mut solver, collected| {
+        let info = SourceLoc::command_line();
+
+        // Each register mentioned by the constraints is looked up
+        // along with them, so its value can be read from the model
+        let mut registers: Vec<Loc<String>> = Vec::new();
+        for constraint in constraints.iter() {
+            let _ = constraint.map_var(&mut |loc| {
+                if !registers.contains(loc) {
+                    registers.push(loc.clone())
+                }
+                Ok::<_, ()>(smtlib::Exp::Var(()))
+            });
+        }
+        let queries: Vec<smtlib::Exp<Loc<String>>> =
+            constraints.iter().cloned().chain(registers.iter().cloned().map(smtlib::Exp::Var)).collect();
+
+        let exps: Result<Vec<smtlib::Exp<Sym>>, String> = match result {
+            Ok((_, mut frame)) => queries
+                .iter()
+                .map(|query| {
+                    executor::constraint_exp(query, &mut frame, shared_state, &mut solver, info)
+                        .map_err(|e| e.to_string())
+                })
+                .collect(),
+            Err((ExecError::Exit, _)) => {
+                let events: Vec<Event<B>> =
+                    solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect();
+                let values = trace::final_register_values(&events);
+                queries
+                    .iter()
+                    .map(|query| {
+                        query.map_var(&mut |loc| {
+                            let value = shared_state
+                                .symtab
+                                .get_loc(loc)
+                                .and_then(|loc| final_loc_value(&loc, &values))
+                                .ok_or_else(|| format!("{} is not accessed by a path which exits", loc))?;
+                            smt_value(&value, info).map_err(|e| e.to_string())
+                        })
+                    })
+                    .collect()
+            }
+            Err((ExecError::Dead, _)) => return,
+            Err((err, _)) => {
+                collected.push(Err(err.to_string()));
+                return;
+            }
+        };
+        let mut exps = match exps {
+            Ok(exps) => exps,
+            Err(msg) => {
+                collected.push(Err(msg));
+                return;
+            }
+        };
+
+        let register_exps = exps.split_off(constraints.len());
+        solver.assert_all(exps);
+        match solver.check_sat() {
+            SmtResult::Sat => {
+                let mut model = smt::Model::new(&solver);
+                model.set_complete_model(true);
+                let values = registers
+                    .iter()
+                    .zip(register_exps.iter())
+                    .map(|(loc, exp)| {
+                        let value = match model.get_exp(exp) {
+                            Ok(Some(value)) => {
+                                let mut buf = Vec::new();
+                                simplify::write_smt_exp(&mut buf, &value, shared_state, &WriteOpts::default()).unwrap();
+                                String::from_utf8_lossy(&buf).into_owned()
+                            }
+                            _ => "?".to_string(),
+                        };
+                        (loc.to_string(), value)
+                    })
+                    .collect();
+                collected.push(Ok(Some(values)))
+            }
+            SmtResult::Unsat => collected.push(Ok(None)),
+            SmtResult::Unknown => collected.push(Err("Solver returned unknown for --assert-final".to_string())),
+        }
+    })
+}
+
+/// The result of `--find-effect` for each path: if the path has the
+/// effect, the opcodes found which take it.
+type EffectPaths<B> = SegQueue<Result<Option<Vec<B>>, String>>;
+
+/// A collector for `--find-effect`, which checks whether each path has
+/// an event matching every pattern, and if so finds up to `witnesses`
+/// opcodes which take that path. Only the events from the first
+/// `(cycle)` onwards are matched, so the effects of initialization
+/// are not counted.
+fn effect_collector<'ir, B: BV>(
+    patterns: Arc<Vec<EventPattern>>,
+    opcode_val: Val<B>,
+    witnesses: usize,
+) -> Box<Collector<'ir, B, EffectPaths<B>>> {
+    Box::new(move |_, _, result, _, mut solver, collected| {
+        match result {
+            Ok(_) | Err((ExecError::Exit, _)) => (),
+            Err((ExecError::Dead, _)) => return,
+            Err((err, _)) => {
+                collected.push(Err(err.to_string()));
+                return;
+            }
+        }
+        let events = solver.trace().to_vec_in(EventOrder::Execution);
+        let start = events.iter().position(|event| matches!(event, Event::Cycle)).unwrap_or(0);
+        if patterns.iter().all(|pattern| events[start..].iter().any(|event| pattern.matches(event))) {
+            drop(events);
+            collected.push(enumerate_opcodes(&opcode_val, Some(witnesses), &mut solver).map(Some))
+        } else {
+            collected.push(Ok(None))
+        }
+    })
+}
+
+/// Parse a constraint given on the command line, checking that each
+/// location it refers to is a register in the architecture.
+fn parse_register_constraint(constraint: &str, symtab: &Symtab) -> Result<smtlib::Exp<Loc<String>>, String> {
+    let exp = smt_parser::ExpParser::new().parse(constraint).map_err(|e| e.to_string())?;
+    exp.map_var(&mut |loc| match symtab.get_loc(loc) {
+        Some(_) => Ok(smtlib::Exp::Var(())),
+        None => Err(format!("{} is not a register in the architecture", loc)),
+    })?;
+    Ok(exp)
+}
+
+/// Write the constraints found by `--encoding-constraints` as strings
+/// which can be passed to `--instruction-constraint`. Constraints from
+/// paths which only differ in branches on other values are merged.
+fn encoding_constraint_strings<B: BV>(constraint: &EncodingConstraint, shared_state: &SharedState<B>) -> Vec<String> {
+    let mut conditions: Vec<String> = constraint
+        .conditions
+        .iter()
+        .map(|exp| {
+            let mut buf = Vec::new();
+            simplify::write_smt_exp(&mut buf, exp, shared_state, &WriteOpts::default()).unwrap();
+            String::from_utf8_lossy(&buf).into_owned()
+        })
+        .collect();
+    conditions.sort();
+    conditions.dedup();
+    conditions
+}
+
+fn write_encoding_constraint(
+    buf: &mut dyn Write,
+    outcome: &EncodingOutcome,
+    conditions: &[String],
+) -> std::io::Result<()> {
+    write!(buf, "(encoding ")?;
+    match outcome {
+        EncodingOutcome::Decoded => write!(buf, "(decoded)")?,
+        EncodingOutcome::Exception(kind) => write!(buf, "(exception |{}|)", kind)?,
+        EncodingOutcome::Error(msg) => write!(buf, "(error {:?})", msg)?,
+    }
+    for condition in conditions {
+        write!(buf, "\n  {:?}", condition)?
+    }
+    writeln!(buf, ")")
+}
+
+fn encoding_constraint_json(outcome: &EncodingOutcome, conditions: &[String]) -> serde_json::Value {
+    let mut value = match outcome {
+        EncodingOutcome::Decoded => json!({ "outcome": "decoded" }),
+        EncodingOutcome::Exception(kind) => json!({ "outcome": "exception", "kind": kind }),
+        EncodingOutcome::Error(msg) => json!({ "outcome": "error", "message": msg }),
+    };
+    value["constraints"] = json!(conditions);
+    value
+}
+
+fn print_unsat_core(core: &[String]) {
+    print!("(unsat-core");
+    for assertion in core {
+        print!("\n  {}", assertion)
+    }
+    println!(")")
+}
+
+/// Symbolic segments are local to each instruction, unless their
+/// name has been declared as shared, in which case every instruction
+/// using that name refers to the same variable in `shared`. Unless
+/// the instruction is a single concrete opcode, the variables for its
+/// segments are also returned. Each constraint is pushed onto
+/// `asserted` alongside the SMT expression it was asserted as.
 fn instruction_to_val<B: BV>(
     opcode: &[InstructionSegment<B>],
     constraints: &[String],
+    shared: &HashMap<String, (u32, Sym)>,
+    asserted: &mut Vec<(String, smtlib::Exp<Sym>)>,
     solver: &mut Solver<B>,
-) -> Val<B> {
+) -> Result<(Val<B>, Option<SegmentVars>), String> {
     match opcode {
-        [InstructionSegment::Concrete(bv)] => Val::Bits(*bv),
+        [InstructionSegment::Concrete(bv)] => Ok((Val::Bits(*bv), None)),
         _ => {
-            print!("(segments");
-            let mut var_map = HashMap::new();
-            let val = Val::MixedBits(
-                opcode
-                    .iter()
-                    .map(|segment| match segment {
-                        InstructionSegment::Concrete(bv) => BitsSegment::Concrete(*bv),
-                        InstructionSegment::Symbolic(name, size) => {
-                            if let Some((size2, v)) = var_map.get(name) {
-                                if size == size2 {
-                                    BitsSegment::Symbolic(*v)
-                                } else {
-                                    panic!(
-                                        "{} appears in instruction with different sizes, {} and {}",
-                                        name, size, size2
-                                    )
-                                }
-                            } else {
-                                let v = solver.declare_const(smtlib::Ty::BitVec(*size), SourceLoc::unknown());
-                                print!("\n  (|{}| {} v{})", name, size, v);
+            let mut segments = Vec::new();
+            let mut var_map: HashMap<&String, (u32, Sym)> = HashMap::new();
+            let mut bits = Vec::new();
+            for segment in opcode {
+                match segment {
+                    InstructionSegment::Concrete(bv) => bits.push(BitsSegment::Concrete(*bv)),
+                    InstructionSegment::Symbolic(name, size) => {
+                        if let Some((size2, v)) = var_map.get(name).or_else(|| shared.get(name)).copied() {
+                            if *size != size2 {
+                                return Err(format!(
+                                    "{} appears in instruction with different sizes, {} and {}",
+                                    name, size, size2
+                                ));
+                            }
+                            if !var_map.contains_key(name) {
+                                segments.push((name.clone(), *size, v));
                                 var_map.insert(name, (*size, v));
-                                BitsSegment::Symbolic(v)
                             }
+                            bits.push(BitsSegment::Symbolic(v))
+                        } else {
+                            let v = solver.declare_const(smtlib::Ty::BitVec(*size), SourceLoc::unknown());
+                            segments.push((name.clone(), *size, v));
+                            var_map.insert(name, (*size, v));
+                            bits.push(BitsSegment::Symbolic(v))
                         }
-                    })
-                    .collect(),
-            );
-            println!(")");
+                    }
+                }
+            }
             for constraint in constraints {
                 let mut lookup = |loc: &Loc<String>| match loc {
-                    Loc::Id(name) => match var_map.get(&zencode::decode(name)) {
-                        Some((_size, v)) => Ok(smtlib::Exp::Var(*v)),
-                        None => Err(format!("No variable {} in constraint", name)),
-                    },
+                    Loc::Id(name) => {
+                        match var_map.get(&zencode::decode(name)).or_else(|| shared.get(&zencode::decode(name))) {
+                            Some((_size, v)) => Ok(smtlib::Exp::Var(*v)),
+                            None => Err(format!("No variable {} in constraint", name)),
+                        }
+                    }
                     _ => Err(format!("Only names can appear in instruction constraints, not {}", loc)),
                 };
-                let assertion = smt_parser::ExpParser::new().parse(&constraint).expect("Bad instruction constraint");
-                solver.add_event(Event::Assume(assertion.clone()));
-                let assertion_exp = assertion.map_var(&mut lookup).expect("Bad instruction constraint");
+                let assertion = smt_parser::ExpParser::new()
+                    .parse(constraint)
+                    .map_err(|e| format!("Bad instruction constraint {}: {}", constraint, e))?;
+                let assertion_exp = assertion
+                    .map_var(&mut lookup)
+                    .map_err(|e| format!("Bad instruction constraint {}: {}", constraint, e))?;
+                solver.add_event(Event::Assume(assertion));
+                asserted.push((constraint.clone(), assertion_exp.clone()));
                 solver.add(smtlib::Def::Assert(assertion_exp));
             }
-            val
+            Ok((Val::MixedBits(bits), Some(segments)))
+        }
+    }
+}
+
+/// Find up to `limit` concrete opcodes for a partial instruction
+/// which satisfy its constraints. Each opcode is blocked once found,
+/// so the next satisfiability check must give a different one.
+fn enumerate_opcodes<B: BV>(
+    opcode_val: &Val<B>,
+    limit: Option<usize>,
+    solver: &mut Solver<B>,
+) -> Result<Vec<B>, String> {
+    use smtlib::Exp::*;
+    let segments = match opcode_val {
+        Val::Bits(bv) => return Ok(vec![*bv]),
+        Val::MixedBits(segments) => segments,
+        _ => return Err(format!("Cannot enumerate opcode {:?}", opcode_val)),
+    };
+    let opcode_exp = segments
+        .iter()
+        .map(|segment| match segment {
+            BitsSegment::Concrete(bv) => smt_value(&Val::Bits(*bv), SourceLoc::unknown()).unwrap(),
+            BitsSegment::Symbolic(v) => Var(*v),
+        })
+        .reduce(|hi, lo| Concat(Box::new(hi), Box::new(lo)))
+        .ok_or_else(|| "Cannot enumerate an empty instruction".to_string())?;
+
+    let mut opcodes = Vec::new();
+    while limit.map_or(true, |limit| opcodes.len() < limit) {
+        match solver.check_sat() {
+            SmtResult::Sat => (),
+            SmtResult::Unsat => break,
+            SmtResult::Unknown => return Err("Solver returned unknown while enumerating opcodes".to_string()),
+        }
+        let opcode = {
+            // Segments without constraints may be missing from the model
+            let mut model = smt::Model::new(solver);
+            model.set_complete_model(true);
+            match model.get_exp(&opcode_exp) {
+                Ok(Some(Bits64(bv))) => B::new(bv.lower_u64(), bv.len()),
+                Ok(exp) => return Err(format!("Could not find a concrete opcode in the model, got {:?}", exp)),
+                Err(e) => return Err(e.to_string()),
+            }
+        };
+        let block = smt_value(&Val::Bits(opcode), SourceLoc::unknown()).unwrap();
+        solver.assert(Neq(Box::new(opcode_exp.clone()), Box::new(block)));
+        opcodes.push(opcode)
+    }
+    Ok(opcodes)
+}
+
+/// Why an instruction could not be set up to run
+enum SetupError {
+    /// The instruction or `--assume` constraints are unsatisfiable.
+    /// With `--unsat-core`, the core of the instruction constraints is
+    /// included if one is found.
+    Unsat(String, Option<Vec<String>>),
+    Failed(String),
+}
+
+/// An instruction ready to run from the initial state
+struct PreparedInstruction<'ir> {
+    opcode_val: Val<B129>,
+    /// The instruction constraints, by name, asserted on its segments
+    asserted: Vec<(String, smtlib::Exp<Sym>)>,
+    /// The variable controlling alignment checks, when they are symbolic
+    alignment_var: Option<Sym>,
+    reset_registers: HashMap<Loc<Name>, Reset<B129>>,
+    frame: LocalFrame<'ir, B129>,
+    checkpoint: Checkpoint<B129>,
+}
+
+/// The initial state each instruction is run from, and how its paths
+/// are explored. The instruction given by -i and each instruction run
+/// by --stdin, --test, or --enumerate-footprint are all set up by this.
+struct InstructionSetup<'ir, 'a> {
+    shared_state: &'a SharedState<'ir, B129>,
+    isa_config: &'a ISAConfig<B129>,
+    function_id: Name,
+    regs: &'a RegisterBindings<'ir, B129>,
+    lets: &'a Bindings<'ir, B129>,
+    memory: Memory<B129>,
+    checkpoint: Checkpoint<B129>,
+    reset_registers: HashMap<Loc<Name>, Reset<B129>>,
+    alignment_check: Option<(AlignmentCheck, Reset<B129>, String)>,
+    unsat_core: bool,
+    exploration: &'a opts::ExplorationOpts,
+    guard_functions: HashSet<Name>,
+    decode_functions: HashSet<Name>,
+    audit_undefined: bool,
+    trace_budget: Option<usize>,
+    concurrent_forks: bool,
+    time_slice: Option<Duration>,
+    progress: Option<Progress>,
+    checkpoint_cache: Option<Arc<CheckpointCache<B129>>>,
+}
+
+impl<'ir, 'a> InstructionSetup<'ir, 'a> {
+    /// Call the footprint function on the opcode returned by `decode`,
+    /// which declares any symbolic segments in the solver and records
+    /// the constraints it asserts on them. If `constrained`, these
+    /// constraints are checked to be satisfiable first.
+    fn prepare(
+        &self,
+        constrained: bool,
+        decode: impl FnOnce(&mut Solver<B129>, &mut Vec<(String, smtlib::Exp<Sym>)>) -> Result<Val<B129>, String>,
+    ) -> Result<PreparedInstruction<'ir>, SetupError> {
+        let shared_state = self.shared_state;
+        let solver_cfg = smt::Config::new();
+        let solver_ctx = smt::Context::new(solver_cfg);
+        let mut solver = Solver::from_checkpoint(&solver_ctx, self.checkpoint.clone());
+        let mut asserted = Vec::new();
+        let opcode_val = decode(&mut solver, &mut asserted).map_err(SetupError::Failed)?;
+        if constrained && solver.check_sat() == SmtResult::Unsat {
+            let core = if self.unsat_core {
+                let events: Vec<Event<B129>> =
+                    solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect();
+                unsat_core(&events, &asserted, shared_state)
+            } else {
+                None
+            };
+            return Err(SetupError::Unsat("Instruction constraints are unsatisfiable".to_string(), core));
+        }
+
+        let mut reset_registers = self.reset_registers.clone();
+        let mut alignment_var = None;
+        if let Some((check, base, mode)) = &self.alignment_check {
+            let enabled = match mode.as_str() {
+                "on" => Val::Bits(B129::new(1, 1)),
+                "off" => Val::Bits(B129::new(0, 1)),
+                _ => {
+                    let v = solver.declare_const(smtlib::Ty::BitVec(1), SourceLoc::unknown());
+                    alignment_var = Some(v);
+                    Val::Symbolic(v)
+                }
+            };
+            reset_registers.insert(Loc::Id(check.register), alignment_check_reset(*check, base.clone(), enabled));
+        }
+        // Record register assumptions from defaults; others are recorded at reset-registers
+        let mut sorted_regs: Vec<(&Name, &Register<_>)> = self.regs.iter().collect();
+        sorted_regs.sort_by_key(|(name, _)| *name);
+        for (name, reg) in sorted_regs {
+            if let Some(value) = reg.read_last_if_initialized() {
+                solver.add_event(Event::AssumeReg(*name, vec![], value.clone()))
+            }
+        }
+
+        let (args, ret_ty, instrs) = shared_state.functions.get(&self.function_id).unwrap();
+        let mut frame =
+            LocalFrame::new(self.function_id, args, ret_ty, Some(&[function_arg(args, &opcode_val)]), instrs);
+        frame.add_lets(self.lets).add_regs(self.regs).set_memory(self.memory.clone());
+        match executor::assume_constraints(
+            &self.isa_config.assumptions,
+            &mut frame,
+            shared_state,
+            &mut solver,
+            SourceLoc::command_line(),
+        ) {
+            Ok(()) => (),
+            Err(ExecError::Dead) => {
+                return Err(SetupError::Unsat("The --assume constraints are unsatisfiable".to_string(), None))
+            }
+            Err(err) => return Err(SetupError::Failed(err.to_string())),
+        }
+
+        let checkpoint = smt::checkpoint(&mut solver);
+        Ok(PreparedInstruction { opcode_val, asserted, alignment_var, reset_registers, frame, checkpoint })
+    }
+
+    /// The task state for running a prepared instruction
+    fn task_state(&self, reset_registers: HashMap<Loc<Name>, Reset<B129>>) -> TaskState<B129> {
+        let task_state = TaskState::new()
+            .with_reset_registers(reset_registers)
+            .with_guard_functions(self.guard_functions.clone())
+            .with_decode_trace(self.decode_functions.clone())
+            .with_undefined_audit(self.audit_undefined)
+            .with_trace_budget(self.trace_budget)
+            .with_concurrent_fork_checks(self.concurrent_forks)
+            .with_time_slice(self.time_slice)
+            .with_progress(self.progress);
+        let task_state = self.exploration.task_state(task_state);
+        match &self.checkpoint_cache {
+            Some(cache) => task_state.with_checkpoint_cache(cache.clone()),
+            None => task_state,
+        }
+    }
+}
+
+/// Apply the simplifications used by `--simplify` to a trace. With
+/// `split_instructions`, definitions are not propagated from one
+/// instruction's events into another's.
+fn simplify_trace<B: BV>(events: &mut Vec<Event<B>>, simplify_registers: bool, split_instructions: bool) {
+    simplify::hide_initialization(events);
+    if simplify_registers {
+        simplify::remove_extra_register_fields(events);
+        simplify::remove_repeated_register_reads(events);
+        simplify::remove_unused_register_assumptions(events);
+    }
+    simplify::remove_unused(events);
+    if split_instructions {
+        simplify::propagate_forwards_used_once_segments(events)
+    } else {
+        simplify::propagate_forwards_used_once(events)
+    }
+    simplify::commute_extract(events);
+    simplify::eval(events);
+}
+
+/// The events of a path, as returned by the collector, which are
+/// used by the footprint analysis, in execution order and followed by
+/// the opcode.
+fn dependency_path<B: BV>(events: &[Event<B>], keep_ifetch: bool, opcode_val: &Val<B>) -> EvPath<B> {
+    let mut events: EvPath<B> = events
+        .iter()
+        .rev()
+        .filter(|ev| {
+            (ev.is_memory_read_or_write() && (keep_ifetch || !ev.is_ifetch()))
+                || ev.is_smt()
+                || ev.is_instr()
+                || ev.is_cycle()
+                || ev.is_write_reg()
+        })
+        .cloned()
+        .collect();
+    simplify::remove_unused(&mut events);
+    events.push(Event::Instr(opcode_val.clone()));
+    events
+}
+
+/// The branch taken at each fork of a path in execution order
+fn path_branches<B: BV>(events: &[Event<B>]) -> Vec<u32> {
+    events
+        .iter()
+        .filter_map(|event| if let Event::Fork(_, _, branch, _) = event { Some(*branch) } else { None })
+        .collect()
+}
+
+/// Paths finish in an arbitrary order, so sort them by the branches
+/// they take to number them consistently between runs.
+fn sort_paths_by_branches<B: BV, T>(paths: &mut [T], events: impl Fn(&T) -> &[Event<B>]) {
+    paths.sort_by_cached_key(|path| path_branches(events(path)))
+}
+
+/// Take every result from a queue filled by
+/// [executor::start_multi_with_strategy], passing each successful
+/// path to `path`. Errors are printed with `report` and counted in the
+/// manifest, and unless `continue_on_error` is set the first one ends
+/// the run with the returned exit code.
+fn drain_paths_with<T, E>(
+    queue: &SegQueue<Result<T, E>>,
+    manifest: &mut Manifest,
+    continue_on_error: bool,
+    report: impl Fn(E) -> String,
+    mut path: impl FnMut(&mut Manifest, T),
+) -> Result<(), i32> {
+    while let Some(result) = queue.pop() {
+        match result {
+            Ok(value) => path(manifest, value),
+            Err(err) => {
+                eprintln!("{}", report(err));
+                manifest.errors += 1;
+                if !continue_on_error {
+                    return Err(manifest.set_outcome(Outcome::Errored));
+                }
+            }
         }
     }
+    Ok(())
 }
 
-fn opcode_bytes<B: BV>(opcode: Vec<u8>, little_endian: bool) -> B {
+/// As [drain_paths_with], printing errors as they are.
+fn drain_paths<T, E: std::fmt::Display>(
+    queue: &SegQueue<Result<T, E>>,
+    manifest: &mut Manifest,
+    continue_on_error: bool,
+    path: impl FnMut(&mut Manifest, T),
+) -> Result<(), i32> {
+    drain_paths_with(queue, manifest, continue_on_error, |err| err.to_string(), path)
+}
+
+/// The disassembly of an opcode, given as bytes in memory order, if
+/// the configuration names a disassembler (and isla was built with
+/// the `disassemble` feature).
+fn opcode_disassembly<B: BV>(opcode: &[u8], isa_config: &ISAConfig<B>, little_endian: bool) -> Option<String> {
+    disassemble::disassemble(opcode, isa_config.disassembler.as_deref()?, little_endian)
+}
+
+fn opcode_bytes<B: BV>(opcode: Vec<u8>, little_endian: bool) -> Result<B, String> {
     if opcode.len() > 8 {
-        eprintln!("Currently instructions greater than 8 bytes in length are not supported");
-        exit(1);
+        return Err("Currently instructions greater than 8 bytes in length are not supported".to_string());
     }
 
-    if opcode.len() == 2 {
+    Ok(if opcode.len() == 2 {
         let opcode: Box<[u8; 2]> = opcode.into_boxed_slice().try_into().unwrap();
         B::from_u16(if little_endian { u16::from_le_bytes(*opcode) } else { u16::from_be_bytes(*opcode) })
     } else if opcode.len() == 4 {
@@ -166,524 +899,3345 @@ fn opcode_bytes<B: BV>(opcode: Vec<u8>, little_endian: bool) -> B {
         B::from_u32(if little_endian { u32::from_le_bytes(*opcode) } else { u32::from_be_bytes(*opcode) })
     } else {
         B::from_bytes(&opcode)
-    }
+    })
 }
 
-fn parse_elf_function_offset(input: &str) -> Option<(&str, u64)> {
-    let (symbol, offset) = input.split_once(":")?;
+fn write_pair_dependencies(buf: &mut dyn Write, dependencies: &PairDependencies) -> std::io::Result<()> {
+    writeln!(buf, "Pair dependencies:")?;
+    writeln!(buf, "  Address dependency: {}", dependencies.addr)?;
+    writeln!(buf, "  Data dependency: {}", dependencies.data)?;
+    writeln!(buf, "  Control dependency: {}", dependencies.ctrl)?;
+    writeln!(buf, "  RMW dependency: {}", dependencies.rmw)?;
+    writeln!(buf, "  Registers passed: {}", dependencies.registers.join(" "))?;
+    writeln!(buf, "  Store to load: {}", dependencies.store_to_load)?;
+    writeln!(buf, "  Combined register reads: {}", dependencies.combined.registers_read.join(" "))?;
+    writeln!(buf, "  Combined register writes: {}", dependencies.combined.registers_written.join(" "))
+}
 
-    match offset.parse::<u64>() {
-        Ok(offset) => Some((symbol, offset)),
-        Err(_) => {
-            let bv = B129::from_str(offset)?;
-            Some((symbol, bv.lower_u64()))
+fn write_forwarding<B: BV>(
+    buf: &mut dyn Write,
+    pair: &ForwardingPair<B>,
+    shared_state: &SharedState<B>,
+    write_opts: &WriteOpts,
+) -> std::io::Result<()> {
+    write!(buf, "(forwarding (store ")?;
+    simplify::write_smt_exp(
+        buf,
+        &smt_value(&pair.store_address, SourceLoc::unknown()).unwrap(),
+        shared_state,
+        write_opts,
+    )?;
+    write!(buf, " {}) (load ", pair.store_bytes)?;
+    simplify::write_smt_exp(
+        buf,
+        &smt_value(&pair.load_address, SourceLoc::unknown()).unwrap(),
+        shared_state,
+        write_opts,
+    )?;
+    write!(buf, " {}) ", pair.load_bytes)?;
+    match &pair.forwarding {
+        Forwarding::Always => write!(buf, "always")?,
+        Forwarding::Never => write!(buf, "never")?,
+        Forwarding::When(exp) => {
+            write!(buf, "(when ")?;
+            simplify::write_smt_exp(buf, exp, shared_state, write_opts)?;
+            write!(buf, ")")?
         }
     }
+    writeln!(buf, ")")
 }
 
-#[allow(dead_code)]
-struct OpcodeInfo<'a, B> {
-    call: Name,
-    args: Vec<&'a str>,
-    bits: B,
-    mask: B,
-    slice: Vec<(&'a str, u32, u32)>,
-    see: Option<i64>,
+/// The aliased register views accessed by an instruction, along with
+/// the part of the canonical register each refers to, in sorted order.
+fn register_view_strings<B: BV>(footprint: &Footprint, isa_config: &ISAConfig<B>, symtab: &Symtab) -> Vec<String> {
+    let mut views: Vec<String> =
+        footprint.register_views().iter().map(|view| isa_config.register_view_string(*view, symtab)).collect();
+    views.sort();
+    views
 }
 
-impl<'a, B: BV> OpcodeInfo<'a, B> {
-    fn parse<'b>(value: &'a toml::Value, symtab: &'b Symtab) -> Result<Self, String> {
-        let Some(call_str) = value.get("call").and_then(toml::Value::as_str) else {
-            return Err("Could not parse call field as string in opcode info".to_string());
-        };
-
-        let Some(call) = symtab.get(&zencode::encode(call_str)) else {
-            return Err(format!("Could not find function {}", call_str));
-        };
-
-        let Some(args) = value
-            .get("args")
-            .and_then(toml::Value::as_array)
-            .and_then(|arr| arr.iter().map(toml::Value::as_str).collect::<Option<Vec<_>>>())
-        else {
-            return Err(format!("Could not parse args field in opcode info for {}", call_str));
-        };
-
-        let bits = match value.get("bits").and_then(toml::Value::as_str) {
-            Some(hex_str) => match hex_bytes(&hex_str) {
-                Ok(bytes) => opcode_bytes(bytes, false),
-                Err(e) => return Err(format!("Could not parse hexadecimal bits {} for {}: {}", hex_str, call_str, e)),
-            },
-            None => return Err(format!("Expected string value for bits field in opcode info for {}", call_str)),
-        };
-
-        let mask = match value.get("mask").and_then(toml::Value::as_str) {
-            Some(hex_str) => match hex_bytes(&hex_str) {
-                Ok(bytes) => opcode_bytes(bytes, false),
-                Err(e) => return Err(format!("Could not parse hexadecimal mask {} for {}: {}", hex_str, call_str, e)),
-            },
-            None => return Err(format!("Expected string value for mask field in opcode info for {}", call_str)),
-        };
-
-        let slice = match value.get("slice").and_then(toml::Value::as_table) {
-            Some(table) => {
-                let mut slice = Vec::new();
-                for (arg, indices) in table.iter() {
-                    if let Some(ix) = indices.as_array() {
-                        if ix.len() == 1 || ix.len() == 2 {
-                            let Some(hi) = ix[0].as_integer().and_then(|i| u32::try_from(i).ok()) else {
-                                return Err(format!("Failed to parse integer slice index {} for {}", arg, call_str));
-                            };
-                            let Some(lo) = ix[ix.len() - 1].as_integer().and_then(|i| u32::try_from(i).ok()) else {
-                                return Err(format!("Failed to parse integer slice index {} for {}", arg, call_str));
-                            };
-                            slice.push((arg.as_str(), hi, lo))
-                        } else {
-                            return Err(format!("Incorrect slice length {} for {}", arg, call_str));
-                        }
-                    }
-                }
-                slice
-            }
-            None => return Err(format!("Expected table value for slice field in opcode info for {}", call_str)),
-        };
+/// The distinct arbitrary and undefined sites reached with
+/// `--audit-undefined`, keyed by function, kind, and location, with
+/// the number of times each was reached and the number of paths
+/// reaching it.
+#[derive(Default)]
+struct UndefinedAudit {
+    sites: BTreeMap<(String, UndefinedKind, String), (usize, usize)>,
+}
 
-        let see = match value.get("see") {
-            Some(v) => {
-                if let Some(i) = v.as_integer() {
-                    Some(i)
+impl UndefinedAudit {
+    fn record<B: BV>(&mut self, events: &[Event<B>], shared_state: &SharedState<B>) {
+        let mut on_path = HashSet::new();
+        for event in events {
+            if let Event::Undefined { function, kind, info } = event {
+                let location = if info.is_unknown() {
+                    "unknown location".to_string()
                 } else {
-                    return Err(format!("Could not parse see field in opcode info for {}", call_str));
+                    info.location_string(shared_state.symtab.files())
+                };
+                let key = (zencode::decode(shared_state.symtab.to_str(*function)), *kind, location);
+                let (hits, paths) = self.sites.entry(key.clone()).or_insert((0, 0));
+                *hits += 1;
+                if on_path.insert(key) {
+                    *paths += 1
                 }
             }
-            None => None,
-        };
+        }
+    }
 
-        Ok(OpcodeInfo { call, args, bits, mask, slice, see })
+    fn print(&self) {
+        if self.sites.is_empty() {
+            eprintln!("No arbitrary or undefined sites reached");
+            return;
+        }
+        eprintln!("Reached {} distinct arbitrary or undefined sites:", self.sites.len());
+        for ((function, kind, location), (hits, paths)) in &self.sites {
+            eprintln!("  {} in {} at {} ({} times on {} paths)", kind, function, location, hits, paths)
+        }
     }
+}
 
-    fn to_instruction_segments(&self, constraints: &mut Vec<String>) -> Vec<InstructionSegment<B>> {
-        let length = self.bits.len();
-        let mut current = length - 1;
+/// Write the registers changed along a path (in execution order),
+/// with each symbolic value expressed over the registers read by the
+/// path (listed as its inputs), by inlining the definitions in the trace.
+fn write_register_deltas<B: BV>(
+    buf: &mut dyn Write,
+    events: &[Event<B>],
+    deltas: &[trace::RegisterDelta<B>],
+    shared_state: &SharedState<B>,
+    write_opts: &WriteOpts,
+) -> std::io::Result<()> {
+    let defs = trace::inline_definitions(events);
+    let inline = |value: &Val<B>| {
+        smt_value(value, SourceLoc::unknown()).ok().map(|mut exp| {
+            exp.modify(&mut |e| {
+                if let smtlib::Exp::Var(v) = e {
+                    if let Some(def) = defs.get(v) {
+                        *e = def.clone()
+                    }
+                }
+            });
+            exp
+        })
+    };
+
+    let mut used = HashSet::new();
+    for delta in deltas {
+        for value in delta.before.iter().chain(std::iter::once(&delta.after)) {
+            if let Some(exp) = inline(value) {
+                exp.collect_variables(&mut used)
+            }
+        }
+    }
+    let mut inputs: Vec<(Sym, Name)> = Vec::new();
+    for event in events {
+        if let Event::ReadReg(name, accessors, Val::Symbolic(v), _) = event {
+            if accessors.is_empty() && used.contains(v) && !inputs.iter().any(|(input, _)| input == v) {
+                inputs.push((*v, *name))
+            }
+        }
+    }
+
+    let write_value = |buf: &mut dyn Write, value: &Val<B>| match inline(value) {
+        Some(exp) => simplify::write_smt_exp(buf, &exp, shared_state, write_opts),
+        None => write!(buf, "{}", value.to_string(shared_state)),
+    };
+
+    write!(buf, "(reg-delta")?;
+    for (v, name) in &inputs {
+        let name = zencode::decode(shared_state.symtab.to_str(*name));
+        write!(buf, "\n  (input ")?;
+        v.write_var(buf, write_opts)?;
+        write!(buf, " |{}|)", name)?
+    }
+    for delta in deltas {
+        write!(buf, "\n  (reg |{}| ", zencode::decode(shared_state.symtab.to_str(delta.name)))?;
+        match &delta.before {
+            Some(value) => write_value(buf, value)?,
+            None => write!(buf, "undefined")?,
+        }
+        write!(buf, " ")?;
+        write_value(buf, &delta.after)?;
+        write!(buf, ")")?
+    }
+    writeln!(buf, ")")
+}
+
+fn write_conditional_effect<B: BV>(
+    buf: &mut dyn Write,
+    conditional: &ConditionalEffect,
+    shared_state: &SharedState<B>,
+    write_opts: &WriteOpts,
+) -> std::io::Result<()> {
+    write!(buf, "(effect ")?;
+    match &conditional.effect {
+        Effect::ReadReg(name) => write!(buf, "(read-reg |{}|) ", name)?,
+        Effect::WriteReg(name) => write!(buf, "(write-reg |{}|) ", name)?,
+        Effect::ReadMem(bytes) => write!(buf, "(read-mem {}) ", bytes)?,
+        Effect::WriteMem(bytes) => write!(buf, "(write-mem {}) ", bytes)?,
+        Effect::BlockOp(bytes) => write!(buf, "(block-op {}) ", bytes)?,
+        Effect::Branch => write!(buf, "(branch) ")?,
+        Effect::Exception(kind) => write!(buf, "(exception |{}|) ", kind)?,
+        Effect::NoException => write!(buf, "(no-exception) ")?,
+    }
+    match &conditional.condition {
+        None => write!(buf, "always")?,
+        Some(exp) => {
+            write!(buf, "(when ")?;
+            simplify::write_smt_exp(buf, exp, shared_state, write_opts)?;
+            write!(buf, ")")?
+        }
+    }
+    writeln!(buf, ")")
+}
+
+/// The label of an effect's row in the `--el-matrix` table, along
+/// with its group, so the rows can be sorted with register effects
+/// first and exceptions last.
+fn el_matrix_row(effect: &Effect) -> (usize, String) {
+    match effect {
+        Effect::ReadReg(name) => (0, format!("read-reg {}", name)),
+        Effect::WriteReg(name) => (1, format!("write-reg {}", name)),
+        Effect::ReadMem(bytes) => (2, format!("read-mem {}", bytes)),
+        Effect::WriteMem(bytes) => (3, format!("write-mem {}", bytes)),
+        Effect::BlockOp(bytes) => (4, format!("block-op {}", bytes)),
+        Effect::Branch => (5, "branch".to_string()),
+        Effect::Exception(kind) => (6, format!("exception {}", kind)),
+        Effect::NoException => (7, "no-exception".to_string()),
+    }
+}
+
+/// The number of paths an instruction has at a privilege level and
+/// their conditional effects, or `None` if the level's constraints
+/// are unsatisfiable.
+type LevelEffects = Option<(usize, Vec<ConditionalEffect>)>;
+
+/// Print the effects of an instruction at each privilege level as a
+/// table with a column per level. An effect is `always` taken, taken
+/// only on some paths (`cond`), or not taken (`-`), and rows which
+/// differ between the satisfiable levels are marked with `*`.
+fn write_el_matrix(buf: &mut dyn Write, levels: &[(String, LevelEffects)]) -> std::io::Result<()> {
+    let rows = el_matrix_rows(levels);
+    let paths: Vec<String> = levels
+        .iter()
+        .map(|(_, result)| match result {
+            Some((paths, _)) => paths.to_string(),
+            None => "unsat".to_string(),
+        })
+        .collect();
+
+    let mut table: Vec<(String, Vec<&str>)> = vec![("paths".to_string(), paths.iter().map(String::as_str).collect())];
+    table.extend(rows);
+    let label_width = table.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = levels
+        .iter()
+        .enumerate()
+        .map(|(i, (level, _))| table.iter().map(|(_, cells)| cells[i].len()).chain([level.len()]).max().unwrap())
+        .collect();
+
+    let line = |marker: char, label: &str, cells: &mut dyn Iterator<Item = &str>| {
+        let mut line = format!("{} {:label_width$}", marker, label);
+        for (cell, width) in cells.zip(&widths) {
+            line.push_str(&format!("  {:width$}", cell))
+        }
+        line.trim_end().to_string()
+    };
+    writeln!(buf, "{}", line(' ', "", &mut levels.iter().map(|(level, _)| level.as_str())))?;
+    for (label, cells) in &table {
+        let marker = if el_matrix_differs(cells, levels) { '*' } else { ' ' };
+        writeln!(buf, "{}", line(marker, label, &mut cells.iter().copied()))?
+    }
+    Ok(())
+}
+
+/// The effect rows of an `--el-matrix` table, in order, with a cell
+/// for each level.
+fn el_matrix_rows(levels: &[(String, LevelEffects)]) -> Vec<(String, Vec<&'static str>)> {
+    let mut rows: BTreeMap<(usize, String), Vec<&str>> = BTreeMap::new();
+    for (i, (_, result)) in levels.iter().enumerate() {
+        for conditional in result.iter().flat_map(|(_, effects)| effects) {
+            let cells = rows.entry(el_matrix_row(&conditional.effect)).or_insert_with(|| {
+                levels.iter().map(|(_, result)| if result.is_some() { "-" } else { "n/a" }).collect()
+            });
+            cells[i] = if conditional.condition.is_none() { "always" } else { "cond" }
+        }
+    }
+    rows.into_iter().map(|((_, label), cells)| (label, cells)).collect()
+}
+
+/// Whether a row of an `--el-matrix` table differs between the
+/// satisfiable levels.
+fn el_matrix_differs(cells: &[&str], levels: &[(String, LevelEffects)]) -> bool {
+    let mut feasible = cells.iter().zip(levels).filter(|(_, (_, result))| result.is_some()).map(|(cell, _)| cell);
+    let first = feasible.next();
+    feasible.any(|cell| Some(cell) != first)
+}
+
+/// A path found with a symbolic alignment check bit, from its first
+/// cycle in execution order, along with whether it is feasible with
+/// alignment checking off and on.
+struct AlignmentPath<B> {
+    events: EvPath<B>,
+    off: bool,
+    on: bool,
+}
+
+/// Find which values of the alignment check bit `v` a path (as
+/// collected by `executor::trace_collector`) is feasible for, by
+/// replaying its SMT events.
+fn alignment_path<B: BV>(events: &[Event<B>], v: Sym) -> AlignmentPath<B> {
+    use smtlib::Exp::*;
+    let mut events = events.to_vec();
+    executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
+    let solver_cfg = smt::Config::new();
+    let solver_ctx = smt::Context::new(solver_cfg);
+    let mut solver = Solver::<B>::new(&solver_ctx);
+    for event in &events {
+        if let Event::Smt(def, _, _) = event {
+            solver.add(def.clone())
+        }
+    }
+    let mut feasible = |bit| solver.check_sat_with(&Eq(Box::new(Var(v)), Box::new(bits64(bit, 1)))) != SmtResult::Unsat;
+    let (off, on) = (feasible(0), feasible(1));
+    // The first cycle is reserved for initialization
+    let events = events.into_iter().skip_while(|ev| !ev.is_cycle()).collect();
+    AlignmentPath { events, off, on }
+}
+
+/// The effects of the paths feasible with alignment checking off and
+/// on, as the columns of an `--el-matrix` table, so the rows which
+/// differ are the effects which depend on the alignment check.
+fn alignment_levels<B: BV>(
+    paths: &[AlignmentPath<B>],
+    isa_config: &ISAConfig<B>,
+    symtab: &Symtab,
+) -> Vec<(String, LevelEffects)> {
+    let level = |feasible: &dyn Fn(&AlignmentPath<B>) -> bool| -> LevelEffects {
+        let paths: Vec<EvPath<B>> =
+            paths.iter().filter(|path| feasible(path)).map(|path| path.events.clone()).collect();
+        if paths.is_empty() {
+            return None;
+        }
+        let effects =
+            conditional_effects(&paths, &isa_config.ignored_registers, &isa_config.exception_functions, symtab);
+        Some((paths.len(), effects))
+    };
+    vec![("checks-off".to_string(), level(&|path| path.off)), ("checks-on".to_string(), level(&|path| path.on))]
+}
+
+/// The labels of the effects which depend on the alignment check, as
+/// marked in the table printed by [write_el_matrix].
+fn alignment_dependent(levels: &[(String, LevelEffects)]) -> Vec<String> {
+    el_matrix_rows(levels)
+        .into_iter()
+        .filter(|(_, cells)| el_matrix_differs(cells, levels))
+        .map(|(label, _)| label)
+        .collect()
+}
+
+/// Print a path (in execution order) as a separate trace for each
+/// instruction, as split by [trace::instruction_segments]. Each is
+/// preceded by its index, the range of its events in the path, and
+/// its opcode if the path announces one. Types are shared between the
+/// traces, so variables defined by earlier instructions can be used.
+fn write_instruction_segments<B: BV>(
+    buf: &mut dyn Write,
+    events: &[Event<B>],
+    shared_state: &SharedState<B>,
+    write_opts: &WriteOpts,
+) -> std::io::Result<()> {
+    let mut tcx = Cow::Owned(HashMap::new());
+    let mut ftcx = Cow::Owned(HashMap::new());
+    for (n, segment) in trace::instruction_segments(events).into_iter().enumerate() {
+        write!(buf, "(instruction {} (events {} {})", n, segment.events.start, segment.events.end)?;
+        if let Some(Event::Instr(opcode)) = segment.instr.map(|i| &events[i]) {
+            write!(buf, " (opcode {})", opcode.to_string(shared_state))?
+        }
+        writeln!(buf, ")")?;
+        simplify::write_events_in_context(buf, &events[segment.events], shared_state, write_opts, &mut tcx, &mut ftcx)?
+    }
+    Ok(())
+}
+
+/// Print a value returned by the decoder for `--decode-only`.
+/// Symbolic values are expanded using the definitions in `defs`, so
+/// they are written in terms of the instruction's segments. Structs
+/// which Sail generated for tuples are printed as their values in
+/// order, and other structs as their named fields.
+fn write_decoded_value<B: BV>(
+    buf: &mut dyn Write,
+    value: &Val<B>,
+    defs: &HashMap<Sym, &smtlib::Exp<Sym>>,
+    shared_state: &SharedState<B>,
+    write_opts: &WriteOpts,
+) -> std::io::Result<()> {
+    match value {
+        Val::Symbolic(v) => {
+            let exp = smtlib::Exp::Var(*v).clone_expand(defs).eval();
+            simplify::write_smt_exp(buf, &exp, shared_state, write_opts)
+        }
+        Val::MixedBits(segments) => {
+            write!(buf, "(concat")?;
+            for segment in segments {
+                write!(buf, " ")?;
+                write_decoded_value(buf, &Val::from(segment), defs, shared_state, write_opts)?
+            }
+            write!(buf, ")")
+        }
+        Val::Struct(fields) => {
+            let symtab = &shared_state.symtab;
+            let mut fields: Vec<(Name, &Val<B>)> = fields.iter().map(|(f, v)| (*f, v)).collect();
+            let tuple = fields.iter().all(|(f, _)| symtab.tuple_struct_field_number(*f).is_some());
+            if tuple {
+                fields.sort_by_key(|(f, _)| symtab.tuple_struct_field_number(*f))
+            } else {
+                fields.sort_by_key(|(f, _)| symtab.to_str(*f))
+            }
+            for (i, (name, v)) in fields.iter().enumerate() {
+                if i > 0 {
+                    write!(buf, " ")?
+                }
+                if tuple {
+                    write_decoded_value(buf, v, defs, shared_state, write_opts)?
+                } else {
+                    write!(buf, "(|{}| ", zencode::decode(symtab.to_str(*name)))?;
+                    write_decoded_value(buf, v, defs, shared_state, write_opts)?;
+                    write!(buf, ")")?
+                }
+            }
+            Ok(())
+        }
+        _ => write!(buf, "{}", value.to_string(shared_state)),
+    }
+}
+
+/// Print the encoding the decoder selected on a path for
+/// `--decode-only`. When the decoder returns a union constructor, as
+/// decoders producing an AST do, it is printed along with its fields.
+fn write_decoded<B: BV>(
+    buf: &mut dyn Write,
+    path: usize,
+    value: &Val<B>,
+    events: &[Event<B>],
+    shared_state: &SharedState<B>,
+    write_opts: &WriteOpts,
+) -> std::io::Result<()> {
+    let defs: HashMap<Sym, &smtlib::Exp<Sym>> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Smt(smtlib::Def::DefineConst(v, exp), _, _) => Some((*v, exp)),
+            _ => None,
+        })
+        .collect();
+    write!(buf, "(decoded (path {}) ", path)?;
+    match value {
+        Val::Ctor(ctor, arg) => {
+            write!(buf, "(encoding |{}|)", zencode::decode(shared_state.symtab.to_str(*ctor)))?;
+            if !matches!(**arg, Val::Unit) {
+                write!(buf, " (fields ")?;
+                write_decoded_value(buf, arg, &defs, shared_state, write_opts)?;
+                write!(buf, ")")?
+            }
+        }
+        _ => {
+            write!(buf, "(value ")?;
+            write_decoded_value(buf, value, &defs, shared_state, write_opts)?;
+            write!(buf, ")")?
+        }
+    }
+    writeln!(buf, ")")
+}
+
+/// Print the calls recorded by `--trace-decode` on a path as an
+/// indented narrative, in execution order. A call that makes no
+/// recorded calls of its own is printed on one line with its result.
+/// The values computed and branches tested by each function are
+/// printed below its call.
+fn write_decode_narrative<B: BV>(
+    buf: &mut dyn Write,
+    events: &[Event<B>],
+    order: EventOrder,
+    shared_state: &SharedState<B>,
+) -> std::io::Result<()> {
+    let symtab = &shared_state.symtab;
+    let name = |f: &Name| zencode::decode(symtab.to_str(*f));
+    let args_string =
+        |args: &[Val<B>]| args.iter().map(|arg| arg.to_string(shared_state)).collect::<Vec<_>>().join(", ");
+
+    let mut decode: Vec<&Event<B>> =
+        events.iter().filter(|ev| ev.is_decode() || matches!(ev, Event::Guard { .. })).collect();
+    order.convert(EventOrder::Execution, &mut decode);
+
+    writeln!(buf, "Decode:")?;
+    let mut depth = 1;
+    let mut i = 0;
+    while i < decode.len() {
+        let indent = 2 * depth;
+        match (decode[i], decode.get(i + 1)) {
+            (Event::DecodeCall { name: f, args, .. }, Some(Event::DecodeReturn { name: g, return_value }))
+                if f == g =>
+            {
+                let result = return_value.to_string(shared_state);
+                writeln!(buf, "{:indent$}{}({}) = {}", "", name(f), args_string(args), result)?;
+                i += 1
+            }
+            (Event::DecodeCall { name: f, args, .. }, _) => {
+                writeln!(buf, "{:indent$}{}({})", "", name(f), args_string(args))?;
+                depth += 1
+            }
+            (Event::DecodeReturn { name: f, return_value }, _) => {
+                depth = usize::max(depth - 1, 1);
+                let result = return_value.to_string(shared_state);
+                writeln!(buf, "{:indent$}{} = {}", "", name(f), result, indent = 2 * depth)?
+            }
+            (Event::DecodeValue { name: x, args, value, .. }, _) => {
+                let value = value.to_string(shared_state);
+                if args.is_empty() {
+                    writeln!(buf, "{:indent$}{} = {}", "", name(x), value)?
+                } else {
+                    writeln!(buf, "{:indent$}{} = {} from ({})", "", name(x), value, args_string(args))?
+                }
+            }
+            (Event::DecodeTest { args, value, info }, _) => {
+                let location = info.location_string(symtab.files());
+                let value = value.to_string(shared_state);
+                if args.is_empty() {
+                    writeln!(buf, "{:indent$}branch on {} at {}", "", value, location)?
+                } else {
+                    writeln!(buf, "{:indent$}branch on ({}) = {} at {}", "", args_string(args), value, location)?
+                }
+            }
+            (Event::Guard { function, taken, info }, _) => {
+                let taken = if *taken { "taken" } else { "not taken" };
+                writeln!(
+                    buf,
+                    "{:indent$}branch in {} {} at {}",
+                    "",
+                    name(function),
+                    taken,
+                    info.location_string(symtab.files())
+                )?
+            }
+            _ => (),
+        }
+        i += 1
+    }
+    Ok(())
+}
+
+fn set_alignment_bit<B: BV>(
+    bits: Val<B>,
+    bit: u32,
+    enabled: &Val<B>,
+    solver: &mut Solver<B>,
+) -> Result<Val<B>, ExecError> {
+    let info = SourceLoc::unknown();
+    match (bits, enabled) {
+        (Val::Bits(bv), Val::Bits(b)) => Ok(Val::Bits(bv.set_slice(bit, *b))),
+        (Val::Bits(bv), Val::Symbolic(v)) => {
+            use smtlib::Exp::*;
+            let cleared = smt_value(&Val::Bits(bv.set_slice(bit, B::zeros(1))), info)?;
+            let shifted =
+                Bvshl(Box::new(ZeroExtend(bv.len() - 1, Box::new(Var(*v)))), Box::new(bits64(bit as u64, bv.len())));
+            Ok(Val::Symbolic(solver.define_const(Bvor(Box::new(cleared), Box::new(shifted)), info)))
+        }
+        (bits, _) => Err(ExecError::Type(format!("alignment check register {:?}", &bits), info)),
+    }
+}
+
+/// Wrap the reset value of the alignment check register so the
+/// alignment check bit is replaced by `enabled`, which is either a
+/// concrete bit or a symbolic 1-bit variable.
+fn alignment_check_reset<B: BV>(check: AlignmentCheck, base: Reset<B>, enabled: Val<B>) -> Reset<B> {
+    Arc::new(move |memory, typedefs, solver| match (base(memory, typedefs, solver)?, check.field) {
+        (Val::Struct(mut fields), Some(field)) => {
+            let bits = fields
+                .remove(&field)
+                .ok_or_else(|| ExecError::Type("alignment check field missing".to_string(), SourceLoc::unknown()))?;
+            fields.insert(field, set_alignment_bit(bits, check.bit, &enabled, solver)?);
+            Ok(Val::Struct(fields))
+        }
+        (bits, None) => set_alignment_bit(bits, check.bit, &enabled, solver),
+        (other, Some(_)) => {
+            Err(ExecError::Type(format!("alignment check register {:?} is not a struct", &other), SourceLoc::unknown()))
+        }
+    })
+}
+
+type ForwardingQueue<B> = SegQueue<Result<(usize, Vec<Event<B>>, Vec<ForwardingPair<B>>), TraceError>>;
+
+fn parse_hex_u64(s: &str) -> Result<u64, String> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("#x")).unwrap_or(s);
+    u64::from_str_radix(digits, 16).map_err(|_| format!("Cannot parse {} as hexadecimal", s))
+}
+
+/// Parse a half-open address range written as `<base>..<top>`.
+fn parse_address_range(s: &str) -> Result<Range<u64>, String> {
+    let (base, top) = s.split_once("..").ok_or_else(|| "expected a range <base>..<top>".to_string())?;
+    let (base, top) = (parse_hex_u64(base)?, parse_hex_u64(top)?);
+    if base < top {
+        Ok(base..top)
+    } else {
+        Err(format!("range base 0x{:x} must be below its top 0x{:x}", base, top))
+    }
+}
+
+/// Create a resolver for reads from unmapped memory. Given `-` the
+/// user is asked for each value on the terminal (an empty answer
+/// leaves the read symbolic), otherwise values are taken from a file
+/// of `<address> <value>` lines. Answers are remembered, so each
+/// location is only asked about once across all paths.
+fn read_resolver(source: &str) -> Result<ReadResolver, String> {
+    if source == "-" {
+        let answers: Mutex<HashMap<(u64, u32), Option<u64>>> = Mutex::new(HashMap::new());
+        // Questions from different threads are asked one at a time,
+        // but only the threads with a question wait for the terminal
+        let terminal = Mutex::new(());
+        Ok(ReadResolver::new(move |address, bytes| {
+            let answered = |answers: &Mutex<HashMap<_, _>>| answers.lock().unwrap().get(&(address, bytes)).copied();
+            if let Some(answer) = answered(&answers) {
+                return answer;
+            }
+            let _terminal = terminal.lock().unwrap();
+            // Another thread may have asked about the same location
+            // while we were waiting
+            if let Some(answer) = answered(&answers) {
+                return answer;
+            }
+            let answer = loop {
+                eprint!("Value for {}-byte read from unmapped address {:#x}: ", bytes, address);
+                let mut line = String::new();
+                match std::io::stdin().read_line(&mut line) {
+                    Ok(0) | Err(_) => break None,
+                    Ok(_) if line.trim().is_empty() => break None,
+                    Ok(_) => match parse_hex_u64(line.trim()) {
+                        Ok(value) if bytes < 8 && value >> (bytes * 8) != 0 => {
+                            eprintln!("{:#x} does not fit in {} bytes", value, bytes)
+                        }
+                        Ok(value) => break Some(value),
+                        Err(msg) => eprintln!("{}", msg),
+                    },
+                }
+            };
+            answers.lock().unwrap().insert((address, bytes), answer);
+            answer
+        }))
+    } else {
+        let contents = std::fs::read_to_string(source).map_err(|e| format!("Could not read {}: {}", source, e))?;
+        let mut values = HashMap::new();
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [address, value] => {
+                    values.insert(parse_hex_u64(address)?, parse_hex_u64(value)?);
+                }
+                _ => return Err(format!("Expected <address> <value> in {}, got {}", source, line)),
+            }
+        }
+        Ok(ReadResolver::new(move |address, _| values.get(&address).copied()))
+    }
+}
+
+#[derive(Copy, Clone)]
+enum FootprintFormat {
+    Pretty,
+    Json,
+    Defs,
+}
+
+#[derive(Copy, Clone)]
+enum EncodingFormat {
+    Text,
+    Json,
+}
+
+/// What isla-footprint does, chosen once from the options. Each mode
+/// but printing traces is selected by its own option, and only one
+/// may be given.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Traces,
+    Dependency,
+    Tree,
+    ForwardLoad,
+    EffectConditions,
+    Exceptions,
+    ElMatrix,
+    DecodeOnly,
+    EncodingConstraints,
+    Enumerate,
+    RegDelta,
+    KnownBits,
+    AssertFinal,
+    FindEffect,
+    Gdb,
+    StaticFootprint,
+    AbstractFootprint,
+    AnalyzeIr,
+    Sequence,
+    Stdin,
+    Test,
+}
+
+impl Mode {
+    /// The option selecting each mode. -d/--dependency is not
+    /// included, as it also modifies some other modes.
+    const OPTIONS: [(&'static str, Mode); 19] = [
+        ("stdin", Mode::Stdin),
+        ("test", Mode::Test),
+        ("static-footprint", Mode::StaticFootprint),
+        ("abstract-footprint", Mode::AbstractFootprint),
+        ("analyze-ir", Mode::AnalyzeIr),
+        ("sequence", Mode::Sequence),
+        ("forward-load", Mode::ForwardLoad),
+        ("effect-conditions", Mode::EffectConditions),
+        ("exceptions", Mode::Exceptions),
+        ("el-matrix", Mode::ElMatrix),
+        ("decode-only", Mode::DecodeOnly),
+        ("encoding-constraints", Mode::EncodingConstraints),
+        ("enumerate", Mode::Enumerate),
+        ("reg-delta", Mode::RegDelta),
+        ("known-bits", Mode::KnownBits),
+        ("assert-final", Mode::AssertFinal),
+        ("find-effect", Mode::FindEffect),
+        ("gdb", Mode::Gdb),
+        ("tree", Mode::Tree),
+    ];
+
+    /// Options which change how the instruction is run or how its
+    /// results are written, but only apply in some modes
+    const MODIFIERS: [&'static str; 22] = [
+        "dependency",
+        "function",
+        "elf",
+        "partial",
+        "from-file",
+        "hex",
+        "shared-symbol",
+        "replay",
+        "check",
+        "flags",
+        "register-views",
+        "traces-out",
+        "footprint-out",
+        "output",
+        "audit-undefined",
+        "executable",
+        "split-instructions",
+        "save-trace",
+        "post-processor",
+        "unsat-core",
+        "trace-decode",
+        "enumerate-footprint",
+    ];
+
+    fn from_matches(matches: &Matches) -> Result<Self, String> {
+        let mut selected = Mode::OPTIONS.iter().filter(|(opt, _)| matches.opt_present(opt));
+        match (selected.next(), selected.next()) {
+            (Some((first, _)), Some((second, _))) => Err(format!("--{} cannot be used with --{}", second, first)),
+            (Some((_, mode)), None) => Ok(*mode),
+            (None, _) if matches.opt_present("dependency") => Ok(Mode::Dependency),
+            (None, _) => Ok(Mode::Traces),
+        }
+    }
+
+    /// The name of the mode, as recorded in the manifest. Other than
+    /// for printing traces, this is the option which selects it.
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Traces => "traces",
+            Mode::Dependency => "dependency",
+            _ => Mode::OPTIONS.iter().find(|(_, mode)| *mode == self).unwrap().0,
+        }
+    }
+
+    /// The error for a modifier this mode does not accept
+    fn rejects(self, opt: &str) -> String {
+        match self {
+            Mode::Traces => format!("--{} cannot be used when only printing traces", opt),
+            Mode::Dependency => format!("--{} cannot be used with -d/--dependency", opt),
+            _ if opt == "shared-symbol" && (self.is_batch() || self == Mode::Sequence) => format!(
+                "--shared-symbol cannot be used with --{}, symbols are only shared between the -i and --forward-load \
+                 instructions of a single run",
+                self.name()
+            ),
+            _ => format!("--{} cannot be used with --{}", opt, self.name()),
+        }
+    }
+
+    /// Whether a single instruction is given with -i. Batches are read
+    /// from stdin or a test suite, and --static-footprint and
+    /// --analyze-ir analyse the functions given with -f.
+    fn takes_instruction(self) -> bool {
+        !matches!(self, Mode::Stdin | Mode::Test | Mode::StaticFootprint | Mode::AnalyzeIr)
+    }
+
+    fn is_batch(self) -> bool {
+        matches!(self, Mode::Stdin | Mode::Test)
+    }
+
+    /// Whether one of [Mode::MODIFIERS] applies in this mode
+    fn accepts(self, opt: &str) -> bool {
+        use Mode::*;
+        // Modes which decode the instruction given by -i
+        let decodes = self.takes_instruction() && self != Sequence;
+        match opt {
+            "dependency" => matches!(self, Dependency | ForwardLoad | Enumerate | Stdin | Test),
+            "function" => self != DecodeOnly,
+            "elf" | "partial" | "from-file" | "shared-symbol" => decodes,
+            "hex" => self != Sequence,
+            "replay" => decodes && self != AbstractFootprint,
+            "check" | "flags" | "register-views" => matches!(self, Dependency | Enumerate | Stdin | Test),
+            "traces-out" | "footprint-out" => self == Dependency,
+            "output" | "audit-undefined" => matches!(self, Traces | Tree),
+            "executable" => self == Tree,
+            "split-instructions" | "save-trace" => matches!(self, Traces | Dependency),
+            "post-processor" => matches!(self, Traces | Dependency | Tree | EffectConditions | Exceptions),
+            "unsat-core" => (decodes || self.is_batch()) && !matches!(self, ElMatrix | DecodeOnly | Gdb),
+            "trace-decode" => matches!(self, Traces | Dependency | Enumerate | Stdin),
+            "enumerate-footprint" => self == Enumerate,
+            _ => true,
+        }
+    }
+}
+
+/// A memory-mapped device loaded from a `--devices` file. Each of its
+/// registers, at an offset from the base address, holds the last
+/// value written to it. Registers start with the values given in the
+/// file, and reads of registers without a value are symbolic.
+#[derive(Clone)]
+struct ConfigDevice {
+    name: &'static str,
+    base: u64,
+    registers: HashMap<u64, u64>,
+}
+
+impl<B: BV> RegionCallbacks<B> for ConfigDevice {
+    fn read(&self, address: u64, bytes: u32) -> Option<B> {
+        if bytes > 8 {
+            return None;
+        }
+        self.registers.get(&(address - self.base)).map(|value| B::new(bzhi_u64(*value, bytes * 8), bytes * 8))
+    }
+
+    fn write(&mut self, address: u64, _bytes: u32, data: &Val<B>) {
+        match data {
+            Val::Bits(bv) if bv.len() <= 64 => self.registers.insert(address - self.base, bv.lower_u64()),
+            _ => self.registers.remove(&(address - self.base)),
+        };
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn clone_dyn(&self) -> Box<dyn RegionCallbacks<B>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Load the devices from a file of `[[device]]` tables, each with a
+/// name, base address, size in bytes, and optionally the initial
+/// values of its registers keyed by offset.
+fn load_devices<B: BV>(file: &str, memory: &mut Memory<B>) -> Result<(), String> {
+    let number = |value: &toml::Value| match value {
+        toml::Value::String(s) => parse_hex_u64(s),
+        toml::Value::Integer(i) => u64::try_from(*i).map_err(|_| format!("Negative number {} in {}", i, file)),
+        _ => Err(format!("Expected a number in {}, got {}", file, value)),
+    };
+
+    let contents = std::fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+    let devices = contents.parse::<toml::Value>().map_err(|e| format!("Could not parse {}: {}", file, e))?;
+    let Some(devices) = devices.get("device").and_then(toml::Value::as_array) else {
+        return Err(format!("No [[device]] tables in {}", file));
+    };
+
+    for device in devices {
+        let Some(name) = device.get("name").and_then(toml::Value::as_str) else {
+            return Err(format!("Device without a name in {}", file));
+        };
+        let field = |key: &str| {
+            device.get(key).ok_or_else(|| format!("Device {} has no {} in {}", name, key, file)).and_then(number)
+        };
+        let base = field("base")?;
+        let top = base.checked_add(field("size")?).ok_or_else(|| format!("Device {} overflows", name))?;
+
+        let mut registers = HashMap::new();
+        if let Some(values) = device.get("registers").and_then(toml::Value::as_table) {
+            for (offset, value) in values {
+                registers.insert(parse_hex_u64(offset)?, number(value)?);
+            }
+        }
+
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        memory.add_callback_region(base..top, Box::new(ConfigDevice { name, base, registers }))
+    }
+
+    Ok(())
+}
+
+fn parse_elf_function_offset(input: &str) -> Option<(&str, u64)> {
+    let (symbol, offset) = input.split_once(":")?;
+
+    match offset.parse::<u64>() {
+        Ok(offset) => Some((symbol, offset)),
+        Err(_) => {
+            let bv = B129::from_str(offset)?;
+            Some((symbol, bv.lower_u64()))
+        }
+    }
+}
+
+#[allow(dead_code)]
+struct OpcodeInfo<'a, B> {
+    call: Name,
+    args: Vec<&'a str>,
+    bits: B,
+    mask: B,
+    slice: Vec<(&'a str, u32, u32)>,
+    see: Option<i64>,
+}
+
+impl<'a, B: BV> OpcodeInfo<'a, B> {
+    fn parse<'b>(value: &'a toml::Value, symtab: &'b Symtab) -> Result<Self, String> {
+        let Some(call_str) = value.get("call").and_then(toml::Value::as_str) else {
+            return Err("Could not parse call field as string in opcode info".to_string());
+        };
+
+        let Some(call) = symtab.get(&zencode::encode(call_str)) else {
+            return Err(format!("Could not find function {}", call_str));
+        };
+
+        let Some(args) = value
+            .get("args")
+            .and_then(toml::Value::as_array)
+            .and_then(|arr| arr.iter().map(toml::Value::as_str).collect::<Option<Vec<_>>>())
+        else {
+            return Err(format!("Could not parse args field in opcode info for {}", call_str));
+        };
+
+        let bits = match value.get("bits").and_then(toml::Value::as_str) {
+            Some(hex_str) => match hex_bytes(&hex_str) {
+                Ok(bytes) => opcode_bytes(bytes, false)?,
+                Err(e) => return Err(format!("Could not parse hexadecimal bits {} for {}: {}", hex_str, call_str, e)),
+            },
+            None => return Err(format!("Expected string value for bits field in opcode info for {}", call_str)),
+        };
+
+        let mask = match value.get("mask").and_then(toml::Value::as_str) {
+            Some(hex_str) => match hex_bytes(&hex_str) {
+                Ok(bytes) => opcode_bytes(bytes, false)?,
+                Err(e) => return Err(format!("Could not parse hexadecimal mask {} for {}: {}", hex_str, call_str, e)),
+            },
+            None => return Err(format!("Expected string value for mask field in opcode info for {}", call_str)),
+        };
+
+        let slice = match value.get("slice").and_then(toml::Value::as_table) {
+            Some(table) => {
+                let mut slice = Vec::new();
+                for (arg, indices) in table.iter() {
+                    if let Some(ix) = indices.as_array() {
+                        if ix.len() == 1 || ix.len() == 2 {
+                            let Some(hi) = ix[0].as_integer().and_then(|i| u32::try_from(i).ok()) else {
+                                return Err(format!("Failed to parse integer slice index {} for {}", arg, call_str));
+                            };
+                            let Some(lo) = ix[ix.len() - 1].as_integer().and_then(|i| u32::try_from(i).ok()) else {
+                                return Err(format!("Failed to parse integer slice index {} for {}", arg, call_str));
+                            };
+                            slice.push((arg.as_str(), hi, lo))
+                        } else {
+                            return Err(format!("Incorrect slice length {} for {}", arg, call_str));
+                        }
+                    }
+                }
+                slice
+            }
+            None => return Err(format!("Expected table value for slice field in opcode info for {}", call_str)),
+        };
+
+        let see = match value.get("see") {
+            Some(v) => {
+                if let Some(i) = v.as_integer() {
+                    Some(i)
+                } else {
+                    return Err(format!("Could not parse see field in opcode info for {}", call_str));
+                }
+            }
+            None => None,
+        };
+
+        Ok(OpcodeInfo { call, args, bits, mask, slice, see })
+    }
+
+    fn to_instruction_segments(&self, constraints: &mut Vec<String>) -> Vec<InstructionSegment<B>> {
+        let length = self.bits.len();
+        let mut current = length - 1;
 
         let mut ordered_slices = self.slice.clone();
         ordered_slices.sort_by(|(_, hi1, _), (_, hi2, _)| hi2.cmp(&hi1));
 
-        let mut segments = Vec::new();
-        for (field, hi, lo) in ordered_slices {
-            if current > hi {
-                segments.push(InstructionSegment::Concrete(self.bits.extract(current, hi + 1).unwrap()))
+        let mut segments = Vec::new();
+        for (field, hi, lo) in ordered_slices {
+            if current > hi {
+                segments.push(InstructionSegment::Concrete(self.bits.extract(current, hi + 1).unwrap()))
+            }
+            let bits = self.bits.extract(hi, lo).unwrap();
+            let mask = self.mask.extract(hi, lo).unwrap();
+            if mask == B::ones((hi - lo) + 1) {
+                segments.push(InstructionSegment::Concrete(bits))
+            } else if mask.is_zero() {
+                segments.push(InstructionSegment::Symbolic(field.to_string(), (hi - lo) + 1))
+            } else {
+                segments.push(InstructionSegment::Symbolic(field.to_string(), (hi - lo) + 1));
+                constraints.push(format!("(= (bvand {} {}) {})", field, mask, bits));
+            }
+            current = lo - 1
+        }
+        if current != u32::MAX {
+            segments.push(InstructionSegment::Concrete(self.bits.extract(current, 0).unwrap()))
+        }
+
+        segments
+    }
+}
+
+fn isla_main() -> i32 {
+    let mut manifest = Manifest::new("isla-footprint");
+    let code = footprint_main(&mut manifest);
+    log!(log::VERBOSE, &format!("SMT: {}", smt::fork_check_counts()));
+    log!(log::VERBOSE, &format!("SMT: {}", smt::checkpoint_restore_counts()));
+    manifest.finish(code)
+}
+
+fn footprint_main(manifest: &mut Manifest) -> i32 {
+    let now = Instant::now();
+
+    let mut opts = opts::common_opts();
+    opts.optopt("i", "instruction", "display footprint of instruction", "<instruction>");
+    opts.optflag("", "stdin", "read instructions as JSON objects, one per line, from stdin and write JSON results");
+    opts.optopt("", "test", "check the footprints of the instructions in a YAML test suite", "<suite.yaml>");
+    opts.optopt("e", "endianness", "instruction encoding endianness (default: from configuration)", "big/little");
+    opts.optopt("", "elf", "load an elf file, and use instructions from it", "<file>");
+    opts.optopt("", "save-trace", "save the trace of each path to a file, for use with --replay", "<file>");
+    opts.optopt(
+        "",
+        "output",
+        "write traces to a file rather than stdout, compressed with zstd if it ends in .zst",
+        "<file>",
+    );
+    opts.optopt(
+        "",
+        "replay",
+        "replay a path (default 0) saved with --save-trace, and continue execution from the end of it",
+        "<file>[:<path>]",
+    );
+    opts.optflag("d", "dependency", "view instruction dependency info");
+    opts.optopt("", "traces-out", "with -d, also write the trace of each path to a file", "<file>");
+    opts.optopt("", "footprint-out", "with -d, write dependency info to a file rather than stdout", "<file>");
+    opts.optopt("", "cache", "with -d, share footprints with isla-axiomatic through this cache directory", "<path>");
+    opts.optopt(
+        "",
+        "alignment-checks",
+        "set the alignment check bit given by the ISA config, reporting which effects depend on it if symbolic",
+        "<on|off|symbolic>",
+    );
+    opts.optflag("", "effect-conditions", "report the conditions under which each register and memory effect occurs");
+    opts.optflag("", "exceptions", "report the exceptions the instruction can take, and when it takes each one");
+    opts.optflag(
+        "",
+        "el-matrix",
+        "compare the effects and exceptions of the instruction at each privilege level in the ISA config",
+    );
+    opts.optflag("", "keep-ifetch", "track instruction fetches as separate events in dependency info");
+    opts.optflag("", "no-function-cache", "do not reuse results of pure function calls in dependency info");
+    opts.optflag(
+        "",
+        "check",
+        "with -d, check the footprint by re-running with each unread register made symbolic, reporting any differences",
+    );
+    opts.optopt("", "footprint-format", "output format for dependency info (default: pretty)", "<pretty|json|defs>");
+    opts.optflag(
+        "",
+        "flags",
+        "report which condition flags (from registers.flags) are defined and used in dependency info",
+    );
+    opts.optflag(
+        "",
+        "register-views",
+        "report which register aliases (from registers.aliases) are accessed in dependency info",
+    );
+    opts.optflag("x", "hex", "parse instruction as hexadecimal opcode, rather than assembly");
+    opts.optflag("s", "simplify", "simplify instruction footprint");
+    opts.optflag("", "simplify-registers", "simplify register accesses in traces");
+    opts.optflag("", "hide", "hide uninteresting trace elements");
+    opts.optflag("", "source-locations", "annotate trace events with the Sail source location that generated them");
+    opts.optopt("", "hide-events", "do not print events of the given kinds in traces", "<kind>,...");
+    opts.optopt("", "hide-register-writes", "do not print writes to the given registers in traces", "<register>,...");
+    opts.optflag("", "collapse-abstract", "print runs of identical abstract events in traces once");
+    opts.optflag("", "pretty-values", "print values in traces with named enum members and struct fields, and hex bits");
+    opts.optopt(
+        "",
+        "minimize-for",
+        "only print the events that events matching a pattern depend on",
+        "<read-reg|write-reg|read-mem|write-mem|abstract>[:<name|bytes|kind>]",
+    );
+    opts.optopt("", "order", "order in which trace events are printed (default: execution)", "<execution|reverse>");
+    opts.optflag("t", "tree", "combine traces into tree");
+    opts.optflag(
+        "",
+        "split-instructions",
+        "print a separate trace for each instruction in a path, preceded by its range of events",
+    );
+    opts.optmulti(
+        "f",
+        "function",
+        "use a custom footprint function (repeat to run several in sequence)",
+        "<identifer>",
+    );
+    opts.optflag("c", "continue-on-error", "continue generating traces upon encountering an error");
+    opts.optopt("", "armv8-page-tables", "set up page tables with provided constraints", "<constraints>");
+    opts.optflagopt(
+        "",
+        "zero-memory",
+        "treat memory as being zero, optionally only within a range (default: from configuration)",
+        "<base>..<top>",
+    );
+    opts.optopt("", "devices", "model memory-mapped devices described in a file", "<file.toml>");
+    opts.optopt(
+        "",
+        "resolve-reads",
+        "take values for reads from unmapped addresses from a file of <address> <value> lines, or ask for them given -",
+        "<file>",
+    );
+    opts.optflag("", "partial", "parse instruction as binary with unknown (name:size) and don't-care (x) bits");
+    opts.optopt("", "from-file", "parse instruction from opcodes file", "<file>");
+    opts.optmulti("", "instruction-constraint", "add constraint on variables in a partial instruction", "<constraint>");
+    opts.optflagopt(
+        "",
+        "enumerate",
+        "print the opcodes (at most n) which satisfy the constraints on a partial instruction, rather than executing it",
+        "<n>",
+    );
+    opts.optflag("", "enumerate-footprint", "with --enumerate, also run the footprint for each opcode found");
+    opts.optflagopt(
+        "",
+        "encoding-constraints",
+        "print the constraints on the fields of a partial instruction which lead to each outcome of decoding it",
+        "<text|json>",
+    );
+    opts.optflag(
+        "",
+        "reg-delta",
+        "for each path, print only the registers changed by the footprint function, rather than a trace",
+    );
+    opts.optflag(
+        "",
+        "known-bits",
+        "print which bits of each register written by the instruction are the same on every path, rather than traces",
+    );
+    opts.optmulti(
+        "",
+        "assert-final",
+        "check whether a constraint over registers can hold at the end of each path, rather than printing traces",
+        "<constraint>",
+    );
+    opts.optmulti(
+        "",
+        "find-effect",
+        "find opcodes with a path which has an event matching every pattern, rather than printing traces",
+        "<pattern>",
+    );
+    opts.optopt("", "witnesses", "with --find-effect, the number of opcodes found for each path (default 1)", "<n>");
+    opts.optflag(
+        "",
+        "audit-undefined",
+        "record each arbitrary or undefined construct a path evaluates, and list the distinct sites reached",
+    );
+    opts.optopt(
+        "",
+        "gdb",
+        "run a GDB remote protocol stub on this port, replaying the concrete values of a single path",
+        "<port>",
+    );
+    opts.optopt("", "gdb-path", "with --gdb, the path to replay, numbered by its branches (default: 0)", "<n>");
+    opts.optflag(
+        "",
+        "unsat-core",
+        "if no path is feasible, print an unsat core naming the conflicting instruction constraints",
+    );
+    opts.optmulti(
+        "k",
+        "kill-at",
+        "stop executions early and discard if they reach this function (with optional context)",
+        "<function name[, function_name]>",
+    );
+    opts.optmulti(
+        "",
+        "stop-at",
+        "stop executions early and keep trace if they reach this function (with optional context)",
+        "<function name[, function_name]>",
+    );
+    opts.optflag("", "pessimistic", "fail on any assertion that is not necessarily true");
+    opts.optopt("", "timeout", "Add a timeout (in seconds)", "<n>");
+    opts.optopt(
+        "",
+        "strategy",
+        "order in which to explore paths, mem-first prefers paths with more memory accesses (default: dfs)",
+        "<dfs|bfs|mem-first>",
+    );
+    opts.optflag("", "executable", "make trace executable");
+    opts.optmulti(
+        "",
+        "shared-symbol",
+        "declare a symbolic segment name shared between the -i and --forward-load partial instructions (not in batch modes)",
+        "<name:size>",
+    );
+    opts.optopt(
+        "",
+        "forward-load",
+        "run this load after the -i store, reporting when the load can read from the store (and with -d, the dependencies between them)",
+        "<instruction>",
+    );
+    opts.optopt("", "manifest", "write a JSON summary of this run to a file", "<file>");
+    opts.optflag("", "status", "print a JSON status object with the run's outcome to stderr");
+    opts.optflag(
+        "",
+        "static-footprint",
+        "report the registers the footprint functions given by -f could access, without symbolic execution",
+    );
+    opts.optflag(
+        "",
+        "abstract-footprint",
+        "report the registers and memory the instruction could access, with the values known for each, by abstract interpretation",
+    );
+    opts.optflag(
+        "",
+        "analyze-ir",
+        "report unreachable code and dead assignments in the functions given by -f and their callees",
+    );
+    opts.optflag(
+        "",
+        "sequence",
+        "assemble -i as a sequence of instructions with labels, and report each opcode and label offset",
+    );
+    opts.optflag("", "let-report", "report top-level let bindings that were not evaluated to concrete values");
+    opts.optmulti("", "decode-guard", "record the outcome of each concrete branch in this function", "<function>");
+    opts::exploration_opts(&mut opts);
+    opts.optopt(
+        "",
+        "max-trace-kb",
+        "simplify the trace of a path as it executes whenever it grows beyond n kilobytes",
+        "<n>",
+    );
+    opts.optflag("", "concurrent-forks", "check both sides of each symbolic branch for feasibility concurrently");
+    opts.optopt(
+        "",
+        "time-slice",
+        "re-queue paths which run for longer than this many milliseconds without finishing",
+        "<ms>",
+    );
+    opts.optflagopt(
+        "",
+        "checkpoint-cache",
+        "start each path from the cached solver state of a shared prefix, caching up to n (default: 64)",
+        "<n>",
+    );
+    opts.optflagopt(
+        "",
+        "trace-decode",
+        "print the calls, opcode slices, and tests made while decoding each path, from this function or the config's decode_function",
+        "<function>",
+    );
+    opts.optopt("", "trace-decode-depth", "levels of calls below the decode function to print (default: 2)", "<n>");
+    opts.optmulti(
+        "",
+        "post-processor",
+        "run the trace post-processors registered by a dynamic library over each trace (requires the plugins feature)",
+        "<library>",
+    );
+    opts.optflagopt(
+        "",
+        "decode-only",
+        "run only the decoder, this function or the config's decode_function, and report the encoding of each path",
+        "<function>",
+    );
+    opts.optmulti(
+        "",
+        "assume",
+        "assume a constraint over registers holds in the initial state, e.g. \"(= PSTATE.EL #b00)\"",
+        "<constraint>",
+    );
+    opts.optmulti(
+        "",
+        "access-assertion",
+        "assert a property of every memory access, using the variables addr and size",
+        "<constraint>",
+    );
+
+    // The options for reporting the run are read before anything
+    // else, so invalid options are reported as a failure too
+    let args: Vec<String> = std::env::args().collect();
+    if let Ok(matches) = opts.parse(&args[1..]) {
+        if let Some(path) = matches.opt_str("manifest") {
+            manifest.set_path(path)
+        }
+        manifest.set_report_status(matches.opt_present("status"));
+    }
+    let mut failure = manifest.clone();
+    opts::set_failure_hook(move |code| failure.finish(code));
+
+    let mut hasher = Sha256::new();
+    let (matches, arch) = opts::parse(&mut hasher, &opts);
+    if !matches.free.is_empty() {
+        eprintln!("Unexpected arguments: {}", matches.free.join(" "));
+        return 1;
+    }
+    let CommonOpts { num_threads, mut arch, symtab, type_info, mut isa_config, source_path, progress } =
+        opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
+
+    let test_suite = matches.opt_str("test");
+    let unsat_core_mode = matches.opt_present("unsat-core");
+    let mode = match Mode::from_matches(&matches) {
+        Ok(mode) => mode,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return 1;
+        }
+    };
+    if mode.takes_instruction() != matches.opt_present("instruction") {
+        match mode {
+            Mode::StaticFootprint | Mode::AnalyzeIr => {
+                eprintln!("--{} cannot be used with -i/--instruction, give the functions with -f", mode.name())
+            }
+            Mode::Stdin | Mode::Test => eprintln!("-i/--instruction cannot be used with --{}", mode.name()),
+            _ => eprintln!("Exactly one of -i/--instruction, --stdin, or --test must be given"),
+        }
+        return 1;
+    }
+    // Both --stdin and --test run a batch of instructions, each from
+    // its own copy of the initial state
+    let stream = mode.is_batch();
+    if stream && matches.opt_strs("function").len() > 1 {
+        eprintln!("Only one footprint function can be used with --{}", mode.name());
+        return 1;
+    }
+
+    let keep_ifetch = matches.opt_present("keep-ifetch");
+    let continue_on_error = matches.opt_present("continue-on-error");
+    let function_cache = !matches.opt_present("no-function-cache");
+
+    // Footprints are cached under the same keys as isla-axiomatic's
+    // --cache, so a litmus test reuses the footprints of any opcodes
+    // already analysed here
+    let footprint_cache = matches.opt_str("cache").map(PathBuf::from);
+    if let Some(dir) = &footprint_cache {
+        if !matches.opt_present("dependency") {
+            eprintln!("--cache requires -d/--dependency");
+            return 1;
+        }
+        if keep_ifetch {
+            eprintln!(
+                "--cache cannot be used with --keep-ifetch, as isla-axiomatic's footprints ignore instruction fetches"
+            );
+            return 1;
+        }
+        if !dir.is_dir() {
+            eprintln!("--cache directory {} does not exist", dir.display());
+            return 1;
+        }
+    }
+    let check_footprints = matches.opt_present("check");
+    let traces_out = matches.opt_present("traces-out");
+    for opt in ["check", "register-views", "traces-out", "footprint-out"] {
+        if matches.opt_present(opt) && !matches.opt_present("dependency") {
+            eprintln!("--{} requires -d/--dependency", opt);
+            return 1;
+        }
+    }
+    if let Some(opt) = Mode::MODIFIERS.iter().find(|opt| matches.opt_present(opt) && !mode.accepts(opt)) {
+        eprintln!("{}", mode.rejects(opt));
+        return 1;
+    }
+    let report_flags = matches.opt_present("flags");
+    if report_flags && isa_config.flags.is_empty() {
+        eprintln!("--flags requires registers.flags to be set in the ISA config");
+        return 1;
+    }
+    let report_views = matches.opt_present("register-views");
+    let footprint_format = match matches.opt_str("footprint-format").as_deref() {
+        None | Some("pretty") => FootprintFormat::Pretty,
+        Some("json") => FootprintFormat::Json,
+        Some("defs") => FootprintFormat::Defs,
+        Some(format) => {
+            eprintln!("Unknown footprint format {}, expected pretty, json, or defs", format);
+            return 1;
+        }
+    };
+    if let (Mode::ForwardLoad, true, FootprintFormat::Defs) =
+        (mode, matches.opt_present("dependency"), footprint_format)
+    {
+        eprintln!("--footprint-format defs cannot be used with --forward-load");
+        return 1;
+    }
+
+    let split_instructions = matches.opt_present("split-instructions");
+
+    let encoding_format = if mode == Mode::EncodingConstraints {
+        match matches.opt_str("encoding-constraints").as_deref() {
+            None | Some("text") => Some(EncodingFormat::Text),
+            Some("json") => Some(EncodingFormat::Json),
+            Some(format) => {
+                eprintln!("Unknown encoding constraints format {}, expected text or json", format);
+                return 1;
+            }
+        }
+    } else {
+        None
+    };
+    if mode != Mode::FindEffect && matches.opt_present("witnesses") {
+        eprintln!("--witnesses requires --find-effect");
+        return 1;
+    }
+    let witnesses: usize = match matches.opt_get_default("witnesses", 1) {
+        Ok(n) if n > 0 => n,
+        Ok(_) => {
+            eprintln!("--witnesses must be at least 1");
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("Could not parse --witnesses: {}", e);
+            return 1;
+        }
+    };
+    let gdb_port: Option<u16> = match matches.opt_get("gdb") {
+        Ok(port) => port,
+        Err(e) => {
+            eprintln!("Could not parse --gdb: {}", e);
+            return 1;
+        }
+    };
+    let gdb_path: usize = match matches.opt_get_default("gdb-path", 0) {
+        Ok(n) => n,
+        Err(e) => {
+            eprintln!("Could not parse --gdb-path: {}", e);
+            return 1;
+        }
+    };
+    if mode != Mode::Gdb && matches.opt_present("gdb-path") {
+        eprintln!("--gdb-path requires --gdb");
+        return 1;
+    }
+    let audit_undefined = matches.opt_present("audit-undefined");
+
+    let print_order = match matches.opt_str("order").map(|order| order.parse::<EventOrder>()).transpose() {
+        Ok(order) => order.unwrap_or(EventOrder::Execution),
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return 1;
+        }
+    };
+
+    manifest.set_arch(&matches.opt_str("arch").unwrap());
+    manifest.solver_seed = isa_config.solver_seed;
+    if let Some(config) = matches.opt_str("config") {
+        manifest.set_config(&config)
+    }
+    if let Some(instruction) = matches.opt_str("instruction") {
+        manifest.instructions.push(instruction)
+    }
+    if let Some(load_instruction) = matches.opt_str("forward-load") {
+        manifest.instructions.push(load_instruction)
+    }
+    manifest.mode = mode.name().to_string();
+
+    for constraint in matches.opt_strs("assume") {
+        match parse_register_constraint(&constraint, &symtab) {
+            Ok(exp) => isa_config.assumptions.push(exp),
+            Err(msg) => {
+                eprintln!("Could not parse --assume {}: {}", constraint, msg);
+                return 1;
+            }
+        }
+    }
+    let mut final_assertions = Vec::new();
+    for constraint in matches.opt_strs("assert-final") {
+        match parse_register_constraint(&constraint, &symtab) {
+            Ok(exp) => final_assertions.push(exp),
+            Err(msg) => {
+                eprintln!("Could not parse --assert-final {}: {}", constraint, msg);
+                return 1;
+            }
+        }
+    }
+
+    // The stub stops at Sail function calls, so we need them all in
+    // the trace
+    if mode == Mode::Gdb {
+        for def in arch.iter() {
+            if let Def::Fn(f, _, _) = def {
+                isa_config.trace_functions.insert(*f);
+            }
+        }
+    }
+
+    // Note this is the opposite default to other tools
+    let assertion_mode =
+        if matches.opt_present("pessimistic") { AssertionMode::Pessimistic } else { AssertionMode::Optimistic };
+
+    let use_model_reg_init = !matches.opt_present("no-model-reg-init");
+    let iarch = initialize_architecture(&mut arch, symtab, type_info, &isa_config, assertion_mode, use_model_reg_init);
+    let iarch_config = InitArchWithConfig::from_initialized(&iarch, &isa_config);
+    let regs = &iarch.regs;
+    let lets = &iarch.lets;
+    let shared_state = &&iarch.shared_state;
+
+    let minimize_for = match matches
+        .opt_str("minimize-for")
+        .map(|p| EventPattern::parse(&p, &shared_state.symtab, &isa_config.access_kinds))
+    {
+        Some(Ok(pattern)) => Some(pattern),
+        Some(Err(msg)) => {
+            eprintln!("{}", msg);
+            return 1;
+        }
+        None => None,
+    };
+    let mut effect_patterns = Vec::new();
+    for pattern in matches.opt_strs("find-effect") {
+        match EventPattern::parse(&pattern, &shared_state.symtab, &isa_config.access_kinds) {
+            Ok(pattern) => effect_patterns.push(pattern),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        }
+    }
+
+    let mut hide_kinds = HashSet::new();
+    for kind in matches.opt_str("hide-events").iter().flat_map(|kinds| kinds.split(',')) {
+        match EventKind::parse(kind) {
+            Ok(kind) => {
+                hide_kinds.insert(kind);
+            }
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        }
+    }
+    let mut hide_register_writes = HashSet::new();
+    for reg in matches.opt_str("hide-register-writes").iter().flat_map(|regs| regs.split(',')) {
+        match shared_state.symtab.get(&zencode::encode(reg)) {
+            Some(name) => {
+                hide_register_writes.insert(name);
+            }
+            None => {
+                eprintln!("Unknown register {} in --hide-register-writes", reg);
+                return 1;
+            }
+        }
+    }
+
+    if mode == Mode::Exceptions && isa_config.exception_functions.is_empty() {
+        eprintln!("--exceptions requires the ISA config to list exception functions in an [exceptions] table");
+        return 1;
+    }
+    let mut post_processors = PostProcessors::new();
+    for library in matches.opt_strs("post-processor") {
+        if let Err(msg) = post_processors.load_plugin(Path::new(&library)) {
+            eprintln!("{}", msg);
+            return 1;
+        }
+    }
+    let post_processors = Arc::new(post_processors);
+
+    if mode == Mode::ElMatrix && isa_config.privilege_levels.is_empty() {
+        eprintln!("--el-matrix requires the ISA config to define privilege levels in a [privilege_levels] table");
+        return 1;
+    }
+
+    if mode == Mode::StaticFootprint {
+        let mut functions = matches.opt_strs("function");
+        if functions.is_empty() {
+            functions.push("isla_footprint".to_string())
+        }
+        manifest.add_output("static_footprint");
+        let sorted = |regs: &HashSet<Name>| {
+            let mut names: Vec<String> =
+                regs.iter().map(|reg| format!("|{}|", zencode::decode(shared_state.symtab.to_str(*reg)))).collect();
+            names.sort();
+            names.join(" ")
+        };
+        for name in functions {
+            let Some(function) = shared_state.symtab.get(&zencode::encode(&name)) else {
+                eprintln!("Function {} does not exist in the specified architecture", name);
+                return 1;
+            };
+            let footprint = static_footprint(function, shared_state);
+            println!("(static-footprint |{}|", name);
+            println!("  (reads {})", sorted(&footprint.reads));
+            println!("  (writes {})", sorted(&footprint.writes));
+            println!("  (references {}))", sorted(&footprint.references));
+        }
+        return 0;
+    }
+
+    if mode == Mode::AnalyzeIr {
+        let mut roots = matches.opt_strs("function");
+        if roots.is_empty() {
+            roots.push("isla_footprint".to_string())
+        }
+        let mut reachable = HashSet::new();
+        for name in roots {
+            let Some(function) = shared_state.symtab.get(&zencode::encode(&name)) else {
+                eprintln!("Function {} does not exist in the specified architecture", name);
+                return 1;
+            };
+            reachable.extend(static_footprint(function, shared_state).functions)
+        }
+        manifest.add_output("analyze_ir");
+        let constants = boolean_lets(&iarch.lets);
+        let files = shared_state.symtab.files();
+        let location = |instrs: &[Instr<Name, B129>]| {
+            instrs
+                .iter()
+                .find_map(|instr| instr.source_loc())
+                .map(|loc| format!(" ({})", loc.short_location_string(files)))
+                .unwrap_or_default()
+        };
+
+        let mut functions: Vec<(String, Name)> =
+            reachable.into_iter().map(|f| (zencode::decode(shared_state.symtab.to_str(f)), f)).collect();
+        functions.sort();
+
+        let (mut unreachable, mut dead_assignments) = (0, 0);
+        for (name, f) in &functions {
+            let (_, _, instrs) = &shared_state.functions[f];
+            let dead = dead_code(instrs, &constants);
+            for range in &dead.unreachable {
+                println!(
+                    "{}: unreachable instructions {}..{}{}",
+                    name,
+                    range.start,
+                    range.end,
+                    location(&instrs[range.clone()])
+                )
+            }
+            for (i, id) in &dead.dead_assignments {
+                println!(
+                    "{}: value assigned to {} at instruction {} is never read{}",
+                    name,
+                    zencode::decode(shared_state.symtab.to_str(*id)),
+                    i,
+                    location(&instrs[*i..=*i])
+                )
+            }
+            unreachable += dead.unreachable.len();
+            dead_assignments += dead.dead_assignments.len()
+        }
+        eprintln!(
+            "Analyzed {} functions: {} unreachable blocks, {} dead assignments",
+            functions.len(),
+            unreachable,
+            dead_assignments
+        );
+        return 0;
+    }
+
+    if matches.opt_present("let-report") {
+        for (id, status) in &iarch.let_status {
+            if !matches!(status, LetStatus::Concrete) {
+                eprintln!("let {}: {}", zencode::decode(shared_state.symtab.to_str(*id)), status)
+            }
+        }
+    }
+
+    log!(log::VERBOSE, &format!("Parsing took: {}ms", now.elapsed().as_millis()));
+    manifest.time("parse", now);
+
+    let little_endian = match matches.opt_str("endianness").as_deref() {
+        Some("little") => true,
+        Some("big") => false,
+        None => isa_config.instruction_endianness == Endianness::Little,
+        Some(_) => {
+            eprintln!("--endianness argument must be one of either `big` or `little`");
+            return 1;
+        }
+    };
+
+    if mode == Mode::Sequence {
+        let instruction = matches.opt_str("instruction").unwrap();
+        let sequence = match assemble_sequence(&instruction, &isa_config) {
+            Ok(sequence) => sequence,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return manifest.set_outcome(Outcome::DecodeFailed);
+            }
+        };
+        manifest.add_output("sequence");
+        let mut lines = vec!["(sequence".to_string()];
+        let mut offset = 0;
+        for opcode in sequence.opcodes {
+            let len = opcode.len();
+            match opcode_bytes::<B129>(opcode, little_endian) {
+                Ok(opcode) => lines.push(format!("  (instr #x{:x} {})", offset, opcode)),
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    return manifest.set_outcome(Outcome::DecodeFailed);
+                }
+            }
+            offset += len
+        }
+        let mut labels: Vec<(String, u64)> = sequence.labels.into_iter().collect();
+        labels.sort_by_key(|(label, offset)| (*offset, label.clone()));
+        for (label, offset) in labels {
+            lines.push(format!("  (label {} #x{:x})", label, offset))
+        }
+        println!("{})", lines.join("\n"));
+        return 0;
+    }
+
+    let timeout: Option<u64> = match matches.opt_get("timeout") {
+        Ok(timeout) => timeout,
+        Err(e) => {
+            eprintln!("Failed to parse --timeout: {}", e);
+            return 1;
+        }
+    };
+
+    let strategy: Strategy<B129> = match matches.opt_str("strategy").as_deref() {
+        None | Some("dfs") => Strategy::DepthFirst,
+        Some("bfs") => Strategy::BreadthFirst,
+        Some("mem-first") => Strategy::Priority(&executor::memory_first),
+        Some(strategy) => {
+            eprintln!("Unknown strategy {}, expected dfs, bfs, or mem-first", strategy);
+            return 1;
+        }
+    };
+
+    let instruction = matches.opt_str("instruction").unwrap_or_default();
+
+    let mut reset_registers: HashMap<Loc<Name>, Reset<B129>> = HashMap::new();
+    let mut constraints: Vec<String> = matches.opt_strs("instruction-constraint");
+
+    let mut disassembly = None;
+    let opcode: Vec<InstructionSegment<B129>> = if stream {
+        Vec::new()
+    } else if matches.opt_present("partial") {
+        match parse_partial_instruction(&instruction) {
+            Ok(opcode) => opcode,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return manifest.set_outcome(Outcome::DecodeFailed);
+            }
+        }
+    } else if let Some(opcode_file) = matches.opt_str("from-file").as_deref() {
+        let mut contents = String::new();
+        match File::open(opcode_file) {
+            Ok(mut handle) => match handle.read_to_string(&mut contents) {
+                Ok(_) => (),
+                Err(e) => {
+                    eprintln!("Unexpected error when reading opcode from {}: {}", opcode_file, e);
+                    return 1;
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to open opcode file: {}", e);
+                return 1;
+            }
+        }
+        let opcodes = match contents.parse::<toml::Value>() {
+            Ok(toml) => {
+                if let toml::Value::Table(mut tbl) = toml {
+                    match tbl.remove("opcode") {
+                        Some(toml::Value::Array(opcodes)) => opcodes,
+                        _ => {
+                            eprintln!("Expected a sequence of [[opcode]] items");
+                            return 1;
+                        }
+                    }
+                } else {
+                    eprintln!("Invalid opcodes file");
+                    return 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("Error when parsing configuration: {}", e);
+                return 1;
+            }
+        };
+        let opcodes = opcodes
+            .iter()
+            .map(|value| OpcodeInfo::<B129>::parse(value, &shared_state.symtab))
+            .collect::<Result<Vec<_>, _>>();
+        if let Err(msg) = opcodes {
+            eprintln!("{}", msg);
+            return 1;
+        }
+        let opcodes = opcodes.unwrap();
+        let (instruction, n, explicit_n): (&str, usize, bool) = match instruction.split_once(':') {
+            Some((instruction, n)) => {
+                let Ok(n) = usize::from_str_radix(n, 10) else {
+                    eprintln!("Could not parse instruction index");
+                    return 1;
+                };
+                (instruction, n, true)
+            }
+            None => (&instruction, 0, false),
+        };
+        let call = shared_state.symtab.lookup(&zencode::encode(&instruction));
+        let opcode_infos: Vec<&OpcodeInfo<B129>> = opcodes.iter().filter(|op| op.call == call).collect();
+        if !explicit_n && opcode_infos.len() > 1 {
+            eprintln!(
+                "{} has {} decode clauses. Use -i/--instruction {}:<n> to choose one",
+                instruction,
+                opcode_infos.len(),
+                instruction
+            );
+            return 1;
+        } else if opcode_infos.len() == 0 {
+            eprintln!("Could not find opcode info for {}", instruction);
+            return manifest.set_outcome(Outcome::DecodeFailed);
+        }
+        let Some(opcode_info) = opcode_infos.get(n) else {
+            eprintln!("{} has {} decode clauses. Index {} is out of bounds", instruction, opcode_infos.len(), n);
+            return 1;
+        };
+        if let Some(see) = opcode_info.see {
+            let see_reg = shared_state.symtab.lookup("zSEE");
+            reset_registers.insert(Loc::Id(see_reg), Arc::new(move |_, _, _| Ok(Val::I128(see as i128 - 1))));
+        }
+        opcode_info.to_instruction_segments(&mut constraints)
+    } else if matches.opt_present("hex") {
+        let opcode = hex_bytes(&instruction).map_err(|e| format!("Could not parse hexadecimal opcode: {}", e));
+        match opcode.and_then(|opcode| {
+            disassembly = opcode_disassembly(&opcode, &isa_config, little_endian);
+            opcode_bytes(opcode, little_endian)
+        }) {
+            Ok(opcode) => vec![InstructionSegment::Concrete(opcode)],
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return manifest.set_outcome(Outcome::DecodeFailed);
+            }
+        }
+    } else if matches.opt_present("elf") {
+        Vec::new()
+    } else {
+        match assemble_instruction(&instruction, &isa_config).and_then(|opcode| {
+            disassembly = opcode_disassembly(&opcode, &isa_config, little_endian);
+            opcode_bytes(opcode, little_endian)
+        }) {
+            Ok(opcode) => vec![InstructionSegment::Concrete(opcode)],
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return manifest.set_outcome(Outcome::DecodeFailed);
+            }
+        }
+    };
+
+    if !matches.opt_present("elf") && !stream {
+        match &disassembly {
+            Some(disassembly) => {
+                log!(log::VERBOSE, &format!("opcode: {} ({})", instruction_to_string(&opcode).trim_end(), disassembly))
+            }
+            None => log!(log::VERBOSE, &format!("opcode: {}", instruction_to_string(&opcode))),
+        }
+    }
+
+    let kill_conditions = StopConditions::parse(matches.opt_strs("kill-at"), &shared_state, StopAction::Kill);
+    let abstract_conditions = StopConditions::parse(matches.opt_strs("stop-at"), &shared_state, StopAction::Abstract);
+    let stop_conditions = kill_conditions.union(&abstract_conditions);
+
+    let mut memory = Memory::new();
+    memory.set_endianness(isa_config.data_endianness, isa_config.instruction_endianness);
+
+    let PageTableSetup { memory_checkpoint, .. } = if let Some(setup) = matches.opt_str("armv8-page-tables") {
+        let lexer = page_table::setup_lexer::SetupLexer::new(&setup);
+        let constraints = match page_table::setup_parser::SetupParser::new()
+            .parse(&isa_config, lexer)
+            .map_err(|error| error.to_string())
+        {
+            Ok(constraints) => constraints,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        };
+        page_table::setup::armv8_page_tables(&mut memory, HashMap::new(), 0, &constraints, &isa_config).unwrap()
+    } else {
+        PageTableSetup {
+            memory_checkpoint: Checkpoint::new(),
+            all_addrs: HashMap::new(),
+            physical_addrs: HashMap::new(),
+            initial_physical_addrs: HashMap::new(),
+            tables: HashMap::new(),
+            maybe_mapped: HashSet::new(),
+        }
+    };
+
+    let mut replayed_regs = None;
+    let memory_checkpoint = if let Some(replay) = matches.opt_str("replay") {
+        if matches.opt_present("armv8-page-tables") {
+            eprintln!("--replay cannot be used with --armv8-page-tables");
+            return 1;
+        }
+        let (file, path) = match replay.rsplit_once(':').map(|(file, path)| (file, path.parse::<usize>())) {
+            Some((file, Ok(path))) => (file, path),
+            _ => (replay.as_str(), 0),
+        };
+        let mut traces = match trace::load_traces::<B129, _>(file) {
+            Ok(traces) => traces,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        };
+        if path >= traces.len() {
+            eprintln!("{} contains {} paths, so there is no path {}", file, traces.len(), path);
+            return 1;
+        }
+        let events = traces.swap_remove(path);
+        let mut regs = regs.clone();
+        for (name, value) in trace::final_register_values(&events) {
+            if regs.contains_key(name) {
+                regs.assign(name, value, shared_state)
+            }
+        }
+        replayed_regs = Some(regs);
+        Checkpoint::from_events(events)
+    } else {
+        memory_checkpoint
+    };
+    let regs = replayed_regs.as_ref().unwrap_or(regs);
+
+    let (elf_checkpoint, elf_opcode_val) = if let Some(file) = matches.opt_str("elf") {
+        let (symbol, offset) = match parse_elf_function_offset(instruction.as_ref()) {
+            Some((symbol, offset)) => (symbol, offset),
+            None => {
+                eprintln!("Could not parse elf instruction argument {}. Format is 'symbol:offset'", instruction);
+                eprintln!("'offset' can be decimal [0-9]+, hexadecimal 0x[0-9a-fA-F]+, or binary 0b[0-1]+");
+                return 1;
+            }
+        };
+
+        match std::fs::read(&file) {
+            Ok(buf) => {
+                if let Some((_endianness, elf, _dwarf)) = elf::parse_elf_with_debug_info(&buf) {
+                    if let Some(func) = elf::elf_function::<AArch64>(&elf, &buf, symbol) {
+                        eprintln!("{:?}", func);
+                        let instr = func.get_instruction_at_section_offset(offset).unwrap();
+                        eprintln!("opcode: {:?}", instr);
+
+                        let solver_cfg = smt::Config::new();
+                        let solver_ctx = smt::Context::new(solver_cfg);
+                        let mut solver = Solver::from_checkpoint(&solver_ctx, memory_checkpoint);
+
+                        let SymbolicRelocation { symbol, place, opcode } =
+                            instr.relocate_symbolic::<AArch64, B129>(&mut solver, SourceLoc::unknown()).unwrap();
+
+                        eprintln!("Symbol = v{}, Place = v{}", symbol, place);
+
+                        (smt::checkpoint(&mut solver), Some(opcode))
+                    } else {
+                        eprintln!("Failed to get function {} from ELF file {}", symbol, file);
+                        return 1;
+                    }
+                } else {
+                    eprintln!("Failed to parse ELF file {}", file);
+                    return 1;
+                }
+            }
+
+            Err(err) => {
+                eprintln!("Could not read ELF file {}: {}", file, err);
+                return 1;
+            }
+        }
+    } else {
+        (memory_checkpoint, None)
+    };
+
+    if let Some(file) = matches.opt_str("devices") {
+        if let Err(msg) = load_devices(&file, &mut memory) {
+            eprintln!("{}", msg);
+            return 1;
+        }
+    }
+
+    if matches.opt_present("zero-memory") {
+        let region = match matches.opt_str("zero-memory") {
+            Some(range) => match parse_address_range(&range) {
+                Ok(region) => region,
+                Err(msg) => {
+                    eprintln!("Could not parse --zero-memory {}: {}", range, msg);
+                    return 1;
+                }
+            },
+            None => isa_config.zero_region.clone(),
+        };
+        memory.add_zero_region(region);
+    }
+
+    if let Some(source) = matches.opt_str("resolve-reads") {
+        match read_resolver(&source) {
+            Ok(resolver) => memory.set_read_resolver(resolver),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        }
+    }
+
+    for constraint in matches.opt_strs("access-assertion") {
+        let assertion = smt_parser::ExpParser::new()
+            .parse(&constraint)
+            .map_err(|e| e.to_string())
+            .and_then(|exp| AccessAssertion::from_template(&constraint, exp));
+        match assertion {
+            Ok(assertion) => memory.add_access_assertion(assertion),
+            Err(msg) => {
+                eprintln!("Could not parse --access-assertion {}: {}", constraint, msg);
+                return 1;
+            }
+        }
+    }
+
+    let mut footprint_functions = Vec::new();
+    for id in matches.opt_strs("function") {
+        match shared_state.symtab.get(&zencode::encode(&id)) {
+            Some(function_id) => footprint_functions.push(function_id),
+            None => {
+                eprintln!("Function {} does not exist in the specified architecture", id);
+                return 1;
+            }
+        }
+    }
+    if mode == Mode::DecodeOnly {
+        let decoder = match matches.opt_str("decode-only") {
+            Some(function) => shared_state.symtab.get(&zencode::encode(&function)),
+            None => isa_config.decode_function,
+        };
+        let Some(decoder) = decoder else {
+            match matches.opt_str("decode-only") {
+                Some(function) => eprintln!("Function {} does not exist in the specified architecture", function),
+                None => eprintln!("No decode_function in the ISA config, use --decode-only=<function>"),
+            }
+            return 1;
+        };
+        if !matches!(shared_state.functions.get(&decoder), Some((args, _, _)) if args.len() == 1) {
+            eprintln!("--decode-only requires a decode function which takes the opcode as its only argument");
+            return 1;
+        }
+        footprint_functions.push(decoder)
+    }
+    if footprint_functions.is_empty() {
+        footprint_functions.push(shared_state.symtab.lookup("zisla_footprint"))
+    }
+
+    let load_opcode: Option<Vec<InstructionSegment<B129>>> = match matches.opt_str("forward-load") {
+        Some(load_instruction) if matches.opt_present("partial") => {
+            match parse_partial_instruction(&load_instruction) {
+                Ok(load_opcode) => Some(load_opcode),
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    return manifest.set_outcome(Outcome::DecodeFailed);
+                }
             }
-            let bits = self.bits.extract(hi, lo).unwrap();
-            let mask = self.mask.extract(hi, lo).unwrap();
-            if mask == B::ones((hi - lo) + 1) {
-                segments.push(InstructionSegment::Concrete(bits))
-            } else if mask.is_zero() {
-                segments.push(InstructionSegment::Symbolic(field.to_string(), (hi - lo) + 1))
+        }
+        Some(load_instruction) => {
+            let load_opcode = if matches.opt_present("hex") {
+                hex_bytes(&load_instruction).map_err(|e| format!("Could not parse hexadecimal opcode: {}", e))
             } else {
-                segments.push(InstructionSegment::Symbolic(field.to_string(), (hi - lo) + 1));
-                constraints.push(format!("(= (bvand {} {}) {})", field, mask, bits));
+                assemble_instruction(&load_instruction, &isa_config)
+            };
+            match load_opcode.and_then(|opcode| opcode_bytes(opcode, little_endian)) {
+                Ok(opcode) => Some(vec![InstructionSegment::Concrete(opcode)]),
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    return manifest.set_outcome(Outcome::DecodeFailed);
+                }
             }
-            current = lo - 1
-        }
-        if current != u32::MAX {
-            segments.push(InstructionSegment::Concrete(self.bits.extract(current, 0).unwrap()))
         }
+        None => None,
+    };
 
-        segments
+    let mut shared_symbols: Vec<(String, u32)> = Vec::new();
+    for arg in matches.opt_strs("shared-symbol") {
+        match arg.split_once(':').and_then(|(name, size)| Some((name.to_string(), size.parse::<u32>().ok()?))) {
+            Some(symbol) => shared_symbols.push(symbol),
+            None => {
+                eprintln!("Could not parse --shared-symbol {}, expected <name:size>", arg);
+                return 1;
+            }
+        }
     }
-}
-
-fn isla_main() -> i32 {
-    let now = Instant::now();
 
-    let mut opts = opts::common_opts();
-    opts.reqopt("i", "instruction", "display footprint of instruction", "<instruction>");
-    opts.optopt("e", "endianness", "instruction encoding endianness (default: little)", "big/little");
-    opts.optopt("", "elf", "load an elf file, and use instructions from it", "<file>");
-    opts.optflag("d", "dependency", "view instruction dependency info");
-    opts.optflag("x", "hex", "parse instruction as hexadecimal opcode, rather than assembly");
-    opts.optflag("s", "simplify", "simplify instruction footprint");
-    opts.optflag("", "simplify-registers", "simplify register accesses in traces");
-    opts.optflag("", "hide", "hide uninteresting trace elements");
-    opts.optflag("t", "tree", "combine traces into tree");
-    opts.optopt("f", "function", "use a custom footprint function", "<identifer>");
-    opts.optflag("c", "continue-on-error", "continue generating traces upon encountering an error");
-    opts.optopt("", "armv8-page-tables", "set up page tables with provided constraints", "<constraints>");
-    opts.optflag("", "zero-memory", "treat all memory as being zero");
-    opts.optflag("", "partial", "parse instruction as binary with unknown bits");
-    opts.optopt("", "from-file", "parse instruction from opcodes file", "<file>");
-    opts.optmulti("", "instruction-constraint", "add constraint on variables in a partial instruction", "<constraint>");
-    opts.optmulti(
-        "k",
-        "kill-at",
-        "stop executions early and discard if they reach this function (with optional context)",
-        "<function name[, function_name]>",
-    );
-    opts.optmulti(
-        "",
-        "stop-at",
-        "stop executions early and keep trace if they reach this function (with optional context)",
-        "<function name[, function_name]>",
-    );
-    opts.optflag("", "pessimistic", "fail on any assertion that is not necessarily true");
-    opts.optopt("", "timeout", "Add a timeout (in seconds)", "<n>");
-    opts.optflag("", "executable", "make trace executable");
+    let alignment_check = match matches.opt_str("alignment-checks") {
+        None => None,
+        Some(mode) if mode == "on" || mode == "off" || mode == "symbolic" => {
+            let Some(check) = isa_config.alignment_check else {
+                eprintln!("--alignment-checks requires an alignment_check section in the ISA config");
+                return 1;
+            };
+            let Some((_, base)) = isa_config.reset_registers.iter().find(|(loc, _)| *loc == Loc::Id(check.register))
+            else {
+                eprintln!(
+                    "--alignment-checks requires a reset value for {} in the ISA config",
+                    zencode::decode(shared_state.symtab.to_str(check.register))
+                );
+                return 1;
+            };
+            Some((check, base.clone(), mode))
+        }
+        Some(mode) => {
+            eprintln!("Unknown --alignment-checks mode {}, expected on, off, or symbolic", mode);
+            return 1;
+        }
+    };
 
-    let mut hasher = Sha256::new();
-    let (matches, arch) = opts::parse(&mut hasher, &opts);
-    if !matches.free.is_empty() {
-        eprintln!("Unexpected arguments: {}", matches.free.join(" "));
-        exit(1)
+    let mut guard_functions = HashSet::new();
+    for function in matches.opt_strs("decode-guard") {
+        match shared_state.symtab.get(&zencode::encode(&function)) {
+            Some(id) => {
+                guard_functions.insert(id);
+            }
+            None => {
+                eprintln!("Function {} does not exist in the specified architecture", function);
+                return 1;
+            }
+        }
     }
-    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path } =
-        opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
-
-    // Note this is the opposite default to other tools
-    let assertion_mode =
-        if matches.opt_present("pessimistic") { AssertionMode::Pessimistic } else { AssertionMode::Optimistic };
-
-    let use_model_reg_init = !matches.opt_present("no-model-reg-init");
-    let iarch = initialize_architecture(&mut arch, symtab, type_info, &isa_config, assertion_mode, use_model_reg_init);
-    let iarch_config = InitArchWithConfig::from_initialized(&iarch, &isa_config);
-    let regs = &iarch.regs;
-    let lets = &iarch.lets;
-    let shared_state = &&iarch.shared_state;
 
-    log!(log::VERBOSE, &format!("Parsing took: {}ms", now.elapsed().as_millis()));
+    let exploration = opts::parse_exploration_opts(&matches, &shared_state.symtab);
 
-    let little_endian = match matches.opt_str("endianness").as_deref() {
-        Some("little") | None => true,
-        Some("big") => false,
-        Some(_) => {
-            eprintln!("--endianness argument must be one of either `big` or `little`");
-            exit(1)
+    let trace_budget: Option<usize> = match matches.opt_get::<usize>("max-trace-kb") {
+        Ok(kb) => kb.map(|kb| kb * 1024),
+        Err(e) => {
+            eprintln!("Failed to parse --max-trace-kb: {}", e);
+            return 1;
         }
     };
-
-    let timeout: Option<u64> = match matches.opt_get("timeout") {
-        Ok(timeout) => timeout,
+    let time_slice = match matches.opt_get::<u64>("time-slice") {
+        Ok(Some(0)) => {
+            eprintln!("--time-slice must be greater than zero");
+            return 1;
+        }
+        Ok(ms) => ms.map(Duration::from_millis),
         Err(e) => {
-            eprintln!("Failed to parse --timeout: {}", e);
+            eprintln!("Failed to parse --time-slice: {}", e);
             return 1;
         }
     };
+    let checkpoint_cache = match matches.opt_default("checkpoint-cache", "64").map(|n| n.parse::<usize>()) {
+        Some(Ok(capacity)) => Some(Arc::new(CheckpointCache::new(capacity))),
+        Some(Err(e)) => {
+            eprintln!("Failed to parse --checkpoint-cache: {}", e);
+            return 1;
+        }
+        None => None,
+    };
+    if let Some(cache) = &checkpoint_cache {
+        manifest.set_checkpoint_cache(cache.clone())
+    }
 
-    let instruction = matches.opt_str("instruction").unwrap();
-
-    let mut reset_registers: HashMap<Loc<Name>, Reset<B129>> = HashMap::new();
-    let mut constraints: Vec<String> = matches.opt_strs("instruction-constraint");
-
-    let opcode: Vec<InstructionSegment<B129>> = if matches.opt_present("partial") {
-        instruction
-            .split_ascii_whitespace()
-            .map(|s| {
-                B129::from_str(&format!("0b{}", s))
-                    .map(InstructionSegment::Concrete)
-                    .or_else(|| {
-                        let mut it = s.split(':');
-                        let name = it.next()?;
-                        let size = it.next()?;
-                        size.parse().ok().map(|size| InstructionSegment::Symbolic(name.to_string(), size))
-                    })
-                    .unwrap_or_else(|| {
-                        eprintln!("Unable to parse instruction segment {}", s);
-                        exit(1)
-                    })
-            })
-            .collect()
-    } else if let Some(opcode_file) = matches.opt_str("from-file").as_deref() {
-        let mut contents = String::new();
-        match File::open(opcode_file) {
-            Ok(mut handle) => match handle.read_to_string(&mut contents) {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!("Unexpected error when reading opcode from {}: {}", opcode_file, e);
+    let trace_decode = matches.opt_present("trace-decode");
+    let mut decode_functions = HashSet::new();
+    if trace_decode {
+        let root = match matches.opt_str("trace-decode") {
+            Some(function) => match shared_state.symtab.get(&zencode::encode(&function)) {
+                Some(id) => id,
+                None => {
+                    eprintln!("Function {} does not exist in the specified architecture", function);
+                    return 1;
+                }
+            },
+            None => match isa_config.decode_function {
+                Some(id) => id,
+                None => {
+                    eprintln!("No decode_function in the ISA config, use --trace-decode=<function>");
                     return 1;
                 }
             },
+        };
+        let depth: usize = match matches.opt_get_default("trace-decode-depth", 2) {
+            Ok(depth) if depth > 0 => depth,
+            Ok(_) => {
+                eprintln!("--trace-decode-depth must be at least 1");
+                return 1;
+            }
             Err(e) => {
-                eprintln!("Failed to open opcode file: {}", e);
+                eprintln!("Failed to parse --trace-decode-depth: {}", e);
                 return 1;
             }
+        };
+        // Calls made by any of these functions are recorded, so the
+        // tree stops one level above the deepest calls printed
+        decode_functions = call_tree(root, depth - 1, shared_state)
+    }
+
+    // Each instruction read by --stdin, or each opcode found by
+    // --enumerate-footprint, is run from its own copy of the initial state
+    let setup = InstructionSetup {
+        shared_state,
+        isa_config: &isa_config,
+        function_id: footprint_functions[0],
+        regs,
+        lets,
+        memory,
+        checkpoint: elf_checkpoint,
+        reset_registers,
+        alignment_check,
+        unsat_core: unsat_core_mode,
+        exploration: &exploration,
+        guard_functions,
+        decode_functions,
+        audit_undefined,
+        trace_budget,
+        concurrent_forks: matches.opt_present("concurrent-forks"),
+        time_slice,
+        progress,
+        checkpoint_cache: checkpoint_cache.clone(),
+    };
+    let request_write_opts = WriteOpts {
+        define_enum: !matches.opt_present("simplify"),
+        hide_uninteresting: matches.opt_present("hide"),
+        source_locations: matches.opt_present("source-locations"),
+        events_order: simplify::SIMPLIFY_ORDER,
+        print_order,
+        hide_kinds: hide_kinds.clone(),
+        hide_register_writes: hide_register_writes.clone(),
+        collapse_abstract: matches.opt_present("collapse-abstract"),
+        pretty_values: matches.opt_present("pretty-values"),
+        ..WriteOpts::default()
+    };
+    // Test suites compare footprints, so always compute them
+    let dependency = matches.opt_present("dependency") || test_suite.is_some();
+    let footprint_instruction = |request: &io::Request| -> Result<serde_json::Value, String> {
+        let mut disassembly = None;
+        let opcode: Vec<InstructionSegment<B129>> = if request.partial {
+            parse_partial_instruction(&request.opcode)?
+        } else if request.hex {
+            let opcode =
+                hex_bytes(&request.opcode).map_err(|e| format!("Could not parse hexadecimal opcode: {}", e))?;
+            disassembly = opcode_disassembly(&opcode, &isa_config, little_endian);
+            vec![InstructionSegment::Concrete(opcode_bytes(opcode, little_endian)?)]
+        } else {
+            let opcode = assemble_instruction(&request.opcode, &isa_config)?;
+            disassembly = opcode_disassembly(&opcode, &isa_config, little_endian);
+            vec![InstructionSegment::Concrete(opcode_bytes(opcode, little_endian)?)]
+        };
+
+        let mut segments = None;
+        let prepared = setup.prepare(!request.constraints.is_empty(), |solver, asserted| {
+            let (opcode_val, opcode_segments) =
+                instruction_to_val(&opcode, &request.constraints, &HashMap::new(), asserted, solver)?;
+            segments = opcode_segments;
+            Ok(opcode_val)
+        });
+        let PreparedInstruction { opcode_val, asserted, alignment_var, reset_registers, frame, checkpoint } =
+            match prepared {
+                Ok(prepared) => prepared,
+                Err(SetupError::Unsat(msg, Some(core))) => {
+                    return Err(format!("{}, unsat core: {}", msg, core.join(" ")))
+                }
+                Err(SetupError::Unsat(msg, None) | SetupError::Failed(msg)) => return Err(msg),
+            };
+        let mut result = json!({ "opcode": instruction_to_string(&opcode).trim_end() });
+        if let Some(disassembly) = disassembly {
+            result["disassembly"] = json!(disassembly)
         }
-        let opcodes = match contents.parse::<toml::Value>() {
-            Ok(toml) => {
-                if let toml::Value::Table(mut tbl) = toml {
-                    match tbl.remove("opcode") {
-                        Some(toml::Value::Array(opcodes)) => opcodes,
-                        _ => {
-                            eprintln!("Expected a sequence of [[opcode]] items");
-                            return 1;
-                        }
+        if let Some(segments) = &segments {
+            result["segments"] = segments
+                .iter()
+                .map(|(name, size, v)| json!({ "name": name, "size": size, "var": format!("v{}", v) }))
+                .collect();
+        }
+        if let Some(v) = alignment_var {
+            result["alignment_check"] = json!(format!("v{}", v))
+        }
+        let write_opts = &WriteOpts {
+            variable_names: segments.as_deref().map(segment_names).unwrap_or_default(),
+            ..request_write_opts.clone()
+        };
+
+        let task_state = setup.task_state(reset_registers);
+        let mut task = frame.task_with_checkpoint(0, &task_state, checkpoint);
+        task.set_stop_conditions(&stop_conditions);
+
+        let queue = Arc::new(SegQueue::new());
+        let dead_paths = Arc::new(SegQueue::new());
+        let dead_collector = dead_path_collector(dead_paths.clone());
+        let collector: &Collector<B129, TraceQueue<B129>> =
+            if unsat_core_mode { &*dead_collector } else { &executor::trace_collector };
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            vec![task],
+            shared_state,
+            queue.clone(),
+            collector,
+        );
+
+        let mut traces = Vec::new();
+        let mut errors = Vec::new();
+        let mut paths = Vec::new();
+        let mut alignment_paths = Vec::new();
+        while let Some(path) = queue.pop() {
+            match path {
+                Ok((_, mut events)) => {
+                    if let Some(v) = alignment_var {
+                        alignment_paths.push(alignment_path(&events, v))
                     }
-                } else {
-                    eprintln!("Invalid opcodes file");
-                    return 1;
+                    if dependency {
+                        paths.push(dependency_path(&events, keep_ifetch, &opcode_val))
+                    }
+                    executor::COLLECTOR_ORDER.convert(simplify::SIMPLIFY_ORDER, &mut events);
+                    if let Some(ref pattern) = minimize_for {
+                        simplify::minimize_for(&mut events, pattern);
+                    }
+                    if matches.opt_present("simplify") {
+                        simplify_trace(&mut events, matches.opt_present("simplify-registers"), false)
+                    }
+                    let mut buf = Vec::new();
+                    if trace_decode {
+                        write_decode_narrative(&mut buf, &events, simplify::SIMPLIFY_ORDER, shared_state)
+                            .map_err(|e| e.to_string())?
+                    }
+                    simplify::write_events_with_opts(&mut buf, &events, shared_state, write_opts)
+                        .map_err(|e| e.to_string())?;
+                    traces.push(String::from_utf8_lossy(&buf).into_owned())
+                }
+                Err(err) => errors.push(err.to_string()),
+            }
+        }
+        if unsat_core_mode && traces.is_empty() && errors.is_empty() {
+            result["unsat_cores"] = json!(dead_path_cores(&dead_paths, &asserted, shared_state))
+        }
+        result["outcome"] = json!(Outcome::from_counts(traces.len(), errors.len()));
+        result["traces"] = json!(traces);
+        result["errors"] = json!(errors);
+        if alignment_var.is_some() {
+            let levels = alignment_levels(&alignment_paths, &isa_config, &shared_state.symtab);
+            result["alignment_dependent"] = json!(alignment_dependent(&levels))
+        }
+
+        if dependency && !paths.is_empty() {
+            let footprints = footprint_analysis(
+                num_threads,
+                &[paths],
+                &iarch_config,
+                footprint_cache.as_deref(),
+                keep_ifetch,
+                function_cache,
+            )
+            .map_err(|e| format!("{:?}", e))?;
+            if let Some((opcode, footprint)) = footprints.into_iter().next() {
+                result["footprint"] = json!(footprint.summary(&shared_state.symtab));
+                if check_footprints {
+                    result["check"] = json!(check_footprint(num_threads, opcode, &footprint, &iarch_config)
+                        .map_err(|e| format!("{:?}", e))?)
+                }
+                if report_flags {
+                    result["flags"] = json!(footprint.flag_liveness(
+                        &isa_config.flags,
+                        &isa_config.register_bitfields,
+                        &shared_state.symtab
+                    ))
+                }
+                if report_views {
+                    result["register_views"] =
+                        json!(register_view_strings(&footprint, &isa_config, &shared_state.symtab))
+                }
+            }
+        }
+        Ok(result)
+    };
+
+    if let Some(path) = &test_suite {
+        let suite = match golden::read_suite(path) {
+            Ok(suite) => suite,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        };
+        let mut failed = 0;
+        for test in &suite {
+            let request = test.request();
+            manifest.instructions.push(request.opcode.clone());
+            let now = Instant::now();
+            let result = footprint_instruction(&request);
+            manifest.time("execution", now);
+            let diffs = match result {
+                Ok(result) => {
+                    let errors = result["errors"].as_array().cloned().unwrap_or_default();
+                    manifest.paths += result["traces"].as_array().map_or(0, Vec::len);
+                    manifest.errors += errors.len();
+                    if !errors.is_empty() {
+                        errors
+                            .iter()
+                            .map(|err| format!("error: {}", err.as_str().unwrap_or_default().trim_end()))
+                            .collect()
+                    } else if result["footprint"].is_null() {
+                        vec!["no footprint, as the instruction has no paths".to_string()]
+                    } else {
+                        golden::compare(&test.expect, &result["footprint"])
+                    }
+                }
+                Err(msg) => vec![format!("error: {}", msg.trim_end())],
+            };
+            if diffs.is_empty() {
+                println!("PASS {}", test.name())
+            } else {
+                failed += 1;
+                println!("FAIL {}", test.name());
+                for diff in diffs {
+                    println!("  {}", diff)
+                }
+            }
+        }
+        println!("{} passed, {} failed", suite.len() - failed, failed);
+        // The outcome is whether the suite passed, rather than
+        // whether any paths had errors
+        return manifest.set_outcome(if failed == 0 { Outcome::Success } else { Outcome::Failure });
+    }
+
+    if stream {
+        let stdout = std::io::stdout();
+        let mut output = stdout.lock();
+        for request in io::requests::<io::Request, _>(std::io::stdin().lock()) {
+            let response = match request {
+                Ok(Ok(request)) => {
+                    manifest.instructions.push(request.opcode.clone());
+                    let now = Instant::now();
+                    let result = footprint_instruction(&request);
+                    manifest.time("execution", now);
+                    if let Ok(result) = &result {
+                        let count = |key: &str| result[key].as_array().map_or(0, Vec::len);
+                        manifest.paths += count("traces");
+                        manifest.errors += count("errors")
+                    }
+                    io::respond(&mut output, &request.id, result)
                 }
+                Ok(Err(msg)) => io::respond(&mut output, &serde_json::Value::Null, Err(msg)),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = response {
+                eprintln!("{}", e);
+                return 1;
             }
+        }
+        return 0;
+    }
+
+    if mode == Mode::Enumerate {
+        let limit: Option<usize> = match matches.opt_str("enumerate").map(|n| n.parse()).transpose() {
+            Ok(limit) => limit,
             Err(e) => {
-                eprintln!("Error when parsing configuration: {}", e);
+                eprintln!("Failed to parse --enumerate: {}", e);
                 return 1;
             }
         };
-        let opcodes = opcodes
-            .iter()
-            .map(|value| OpcodeInfo::<B129>::parse(value, &shared_state.symtab))
-            .collect::<Result<Vec<_>, _>>();
-        if let Err(msg) = opcodes {
-            eprintln!("{}", msg);
-            return 1;
+        let solver_cfg = smt::Config::new();
+        let solver_ctx = smt::Context::new(solver_cfg);
+        let mut solver = Solver::from_checkpoint(&solver_ctx, setup.checkpoint.clone());
+        let opcodes = instruction_to_val(&opcode, &constraints, &HashMap::new(), &mut Vec::new(), &mut solver)
+            .and_then(|(opcode_val, _)| enumerate_opcodes(&opcode_val, limit, &mut solver));
+        let opcodes = match opcodes {
+            Ok(opcodes) => opcodes,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        };
+        if opcodes.is_empty() {
+            eprintln!("Instruction constraints are unsatisfiable");
+            return manifest.set_outcome(Outcome::Unsat);
         }
-        let opcodes = opcodes.unwrap();
-        let (instruction, n, explicit_n): (&str, usize, bool) = match instruction.split_once(':') {
-            Some((instruction, n)) => {
-                let Ok(n) = usize::from_str_radix(n, 10) else {
-                    eprintln!("Could not parse instruction index");
-                    return 1;
+
+        let stdout = std::io::stdout();
+        let mut output = stdout.lock();
+        for opcode in opcodes {
+            let response = if matches.opt_present("enumerate-footprint") {
+                // Requests give hexadecimal opcodes as bytes, in the same order as -x
+                let bytes = if little_endian { opcode.to_le_bytes() } else { opcode.to_be_bytes() };
+                let request = io::Request {
+                    id: json!(format!("{}", opcode)),
+                    opcode: bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+                    hex: true,
+                    partial: false,
+                    constraints: Vec::new(),
                 };
-                (instruction, n, true)
+                manifest.instructions.push(request.opcode.clone());
+                let result = footprint_instruction(&request);
+                if let Ok(result) = &result {
+                    let count = |key: &str| result[key].as_array().map_or(0, Vec::len);
+                    manifest.paths += count("traces");
+                    manifest.errors += count("errors")
+                }
+                io::respond(&mut output, &request.id, result)
+            } else {
+                writeln!(output, "{}", opcode)
+            };
+            if let Err(e) = response {
+                eprintln!("{}", e);
+                return 1;
             }
-            None => (&instruction, 0, false),
-        };
-        let call = shared_state.symtab.lookup(&zencode::encode(&instruction));
-        let opcode_infos: Vec<&OpcodeInfo<B129>> = opcodes.iter().filter(|op| op.call == call).collect();
-        if !explicit_n && opcode_infos.len() > 1 {
-            eprintln!(
-                "{} has {} decode clauses. Use -i/--instruction {}:<n> to choose one",
-                instruction,
-                opcode_infos.len(),
-                instruction
-            );
-            return 1;
-        } else if opcode_infos.len() == 0 {
-            eprintln!("Could not find opcode info for {}", instruction);
-            return 1;
         }
-        let Some(opcode_info) = opcode_infos.get(n) else {
-            eprintln!("{} has {} decode clauses. Index {} is out of bounds", instruction, opcode_infos.len(), n);
-            return 1;
+        return 0;
+    }
+
+    let mut fields: Vec<(String, u32, Sym)> = Vec::new();
+    // Every symbolic segment, including those of --forward-load
+    let mut named: SegmentVars = Vec::new();
+    let mut load_opcode_val = None;
+    let prepared = setup.prepare(!constraints.is_empty(), |solver, asserted| {
+        let mut shared = HashMap::new();
+        if !shared_symbols.is_empty() {
+            print!("(shared");
+            for (name, size) in shared_symbols {
+                let v = solver.declare_const(smtlib::Ty::BitVec(size), SourceLoc::unknown());
+                print!("\n  (|{}| {} v{})", name, size, v);
+                named.push((name.clone(), size, v));
+                shared.insert(name, (size, v));
+            }
+            println!(")")
+        }
+        let opcode_val = match elf_opcode_val {
+            Some(opcode_val) => opcode_val,
+            None => {
+                let (opcode_val, segments) = instruction_to_val(&opcode, &constraints, &shared, asserted, solver)?;
+                if encoding_format.is_none() {
+                    segments.iter().for_each(print_segments)
+                }
+                fields = segments.unwrap_or_default();
+                named.extend(fields.iter().cloned());
+                opcode_val
+            }
         };
-        if let Some(see) = opcode_info.see {
-            let see_reg = shared_state.symtab.lookup("zSEE");
-            reset_registers.insert(Loc::Id(see_reg), Arc::new(move |_, _, _| Ok(Val::I128(see as i128 - 1))));
+        if let Some(load_opcode) = load_opcode {
+            let (opcode_val, segments) = instruction_to_val(&load_opcode, &[], &shared, asserted, solver)?;
+            segments.iter().for_each(print_segments);
+            named.extend(segments.into_iter().flatten());
+            load_opcode_val = Some(opcode_val)
         }
-        opcode_info.to_instruction_segments(&mut constraints)
-    } else if matches.opt_present("hex") {
-        match hex_bytes(&instruction) {
-            Ok(opcode) => vec![InstructionSegment::Concrete(opcode_bytes(opcode, little_endian))],
-            Err(e) => {
-                eprintln!("Could not parse hexadecimal opcode: {}", e);
-                exit(1)
+        Ok(opcode_val)
+    });
+    manifest.segments = named
+        .iter()
+        .map(|(name, size, v)| manifest::Segment { name: name.clone(), size: *size, var: format!("v{}", v) })
+        .collect();
+    let PreparedInstruction {
+        opcode_val,
+        asserted,
+        alignment_var,
+        reset_registers,
+        frame,
+        checkpoint: initial_checkpoint,
+    } = match prepared {
+        Ok(prepared) => prepared,
+        Err(SetupError::Unsat(msg, core)) => {
+            eprintln!("{}", msg);
+            if let Some(core) = core {
+                print_unsat_core(&core)
             }
+            return manifest.set_outcome(Outcome::Unsat);
+        }
+        Err(SetupError::Failed(msg)) => {
+            eprintln!("{}", msg);
+            return 1;
+        }
+    };
+    if let Some(v) = alignment_var {
+        println!("(alignment-check v{})", v)
+    }
+    let function_id = setup.function_id;
+    let (args, _, _) = shared_state.functions.get(&function_id).unwrap();
+
+    if mode == Mode::AbstractFootprint {
+        if footprint_functions.len() > 1 {
+            eprintln!("--abstract-footprint can only be used with a single footprint function");
+            return 1;
         }
-    } else if matches.opt_present("elf") {
-        Vec::new()
-    } else {
-        match assemble_instruction(&instruction, &isa_config) {
-            Ok(opcode) => vec![InstructionSegment::Concrete(opcode_bytes(opcode, little_endian))],
+        let now = Instant::now();
+        let footprint = match abstract_footprint(
+            function_id,
+            &[function_arg(args, &opcode_val)],
+            &frame,
+            &reset_registers,
+            shared_state,
+        ) {
+            Ok(footprint) => footprint,
             Err(msg) => {
                 eprintln!("{}", msg);
                 return 1;
             }
+        };
+        log!(log::VERBOSE, &format!("Abstract interpretation took: {}ms", now.elapsed().as_millis()));
+        manifest.add_output("abstract_footprint");
+        let sorted = |regs: &HashMap<Name, AbsVal<B129>>| {
+            let mut facts: Vec<String> = regs
+                .iter()
+                .map(|(reg, v)| {
+                    format!("(|{}| {})", zencode::decode(shared_state.symtab.to_str(*reg)), v.to_string(shared_state))
+                })
+                .collect();
+            facts.sort();
+            facts.join(" ")
+        };
+        println!("(abstract-footprint");
+        println!("  (reads {})", sorted(&footprint.reads));
+        println!("  (writes {})", sorted(&footprint.writes));
+        let memory: Vec<String> = footprint
+            .memory
+            .iter()
+            .map(|access| {
+                let mut fact = format!(
+                    "({} {} {}",
+                    access.kind,
+                    access.address.to_string(shared_state),
+                    access.bytes.to_string(shared_state)
+                );
+                if let Some(data) = &access.data {
+                    fact = format!("{} {}", fact, data.to_string(shared_state))
+                }
+                fact + ")"
+            })
+            .collect();
+        println!("  (memory {})", memory.join(" "));
+        let mut summarized: Vec<String> = footprint
+            .summarized
+            .iter()
+            .map(|f| format!("|{}|", zencode::decode(shared_state.symtab.to_str(*f))))
+            .collect();
+        summarized.sort();
+        println!("  (summarized {}))", summarized.join(" "));
+        return 0;
+    }
+
+    let task_state = setup.task_state(reset_registers.clone());
+
+    if mode == Mode::ElMatrix {
+        if footprint_functions.len() > 1 {
+            eprintln!("--el-matrix can only be used with a single footprint function");
+            return 1;
+        }
+        let mut levels = Vec::new();
+        for level in &isa_config.privilege_levels {
+            let solver_cfg = smt::Config::new();
+            let solver_ctx = smt::Context::new(solver_cfg);
+            let mut solver = Solver::from_checkpoint(&solver_ctx, initial_checkpoint.clone());
+            let mut frame = executor::unfreeze_frame(&executor::freeze_frame(&frame));
+            match executor::assume_constraints(
+                &level.constraints,
+                &mut frame,
+                shared_state,
+                &mut solver,
+                SourceLoc::command_line(),
+            ) {
+                Ok(()) => (),
+                Err(ExecError::Dead) => {
+                    levels.push((level.name.clone(), None));
+                    continue;
+                }
+                Err(err) => {
+                    eprintln!("{}: {}", level.name, err);
+                    return 1;
+                }
+            }
+            let mut task = frame.task_with_checkpoint(0, &task_state, smt::checkpoint(&mut solver));
+            task.set_stop_conditions(&stop_conditions);
+
+            let queue = Arc::new(SegQueue::new());
+            let now = Instant::now();
+            executor::start_multi_with_strategy(
+                num_threads,
+                timeout,
+                strategy,
+                vec![task],
+                shared_state,
+                queue.clone(),
+                &executor::trace_collector,
+            );
+            manifest.time("execution", now);
+
+            let mut paths = Vec::new();
+            let drained = drain_paths_with(
+                &queue,
+                manifest,
+                continue_on_error,
+                |err| format!("{}: {}", level.name, err),
+                |manifest, (_, mut events)| {
+                    manifest.paths += 1;
+                    executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
+                    // The first cycle is reserved for initialization
+                    let events: EvPath<B129> = events.drain(..).skip_while(|ev| !ev.is_cycle()).collect();
+                    paths.push(events)
+                },
+            );
+            if let Err(code) = drained {
+                return code;
+            }
+            let effects = conditional_effects(
+                &paths,
+                &isa_config.ignored_registers,
+                &isa_config.exception_functions,
+                &shared_state.symtab,
+            );
+            levels.push((level.name.clone(), Some((paths.len(), effects))))
         }
-    };
 
-    if !matches.opt_present("elf") {
-        log!(log::VERBOSE, &format!("opcode: {}", instruction_to_string(&opcode)));
+        manifest.add_output("el_matrix");
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        write_el_matrix(&mut handle, &levels).unwrap();
+        return 0;
     }
 
-    let kill_conditions = StopConditions::parse(matches.opt_strs("kill-at"), &shared_state, StopAction::Kill);
-    let abstract_conditions = StopConditions::parse(matches.opt_strs("stop-at"), &shared_state, StopAction::Abstract);
-    let stop_conditions = kill_conditions.union(&abstract_conditions);
+    let mut task = frame.task_with_checkpoint(0, &task_state, initial_checkpoint);
+    task.set_stop_conditions(&stop_conditions);
+    let mut tasks = vec![task];
 
-    let mut memory = Memory::new();
+    // When several functions are given, each path that returns from
+    // one function continues by calling the next in the same frame,
+    // so register and memory state is threaded between them.
+    for function_id in &footprint_functions[1..] {
+        let (args, ret_ty, instrs) = shared_state.functions.get(function_id).unwrap();
+        let frame_queue = Arc::new(SegQueue::new());
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            frame_queue.clone(),
+            &executor::frame_collector,
+        );
+        manifest.time("execution", now);
 
-    let PageTableSetup { memory_checkpoint, .. } = if let Some(setup) = matches.opt_str("armv8-page-tables") {
-        let lexer = page_table::setup_lexer::SetupLexer::new(&setup);
-        let constraints = match page_table::setup_parser::SetupParser::new()
-            .parse(&isa_config, lexer)
-            .map_err(|error| error.to_string())
-        {
-            Ok(constraints) => constraints,
-            Err(msg) => {
-                eprintln!("{}", msg);
-                return 1;
+        tasks = Vec::new();
+        let mut exited = 0;
+        let drained = drain_paths(&frame_queue, manifest, continue_on_error, |_, (_, path)| match path {
+            FramePath::Returned(frame, checkpoint) => {
+                let mut task = executor::unfreeze_frame(&frame)
+                    .new_call(*function_id, args, ret_ty, Some(&[function_arg(args, &opcode_val)]), instrs)
+                    .task_with_checkpoint(tasks.len(), &task_state, checkpoint);
+                task.set_stop_conditions(&stop_conditions);
+                tasks.push(task)
             }
-        };
-        page_table::setup::armv8_page_tables(&mut memory, HashMap::new(), 0, &constraints, &isa_config).unwrap()
-    } else {
-        PageTableSetup {
-            memory_checkpoint: Checkpoint::new(),
-            all_addrs: HashMap::new(),
-            physical_addrs: HashMap::new(),
-            initial_physical_addrs: HashMap::new(),
-            tables: HashMap::new(),
-            maybe_mapped: HashSet::new(),
+            FramePath::Exited => exited += 1,
+        });
+        if let Err(code) = drained {
+            return code;
+        }
+        if exited > 0 {
+            eprintln!(
+                "Warning: {} path(s) exited before calling {}, and are not included in the output",
+                exited,
+                zencode::decode(shared_state.symtab.to_str(*function_id))
+            )
         }
+    }
+    let function_id = *footprint_functions.last().unwrap();
+    let (args, ret_ty, instrs) = shared_state.functions.get(&function_id).unwrap();
+
+    // Saved traces record the format they were written in, so they
+    // can be read back with simplify::read_traces, which requires
+    // variables to be printed without names
+    let saved_traces = matches.opt_present("output") || matches.opt_present("traces-out");
+    let write_opts = WriteOpts {
+        define_enum: !matches.opt_present("simplify"),
+        hide_uninteresting: matches.opt_present("hide"),
+        source_locations: matches.opt_present("source-locations"),
+        print_order,
+        hide_kinds,
+        hide_register_writes,
+        collapse_abstract: matches.opt_present("collapse-abstract"),
+        pretty_values: matches.opt_present("pretty-values"),
+        format_version: saved_traces,
+        variable_names: if saved_traces { HashMap::new() } else { segment_names(&named) },
+        ..WriteOpts::default()
     };
 
-    let (elf_checkpoint, have_elf, elf_opcode_val) = if let Some(file) = matches.opt_str("elf") {
-        let (symbol, offset) = match parse_elf_function_offset(instruction.as_ref()) {
-            Some((symbol, offset)) => (symbol, offset),
-            None => {
-                eprintln!("Could not parse elf instruction argument {}. Format is 'symbol:offset'", instruction);
-                eprintln!("'offset' can be decimal [0-9]+, hexadecimal 0x[0-9a-fA-F]+, or binary 0b[0-1]+");
-                return 1;
+    if let Some(load_opcode_val) = load_opcode_val {
+        let now = Instant::now();
+        let frame_queue = Arc::new(SegQueue::new());
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            frame_queue.clone(),
+            &executor::frame_collector,
+        );
+
+        // Continue each path of the store with the load, recording
+        // how many events belong to the store for each new task.
+        let load_task_state =
+            TaskState::new().with_watchpoints(exploration.watchpoints.clone()).with_progress(progress);
+        let mut splits: Vec<usize> = Vec::new();
+        let mut load_tasks = Vec::new();
+        let mut exited = 0;
+        let drained = drain_paths(&frame_queue, manifest, continue_on_error, |_, (_, path)| match path {
+            FramePath::Returned(frame, checkpoint) => {
+                splits.push(checkpoint.trace().as_ref().map_or(0, |trace| trace.len()));
+                let mut load_task = executor::unfreeze_frame(&frame)
+                    .new_call(function_id, args, ret_ty, Some(&[load_opcode_val.clone()]), instrs)
+                    .task_with_checkpoint(load_tasks.len(), &load_task_state, checkpoint);
+                load_task.set_stop_conditions(&stop_conditions);
+                load_tasks.push(load_task)
             }
-        };
+            FramePath::Exited => exited += 1,
+        });
+        if let Err(code) = drained {
+            return code;
+        }
 
-        match std::fs::read(&file) {
-            Ok(buf) => {
-                if let Some((_endianness, elf, _dwarf)) = elf::parse_elf_with_debug_info(&buf) {
-                    if let Some(func) = elf::elf_function::<AArch64>(&elf, &buf, symbol) {
-                        eprintln!("{:?}", func);
-                        let instr = func.get_instruction_at_section_offset(offset).unwrap();
-                        eprintln!("opcode: {:?}", instr);
+        if exited > 0 {
+            eprintln!("Warning: {} path(s) of the store exited, so the load was not run after them", exited)
+        }
 
-                        let solver_cfg = smt::Config::new();
-                        let solver_ctx = smt::Context::new(solver_cfg);
-                        let mut solver = Solver::from_checkpoint(&solver_ctx, memory_checkpoint);
+        let alias_analysis = isa_config.alias_analysis;
+        let forwarding_collector = move |_: usize,
+                                         task_id: usize,
+                                         result: Result<(Val<B129>, LocalFrame<B129>), (ExecError, Backtrace)>,
+                                         _: &SharedState<B129>,
+                                         mut solver: Solver<B129>,
+                                         collected: &ForwardingQueue<B129>| {
+            match result {
+                Ok(_) | Err((ExecError::Exit, _)) => {
+                    let events: Vec<Event<B129>> =
+                        solver.trace().to_vec_in(EventOrder::Execution).into_iter().cloned().collect();
+                    match store_to_load_forwarding(&events, splits[task_id], alias_analysis, &mut solver) {
+                        Ok(pairs) => collected.push(Ok((task_id, events, pairs))),
+                        Err(err) => collected.push(Err(TraceError::exec(err))),
+                    }
+                }
+                Err((ExecError::Dead, _)) => (),
+                Err((err, _)) => collected.push(Err(TraceError::exec(err))),
+            }
+        };
 
-                        let SymbolicRelocation { symbol, place, opcode } =
-                            instr.relocate_symbolic::<AArch64, B129>(&mut solver, SourceLoc::unknown()).unwrap();
+        let queue = Arc::new(SegQueue::new());
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            load_tasks,
+            shared_state,
+            queue.clone(),
+            &forwarding_collector,
+        );
+        manifest.time("execution", now);
 
-                        eprintln!("Symbol = v{}, Place = v{}", symbol, place);
+        let drained = drain_paths(&queue, manifest, continue_on_error, |manifest, (_, events, pairs)| {
+            manifest.paths += 1;
+            manifest.add_output("traces");
+            manifest.add_output("forwarding");
+            let stdout = std::io::stdout();
+            let mut handle = BufWriter::with_capacity(5 * usize::pow(2, 20), stdout.lock());
+            simplify::write_events_with_opts(&mut handle, &events, &shared_state, &write_opts).unwrap();
+            for pair in &pairs {
+                write_forwarding(&mut handle, pair, &shared_state, &write_opts).unwrap()
+            }
+            handle.flush().unwrap()
+        });
+        if let Err(code) = drained {
+            return code;
+        }
 
-                        (smt::checkpoint(&mut solver), true, Some(opcode))
-                    } else {
-                        eprintln!("Failed to get function {} from ELF file {}", symbol, file);
-                        return 1;
-                    }
-                } else {
-                    eprintln!("Failed to parse ELF file {}", file);
+        if matches.opt_present("dependency") {
+            // Each instruction is analysed separately, so the paths
+            // only need to contain the opcodes
+            let paths = vec![vec![Event::Instr(opcode_val.clone())], vec![Event::Instr(load_opcode_val.clone())]];
+            let now = Instant::now();
+            let footprints = footprint_analysis(
+                num_threads,
+                &[paths],
+                &iarch_config,
+                footprint_cache.as_deref(),
+                keep_ifetch,
+                function_cache,
+            );
+            manifest.time("footprint_analysis", now);
+            let footprints = match footprints {
+                Ok(footprints) => footprints,
+                Err(footprint_error) => {
+                    eprintln!("{:?}", footprint_error);
                     return 1;
                 }
+            };
+            let (Val::Bits(first), Val::Bits(second)) = (&opcode_val, &load_opcode_val) else {
+                unreachable!("footprint analysis succeeded with a symbolic opcode")
+            };
+            let dependencies = pair_dependencies(*first, *second, &footprints, &shared_state.symtab);
+            manifest.add_output("footprint");
+            let stdout = std::io::stdout();
+            let mut handle = stdout.lock();
+            match footprint_format {
+                FootprintFormat::Json => {
+                    let summary = json!({
+                        "first": footprints[first].summary(&shared_state.symtab),
+                        "second": footprints[second].summary(&shared_state.symtab),
+                        "dependencies": dependencies,
+                    });
+                    writeln!(&mut handle, "{}", summary).unwrap()
+                }
+                _ => {
+                    let _ = footprints[first].pretty(&mut handle, &shared_state.symtab);
+                    let _ = footprints[second].pretty(&mut handle, &shared_state.symtab);
+                    write_pair_dependencies(&mut handle, &dependencies).unwrap()
+                }
             }
+        }
+
+        return 0;
+    }
+
+    if let Some(format) = encoding_format {
+        if fields.is_empty() {
+            eprintln!("--encoding-constraints requires a partial instruction with symbolic segments");
+            return 1;
+        }
+        let queue = Arc::new(SegQueue::new());
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            queue.clone(),
+            &encoding_collector,
+        );
+        manifest.time("execution", now);
 
+        let mut paths = Vec::new();
+        while let Some(path) = queue.pop() {
+            manifest.paths += 1;
+            paths.push(path)
+        }
+        let assumptions: Vec<smtlib::Exp<Sym>> = asserted.iter().map(|(_, exp)| exp.clone()).collect();
+        let encodings = match encoding_constraints(&paths, &fields, &assumptions, &isa_config.exception_functions) {
+            Ok(encodings) => encodings,
             Err(err) => {
-                eprintln!("Could not read ELF file {}: {}", file, err);
+                eprintln!("{}", err);
                 return 1;
             }
+        };
+
+        manifest.add_output("encoding_constraints");
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        match format {
+            EncodingFormat::Text => {
+                for constraint in &encodings {
+                    let conditions = encoding_constraint_strings(constraint, shared_state);
+                    write_encoding_constraint(&mut handle, &constraint.outcome, &conditions).unwrap()
+                }
+            }
+            EncodingFormat::Json => {
+                let outcomes: Vec<serde_json::Value> = encodings
+                    .iter()
+                    .map(|constraint| {
+                        encoding_constraint_json(
+                            &constraint.outcome,
+                            &encoding_constraint_strings(constraint, shared_state),
+                        )
+                    })
+                    .collect();
+                let fields: Vec<serde_json::Value> =
+                    fields.iter().map(|(name, size, _)| json!({ "name": name, "size": size })).collect();
+                let summary = json!({
+                    "instruction": instruction,
+                    "constraints": constraints,
+                    "fields": fields,
+                    "outcomes": outcomes,
+                });
+                writeln!(&mut handle, "{}", summary).unwrap()
+            }
         }
-    } else {
-        (memory_checkpoint, false, None)
-    };
+        return 0;
+    }
 
-    if matches.opt_present("zero-memory") {
-        memory.add_zero_region(0x0..0xffff_ffff_ffff_ffff);
+    if mode == Mode::DecodeOnly {
+        let queue = Arc::new(SegQueue::new());
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            queue.clone(),
+            &executor::trace_value_collector,
+        );
+        manifest.time("execution", now);
+
+        let mut paths = Vec::new();
+        let drained = drain_paths(&queue, manifest, continue_on_error, |manifest, (_, value, mut events)| {
+            manifest.paths += 1;
+            executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
+            paths.push((value, events))
+        });
+        if let Err(code) = drained {
+            return code;
+        }
+
+        sort_paths_by_branches(&mut paths, |(_, events)| events);
+        manifest.add_output("decoded");
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        for (i, (value, events)) in paths.iter().enumerate() {
+            write_decoded(&mut handle, i, value, events, shared_state, &write_opts).unwrap()
+        }
+        return 0;
     }
 
-    let footprint_function = match matches.opt_str("function") {
-        Some(id) => zencode::encode(&id),
-        None => "zisla_footprint".to_string(),
-    };
+    if mode == Mode::RegDelta {
+        let queue = Arc::new(SegQueue::new());
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            queue.clone(),
+            &executor::trace_registers_collector,
+        );
+        manifest.time("execution", now);
 
-    let (initial_checkpoint, opcode_val) = {
-        let solver_cfg = smt::Config::new();
-        let solver_ctx = smt::Context::new(solver_cfg);
-        let mut solver = Solver::from_checkpoint(&solver_ctx, elf_checkpoint);
-        let opcode_val =
-            if have_elf { elf_opcode_val.unwrap() } else { instruction_to_val(&opcode, &constraints, &mut solver) };
-        // Record register assumptions from defaults; others are recorded at reset-registers
-        let mut sorted_regs: Vec<(&Name, &Register<_>)> = regs.iter().collect();
-        sorted_regs.sort_by_key(|(name, _)| *name);
-        for (name, reg) in sorted_regs {
-            if let Some(value) = reg.read_last_if_initialized() {
-                solver.add_event(Event::AssumeReg(*name, vec![], value.clone()))
+        let drained = drain_paths(&queue, manifest, continue_on_error, |manifest, (_, mut events, after)| {
+            manifest.paths += 1;
+            manifest.add_output("reg_delta");
+            executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
+            let deltas = trace::register_deltas(&events, regs, &after);
+            let stdout = std::io::stdout();
+            let mut handle = stdout.lock();
+            write_register_deltas(&mut handle, &events, &deltas, shared_state, &write_opts).unwrap()
+        });
+        if let Err(code) = drained {
+            return code;
+        }
+
+        return 0;
+    }
+
+    if mode == Mode::KnownBits {
+        let queue = Arc::new(SegQueue::new());
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            queue.clone(),
+            &known_bits_collector,
+        );
+        manifest.time("execution", now);
+
+        // Join the known bits of each register over the paths which
+        // write it, counting how many of them do
+        let mut registers: HashMap<Name, (KnownBits<B129>, usize)> = HashMap::new();
+        let drained = drain_paths(&queue, manifest, continue_on_error, |manifest, known| {
+            manifest.paths += 1;
+            for (name, bits) in known {
+                match registers.get_mut(&name) {
+                    Some((joined, paths)) => {
+                        *joined = joined.join(bits);
+                        *paths += 1
+                    }
+                    None => {
+                        registers.insert(name, (bits, 1));
+                    }
+                }
             }
+        });
+        if let Err(code) = drained {
+            return code;
         }
-        (smt::checkpoint(&mut solver), opcode_val)
-    };
 
-    let function_id = shared_state.symtab.lookup(&footprint_function);
-    let (args, ret_ty, instrs) = shared_state.functions.get(&function_id).unwrap();
-    let task_state = TaskState::new().with_reset_registers(reset_registers);
-    let mut task = LocalFrame::new(function_id, args, ret_ty, Some(&[opcode_val.clone()]), instrs)
-        .add_lets(lets)
-        .add_regs(regs)
-        .set_memory(memory)
-        .task_with_checkpoint(0, &task_state, initial_checkpoint);
-    task.set_stop_conditions(&stop_conditions);
+        manifest.add_output("known_bits");
+        let mut registers: Vec<(String, KnownBits<B129>, usize)> = registers
+            .into_iter()
+            .map(|(name, (bits, paths))| (zencode::decode(shared_state.symtab.to_str(name)), bits, paths))
+            .collect();
+        registers.sort_by(|(name1, _, _), (name2, _, _)| name1.cmp(name2));
+        print!("(known-bits (paths {})", manifest.paths);
+        for (name, bits, paths) in &registers {
+            print!("\n  (|{}| {} {})", name, bits, paths)
+        }
+        println!(")");
+        return 0;
+    }
+
+    if mode == Mode::AssertFinal {
+        let queue = Arc::new(SegQueue::new());
+        let collector = final_assertion_collector(Arc::new(final_assertions));
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            queue.clone(),
+            collector.as_ref(),
+        );
+        manifest.time("execution", now);
+
+        let mut paths = Vec::new();
+        let drained = drain_paths(&queue, manifest, continue_on_error, |manifest, result| {
+            manifest.paths += 1;
+            paths.push(match result {
+                Some(values) => {
+                    let values: Vec<String> =
+                        values.iter().map(|(loc, value)| format!(" ({} {})", loc, value)).collect();
+                    format!("(sat{})", values.join(""))
+                }
+                None => "(unsat)".to_string(),
+            })
+        });
+        if let Err(code) = drained {
+            return code;
+        }
+
+        manifest.add_output("assert_final");
+        // Paths finish in any order, so sort them for stable output
+        paths.sort();
+        print!("(assert-final (paths {})", manifest.paths);
+        for path in &paths {
+            print!("\n  {}", path)
+        }
+        println!(")");
+        return 0;
+    }
+
+    if mode == Mode::FindEffect {
+        let queue = Arc::new(SegQueue::new());
+        let collector = effect_collector(Arc::new(effect_patterns), opcode_val.clone(), witnesses);
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            queue.clone(),
+            collector.as_ref(),
+        );
+        manifest.time("execution", now);
+
+        let mut matching = 0;
+        let mut opcodes = Vec::new();
+        let drained = drain_paths(&queue, manifest, continue_on_error, |manifest, result| {
+            manifest.paths += 1;
+            if let Some(witnesses) = result {
+                matching += 1;
+                opcodes.extend(witnesses.iter().map(|opcode| opcode.to_string()))
+            }
+        });
+        if let Err(code) = drained {
+            return code;
+        }
+
+        manifest.add_output("find_effect");
+        // Paths finish in any order, so sort the opcodes for stable output
+        opcodes.sort();
+        opcodes.dedup();
+        print!("(find-effect (paths {}) (matching {})", manifest.paths, matching);
+        for opcode in &opcodes {
+            print!("\n  {}", opcode)
+        }
+        println!(")");
+        return 0;
+    }
+
+    if let Some(port) = gdb_port {
+        let queue = Arc::new(SegQueue::new());
+        let now = Instant::now();
+        executor::start_multi_with_strategy(
+            num_threads,
+            timeout,
+            strategy,
+            tasks,
+            shared_state,
+            queue.clone(),
+            &executor::trace_collector,
+        );
+        manifest.time("execution", now);
+
+        let mut paths = Vec::new();
+        let drained = drain_paths(&queue, manifest, continue_on_error, |manifest, (_, mut events)| {
+            manifest.paths += 1;
+            executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
+            paths.push(events)
+        });
+        if let Err(code) = drained {
+            return code;
+        }
+
+        sort_paths_by_branches(&mut paths, |events| events);
+        for (i, events) in paths.iter().enumerate() {
+            let taken: Vec<String> = path_branches(events).iter().map(|branch| branch.to_string()).collect();
+            eprintln!("Path {}: branches [{}]", i, taken.join(", "))
+        }
+        let Some(events) = paths.get(gdb_path) else {
+            eprintln!("No path {} to replay, there are {} paths", gdb_path, paths.len());
+            return 1;
+        };
+        if let Err(e) = gdb::serve(port, events, shared_state, isa_config.pc, isa_config.data_endianness) {
+            eprintln!("GDB stub failed: {}", e);
+            return 1;
+        }
+
+        return 0;
+    }
 
     let queue = Arc::new(SegQueue::new());
 
     let now = Instant::now();
-    executor::start_multi(num_threads, timeout, vec![task], shared_state, queue.clone(), &executor::trace_collector);
+    let dead_paths = Arc::new(SegQueue::new());
+    let mut collector: Box<Collector<B129, TraceQueue<B129>>> =
+        if unsat_core_mode { dead_path_collector(dead_paths.clone()) } else { Box::new(executor::trace_collector) };
+    if !post_processors.is_empty() {
+        collector = postprocess::collector(post_processors.clone(), collector)
+    }
+    executor::start_multi_with_strategy(
+        num_threads,
+        timeout,
+        strategy,
+        tasks,
+        shared_state,
+        queue.clone(),
+        &*collector,
+    );
     log!(log::VERBOSE, &format!("Execution took: {}ms", now.elapsed().as_millis()));
+    if let Some(cache) = &checkpoint_cache {
+        log!(log::VERBOSE, &format!("Checkpoint cache: {}", cache.counts()))
+    }
+    manifest.time("execution", now);
+
+    let mut output: Box<dyn Write> = match matches.opt_str("output").or_else(|| matches.opt_str("traces-out")) {
+        Some(file) => match trace::create_output(&file) {
+            Ok(output) => output,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        },
+        // Traces can be large, so use a 5MB buffer
+        None => Box::new(BufWriter::with_capacity(5 * usize::pow(2, 20), std::io::stdout())),
+    };
 
     let mut paths = Vec::new();
     let mut evtree: Option<EventTree<B129>> = None;
+    let mut saved_traces = Vec::new();
+    let mut audit = UndefinedAudit::default();
+    let mut alignment_paths = Vec::new();
 
-    let write_opts = WriteOpts {
-        define_enum: !matches.opt_present("simplify"),
-        hide_uninteresting: matches.opt_present("hide"),
-        ..WriteOpts::default()
+    let report = |err: TraceError| {
+        let msg = format!("{}", err);
+        err.source_loc().message(source_path.as_ref(), shared_state.symtab.files(), &msg, true, true)
     };
-
-    loop {
-        match queue.pop() {
-            Some(Ok((_, mut events))) if matches.opt_present("dependency") => {
-                let mut events: EvPath<B129> = events
-                    .drain(..)
-                    .rev()
-                    .filter(|ev| {
-                        (ev.is_memory_read_or_write() && !ev.is_ifetch())
-                            || ev.is_smt()
-                            || ev.is_instr()
-                            || ev.is_cycle()
-                            || ev.is_write_reg()
-                    })
-                    .collect();
-                simplify::remove_unused(&mut events);
-                events.push(Event::Instr(opcode_val.clone()));
-                paths.push(events)
-            }
-            Some(Ok((_, mut events))) if matches.opt_present("tree") => {
-                let events: Vec<Event<B129>> = events.drain(..).rev().collect();
-                if let Some(ref mut evtree) = evtree {
-                    evtree.add_events(&events)
-                } else {
-                    evtree = Some(EventTree::from_events(&events))
+    let drained = drain_paths_with(&queue, manifest, continue_on_error, report, |manifest, (_, mut events)| {
+        if let Some(v) = alignment_var {
+            alignment_paths.push(alignment_path(&events, v))
+        }
+        if matches!(mode, Mode::EffectConditions | Mode::Exceptions) {
+            manifest.paths += 1;
+            executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
+            // The first cycle is reserved for initialization
+            let events: EvPath<B129> = events.drain(..).skip_while(|ev| !ev.is_cycle()).collect();
+            paths.push(events)
+        } else if mode == Mode::Dependency && !traces_out {
+            manifest.paths += 1;
+            paths.push(dependency_path(&events, keep_ifetch, &opcode_val))
+        } else if mode == Mode::Tree {
+            manifest.paths += 1;
+            audit.record(&events, shared_state);
+            executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut events);
+            if let Some(ref pattern) = minimize_for {
+                if !simplify::minimize_for(&mut events, pattern) {
+                    eprintln!("No event in path matches {}", matches.opt_str("minimize-for").unwrap())
                 }
             }
-            Some(Ok((_, mut events))) => {
-                if matches.opt_present("simplify") {
-                    simplify::hide_initialization(&mut events);
-                    if matches.opt_present("simplify-registers") {
-                        simplify::remove_extra_register_fields(&mut events);
-                        simplify::remove_repeated_register_reads(&mut events);
-                        simplify::remove_unused_register_assumptions(&mut events);
-                    }
-                    simplify::remove_unused(&mut events);
-                    simplify::propagate_forwards_used_once(&mut events);
-                    simplify::commute_extract(&mut events);
-                    simplify::eval(&mut events);
-                }
-                let events: Vec<Event<B129>> = events.drain(..).rev().collect();
-                let stdout = std::io::stdout();
-                // Traces can be large, so use a 5MB buffer
-                let mut handle = BufWriter::with_capacity(5 * usize::pow(2, 20), stdout.lock());
-                simplify::write_events_with_opts(&mut handle, &events, &shared_state, &write_opts).unwrap();
-                handle.flush().unwrap()
-            }
-            // Error during execution
-            Some(Err(err)) => {
-                let msg = format!("{}", err);
-                eprintln!(
-                    "{}",
-                    err.source_loc().message(source_path.as_ref(), shared_state.symtab.files(), &msg, true, true)
-                );
-                if !matches.opt_present("continue-on-error") {
-                    return 1;
+            if let Some(ref mut evtree) = evtree {
+                evtree.add_events(&events)
+            } else {
+                evtree = Some(EventTree::from_events(&events))
+            }
+        } else {
+            manifest.paths += 1;
+            manifest.add_output("traces");
+            if traces_out {
+                paths.push(dependency_path(&events, keep_ifetch, &opcode_val))
+            }
+            audit.record(&events, shared_state);
+            if matches.opt_present("save-trace") {
+                let mut saved = events.clone();
+                executor::COLLECTOR_ORDER.convert(EventOrder::Execution, &mut saved);
+                saved_traces.push(saved)
+            }
+            executor::COLLECTOR_ORDER.convert(simplify::SIMPLIFY_ORDER, &mut events);
+            if let Some(ref pattern) = minimize_for {
+                if !simplify::minimize_for(&mut events, pattern) {
+                    eprintln!("No event in path matches {}", matches.opt_str("minimize-for").unwrap())
                 }
             }
-            // Empty queue
-            None => break,
+            if matches.opt_present("simplify") {
+                simplify_trace(&mut events, matches.opt_present("simplify-registers"), split_instructions)
+            }
+            let write_opts = WriteOpts { events_order: simplify::SIMPLIFY_ORDER, ..write_opts.clone() };
+            if trace_decode {
+                write_decode_narrative(&mut output, &events, simplify::SIMPLIFY_ORDER, &shared_state).unwrap()
+            }
+            if split_instructions {
+                simplify::SIMPLIFY_ORDER.convert(EventOrder::Execution, &mut events);
+                let write_opts = WriteOpts { events_order: EventOrder::Execution, ..write_opts };
+                write_instruction_segments(&mut output, &events, shared_state, &write_opts).unwrap()
+            } else {
+                simplify::write_events_with_opts(&mut output, &events, &shared_state, &write_opts).unwrap()
+            }
+            output.flush().unwrap()
         }
+    });
+    if let Err(code) = drained {
+        return code;
     }
 
-    if matches.opt_present("tree") {
+    if audit_undefined {
+        audit.print()
+    }
+
+    if let Err((name, msg)) = post_processors.finish(shared_state) {
+        eprintln!("Post-processor {} failed: {}", name, msg);
+        return 1;
+    }
+
+    if let Some(file) = matches.opt_str("save-trace") {
+        if let Err(msg) = trace::save_traces(&saved_traces, &file) {
+            eprintln!("{}", msg);
+            return 1;
+        }
+    }
+
+    if unsat_core_mode && manifest.paths == 0 && manifest.errors == 0 {
+        eprintln!("No feasible paths");
+        for core in dead_path_cores(&dead_paths, &asserted, shared_state) {
+            print_unsat_core(&core)
+        }
+    }
+
+    if mode == Mode::Tree {
         if let Some(ref mut evtree) = evtree {
             evtree.sort();
             evtree.renumber();
@@ -702,21 +4256,138 @@ fn isla_main() -> i32 {
             if matches.opt_present("executable") {
                 evtree.make_executable()
             }
-            let stdout = std::io::stdout();
-            let mut handle = stdout.lock();
-            simplify::write_event_tree(&mut handle, evtree, &shared_state, &write_opts);
-            writeln!(&mut handle).unwrap();
+            manifest.add_output("tree");
+            simplify::write_event_tree(&mut output, evtree, &shared_state, &write_opts);
+            writeln!(&mut output).unwrap();
+            output.flush().unwrap()
+        }
+    }
+
+    if mode == Mode::EffectConditions {
+        manifest.add_output("effect_conditions");
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        for conditional in conditional_effects(
+            &paths,
+            &isa_config.ignored_registers,
+            &isa_config.exception_functions,
+            &shared_state.symtab,
+        ) {
+            write_conditional_effect(&mut handle, &conditional, &shared_state, &write_opts).unwrap()
+        }
+    } else if mode == Mode::Exceptions {
+        manifest.add_output("exceptions");
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        for conditional in exception_conditions(
+            &paths,
+            &isa_config.ignored_registers,
+            &isa_config.exception_functions,
+            &shared_state.symtab,
+        ) {
+            write_conditional_effect(&mut handle, &conditional, &shared_state, &write_opts).unwrap()
         }
     }
 
-    if matches.opt_present("dependency") {
-        match footprint_analysis(num_threads, &[paths], &iarch_config, None) {
+    if alignment_var.is_some() {
+        manifest.add_output("alignment_effects");
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        write_el_matrix(&mut handle, &alignment_levels(&alignment_paths, &isa_config, &shared_state.symtab)).unwrap()
+    }
+
+    if mode == Mode::Dependency {
+        let now = Instant::now();
+        let footprints = footprint_analysis(
+            num_threads,
+            &[paths],
+            &iarch_config,
+            footprint_cache.as_deref(),
+            keep_ifetch,
+            function_cache,
+        );
+        manifest.time("footprint_analysis", now);
+        let mut handle: Box<dyn Write> = match matches.opt_str("footprint-out") {
+            Some(file) => match trace::create_output(&file) {
+                Ok(output) => output,
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    return 1;
+                }
+            },
+            None => Box::new(std::io::stdout()),
+        };
+        let mut check_failed = false;
+        match footprints {
             Ok(footprints) => {
-                for (_, footprint) in footprints {
-                    {
-                        let stdout = std::io::stdout();
-                        let mut handle = stdout.lock();
-                        let _ = footprint.pretty(&mut handle, &shared_state.symtab);
+                manifest.add_output("footprint");
+                for (opcode, footprint) in footprints {
+                    let flags = if report_flags {
+                        Some(footprint.flag_liveness(
+                            &isa_config.flags,
+                            &isa_config.register_bitfields,
+                            &shared_state.symtab,
+                        ))
+                    } else {
+                        None
+                    };
+                    let views = if report_views {
+                        Some(register_view_strings(&footprint, &isa_config, &shared_state.symtab))
+                    } else {
+                        None
+                    };
+                    match footprint_format {
+                        FootprintFormat::Json => {
+                            let mut summary = json!({
+                                "opcode": opcode.to_string(),
+                                "footprint": footprint.summary(&shared_state.symtab),
+                            });
+                            if let Some(flags) = flags {
+                                summary["flags"] = json!(flags)
+                            }
+                            if let Some(views) = views {
+                                summary["register_views"] = json!(views)
+                            }
+                            if let Some(disassembly) = &disassembly {
+                                summary["disassembly"] = json!(disassembly)
+                            }
+                            writeln!(&mut handle, "{}", summary).unwrap()
+                        }
+                        FootprintFormat::Defs => {
+                            let _ = footprint.defs(&mut handle, opcode, &shared_state.symtab);
+                        }
+                        FootprintFormat::Pretty => {
+                            let _ = footprint.pretty(&mut handle, &shared_state.symtab);
+                            if let Some(flags) = flags {
+                                writeln!(&mut handle, "  Flags defined: {}", flags.defined.join(" ")).unwrap();
+                                writeln!(&mut handle, "  Flags used: {}", flags.used.join(" ")).unwrap()
+                            }
+                            if let Some(views) = views {
+                                writeln!(&mut handle, "  Register views: {}", views.join(" ")).unwrap()
+                            }
+                            if let Some(disassembly) = &disassembly {
+                                writeln!(&mut handle, "  Disassembly: {}", disassembly).unwrap()
+                            }
+                        }
+                    }
+                    if check_footprints {
+                        match check_footprint(num_threads, opcode, &footprint, &iarch_config) {
+                            Ok(discrepancies) if discrepancies.is_empty() => eprintln!("Footprint check passed"),
+                            Ok(discrepancies) => {
+                                for discrepancy in discrepancies {
+                                    eprintln!(
+                                        "Footprint check failed, possible footprint analysis bug: {}",
+                                        discrepancy
+                                    )
+                                }
+                                manifest.errors += 1;
+                                check_failed = true
+                            }
+                            Err(footprint_error) => {
+                                eprintln!("{:?}", footprint_error);
+                                return 1;
+                            }
+                        }
                     }
                 }
             }
@@ -725,7 +4396,30 @@ fn isla_main() -> i32 {
                 return 1;
             }
         }
+        handle.flush().unwrap();
+        if check_failed {
+            return 1;
+        }
     }
 
     0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use isla_lib::bitvector::b64::B64;
+
+    #[test]
+    fn partial_instruction_segments() {
+        let segments = parse_partial_instruction::<B64>("00xx1 x:4 ax:8 1x").unwrap();
+        let segments: Vec<String> = segments.iter().map(|segment| segment.to_string()).collect();
+        assert_eq!(segments, vec!["#b00001", "x:4", "ax:8", "#b10"]);
+        // Don't-care bits are zero, rather than fields named x
+        match &parse_partial_instruction::<B64>("00xx1").unwrap()[..] {
+            [InstructionSegment::Concrete(bv)] => assert_eq!(*bv, B64::new(1, 5)),
+            segments => panic!("unexpected segments {:?}", segments),
+        }
+        assert!(parse_partial_instruction::<B64>("00y1").is_err())
+    }
+}
@@ -0,0 +1,667 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+//! A minimal GDB remote serial protocol stub for inspecting a single
+//! path of the footprint function. The path is executed in full
+//! beforehand, so the stub replays its recorded events rather than
+//! stepping the symbolic executor itself. A single step runs to the
+//! next Sail function call, write to the program counter, or cycle,
+//! and registers and memory are reported with the values the path
+//! has seen so far, taking symbolic values from a model of the path's
+//! constraints. Function breakpoints are set with
+//! `monitor break <function>`, as GDB has no notion of Sail
+//! functions; `monitor help` lists the other commands.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufReader, Read, Write};
+use std::net::TcpListener;
+
+use isla_lib::bitvector::BV;
+use isla_lib::ir::{Name, SharedState, Ty, Val};
+use isla_lib::memory::{byte_order, Address, Endianness};
+use isla_lib::smt;
+use isla_lib::smt::smtlib::Exp;
+use isla_lib::smt::{Checkpoint, Event, Model, SmtResult, Solver, Sym};
+use isla_lib::zencode;
+
+/// A register exposed to GDB, with its width rounded up to a whole
+/// number of bytes
+struct Register {
+    name: Name,
+    display: String,
+    bytes: u32,
+}
+
+enum Stop {
+    Breakpoint,
+    Step,
+    Finished,
+}
+
+/// Concrete values for the symbolic register and memory values in a
+/// path, from a model of its constraints. Only values of up to 64
+/// bits are included.
+fn witness<B: BV>(events: &[Event<B>]) -> HashMap<Sym, Val<B>> {
+    let mut vars = Vec::new();
+    for event in events {
+        match event {
+            Event::ReadReg(_, _, Val::Symbolic(v), _)
+            | Event::WriteReg(_, _, Val::Symbolic(v), _)
+            | Event::AssumeReg(_, _, Val::Symbolic(v))
+            | Event::ReadMem { value: Val::Symbolic(v), .. }
+            | Event::WriteMem { data: Val::Symbolic(v), .. } => vars.push(*v),
+            _ => (),
+        }
+        match event {
+            Event::ReadMem { address: Val::Symbolic(v), .. } | Event::WriteMem { address: Val::Symbolic(v), .. } => {
+                vars.push(*v)
+            }
+            _ => (),
+        }
+    }
+
+    let mut values = HashMap::new();
+    let cfg = smt::Config::new();
+    let ctx = smt::Context::new(cfg);
+    let mut solver = Solver::from_checkpoint(&ctx, Checkpoint::from_events(events.to_vec()));
+    if solver.check_sat() != SmtResult::Sat {
+        return values;
+    }
+    let mut model = Model::new(&solver);
+    for v in vars {
+        match model.get_var(v) {
+            Ok(Some(Exp::Bits64(bv))) => {
+                values.insert(v, Val::Bits(B::new(bv.lower_u64(), bv.len())));
+            }
+            Ok(Some(Exp::Bool(b))) => {
+                values.insert(v, Val::Bool(b));
+            }
+            _ => (),
+        }
+    }
+    values
+}
+
+struct Replay<'ev, 'ir, B> {
+    events: &'ev [Event<B>],
+    witness: HashMap<Sym, Val<B>>,
+    position: usize,
+    shared_state: &'ev SharedState<'ir, B>,
+    registers: Vec<Register>,
+    values: HashMap<Name, Val<B>>,
+    memory: HashMap<Address, u8>,
+    stack: Vec<Name>,
+    function_breakpoints: HashSet<Name>,
+    address_breakpoints: HashSet<Address>,
+    pc: Name,
+    endianness: Endianness,
+}
+
+impl<'ev, 'ir, B: BV> Replay<'ev, 'ir, B> {
+    fn new(events: &'ev [Event<B>], shared_state: &'ev SharedState<'ir, B>, pc: Name, endianness: Endianness) -> Self {
+        let mut registers: Vec<Register> = shared_state
+            .registers
+            .iter()
+            .filter_map(|(name, ty)| {
+                let bytes = match ty {
+                    Ty::Bits(sz) if *sz > 0 => (sz - 1) / 8 + 1,
+                    Ty::Bool => 1,
+                    _ => return None,
+                };
+                Some(Register { name: *name, display: zencode::decode(shared_state.symtab.to_str(*name)), bytes })
+            })
+            .collect();
+        registers.sort_by(|r1, r2| r1.display.cmp(&r2.display));
+        Replay {
+            events,
+            witness: witness(events),
+            position: 0,
+            shared_state,
+            registers,
+            values: HashMap::new(),
+            memory: HashMap::new(),
+            stack: Vec::new(),
+            function_breakpoints: HashSet::new(),
+            address_breakpoints: HashSet::new(),
+            pc,
+            endianness,
+        }
+    }
+
+    /// The value, or its value in the model if it is symbolic
+    fn concrete<'a>(&'a self, value: &'a Val<B>) -> &'a Val<B> {
+        match value {
+            Val::Symbolic(v) => self.witness.get(v).unwrap_or(value),
+            _ => value,
+        }
+    }
+
+    fn record_memory(&mut self, address: &Val<B>, bytes: u32, value: &Val<B>) {
+        if let (Val::Bits(address), Val::Bits(value)) = (self.concrete(address), self.concrete(value)) {
+            if value.len() == bytes * 8 {
+                let addresses = byte_order(self.endianness, address.lower_u64(), bytes);
+                for (address, byte) in addresses.into_iter().zip(value.to_be_bytes()) {
+                    self.memory.insert(address, byte);
+                }
+            }
+        }
+    }
+
+    /// Apply the next event, returning whether it is a stopping point
+    /// for a single step, and whether it hit a breakpoint.
+    fn apply(&mut self) -> (bool, bool) {
+        let event = &self.events[self.position];
+        self.position += 1;
+        match event {
+            Event::ReadReg(name, accessor, value, _) | Event::WriteReg(name, accessor, value, _)
+                if accessor.is_empty() =>
+            {
+                self.values.insert(*name, value.clone());
+                if *name == self.pc && matches!(event, Event::WriteReg(..)) {
+                    let hit = match self.concrete(value) {
+                        Val::Bits(bv) => self.address_breakpoints.contains(&bv.lower_u64()),
+                        _ => false,
+                    };
+                    return (true, hit);
+                }
+                (false, false)
+            }
+            Event::AssumeReg(name, accessor, value) if accessor.is_empty() => {
+                self.values.insert(*name, value.clone());
+                (false, false)
+            }
+            Event::ReadMem { value, address, bytes, .. } => {
+                self.record_memory(address, *bytes, value);
+                (false, false)
+            }
+            Event::WriteMem { address, data, bytes, .. } => {
+                self.record_memory(address, *bytes, data);
+                (false, false)
+            }
+            Event::Function { name, call: true } => {
+                self.stack.push(*name);
+                (true, self.function_breakpoints.contains(name))
+            }
+            Event::Function { name, call: false } => {
+                if let Some(i) = self.stack.iter().rposition(|f| f == name) {
+                    self.stack.truncate(i)
+                }
+                (false, false)
+            }
+            Event::Cycle => (true, false),
+            _ => (false, false),
+        }
+    }
+
+    fn step(&mut self) -> Stop {
+        while self.position < self.events.len() {
+            let (stop, hit) = self.apply();
+            if hit {
+                return Stop::Breakpoint;
+            } else if stop {
+                return Stop::Step;
+            }
+        }
+        Stop::Finished
+    }
+
+    fn resume(&mut self) -> Stop {
+        while self.position < self.events.len() {
+            if let (_, true) = self.apply() {
+                return Stop::Breakpoint;
+            }
+        }
+        Stop::Finished
+    }
+
+    /// The register's value as little-endian hex, with `xx` for
+    /// each byte which is not (yet) known concretely
+    fn register_hex(&self, register: &Register) -> String {
+        match self.values.get(&register.name).map(|value| self.concrete(value)) {
+            Some(Val::Bits(bv)) if bv.len() <= register.bytes * 8 => {
+                bv.zero_extend(register.bytes * 8).to_le_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+            }
+            Some(Val::Bool(b)) => format!("{:02x}", *b as u8),
+            _ => "xx".repeat(register.bytes as usize),
+        }
+    }
+
+    /// Reply to an `m` packet reading memory, with the bytes known up
+    /// to the first one which is not
+    fn read_memory(&self, args: &str) -> String {
+        match parse_address_length(args) {
+            Some((address, length)) if address.checked_add(length).is_some() => {
+                let bytes: Vec<u8> =
+                    (address..address + length).map_while(|addr| self.memory.get(&addr).copied()).collect();
+                if bytes.is_empty() && length > 0 {
+                    "E01".to_string()
+                } else {
+                    hex_encode(&bytes)
+                }
+            }
+            _ => "E01".to_string(),
+        }
+    }
+
+    fn target_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n<target version=\"1.0\">\n<feature name=\"org.isla.sail\">\n",
+        );
+        for (i, register) in self.registers.iter().enumerate() {
+            let ty = if register.name == self.pc { "code_ptr" } else { "int" };
+            xml.push_str(&format!(
+                "<reg name=\"{}\" bitsize=\"{}\" regnum=\"{}\" type=\"{}\"/>\n",
+                escape_xml(&register.display),
+                register.bytes * 8,
+                i,
+                ty
+            ))
+        }
+        xml.push_str("</feature>\n</target>\n");
+        xml
+    }
+
+    fn function(&self, name: &str) -> Result<Name, String> {
+        match self.shared_state.symtab.get(&zencode::encode(name)) {
+            Some(f) if self.shared_state.functions.contains_key(&f) => Ok(f),
+            _ => Err(format!("No function {}\n", name)),
+        }
+    }
+
+    fn monitor(&mut self, command: &str) -> String {
+        let mut words = command.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("break"), Some(name)) => match self.function(name) {
+                Ok(f) => {
+                    self.function_breakpoints.insert(f);
+                    format!("Breakpoint on calls to {}\n", name)
+                }
+                Err(msg) => msg,
+            },
+            (Some("delete"), Some(name)) => match self.function(name) {
+                Ok(f) => {
+                    self.function_breakpoints.remove(&f);
+                    format!("Deleted breakpoint on {}\n", name)
+                }
+                Err(msg) => msg,
+            },
+            (Some("backtrace"), None) => {
+                let mut out = String::new();
+                for (i, f) in self.stack.iter().rev().enumerate() {
+                    out.push_str(&format!("#{} {}\n", i, zencode::decode(self.shared_state.symtab.to_str(*f))))
+                }
+                out
+            }
+            (Some("event"), None) => format!("{} of {} events replayed\n", self.position, self.events.len()),
+            _ => "Commands: break <function>, delete <function>, backtrace, event\n".to_string(),
+        }
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn stop_reply(stop: Stop) -> String {
+    match stop {
+        Stop::Breakpoint | Stop::Step => "S05".to_string(),
+        Stop::Finished => "W00".to_string(),
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Read a single packet, acknowledging it, or return `None` when
+/// the connection is closed. A packet with the wrong checksum is
+/// rejected with `-`, so GDB sends it again.
+fn read_packet<R: Read, W: Write>(reader: &mut R, ack: &mut W) -> std::io::Result<Option<String>> {
+    let mut byte = [0u8];
+    loop {
+        loop {
+            if reader.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            // Skip acknowledgements, and interrupt requests, which we
+            // can ignore as every command replies immediately
+            if byte[0] == b'$' {
+                break;
+            }
+        }
+        let mut data = Vec::new();
+        loop {
+            if reader.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] == b'#' {
+                break;
+            }
+            data.push(byte[0])
+        }
+        let mut sum = [0u8; 2];
+        reader.read_exact(&mut sum)?;
+        if std::str::from_utf8(&sum).ok().and_then(|sum| u8::from_str_radix(sum, 16).ok()) == Some(checksum(&data)) {
+            ack.write_all(b"+")?;
+            return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+        }
+        ack.write_all(b"-")?
+    }
+}
+
+fn write_packet<W: Write>(stream: &mut W, data: &str) -> std::io::Result<()> {
+    let mut escaped = Vec::new();
+    for byte in data.bytes() {
+        if matches!(byte, b'#' | b'$' | b'}' | b'*') {
+            escaped.push(b'}');
+            escaped.push(byte ^ 0x20)
+        } else {
+            escaped.push(byte)
+        }
+    }
+    stream.write_all(b"$")?;
+    stream.write_all(&escaped)?;
+    write!(stream, "#{:02x}", checksum(&escaped))
+}
+
+/// Reply to a `qXfer` read of `offset` and `length` from `contents`
+fn transfer(contents: &str, args: &str) -> String {
+    let range = args.split_once(',').and_then(|(offset, length)| {
+        Some((usize::from_str_radix(offset, 16).ok()?, usize::from_str_radix(length, 16).ok()?))
+    });
+    match range {
+        Some((offset, length)) if offset <= contents.len() => {
+            let end = std::cmp::min(offset.saturating_add(length), contents.len());
+            let chunk = &contents[offset..end];
+            if end == contents.len() {
+                format!("l{}", chunk)
+            } else {
+                format!("m{}", chunk)
+            }
+        }
+        _ => "E01".to_string(),
+    }
+}
+
+fn parse_address_length(args: &str) -> Option<(Address, u64)> {
+    let (address, length) = args.split_once(',')?;
+    Some((u64::from_str_radix(address, 16).ok()?, u64::from_str_radix(length, 16).ok()?))
+}
+
+/// Serve the events of a single path to one GDB connection on
+/// `127.0.0.1:port`, until the debugger detaches or kills the
+/// target.
+pub fn serve<B: BV>(
+    port: u16,
+    events: &[Event<B>],
+    shared_state: &SharedState<B>,
+    pc: Name,
+    endianness: Endianness,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("Waiting for GDB to connect on 127.0.0.1:{}", port);
+    let (mut stream, _) = listener.accept()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut replay = Replay::new(events, shared_state, pc, endianness);
+    let target_xml = replay.target_xml();
+
+    while let Some(packet) = read_packet(&mut reader, &mut stream)? {
+        let reply = if packet.starts_with("qSupported") {
+            "PacketSize=4000;qXfer:features:read+".to_string()
+        } else if let Some(args) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            transfer(&target_xml, args)
+        } else if packet == "qAttached" {
+            "1".to_string()
+        } else if packet == "qfThreadInfo" {
+            "m1".to_string()
+        } else if packet == "qsThreadInfo" {
+            "l".to_string()
+        } else if packet == "qC" {
+            "QC1".to_string()
+        } else if packet.starts_with('H') {
+            "OK".to_string()
+        } else if packet == "?" {
+            "S05".to_string()
+        } else if packet == "g" {
+            replay.registers.iter().map(|register| replay.register_hex(register)).collect()
+        } else if let Some(n) = packet.strip_prefix('p') {
+            match usize::from_str_radix(n, 16).ok().and_then(|n| replay.registers.get(n)) {
+                Some(register) => replay.register_hex(register),
+                None => "E01".to_string(),
+            }
+        } else if let Some(args) = packet.strip_prefix('m') {
+            replay.read_memory(args)
+        } else if packet.starts_with('s') {
+            stop_reply(replay.step())
+        } else if packet.starts_with('c') {
+            stop_reply(replay.resume())
+        } else if let Some(args) = packet.strip_prefix("Z0,") {
+            match parse_address_length(args) {
+                Some((address, _)) => {
+                    replay.address_breakpoints.insert(address);
+                    "OK".to_string()
+                }
+                None => "E01".to_string(),
+            }
+        } else if let Some(args) = packet.strip_prefix("z0,") {
+            match parse_address_length(args) {
+                Some((address, _)) => {
+                    replay.address_breakpoints.remove(&address);
+                    "OK".to_string()
+                }
+                None => "E01".to_string(),
+            }
+        } else if let Some(hex) = packet.strip_prefix("qRcmd,") {
+            match hex_decode(hex).map(|command| String::from_utf8_lossy(&command).into_owned()) {
+                Some(command) => hex_encode(replay.monitor(&command).as_bytes()),
+                None => "E01".to_string(),
+            }
+        } else if packet == "k" || packet.starts_with('D') {
+            write_packet(&mut stream, "OK")?;
+            break;
+        } else {
+            String::new()
+        };
+        write_packet(&mut stream, &reply)?
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use isla_lib::bitvector::b64::B64;
+    use isla_lib::ir::{IRTypeInfo, Symtab};
+    use isla_lib::ir_parse::parse_ir;
+    use isla_lib::smt::smtlib::{self, bits64, Def};
+    use isla_lib::smt::{DefAttrs, ReadOpts};
+    use isla_lib::source_loc::SourceLoc;
+
+    const FIXTURE: &str = r#"
+register zPC : %bv64
+
+register zR0 : %bv64
+
+val zf : (%unit) -> %unit
+
+fn zf(zx) {
+  return = ();
+  end
+}
+
+val zg : (%unit) -> %unit
+
+fn zg(zx) {
+  return = ();
+  end
+}
+"#;
+
+    fn read_packets(input: &[u8]) -> (Vec<String>, Vec<u8>) {
+        let mut reader = input;
+        let mut ack = Vec::new();
+        let mut packets = Vec::new();
+        while let Some(packet) = read_packet(&mut reader, &mut ack).unwrap() {
+            packets.push(packet)
+        }
+        (packets, ack)
+    }
+
+    #[test]
+    fn packet_framing() {
+        let (packets, ack) = read_packets(b"+$qC#b4\x03+$g#67");
+        assert_eq!(packets, vec!["qC", "g"]);
+        assert_eq!(ack, b"++");
+
+        // GDB re-sends a packet rejected for a bad checksum
+        let (packets, ack) = read_packets(b"$qC#00$qC#b4");
+        assert_eq!(packets, vec!["qC"]);
+        assert_eq!(ack, b"-+");
+
+        let mut output = Vec::new();
+        write_packet(&mut output, "a#b$c}d*").unwrap();
+        assert_eq!(output, b"$a}\x03b}\x04c}\x5dd}\x0a#ec");
+        let (packets, _) = read_packets(&output);
+        assert_eq!(packets, vec!["a}\u{3}b}\u{4}c}]d}\n"])
+    }
+
+    #[test]
+    fn packet_arguments() {
+        let xml = "0123456789";
+        assert_eq!(transfer(xml, "0,4"), "m0123");
+        assert_eq!(transfer(xml, "8,4"), "l89");
+        assert_eq!(transfer(xml, "a,4"), "l");
+        assert_eq!(transfer(xml, "4,ffffffffffffffff"), "l456789");
+        assert_eq!(transfer(xml, "b,4"), "E01");
+        assert_eq!(transfer(xml, "4"), "E01");
+
+        assert_eq!(hex_decode("6869"), Some(b"hi".to_vec()));
+        assert_eq!(hex_decode(""), Some(Vec::new()));
+        assert_eq!(hex_decode("686"), None);
+        assert_eq!(hex_decode("6g"), None);
+        assert_eq!(hex_encode(b"hi"), "6869");
+
+        assert_eq!(parse_address_length("1000,8"), Some((0x1000, 8)));
+        assert_eq!(parse_address_length("1000"), None);
+        assert_eq!(parse_address_length("x,8"), None)
+    }
+
+    #[test]
+    fn replay() {
+        let mut symtab = Symtab::new();
+        let defs = parse_ir::<B64>(FIXTURE, &mut symtab).map_err(|_| "parse failed").unwrap();
+        let type_info = IRTypeInfo::new(&defs);
+        let pc = symtab.lookup("zPC");
+        let r0 = symtab.lookup("zR0");
+        let f = symtab.lookup("zf");
+        let g = symtab.lookup("zg");
+        let shared_state = SharedState::new(
+            symtab,
+            &defs,
+            type_info,
+            HashSet::new(),
+            HashSet::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        );
+
+        let v = Sym::from_u32(0);
+        let bits = |bits| Val::Bits(B64::new(bits, 64));
+        let events: Vec<Event<B64>> = vec![
+            Event::Cycle,
+            Event::Function { name: f, call: true },
+            Event::WriteReg(pc, Vec::new(), bits(0x1004), SourceLoc::unknown()),
+            Event::ReadReg(r0, Vec::new(), bits(0x2a), SourceLoc::unknown()),
+            Event::ReadMem {
+                value: Val::Bits(B64::new(0x1122, 16)),
+                read_kind: Val::Bool(false),
+                address: bits(0x2000),
+                bytes: 2,
+                tag_value: None,
+                opts: ReadOpts::default(),
+                region: "default",
+                info: SourceLoc::unknown(),
+            },
+            Event::Function { name: g, call: true },
+            Event::Function { name: g, call: false },
+            Event::Function { name: f, call: false },
+            Event::Cycle,
+            Event::Smt(Def::DeclareConst(v, smtlib::Ty::BitVec(64)), DefAttrs::default(), SourceLoc::unknown()),
+            Event::Smt(
+                Def::Assert(Exp::Eq(Box::new(Exp::Var(v)), Box::new(bits64(0x1008, 64)))),
+                DefAttrs::default(),
+                SourceLoc::unknown(),
+            ),
+            Event::WriteReg(pc, Vec::new(), Val::Symbolic(v), SourceLoc::unknown()),
+            Event::Cycle,
+        ];
+
+        let mut replay = Replay::new(&events, &shared_state, pc, Endianness::Little);
+        let pc_register = replay.registers.iter().position(|register| register.name == pc).unwrap();
+        assert_eq!(replay.register_hex(&replay.registers[pc_register]), "xx".repeat(8));
+
+        assert!(matches!(replay.step(), Stop::Step));
+        assert!(matches!(replay.step(), Stop::Step));
+        assert_eq!(replay.monitor("backtrace"), "#0 f\n");
+        assert!(matches!(replay.step(), Stop::Step));
+        assert_eq!(replay.register_hex(&replay.registers[pc_register]), "0410000000000000");
+
+        assert_eq!(replay.monitor("break g"), "Breakpoint on calls to g\n");
+        assert_eq!(replay.monitor("break h"), "No function h\n");
+        assert!(matches!(replay.resume(), Stop::Breakpoint));
+        assert_eq!(replay.monitor("backtrace"), "#0 g\n#1 f\n");
+        assert_eq!(replay.read_memory("2000,2"), "2211");
+        assert_eq!(replay.read_memory("1fff,4"), "E01");
+        assert_eq!(replay.read_memory("2001,4"), "11");
+        assert_eq!(replay.read_memory("ffffffffffffffff,2"), "E01");
+
+        // The symbolic PC takes its value from a model of the path
+        replay.address_breakpoints.insert(0x1008);
+        assert!(matches!(replay.resume(), Stop::Breakpoint));
+        assert_eq!(replay.monitor("backtrace"), "");
+        assert_eq!(replay.register_hex(&replay.registers[pc_register]), "0810000000000000");
+        assert!(matches!(replay.step(), Stop::Step));
+        assert!(matches!(replay.resume(), Stop::Finished));
+        assert_eq!(replay.monitor("event"), format!("{} of {} events replayed\n", events.len(), events.len()))
+    }
+}
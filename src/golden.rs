@@ -0,0 +1,199 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Golden test suites for `isla-footprint --test`. A suite is a YAML
+//! list of instructions, each giving the parts of its footprint
+//! summary (as printed by `--footprint-format json`) that are
+//! expected:
+//!
+//! ```text
+//! - name: add
+//!   opcode: "01010203"
+//!   hex: true
+//!   expect:
+//!     registers_read: [PC, R2, R3]
+//!     registers_written: [PC, R1]
+//!     memory_reads: []
+//!     is_branch: false
+//! ```
+//!
+//! The `opcode`, `hex`, `partial`, and `constraints` fields are as for
+//! `--stdin` requests. Fields of the summary which are not given in
+//! `expect` are not checked, and lists are compared as sets.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+
+use isla::io::Request;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    /// A name for the entry in the report, which defaults to the
+    /// opcode
+    #[serde(default)]
+    pub name: Option<String>,
+    pub opcode: String,
+    #[serde(default)]
+    pub hex: bool,
+    #[serde(default)]
+    pub partial: bool,
+    #[serde(default)]
+    pub constraints: Vec<String>,
+    /// The expected footprint summary fields
+    pub expect: BTreeMap<String, Value>,
+}
+
+impl TestCase {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.opcode)
+    }
+
+    pub fn request(&self) -> Request {
+        Request {
+            id: Value::Null,
+            opcode: self.opcode.clone(),
+            hex: self.hex,
+            partial: self.partial,
+            constraints: self.constraints.clone(),
+        }
+    }
+}
+
+pub fn read_suite(path: &str) -> Result<Vec<TestCase>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open test suite {}: {}", path, e))?;
+    serde_yaml_ng::from_reader(file).map_err(|e| format!("Failed to parse test suite {}: {}", path, e))
+}
+
+fn value_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// Compare the expected fields against a footprint summary, returning
+/// a line describing each difference.
+pub fn compare(expect: &BTreeMap<String, Value>, summary: &Value) -> Vec<String> {
+    let mut diffs = Vec::new();
+    for (field, expected) in expect {
+        let actual = match summary.get(field) {
+            Some(actual) => actual,
+            None => {
+                diffs.push(format!("{}: not a footprint summary field", field));
+                continue;
+            }
+        };
+        match (expected, actual) {
+            (Value::Array(expected), Value::Array(actual)) => {
+                let expected: Vec<String> = expected.iter().map(value_string).collect();
+                let actual: Vec<String> = actual.iter().map(value_string).collect();
+                let missing: Vec<&str> = expected.iter().filter(|v| !actual.contains(v)).map(String::as_str).collect();
+                let unexpected: Vec<&str> =
+                    actual.iter().filter(|v| !expected.contains(v)).map(String::as_str).collect();
+                if !missing.is_empty() {
+                    diffs.push(format!("{}: missing {}", field, missing.join(" ")))
+                }
+                if !unexpected.is_empty() {
+                    diffs.push(format!("{}: unexpected {}", field, unexpected.join(" ")))
+                }
+            }
+            _ if expected != actual => {
+                diffs.push(format!("{}: expected {}, got {}", field, value_string(expected), value_string(actual)))
+            }
+            _ => (),
+        }
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn expect(fields: Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(fields).unwrap()
+    }
+
+    #[test]
+    fn compare_fields() {
+        let summary = json!({ "registers_read": ["PC", "R2", "R3"], "memory_reads": [], "is_branch": false });
+
+        // Lists are compared as sets
+        assert!(
+            compare(&expect(json!({ "registers_read": ["R3", "PC", "R2"], "memory_reads": [] })), &summary).is_empty()
+        );
+        assert_eq!(
+            compare(&expect(json!({ "registers_read": ["PC", "R1", "R2", "R3", "R4"] })), &summary),
+            vec!["registers_read: missing R1 R4"]
+        );
+        assert_eq!(
+            compare(&expect(json!({ "registers_read": ["PC"] })), &summary),
+            vec!["registers_read: unexpected R2 R3"]
+        );
+        assert_eq!(
+            compare(&expect(json!({ "registers_read": ["PC", "R1", "R2"] })), &summary),
+            vec!["registers_read: missing R1", "registers_read: unexpected R3"]
+        );
+
+        assert_eq!(
+            compare(&expect(json!({ "is_branch": true })), &summary),
+            vec!["is_branch: expected true, got false"]
+        );
+        assert_eq!(compare(&expect(json!({ "memory_reads": 0 })), &summary), vec!["memory_reads: expected 0, got []"]);
+        assert_eq!(
+            compare(&expect(json!({ "is_brnach": false })), &summary),
+            vec!["is_brnach: not a footprint summary field"]
+        )
+    }
+
+    #[test]
+    fn suite_fields() {
+        let path = std::env::temp_dir().join(format!("isla-golden-test-{}.yaml", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        std::fs::write(path, "- opcode: \"01010203\"\n  hex: true\n  expect:\n    registers_read: [PC, R2]\n    is_branch: false\n- name: load\n  opcode: \"0000001x\"\n  partial: true\n  constraints: [\"(= x #b1)\"]\n  expect: {}\n").unwrap();
+        let suite = read_suite(path).unwrap();
+        assert_eq!(suite.len(), 2);
+        assert_eq!((suite[0].name(), suite[0].hex, suite[0].partial), ("01010203", true, false));
+        assert_eq!(suite[0].expect["registers_read"], json!(["PC", "R2"]));
+        assert_eq!(suite[0].expect["is_branch"], json!(false));
+        assert_eq!((suite[1].name(), suite[1].hex, suite[1].partial), ("load", false, true));
+        assert_eq!(suite[1].request().constraints, vec!["(= x #b1)"]);
+
+        std::fs::write(path, "- opcode: \"01010203\"\n  hexadecimal: true\n  expect: {}\n").unwrap();
+        assert!(read_suite(path).is_err());
+        std::fs::write(path, "- name: add\n  expect: {}\n").unwrap();
+        assert!(read_suite(path).is_err());
+        std::fs::remove_file(path).unwrap()
+    }
+}
@@ -0,0 +1,112 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The JSON lines protocol shared by `isla-footprint --stdin` and
+//! `isla-server`, which let another process (such as a test
+//! generator) drive isla without re-initializing the architecture
+//! for every instruction. Each line of input is one JSON request, and
+//! each response is written as one line containing the request's `id`
+//! and either a `result` or an `error` message.
+//!
+//! For `isla-footprint --stdin` each request describes one
+//! instruction:
+//!
+//! ```text
+//! {"id": 1, "opcode": "8b020020", "hex": true}
+//! {"id": 2, "opcode": "11010101 Rt:5", "partial": true, "constraints": ["(bvult Rt #b11110)"]}
+//! ```
+//!
+//! Without `hex` or `partial` the opcode is assembled. With
+//! `--unsat-core`, a result without any traces lists the
+//! `unsat_cores` of the paths which became contradictory.
+//!
+//! `isla-server` instead takes a [Call] naming a method and its
+//! parameters:
+//!
+//! ```text
+//! {"id": 1, "method": "footprint", "params": {"opcode": "0x8b020020"}}
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Result, Write};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Request {
+    /// An arbitrary value which is copied into the response
+    #[serde(default)]
+    pub id: Value,
+    pub opcode: String,
+    /// The opcode is in hexadecimal, rather than assembly
+    #[serde(default)]
+    pub hex: bool,
+    /// The opcode is a partial instruction, as for `--partial`
+    #[serde(default)]
+    pub partial: bool,
+    /// Constraints on the symbolic segments of a partial instruction,
+    /// as for `--instruction-constraint`
+    #[serde(default)]
+    pub constraints: Vec<String>,
+}
+
+/// A request to `isla-server`
+#[derive(Debug, Deserialize)]
+pub struct Call {
+    /// An arbitrary value which is copied into the response
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Read a request from each non-empty line of the input. Lines which
+/// are not valid requests give an error message rather than ending
+/// the stream.
+pub fn requests<T: DeserializeOwned, R: BufRead>(
+    input: R,
+) -> impl Iterator<Item = Result<std::result::Result<T, String>>> {
+    input
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| line.map(|line| serde_json::from_str(&line).map_err(|e| format!("Invalid request: {}", e))))
+}
+
+/// Write the response to a request as a single line, flushing the
+/// output so the client can read it immediately.
+pub fn respond<W: Write>(output: &mut W, id: &Value, result: std::result::Result<Value, String>) -> Result<()> {
+    let response = match result {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(msg) => json!({ "id": id, "error": msg }),
+    };
+    writeln!(output, "{}", response)?;
+    output.flush()
+}
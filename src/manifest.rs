@@ -0,0 +1,297 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2019, 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A run manifest is a machine-readable (JSON) summary of a single
+//! invocation of a tool, recording its inputs, outputs, timing, and
+//! path and error counts so runs can be tracked by experiment
+//! pipelines.
+//!
+//! At the end of a run the manifest also decides the run's
+//! [`Outcome`], which determines the process exit code.
+
+use isla_lib::bitvector::b129::B129;
+use isla_lib::smt::{
+    checkpoint_restore_counts, fork_check_counts, CheckpointCache, CheckpointCacheCounts, CheckpointRestoreCounts,
+    ForkCheckCounts,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The outcome of a run. By default a run exits with 0 or 1 as it
+/// always has, but with `--status` each outcome has its own exit code
+/// so scripts can distinguish categories of failure:
+///
+/// | Code | Default | Outcome         | Meaning                                                          |
+/// |------|---------|-----------------|------------------------------------------------------------------|
+/// | 0    | 0       | `success`       | the run completed and no path errored                            |
+/// | 1    | 1       | `failure`       | any other failure, e.g. bad arguments or configuration           |
+/// | 2    | 0       | `no_paths`      | symbolic execution finished without producing any paths          |
+/// | 3    | 0       | `all_errored`   | with `-c`, every path ended in an error                          |
+/// | 4    | 0       | `some_errored`  | with `-c`, some paths completed, but at least one ended in error |
+/// | 5    | 0       | `unsat`         | the constraints on the instruction are unsatisfiable             |
+/// | 6    | 1       | `decode_failed` | the instruction could not be turned into an opcode               |
+/// | 7    | 1       | `errored`       | without `-c`, a path ended in an error, which stopped the run    |
+///
+/// Without `-c` the run stops at the first error, so how many paths
+/// completed before it depends on the order in which they were
+/// explored, and the outcome is always `errored`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success = 0,
+    Failure = 1,
+    NoPaths = 2,
+    AllErrored = 3,
+    SomeErrored = 4,
+    Unsat = 5,
+    DecodeFailed = 6,
+    Errored = 7,
+}
+
+impl Outcome {
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+
+    /// The exit code used without `--status`
+    pub fn default_exit_code(self) -> i32 {
+        use Outcome::*;
+        match self {
+            Success | NoPaths | AllErrored | SomeErrored | Unsat => 0,
+            Failure | DecodeFailed | Errored => 1,
+        }
+    }
+
+    /// The outcome of symbolically executing an instruction to
+    /// completion, given how many paths it had and how many of them
+    /// ended in an error.
+    pub fn from_counts(paths: usize, errors: usize) -> Self {
+        match (paths, errors) {
+            (0, 0) => Outcome::NoPaths,
+            (_, 0) => Outcome::Success,
+            (0, _) => Outcome::AllErrored,
+            (_, _) => Outcome::SomeErrored,
+        }
+    }
+}
+
+/// A symbolic segment of a partial instruction, along with the
+/// variable standing for it in traces.
+#[derive(Clone, Debug, Serialize)]
+pub struct Segment {
+    pub name: String,
+    pub size: u32,
+    pub var: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Manifest {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    start: Instant,
+    #[serde(skip)]
+    report_status: bool,
+    #[serde(skip)]
+    cache: Option<Arc<CheckpointCache<B129>>>,
+    pub tool: String,
+    pub mode: String,
+    pub arch: Option<String>,
+    pub arch_hash: Option<String>,
+    pub config: Option<String>,
+    pub config_hash: Option<String>,
+    pub instructions: Vec<String>,
+    pub segments: Vec<Segment>,
+    pub solver_seed: Option<u32>,
+    pub outputs: Vec<String>,
+    pub timing_ms: BTreeMap<String, u128>,
+    pub paths: usize,
+    pub errors: usize,
+    pub fork_checks: Option<ForkCheckCounts>,
+    pub checkpoint_restores: Option<CheckpointRestoreCounts>,
+    pub checkpoint_cache: Option<CheckpointCacheCounts>,
+    pub outcome: Option<Outcome>,
+    pub exit_code: Option<i32>,
+}
+
+/// Compute the hex SHA256 digest of a file, or None if it cannot be read
+fn hash_file<P: AsRef<Path>>(file: P) -> Option<String> {
+    let contents = std::fs::read(file).ok()?;
+    Some(format!("{:x}", Sha256::digest(&contents)))
+}
+
+impl Manifest {
+    pub fn new(tool: &str) -> Self {
+        Manifest {
+            path: None,
+            start: Instant::now(),
+            report_status: false,
+            cache: None,
+            tool: tool.to_string(),
+            mode: "default".to_string(),
+            arch: None,
+            arch_hash: None,
+            config: None,
+            config_hash: None,
+            instructions: Vec::new(),
+            segments: Vec::new(),
+            solver_seed: None,
+            outputs: Vec::new(),
+            timing_ms: BTreeMap::new(),
+            paths: 0,
+            errors: 0,
+            fork_checks: None,
+            checkpoint_restores: None,
+            checkpoint_cache: None,
+            outcome: None,
+            exit_code: None,
+        }
+    }
+
+    /// Set the file the manifest will be written to. If this is never
+    /// called, `finish` does nothing.
+    pub fn set_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.path = Some(path.as_ref().to_path_buf())
+    }
+
+    /// Print a JSON status object to stderr when the run finishes
+    pub fn set_report_status(&mut self, report_status: bool) {
+        self.report_status = report_status
+    }
+
+    /// Record an outcome that cannot be inferred from the path and
+    /// error counts alone. Returns the default exit code for the
+    /// outcome, which `finish` replaces with the outcome's own exit
+    /// code when reporting the status.
+    pub fn set_outcome(&mut self, outcome: Outcome) -> i32 {
+        self.outcome = Some(outcome);
+        outcome.default_exit_code()
+    }
+
+    /// Record how often the checkpoint cache was used when the run
+    /// finishes
+    pub fn set_checkpoint_cache(&mut self, cache: Arc<CheckpointCache<B129>>) {
+        self.cache = Some(cache)
+    }
+
+    fn infer_outcome(&self, exit_code: i32) -> Outcome {
+        // Only runs that reached symbolic execution can have no paths
+        let executed = self.timing_ms.contains_key("execution");
+        if exit_code != 0 {
+            Outcome::Failure
+        } else if self.paths == 0 && self.errors == 0 && !executed {
+            Outcome::Success
+        } else {
+            Outcome::from_counts(self.paths, self.errors)
+        }
+    }
+
+    pub fn set_arch(&mut self, file: &str) {
+        self.arch_hash = hash_file(file);
+        self.arch = Some(file.to_string())
+    }
+
+    pub fn set_config(&mut self, file: &str) {
+        self.config_hash = hash_file(file);
+        self.config = Some(file.to_string())
+    }
+
+    pub fn add_output(&mut self, output: &str) {
+        if !self.outputs.iter().any(|o| o == output) {
+            self.outputs.push(output.to_string())
+        }
+    }
+
+    /// Record the time taken by a phase of execution. A phase which
+    /// is repeated, e.g. executing each instruction in a stream of
+    /// requests, is timed in total.
+    pub fn time(&mut self, phase: &str, since: Instant) {
+        *self.timing_ms.entry(phase.to_string()).or_insert(0) += since.elapsed().as_millis()
+    }
+
+    /// Decide the outcome of the run, record it with the total time,
+    /// and write the manifest (if a path was set). Returns the exit
+    /// code, which is the outcome's own exit code when reporting the
+    /// status, and `exit_code` otherwise.
+    pub fn finish(&mut self, exit_code: i32) -> i32 {
+        let outcome = self.outcome.unwrap_or_else(|| self.infer_outcome(exit_code));
+        let exit_code = if self.report_status { outcome.exit_code() } else { exit_code };
+        self.outcome = Some(outcome);
+        self.exit_code = Some(exit_code);
+        self.fork_checks = Some(fork_check_counts());
+        self.checkpoint_restores = Some(checkpoint_restore_counts());
+        self.checkpoint_cache = self.cache.as_ref().map(|cache| cache.counts());
+        let start = self.start;
+        self.time("total", start);
+        if let Some(path) = &self.path {
+            let result = File::create(path)
+                .map_err(|e| e.to_string())
+                .and_then(|file| serde_json::to_writer_pretty(BufWriter::new(file), self).map_err(|e| e.to_string()));
+            if let Err(e) = result {
+                eprintln!("Failed to write manifest {}: {}", path.display(), e)
+            }
+        }
+        if self.report_status {
+            let status = serde_json::json!({
+                "outcome": outcome,
+                "exit_code": exit_code,
+                "paths": self.paths,
+                "errors": self.errors,
+            });
+            eprintln!("{}", status)
+        }
+        exit_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn repeated_phases_accumulate() {
+        let mut manifest = Manifest::new("isla-footprint");
+        let since = Instant::now() - Duration::from_millis(10);
+        manifest.time("execution", since);
+        manifest.time("execution", since);
+        assert!(manifest.timing_ms["execution"] >= 20);
+
+        manifest.set_checkpoint_cache(Arc::new(CheckpointCache::new(1)));
+        assert_eq!(manifest.finish(0), 0);
+        assert_eq!(manifest.checkpoint_cache, Some(CheckpointCacheCounts::default()));
+        assert!(manifest.timing_ms.contains_key("total"))
+    }
+}
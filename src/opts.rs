@@ -29,31 +29,55 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use getopts::{Matches, Options};
-use isla_lib::ir_lexer::new_ir_lexer;
+use isla_lib::ir_lexer::{expand_includes, new_ir_lexer};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::prelude::*;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::process::exit;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use isla_lib::bitvector::BV;
+use isla_lib::bitvector::{self, BV};
 use isla_lib::config::ISAConfig;
+use isla_lib::executor::{Progress, ProgressFormat, TaskState};
 use isla_lib::ir;
 use isla_lib::ir::linearize;
 use isla_lib::ir::partial_linearize;
 use isla_lib::ir::serialize::{read_serialized_architecture, DeserializedArchitecture, SerializationError};
 use isla_lib::ir::*;
-use isla_lib::ir_parser;
+use isla_lib::ir_parse;
 use isla_lib::log;
+use isla_lib::memory::Address;
 use isla_lib::primop_util::symbolic_from_typedefs;
+use isla_lib::probe;
+use isla_lib::smt;
 use isla_lib::smt_parser;
 use isla_lib::source_loc::SourceLoc;
 use isla_lib::value_parser;
 use isla_lib::zencode;
 
+type FailureHook = Box<dyn FnOnce(i32) -> i32 + Send>;
+
+static FAILURE_HOOK: Mutex<Option<FailureHook>> = Mutex::new(None);
+
+/// Set a function which is called with the exit code when the options
+/// are invalid, before the process exits with the code it returns.
+/// This lets a tool report the failure, e.g. in its run manifest.
+#[allow(dead_code)]
+pub fn set_failure_hook<F: FnOnce(i32) -> i32 + Send + 'static>(hook: F) {
+    *FAILURE_HOOK.lock().unwrap() = Some(Box::new(hook))
+}
+
+fn exit(code: i32) -> ! {
+    let hook = if code != 0 { FAILURE_HOOK.lock().unwrap().take() } else { None };
+    std::process::exit(hook.map_or(code, |hook| hook(code)))
+}
+
 fn tool_name() -> Option<String> {
     match std::env::current_exe() {
         Ok(path) => Some(path.components().last()?.as_os_str().to_str()?.to_string()),
@@ -76,15 +100,25 @@ pub fn common_opts() -> Options {
     opts.optopt("T", "threads", "use this many worker threads", "<n>");
     opts.reqopt("A", "arch", "load architecture file", "<file>");
     opts.optopt("C", "config", "load custom config for architecture", "<file>");
+    opts.optmulti(
+        "",
+        "config-overlay",
+        "merge a config file over the config, with later overlays taking precedence",
+        "<file>",
+    );
     opts.optopt("", "toolchain", "use specified toolchain from config", "<name>");
     opts.optmulti("R", "register", "set a register, via the reset_registers builtin", "<register>=<value>");
     opts.optmulti("I", "initial", "set a register in the initial state", "<register>=<value>");
+    opts.optmulti("", "symbolic-register", "start a register as a fresh symbolic variable", "<register>");
+    opts.optmulti("", "concrete-register", "start a register with its default or initializer value", "<register>");
     opts.optflag("h", "help", "print this help message");
     opts.optflag("", "verbose", "print verbose output");
     opts.optopt("D", "debug", "set debugging flags", "<flags>");
     opts.optmulti("", "probe", "trace specified function calls or location assignments in debug output", "<id>");
     opts.optflag("", "probe-all", "probe everything (very verbose)");
+    opts.optmulti("", "probe-register", "trace old and new values of writes to a register in debug output", "<name>");
     opts.optmulti("", "trace-function", "trace specified function calls in the trace output", "<id>");
+    opts.optmulti("", "pure", "cache the results of a function that depends only on its arguments", "<id>");
     opts.optflag("", "trace-all", "trace everything");
     opts.optmulti("L", "linearize", "rewrite function into linear form", "<id>");
     opts.optmulti("P", "partial-linearize", "rewrite function into linear form", "<id>");
@@ -92,13 +126,120 @@ pub fn common_opts() -> Options {
     opts.optflag("", "test-linearize", "test that linearization rewrite has been performed correctly");
     opts.optmulti("", "abstract", "make function abstract", "<id>");
     opts.optmulti("", "debug-id", "print the name of an interned identifier (for debugging)", "<name id>");
+    opts.optflagopt(
+        "",
+        "list-functions",
+        "list the functions in the architecture whose names contain a pattern, with their types, and exit",
+        "<pattern>",
+    );
     opts.optmulti("", "reset-constraint", "property to enforce at the reset_registers builtin", "<constraint>");
     opts.optflag("", "fork-assertions", "change assertions into explicit control flow");
     opts.optmulti("", "fun-assumption", "add an assumption about the behaviour of a Sail function", "<assumption>");
     opts.optflag("", "no-model-reg-init", "don't use register initializers from the model");
+    opts.optflag("", "precise-blocks", "model block operations (e.g. DC ZVA) using the configured block sizes");
+    opts.optflag("", "imprecise-blocks", "treat block operations as ordinary abstract calls");
+    opts.optopt(
+        "",
+        "solver-seed",
+        "random seed for the SMT solver, or random to pick (and print) one (default: from configuration)",
+        "<n|random>",
+    );
+    opts.optmulti("", "solver-param", "set a Z3 parameter for every solver", "<param>=<value>");
+    opts.optopt(
+        "",
+        "randomize-addresses",
+        "offset memory regions by a random amount within the configuration's address_randomization bounds",
+        "<seed|random>",
+    );
+    opts.optflag(
+        "",
+        "check-bv-lengths",
+        "fail when a bitvector operation is applied to operands of the wrong lengths (requires the length-checks feature)",
+    );
+    opts.optflagopt(
+        "",
+        "progress",
+        "periodically print the number of tasks queued and finished, traces, and solver time to stderr",
+        "<text|json>",
+    );
+    opts.optopt("", "progress-interval", "seconds between --progress reports (default 1)", "<seconds>");
+    opts.optflag("", "optimize-ir", "optimize the IR when loading the architecture");
+    opts.optflag("", "no-optimize-ir", "do not optimize the IR when loading the architecture");
     opts
 }
 
+/// Add the options which control how paths are explored, which are
+/// shared by the tools that run symbolic execution themselves.
+#[allow(dead_code)]
+pub fn exploration_opts(opts: &mut Options) {
+    opts.optopt("", "max-loop-iterations", "stop paths which jump back to a loop head more than n times", "<n>");
+    opts.optmulti("", "function-loop-bound", "set the loop bound for a specific function", "<function>=<n>");
+    opts.optflag("", "merge-paths", "merge branches which only compute local variables rather than forking");
+    opts.optmulti(
+        "",
+        "watch",
+        "log memory accesses which may touch this address range in debug output",
+        "<start>..<end>|<address>[:<bytes>]",
+    );
+}
+
+/// The options added by [exploration_opts]
+pub struct ExplorationOpts {
+    pub watchpoints: Vec<Range<Address>>,
+    pub loop_bound: Option<usize>,
+    pub function_loop_bounds: HashMap<Name, usize>,
+    pub merge_paths: bool,
+}
+
+impl ExplorationOpts {
+    #[allow(dead_code)]
+    pub fn task_state<B: BV>(&self, task_state: TaskState<B>) -> TaskState<B> {
+        task_state
+            .with_watchpoints(self.watchpoints.clone())
+            .with_loop_bounds(self.loop_bound, self.function_loop_bounds.clone())
+            .with_path_merging(self.merge_paths)
+    }
+}
+
+#[allow(dead_code)]
+pub fn parse_exploration_opts(matches: &Matches, symtab: &Symtab) -> ExplorationOpts {
+    let watchpoints = match matches.opt_strs("watch").iter().map(|w| probe::parse_watchpoint(w)).collect() {
+        Ok(watchpoints) => watchpoints,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            exit(1)
+        }
+    };
+
+    let loop_bound = match matches.opt_get("max-loop-iterations") {
+        Ok(bound) => bound,
+        Err(e) => {
+            eprintln!("Failed to parse --max-loop-iterations: {}", e);
+            exit(1)
+        }
+    };
+
+    let mut function_loop_bounds = HashMap::new();
+    for arg in matches.opt_strs("function-loop-bound") {
+        let bound = arg.split_once('=').map(|(f, n)| (symtab.get(&zencode::encode(f)), n.parse()));
+        match bound {
+            Some((Some(id), Ok(bound))) => {
+                function_loop_bounds.insert(id, bound);
+            }
+            Some((None, _)) => {
+                eprintln!("Function in --function-loop-bound {} does not exist in the specified architecture", arg);
+                exit(1)
+            }
+            _ => {
+                eprintln!("--function-loop-bound {} should have the form <function>=<n>", arg);
+                exit(1)
+            }
+        }
+    }
+
+    ExplorationOpts { watchpoints, loop_bound, function_loop_bounds, merge_paths: matches.opt_present("merge-paths") }
+}
+
 /// An architecture passed on the command line (via the -A flag) can
 /// either be an unparsed Sail IR file, or a serialized pre-parsed
 /// file.
@@ -108,10 +249,13 @@ pub enum Architecture<B> {
 }
 
 fn parse_ir<'a, 'input, B: BV>(contents: &'input str, symtab: &'a mut Symtab<'input>) -> Vec<Def<Name, B>> {
-    match ir_parser::IrParser::new().parse(symtab, new_ir_lexer(contents)) {
+    match ir_parse::parse_ir(contents, symtab) {
         Ok(ir) => ir,
-        Err(parse_error) => {
-            eprintln!("Parse error: {}", parse_error);
+        Err(parse_errors) => {
+            for parse_error in &parse_errors {
+                eprintln!("Parse error at {}", parse_error)
+            }
+            eprintln!("{} parse error(s) in architecture", parse_errors.len());
             exit(1)
         }
     }
@@ -136,6 +280,13 @@ where
             let mut buf = File::open(&file).map_err(IOError)?;
             let mut contents = String::new();
             buf.read_to_string(&mut contents).map_err(IOError)?;
+            let contents = match expand_includes(&contents, file) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(1)
+                }
+            };
             hasher.input(&contents);
             Ok(Architecture::Unparsed(contents))
         }
@@ -149,6 +300,9 @@ pub struct CommonOpts<'ir, B> {
     pub type_info: IRTypeInfo,
     pub isa_config: ISAConfig<B>,
     pub source_path: Option<PathBuf>,
+    /// How to report the progress of each run, to be given to its
+    /// task state with [TaskState::with_progress]
+    pub progress: Option<Progress>,
 }
 
 pub fn parse<B: BV>(hasher: &mut Sha256, opts: &Options) -> (Matches, Architecture<B>) {
@@ -245,10 +399,40 @@ pub fn parse_with_arch<'ir, B: BV>(
         }
     };
 
+    if matches.opt_present("list-functions") {
+        let pattern = matches.opt_str("list-functions").unwrap_or_default();
+        let mut signatures: Vec<(String, String)> = ir::function_signatures(&arch)
+            .into_iter()
+            .map(|(f, args, ret_ty)| (zencode::decode(symtab.to_str(f)), args, ret_ty))
+            .filter(|(name, _, _)| name.contains(&pattern))
+            .map(|(name, args, ret_ty)| {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|(arg, ty)| format!("{}: {}", zencode::decode(symtab.to_str(*arg)), ty.to_string(&symtab)))
+                    .collect();
+                let signature = format!("({}) -> {}", args.join(", "), ret_ty.to_string(&symtab));
+                (name, signature)
+            })
+            .collect();
+        signatures.sort();
+        for (name, signature) in signatures {
+            println!("{}{}", name, signature)
+        }
+        exit(0)
+    }
+
     let type_info = IRTypeInfo::new(&arch);
 
     let mut isa_config = if let Some(file) = matches.opt_str("config") {
-        match ISAConfig::from_file(hasher, file, matches.opt_str("toolchain").as_deref(), &symtab, &type_info) {
+        let overlays = matches.opt_strs("config-overlay");
+        match ISAConfig::from_file_with_overlays(
+            hasher,
+            file,
+            &overlays,
+            matches.opt_str("toolchain").as_deref(),
+            &symtab,
+            &type_info,
+        ) {
             Ok(isa_config) => isa_config,
             Err(e) => {
                 eprintln!("{}", e);
@@ -274,10 +458,39 @@ pub fn parse_with_arch<'ir, B: BV>(
         }
     });
 
+    matches.opt_strs("probe-register").iter().for_each(|arg| {
+        let reg = isa_config
+            .register_renames
+            .get(arg)
+            .copied()
+            .or_else(|| symtab.get(&zencode::encode(arg)))
+            .or_else(|| symtab.get(arg))
+            .map(|reg| isa_config.canonical_register(reg))
+            .filter(|reg| arch.iter().any(|def| matches!(def, Def::Register(id, _, _) if id == reg)));
+        if let Some(reg) = reg {
+            // Probing any view of an aliased register probes all of them
+            let views: Vec<Name> = isa_config.register_views(reg).collect();
+            isa_config.register_probes.insert(reg);
+            isa_config.register_probes.extend(views)
+        } else {
+            eprintln!("Register {} does not exist in the specified architecture", arg);
+            exit(1)
+        }
+    });
+
     if matches.opt_present("probe-all") {
         isa_config.probes.extend(symtab.all_names());
     }
 
+    matches.opt_strs("pure").iter().for_each(|arg| {
+        if let Some(id) = symtab.get(&zencode::encode(arg)) {
+            isa_config.pure_functions.insert(id);
+        } else {
+            eprintln!("Function {} does not exist in the specified architecture", arg);
+            exit(1)
+        }
+    });
+
     matches.opt_strs("trace-function").iter().for_each(|arg| {
         if let Some(id) = symtab.get(&zencode::encode(arg)) {
             isa_config.trace_functions.insert(id);
@@ -327,6 +540,121 @@ pub fn parse_with_arch<'ir, B: BV>(
         }
     });
 
+    // Apply these in command line order, so a later flag for the
+    // same register takes precedence
+    let mut register_kinds: Vec<(usize, String, bool)> = Vec::new();
+    for (flag, symbolic) in [("symbolic-register", true), ("concrete-register", false)] {
+        register_kinds.extend(matches.opt_strs_pos(flag).drain(..).map(|(pos, reg)| (pos, reg, symbolic)))
+    }
+    register_kinds.sort_by_key(|(pos, _, _)| *pos);
+    for (_, reg, symbolic) in register_kinds {
+        match symtab.get(&zencode::encode(&reg)) {
+            Some(reg) if symbolic => isa_config.set_symbolic_register(reg),
+            Some(reg) => isa_config.set_concrete_register(reg),
+            None => {
+                eprintln!("Register {} does not exist in the specified architecture", reg);
+                exit(1)
+            }
+        }
+    }
+
+    if matches.opt_present("precise-blocks") {
+        isa_config.precise_blocks = true
+    } else if matches.opt_present("imprecise-blocks") {
+        isa_config.precise_blocks = false
+    }
+
+    if matches.opt_present("optimize-ir") {
+        isa_config.optimize_ir = true
+    } else if matches.opt_present("no-optimize-ir") {
+        isa_config.optimize_ir = false
+    }
+
+    match matches.opt_str("solver-seed").as_deref() {
+        Some("random") => {
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+            eprintln!("Solver seed: {}", nanos);
+            isa_config.solver_seed = Some(nanos)
+        }
+        Some(seed) => match seed.parse::<u32>() {
+            Ok(seed) => isa_config.solver_seed = Some(seed),
+            Err(_) => {
+                eprintln!("--solver-seed must be a 32-bit unsigned integer or random");
+                exit(1)
+            }
+        },
+        None => (),
+    }
+    for arg in matches.opt_strs("solver-param") {
+        match arg.split_once('=') {
+            Some((param, value)) => isa_config.solver_params.push((param.trim().to_string(), value.trim().to_string())),
+            None => {
+                eprintln!("--solver-param {} should have the form <param>=<value>", arg);
+                exit(1)
+            }
+        }
+    }
+    if let Some(seed) = matches.opt_str("randomize-addresses") {
+        let seed = match seed.as_str() {
+            "random" => rand::random(),
+            _ => match seed.parse::<u64>() {
+                Ok(seed) => seed,
+                Err(_) => {
+                    eprintln!("--randomize-addresses must be a 64-bit unsigned integer seed or random");
+                    exit(1)
+                }
+            },
+        };
+        match isa_config.randomize_addresses(&mut StdRng::seed_from_u64(seed)) {
+            Ok(offset) => eprintln!("Address randomization seed: {}, offset: 0x{:x}", seed, offset),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                exit(1)
+            }
+        }
+    }
+    if matches.opt_present("check-bv-lengths") {
+        if let Err(msg) = bitvector::length_check::set_length_checks(true) {
+            eprintln!("{}", msg);
+            exit(1)
+        }
+    }
+    let progress = if matches.opt_present("progress") {
+        let format = match matches.opt_str("progress").as_deref() {
+            None | Some("text") => ProgressFormat::Text,
+            Some("json") => ProgressFormat::Json,
+            Some(format) => {
+                eprintln!("Unknown --progress format {}, expected text or json", format);
+                exit(1)
+            }
+        };
+        let interval = match matches.opt_get_default("progress-interval", 1.0f64) {
+            Ok(secs) if secs > 0.0 && secs.is_finite() => Duration::from_secs_f64(secs),
+            _ => {
+                eprintln!("--progress-interval must be a positive number of seconds");
+                exit(1)
+            }
+        };
+        Some(Progress { interval, format })
+    } else if matches.opt_present("progress-interval") {
+        eprintln!("--progress-interval requires --progress");
+        exit(1)
+    } else {
+        None
+    };
+
+    // Z3 global parameters apply to every solver context created
+    // afterwards, including those of each worker thread
+    if let Some(seed) = isa_config.solver_seed {
+        log!(log::VERBOSE, &format!("Solver seed: {}", seed));
+        smt::global_set_param_value("smt.random_seed", &seed.to_string());
+        smt::global_set_param_value("sat.random_seed", &seed.to_string())
+    }
+    for (param, value) in &isa_config.solver_params {
+        log!(log::VERBOSE, &format!("Solver parameter {} = {}", param, value));
+        smt::global_set_param_value(param, value)
+    }
+
     matches.opt_strs("abstract").iter().for_each(|arg| {
         if let Some((id, property_id)) = arg.split_once(|c| c == ' ' || c == ':') {
             let target = symtab.get(&zencode::encode(id.trim()));
@@ -501,5 +829,5 @@ pub fn parse_with_arch<'ir, B: BV>(
 
     let source_path = matches.opt_str("source").map(PathBuf::from);
 
-    CommonOpts { num_threads, arch, symtab, type_info, isa_config, source_path }
+    CommonOpts { num_threads, arch, symtab, type_info, isa_config, source_path, progress }
 }
@@ -28,9 +28,15 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::process::exit;
 
 use isla_lib::bitvector::b64::B64;
+use isla_lib::init::constant_registers;
+use isla_lib::ir::optimize::{fix_register, specialize};
 use isla_lib::ir::serialize::write_serialized_architecture;
+use isla_lib::ir_lexer::new_ir_lexer;
+use isla_lib::value_parser::AssignParser;
 
 mod opts;
 use opts::CommonOpts;
@@ -38,17 +44,42 @@ use opts::CommonOpts;
 fn main() {
     let mut opts = opts::common_opts();
     opts.reqopt("o", "output", "output name for processed architecture and symbol table info", "<file>");
+    opts.optmulti("", "specialize", "specialize the architecture to a fixed register value", "<register>=<value>");
+    opts.optflag("", "specialize-config", "specialize the architecture to the constant registers in the config");
 
     // Note that an architecuture loaded and processed with B64 can be
     // loaded with any bitvector width.
     let mut hasher = Sha256::new();
     let (matches, arch) = opts::parse::<B64>(&mut hasher, &opts);
-    let CommonOpts { arch, symtab, .. } = opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
+    let CommonOpts { mut arch, symtab, type_info, isa_config, .. } =
+        opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
+
+    if matches.opt_present("specialize") || matches.opt_present("specialize-config") {
+        let mut registers = if matches.opt_present("specialize-config") {
+            constant_registers(&arch, &isa_config)
+        } else {
+            HashMap::new()
+        };
+        for arg in matches.opt_strs("specialize") {
+            let result = match AssignParser::new().parse(&symtab, &type_info, new_ir_lexer(&arg)) {
+                Ok((loc, value)) => match symtab.get_loc(&loc) {
+                    Some(loc) => fix_register(&arch, &mut registers, &loc, value),
+                    None => Err("register or field does not exist in the specified architecture".to_string()),
+                },
+                Err(_) => Err("could not parse register assignment".to_string()),
+            };
+            if let Err(msg) = result {
+                eprintln!("--specialize {}: {}", arg, msg);
+                exit(1)
+            }
+        }
+        eprintln!("Specialized architecture: {}", specialize(&mut arch, &registers))
+    }
 
     let output = matches.opt_str("output").unwrap();
 
     if let Err(e) = write_serialized_architecture(&format!("{}.irx", output), arch, &symtab) {
         eprintln!("Failed to write output file '{}.irx' error: {}", output, e);
-        std::process::exit(1)
+        exit(1)
     }
 }
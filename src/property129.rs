@@ -56,21 +56,21 @@ fn isla_main() -> i32 {
 
     let mut hasher = Sha256::new();
     let (matches, arch) = opts::parse::<B129>(&mut hasher, &opts);
-    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path: _ } =
+    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path: _, progress } =
         opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
     let use_model_reg_init = !matches.opt_present("no-model-reg-init");
 
     let assertion_mode =
         if matches.opt_present("optimistic") { AssertionMode::Optimistic } else { AssertionMode::Pessimistic };
 
-    let Initialized { regs, lets, shared_state } =
+    let Initialized { regs, lets, shared_state, .. } =
         initialize_architecture(&mut arch, symtab, type_info, &isa_config, assertion_mode, use_model_reg_init);
 
     let property = zencode::encode(&matches.opt_str("property").unwrap());
 
     let function_id = shared_state.symtab.lookup(&property);
     let (args, ret_ty, instrs) = shared_state.functions.get(&function_id).unwrap();
-    let task_state = TaskState::new();
+    let task_state = TaskState::new().with_progress(progress);
     let task =
         LocalFrame::new(function_id, args, ret_ty, None, instrs).add_lets(&lets).add_regs(&regs).task(0, &task_state);
     let result = Arc::new(AtomicBool::new(true));
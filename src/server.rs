@@ -0,0 +1,459 @@
+// BSD 2-Clause License
+//
+// Copyright (c) 2020 Alasdair Armstrong
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+// 1. Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright
+// notice, this list of conditions and the following disclaimer in the
+// documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// HOLDER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A long-running process that initializes an architecture once and
+//! then answers footprint and trace queries, avoiding the start-up
+//! cost of running isla-footprint for every instruction. Requests and
+//! responses use the JSON lines protocol in [isla::io], read from
+//! stdin (or from each connection to a unix socket when `--socket` is
+//! given):
+//!
+//! ```text
+//! {"id": 1, "method": "footprint", "params": {"opcode": "0x8b020020"}}
+//! {"id": 1, "result": {"opcode": "0x000000008b020020", "footprint": {...}}}
+//! ```
+//!
+//! The methods are `version`, `footprint`, `trace`, and `shutdown`.
+//! Instructions are given either as an `opcode` in hexadecimal or as
+//! assembly with `asm`. A `trace` result lists the traces and errors
+//! of every path, along with the `outcome` of the instruction as
+//! reported by `isla-footprint --status`.
+//!
+//! Requests are executed by a pool of worker threads (as many as
+//! `--threads`) which is started once, each worker running one
+//! request at a time. Connections to the socket are served
+//! concurrently, with the responses on each connection in the order
+//! of its requests.
+
+use crossbeam::channel::{self, Receiver, Sender};
+use crossbeam::queue::SegQueue;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use isla::io::{self, Call};
+use isla::manifest::Outcome;
+use isla_axiomatic::footprint_analysis::footprint_analysis;
+use isla_axiomatic::litmus::assemble_instruction;
+use isla_lib::bitvector::{b64::B64, BV};
+use isla_lib::executor;
+use isla_lib::executor::{LocalFrame, TaskState};
+use isla_lib::init::{initialize_architecture, InitArchWithConfig};
+use isla_lib::ir::*;
+use isla_lib::memory::Endianness;
+use isla_lib::simplify::write_events;
+use isla_lib::smt::Event;
+
+mod opts;
+use opts::CommonOpts;
+
+struct Server<'ir> {
+    keep_ifetch: bool,
+    arch: InitArchWithConfig<'ir, B64>,
+}
+
+/// A request waiting for a worker, with the channel its result is
+/// sent back on
+type Job = (Call, mpsc::Sender<Result<Value, String>>);
+
+/// Read the bytes of an assembled instruction in the configured
+/// instruction byte order
+fn instruction_opcode(bytes: Vec<u8>, endianness: Endianness) -> Result<B64, String> {
+    let opcode: [u8; 4] = bytes.try_into().map_err(|_| "Instruction is not four bytes long".to_string())?;
+    Ok(B64::from_u32(match endianness {
+        Endianness::Little => u32::from_le_bytes(opcode),
+        _ => u32::from_be_bytes(opcode),
+    }))
+}
+
+impl<'ir> Server<'ir> {
+    fn opcode(&self, params: &Value) -> Result<B64, String> {
+        if let Some(opcode) = params.get("opcode").and_then(Value::as_str) {
+            let digits = opcode.strip_prefix("0x").unwrap_or(opcode);
+            u32::from_str_radix(digits, 16).map(B64::from_u32).map_err(|_| format!("Could not parse opcode {}", opcode))
+        } else if let Some(instruction) = params.get("asm").and_then(Value::as_str) {
+            let bytes = assemble_instruction(instruction, self.arch.isa_config)?;
+            instruction_opcode(bytes, self.arch.isa_config.instruction_endianness)
+                .map_err(|msg| format!("{}: {}", instruction, msg))
+        } else {
+            Err("Expected an opcode or asm parameter".to_string())
+        }
+    }
+
+    fn footprint(&self, opcode: B64) -> Result<Value, String> {
+        let paths = vec![vec![Event::Instr(Val::Bits(opcode))]];
+        // Each request runs on the worker thread that took it
+        let mut footprints = footprint_analysis(0, &[paths], &self.arch, None, self.keep_ifetch, true)
+            .map_err(|err| format!("{:?}", err))?;
+        let footprint = footprints.remove(&opcode).ok_or_else(|| "No footprint computed".to_string())?;
+        Ok(json!({
+            "opcode": opcode.to_string(),
+            "footprint": footprint.summary(&self.arch.shared_state.symtab),
+        }))
+    }
+
+    fn trace(&self, opcode: B64) -> Result<Value, String> {
+        let shared_state = self.arch.shared_state;
+        let function_id = shared_state.symtab.get("zisla_footprint").ok_or("No isla_footprint function")?;
+        let (args, ret_ty, instrs) = shared_state.functions.get(&function_id).unwrap();
+        let task_state = TaskState::new();
+        let task = LocalFrame::new(function_id, args, ret_ty, Some(&[Val::Bits(opcode)]), instrs)
+            .add_lets(self.arch.lets)
+            .add_regs(self.arch.regs)
+            .task(0, &task_state);
+
+        let queue = SegQueue::new();
+        executor::start_single(task, shared_state, &queue, &executor::trace_result_collector);
+
+        let mut traces = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match queue.pop() {
+                Some(Ok((_, result, mut events))) => {
+                    let mut buf = Vec::new();
+                    let events: Vec<Event<B64>> = events.drain(..).rev().collect();
+                    write_events(&mut buf, &events, shared_state);
+                    traces.push(json!({ "result": result, "trace": String::from_utf8_lossy(&buf) }))
+                }
+                Some(Err(msg)) => errors.push(msg.to_string()),
+                None => break,
+            }
+        }
+        Ok(json!({
+            "opcode": opcode.to_string(),
+            "outcome": Outcome::from_counts(traces.len(), errors.len()),
+            "traces": traces,
+            "errors": errors,
+        }))
+    }
+
+    fn handle(&self, call: &Call) -> Result<Value, String> {
+        match call.method.as_str() {
+            "version" => Ok(json!(String::from_utf8_lossy(isla_lib::ISLA_VERSION.as_ref()))),
+            "footprint" => self.opcode(&call.params).and_then(|opcode| self.footprint(opcode)),
+            "trace" => self.opcode(&call.params).and_then(|opcode| self.trace(opcode)),
+            method => Err(format!("Unknown method {}", method)),
+        }
+    }
+
+    /// Answer the requests sent to a worker until every connection
+    /// has finished.
+    fn work(&self, jobs: Receiver<Job>) {
+        for (call, reply) in jobs {
+            // The connection may have been closed while waiting
+            let _ = reply.send(self.handle(&call));
+        }
+    }
+
+    /// Start `num_threads` workers, and answer requests from stdin,
+    /// or from every connection to `listener`, until the input is
+    /// closed or a shutdown is requested.
+    fn run(&self, num_threads: usize, listener: Option<&UnixListener>) -> std::io::Result<()> {
+        let (jobs, pending) = channel::unbounded();
+        let connections = Mutex::new(HashMap::new());
+        let shutdown = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            for _ in 0..num_threads.max(1) {
+                let pending = pending.clone();
+                scope.spawn(move || self.work(pending));
+            }
+
+            let result = match listener {
+                Some(listener) => {
+                    self.listen(scope, listener, &jobs, &connections, &shutdown);
+                    Ok(())
+                }
+                None => {
+                    let stdin = std::io::stdin();
+                    let stdout = std::io::stdout();
+                    serve(stdin.lock(), &mut stdout.lock(), &jobs).map(|_| ())
+                }
+            };
+            // The workers stop once every connection has finished
+            // and dropped its copy of the sender
+            drop(jobs);
+            result
+        })
+    }
+
+    /// Serve each connection to `listener` on its own thread until
+    /// one of them requests a shutdown, then close the others.
+    fn listen<'scope, 'env>(
+        &'env self,
+        scope: &'scope thread::Scope<'scope, 'env>,
+        listener: &'env UnixListener,
+        jobs: &Sender<Job>,
+        connections: &'env Mutex<HashMap<usize, UnixStream>>,
+        shutdown: &'env AtomicBool,
+    ) {
+        for (n, stream) in listener.incoming().enumerate() {
+            if shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            // A failed connection only ends that client's session
+            let stream = match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+                Ok((reader, stream)) => {
+                    connections.lock().unwrap().insert(n, stream);
+                    reader
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            };
+            let jobs = jobs.clone();
+            scope.spawn(move || {
+                match serve(BufReader::new(&stream), &mut &stream, &jobs) {
+                    Ok(true) => {
+                        shutdown.store(true, Ordering::SeqCst);
+                        // Wake the listener so it sees the shutdown
+                        if let Ok(addr) = listener.local_addr() {
+                            if let Some(path) = addr.as_pathname() {
+                                let _ = UnixStream::connect(path);
+                            }
+                        }
+                    }
+                    Ok(false) => (),
+                    Err(e) => eprintln!("{}", e),
+                }
+                connections.lock().unwrap().remove(&n);
+            });
+        }
+
+        // Close any other connections, so their sessions end
+        for stream in connections.lock().unwrap().values() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Answer requests on one connection by passing them to the workers,
+/// until the input is closed, returning `true` if a shutdown was
+/// requested.
+fn serve<R: BufRead, W: Write>(reader: R, writer: &mut W, jobs: &Sender<Job>) -> std::io::Result<bool> {
+    let (reply, result) = mpsc::channel();
+    for call in io::requests::<Call, _>(reader) {
+        match call? {
+            Ok(call) if call.method == "shutdown" => return Ok(true),
+            Ok(call) => {
+                let id = call.id.clone();
+                jobs.send((call, reply.clone())).expect("Worker threads stopped");
+                io::respond(writer, &id, result.recv().expect("Worker thread stopped"))?
+            }
+            Err(msg) => io::respond(writer, &Value::Null, Err(msg))?,
+        }
+    }
+    Ok(false)
+}
+
+fn main() {
+    let code = isla_main();
+    unsafe { isla_lib::smt::finalize_solver() };
+    exit(code)
+}
+
+fn isla_main() -> i32 {
+    let mut opts = opts::common_opts();
+    opts.optopt("", "socket", "listen for connections on a unix socket rather than using stdin", "<path>");
+    opts.optflag("", "keep-ifetch", "track instruction fetches separately in footprints");
+
+    let mut hasher = Sha256::new();
+    let (matches, arch) = opts::parse(&mut hasher, &opts);
+    let CommonOpts { num_threads, mut arch, symtab, type_info, isa_config, source_path: _, progress } =
+        opts::parse_with_arch(&mut hasher, &opts, &matches, &arch);
+    if progress.is_some() {
+        eprintln!("--progress is not supported by isla-server");
+        return 1;
+    }
+    let use_model_reg_init = !matches.opt_present("no-model-reg-init");
+
+    let iarch = initialize_architecture(
+        &mut arch,
+        symtab,
+        type_info,
+        &isa_config,
+        AssertionMode::Optimistic,
+        use_model_reg_init,
+    );
+
+    let server = Server {
+        keep_ifetch: matches.opt_present("keep-ifetch"),
+        arch: InitArchWithConfig::from_initialized(&iarch, &isa_config),
+    };
+
+    let result = if let Some(socket_path) = matches.opt_str("socket") {
+        let listener = match UnixListener::bind(&socket_path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Could not listen on socket {}: {}", socket_path, e);
+                return 1;
+            }
+        };
+        eprintln!("Listening on {}", socket_path);
+        let result = server.run(num_threads, Some(&listener));
+        result.and(std::fs::remove_file(&socket_path))
+    } else {
+        server.run(num_threads, None)
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::path::Path;
+
+    use isla_lib::config::ISAConfig;
+    use isla_lib::ir_parse::parse_ir;
+
+    /// Initialize the architecture in examples/demo, and start a
+    /// server for it
+    fn with_demo_server(f: impl FnOnce(&Server)) {
+        let demo = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples").join("demo");
+        let contents = fs::read_to_string(demo.join("demo.ir")).unwrap();
+        let mut symtab = Symtab::new();
+        let mut defs = parse_ir(&contents, &mut symtab).unwrap();
+        let type_info = IRTypeInfo::new(&defs);
+        let config = fs::read_to_string(demo.join("demo.toml")).unwrap();
+        let isa_config = ISAConfig::parse(&config, None, &symtab, &type_info).unwrap();
+        let iarch = initialize_architecture(&mut defs, symtab, type_info, &isa_config, AssertionMode::Optimistic, true);
+        f(&Server { keep_ifetch: false, arch: InitArchWithConfig::from_initialized(&iarch, &isa_config) })
+    }
+
+    fn responses(output: &[u8]) -> Vec<Value> {
+        output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn asm_byte_order() {
+        let bytes = vec![0x20, 0x00, 0x02, 0x8b];
+        assert_eq!(instruction_opcode(bytes.clone(), Endianness::Little).unwrap(), B64::from_u32(0x8b02_0020));
+        assert_eq!(instruction_opcode(bytes.clone(), Endianness::Big).unwrap(), B64::from_u32(0x2000_028b));
+        assert_eq!(instruction_opcode(bytes, Endianness::WordInvariantBig).unwrap(), B64::from_u32(0x2000_028b));
+        assert!(instruction_opcode(vec![0x20, 0x00], Endianness::Little).is_err())
+    }
+
+    #[test]
+    fn serve_requests() {
+        with_demo_server(|server| {
+            let (jobs, pending) = channel::unbounded();
+            let input = concat!(
+                r#"{"id": 1, "method": "footprint", "params": {"opcode": "0x02000100"}}"#,
+                "\n\n",
+                r#"{"id": 2, "method": "trace", "params": {"opcode": "0x00000000"}}"#,
+                "\n",
+                r#"{"id": 3, "method": "trace", "params": {"opcode": "0xff000000"}}"#,
+                "\n",
+                r#"{"id": 4, "method": "frobnicate"}"#,
+                "\n",
+                "not json\n",
+                r#"{"id": 5, "method": "shutdown"}"#,
+                "\n",
+                r#"{"id": 6, "method": "version"}"#,
+                "\n",
+            );
+            let mut output = Vec::new();
+            let shutdown = thread::scope(|scope| {
+                scope.spawn(move || server.work(pending));
+                let shutdown = serve(input.as_bytes(), &mut output, &jobs);
+                drop(jobs);
+                shutdown
+            });
+            assert!(shutdown.unwrap());
+
+            let responses = responses(&output);
+            assert_eq!(responses.len(), 5);
+            assert_eq!(responses[0]["id"], 1);
+            assert_eq!(responses[0]["result"]["footprint"]["memory_reads"], json!([8]));
+            assert_eq!(responses[1]["id"], 2);
+            assert_eq!(responses[1]["result"]["outcome"], json!(Outcome::Success));
+            assert_eq!(responses[1]["result"]["traces"].as_array().unwrap().len(), 1);
+            assert_eq!(responses[2]["result"]["outcome"], json!(Outcome::AllErrored));
+            assert_eq!(responses[3]["error"], "Unknown method frobnicate");
+            assert_eq!(responses[4]["id"], Value::Null);
+            assert!(responses[4]["error"].as_str().unwrap().starts_with("Invalid request"))
+        })
+    }
+
+    #[test]
+    fn concurrent_connections() {
+        let path = std::env::temp_dir().join(format!("isla-server-test-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        with_demo_server(|server| {
+            thread::scope(|scope| {
+                let running = scope.spawn(|| server.run(2, Some(&listener)));
+
+                // An idle connection does not stop another from being
+                // answered, and is closed by the shutdown
+                let idle = UnixStream::connect(&path).unwrap();
+                let mut client = UnixStream::connect(&path).unwrap();
+                let mut replies = BufReader::new(client.try_clone().unwrap()).lines();
+                for id in 0..2 {
+                    writeln!(
+                        client,
+                        r#"{{"id": {}, "method": "footprint", "params": {{"opcode": "0x03000103"}}}}"#,
+                        id
+                    )
+                    .unwrap();
+                    let response: Value = serde_json::from_str(&replies.next().unwrap().unwrap()).unwrap();
+                    assert_eq!(response["id"], id);
+                    assert_eq!(response["result"]["footprint"]["memory_writes"], json!([8]))
+                }
+                writeln!(client, r#"{{"method": "shutdown"}}"#).unwrap();
+
+                running.join().unwrap().unwrap();
+                assert!(BufReader::new(idle).lines().next().is_none())
+            })
+        });
+        fs::remove_file(&path).unwrap()
+    }
+}
@@ -326,6 +326,11 @@ fn handle_request() -> Result<Response, Box<dyn Error>> {
         armv8_page_tables: req.armv8_page_tables,
         merge_translations: if req.merge_translations { Some(req.merge_split_stages) } else { None },
         remove_uninteresting_translates: if req.remove_uninteresting { Some(true) } else { None },
+        watchpoints: Vec::new(),
+        loop_bound: None,
+        function_loop_bounds: HashMap::new(),
+        merge_paths: false,
+        progress: None,
     };
 
     let graph_opts = GraphOpts {